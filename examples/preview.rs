@@ -0,0 +1,24 @@
+//! Shows the default world rendering in a window. Run with
+//! `cargo run --release --example preview --features preview`.
+
+use std::f64::consts::FRAC_PI_3;
+
+use raytracer::{
+    camera::Camera,
+    math::{
+        matrix::Matrix,
+        tuple::{point, pointi, vectori},
+    },
+    preview,
+    world::World,
+};
+
+fn main() -> Result<(), preview::PreviewError> {
+    let camera = Camera::new_with_transform(
+        640,
+        480,
+        FRAC_PI_3,
+        Matrix::view_transform(point(0.0, 1.0, -5.0), pointi(0, 0, 0), vectori(0, 1, 0)),
+    );
+    preview::show(&camera, &World::default())
+}
@@ -0,0 +1,41 @@
+//! Renders the default world with every span and event down to DEBUG logged to stdout, span
+//! timings included. Run with `cargo run --example trace_render --features tracing`.
+
+use std::{
+    f64::consts::FRAC_PI_2,
+    fs::{self, File},
+    io::BufWriter,
+};
+
+use raytracer::{
+    camera::Camera,
+    math::{
+        matrix::Matrix,
+        tuple::{pointi, vectori},
+    },
+    scene::{RenderSettings, Scene},
+    world::World,
+};
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .init();
+
+    let camera = Camera::new_with_transform(
+        100,
+        50,
+        FRAC_PI_2,
+        Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+    );
+    let scene = Scene::new(World::default(), camera).with_settings(RenderSettings {
+        threads: 4,
+        ..Default::default()
+    });
+
+    let canvas = scene.render();
+    fs::create_dir_all("out").unwrap();
+    let out = BufWriter::new(File::create("out/trace_render.ppm").unwrap());
+    canvas.write_ppm(false, out).unwrap();
+}
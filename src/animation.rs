@@ -0,0 +1,223 @@
+//! Moving the camera over a run of frames.
+
+use core::f64::consts::TAU;
+
+use crate::{
+    camera::{
+        rig::{rotate_about, CameraRig},
+        Camera,
+    },
+    math::{
+        float::EPSILON,
+        matrix::Matrix,
+        point::{Point, Vector},
+        tuple::{Tuple, ZERO_POINT},
+    },
+    ray::Ray,
+    scene::Scene,
+};
+
+/// The camera swinging once around what it's looking at, see turntable
+#[derive(Clone)]
+pub struct Turntable {
+    camera: Camera,
+    rig: CameraRig,
+    /// The camera's own up, which turns with it so any roll is kept
+    up: Tuple,
+    frames: usize,
+}
+
+impl Turntable {
+    /// Panics if `axis` is zero.
+    pub fn new(scene: &Scene, frames: usize, axis: Tuple, radius_override: Option<f64>) -> Self {
+        assert!(axis.magnitude() > 0.0, "a turntable axis can't be zero");
+        let axis = axis.normalize();
+        let config = scene.camera.to_config();
+        let forward = (config.to - config.from).normalize();
+        let target = look_at(scene, config.from, forward, axis);
+
+        let mut from = config.from;
+        if let Some(radius) = radius_override {
+            let offset = from - target;
+            let along = axis * offset.dot(&axis);
+            let out = offset - along;
+            if out.magnitude() > 0.0 {
+                from = target + along + out.normalize() * radius;
+            }
+        }
+        Self {
+            camera: scene.camera.clone(),
+            rig: CameraRig::new(from, target, axis),
+            up: config.up,
+            frames,
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// The point everything turns around
+    pub fn target(&self) -> Tuple {
+        self.rig.to
+    }
+
+    /// The camera for frame `n`, a full turn is `frames` frames so frame `frames` is frame 0
+    /// again.
+    pub fn camera(&self, n: usize) -> Camera {
+        let radians = match self.frames {
+            0 => 0.0,
+            frames => TAU * (n % frames) as f64 / frames as f64,
+        };
+        let mut rig = self.rig;
+        rig.orbit_horizontal(radians);
+        let up = rotate_about(self.up, self.rig.up, radians);
+
+        let mut camera = self.camera.clone();
+        camera.transform = Matrix::view_transform(rig.from, rig.to, up);
+        camera.inverse_transform = camera.transform.inverse().unwrap();
+        camera
+    }
+}
+
+/// One camera per frame, orbiting the scene's camera around `axis` (through what it's looking
+/// at) for one whole turn. Frame 0 is the scene's own camera. With `radius_override`, the
+/// camera's first moved towards or away from the axis to be that far from it, keeping its
+/// height.
+///
+/// Cameras only know which way they look, not how far, so what they're looking at is taken to
+/// be where their line of sight passes closest to `axis` through the origin. If that's behind
+/// the camera (or it's looking straight along the axis), it's the first thing the middle of
+/// the view hits instead, and failing that as far ahead as the camera is from the origin.
+pub fn turntable(
+    scene: &Scene,
+    frames: usize,
+    axis: Tuple,
+    radius_override: Option<f64>,
+) -> impl Iterator<Item = Camera> {
+    let turntable = Turntable::new(scene, frames, axis, radius_override);
+    (0..frames).map(move |n| turntable.camera(n))
+}
+
+/// See turntable, `forward` and `axis` are normalized
+fn look_at(scene: &Scene, from: Tuple, forward: Tuple, axis: Tuple) -> Tuple {
+    // Closest approach of the two lines, from + t * forward and s * axis
+    let w = from - ZERO_POINT;
+    let b = forward.dot(&axis);
+    let denominator = 1.0 - b * b;
+    if denominator > EPSILON {
+        let t = (b * w.dot(&axis) - w.dot(&forward)) / denominator;
+        if t > EPSILON {
+            return from + forward * t;
+        }
+    }
+
+    let distance = scene
+        .world
+        .distance_to_first_hit(Ray::new(
+            Point::from_tuple(from),
+            Vector::from_tuple(forward),
+        ))
+        .unwrap_or_else(|| w.magnitude().max(1.0));
+    from + forward * distance
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_3;
+
+    use crate::{
+        camera::Camera,
+        math::{
+            float,
+            matrix::Matrix,
+            tuple::{point, pointi, vector, vectori, Tuple, ZERO_POINT},
+        },
+        scene::Scene,
+        world::World,
+    };
+
+    use super::{turntable, Turntable};
+
+    fn scene(from: Tuple, to: Tuple) -> Scene {
+        Scene::new(
+            World::default(),
+            Camera::new_with_transform(
+                32,
+                32,
+                FRAC_PI_3,
+                Matrix::view_transform(from, to, vectori(0, 1, 0)),
+            ),
+        )
+    }
+
+    fn position(camera: &Camera) -> Tuple {
+        &camera.inverse_transform * ZERO_POINT
+    }
+
+    #[test]
+    fn circle() {
+        let scene = scene(point(0.0, 1.5, -5.0), pointi(0, 1, 0));
+        let cameras: Vec<_> = turntable(&scene, 12, vectori(0, 1, 0), None).collect();
+        assert_eq!(cameras.len(), 12);
+
+        for camera in &cameras {
+            let from = position(camera);
+            assert!(float::equal(from.y, 1.5));
+            assert!(float::equal(vector(from.x, 0.0, from.z).magnitude(), 5.0));
+            assert!(float::equal(
+                (from - pointi(0, 1, 0)).magnitude(),
+                25.25_f64.sqrt()
+            ));
+            // Always looking at the target
+            let to = position(camera) + camera.to_config().to - camera.to_config().from;
+            let towards = (pointi(0, 1, 0) - from).normalize();
+            assert_eq!((to - from).normalize(), towards);
+        }
+        // A quarter turn is anticlockwise looking down
+        assert_eq!(position(&cameras[3]), point(-5.0, 1.5, 0.0));
+        assert_eq!(position(&cameras[6]), point(0.0, 1.5, 5.0));
+    }
+
+    #[test]
+    fn wraps_around() {
+        let scene = scene(point(1.0, 2.0, -4.0), pointi(0, 0, 0));
+        let turntable = Turntable::new(&scene, 8, vectori(0, 1, 0), None);
+        assert_eq!(turntable.target(), pointi(0, 0, 0));
+
+        let first = turntable.camera(0);
+        assert_eq!(first.transform, scene.camera.transform);
+        assert_eq!(first.inverse_transform, scene.camera.inverse_transform);
+        assert_eq!(turntable.camera(8).transform, scene.camera.transform);
+        assert_eq!(turntable.camera(9).transform, turntable.camera(1).transform);
+        assert!(turntable.camera(4).transform != scene.camera.transform);
+    }
+
+    #[test]
+    fn radius_override() {
+        let scene = scene(point(0.0, 3.0, -5.0), pointi(0, 1, 0));
+        let distances: Vec<_> = turntable(&scene, 5, vectori(0, 1, 0), Some(2.0))
+            .map(|camera| {
+                let from = position(&camera);
+                assert!(float::equal(from.y, 3.0));
+                assert!(float::equal(vector(from.x, 0.0, from.z).magnitude(), 2.0));
+                (from - pointi(0, 1, 0)).magnitude()
+            })
+            .collect();
+        assert!(distances.iter().all(|d| float::equal(*d, 8.0_f64.sqrt())));
+    }
+
+    #[test]
+    fn targets() {
+        // Looking straight down the axis, at the default world's spheres
+        let scene = scene(pointi(0, 0, -5), pointi(0, 0, 0));
+        let turntable = Turntable::new(&scene, 4, vectori(0, 0, 1), None);
+        assert_eq!(turntable.target(), pointi(0, 0, -1));
+        assert_eq!(position(&turntable.camera(2)), pointi(0, 0, -5));
+
+        // And away from everything
+        let scene = self::scene(pointi(0, 0, -5), pointi(0, 0, -10));
+        let turntable = Turntable::new(&scene, 4, vectori(0, 1, 0), None);
+        assert_eq!(turntable.target(), pointi(0, 0, -10));
+    }
+}
@@ -1,16 +1,16 @@
-use std::fs;
+use std::{fs::File, io::BufWriter};
 
 use raytracer::{
     canvas::Canvas,
     colour::Colour,
-    math::{matrix::Matrix, tuple::Tuple},
+    math::{matrix::Matrix, point::Point},
     ray::{Ray, RayIntersect},
     shape::sphere::Sphere,
 };
 
 fn main() -> std::result::Result<(), std::io::Error> {
     let mut canvas = Canvas::new_with_colour(400, 400, Colour::BLACK);
-    let ray_origin = Tuple::pointi(0, 0, -5);
+    let ray_origin = Point::newi(0, 0, -5);
     let wall_z = 10.0;
     let wall_size = 7.0;
     let pixel_size = wall_size / canvas.width as f64;
@@ -27,15 +27,15 @@ fn main() -> std::result::Result<(), std::io::Error> {
         for col_p in 0..canvas.width {
             let world_x = -wall_half + pixel_size * col_p as f64;
 
-            let pos = Tuple::point(world_x, world_y, wall_z);
+            let pos = Point::new(world_x, world_y, wall_z);
 
             let ray = Ray::new(ray_origin, (pos - ray_origin).normalize());
 
             if sphere.intersect(ray).is_some() {
-                canvas[(row_p, col_p)] = Colour::RED;
+                canvas[(col_p, row_p)] = Colour::RED;
             }
         }
     }
 
-    fs::write("out/shadow.ppm", canvas.into_ppm())
+    canvas.write_ppm(false, BufWriter::new(File::create("out/shadow.ppm")?))
 }
@@ -1,21 +1,19 @@
-use std::fs;
+use std::{fs::File, io::BufWriter};
 
 use raytracer::{
     canvas::Canvas,
     colour::Colour,
     lights::PointLight,
     materials::Material,
-    math::{
-        matrix::Matrix,
-        tuple::{pointi, Tuple},
-    },
+    math::{matrix::Matrix, point::Point, tuple::pointi},
+    pattern::solid::SolidPattern,
     ray::{Ray, RayIntersect},
     shape::{sphere::Sphere, Shape},
 };
 
 fn main() -> std::result::Result<(), std::io::Error> {
     let mut canvas = Canvas::new_with_colour(400, 400, Colour::BLACK);
-    let ray_origin = Tuple::pointi(0, 0, -5);
+    let ray_origin = Point::newi(0, 0, -5);
     let wall_z = 10.0;
     let wall_size = 7.0;
     let pixel_size = wall_size / canvas.width as f64;
@@ -26,8 +24,8 @@ fn main() -> std::result::Result<(), std::io::Error> {
     );
 
     sphere.set_material(Material {
-        colour: Colour::new(1.0, 0.2, 1.0),
-        ..*sphere.material()
+        pattern: Box::new(SolidPattern(Colour::new(1.0, 0.2, 1.0))),
+        ..sphere.material().clone()
     });
 
     let light = PointLight::new(Colour::newi(1, 1, 1), pointi(-10, 10, -10));
@@ -37,22 +35,25 @@ fn main() -> std::result::Result<(), std::io::Error> {
         for col_p in 0..canvas.width {
             let world_x = -wall_half + pixel_size * col_p as f64;
 
-            let pos = Tuple::point(world_x, world_y, wall_z);
+            let pos = Point::new(world_x, world_y, wall_z);
 
             let ray = Ray::new(ray_origin, (pos - ray_origin).normalize());
 
             if let Some(xs) = sphere.intersect(ray) {
                 let first = xs.first().unwrap();
-                let point = ray.position(first.t);
-                let normal = first.object.normal_at(point);
-                let eye = -ray.direction;
-                canvas[(row_p, col_p)] = first
-                    .object
-                    .material()
-                    .lighting(&light, point, eye, normal, false);
+                let comps = first.prepare_computations(ray);
+                canvas[(col_p, row_p)] = first.object.material().lighting(
+                    &light,
+                    comps.point,
+                    comps.object_point,
+                    comps.eye_vector,
+                    comps.normal_vector,
+                    1.0,
+                );
             }
         }
     }
 
-    fs::write("out/shadow_lit_squished.ppm", canvas.into_ppm())
+    let out = BufWriter::new(File::create("out/shadow_lit_squished.ppm")?);
+    canvas.write_ppm(false, out)
 }
@@ -1,24 +1,26 @@
 use std::{
     f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4},
-    fs,
+    fs::File,
+    io::BufWriter,
 };
 
 use raytracer::{
     camera::Camera,
     colour::Colour,
-    lights::{Light, PointLight},
+    lights::rigs,
     materials::Material,
     math::{
         matrix::Matrix,
-        tuple::{point, vectori, ZERO_POINT},
+        tuple::{point, vectori},
     },
+    pattern::solid::SolidPattern,
+    scene::{RenderSettings, Scene},
     shape::{sphere::Sphere, Shape},
     world::World,
 };
 
 fn main() {
     let mul = 50;
-    let world = make_scene();
     let camera = Camera::new_with_transform(
         100 * mul,
         50 * mul,
@@ -30,16 +32,21 @@ fn main() {
         ),
     );
 
-    let res = camera.render_parallel(world);
+    let scene = Scene::new(make_scene(), camera).with_settings(RenderSettings {
+        threads: 16,
+        ..Default::default()
+    });
+    let res = scene.render();
 
-    fs::write("out/7_8_world_sync_binary.ppm", res.into_ppm_binary()).unwrap();
+    let out = BufWriter::new(File::create("out/7_8_world_sync_binary.ppm").unwrap());
+    res.write_ppm_binary(false, out).unwrap();
 }
 
 fn make_scene() -> World {
     let floor = Sphere::new(
         Matrix::scaling(10.0, 0.01, 10.0),
         Material {
-            colour: Colour::new(1.0, 0.9, 0.9),
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.9, 0.9))),
             specular: 0.0,
             ..Default::default()
         },
@@ -50,20 +57,20 @@ fn make_scene() -> World {
             .rotate_x(FRAC_PI_2)
             .rotate_y(-FRAC_PI_4)
             .translate(0.0, 0.0, 5.0),
-        floor.material,
+        floor.material.clone(),
     );
     let right_wall = Sphere::new(
         Matrix::scaling(10.0, 0.01, 10.0)
             .rotate_x(FRAC_PI_2)
             .rotate_y(FRAC_PI_4)
             .translate(0.0, 0.0, 5.0),
-        floor.material,
+        floor.material.clone(),
     );
 
     let middle = Sphere::new(
         Matrix::translation(-0.5, 1.0, 0.5),
         Material {
-            colour: Colour::new(0.1, 1.0, 0.5),
+            pattern: Box::new(SolidPattern(Colour::new(0.1, 1.0, 0.5))),
             diffuse: 0.7,
             specular: 0.3,
             ..Default::default()
@@ -73,22 +80,22 @@ fn make_scene() -> World {
     let right = Sphere::new(
         Matrix::scaling(0.5, 0.5, 0.5).translate(1.5, 0.5, -0.5),
         Material {
-            colour: Colour::new(0.5, 1.0, 0.1),
-            ..middle.material
+            pattern: Box::new(SolidPattern(Colour::new(0.5, 1.0, 0.1))),
+            ..middle.material.clone()
         },
     );
 
     let left = Sphere::new(
         Matrix::scaling(0.33, 0.33, 0.33).translate(-1.5, 0.33, -0.75),
         Material {
-            colour: Colour::new(1.0, 0.8, 0.1),
-            ..right.material
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.8, 0.1))),
+            ..right.material.clone()
         },
     );
 
-    let mut lights: Vec<Box<dyn Light>> = vec![];
     let mut objects = vec![floor, left_wall, right_wall, middle, right, left];
 
+    // Little spheres under each light, to show where they are
     for i in 0..8 {
         objects.push(Sphere::new_with_transform(
             Matrix::scaling(0.10, 0.10, 0.10)
@@ -96,21 +103,16 @@ fn make_scene() -> World {
                 .rotate_y(((360.0 / 8.0) * i as f64).to_radians())
                 .translate(0.0, 4.0, 0.0),
         ));
-
-        lights.push(PointLight::new_boxed(
-            Colour::WHITE,
-            Matrix::translation(0.0, 0.0, 30.0)
-                .rotate_y(((360.0 / 8.0) * i as f64).to_radians())
-                .translate(0.0, 50.0, 0.0)
-                * ZERO_POINT,
-        ));
     }
 
-    World {
-        objects: objects
+    // One full-white light's worth, shared between them
+    let lights = rigs::ring(point(0.0, 50.0, 0.0), 30.0, 8, Colour::WHITE, 0.0);
+
+    World::new(
+        objects
             .into_iter()
             .map(|o| Box::new(o) as Box<dyn Shape>)
             .collect(),
-        light: lights,
-    }
+        lights,
+    )
 }
@@ -1,6 +1,7 @@
 use std::{
     f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4},
-    fs,
+    fs::File,
+    io::BufWriter,
 };
 
 use raytracer::{
@@ -12,13 +13,14 @@ use raytracer::{
         matrix::Matrix,
         tuple::{point, vectori},
     },
+    pattern::solid::SolidPattern,
+    scene::{RenderSettings, Scene},
     shape::{sphere::Sphere, Shape},
     world::World,
 };
 
 fn main() {
     let mul = 20;
-    let world = make_scene();
     let camera = Camera::new_with_transform(
         100 * mul,
         50 * mul,
@@ -30,17 +32,23 @@ fn main() {
         ),
     );
 
-    let res = camera.render_parallel(world);
+    let scene = Scene::new(make_scene(), camera).with_settings(RenderSettings {
+        threads: 16,
+        ..Default::default()
+    });
+    let res = scene.render();
 
-    fs::write("out/7_world_sync.ppm", res.into_ppm()).unwrap();
-    fs::write("out/7_world_sync_BINARY.ppm", res.into_ppm_binary()).unwrap();
+    let out = BufWriter::new(File::create("out/7_world_sync.ppm").unwrap());
+    res.write_ppm(false, out).unwrap();
+    let out = BufWriter::new(File::create("out/7_world_sync_BINARY.ppm").unwrap());
+    res.write_ppm_binary(false, out).unwrap();
 }
 
 fn make_scene() -> World {
     let floor = Sphere::new(
         Matrix::scaling(10.0, 0.01, 10.0),
         Material {
-            colour: Colour::new(1.0, 0.9, 0.9),
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.9, 0.9))),
             specular: 0.0,
             ..Default::default()
         },
@@ -51,20 +59,20 @@ fn make_scene() -> World {
             .rotate_x(FRAC_PI_2)
             .rotate_y(-FRAC_PI_4)
             .translate(0.0, 0.0, 5.0),
-        floor.material,
+        floor.material.clone(),
     );
     let right_wall = Sphere::new(
         Matrix::scaling(10.0, 0.01, 10.0)
             .rotate_x(FRAC_PI_2)
             .rotate_y(FRAC_PI_4)
             .translate(0.0, 0.0, 5.0),
-        floor.material,
+        floor.material.clone(),
     );
 
     let middle = Sphere::new(
         Matrix::translation(-0.5, 1.0, 0.5),
         Material {
-            colour: Colour::new(0.1, 1.0, 0.5),
+            pattern: Box::new(SolidPattern(Colour::new(0.1, 1.0, 0.5))),
             diffuse: 0.7,
             specular: 0.3,
             ..Default::default()
@@ -74,26 +82,26 @@ fn make_scene() -> World {
     let right = Sphere::new(
         Matrix::scaling(0.5, 0.5, 0.5).translate(1.5, 0.5, -0.5),
         Material {
-            colour: Colour::new(0.5, 1.0, 0.1),
-            ..middle.material
+            pattern: Box::new(SolidPattern(Colour::new(0.5, 1.0, 0.1))),
+            ..middle.material.clone()
         },
     );
 
     let left = Sphere::new(
         Matrix::scaling(0.33, 0.33, 0.33).translate(-1.5, 0.33, -0.75),
         Material {
-            colour: Colour::new(1.0, 0.8, 0.1),
-            ..right.material
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.8, 0.1))),
+            ..right.material.clone()
         },
     );
 
     let light = PointLight::new(Colour::WHITE, point(-10.0, 10.0, -10.0));
 
-    World {
-        objects: vec![floor, left_wall, right_wall, middle, right, left]
+    World::new(
+        vec![floor, left_wall, right_wall, middle, right, left]
             .into_iter()
             .map(|o| Box::new(o) as Box<dyn Shape>)
             .collect(),
-        light: vec![Box::new(light)],
-    }
+        vec![Box::new(light)],
+    )
 }
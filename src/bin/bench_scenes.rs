@@ -0,0 +1,537 @@
+//! Renders a few fixed scenes and times each stage, to tell whether a change made things faster
+//! or slower. Prints one JSON object a line per scene:
+//!
+//! ```text
+//! {"scene": "default", "width": 200, "height": 200, "threads": 8, "build_ms": 0.012, ...}
+//! ```
+//!
+//! Save that, and a later run with `--baseline that_file` points out every stage that got
+//! more than `--threshold` percent (10 by default) slower, exiting with 1 if any did.
+
+use std::{
+    env,
+    f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4},
+    fs,
+    process::ExitCode,
+    time::Instant,
+};
+
+use raytracer::{
+    camera::Camera,
+    colour::Colour,
+    lights::PointLight,
+    materials::Material,
+    math::{
+        matrix::{Matrix, IDENTITY_4X4},
+        rng::Rng,
+        tuple::{point, vectori},
+    },
+    pattern::solid::SolidPattern,
+    scene::{RenderSettings, Scene},
+    shape::{plane::Plane, sphere::Sphere, Shape},
+    stats::StatsSnapshot,
+    world::World,
+};
+
+/// For the stress field's layout, so it's the same every run
+const SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+/// Stages quicker than this are all noise, never call them regressions
+const MIN_MS: f64 = 1.0;
+
+struct Bench {
+    name: &'static str,
+    width: usize,
+    height: usize,
+    build: fn() -> World,
+    camera: fn(usize, usize) -> Camera,
+}
+
+const BENCHES: [Bench; 3] = [
+    Bench {
+        name: "default",
+        width: 200,
+        height: 200,
+        build: World::default,
+        camera: |width, height| {
+            Camera::new_with_transform(
+                width,
+                height,
+                FRAC_PI_2,
+                Matrix::view_transform(
+                    point(0.0, 0.0, -5.0),
+                    point(0.0, 0.0, 0.0),
+                    vectori(0, 1, 0),
+                ),
+            )
+        },
+    },
+    Bench {
+        name: "room",
+        width: 300,
+        height: 150,
+        build: room,
+        camera: |width, height| {
+            Camera::new_with_transform(
+                width,
+                height,
+                FRAC_PI_3,
+                Matrix::view_transform(
+                    point(0.0, 1.5, -5.0),
+                    point(0.0, 1.0, 0.0),
+                    vectori(0, 1, 0),
+                ),
+            )
+        },
+    },
+    Bench {
+        name: "stress",
+        width: 120,
+        height: 80,
+        build: stress_field,
+        camera: |width, height| {
+            Camera::new_with_transform(
+                width,
+                height,
+                FRAC_PI_3,
+                Matrix::view_transform(
+                    point(0.0, 9.0, -16.0),
+                    point(0.0, 0.0, 0.0),
+                    vectori(0, 1, 0),
+                ),
+            )
+        },
+    },
+];
+
+/// How one scene went
+#[derive(Debug, Clone, PartialEq)]
+struct Summary {
+    scene: String,
+    width: usize,
+    height: usize,
+    threads: usize,
+    build_ms: f64,
+    /// None until there's an acceleration structure to build
+    accel_ms: Option<f64>,
+    render_ms: f64,
+    encode_ms: f64,
+    stats: StatsSnapshot,
+}
+
+impl Summary {
+    fn stages(&self) -> [(&'static str, Option<f64>); 4] {
+        [
+            ("build_ms", Some(self.build_ms)),
+            ("accel_ms", self.accel_ms),
+            ("render_ms", Some(self.render_ms)),
+            ("encode_ms", Some(self.encode_ms)),
+        ]
+    }
+
+    fn to_json(&self) -> String {
+        let accel = self
+            .accel_ms
+            .map_or("null".to_owned(), |ms| format!("{ms:.3}"));
+        let s = &self.stats;
+        format!(
+            "{{\"scene\": {:?}, \"width\": {}, \"height\": {}, \"threads\": {}, \
+             \"build_ms\": {:.3}, \"accel_ms\": {accel}, \"render_ms\": {:.3}, \"encode_ms\": {:.3}, \
+             \"primary_rays\": {}, \"reflection_rays\": {}, \"refraction_rays\": {}, \
+             \"shadow_rays\": {}, \"intersection_tests\": {}}}",
+            self.scene,
+            self.width,
+            self.height,
+            self.threads,
+            self.build_ms,
+            self.render_ms,
+            self.encode_ms,
+            s.primary_rays,
+            s.reflection_rays,
+            s.refraction_rays,
+            s.shadow_rays,
+            s.intersection_tests,
+        )
+    }
+
+    /// Reads back what to_json wrote. Not a general JSON parser, just enough for these flat
+    /// objects of numbers, nulls and quote free strings.
+    fn parse(line: &str) -> Result<Self, String> {
+        let body = line
+            .trim()
+            .strip_prefix('{')
+            .and_then(|l| l.strip_suffix('}'))
+            .ok_or(format!("not a JSON object: {line}"))?;
+        let fields: Vec<(&str, &str)> = body
+            .split(',')
+            .map(|field| {
+                let (key, value) = field
+                    .split_once(':')
+                    .ok_or(format!("bad field {field:?}"))?;
+                let key = key.trim().trim_matches('"');
+                Ok((key, value.trim()))
+            })
+            .collect::<Result<_, String>>()?;
+
+        let get = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| *v)
+                .ok_or(format!("no {key} in {line}"))
+        };
+        let number = |key: &str| -> Result<f64, String> {
+            let value = get(key)?;
+            value
+                .parse()
+                .map_err(|_| format!("{key} should be a number, not {value}"))
+        };
+        let count = |key: &str| number(key).map(|n| n as u64);
+
+        Ok(Self {
+            scene: get("scene")?.trim_matches('"').to_owned(),
+            width: number("width")? as usize,
+            height: number("height")? as usize,
+            threads: number("threads")? as usize,
+            build_ms: number("build_ms")?,
+            accel_ms: match get("accel_ms")? {
+                "null" => None,
+                _ => Some(number("accel_ms")?),
+            },
+            render_ms: number("render_ms")?,
+            encode_ms: number("encode_ms")?,
+            stats: StatsSnapshot {
+                primary_rays: count("primary_rays")?,
+                reflection_rays: count("reflection_rays")?,
+                refraction_rays: count("refraction_rays")?,
+                shadow_rays: count("shadow_rays")?,
+                intersection_tests: count("intersection_tests")?,
+            },
+        })
+    }
+}
+
+fn run(bench: &Bench, width: usize, height: usize, threads: usize) -> Summary {
+    let start = Instant::now();
+    let world = (bench.build)();
+    let scene = Scene::new(world, (bench.camera)(width, height)).with_settings(RenderSettings {
+        threads,
+        ..Default::default()
+    });
+    let build_ms = ms_since(start);
+
+    let start = Instant::now();
+    let canvas = scene.render();
+    let render_ms = ms_since(start);
+
+    let start = Instant::now();
+    let png = canvas.into_png(false);
+    std::hint::black_box(png);
+    let encode_ms = ms_since(start);
+
+    Summary {
+        scene: bench.name.to_owned(),
+        width,
+        height,
+        threads,
+        build_ms,
+        accel_ms: None,
+        render_ms,
+        encode_ms,
+        stats: scene.world.stats.snapshot(),
+    }
+}
+
+fn ms_since(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Everything in `current` that's more than `threshold` percent worse than in `baseline`, as
+/// messages. Scenes only in one of them are skipped.
+fn regressions(baseline: &[Summary], current: &[Summary], threshold: f64) -> Vec<String> {
+    let worse = |old: f64, new: f64| new > old * (1.0 + threshold / 100.0);
+    let mut found = vec![];
+
+    for new in current {
+        let Some(old) = baseline.iter().find(|old| old.scene == new.scene) else {
+            continue;
+        };
+        for ((stage, old_ms), (_, new_ms)) in old.stages().into_iter().zip(new.stages()) {
+            let (Some(old_ms), Some(new_ms)) = (old_ms, new_ms) else {
+                continue;
+            };
+            if new_ms >= MIN_MS && worse(old_ms, new_ms) {
+                found.push(format!(
+                    "{} {stage} {old_ms:.3} -> {new_ms:.3} ({:+.1}%)",
+                    new.scene,
+                    (new_ms / old_ms - 1.0) * 100.0
+                ));
+            }
+        }
+
+        let (old_tests, new_tests) = (old.stats.intersection_tests, new.stats.intersection_tests);
+        if worse(old_tests as f64, new_tests as f64) {
+            found.push(format!(
+                "{} intersection_tests {old_tests} -> {new_tests}",
+                new.scene
+            ));
+        }
+    }
+    found
+}
+
+fn main() -> ExitCode {
+    let mut baseline = None;
+    let mut threshold = 10.0;
+    let mut threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        let parsed = match (arg.as_str(), &value) {
+            ("--baseline", Some(file)) => {
+                baseline = Some(file.clone());
+                true
+            }
+            ("--threshold", Some(pct)) => pct.parse().map(|pct| threshold = pct).is_ok(),
+            ("--threads", Some(n)) => n.parse().map(|n| threads = n).is_ok(),
+            _ => false,
+        };
+        if !parsed {
+            eprintln!("usage: bench_scenes [--baseline FILE] [--threshold PERCENT] [--threads N]");
+            return ExitCode::from(2);
+        }
+    }
+
+    let baseline = match baseline.map(|file| {
+        fs::read_to_string(&file)
+            .map_err(|e| format!("can't read {file}: {e}"))?
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(Summary::parse)
+            .collect::<Result<Vec<_>, _>>()
+    }) {
+        Some(Err(e)) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+        baseline => baseline.transpose().unwrap(),
+    };
+
+    let summaries: Vec<_> = BENCHES
+        .iter()
+        .map(|bench| {
+            let summary = run(bench, bench.width, bench.height, threads);
+            println!("{}", summary.to_json());
+            summary
+        })
+        .collect();
+
+    if let Some(baseline) = baseline {
+        let found = regressions(&baseline, &summaries, threshold);
+        for regression in &found {
+            eprintln!("regression: {regression}");
+        }
+        if !found.is_empty() {
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// The chapter 7 room: a floor and two walls (squashed spheres) and three spheres
+fn room() -> World {
+    let floor = Sphere::new(
+        Matrix::scaling(10.0, 0.01, 10.0),
+        Material {
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.9, 0.9))),
+            specular: 0.0,
+            ..Default::default()
+        },
+    );
+    let wall = |angle: f64| {
+        Sphere::new(
+            Matrix::scaling(10.0, 0.01, 10.0)
+                .rotate_x(FRAC_PI_2)
+                .rotate_y(angle)
+                .translate(0.0, 0.0, 5.0),
+            floor.material.clone(),
+        )
+    };
+    let (left_wall, right_wall) = (wall(-FRAC_PI_4), wall(FRAC_PI_4));
+
+    let middle = Sphere::new(
+        Matrix::translation(-0.5, 1.0, 0.5),
+        Material {
+            pattern: Box::new(SolidPattern(Colour::new(0.1, 1.0, 0.5))),
+            diffuse: 0.7,
+            specular: 0.3,
+            ..Default::default()
+        },
+    );
+    let right = Sphere::new(
+        Matrix::scaling(0.5, 0.5, 0.5).translate(1.5, 0.5, -0.5),
+        Material {
+            pattern: Box::new(SolidPattern(Colour::new(0.5, 1.0, 0.1))),
+            ..middle.material.clone()
+        },
+    );
+    let left = Sphere::new(
+        Matrix::scaling(0.33, 0.33, 0.33).translate(-1.5, 0.33, -0.75),
+        Material {
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.8, 0.1))),
+            ..right.material.clone()
+        },
+    );
+
+    World::new(
+        vec![floor, left_wall, right_wall, middle, right, left]
+            .into_iter()
+            .map(|o| Box::new(o) as Box<dyn Shape>)
+            .collect(),
+        vec![PointLight::new_boxed(
+            Colour::WHITE,
+            point(-10.0, 10.0, -10.0),
+        )],
+    )
+}
+
+/// 500 spheres on a floor in a 25 x 20 grid, a fifth of them mirrored and a tenth glass
+fn stress_field() -> World {
+    let mut rng = Rng::new(SEED);
+    let mut objects: Vec<Box<dyn Shape>> = vec![Box::new(Plane::new(
+        IDENTITY_4X4,
+        Material {
+            pattern: Box::new(SolidPattern(Colour::new(0.8, 0.8, 0.8))),
+            reflective: 0.1,
+            ..Default::default()
+        },
+    ))];
+
+    for i in 0..500 {
+        let (column, row) = ((i % 25) as f64, (i / 25) as f64);
+        let radius = rng.between(0.15, 0.35);
+        let x = (column - 12.0) * 0.8 + rng.between(-0.1, 0.1);
+        let z = (row - 10.0) * 0.8 + rng.between(-0.1, 0.1);
+
+        let mut material = Material {
+            pattern: Box::new(SolidPattern(Colour::new(
+                rng.next_f64(),
+                rng.next_f64(),
+                rng.next_f64(),
+            ))),
+            ..Default::default()
+        };
+        match rng.next_f64() {
+            kind if kind < 0.1 => {
+                material.transparency = 0.9;
+                material.refractive_index = 1.5;
+                material.diffuse = 0.1;
+            }
+            kind if kind < 0.3 => material.reflective = 0.7,
+            _ => (),
+        }
+
+        objects.push(Box::new(Sphere::new(
+            Matrix::scaling(radius, radius, radius).translate(x, radius, z),
+            material,
+        )));
+    }
+
+    World::new(
+        objects,
+        vec![PointLight::new_boxed(
+            Colour::WHITE,
+            point(-10.0, 20.0, -15.0),
+        )],
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use raytracer::stats::StatsSnapshot;
+
+    use super::{regressions, run, Summary, BENCHES, MIN_MS};
+
+    fn summary(scene: &str, render_ms: f64) -> Summary {
+        Summary {
+            scene: scene.to_owned(),
+            width: 10,
+            height: 5,
+            threads: 2,
+            build_ms: 0.5,
+            accel_ms: None,
+            render_ms,
+            encode_ms: 2.0,
+            stats: StatsSnapshot {
+                primary_rays: 50,
+                reflection_rays: 4,
+                refraction_rays: 3,
+                shadow_rays: 40,
+                intersection_tests: 1000,
+            },
+        }
+    }
+
+    #[test]
+    fn schema() {
+        let s = summary("stress", 123.456);
+        let json: serde_json::Value = serde_json::from_str(&s.to_json()).unwrap();
+        assert_eq!(json["scene"], "stress");
+        assert_eq!(json["width"], 10);
+        assert_eq!(json["threads"], 2);
+        assert_eq!(json["accel_ms"], serde_json::Value::Null);
+        assert_eq!(json["render_ms"], 123.456);
+        assert_eq!(json["intersection_tests"], 1000);
+        assert_eq!(json.as_object().unwrap().len(), 13);
+
+        assert_eq!(Summary::parse(&s.to_json()), Ok(s.clone()));
+        let accelerated = Summary {
+            accel_ms: Some(4.25),
+            ..s
+        };
+        assert_eq!(Summary::parse(&accelerated.to_json()), Ok(accelerated));
+
+        assert!(Summary::parse("{\"scene\": \"x\"}").is_err());
+        assert!(Summary::parse("nope").is_err());
+    }
+
+    #[test]
+    fn deterministic_stress() {
+        let stress = BENCHES.iter().find(|b| b.name == "stress").unwrap();
+        let first = run(stress, 8, 6, 2);
+        let second = run(stress, 8, 6, 1);
+        assert_eq!(first.stats, second.stats);
+        assert_eq!(first.stats.primary_rays, 8 * 6);
+        assert!(first.stats.reflection_rays > 0);
+        assert!(first.stats.refraction_rays > 0);
+    }
+
+    #[test]
+    fn flags_regressions() {
+        let baseline = [summary("default", 100.0), summary("room", 100.0)];
+        let current = [
+            summary("default", 105.0),
+            summary("room", 150.0),
+            summary("new", 1000.0),
+        ];
+        assert_eq!(
+            regressions(&baseline, &current, 10.0),
+            ["room render_ms 100.000 -> 150.000 (+50.0%)"]
+        );
+        assert_eq!(regressions(&baseline, &current, 60.0), Vec::<String>::new());
+        // Faster is fine
+        assert!(regressions(&current, &baseline, 10.0).is_empty());
+
+        // Tiny stages are noise
+        let mut quick = summary("default", 100.0);
+        quick.build_ms = MIN_MS / 2.0;
+        assert!(regressions(&[summary("default", 100.0)], &[quick], 10.0).is_empty());
+
+        let mut busier = summary("default", 100.0);
+        busier.stats.intersection_tests = 2000;
+        assert_eq!(
+            regressions(&baseline, &[busier], 10.0),
+            ["default intersection_tests 1000 -> 2000"]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! A sphere wrapped in UvCheckers, 16 across and 8 up so the squares at the equator come out
+//! square
+
+use std::{
+    f64::consts::FRAC_PI_3,
+    fs::{self, File},
+    io::BufWriter,
+};
+
+use raytracer::{
+    camera::Camera,
+    colour::Colour,
+    lights::PointLight,
+    materials::Material,
+    math::{
+        matrix::Matrix,
+        tuple::{point, vectori},
+    },
+    pattern::uv_checkers::UvCheckers,
+    scene::{RenderSettings, Scene},
+    shape::{plane::Plane, sphere::Sphere, Shape},
+    world::World,
+};
+
+fn main() -> std::io::Result<()> {
+    fs::create_dir_all("out")?;
+    let camera = Camera::new_with_transform(
+        800,
+        400,
+        FRAC_PI_3,
+        Matrix::view_transform(
+            point(0.0, 1.5, -5.0),
+            point(0.0, 1.0, 0.0),
+            vectori(0, 1, 0),
+        ),
+    );
+
+    let scene = Scene::new(make_scene(), camera).with_settings(RenderSettings {
+        threads: 16,
+        ..Default::default()
+    });
+    let res = scene.render();
+
+    let out = BufWriter::new(File::create("out/checkered_globe.png")?);
+    res.write_png(true, out)
+}
+
+fn make_scene() -> World {
+    let floor = Plane::new_with_material(Material {
+        specular: 0.0,
+        ..Material::default().with_colour(Colour::new(0.6, 0.6, 0.6))
+    });
+
+    let globe = Sphere::new(
+        Matrix::translationi(0, 1, 0).rotate_y(0.3),
+        Material {
+            pattern: Box::new(UvCheckers::new(
+                16,
+                8,
+                Colour::new(0.1, 0.3, 0.8),
+                Colour::new(0.9, 0.9, 0.8),
+            )),
+            diffuse: 0.8,
+            specular: 0.2,
+            ..Default::default()
+        },
+    );
+
+    let light = PointLight::new(Colour::WHITE, point(-10.0, 10.0, -10.0));
+
+    World::new(
+        vec![Box::new(floor) as Box<dyn Shape>, Box::new(globe)],
+        vec![Box::new(light)],
+    )
+}
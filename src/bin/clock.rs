@@ -1,4 +1,4 @@
-use std::fs;
+use std::{fs::File, io::BufWriter};
 
 use raytracer::{
     canvas::Canvas,
@@ -24,6 +24,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     println!("Starting output");
-    fs::write("out/clock.ppm", canvas.into_ppm())?;
+    canvas.write_ppm(false, BufWriter::new(File::create("out/clock.ppm")?))?;
     Ok(())
 }
@@ -0,0 +1,168 @@
+//! Renders scenes::random_spheres, a field of randomly placed spheres on a floor:
+//!
+//! ```text
+//! gen_scene --seed 42 --count 200 --width 800 --height 450 --out spheres.png
+//! ```
+//!
+//! The same seed and count always make the same scene, whatever the resolution.
+
+use std::{env, fs, path::PathBuf, process::ExitCode, time::Instant};
+
+use raytracer::{camera::Camera, scene::RenderSettings, scenes::random_spheres};
+
+const USAGE: &str = "usage: gen_scene [options]
+options: --seed N (1), --count N (50), --width N (400), --height N (225),
+         --out FILE.(ppm|png) (spheres.png), --threads N (all of them)";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Options {
+    seed: u64,
+    count: usize,
+    width: usize,
+    height: usize,
+    out: PathBuf,
+    threads: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            count: 50,
+            width: 400,
+            height: 225,
+            out: PathBuf::from("spheres.png"),
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_owned(), Some(value.into())),
+            _ => (arg.clone(), None),
+        };
+        let value = inline
+            .or_else(|| args.next())
+            .ok_or(format!("{flag} needs a value"))?;
+        let number = |min: usize| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n >= min)
+                .ok_or(format!("bad {flag} {value:?}"))
+        };
+
+        match flag.as_str() {
+            "--seed" => {
+                options.seed = value.parse().map_err(|_| format!("bad --seed {value:?}"))?
+            }
+            "--count" => options.count = number(0)?,
+            "--width" => options.width = number(1)?,
+            "--height" => options.height = number(1)?,
+            "--threads" => options.threads = number(1)?,
+            "--out" => {
+                let out = PathBuf::from(&value);
+                if !matches!(
+                    out.extension().and_then(|e| e.to_str()),
+                    Some("ppm" | "png")
+                ) {
+                    return Err(format!("can only write .ppm or .png, not {value:?}"));
+                }
+                options.out = out;
+            }
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut scene = random_spheres(options.seed, options.count);
+    scene.camera = match Camera::try_new_with_transform(
+        options.width,
+        options.height,
+        scene.camera.fov,
+        scene.camera.transform.clone(),
+    ) {
+        Ok(camera) => camera,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let scene = scene.with_settings(RenderSettings {
+        threads: options.threads,
+        ..RenderSettings::DEFAULT
+    });
+
+    let start = Instant::now();
+    let canvas = scene.render();
+    eprintln!(
+        "{} spheres (seed {}) at {}x{} in {:.2?}",
+        options.count,
+        options.seed,
+        options.width,
+        options.height,
+        start.elapsed()
+    );
+
+    let bytes = if options.out.extension().is_some_and(|e| e == "ppm") {
+        canvas.into_ppm_binary(false)
+    } else {
+        canvas.into_png(false)
+    };
+    if let Err(e) = fs::write(&options.out, bytes) {
+        eprintln!("error: couldn't write {}: {e}", options.out.display());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{parse_args, Options};
+
+    fn args(line: &str) -> Result<Options, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(args("").unwrap(), Options::default());
+        assert_eq!(
+            args("--seed 42 --count=200 --width 80 --height=45 --out a.ppm --threads 2").unwrap(),
+            Options {
+                seed: 42,
+                count: 200,
+                width: 80,
+                height: 45,
+                out: PathBuf::from("a.ppm"),
+                threads: 2,
+            }
+        );
+
+        assert_eq!(args("--width 0").unwrap_err(), "bad --width \"0\"");
+        assert_eq!(args("--seed -1").unwrap_err(), "bad --seed \"-1\"");
+        assert_eq!(args("--count").unwrap_err(), "--count needs a value");
+        assert_eq!(
+            args("--out a.jpg").unwrap_err(),
+            "can only write .ppm or .png, not \"a.jpg\""
+        );
+        assert_eq!(args("--nope 1").unwrap_err(), "unknown option --nope");
+    }
+}
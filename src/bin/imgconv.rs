@@ -0,0 +1,420 @@
+//! Converts images between PPM, PNG and BMP, optionally flipping, gamma correcting or resizing
+//! them on the way. One file:
+//!
+//! ```text
+//! imgconv input.ppm -o output.png
+//! ```
+//!
+//! or a whole batch, each written to `--out-dir` under the same name with the new extension:
+//!
+//! ```text
+//! imgconv frames/*.ppm --to png --out-dir converted/
+//! ```
+//!
+//! Inputs have to be PPMs. In a batch, a file that can't be converted is reported and skipped,
+//! and the exit code is 1 if any were.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use raytracer::canvas::Canvas;
+
+const USAGE: &str = "usage: imgconv INPUT.ppm -o OUTPUT.(ppm|png|bmp) [options]
+       imgconv INPUT.ppm... --to (ppm|png|bmp) --out-dir DIR [options]
+options: --flip-v, --gamma G, --resize WxH";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ppm,
+    Png,
+    Bmp,
+}
+
+impl Format {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            "bmp" => Some(Format::Bmp),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        Format::parse(path.extension()?.to_str()?)
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Ppm => "ppm",
+            Format::Png => "png",
+            Format::Bmp => "bmp",
+        }
+    }
+
+    fn encode(self, canvas: &Canvas) -> Vec<u8> {
+        // What was read in is already whatever encoding it was written with, so it's left alone
+        match self {
+            Format::Ppm => canvas.into_ppm_binary(false),
+            Format::Png => canvas.into_png(false),
+            Format::Bmp => canvas.into_bmp(false),
+        }
+    }
+}
+
+/// What to do to each image, in this order
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Edits {
+    flip_v: bool,
+    gamma: Option<f64>,
+    resize: Option<(usize, usize)>,
+}
+
+impl Edits {
+    fn apply(&self, mut canvas: Canvas) -> Canvas {
+        if self.flip_v {
+            canvas = canvas.flipped_vertical();
+        }
+        if let Some(gamma) = self.gamma {
+            canvas = canvas.gamma_corrected(gamma);
+        }
+        if let Some((width, height)) = self.resize {
+            canvas = canvas.resized(width, height);
+        }
+        canvas
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Job {
+    Single {
+        input: PathBuf,
+        output: PathBuf,
+        format: Format,
+    },
+    Batch {
+        inputs: Vec<PathBuf>,
+        format: Format,
+        out_dir: PathBuf,
+    },
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<(Job, Edits), String> {
+    let mut inputs = vec![];
+    let mut output = None;
+    let mut format = None;
+    let mut out_dir = None;
+    let mut edits = Edits::default();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_owned(), Some(value.into())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or(format!("{flag} needs a value"))
+        };
+
+        match flag.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "--to" => {
+                let name = value()?;
+                format = Some(Format::parse(&name).ok_or(format!("unknown format {name:?}"))?);
+            }
+            "--out-dir" => out_dir = Some(PathBuf::from(value()?)),
+            "--flip-v" => edits.flip_v = true,
+            "--gamma" => {
+                let gamma = value()?;
+                edits.gamma = Some(
+                    gamma
+                        .parse()
+                        .ok()
+                        .filter(|g: &f64| g.is_finite() && *g > 0.0)
+                        .ok_or(format!("bad gamma {gamma:?}"))?,
+                );
+            }
+            "--resize" => {
+                let size = value()?;
+                edits.resize = Some(
+                    size.split_once(['x', 'X'])
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|(w, h)| *w > 0 && *h > 0)
+                        .ok_or(format!("bad size {size:?}, expected WxH"))?,
+                );
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("no input files".into());
+    }
+    let job = match (output, out_dir) {
+        (Some(_), Some(_)) => return Err("-o and --out-dir can't be used together".into()),
+        (Some(output), None) => {
+            if inputs.len() > 1 {
+                return Err("-o only takes one input, use --out-dir for several".into());
+            }
+            // --to wins over the extension
+            let format = format
+                .or_else(|| Format::from_path(&output))
+                .ok_or(format!(
+                    "can't tell the format of {}, use --to",
+                    output.display()
+                ))?;
+            Job::Single {
+                input: inputs.remove(0),
+                output,
+                format,
+            }
+        }
+        (None, Some(out_dir)) => Job::Batch {
+            inputs,
+            format: format.ok_or("--out-dir needs --to")?,
+            out_dir,
+        },
+        (None, None) => return Err("needs -o or --out-dir".into()),
+    };
+    Ok((job, edits))
+}
+
+/// Reads `input`, edits it and writes it to `output` as `format`
+fn convert(input: &Path, output: &Path, format: Format, edits: &Edits) -> Result<(), String> {
+    let data = fs::read(input).map_err(|e| format!("can't read {}: {e}", input.display()))?;
+    let canvas = Canvas::from_ppm(&data).map_err(|e| format!("{}: {e}", input.display()))?;
+    fs::write(output, format.encode(&edits.apply(canvas)))
+        .map_err(|e| format!("can't write {}: {e}", output.display()))
+}
+
+/// Does the job, returning each failure. Only a batch carries on after one.
+fn run(job: &Job, edits: &Edits) -> Vec<String> {
+    match job {
+        Job::Single {
+            input,
+            output,
+            format,
+        } => convert(input, output, *format, edits)
+            .err()
+            .into_iter()
+            .collect(),
+        Job::Batch {
+            inputs,
+            format,
+            out_dir,
+        } => {
+            if let Err(e) = fs::create_dir_all(out_dir) {
+                return vec![format!("can't create {}: {e}", out_dir.display())];
+            }
+            inputs
+                .iter()
+                .filter_map(|input| {
+                    let Some(name) = input.file_stem() else {
+                        return Some(format!("{} isn't a file", input.display()));
+                    };
+                    // Not with_extension, which would eat any dot in the name
+                    let mut name = name.to_owned();
+                    name.push(".");
+                    name.push(format.extension());
+                    let output = out_dir.join(name);
+                    convert(input, &output, *format, edits).err()
+                })
+                .collect()
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let (job, edits) = match parse_args(env::args().skip(1)) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("error: {e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let failures = run(&job, &edits);
+    for failure in &failures {
+        eprintln!("error: {failure}");
+    }
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+#[path = "../test_util.rs"]
+mod test_util;
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use raytracer::{canvas::Canvas, colour::Colour};
+
+    use crate::test_util::scratch_dir;
+
+    use super::{parse_args, run, Edits, Format, Job};
+
+    fn args(line: &str) -> Result<(Job, Edits), String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    fn picture() -> Canvas {
+        let mut c = Canvas::new(4, 2);
+        c[(0, 0)] = Colour::new(1.0, 0.0, 0.0);
+        c[(3, 1)] = Colour::new(0.0, 0.2, 1.0);
+        c
+    }
+
+    fn convert(edits: &str, from: &Canvas) -> Canvas {
+        let dir = scratch_dir(&format!("imgconv-{}", edits.replace(' ', "")));
+        fs::write(dir.join("in.ppm"), from.into_ppm_binary(false)).unwrap();
+        let (job, edits) =
+            args(&format!("{0}/in.ppm -o {0}/out.ppm {edits}", dir.display())).unwrap();
+        assert!(run(&job, &edits).is_empty());
+
+        let out = Canvas::from_ppm(&fs::read(dir.join("out.ppm")).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        out
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(
+            args("a.ppm -o b.PNG --flip-v --gamma=2.2 --resize 10x5").unwrap(),
+            (
+                Job::Single {
+                    input: "a.ppm".into(),
+                    output: "b.PNG".into(),
+                    format: Format::Png
+                },
+                Edits {
+                    flip_v: true,
+                    gamma: Some(2.2),
+                    resize: Some((10, 5))
+                }
+            )
+        );
+        assert_eq!(
+            args("a.ppm -o b --to bmp").unwrap().0,
+            Job::Single {
+                input: "a.ppm".into(),
+                output: "b".into(),
+                format: Format::Bmp
+            }
+        );
+        assert_eq!(
+            args("a.ppm b.ppm --to png --out-dir out").unwrap().0,
+            Job::Batch {
+                inputs: vec!["a.ppm".into(), "b.ppm".into()],
+                format: Format::Png,
+                out_dir: "out".into()
+            }
+        );
+
+        for bad in [
+            "",
+            "a.ppm",
+            "a.ppm -o b",
+            "a.ppm b.ppm -o c.png",
+            "a.ppm --out-dir out",
+            "a.ppm -o b.png --out-dir out",
+            "a.ppm -o b.png --to gif",
+            "a.ppm -o b.png --gamma 0",
+            "a.ppm -o b.png --resize 10",
+            "a.ppm -o b.png --resize 0x3",
+            "a.ppm -o b.png --bogus",
+            "a.ppm -o",
+        ] {
+            assert!(args(bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn single_file_round_trip() {
+        let dir = scratch_dir("imgconv-single");
+        let c = picture();
+        fs::write(dir.join("in.ppm"), c.into_ppm(false)).unwrap();
+
+        for (name, expected) in [
+            ("out.png", c.into_png(false)),
+            ("out.bmp", c.into_bmp(false)),
+            ("out.ppm", c.into_ppm_binary(false)),
+        ] {
+            let (job, edits) = args(&format!("{0}/in.ppm -o {0}/{name}", dir.display())).unwrap();
+            assert!(run(&job, &edits).is_empty());
+            assert_eq!(fs::read(dir.join(name)).unwrap(), expected);
+        }
+
+        let (job, edits) = args(&format!("{0}/missing.ppm -o {0}/x.png", dir.display())).unwrap();
+        assert_eq!(run(&job, &edits).len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn batch_skips_bad_files() {
+        let dir = scratch_dir("imgconv-batch");
+        let c = picture();
+        fs::write(dir.join("a.ppm"), c.into_ppm_binary(false)).unwrap();
+        fs::write(dir.join("b.ppm"), "P6 4 2 255\ntruncated").unwrap();
+        fs::write(dir.join("c.ppm"), c.into_ppm(false)).unwrap();
+
+        let (job, edits) = args(&format!(
+            "{0}/a.ppm {0}/b.ppm {0}/c.ppm --to png --out-dir {0}/converted",
+            dir.display()
+        ))
+        .unwrap();
+        let failures = run(&job, &edits);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("b.ppm"), "{failures:?}");
+
+        let converted = dir.join("converted");
+        assert_eq!(
+            fs::read(converted.join("a.png")).unwrap(),
+            c.into_png(false)
+        );
+        assert_eq!(
+            fs::read(converted.join("c.png")).unwrap(),
+            c.into_png(false)
+        );
+        assert!(!converted.join("b.png").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flip() {
+        let c = picture();
+        assert!(convert("--flip-v", &c)
+            .iter()
+            .eq(c.flipped_vertical().iter()));
+    }
+
+    #[test]
+    fn gamma() {
+        let c = Canvas::new_with_colour(2, 2, Colour::WHITE * 0.25);
+        // Back through 8 bits, so only as close as that
+        let out = convert("--gamma 2", &c);
+        assert!(out
+            .iter()
+            .all(|p| (p.red - 0.5).abs() < 1.0 / 255.0 && p.red == p.blue));
+    }
+
+    #[test]
+    fn resize() {
+        let c = picture();
+        let out = convert("--resize 8x3", &c);
+        assert_eq!((out.width, out.height), (8, 3));
+        assert_eq!(out[(0, 0)], Colour::new(1.0, 0.0, 0.0));
+        assert_eq!(out[(7, 2)], Colour::new(0.0, 0.2, 1.0));
+    }
+}
@@ -1,4 +1,4 @@
-use std::fs::{self};
+use std::{fs::File, io::BufWriter};
 
 use raytracer::{canvas::Canvas, colour::Colour, math::tuple::Tuple};
 
@@ -17,14 +17,14 @@ fn main() {
     loop {
         proj = tick(&environ, &proj);
         println!("{:?}", proj.position);
-        if (0.0..canvas.width as f64).contains(&proj.position.x)
-            && (0.0..canvas.height as f64).contains(&proj.position.y)
-        {
-            let height = canvas.height;
-            canvas[(
-                proj.position.x.floor() as usize,
-                height - proj.position.y.floor() as usize,
-            )] = Colour::RED;
+        if proj.position.x >= 0.0 && proj.position.y >= 0.0 {
+            // Flipped, as the canvas starts at the top. set clips anything off the edges.
+            if let Some(row) = canvas
+                .height
+                .checked_sub(1 + proj.position.y.floor() as usize)
+            {
+                canvas.set(proj.position.x.floor() as usize, row, Colour::RED);
+            }
         }
 
         if proj.position.y <= 0.0 {
@@ -32,7 +32,8 @@ fn main() {
         }
     }
 
-    fs::write("out/projectile_rendered.ppm", canvas.into_ppm()).unwrap();
+    let out = BufWriter::new(File::create("out/projectile_rendered.ppm").unwrap());
+    canvas.write_ppm(false, out).unwrap();
 }
 
 struct Environment {
@@ -0,0 +1,295 @@
+//! Renders a scene file from all the way round, as numbered frames and optionally a GIF:
+//!
+//! ```text
+//! turntable scenes/example.yaml --frames 36 --out-dir spin --gif spin.gif
+//! ```
+//!
+//! The camera orbits what it's looking at, see animation::turntable.
+
+use std::{
+    env,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+use raytracer::{
+    animation::turntable,
+    camera::Camera,
+    canvas::gif::GifEncoder,
+    math::tuple::{vector, vectori, Tuple},
+    output::{FrameFormat, FrameWriter},
+    scene::{RenderSettings, SceneDescription},
+};
+
+const USAGE: &str = "usage: turntable SCENE.(yaml|json) [options]
+options: --frames N (36), --axis X,Y,Z (0,1,0), --radius R, --width N, --height N,
+         --out-dir DIR (turntable), --format (png|ppm) (png), --gif FILE,
+         --delay HUNDREDTHS (4), --threads N (all of them)";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    scene: PathBuf,
+    frames: usize,
+    axis: Tuple,
+    radius: Option<f64>,
+    /// Overrides the scene camera's
+    size: (Option<usize>, Option<usize>),
+    out_dir: PathBuf,
+    format: FrameFormat,
+    gif: Option<PathBuf>,
+    delay_cs: u16,
+    threads: usize,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut scene = None;
+    let mut options = Options {
+        scene: PathBuf::new(),
+        frames: 36,
+        axis: vectori(0, 1, 0),
+        radius: None,
+        size: (None, None),
+        out_dir: PathBuf::from("turntable"),
+        format: FrameFormat::Png,
+        gif: None,
+        delay_cs: 4,
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            if scene.replace(PathBuf::from(&arg)).is_some() {
+                return Err("only one scene at a time".into());
+            }
+            continue;
+        }
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_owned(), Some(value.into())),
+            _ => (arg.clone(), None),
+        };
+        let value = inline
+            .or_else(|| args.next())
+            .ok_or(format!("{flag} needs a value"))?;
+        let bad = || format!("bad {flag} {value:?}");
+        let positive = || {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(bad)
+        };
+
+        match flag.as_str() {
+            "--frames" => options.frames = positive()?,
+            "--width" => options.size.0 = Some(positive()?),
+            "--height" => options.size.1 = Some(positive()?),
+            "--threads" => options.threads = positive()?,
+            "--axis" => {
+                let xyz: Vec<f64> = value
+                    .split(',')
+                    .map(|n| n.trim().parse().map_err(|_| bad()))
+                    .collect::<Result<_, _>>()?;
+                let [x, y, z] = xyz[..] else {
+                    return Err(bad());
+                };
+                options.axis = vector(x, y, z);
+                if !(options.axis.magnitude() > 0.0 && options.axis.magnitude().is_finite()) {
+                    return Err(format!("--axis can't be {value:?}"));
+                }
+            }
+            "--radius" => {
+                options.radius = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|r: &f64| r.is_finite() && *r > 0.0)
+                        .ok_or_else(bad)?,
+                )
+            }
+            "--out-dir" => options.out_dir = PathBuf::from(value),
+            "--format" => {
+                options.format = match value.to_ascii_lowercase().as_str() {
+                    "png" => FrameFormat::Png,
+                    "ppm" => FrameFormat::PpmBinary,
+                    _ => return Err(format!("unknown format {value:?}, expected png or ppm")),
+                }
+            }
+            "--gif" => options.gif = Some(PathBuf::from(value)),
+            "--delay" => options.delay_cs = value.parse().map_err(|_| bad())?,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    options.scene = scene.ok_or("no scene file")?;
+    Ok(options)
+}
+
+/// Renders every frame, returning where they went
+fn run(options: &Options) -> Result<Vec<PathBuf>, String> {
+    let failed = |path: &Path, e: &dyn std::fmt::Display| format!("{}: {e}", path.display());
+    let mut scene = SceneDescription::load(&options.scene)
+        .and_then(|description| description.to_scene())
+        .map_err(|e| failed(&options.scene, &e))?;
+    scene.settings = RenderSettings {
+        threads: options.threads,
+        ..scene.settings
+    };
+    if options.size != (None, None) {
+        let camera = &scene.camera;
+        scene.camera = Camera::try_new_with_transform(
+            options.size.0.unwrap_or(camera.hsize),
+            options.size.1.unwrap_or(camera.vsize),
+            camera.fov,
+            camera.transform.clone(),
+        )
+        .map_err(|e| failed(&options.scene, &e))?;
+    }
+
+    let mut frames = FrameWriter::new(&options.out_dir, options.format)
+        .and_then(|writer| writer.with_template("frame_{:04}"))
+        .map_err(|e| failed(&options.out_dir, &e))?;
+    let mut gif = match &options.gif {
+        Some(path) => Some((
+            path,
+            GifEncoder::new(
+                BufWriter::new(File::create(path).map_err(|e| failed(path, &e))?),
+                options.delay_cs,
+            ),
+        )),
+        None => None,
+    };
+
+    let cameras: Vec<_> = turntable(&scene, options.frames, options.axis, options.radius).collect();
+    let mut written = vec![];
+    for (n, camera) in cameras.into_iter().enumerate() {
+        let start = Instant::now();
+        scene.camera = camera;
+        let canvas = scene.render();
+        let path = frames
+            .write_frame(n, &canvas)
+            .map_err(|e| failed(&options.out_dir, &e))?;
+        if let Some((path, gif)) = &mut gif {
+            gif.add_frame(&canvas).map_err(|e| failed(path, &e))?;
+        }
+        eprintln!(
+            "frame {}/{} in {:.2?}",
+            n + 1,
+            options.frames,
+            start.elapsed()
+        );
+        written.push(path);
+    }
+    if let Some((path, gif)) = gif {
+        gif.finish().map_err(|e| failed(path, &e))?;
+        written.push(path.clone());
+    }
+    Ok(written)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&options) {
+        Ok(written) => {
+            println!(
+                "wrote {} frames to {}",
+                options.frames,
+                options.out_dir.display()
+            );
+            if let Some(gif) = written.last().filter(|_| options.gif.is_some()) {
+                println!("and {}", gif.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../test_util.rs"]
+mod test_util;
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use raytracer::{canvas::Canvas, math::tuple::vectori, output::FrameFormat};
+
+    use crate::test_util::scratch_dir;
+
+    use super::{parse_args, run, Options};
+
+    fn args(line: &str) -> Result<Options, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn arguments() {
+        let options =
+            args("a.yaml --frames=12 --axis 0,0,1 --radius 3 --width 40 --format ppm").unwrap();
+        assert_eq!(options.scene, std::path::PathBuf::from("a.yaml"));
+        assert_eq!(options.frames, 12);
+        assert_eq!(options.axis, vectori(0, 0, 1));
+        assert_eq!(options.radius, Some(3.0));
+        assert_eq!(options.size, (Some(40), None));
+        assert_eq!(options.format, FrameFormat::PpmBinary);
+        assert_eq!(options.gif, None);
+
+        for (bad, error) in [
+            ("--frames 2", "no scene file"),
+            ("a.yaml b.yaml", "only one scene at a time"),
+            ("a.yaml --frames 0", "bad --frames \"0\""),
+            ("a.yaml --axis 0,1", "bad --axis \"0,1\""),
+            ("a.yaml --axis 0,0,0", "--axis can't be \"0,0,0\""),
+            ("a.yaml --radius -1", "bad --radius \"-1\""),
+            (
+                "a.yaml --format gif",
+                "unknown format \"gif\", expected png or ppm",
+            ),
+            ("a.yaml --spin 1", "unknown option --spin"),
+        ] {
+            assert_eq!(args(bad).unwrap_err(), error, "{bad}");
+        }
+    }
+
+    #[test]
+    fn end_to_end() {
+        let dir = scratch_dir("turntable-end-to-end");
+        let scene = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/example.yaml");
+        let options = args(&format!(
+            "{scene} --frames 4 --width 32 --height 32 --format ppm --threads 1 \
+             --out-dir {0} --gif {0}/spin.gif",
+            dir.display()
+        ))
+        .unwrap();
+
+        let written = run(&options).unwrap();
+        assert_eq!(written.len(), 5);
+        let frames: Vec<_> = written[..4]
+            .iter()
+            .map(|path| Canvas::from_ppm(&fs::read(path).unwrap()).unwrap())
+            .collect();
+        assert!(frames.iter().all(|f| (f.width, f.height) == (32, 32)));
+        assert!(frames[0].vec() != frames[2].vec());
+        assert!(dir.join("manifest.json").exists());
+
+        let gif = fs::read(dir.join("spin.gif")).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert_eq!(&gif[6..10], [32, 0, 32, 0]);
+        assert_eq!(gif.last(), Some(&0x3b));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
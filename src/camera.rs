@@ -1,18 +1,88 @@
+use core::f64::consts::PI;
+#[cfg(feature = "std")]
 use std::{
-    sync::{mpsc, Arc},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
     thread,
 };
 
+#[cfg(feature = "std")]
+use crate::{
+    cancel::{CancellationToken, RenderResult, UNRENDERED},
+    progress::{NoProgress, ProgressSink},
+};
+
 use crate::{
-    canvas::Canvas,
+    canvas::{Canvas, PixelSurface},
+    colour::Colour,
+    error::RayTracerError,
+    intersection::{IntersectVec, IntersectionComputions},
     math::{
+        float::EPSILON,
         matrix::{Matrix, IDENTITY_4X4},
-        tuple::{point, ZERO_POINT},
+        point::Point,
+        tuple::{vector, Tuple, ZERO_POINT},
     },
+    prelude::*,
     ray::Ray,
+    trace,
     world::World,
 };
 
+#[cfg(feature = "std")]
+pub mod checkpoint;
+pub mod passes;
+pub mod rig;
+
+/// What a camera is, without any of the values Camera works out from it. This is the bit worth
+/// saving and loading, see Camera::from_config.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraConfig {
+    pub width: usize,
+    pub height: usize,
+    /// Radians
+    pub fov: f64,
+    pub from: Tuple,
+    pub to: Tuple,
+    pub up: Tuple,
+}
+
+impl CameraConfig {
+    fn validate(&self) -> Result<(), RayTracerError> {
+        let invalid = |reason| Err(RayTracerError::InvalidCamera { reason });
+
+        if self.width == 0 || self.height == 0 {
+            return invalid("width and height must be non-zero");
+        }
+        if !self.fov.is_finite() || self.fov <= 0.0 || self.fov >= PI {
+            return invalid("fov must be between 0 and pi");
+        }
+        if !self.from.is_point() || !self.to.is_point() {
+            return invalid("from and to must be points");
+        }
+        if !self.up.is_vector() {
+            return invalid("up must be a vector");
+        }
+        if [self.from, self.to, self.up]
+            .iter()
+            .any(|t| !(t.x.is_finite() && t.y.is_finite() && t.z.is_finite()))
+        {
+            return invalid("from, to and up must be finite");
+        }
+        let forward = self.to - self.from;
+        if forward.magnitude() == 0.0 {
+            return invalid("from and to must be different");
+        }
+        if forward.cross(&self.up).magnitude() == 0.0 {
+            return invalid("up can't be parallel to the view direction");
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone)]
 pub struct Camera {
     pub hsize: usize,
@@ -27,7 +97,27 @@ pub struct Camera {
 }
 
 impl Camera {
+    /// Panicking version of try_new_with_transform
     pub fn new_with_transform(hsize: usize, vsize: usize, fov: f64, transform: Matrix) -> Self {
+        Self::try_new_with_transform(hsize, vsize, fov, transform).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Fails if either size is 0, or if `transform` can't be inverted (rays are cast through
+    /// the inverse)
+    pub fn try_new_with_transform(
+        hsize: usize,
+        vsize: usize,
+        fov: f64,
+        transform: Matrix,
+    ) -> Result<Self, RayTracerError> {
+        if hsize == 0 || vsize == 0 {
+            return Err(RayTracerError::InvalidCamera {
+                reason: "width and height must be non-zero",
+            });
+        }
+        let inverse_transform = transform
+            .inverse()
+            .ok_or(RayTracerError::NonInvertibleTransform { what: "camera" })?;
         let half_view = (fov / 2.0).tan();
         let aspect_ratio = hsize as f64 / vsize as f64;
         let (half_width, half_height): (f64, f64);
@@ -42,7 +132,7 @@ impl Camera {
 
         let pixel_size = (half_width * 2.0) / hsize as f64;
 
-        Self {
+        Ok(Self {
             hsize,
             vsize,
             fov,
@@ -50,104 +140,402 @@ impl Camera {
             half_width,
             half_height,
             pixel_size,
-            inverse_transform: transform.inverse().expect("Must be invertable."),
-
-            transform, // Must go after the inverse because it moves :D
-        }
+            inverse_transform,
+            transform,
+        })
     }
 
     pub fn new(hsize: usize, vsize: usize, fov: f64) -> Self {
         Self::new_with_transform(hsize, vsize, fov, IDENTITY_4X4.clone())
     }
+
+    pub fn from_config(config: &CameraConfig) -> Result<Self, RayTracerError> {
+        config.validate()?;
+        Self::try_new_with_transform(
+            config.width,
+            config.height,
+            config.fov,
+            Matrix::view_transform(config.from, config.to, config.up),
+        )
+    }
+
+    /// The config for this camera, worked back out of the view transform (so it only makes
+    /// sense if the transform is one). `to` comes back one unit in front of `from`, and `up`
+    /// as one that gives exactly this transform again, which may not be the one it was made with.
+    pub fn to_config(&self) -> CameraConfig {
+        let row = |r: usize| {
+            let t = &self.transform;
+            vector(t[(r, 0)], t[(r, 1)], t[(r, 2)])
+        };
+        // The rows are left, true up and -forward, see Matrix::view_transform
+        let forward = -row(2);
+        let true_up = row(1);
+
+        let from = &self.inverse_transform * ZERO_POINT;
+        // true_up is only as long as the sine of the angle between the original up and forward,
+        // putting the rest back along forward gets a unit up that builds the same matrix.
+        let along = (1.0 - true_up.dot(&true_up)).max(0.0).sqrt();
+
+        CameraConfig {
+            width: self.hsize,
+            height: self.vsize,
+            fov: self.fov,
+            from,
+            to: from + forward,
+            up: true_up + forward * along,
+        }
+    }
 }
 
 impl Camera {
     pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        // offset from corner of canvas to center of pixel in world units
-        let xoffset = (x as f64 + 0.5) * self.pixel_size;
-        let yoffset = (y as f64 + 0.5) * self.pixel_size;
+        self.ray_for_subpixel(x, y, 0.5, 0.5)
+    }
+
+    /// A ray through a point inside pixel (x, y), `dx` and `dy` are 0..1 across the pixel from
+    /// its top left corner. (0.5, 0.5) is the centre, which is what ray_for_pixel uses.
+    pub fn ray_for_subpixel(&self, x: usize, y: usize, dx: f64, dy: f64) -> Ray {
+        // offset from corner of canvas to the point in the pixel in world units
+        let xoffset = (x as f64 + dx) * self.pixel_size;
+        let yoffset = (y as f64 + dy) * self.pixel_size;
 
         // World-space coords, minus z (which is always camera+1)
         let world_x = self.half_width - xoffset;
         let world_y = self.half_height - yoffset;
 
-        let pixel = &self.inverse_transform * point(world_x, world_y, -1.0);
-        let origin = &self.inverse_transform * ZERO_POINT;
+        let pixel = &self.inverse_transform * Point::new(world_x, world_y, -1.0);
+        let origin = &self.inverse_transform * Point::ORIGIN;
         let direction = (pixel - origin).normalize();
 
         Ray::new(origin, direction)
     }
 
+    /// Where on the canvas `point` (in world space) shows up, the opposite of ray_for_pixel.
+    /// Coordinates are in pixels from the top left corner, so the centre of pixel (x, y) is
+    /// (x + 0.5, y + 0.5). None if it's behind the camera or outside the frame.
+    pub fn project(&self, point: Tuple) -> Option<(f64, f64)> {
+        let p = &self.transform * point;
+        // The camera looks down -z, anything level with it or behind never reaches the canvas
+        if p.z > -EPSILON {
+            return None;
+        }
+
+        // Onto the image plane at z = -1, then back into pixels as in ray_for_subpixel
+        let (world_x, world_y) = (p.x / -p.z, p.y / -p.z);
+        let x = (self.half_width - world_x) / self.pixel_size;
+        let y = (self.half_height - world_y) / self.pixel_size;
+
+        let inside = (0.0..self.hsize as f64).contains(&x) && (0.0..self.vsize as f64).contains(&y);
+        inside.then_some((x, y))
+    }
+
     pub fn render(&self, world: &World) -> Canvas {
+        trace::span!(DEBUG, "render", width = self.hsize, height = self.vsize);
         let mut canvas = Canvas::new(self.hsize, self.vsize);
 
-        // the image plane is 1 unit ahead of us, but lets start with width/height
+        // Row by row, the same order the canvas keeps its pixels in
+        for (y, row) in canvas.rows_mut().enumerate() {
+            self.fill_row(world, y, row);
+        }
+
+        canvas
+    }
+
+    /// render, but a world with an object that can't be rendered is an error rather than that
+    /// object silently missing from the image. The world is checked once, up front.
+    pub fn try_render(&self, world: &World) -> Result<Canvas, RayTracerError> {
+        world.check_renderable()?;
+        Ok(self.render(world))
+    }
+
+    /// render, but onto any PixelSurface, which has to be hsize x vsize. A CanvasU8 keeps a
+    /// poster sized render to 3 bytes a pixel.
+    pub fn render_to(
+        &self,
+        world: &World,
+        surface: &mut impl PixelSurface,
+    ) -> Result<(), RayTracerError> {
+        self.check_surface(surface)?;
+        let mut row = vec![Colour::BLACK; self.hsize];
+        for y in 0..self.vsize {
+            self.fill_row(world, y, &mut row);
+            surface.put_row(y, &row);
+        }
+        Ok(())
+    }
+
+    fn check_surface(&self, surface: &impl PixelSurface) -> Result<(), RayTracerError> {
+        let got = (surface.width(), surface.height());
+        if got != (self.hsize, self.vsize) {
+            return Err(RayTracerError::SurfaceSize {
+                expected: (self.hsize, self.vsize),
+                got,
+            });
+        }
+        Ok(())
+    }
+
+    /// Render just row `y` into `out`, which has to be exactly hsize long. Gives the same
+    /// pixels as that row of render.
+    pub fn render_row(
+        &self,
+        world: &World,
+        y: usize,
+        out: &mut [Colour],
+    ) -> Result<(), RayTracerError> {
+        if y >= self.vsize {
+            return Err(RayTracerError::RowOutOfRange {
+                row: y,
+                height: self.vsize,
+            });
+        }
+        if out.len() != self.hsize {
+            return Err(RayTracerError::RowLength {
+                expected: self.hsize,
+                got: out.len(),
+            });
+        }
+        self.fill_row(world, y, out);
+        Ok(())
+    }
+
+    /// Every row of render, top to bottom, each rendered only once it's asked for. For writing
+    /// rows out as they finish rather than holding the whole image.
+    pub fn render_rows<'a>(
+        &'a self,
+        world: &'a World,
+    ) -> impl Iterator<Item = (usize, Vec<Colour>)> + 'a {
+        (0..self.vsize).map(move |y| {
+            let mut row = vec![Colour::BLACK; self.hsize];
+            self.fill_row(world, y, &mut row);
+            (y, row)
+        })
+    }
+
+    fn fill_row(&self, world: &World, y: usize, out: &mut [Colour]) {
+        for (x, pixel) in out.iter_mut().enumerate() {
+            *pixel = world.colour_at(self.ray_for_pixel(x, y));
+        }
+    }
+
+    /// Debug AOV: each pixel is World::bounce_depth_at for its ray, as a raw (unscaled) grey. So
+    /// pixels that see something via one mirror bounce come out as 1.0, two as 2.0, etc.
+    pub fn render_bounce_depth(&self, world: &World) -> Canvas {
+        let mut canvas = Canvas::new(self.hsize, self.vsize);
 
         for x in 0..self.hsize {
             for y in 0..self.vsize {
-                let ray = self.ray_for_pixel(x, y);
-                canvas[(x, y)] = world.colour_at(ray);
+                let depth = world.bounce_depth_at(self.ray_for_pixel(x, y)) as f64;
+                canvas[(x, y)] = Colour::new(depth, depth, depth);
             }
         }
 
         canvas
     }
 
-    pub fn render_parallel(&self, world: World) -> Canvas {
+    /// AOV: the surface normal (facing the camera) each pixel sees, with x, y and z in red,
+    /// green and blue. Black where there's nothing. A guide for Canvas::denoise_guided.
+    pub fn render_normals(&self, world: &World) -> Canvas {
+        self.render_first_hit(world, |_, comps| {
+            let n = comps.geometric_normal;
+            Colour::new(n.x, n.y, n.z)
+        })
+    }
+
+    /// AOV: how far away whatever each pixel sees is, as a raw grey. 0 where there's nothing.
+    /// A guide for Canvas::denoise_guided.
+    pub fn render_depth(&self, world: &World) -> Canvas {
+        self.render_first_hit(world, |ray, comps| {
+            let distance = (comps.point - ray.origin.tuple()).magnitude();
+            Colour::new(distance, distance, distance)
+        })
+    }
+
+    fn render_first_hit(
+        &self,
+        world: &World,
+        f: impl Fn(Ray, &IntersectionComputions) -> Colour,
+    ) -> Canvas {
         let mut canvas = Canvas::new(self.hsize, self.vsize);
-        let (tx, rx) = mpsc::channel::<_>();
+        for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+            let ray = self.ray_for_pixel(x, y);
+            let xs = world.intersect_world(ray);
+            if let Some(hit) = xs.hit() {
+                *pixel = f(ray, &hit.prepare_computations(ray));
+            }
+        }
+        canvas
+    }
 
-        let work: Vec<Vec<_>> = (0..self.hsize)
-            .flat_map(|x| (0..self.vsize).map(move |y| (x, y)))
-            .collect::<Vec<(usize, usize)>>()
-            .chunks((self.hsize * self.vsize) / 16)
-            .map(|x| x.to_owned())
-            .collect();
+    /// render, spread over a handful of threads. Needs std.
+    #[cfg(feature = "std")]
+    pub fn render_parallel(&self, world: &World, progress: Option<&dyn ProgressSink>) -> Canvas {
+        self.render_parallel_cancellable(world, &CancellationToken::new(), progress)
+            .into_canvas()
+    }
+
+    /// render_parallel, but giving up as soon as `token` is cancelled. Each thread checks it
+    /// before every pixel, whatever wasn't reached by then is left as cancel::UNRENDERED.
+    #[cfg(feature = "std")]
+    pub fn render_parallel_cancellable(
+        &self,
+        world: &World,
+        token: &CancellationToken,
+        progress: Option<&dyn ProgressSink>,
+    ) -> RenderResult {
+        trace::span!(
+            DEBUG,
+            "render_parallel",
+            width = self.hsize,
+            height = self.vsize
+        );
+        let mut canvas = Canvas::new_with_colour(self.hsize, self.vsize, UNRENDERED);
+        let finished = self.render_parallel_onto(world, &mut canvas, token, progress, |_, _| true);
+
+        // Cancelled after the last pixel went in is as good as finished
+        if finished {
+            RenderResult::Completed(canvas)
+        } else {
+            RenderResult::Cancelled(canvas)
+        }
+    }
 
-        let world = Arc::new(world);
+    /// render_parallel, but onto any PixelSurface, see render_to
+    #[cfg(feature = "std")]
+    pub fn render_parallel_to(
+        &self,
+        world: &World,
+        surface: &mut impl PixelSurface,
+        progress: Option<&dyn ProgressSink>,
+    ) -> Result<(), RayTracerError> {
+        self.check_surface(surface)?;
+        self.render_parallel_onto(
+            world,
+            surface,
+            &CancellationToken::new(),
+            progress,
+            |_, _| true,
+        );
+        Ok(())
+    }
 
-        for chunk in work {
-            let tx = tx.clone();
-            let s = self.clone();
+    /// The guts of the render_parallel functions: every pixel `wanted` says yes to, onto
+    /// `surface`. Returns whether they all got rendered.
+    #[cfg(feature = "std")]
+    fn render_parallel_onto(
+        &self,
+        world: &World,
+        surface: &mut impl PixelSurface,
+        token: &CancellationToken,
+        progress: Option<&dyn ProgressSink>,
+        wanted: impl Fn(usize, usize) -> bool + Sync,
+    ) -> bool {
+        render_pixels(
+            self.hsize,
+            self.vsize,
+            workers(),
+            token,
+            progress.unwrap_or(&NoProgress),
+            |x, y| wanted(x, y).then(|| world.colour_at(self.ray_for_pixel(x, y))),
+            |x, y, colour| {
+                surface.put(x, y, colour);
+            },
+        )
+    }
+}
+
+/// Every pixel of a `width` x `height` image, spread over `workers` threads that each take a
+/// chunk (see chunk_size) at a time until there are none left. `pixel` works out a pixel's
+/// colour on whichever thread gets it, or None to leave it be, and `put` gets the colours on
+/// this thread as they come in. Returns whether every pixel was got to before `token` was
+/// cancelled.
+#[cfg(feature = "std")]
+pub(crate) fn render_pixels(
+    width: usize,
+    height: usize,
+    workers: usize,
+    token: &CancellationToken,
+    progress: &dyn ProgressSink,
+    pixel: impl Fn(usize, usize) -> Option<Colour> + Sync,
+    mut put: impl FnMut(usize, usize, Colour),
+) -> bool {
+    let (tx, rx) = mpsc::channel::<_>();
 
-            let world = world.clone();
+    // Pixels are handed out a column at a time, as ranges of (x * height + y) so there's no
+    // list of every pixel to hold
+    let total = width * height;
+    let size = chunk_size(total, workers);
+    let next = AtomicUsize::new(0);
 
-            thread::spawn(move || {
-                for (x, y) in chunk.iter().cloned() {
-                    let ray = s.ray_for_pixel(x, y);
-                    let c = world.colour_at(ray);
-                    tx.send((x, y, c)).expect("Unable to send!");
+    let mut count = 0;
+    // Scoped, so the threads can borrow the world (and everything else) rather than needing
+    // their own
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            let (tx, next, pixel) = (tx.clone(), &next, &pixel);
+            scope.spawn(move || loop {
+                let start = next.fetch_add(size, Ordering::Relaxed);
+                if start >= total {
+                    break;
+                }
+                let chunk = start..(start + size).min(total);
+                let at = |i: usize| (i / height, i % height);
+                trace::span!(DEBUG, "render_chunk", first = ?at(chunk.start), pixels = chunk.len());
+                for (x, y) in chunk.map(at) {
+                    if token.is_cancelled() {
+                        return;
+                    }
+                    tx.send((x, y, pixel(x, y))).expect("Unable to send!");
                 }
             });
         }
 
         drop(tx); // drop the "last" one; when all the threads exit we know we're done
 
-        let mut count = 0;
-        let total = self.hsize * self.vsize;
-        while let Ok((x, y, c)) = rx.recv() {
+        while let Ok((x, y, colour)) = rx.recv() {
             count += 1;
-            if count % 1000 == 0 {
-                print!("{count} / {total}\r");
+            progress.on_progress(count, total);
+            if let Some(colour) = colour {
+                put(x, y, colour);
             }
-            canvas[(x, y)] = c;
         }
+    });
+    progress.on_complete();
 
-        canvas
-    }
+    count == total
+}
+
+/// How many threads render_parallel's work is spread over
+#[cfg(feature = "std")]
+fn workers() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Pixels per chunk of render_parallel's work. A few chunks per worker, so one that finishes
+/// early has something left to pick up, and never 0.
+#[cfg(feature = "std")]
+fn chunk_size(pixels: usize, workers: usize) -> usize {
+    const CHUNKS_PER_WORKER: usize = 4;
+    pixels.div_ceil(workers.max(1) * CHUNKS_PER_WORKER).max(1)
 }
 
 #[cfg(test)]
 mod test {
-    use std::f64::consts::FRAC_PI_2;
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
 
     use crate::{
+        canvas::Canvas,
         colour::Colour,
+        error::RayTracerError,
+        lights::PointLight,
+        materials::{Material, ShadingMode},
         math::{
             float,
             matrix::Matrix,
             tuple::{pointi, vectori},
         },
+        pattern::{stripe::StripePattern, Pattern},
+        shape::{plane::Plane, sphere::Sphere},
         world::World,
     };
 
@@ -160,6 +548,27 @@ mod test {
         assert!(float::equal(c.pixel_size, 0.01));
     }
 
+    #[test]
+    fn try_new_with_transform() {
+        let transform = Matrix::translationi(0, -2, 5);
+        let c = Camera::try_new_with_transform(11, 11, FRAC_PI_2, transform.clone()).unwrap();
+        let expected = Camera::new_with_transform(11, 11, FRAC_PI_2, transform);
+        assert_eq!(c.inverse_transform, expected.inverse_transform);
+        assert_eq!(
+            c.ray_for_pixel(3, 7).direction,
+            expected.ray_for_pixel(3, 7).direction
+        );
+
+        let err = Camera::try_new_with_transform(11, 11, FRAC_PI_2, Matrix::scalingi(1, 0, 1))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            RayTracerError::NonInvertibleTransform { what: "camera" }
+        );
+        assert_eq!(err.to_string(), "camera transform is not invertible");
+    }
+
     #[test]
     fn pixel_size_vert() {
         let c = Camera::new(125, 200, FRAC_PI_2);
@@ -167,6 +576,197 @@ mod test {
         assert!(float::equal(c.pixel_size, 0.01));
     }
 
+    mod config {
+        use std::f64::consts::{FRAC_PI_3, PI};
+
+        use crate::{
+            camera::{Camera, CameraConfig},
+            error::RayTracerError,
+            math::{
+                matrix::Matrix,
+                tuple::{point, pointi, vector, vectori},
+            },
+            world::World,
+        };
+
+        fn level() -> CameraConfig {
+            CameraConfig {
+                width: 20,
+                height: 10,
+                fov: FRAC_PI_3,
+                from: point(1.0, 2.0, -5.0),
+                to: point(1.0, 2.0, -4.0),
+                up: vector(0.0, 1.0, 0.0),
+            }
+        }
+
+        fn book() -> CameraConfig {
+            CameraConfig {
+                width: 11,
+                height: 11,
+                fov: FRAC_PI_3,
+                from: point(0.0, 1.5, -5.0),
+                to: pointi(0, 0, 0),
+                up: vectori(0, 1, 0),
+            }
+        }
+
+        #[test]
+        fn round_trip() {
+            let c = Camera::from_config(&level()).unwrap();
+            assert_eq!(c.to_config(), level());
+
+            // A looser config comes back different, but builds the same camera
+            let c = Camera::from_config(&book()).unwrap();
+            let again = Camera::from_config(&c.to_config()).unwrap();
+            assert_eq!(again.transform, c.transform);
+            assert_eq!(again.to_config(), c.to_config());
+        }
+
+        #[test]
+        fn renders_like_view_transform() {
+            let cfg = book();
+            let w = World::default();
+            let from_config = Camera::from_config(&cfg).unwrap();
+            let by_hand = Camera::new_with_transform(
+                cfg.width,
+                cfg.height,
+                cfg.fov,
+                Matrix::view_transform(cfg.from, cfg.to, cfg.up),
+            );
+
+            assert_eq!(from_config.render(&w).vec(), by_hand.render(&w).vec());
+        }
+
+        #[test]
+        fn invalid() {
+            let reason = |cfg: CameraConfig| match Camera::from_config(&cfg) {
+                Err(RayTracerError::InvalidCamera { reason }) => reason,
+                Err(e) => panic!("wrong error {e}"),
+                Ok(_) => panic!("{cfg:?} should be rejected"),
+            };
+
+            assert_eq!(
+                reason(CameraConfig {
+                    width: 0,
+                    ..level()
+                }),
+                "width and height must be non-zero"
+            );
+            assert_eq!(
+                reason(CameraConfig {
+                    height: 0,
+                    ..level()
+                }),
+                "width and height must be non-zero"
+            );
+            for fov in [f64::NAN, f64::INFINITY, 0.0, -1.0, PI] {
+                assert_eq!(
+                    reason(CameraConfig { fov, ..level() }),
+                    "fov must be between 0 and pi"
+                );
+            }
+            assert_eq!(
+                reason(CameraConfig {
+                    to: level().from,
+                    ..level()
+                }),
+                "from and to must be different"
+            );
+            assert_eq!(
+                reason(CameraConfig {
+                    up: vectori(0, 0, 3),
+                    ..level()
+                }),
+                "up can't be parallel to the view direction"
+            );
+            assert_eq!(
+                reason(CameraConfig {
+                    from: point(f64::NAN, 0.0, 0.0),
+                    ..level()
+                }),
+                "from, to and up must be finite"
+            );
+            assert_eq!(
+                reason(CameraConfig {
+                    up: point(0.0, 1.0, 0.0),
+                    ..level()
+                }),
+                "up must be a vector"
+            );
+        }
+
+        #[cfg(feature = "serde")]
+        #[test]
+        fn serde() {
+            let json = serde_json::to_string(&book()).unwrap();
+            let back: CameraConfig = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, book());
+        }
+    }
+
+    mod project {
+        use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4};
+
+        use crate::{
+            camera::Camera,
+            math::{
+                float,
+                matrix::Matrix,
+                tuple::{point, pointi, vectori},
+            },
+        };
+
+        fn lands_on(c: &Camera, x: usize, y: usize, t: f64) {
+            let target = c.ray_for_pixel(x, y).position(t);
+            let (px, py) = c.project(target.tuple()).unwrap();
+            assert!(float::equal(px, x as f64 + 0.5), "{px} for {x}");
+            assert!(float::equal(py, y as f64 + 0.5), "{py} for {y}");
+        }
+
+        #[test]
+        fn centre() {
+            let c = Camera::new(201, 101, FRAC_PI_2);
+            lands_on(&c, 100, 50, 1.0);
+            lands_on(&c, 100, 50, 10.0);
+        }
+
+        #[test]
+        fn round_trip() {
+            let transformed = Camera::new_with_transform(
+                201,
+                101,
+                FRAC_PI_2,
+                Matrix::translation(0.0, -2.0, 5.0).rotate_y(FRAC_PI_4),
+            );
+            let looking = Camera::new_with_transform(
+                64,
+                100,
+                FRAC_PI_3,
+                Matrix::view_transform(pointi(1, 3, -6), pointi(0, 1, 0), vectori(0, 1, 0)),
+            );
+            for c in [transformed, looking] {
+                for (x, y) in [(0, 0), (63, 99), (10, 20), (31, 50), (50, 7)] {
+                    lands_on(&c, x, y, 0.5);
+                    lands_on(&c, x, y, 20.0);
+                }
+            }
+        }
+
+        #[test]
+        fn behind_or_outside() {
+            let c = Camera::new(201, 101, FRAC_PI_2);
+            let ray = c.ray_for_pixel(20, 30);
+            assert!(c.project(ray.position(-3.0).tuple()).is_none());
+            assert!(c.project(point(0.0, 0.0, 0.0)).is_none());
+            assert!(c.project(point(0.0, 0.0, 10.0)).is_none());
+
+            // In front, but well off to the side
+            assert!(c.project(point(50.0, 0.0, -1.0)).is_none());
+            assert!(c.project(point(0.0, -5.0, -1.0)).is_none());
+        }
+    }
+
     mod rays {
         use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2};
 
@@ -225,6 +825,237 @@ mod test {
         assert_eq!(image[(5, 5)], Colour::new(0.38066, 0.47583, 0.2855))
     }
 
+    #[test]
+    fn try_render() {
+        let c = Camera::new_with_transform(
+            11,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        let mut w = World::default();
+        let image = c.try_render(&w).ok().unwrap();
+        assert!(image.iter().zip(c.render(&w).iter()).all(|(a, b)| a == b));
+
+        w.add_object(Box::new(Sphere::new_with_transform(Matrix::scaling(
+            1.0, 0.0, 1.0,
+        ))));
+        assert_eq!(
+            c.try_render(&w).err().unwrap().to_string(),
+            "object 2 has a non-invertible transform"
+        );
+    }
+
+    #[test]
+    fn render_striped() {
+        let mut w: World = Default::default();
+        let mut material = w.object(0).unwrap().material().clone();
+        material.pattern = Box::new(StripePattern::new(
+            material.colour().unwrap(),
+            Colour::BLACK,
+        ));
+        w.object_mut(0).unwrap().set_material(material);
+        let c = Camera::new_with_transform(
+            11,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+
+        let image = c.render(&w);
+
+        // The middle is on the x=0 boundary, so picks the first colour
+        assert_eq!(image[(5, 5)], Colour::new(0.38066, 0.47583, 0.2855));
+        // Either side of it is on a different stripe, the black one only gets specular
+        assert_ne!(image[(4, 5)], image[(6, 5)]);
+        assert_eq!(image[(4, 5)].red, image[(4, 5)].blue);
+    }
+
+    #[test]
+    fn render_presets() {
+        let w = World::default().with_objects(vec![
+            Box::new(Plane::new(
+                Matrix::translationi(0, -1, 0),
+                Material::matte(Colour::new(0.5, 0.5, 0.5)),
+            )),
+            Box::new(Sphere::new(
+                Matrix::translation(-3.0, 0.0, 0.0),
+                Material::glass(),
+            )),
+            Box::new(Sphere::new(
+                Matrix::translation(-1.0, 0.0, 0.0),
+                Material::mirror(),
+            )),
+            Box::new(Sphere::new(
+                Matrix::translation(1.0, 0.0, 0.0),
+                Material::matte(Colour::new(0.2, 0.4, 0.9)),
+            )),
+            Box::new(Sphere::new(
+                Matrix::translation(3.0, 0.0, 0.0),
+                Material::metal(Colour::new(1.0, 0.8, 0.3), 0.25),
+            )),
+        ]);
+        let c = Camera::new_with_transform(
+            16,
+            3,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+
+        let image = c.render(&w);
+
+        // Straight through the middle of each sphere. Glass and the mirror are both mostly
+        // showing the empty sky behind the camera, which is the point, they should stay dark.
+        assert_eq!(image[(3, 1)], Colour::new(0.01548, 0.01548, 0.01548));
+        assert_eq!(image[(6, 1)], Colour::BLACK);
+        assert_eq!(image[(9, 1)], Colour::new(0.13886, 0.27772, 0.62487));
+        assert_eq!(image[(12, 1)], Colour::new(0.34369, 0.27495, 0.10311));
+    }
+
+    fn toon_sphere(outline: Option<Colour>) -> Canvas {
+        let w = World::new(
+            vec![Box::new(Sphere::new_with_material(Material {
+                ambient: 0.0,
+                specular: 0.0,
+                shading: ShadingMode::Toon { bands: 2, outline },
+                ..Default::default()
+            }))],
+            vec![PointLight::new_boxed(Colour::WHITE, pointi(-10, 10, -10))],
+        );
+        let c = Camera::new_with_transform(
+            41,
+            41,
+            FRAC_PI_4,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+
+        c.render(&w)
+    }
+
+    #[test]
+    fn toon_bands() {
+        let image = toon_sphere(None);
+
+        let mut levels: Vec<_> = image
+            .iter()
+            .filter(|&&c| c != Colour::BLACK)
+            .map(|c| c.red)
+            .collect();
+        levels.sort_by(f64::total_cmp);
+        levels.dedup_by(|a, b| float::equal(*a, *b));
+
+        assert_eq!(levels, vec![0.45, 0.9]);
+    }
+
+    #[test]
+    fn toon_outline_only_at_silhouette() {
+        let red = Colour::newi(1, 0, 0);
+        let outlined = toon_sphere(Some(red));
+        let plain = toon_sphere(None);
+        let background = |x: usize, y: usize| outlined[(x, y)] == Colour::BLACK;
+
+        let mut count = 0;
+        for y in 0..outlined.height {
+            for x in 0..outlined.width {
+                if outlined[(x, y)] != red {
+                    assert_eq!(outlined[(x, y)], plain[(x, y)]);
+                    continue;
+                }
+
+                count += 1;
+                let near_edge = (x.saturating_sub(1)..=(x + 1).min(outlined.width - 1)).any(|nx| {
+                    (y.saturating_sub(1)..=(y + 1).min(outlined.height - 1))
+                        .any(|ny| background(nx, ny))
+                });
+                assert!(near_edge, "outline away from the edge at {x},{y}");
+            }
+        }
+
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn bump_mapped_silhouette() {
+        let render = |normal_perturbation| {
+            let mut w = World::default();
+            w.remove_object(1);
+            let material = Material {
+                normal_perturbation,
+                ..w.object(0).unwrap().material().clone()
+            };
+            w.object_mut(0).unwrap().set_material(material);
+            let c = Camera::new_with_transform(
+                21,
+                21,
+                FRAC_PI_4,
+                Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+            );
+            c.render(&w)
+        };
+
+        let flat = render(None);
+        let bumped = render(Some(Box::new(StripePattern::new(
+            Colour::new(0.4, 0.4, 0.0),
+            Colour::BLACK,
+        )) as Box<dyn Pattern>));
+
+        let mut differs = false;
+        for (f, b) in flat.iter().zip(bumped.iter()) {
+            assert_eq!(*f == Colour::BLACK, *b == Colour::BLACK);
+            differs |= f != b;
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn bounce_depth_aov() {
+        let mut w: World = Default::default();
+        w.add_object(Box::new(Plane::new(
+            Matrix::translationi(0, -1, 0),
+            Material {
+                reflective: 1.0,
+                ..Default::default()
+            },
+        )));
+        let c = Camera::new_with_transform(
+            11,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+
+        let image = c.render_bounce_depth(&w);
+
+        // Down the middle column: sky, sphere, floor reflecting the sphere, floor reflecting sky
+        assert_eq!(image[(5, 0)], Colour::BLACK);
+        assert_eq!(image[(5, 5)], Colour::BLACK);
+        assert_eq!(image[(5, 7)], Colour::WHITE);
+        assert_eq!(image[(5, 10)], Colour::BLACK);
+    }
+
+    #[test]
+    fn normal_and_depth_aovs() {
+        let w: World = Default::default();
+        let c = Camera::new_with_transform(
+            11,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+
+        // Straight at the front of the sphere, and past it in the corner
+        let normals = c.render_normals(&w);
+        assert_eq!(normals[(5, 5)], Colour::new(0.0, 0.0, -1.0));
+        assert_eq!(normals[(0, 0)], Colour::BLACK);
+        let depth = c.render_depth(&w);
+        assert_eq!(depth[(5, 5)], Colour::new(4.0, 4.0, 4.0));
+        assert_eq!(depth[(0, 0)], Colour::BLACK);
+
+        // Higher up the sphere faces up a bit, and is further away
+        assert!(normals[(5, 4)].green > 0.0);
+        assert!(depth[(5, 4)].red > 4.0);
+    }
+
     #[test]
     fn render_parallel() {
         let w: World = Default::default();
@@ -235,8 +1066,456 @@ mod test {
             Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
         );
 
-        let image = c.render_parallel(w);
+        let image = c.render_parallel(&w, None);
 
         assert_eq!(image[(5, 5)], Colour::new(0.38066, 0.47583, 0.2855))
     }
+
+    #[test]
+    fn render_parallel_small() {
+        let w = World::default();
+        for (hsize, vsize) in [(1, 1), (2, 2), (3, 5), (100, 1)] {
+            let c = Camera::new_with_transform(
+                hsize,
+                vsize,
+                FRAC_PI_2,
+                Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+            );
+            let parallel = c.render_parallel(&w, None);
+            let serial = c.render(&w);
+            assert_eq!((parallel.width, parallel.height), (hsize, vsize));
+            assert!(
+                parallel.iter().zip(serial.iter()).all(|(a, b)| a == b),
+                "{hsize}x{vsize}"
+            );
+        }
+    }
+
+    #[test]
+    fn render_parallel_reuses_the_world() {
+        // One world, a camera moving through it, as an animation would
+        let w = World::default();
+        for z in [-5, -4] {
+            let c = Camera::new_with_transform(
+                9,
+                7,
+                FRAC_PI_2,
+                Matrix::view_transform(pointi(0, 0, z), pointi(0, 0, 0), vectori(0, 1, 0)),
+            );
+            let first = c.render_parallel(&w, None);
+            let second = c.render_parallel(&w, None);
+            let serial = c.render(&w);
+            assert!(first.iter().eq(second.iter()), "{z}");
+            assert!(first.iter().eq(serial.iter()), "{z}");
+        }
+        assert_eq!(w.objects().len(), 2);
+    }
+
+    fn row_camera() -> Camera {
+        Camera::new_with_transform(
+            7,
+            5,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        )
+    }
+
+    #[test]
+    fn render_matches_pixel_by_pixel() {
+        let w = World::default();
+        let c = row_camera();
+        let image = c.render(&w);
+        // What render used to do, a column at a time
+        for x in 0..c.hsize {
+            for y in 0..c.vsize {
+                assert_eq!(image[(x, y)], w.colour_at(c.ray_for_pixel(x, y)), "{x} {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn render_row() {
+        let w = World::default();
+        let c = row_camera();
+        let image = c.render(&w);
+        let rows: Vec<_> = image.rows().collect();
+
+        let mut out = vec![Colour::BLACK; c.hsize];
+        for y in [0, 2, 4] {
+            c.render_row(&w, y, &mut out).unwrap();
+            assert_eq!(out, rows[y], "{y}");
+        }
+
+        for (y, row) in c.render_rows(&w) {
+            assert_eq!(row, rows[y], "{y}");
+        }
+        assert_eq!(c.render_rows(&w).count(), c.vsize);
+    }
+
+    #[test]
+    fn render_row_rejects_bad_buffers() {
+        let w = World::default();
+        let c = row_camera();
+        for len in [0, 6, 8] {
+            assert_eq!(
+                c.render_row(&w, 0, &mut vec![Colour::BLACK; len]),
+                Err(RayTracerError::RowLength {
+                    expected: 7,
+                    got: len
+                })
+            );
+        }
+        assert_eq!(
+            c.render_row(&w, 5, &mut [Colour::BLACK; 7]),
+            Err(RayTracerError::RowOutOfRange { row: 5, height: 5 })
+        );
+    }
+
+    mod surfaces {
+        use crate::canvas::CanvasU8;
+
+        use super::*;
+
+        #[test]
+        fn canvas_u8_matches_quantized_render() {
+            let w = World::default();
+            let c = row_camera();
+            let full = c.render(&w);
+
+            for srgb in [false, true] {
+                let mut serial = if srgb {
+                    CanvasU8::new_srgb(7, 5)
+                } else {
+                    CanvasU8::new(7, 5)
+                };
+                let mut parallel = serial.clone();
+                c.render_to(&w, &mut serial).unwrap();
+                c.render_parallel_to(&w, &mut parallel, None).unwrap();
+
+                assert_eq!(serial.into_ppm(), full.into_ppm(srgb));
+                assert_eq!(parallel.into_ppm(), full.into_ppm(srgb));
+                assert_eq!(serial.into_png(), full.into_png(srgb));
+            }
+        }
+
+        #[test]
+        fn canvas_matches_render() {
+            let w = World::default();
+            let c = row_camera();
+            let full = c.render(&w);
+
+            let mut serial = Canvas::new(7, 5);
+            c.render_to(&w, &mut serial).unwrap();
+            assert!(serial.iter().eq(full.iter()));
+
+            let mut parallel = Canvas::new(7, 5);
+            c.render_parallel_to(&w, &mut parallel, None).unwrap();
+            assert!(parallel.iter().eq(full.iter()));
+        }
+
+        #[test]
+        fn wrong_size() {
+            let w = World::default();
+            let c = row_camera();
+            let expected = Err(RayTracerError::SurfaceSize {
+                expected: (7, 5),
+                got: (5, 7),
+            });
+            assert_eq!(c.render_to(&w, &mut CanvasU8::new(5, 7)), expected);
+            assert_eq!(
+                c.render_parallel_to(&w, &mut Canvas::new(5, 7), None),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn degenerate_objects_are_left_out() {
+        use crate::{
+            shape::{group::Group, ShapeBase},
+            world::ValidationIssue,
+        };
+
+        let c = Camera::new_with_transform(
+            11,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        let expected = c.render(&World::default());
+
+        // Both squashed flat right in front of the camera, they'd hide everything if they
+        // could be hit at all
+        let mut w = World::default();
+        w.add_object(Box::new(Sphere::new_with_transform(
+            Matrix::scaling(2.0, 2.0, 0.0).translate(0.0, 0.0, -3.0),
+        )));
+        let mut g = Group::new(Matrix::scaling(0.0, 0.0, 0.0));
+        g.add_child(Box::new(Sphere::new_with_transform(Matrix::translationi(
+            0, 0, -3,
+        ))));
+        g.set_transform(Matrix::scalingi(2, 2, 2));
+        w.add_object(Box::new(g));
+        // The group's own transform is fine now, but the child it flattened still isn't
+        assert_eq!(
+            w.validate(),
+            Err(vec![
+                ValidationIssue::NonInvertibleTransform { index: 2 },
+                ValidationIssue::NonInvertibleTransform { index: 3 },
+            ])
+        );
+
+        for image in [c.render(&w), c.render_parallel(&w, None)] {
+            assert!(image.iter().zip(expected.iter()).all(|(a, b)| a == b));
+        }
+    }
+
+    #[test]
+    fn chunk_sizes() {
+        use super::chunk_size;
+
+        for workers in [0, 1, 3, 16] {
+            for pixels in [0, 1, 4, 15, 16, 17, 500] {
+                let size = chunk_size(pixels, workers);
+                assert!(size >= 1);
+                // Every pixel's in a chunk, and there aren't many more chunks than asked for
+                let chunks = pixels.div_ceil(size);
+                assert!(chunks * size >= pixels);
+                assert!(chunks <= workers.max(1) * 4, "{pixels} {workers}");
+            }
+        }
+        // Just over a chunk each doesn't leave one with nearly everything
+        assert_eq!(chunk_size(17, 4), 2);
+    }
+
+    #[test]
+    fn zero_sized() {
+        for (hsize, vsize) in [(0, 0), (0, 5), (5, 0)] {
+            let err = Camera::try_new_with_transform(hsize, vsize, FRAC_PI_2, Matrix::default())
+                .err()
+                .unwrap();
+            assert_eq!(
+                err,
+                RayTracerError::InvalidCamera {
+                    reason: "width and height must be non-zero"
+                }
+            );
+            assert_eq!(
+                err.to_string(),
+                "invalid camera: width and height must be non-zero"
+            );
+        }
+    }
+
+    mod cancellation {
+        use std::{
+            f64::consts::FRAC_PI_2,
+            thread,
+            time::{Duration, Instant},
+        };
+
+        use crate::{
+            camera::Camera,
+            cancel::{CancellationToken, RenderResult, UNRENDERED},
+            math::{
+                matrix::Matrix,
+                tuple::{pointi, vectori},
+            },
+            progress::ProgressSink,
+            world::World,
+        };
+
+        fn camera(hsize: usize, vsize: usize) -> Camera {
+            Camera::new_with_transform(
+                hsize,
+                vsize,
+                FRAC_PI_2,
+                Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+            )
+        }
+
+        #[test]
+        fn cancelled_before_starting() {
+            let token = CancellationToken::new();
+            token.cancel();
+
+            let start = Instant::now();
+            let result =
+                camera(200, 100).render_parallel_cancellable(&World::default(), &token, None);
+            assert!(start.elapsed() < Duration::from_secs(1));
+
+            assert!(matches!(result, RenderResult::Cancelled(_)));
+            assert!(result.canvas().iter().all(|&c| c == UNRENDERED));
+        }
+
+        #[test]
+        fn cancelled_part_way() {
+            /// Cancels the render once `after` pixels are in, from the render's own progress
+            /// reports rather than a timer
+            struct CancelAfter {
+                token: CancellationToken,
+                after: usize,
+            }
+
+            impl ProgressSink for CancelAfter {
+                fn on_progress(&self, done: usize, _total: usize) {
+                    if done >= self.after {
+                        self.token.cancel();
+                    }
+                }
+
+                fn on_complete(&self) {}
+            }
+
+            // Big enough that the workers are nowhere near done when the 100th pixel comes in
+            let c = camera(1000, 500);
+            let token = CancellationToken::new();
+            let sink = CancelAfter {
+                token: token.clone(),
+                after: 100,
+            };
+
+            let result = c.render_parallel_cancellable(&World::default(), &token, Some(&sink));
+
+            assert!(!result.is_completed());
+            let canvas = result.into_canvas();
+            let rendered = canvas.iter().filter(|&&c| c != UNRENDERED).count();
+            assert!(rendered >= 100, "{rendered}");
+            assert!(rendered < 1000 * 500, "{rendered}");
+        }
+
+        #[test]
+        fn not_cancelled() {
+            let c = camera(21, 11);
+            let token = CancellationToken::new();
+            // Letting go of a clone mid-render changes nothing
+            let spare = token.clone();
+            thread::spawn(move || drop(spare));
+
+            let result = c.render_parallel_cancellable(&World::default(), &token, None);
+            assert!(result.is_completed());
+
+            let expected = c.render(&World::default());
+            let canvas = result.canvas();
+            assert!(canvas.iter().zip(expected.iter()).all(|(a, b)| a == b));
+        }
+    }
+
+    mod progress {
+        use std::{
+            cell::{Cell, RefCell},
+            env,
+            f64::consts::FRAC_PI_2,
+            process::Command,
+        };
+
+        use crate::{
+            camera::Camera,
+            math::{
+                matrix::Matrix,
+                tuple::{pointi, vectori},
+            },
+            progress::{ChannelProgress, NoProgress, Progress, ProgressSink, StdoutProgress},
+            world::World,
+        };
+
+        fn camera(hsize: usize, vsize: usize) -> Camera {
+            Camera::new_with_transform(
+                hsize,
+                vsize,
+                FRAC_PI_2,
+                Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+            )
+        }
+
+        /// Not Sync, which it doesn't need to be
+        #[derive(Default)]
+        struct Counting {
+            seen: RefCell<Vec<(usize, usize)>>,
+            completed: Cell<usize>,
+        }
+
+        impl ProgressSink for Counting {
+            fn on_progress(&self, done: usize, total: usize) {
+                self.seen.borrow_mut().push((done, total));
+            }
+
+            fn on_complete(&self) {
+                self.completed.set(self.completed.get() + 1);
+            }
+        }
+
+        #[test]
+        fn counting_sink() {
+            let sink = Counting::default();
+            camera(21, 11).render_parallel(&World::default(), Some(&sink));
+
+            let seen = sink.seen.into_inner();
+            assert!(seen.windows(2).all(|w| w[0].0 <= w[1].0));
+            assert!(seen.iter().all(|&(_, total)| total == 21 * 11));
+            assert_eq!(seen.last(), Some(&(21 * 11, 21 * 11)));
+            assert_eq!(sink.completed.get(), 1);
+        }
+
+        #[test]
+        fn channel_sink() {
+            let (sink, rx) = ChannelProgress::new();
+            camera(20, 10).render_parallel(&World::default(), Some(&sink));
+            drop(sink);
+
+            let messages: Vec<_> = rx.iter().collect();
+            // One for each percent from 0 to 100, then done
+            assert_eq!(messages.len(), 102);
+            assert_eq!(
+                messages[100],
+                Progress::Update {
+                    done: 200,
+                    total: 200
+                }
+            );
+            assert_eq!(messages[101], Progress::Complete);
+        }
+
+        /// Renders 2000 pixels between two markers on stdout, with the sink named by
+        /// PROGRESS_CHILD. Only does anything when run by `printed` below.
+        #[test]
+        fn child() {
+            let Some(sink) = env::var_os("PROGRESS_CHILD") else {
+                return;
+            };
+            let sink: &dyn ProgressSink = if sink == "stdout" {
+                &StdoutProgress
+            } else {
+                &NoProgress
+            };
+            println!("[start]");
+            camera(50, 40).render_parallel(&World::default(), Some(sink));
+            println!("[end]");
+        }
+
+        /// What the child test above printed between its markers, run with output uncaptured
+        fn printed(sink: &str) -> String {
+            let output = Command::new(env::current_exe().unwrap())
+                .args([
+                    "--exact",
+                    "camera::test::progress::child",
+                    "--nocapture",
+                    "--test-threads=1",
+                ])
+                .env("PROGRESS_CHILD", sink)
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let (_, rest) = stdout.split_once("[start]").expect("child didn't run");
+            let (between, _) = rest.split_once("[end]").unwrap();
+            between.trim().to_owned()
+        }
+
+        #[test]
+        fn no_progress_is_quiet() {
+            assert_eq!(printed("none"), "");
+            // Which only means something if the other one isn't
+            assert_eq!(printed("stdout"), "1000 / 2000\r2000 / 2000");
+        }
+    }
 }
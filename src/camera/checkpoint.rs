@@ -0,0 +1,600 @@
+//! Renders that save their progress as they go, so one that dies part way through can pick up
+//! where it left off instead of starting over.
+//!
+//! A checkpoint file is, little endian throughout:
+//!
+//! ```text
+//! "RTCP" 1            magic and version
+//! u64                 hash of the camera and world, see scene_hash
+//! u32 u32             width, height
+//! [u8]                a bit per pixel, set once it's rendered, rows top to bottom
+//! [f32; 3] ...        the colour of each rendered pixel, in the same order
+//! u32                 crc32 of everything before it
+//! ```
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    cancel::{CancellationToken, RenderResult, UNRENDERED},
+    canvas::{Canvas, PixelSurface},
+    colour::Colour,
+    watch::write_atomic,
+    world::World,
+};
+
+use super::Camera;
+
+const MAGIC: &[u8; 4] = b"RTCP";
+const VERSION: u8 = 1;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// The checkpoint is from a different scene, or camera, than the one being rendered
+    SceneChanged {
+        path: PathBuf,
+        expected: u64,
+        found: u64,
+    },
+    /// The checkpoint isn't one, or got damaged
+    Corrupt {
+        path: PathBuf,
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "checkpoint: {e}"),
+            CheckpointError::SceneChanged {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "checkpoint {} is for a different scene or camera (hash {found:016x}, this \
+                 one is {expected:016x}), delete it to start the render over",
+                path.display()
+            ),
+            CheckpointError::Corrupt { path, reason } => write!(
+                f,
+                "checkpoint {} is corrupt ({reason}), delete it to start the render over",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CheckpointError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(value: io::Error) -> Self {
+        CheckpointError::Io(value)
+    }
+}
+
+impl Camera {
+    /// render_parallel, but saving what's been rendered to `checkpoint` every `interval`. If
+    /// `checkpoint` is already there (and is for this camera and world) only what it's missing
+    /// gets rendered. It's deleted once the render finishes.
+    pub fn render_checkpointed(
+        &self,
+        world: &World,
+        checkpoint: impl AsRef<Path>,
+        interval: Duration,
+    ) -> Result<Canvas, CheckpointError> {
+        self.render_checkpointed_cancellable(world, checkpoint, interval, &CancellationToken::new())
+            .map(RenderResult::into_canvas)
+    }
+
+    /// render_checkpointed that stops when `token` is cancelled, saving a checkpoint of how
+    /// far it got on the way out.
+    pub fn render_checkpointed_cancellable(
+        &self,
+        world: &World,
+        checkpoint: impl AsRef<Path>,
+        interval: Duration,
+        token: &CancellationToken,
+    ) -> Result<RenderResult, CheckpointError> {
+        let path = checkpoint.as_ref();
+        let hash = scene_hash(self, world);
+        let mut progress = match fs::read(path) {
+            Ok(bytes) => Checkpoint::decode(&bytes, path, self, hash)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Checkpoint::new(self, hash),
+            Err(e) => return Err(e.into()),
+        };
+
+        // A copy, as the workers can't look at `progress` while it's being filled in
+        let todo: Vec<bool> = progress.done.iter().map(|done| !done).collect();
+        let mut saving = Saving {
+            checkpoint: &mut progress,
+            path,
+            interval,
+            saved: Instant::now(),
+            error: None,
+        };
+        self.render_parallel_onto(world, &mut saving, token, None, |x, y| {
+            todo[y * self.hsize + x]
+        });
+        if let Some(e) = saving.error {
+            return Err(e.into());
+        }
+
+        if progress.is_complete() {
+            // Gone already is fine, it's not wanted either way
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            }
+            Ok(RenderResult::Completed(progress.canvas))
+        } else {
+            progress.save(path)?;
+            Ok(RenderResult::Cancelled(progress.canvas))
+        }
+    }
+}
+
+/// Fills in a Checkpoint as the pixels come in, saving it every `interval`
+struct Saving<'a> {
+    checkpoint: &'a mut Checkpoint,
+    path: &'a Path,
+    interval: Duration,
+    saved: Instant,
+    /// The first save that failed, after which there are no more tries
+    error: Option<io::Error>,
+}
+
+impl PixelSurface for Saving<'_> {
+    fn width(&self) -> usize {
+        self.checkpoint.canvas.width
+    }
+
+    fn height(&self) -> usize {
+        self.checkpoint.canvas.height
+    }
+
+    fn put(&mut self, x: usize, y: usize, colour: Colour) -> bool {
+        self.checkpoint.set(x, y, colour);
+        if self.error.is_none() && self.saved.elapsed() >= self.interval {
+            match self.checkpoint.save(self.path) {
+                Ok(()) => self.saved = Instant::now(),
+                Err(e) => self.error = Some(e),
+            }
+        }
+        true
+    }
+}
+
+/// Identifies what's being rendered, so a checkpoint isn't resumed into a different picture.
+/// Covers the camera, the world's medium and bias, and everything about every object and light
+/// that Debug shows, bar the shapes' ids (which are new every run).
+pub fn scene_hash(camera: &Camera, world: &World) -> u64 {
+    let mut hash = Fnv::default();
+    hash.write(
+        format!(
+            "{} {} {:?} {:?}",
+            camera.hsize,
+            camera.vsize,
+            camera.fov.to_bits(),
+            camera.transform
+        )
+        .as_bytes(),
+    );
+    for object in world.objects() {
+        hash.write(without_ids(&format!("{object:?}")).as_bytes());
+    }
+    for light in world.lights() {
+        hash.write(format!("{light:?}").as_bytes());
+    }
+    hash.write(format!("{:?}", world.medium()).as_bytes());
+    hash.write(&world.bias().to_bits().to_le_bytes());
+    hash.0
+}
+
+/// Debug output with the `_id: ShapeId(n)` fields taken out. Ids count up through the whole
+/// process, so building the same scene twice gives it different ones.
+fn without_ids(debug: &str) -> String {
+    const FIELD: &str = "_id: ";
+
+    let mut out = String::with_capacity(debug.len());
+    let mut rest = debug;
+    while let Some(start) = rest.find(FIELD) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + FIELD.len()..];
+        // Up to the id's closing bracket, and the ", " after it
+        let end = rest.find(')').map_or(rest.len(), |i| i + 1);
+        rest = rest[end..].strip_prefix(", ").unwrap_or(&rest[end..]);
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 64 bit FNV-1a, which is stable between runs and builds (unlike std's DefaultHasher)
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// A render in progress
+struct Checkpoint {
+    hash: u64,
+    canvas: Canvas,
+    done: Vec<bool>,
+}
+
+impl Checkpoint {
+    fn new(camera: &Camera, hash: u64) -> Self {
+        Self {
+            hash,
+            canvas: Canvas::new_with_colour(camera.hsize, camera.vsize, UNRENDERED),
+            done: vec![false; camera.hsize * camera.vsize],
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.done.iter().all(|d| *d)
+    }
+
+    fn set(&mut self, x: usize, y: usize, colour: Colour) {
+        self.canvas[(x, y)] = colour;
+        self.done[y * self.canvas.width + x] = true;
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        out.extend(self.hash.to_le_bytes());
+        out.extend((self.canvas.width as u32).to_le_bytes());
+        out.extend((self.canvas.height as u32).to_le_bytes());
+        out.extend(self.done.chunks(8).map(|bits| {
+            bits.iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, done)| byte | (*done as u8) << i)
+        }));
+        for (colour, _) in self.canvas.iter().zip(&self.done).filter(|(_, d)| **d) {
+            for channel in [colour.red, colour.green, colour.blue] {
+                out.extend((channel as f32).to_le_bytes());
+            }
+        }
+        let crc = crate::canvas::crc32(&out);
+        out.extend(crc.to_le_bytes());
+        out
+    }
+
+    /// A checkpoint for rendering `camera`'s image
+    fn decode(
+        bytes: &[u8],
+        path: &Path,
+        camera: &Camera,
+        hash: u64,
+    ) -> Result<Self, CheckpointError> {
+        let corrupt = |reason| CheckpointError::Corrupt {
+            path: path.to_owned(),
+            reason,
+        };
+        if !bytes.starts_with(MAGIC) {
+            return Err(corrupt("not a checkpoint file"));
+        }
+        let (body, crc) = bytes.split_last_chunk::<4>().ok_or(corrupt("truncated"))?;
+        if crate::canvas::crc32(body) != u32::from_le_bytes(*crc) {
+            return Err(corrupt("checksum doesn't match"));
+        }
+
+        let mut reader = Reader(&body[MAGIC.len()..]);
+        if reader.take::<1>() != Some([VERSION]) {
+            return Err(corrupt("unknown version"));
+        }
+        let found = u64::from_le_bytes(reader.take().ok_or(corrupt("truncated"))?);
+        if found != hash {
+            return Err(CheckpointError::SceneChanged {
+                path: path.to_owned(),
+                expected: hash,
+                found,
+            });
+        }
+        let mut dimension = || {
+            reader
+                .take()
+                .map(|b| u32::from_le_bytes(b) as usize)
+                .ok_or(corrupt("truncated"))
+        };
+        let (width, height) = (dimension()?, dimension()?);
+
+        // A different size would have a different hash, but the pixels are looked up by the
+        // camera's size, so don't count on that
+        if (width, height) != (camera.hsize, camera.vsize) {
+            return Err(corrupt("wrong size"));
+        }
+        let pixels = width * height;
+        let bitmap = reader
+            .0
+            .get(..pixels.div_ceil(8))
+            .ok_or(corrupt("truncated"))?;
+        let done: Vec<bool> = (0..pixels)
+            .map(|i| bitmap[i / 8] & (1 << (i % 8)) != 0)
+            .collect();
+        reader.0 = &reader.0[bitmap.len()..];
+
+        let mut canvas = Canvas::new_with_colour(width, height, UNRENDERED);
+        for (colour, _) in canvas.iter_mut().zip(&done).filter(|(_, d)| **d) {
+            let mut channel = || {
+                reader
+                    .take()
+                    .map(|b| f32::from_le_bytes(b) as f64)
+                    .ok_or(corrupt("truncated"))
+            };
+            *colour = Colour::new(channel()?, channel()?, channel()?);
+        }
+        if !reader.0.is_empty() {
+            return Err(corrupt("extra data at the end"));
+        }
+
+        Ok(Self { hash, canvas, done })
+    }
+
+    /// Into a temporary file first (see write_atomic), so dying part way through a save
+    /// doesn't lose the last one
+    fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomic(path, &self.encode())
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (first, rest) = self.0.split_first_chunk::<N>()?;
+        self.0 = rest;
+        Some(*first)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs, thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        camera::Camera,
+        cancel::{CancellationToken, RenderResult, UNRENDERED},
+        colour::Colour,
+        lights::PointLight,
+        math::{
+            matrix::Matrix,
+            tuple::{pointi, vectori},
+        },
+        medium::Medium,
+        test_util::scratch_dir,
+        world::World,
+    };
+
+    use super::{scene_hash, without_ids, Checkpoint, CheckpointError};
+
+    fn camera(width: usize, height: usize) -> Camera {
+        Camera::new_with_transform(
+            width,
+            height,
+            core::f64::consts::FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        )
+    }
+
+    #[test]
+    fn uninterrupted() {
+        let dir = scratch_dir("checkpoint-uninterrupted");
+        let path = dir.join("render.checkpoint");
+        let (camera, world) = (camera(11, 11), World::default());
+
+        let canvas = camera
+            .render_checkpointed(&world, &path, Duration::ZERO)
+            .unwrap();
+        assert_eq!(canvas[(5, 5)], Colour::new(0.38066, 0.47583, 0.2855));
+        assert!(!path.exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cancel_and_resume() {
+        let dir = scratch_dir("checkpoint-resume");
+        let path = dir.join("render.checkpoint");
+        let (camera, world) = (camera(120, 80), World::default());
+        let token = CancellationToken::new();
+
+        // "Kill" it as soon as the first checkpoint's written
+        let cancelled = thread::scope(|s| {
+            let watcher = s.spawn(|| {
+                let start = Instant::now();
+                while !path.exists() && start.elapsed() < Duration::from_secs(60) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                token.cancel();
+            });
+            let result = camera
+                .render_checkpointed_cancellable(&world, &path, Duration::ZERO, &token)
+                .unwrap();
+            watcher.join().unwrap();
+            result
+        });
+        let RenderResult::Cancelled(partial) = cancelled else {
+            panic!("finished before it could be cancelled");
+        };
+        assert!(path.exists());
+        assert!(partial.iter().any(|c| *c == UNRENDERED));
+
+        // Saved with what was there, no more
+        let saved = Checkpoint::decode(
+            &fs::read(&path).unwrap(),
+            &path,
+            &camera,
+            scene_hash(&camera, &world),
+        )
+        .unwrap();
+        assert!(saved.canvas.iter().eq(partial.iter()));
+
+        let resumed = camera
+            .render_checkpointed(&world, &path, Duration::from_secs(3600))
+            .unwrap();
+        assert!(!path.exists());
+        assert!(resumed.iter().eq(camera.render(&world).iter()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resuming_only_renders_the_rest() {
+        let dir = scratch_dir("checkpoint-rest");
+        let path = dir.join("render.checkpoint");
+        let (camera, world) = (camera(10, 10), World::default());
+
+        let mut half = Checkpoint::new(&camera, scene_hash(&camera, &world));
+        let full = camera.render(&world);
+        for y in 0..5 {
+            for x in 0..10 {
+                half.set(x, y, full[(x, y)]);
+            }
+        }
+        half.save(&path).unwrap();
+
+        let world = World::default();
+        let resumed = camera
+            .render_checkpointed(&world, &path, Duration::ZERO)
+            .unwrap();
+        assert!(resumed.iter().eq(full.iter()));
+        assert_eq!(world.stats.snapshot().primary_rays, 50);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn different_scene() {
+        let dir = scratch_dir("checkpoint-different");
+        let path = dir.join("render.checkpoint");
+        let world = World::default();
+        let token = CancellationToken::new();
+        token.cancel();
+        camera(10, 10)
+            .render_checkpointed_cancellable(&world, &path, Duration::ZERO, &token)
+            .unwrap();
+        let before = fs::read(&path).unwrap();
+
+        // Another camera, the same objects (with new ids) lit differently, seen through fog or with
+        // another bias
+        let moved_light = World::default().with_lights(vec![PointLight::new_boxed(
+            Colour::WHITE,
+            pointi(-10, 11, -10),
+        )]);
+        let fog = World::default().with_medium(Medium::new(0.1, 0.0));
+        let thicker = World::default().with_medium(Medium::new(0.2, 0.0));
+        let biased = World::default().with_bias(0.01);
+        assert_ne!(
+            scene_hash(&camera(10, 10), &fog),
+            scene_hash(&camera(10, 10), &thicker)
+        );
+        for (camera, world) in [
+            (camera(10, 9), World::default()),
+            (camera(10, 10), moved_light),
+            (camera(10, 10), fog),
+            (camera(10, 10), thicker),
+            (camera(10, 10), biased),
+        ] {
+            let err = camera
+                .render_checkpointed(&world, &path, Duration::ZERO)
+                .err()
+                .unwrap();
+            assert!(
+                matches!(&err, CheckpointError::SceneChanged { path: p, .. } if *p == path),
+                "{err}"
+            );
+            assert!(err.to_string().contains("different scene"));
+        }
+        // And it's left alone
+        assert_eq!(fs::read(&path).unwrap(), before);
+
+        // The same again is fine though, ids and all
+        camera(10, 10)
+            .render_checkpointed(&World::default(), &path, Duration::ZERO)
+            .unwrap();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt() {
+        let dir = scratch_dir("checkpoint-corrupt");
+        let path = dir.join("render.checkpoint");
+        let (camera, world) = (camera(10, 10), World::default());
+        let mut half = Checkpoint::new(&camera, scene_hash(&camera, &world));
+        half.set(3, 4, Colour::WHITE);
+        let good = half.encode();
+
+        let mut flipped = good.clone();
+        flipped[30] ^= 0x10;
+        // Gets past the checksum and the hash, but isn't for this size of image
+        let wrong_size =
+            Checkpoint::new(&self::camera(10, 9), scene_hash(&camera, &world)).encode();
+        for (bytes, reason) in [
+            (b"P6 10 10 255\n".to_vec(), "not a checkpoint file"),
+            (good[..3].to_vec(), "not a checkpoint file"),
+            (good[..good.len() - 1].to_vec(), "checksum doesn't match"),
+            (flipped, "checksum doesn't match"),
+            (wrong_size, "wrong size"),
+        ] {
+            fs::write(&path, &bytes).unwrap();
+            let err = camera
+                .render_checkpointed(&world, &path, Duration::ZERO)
+                .err()
+                .unwrap();
+            assert!(
+                matches!(err, CheckpointError::Corrupt { reason: r, .. } if r == reason),
+                "{reason}"
+            );
+            assert_eq!(fs::read(&path).unwrap(), bytes);
+        }
+
+        fs::write(&path, &good).unwrap();
+        let canvas = camera
+            .render_checkpointed(&world, &path, Duration::ZERO)
+            .unwrap();
+        assert_eq!(canvas[(3, 4)], Colour::WHITE);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ids_are_ignored() {
+        assert_eq!(
+            without_ids("Sphere { _id: ShapeId(1234), transform: 1 }"),
+            "Sphere { transform: 1 }"
+        );
+        assert_eq!(
+            scene_hash(&camera(5, 5), &World::default()),
+            scene_hash(&camera(5, 5), &World::default())
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use core::ops::Index;
+
+use crate::{
+    canvas::Canvas,
+    prelude::*,
+    world::{PassId, World},
+};
+
+use super::Camera;
+
+/// A canvas for each PassId, see Camera::render_passes
+#[derive(Clone)]
+pub struct RenderPasses {
+    passes: Vec<Canvas>,
+}
+
+impl RenderPasses {
+    pub fn get(&self, pass: PassId) -> &Canvas {
+        &self.passes[pass as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (PassId, &Canvas)> {
+        PassId::ALL.into_iter().zip(self.passes.iter())
+    }
+
+    /// Every pass added back together, which is the render
+    pub fn beauty(&self) -> Canvas {
+        let mut beauty = self.passes[0].clone();
+        for pass in &self.passes[1..] {
+            for (pixel, other) in beauty.iter_mut().zip(pass.iter()) {
+                *pixel += *other;
+            }
+        }
+        beauty
+    }
+}
+
+impl Index<PassId> for RenderPasses {
+    type Output = Canvas;
+
+    fn index(&self, pass: PassId) -> &Canvas {
+        self.get(pass)
+    }
+}
+
+impl Camera {
+    /// render, but with each pixel split up by where its light came from (see PassId), one
+    /// canvas per pass. Each ray is only traced once. The passes are raw, so the shadow pass
+    /// is negative, and they add up to what render gives.
+    pub fn render_passes(&self, world: &World) -> RenderPasses {
+        let mut passes = vec![Canvas::new(self.hsize, self.vsize); PassId::ALL.len()];
+        for x in 0..self.hsize {
+            for y in 0..self.vsize {
+                let colours = world.colour_at_passes(self.ray_for_pixel(x, y));
+                for (pass, colour) in colours.iter() {
+                    passes[pass as usize][(x, y)] = colour;
+                }
+            }
+        }
+        RenderPasses { passes }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_2;
+
+    use crate::{
+        camera::Camera,
+        colour::Colour,
+        intersection::IntersectVec,
+        materials::Material,
+        math::{
+            matrix::Matrix,
+            tuple::{pointi, vectori},
+        },
+        medium::Medium,
+        shape::plane::Plane,
+        world::{PassId, World},
+    };
+
+    fn camera() -> Camera {
+        Camera::new_with_transform(
+            21,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        )
+    }
+
+    #[test]
+    fn passes_add_up_to_render() {
+        let world = World::default();
+        let camera = camera();
+        let passes = camera.render_passes(&world);
+        assert_eq!(passes.iter().count(), PassId::ALL.len());
+        let render = camera.render(&world);
+        for ((x, y, beauty), pixel) in passes.beauty().enumerate_pixels().zip(render.iter()) {
+            assert_eq!(beauty, pixel, "{x} {y}");
+        }
+        // Something in the lit passes, and the background's in the corners
+        assert!(passes[PassId::Diffuse].iter().any(|c| c.red > 0.0));
+        assert!(passes[PassId::Specular].iter().any(|c| c.red > 0.0));
+        assert_eq!(passes[PassId::Background][(0, 0)], render[(0, 0)]);
+    }
+
+    /// The default world's sphere over a floor, to cast a shadow on
+    fn with_floor(floor: Material) -> World {
+        let mut world = World::default();
+        world.add_object(Box::new(Plane::new(Matrix::translationi(0, -1, 0), floor)));
+        world
+    }
+
+    #[test]
+    fn shadow_pass() {
+        let world = with_floor(Material::default());
+        let camera = Camera::new_with_transform(
+            21,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 2, -5), pointi(0, -1, 0), vectori(0, 1, 0)),
+        );
+        let passes = camera.render_passes(&world);
+        let light = world.lights().next().unwrap();
+        let mut shadowed = 0;
+        for (x, y, shadow) in passes[PassId::Shadow].enumerate_pixels() {
+            assert!(shadow.red <= 0.0 && shadow.green <= 0.0 && shadow.blue <= 0.0);
+            let ray = camera.ray_for_pixel(x, y);
+            let xs = world.intersect_world(ray);
+            let Some(hit) = xs.hit() else {
+                assert_eq!(shadow, &Colour::BLACK);
+                continue;
+            };
+            let comps = hit.prepare_computations(ray);
+            // A surface facing away from the light gets none of it anyway, so there's nothing
+            // for a shadow to take away
+            let faces_light =
+                (*light.position() - comps.over_point).dot(&comps.normal_vector) > 0.0;
+            let in_shadow = world.is_shadowed(comps.over_point) && faces_light;
+            assert_eq!(shadow.red < 0.0, in_shadow, "{x} {y}");
+            shadowed += in_shadow as usize;
+        }
+        assert!(shadowed > 0);
+    }
+
+    #[test]
+    fn unused_passes_are_black() {
+        // Nothing in the default world reflects, refracts, glows or has an outline, and
+        // there's no medium
+        let world = World::default();
+        let passes = camera().render_passes(&world);
+        for pass in [
+            PassId::Reflection,
+            PassId::Refraction,
+            PassId::Emission,
+            PassId::Volume,
+        ] {
+            assert!(passes[pass].iter().all(|c| *c == Colour::BLACK), "{pass:?}");
+        }
+
+        // Which changes once there's a mirror and a bit of fog
+        let world = with_floor(Material {
+            reflective: 0.5,
+            ..Default::default()
+        })
+        .with_medium(Medium::new(0.05, 0.0).with_steps(0.5, 50));
+        let camera = camera();
+        let passes = camera.render_passes(&world);
+        assert!(passes[PassId::Reflection].iter().any(|c| c.red > 0.0));
+        assert!(passes[PassId::Volume].iter().any(|c| c.red > 0.0));
+        let render = camera.render(&world);
+        for (beauty, pixel) in passes.beauty().iter().zip(render.iter()) {
+            assert_eq!(beauty, pixel);
+        }
+    }
+}
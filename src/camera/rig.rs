@@ -0,0 +1,182 @@
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+use crate::math::{float::EPSILON, matrix::Matrix, tuple::Tuple};
+
+use super::Camera;
+
+/// How close (in radians) orbit_vertical will go to straight above/below `to`. Any closer and
+/// up and the view direction line up, which flips the camera over.
+pub const POLE_MARGIN: f64 = 0.01;
+
+/// A camera described by where it is, what it looks at and which way is up, with helpers for
+/// moving it around. Build the actual Camera (or just its transform) once it's in place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraRig {
+    pub from: Tuple,
+    pub to: Tuple,
+    pub up: Tuple,
+}
+
+impl CameraRig {
+    pub fn new(from: Tuple, to: Tuple, up: Tuple) -> Self {
+        Self { from, to, up }
+    }
+
+    pub fn transform(&self) -> Matrix {
+        Matrix::view_transform(self.from, self.to, self.up)
+    }
+
+    pub fn camera(&self, hsize: usize, vsize: usize, fov: f64) -> Camera {
+        Camera::new_with_transform(hsize, vsize, fov, self.transform())
+    }
+
+    pub fn distance(&self) -> f64 {
+        (self.from - self.to).magnitude()
+    }
+
+    /// Swing `from` around `to`, about the up axis. Positive is anticlockwise looking down on it.
+    pub fn orbit_horizontal(&mut self, radians: f64) -> &mut Self {
+        let offset = self.from - self.to;
+        self.from = self.to + rotate_about(offset, self.up.normalize(), radians);
+        self
+    }
+
+    /// Swing `from` up (positive) or down over `to`. Stops POLE_MARGIN short of directly above
+    /// or below it.
+    pub fn orbit_vertical(&mut self, radians: f64) -> &mut Self {
+        let offset = self.from - self.to;
+        let up = self.up.normalize();
+        let axis = offset.cross(&up);
+        if axis.magnitude() == 0.0 {
+            // Already sat on a pole, there's no telling which way is "up" from here
+            return self;
+        }
+
+        // Angle down from the up pole, rotating about axis by +a moves towards it
+        let polar = (offset.normalize().dot(&up)).clamp(-1.0, 1.0).acos();
+        let target = (polar - radians).clamp(POLE_MARGIN, core::f64::consts::PI - POLE_MARGIN);
+        self.from = self.to + rotate_about(offset, axis.normalize(), polar - target);
+        self
+    }
+
+    /// Move `from` towards `to` (or away, for negative distances). Won't go past `to`.
+    pub fn dolly(&mut self, distance: f64) -> &mut Self {
+        let offset = self.from - self.to;
+        let length = (offset.magnitude() - distance).max(EPSILON);
+        self.from = self.to + offset.normalize() * length;
+        self
+    }
+
+    /// Slide both `from` and `to` along the camera's own x and y axes. Like the camera itself,
+    /// +x is to the left as seen in the image.
+    pub fn pan(&mut self, dx: f64, dy: f64) -> &mut Self {
+        let forward = (self.to - self.from).normalize();
+        let left = forward.cross(&self.up).normalize();
+        let true_up = left.cross(&forward);
+
+        let offset = left * dx + true_up * dy;
+        self.from = self.from + offset;
+        self.to = self.to + offset;
+        self
+    }
+}
+
+/// `v` rotated by `radians` about the unit vector `axis` (Rodrigues' formula)
+pub(crate) fn rotate_about(v: Tuple, axis: Tuple, radians: f64) -> Tuple {
+    let (sin, cos) = radians.sin_cos();
+    v * cos + axis.cross(&v) * sin + axis * (axis.dot(&v) * (1.0 - cos))
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, PI};
+
+    use crate::math::{
+        float,
+        tuple::{point, pointi, vectori},
+    };
+
+    use super::{CameraRig, POLE_MARGIN};
+
+    fn rig() -> CameraRig {
+        CameraRig::new(point(0.0, 1.5, -5.0), pointi(0, 1, 0), vectori(0, 1, 0))
+    }
+
+    #[test]
+    fn orbit_full_circle() {
+        let mut r = rig();
+        for _ in 0..36 {
+            r.orbit_horizontal(PI / 18.0);
+        }
+        assert_eq!(r, rig());
+
+        r.orbit_horizontal(FRAC_PI_2);
+        assert_eq!(r.from, point(-5.0, 1.5, 0.0));
+        assert!(float::equal(r.distance(), rig().distance()));
+    }
+
+    #[test]
+    fn orbit_vertical_clamps() {
+        let mut r = rig();
+        r.orbit_vertical(0.2);
+        assert!(r.from.y > rig().from.y);
+        assert!(float::equal(r.distance(), rig().distance()));
+        assert_eq!(r.from.x, 0.0);
+
+        r.orbit_vertical(10.0);
+        let polar = (r.from - r.to).normalize().dot(&r.up).acos();
+        assert!(float::equal(polar, POLE_MARGIN));
+        assert!(float::equal(r.distance(), rig().distance()));
+
+        // Going right over the top would have flipped it, the view should still line up
+        let c = r.camera(11, 11, FRAC_PI_3);
+        let centre = c.ray_for_pixel(5, 5);
+        assert_eq!(centre.direction, (r.to - r.from).normalize());
+
+        r.orbit_vertical(-10.0);
+        let polar = (r.from - r.to).normalize().dot(&r.up).acos();
+        assert!(float::equal(polar, PI - POLE_MARGIN));
+    }
+
+    #[test]
+    fn dolly() {
+        let mut r = rig();
+        let direction = (r.to - r.from).normalize();
+        r.dolly(2.0);
+
+        assert_eq!(r.to, rig().to);
+        assert!(float::equal(r.distance(), rig().distance() - 2.0));
+        assert_eq!((r.to - r.from).normalize(), direction);
+
+        r.dolly(-4.0);
+        assert!(float::equal(r.distance(), rig().distance() + 2.0));
+
+        r.dolly(100.0);
+        assert!(r.distance() > 0.0);
+        assert_eq!((r.to - r.from).normalize(), direction);
+    }
+
+    #[test]
+    fn pan() {
+        // Level, so camera space isn't squashed by view_transform's unnormalized left
+        let level = CameraRig::new(
+            point(0.0, 1.5, -5.0),
+            point(0.0, 1.5, 0.0),
+            vectori(0, 1, 0),
+        );
+        let mut r = level;
+        let before = r.camera(11, 11, FRAC_PI_3);
+        r.pan(1.0, 2.0);
+
+        let moved = r.from - level.from;
+        assert_eq!(r.to - level.to, moved);
+        assert!(float::equal(moved.magnitude(), 5.0_f64.sqrt()));
+        // The same offset in the (unmoved) camera's space
+        assert_eq!(&before.transform * moved, vectori(1, 2, 0));
+        // Still looking the same way
+        assert_eq!(
+            r.camera(11, 11, FRAC_PI_3).ray_for_pixel(5, 5).direction,
+            before.ray_for_pixel(5, 5).direction
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Stopping a render part way through. Hand a CancellationToken to the render, keep a clone,
+//! and `cancel` it from wherever (another thread, a signal handler, a UI button). The render
+//! stops at the next pixel and hands back what it got done.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{canvas::Canvas, colour::Colour};
+
+/// What pixels a cancelled render never got to are left as. Magenta, so they stand out.
+pub const UNRENDERED: Colour = Colour::new(1.0, 0.0, 1.0);
+
+/// A shared "please stop" flag. Clones all share the one flag, and dropping any of them
+/// (including all but the render's own) just means nobody can cancel it any more.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How a cancellable render went, with the canvas either way
+#[derive(Clone)]
+pub enum RenderResult {
+    Completed(Canvas),
+    /// Stopped early, anything not rendered yet is UNRENDERED
+    Cancelled(Canvas),
+}
+
+impl RenderResult {
+    pub fn is_completed(&self) -> bool {
+        matches!(self, RenderResult::Completed(_))
+    }
+
+    pub fn canvas(&self) -> &Canvas {
+        match self {
+            RenderResult::Completed(c) | RenderResult::Cancelled(c) => c,
+        }
+    }
+
+    pub fn into_canvas(self) -> Canvas {
+        match self {
+            RenderResult::Completed(c) | RenderResult::Cancelled(c) => c,
+        }
+    }
+}
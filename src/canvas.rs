@@ -1,6 +1,36 @@
-use std::ops::{Index, IndexMut};
+use core::{
+    convert::Infallible,
+    ops::{Index, IndexMut},
+};
+#[cfg(feature = "std")]
+use std::io;
 
-use crate::colour::Colour;
+use crate::colour::{BlendMode, Colour};
+use crate::error::RayTracerError;
+use crate::prelude::*;
+
+mod bloom;
+mod bmp;
+mod compact;
+mod denoise;
+mod dither;
+mod exposure;
+#[cfg(feature = "std")]
+pub mod gif;
+mod png;
+mod ppm;
+mod tone_map;
+
+pub use bloom::gaussian_kernel;
+pub use compact::CanvasU8;
+pub use denoise::DenoiseSettings;
+pub use dither::DitherMode;
+pub use exposure::{Exposure, MIDDLE_GREY};
+pub use tone_map::ToneMap;
+
+// For checksumming camera checkpoints
+#[cfg(feature = "std")]
+pub(crate) use png::crc32;
 
 /// A canvas using a Vec as a backing store.
 #[derive(Clone)]
@@ -11,8 +41,14 @@ pub struct Canvas {
 }
 
 impl Canvas {
-    fn make_index(width: usize, x: usize, y: usize) -> usize {
-        (width * y) + x
+    /// Where (x, y) is in data, if it's on the canvas. An x past the edge would otherwise land
+    /// on the next row.
+    fn checked_index(&self, x: usize, y: usize) -> Option<usize> {
+        checked_index(self.width, self.height, x, y)
+    }
+
+    fn index_or_panic(&self, x: usize, y: usize) -> usize {
+        index_or_panic(self.width, self.height, x, y)
     }
 
     /// Create a new canvas with the given extents
@@ -37,6 +73,27 @@ impl Canvas {
         }
     }
 
+    /// The pixel at (x, y), None if that's off the canvas
+    pub fn get(&self, x: usize, y: usize) -> Option<&Colour> {
+        self.checked_index(x, y).map(|i| &self.data[i])
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Colour> {
+        self.checked_index(x, y).map(|i| &mut self.data[i])
+    }
+
+    /// Set the pixel at (x, y) if it's on the canvas, quietly doing nothing if it isn't.
+    /// Returns whether it was set.
+    pub fn set(&mut self, x: usize, y: usize, colour: Colour) -> bool {
+        match self.get_mut(x, y) {
+            Some(pixel) => {
+                *pixel = colour;
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Access the underlying vector directly (Note this is NOT a mutable version)
     pub fn vec(&self) -> &Vec<Colour> {
         &self.data
@@ -51,56 +108,476 @@ impl Canvas {
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Colour> {
         self.data.iter_mut()
     }
+
+    /// Every pixel along with its (x, y), in the same order as iter: row by row from the top,
+    /// so y is the outer loop and x the inner one.
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = (usize, usize, &Colour)> {
+        self.rows()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, c)| (x, y, c)))
+    }
+
+    pub fn enumerate_pixels_mut(&mut self) -> impl Iterator<Item = (usize, usize, &mut Colour)> {
+        self.rows_mut()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter_mut().enumerate().map(move |(x, c)| (x, y, c)))
+    }
+
+    /// One `width` long slice per row, top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = &[Colour]> {
+        // chunks won't take 0, and a 0 wide canvas has no pixels anyway
+        self.data.chunks(self.width.max(1))
+    }
+
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [Colour]> {
+        self.data.chunks_mut(self.width.max(1))
+    }
+
+    /// Replaces every NaN/infinite pixel with `replacement`, returning how many there were.
+    /// Handy for tracking down where they came from, paint them something garish.
+    pub fn sanitize(&mut self, replacement: Colour) -> usize {
+        let mut fixed = 0;
+        for pixel in self.iter_mut().filter(|p| !p.is_finite()) {
+            *pixel = replacement;
+            fixed += 1;
+        }
+        fixed
+    }
+
+    /// `other` composited on top of this, pixel by pixel. They need to be the same size.
+    pub fn blend_with(&self, other: &Canvas, mode: BlendMode) -> Result<Canvas, String> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(format!(
+                "can't blend a {}x{} canvas with a {}x{} one",
+                self.width, self.height, other.width, other.height
+            ));
+        }
+
+        let mut out = self.clone();
+        for (pixel, top) in out.iter_mut().zip(other.iter()) {
+            *pixel = pixel.blend(top, mode);
+        }
+        Ok(out)
+    }
+
+    /// A copy with every pixel sRGB encoded, see Colour::linear_to_srgb
+    pub fn encoded_srgb(&self) -> Canvas {
+        let mut out = self.clone();
+        for pixel in out.iter_mut() {
+            *pixel = pixel.linear_to_srgb();
+        }
+        out
+    }
+
+    /// A copy with every pixel sRGB decoded back to linear, see Colour::srgb_to_linear. What an
+    /// image read from a file needs before it's lit, like a texture.
+    pub fn decoded_srgb(&self) -> Canvas {
+        let mut out = self.clone();
+        for pixel in out.iter_mut() {
+            *pixel = pixel.srgb_to_linear();
+        }
+        out
+    }
+
+    /// A copy with every channel on one of the 256 levels an 8 bit file can hold, rounded as
+    /// `dither` says. Writing it out then gives exactly those levels.
+    pub fn quantized(&self, dither: DitherMode) -> Canvas {
+        dither::quantize(self, dither)
+    }
+
+    /// A copy with every pixel replaced by a grey of the same luminance
+    pub fn to_greyscale(&self) -> Canvas {
+        let mut out = self.clone();
+        for pixel in out.iter_mut() {
+            *pixel = pixel.to_greyscale();
+        }
+        out
+    }
+
+    /// A copy upside down, the top row at the bottom
+    pub fn flipped_vertical(&self) -> Canvas {
+        let mut out = self.clone();
+        for (to, from) in out
+            .rows_mut()
+            .zip(self.data.chunks(self.width.max(1)).rev())
+        {
+            to.copy_from_slice(from);
+        }
+        out
+    }
+
+    /// A copy with every channel raised to 1 / `gamma`, so 2.2 brightens the midtones the way
+    /// a plain gamma encode does. Negative channels become 0 rather than NaN.
+    pub fn gamma_corrected(&self, gamma: f64) -> Canvas {
+        let mut out = self.clone();
+        for pixel in out.iter_mut() {
+            *pixel = pixel.map(|c| c.max(0.0).powf(1.0 / gamma));
+        }
+        out
+    }
+
+    /// A `width` x `height` copy, bilinearly filtered. Good for modest changes of size, shrinking
+    /// a lot skips over pixels rather than averaging them.
+    pub fn resized(&self, width: usize, height: usize) -> Canvas {
+        let mut out = Canvas::new(width, height);
+        if self.width == 0 || self.height == 0 {
+            return out;
+        }
+
+        // Where the middle of a new pixel lands in the old canvas, in pixels
+        let source = |new: usize, new_size: usize, old_size: usize| {
+            let at = (new as f64 + 0.5) * old_size as f64 / new_size as f64 - 0.5;
+            let at = at.clamp(0.0, (old_size - 1) as f64);
+            let low = at.floor() as usize;
+            (low, (low + 1).min(old_size - 1), at - low as f64)
+        };
+
+        for (x, y, pixel) in out.enumerate_pixels_mut() {
+            let (x0, x1, fx) = source(x, width, self.width);
+            let (y0, y1, fy) = source(y, height, self.height);
+            let top = self[(x0, y0)] * (1.0 - fx) + self[(x1, y0)] * fx;
+            let bottom = self[(x0, y1)] * (1.0 - fx) + self[(x1, y1)] * fx;
+            *pixel = top * (1.0 - fy) + bottom * fy;
+        }
+        out
+    }
+}
+
+/// Where (x, y) is in a row by row `width` x `height` image, None if it's outside it. Shared
+/// by both kinds of canvas.
+fn checked_index(width: usize, height: usize, x: usize, y: usize) -> Option<usize> {
+    (x < width && y < height).then_some(width * y + x)
+}
+
+fn index_or_panic(width: usize, height: usize, x: usize, y: usize) -> usize {
+    checked_index(width, height, x, y)
+        .unwrap_or_else(|| panic!("pixel ({x}, {y}) is outside a {width}x{height} canvas"))
 }
 
-/// PPM tasks
+/// The longest line into_ppm writes, as the PPM spec asks
+const MAX_PPM_LINE: usize = 70;
+
+/// Writing image files. Pass encode_srgb to write sRGB (what viewers expect) rather than the linear
+/// values that were rendered.
 impl Canvas {
-    pub fn into_ppm(&self) -> String {
-        let mut out = format!("P3\n{} {}\n255\n", self.width, self.height).to_owned();
-        let stream = self
-            .iter()
-            .map(Colour::to_ppm)
-            .flat_map(|s| s.split_whitespace().map(&str::to_owned).collect::<Vec<_>>());
-
-        let mut size = 0;
-        for (i, c) in stream.enumerate() {
-            let next_len = size + c.len() + 1;
-            if next_len >= 70 || i % (self.width * 3) == 0 {
-                size = 0;
-                out.pop();
-                out.push('\n');
-            }
-
-            size += c.len() + 1;
-            out.push_str(c.as_str());
-            out.push(' ');
-        }
-        out.pop(); // Drop trailing space
-        out
+    pub fn into_ppm(&self, encode_srgb: bool) -> String {
+        ppm_string(&self.rgb8_rows(encode_srgb))
     }
 
-    pub fn into_ppm_binary(&self) -> Vec<u8> {
-        let header = format!("P6 {} {} 255\n", self.width, self.height)
-            .as_bytes()
-            .to_owned();
+    pub fn into_ppm_binary(&self, encode_srgb: bool) -> Vec<u8> {
+        to_vec(|put| stream_ppm_binary(&self.rgb8_rows(encode_srgb), put))
+    }
+
+    /// Reads a P3 or P6 PPM, like into_ppm and into_ppm_binary write. Values are scaled to 0..1
+    /// by the file's maximum, and nothing's decoded, so the colours are whatever was written. For
+    /// an sRGB file (most are) that wants decoded_srgb before being used as linear colours.
+    pub fn from_ppm(data: &[u8]) -> Result<Canvas, RayTracerError> {
+        ppm::decode(data)
+    }
+
+    /// The canvas as a 24 bit BMP file
+    pub fn into_bmp(&self, encode_srgb: bool) -> Vec<u8> {
+        if encode_srgb {
+            return self.encoded_srgb().into_bmp(false);
+        }
+        bmp::encode(self)
+    }
 
-        header
-            .into_iter()
-            .chain(self.data.iter().flat_map(Colour::to_binary_ppm))
-            .collect()
+    /// The canvas as a PNG file, 8 bits a channel and uncompressed
+    pub fn into_png(&self, encode_srgb: bool) -> Vec<u8> {
+        to_vec(|put| png::stream(&self.rgb8_rows(encode_srgb), put))
+    }
+
+    fn rgb8_rows(&self, encode_srgb: bool) -> Rgb8Rows<'_> {
+        let width = self.width;
+        Rgb8Rows {
+            width,
+            height: self.height,
+            row: Box::new(move |y, out| {
+                let row = &self.data[y * width..(y + 1) * width];
+                out.extend(row.iter().flat_map(|c| {
+                    let c = if encode_srgb { c.linear_to_srgb() } else { *c };
+                    c.to_u8()
+                }));
+            }),
+        }
     }
 }
 
+/// The same files streamed into `w` a row at a time, rather than built up in memory first, so
+/// a big render can go straight into a file. The bytes are exactly what the into_ versions give.
+/// `w` is flushed at the end, so a BufWriter's last write can't fail unnoticed.
+#[cfg(feature = "std")]
+impl Canvas {
+    pub fn write_ppm(&self, encode_srgb: bool, w: impl io::Write) -> io::Result<()> {
+        write_with(w, |put| stream_ppm(&self.rgb8_rows(encode_srgb), put))
+    }
+
+    pub fn write_ppm_binary(&self, encode_srgb: bool, w: impl io::Write) -> io::Result<()> {
+        write_with(w, |put| {
+            stream_ppm_binary(&self.rgb8_rows(encode_srgb), put)
+        })
+    }
+
+    pub fn write_png(&self, encode_srgb: bool, w: impl io::Write) -> io::Result<()> {
+        write_with(w, |put| png::stream(&self.rgb8_rows(encode_srgb), put))
+    }
+}
+
+/// What the PPM and PNG writers need from either kind of canvas: its size, and a way to add
+/// any one of its rows to a buffer as 8 bit RGB
+struct Rgb8Rows<'a> {
+    width: usize,
+    height: usize,
+    row: RowBytes<'a>,
+}
+
+/// Adds row `y` to the buffer it's given
+type RowBytes<'a> = Box<dyn Fn(usize, &mut Vec<u8>) + 'a>;
+
+/// Hands the PPM to `put` a row at a time
+fn stream_ppm<E>(image: &Rgb8Rows, put: &mut dyn FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+    put(format!("P3\n{} {}\n255\n", image.width, image.height).as_bytes())?;
+    // A 0 wide image has no rows to write at all
+    if image.width == 0 {
+        return Ok(());
+    }
+    let mut bytes = Vec::with_capacity(image.width * 3);
+    let mut text = String::new();
+    for y in 0..image.height {
+        bytes.clear();
+        (image.row)(y, &mut bytes);
+        text.clear();
+        push_ppm_row(bytes.iter().map(|&b| b as u16), &mut text);
+        put(text.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// A row of a P3 file. Every row starts on a new line, and long ones are wrapped so no line's
+/// over MAX_PPM_LINE characters.
+fn push_ppm_row(values: impl Iterator<Item = u16>, text: &mut String) {
+    let mut line_len = 0;
+    for value in values {
+        let token = value.to_string();
+        if line_len > 0 && line_len + 1 + token.len() > MAX_PPM_LINE {
+            text.push('\n');
+            line_len = 0;
+        }
+        if line_len > 0 {
+            text.push(' ');
+            line_len += 1;
+        }
+        text.push_str(&token);
+        line_len += token.len();
+    }
+    text.push('\n');
+}
+
+fn stream_ppm_binary<E>(
+    image: &Rgb8Rows,
+    put: &mut dyn FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    put(format!("P6 {} {} 255\n", image.width, image.height).as_bytes())?;
+    let mut bytes = Vec::with_capacity(image.width * 3);
+    for y in 0..image.height {
+        bytes.clear();
+        (image.row)(y, &mut bytes);
+        put(&bytes)?;
+    }
+    Ok(())
+}
+
+fn ppm_string(image: &Rgb8Rows) -> String {
+    let bytes = to_vec(|put| stream_ppm(image, put));
+    String::from_utf8(bytes).expect("PPMs are only digits and whitespace")
+}
+
+/// Everything a stream_ function puts, in a Vec
+fn to_vec(
+    stream: impl FnOnce(&mut dyn FnMut(&[u8]) -> Result<(), Infallible>) -> Result<(), Infallible>,
+) -> Vec<u8> {
+    let mut out = vec![];
+    let Ok(()) = stream(&mut |bytes| {
+        out.extend_from_slice(bytes);
+        Ok(())
+    });
+    out
+}
+
+/// Everything a stream_ function puts, into `w`, flushing it after
+#[cfg(feature = "std")]
+fn write_with(
+    mut w: impl io::Write,
+    stream: impl FnOnce(&mut dyn FnMut(&[u8]) -> io::Result<()>) -> io::Result<()>,
+) -> io::Result<()> {
+    stream(&mut |bytes| w.write_all(bytes))?;
+    w.flush()
+}
+
+/// How into_ppm_with and write_ppm_with write a PPM. The default is what into_ppm writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PpmOptions {
+    /// What a channel of 1.0 is written as, and the most anything can be. Over 255, P6 files
+    /// take two bytes (big endian) a value, as the spec says. 0 is taken as 1.
+    pub maxval: u16,
+    /// P6 rather than P3
+    pub binary: bool,
+    pub encode_srgb: bool,
+}
+
+impl Default for PpmOptions {
+    fn default() -> Self {
+        Self {
+            maxval: 255,
+            binary: false,
+            encode_srgb: false,
+        }
+    }
+}
+
+/// PPMs with more (or fewer) than 8 bits a channel, to stop smooth gradients banding. A maxval
+/// of 255 gives exactly what into_ppm and into_ppm_binary do.
+impl Canvas {
+    pub fn into_ppm_with(&self, options: PpmOptions) -> Vec<u8> {
+        to_vec(|put| self.stream_ppm_with(options, put))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_ppm_with(&self, w: impl io::Write, options: PpmOptions) -> io::Result<()> {
+        write_with(w, |put| self.stream_ppm_with(options, put))
+    }
+
+    fn stream_ppm_with<E>(
+        &self,
+        options: PpmOptions,
+        put: &mut dyn FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let PpmOptions {
+            maxval,
+            binary,
+            encode_srgb,
+        } = options;
+        let maxval = maxval.max(1);
+        let (width, height) = (self.width, self.height);
+        if binary {
+            put(format!("P6 {width} {height} {maxval}\n").as_bytes())?;
+        } else {
+            put(format!("P3\n{width} {height}\n{maxval}\n").as_bytes())?;
+            if width == 0 {
+                return Ok(());
+            }
+        }
+
+        let mut samples = Vec::with_capacity(width * 3);
+        let mut bytes = Vec::with_capacity(width * 6);
+        let mut text = String::new();
+        for row in (0..height).map(|y| &self.data[y * width..(y + 1) * width]) {
+            samples.clear();
+            samples.extend(row.iter().flat_map(|c| {
+                let c = if encode_srgb { c.linear_to_srgb() } else { *c };
+                c.quantize(maxval)
+            }));
+            if binary {
+                bytes.clear();
+                if maxval > 255 {
+                    bytes.extend(samples.iter().flat_map(|s| s.to_be_bytes()));
+                } else {
+                    bytes.extend(samples.iter().map(|&s| s as u8));
+                }
+                put(&bytes)?;
+            } else {
+                text.clear();
+                push_ppm_row(samples.iter().copied(), &mut text);
+                put(text.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The same again, but rounding to 8 bits as `dither` says (after any sRGB encoding).
+/// DitherMode::None writes exactly what the plain versions do.
+impl Canvas {
+    pub fn into_ppm_dithered(&self, encode_srgb: bool, dither: DitherMode) -> String {
+        self.dithered(encode_srgb, dither, Canvas::into_ppm)
+    }
+
+    pub fn into_ppm_binary_dithered(&self, encode_srgb: bool, dither: DitherMode) -> Vec<u8> {
+        self.dithered(encode_srgb, dither, Canvas::into_ppm_binary)
+    }
+
+    pub fn into_bmp_dithered(&self, encode_srgb: bool, dither: DitherMode) -> Vec<u8> {
+        self.dithered(encode_srgb, dither, Canvas::into_bmp)
+    }
+
+    pub fn into_png_dithered(&self, encode_srgb: bool, dither: DitherMode) -> Vec<u8> {
+        self.dithered(encode_srgb, dither, Canvas::into_png)
+    }
+
+    fn dithered<T>(
+        &self,
+        encode_srgb: bool,
+        dither: DitherMode,
+        write: impl Fn(&Canvas, bool) -> T,
+    ) -> T {
+        match (dither, encode_srgb) {
+            (DitherMode::None, _) => write(self, encode_srgb),
+            (_, true) => write(&self.encoded_srgb().quantized(dither), false),
+            (_, false) => write(&self.quantized(dither), false),
+        }
+    }
+}
+
+/// Panics if (x, y) is off the canvas, see get for a version that doesn't
 impl Index<(usize, usize)> for Canvas {
     type Output = Colour;
-    fn index(&self, index: (usize, usize)) -> &Self::Output {
-        &self.data[Canvas::make_index(self.width, index.0, index.1)]
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        &self.data[self.index_or_panic(x, y)]
     }
 }
 
 impl IndexMut<(usize, usize)> for Canvas {
-    fn index_mut(&mut self, index: (usize, usize)) -> &mut Self::Output {
-        &mut self.data[Canvas::make_index(self.width, index.0, index.1)]
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        let i = self.index_or_panic(x, y);
+        &mut self.data[i]
+    }
+}
+
+/// Somewhere a render can go, see Camera::render_to. Canvas keeps the full f64 colours, CanvasU8
+/// only the 8 bits a channel that get written out, in an eighth of the memory.
+pub trait PixelSurface {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+
+    /// Store `colour` at (x, y), returning false (and doing nothing) if that's off the surface
+    fn put(&mut self, x: usize, y: usize, colour: Colour) -> bool;
+
+    /// Store a whole row at once, `row` being `width` long
+    fn put_row(&mut self, y: usize, row: &[Colour]) {
+        for (x, colour) in row.iter().enumerate() {
+            self.put(x, y, *colour);
+        }
+    }
+}
+
+impl PixelSurface for Canvas {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn put(&mut self, x: usize, y: usize, colour: Colour) -> bool {
+        self.set(x, y, colour)
+    }
+
+    fn put_row(&mut self, y: usize, row: &[Colour]) {
+        if let Some(to) = self.rows_mut().nth(y) {
+            to.copy_from_slice(row);
+        }
     }
 }
 
@@ -137,6 +614,51 @@ mod test {
     fn index_oob() {
         Canvas::new(1, 1)[(1, 1)];
     }
+    #[test]
+    #[should_panic(expected = "pixel (3, 0) is outside a 3x2 canvas")]
+    fn index_past_row_end() {
+        // Used to quietly write to (0, 1)
+        let mut c = Canvas::new(3, 2);
+        c[(3, 0)] = Colour::RED;
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel (0, 2) is outside a 3x2 canvas")]
+    #[allow(clippy::unnecessary_operation)]
+    fn index_past_bottom() {
+        Canvas::new(3, 2)[(0, 2)];
+    }
+
+    #[test]
+    fn get() {
+        let mut c = Canvas::new(3, 2);
+        c[(2, 1)] = Colour::RED;
+        for (x, y) in [(0, 0), (2, 0), (0, 1), (2, 1)] {
+            assert_eq!(c.get(x, y), Some(&c[(x, y)]), "{x} {y}");
+        }
+        assert_eq!(c.get(2, 1), Some(&Colour::RED));
+        for (x, y) in [(3, 0), (0, 2), (3, 1), (2, 2), (usize::MAX, 0)] {
+            assert_eq!(c.get(x, y), None, "{x} {y}");
+        }
+
+        *c.get_mut(0, 1).unwrap() = Colour::WHITE;
+        assert_eq!(c[(0, 1)], Colour::WHITE);
+        assert!(c.get_mut(3, 0).is_none());
+    }
+
+    #[test]
+    fn set_clips() {
+        let mut c = Canvas::new(3, 2);
+        assert!(c.set(1, 1, Colour::RED));
+        assert_eq!(c[(1, 1)], Colour::RED);
+
+        let before = c.clone();
+        for (x, y) in [(3, 0), (0, 2), (5, 5)] {
+            assert!(!c.set(x, y, Colour::WHITE));
+        }
+        assert!(c.iter().zip(before.iter()).all(|(a, b)| a == b));
+    }
+
     #[test]
     fn index_assign() {
         let mut c = Canvas::new(10, 10);
@@ -144,12 +666,204 @@ mod test {
         assert_eq!(c[(4, 4)], Colour::newi(1, 2, 3))
     }
 
+    #[test]
+    fn greyscale() {
+        let mut c = Canvas::new(3, 2);
+        c[(0, 0)] = Colour::RED;
+        c[(2, 1)] = Colour::new(0.1, 0.9, 0.4);
+
+        let grey = c.to_greyscale();
+
+        assert_eq!((grey.width, grey.height), (3, 2));
+        for (before, after) in c.iter().zip(grey.iter()) {
+            assert_eq!(*after, before.to_greyscale());
+        }
+    }
+
+    #[test]
+    fn sanitize() {
+        let mut c = Canvas::new_with_colour(4, 2, Colour::new(0.1, 0.2, 0.3));
+        c[(1, 0)] = Colour::new(f64::NAN, 0.0, 0.0);
+        c[(3, 1)] = Colour::new(0.0, f64::INFINITY, 0.0);
+        let before = c.clone();
+
+        assert_eq!(c.sanitize(Colour::RED), 2);
+
+        assert_eq!(c[(1, 0)], Colour::RED);
+        assert_eq!(c[(3, 1)], Colour::RED);
+        for (i, (old, new)) in before.iter().zip(c.iter()).enumerate() {
+            if i != 1 && i != 7 {
+                let bits = |c: &Colour| [c.red, c.green, c.blue].map(f64::to_bits);
+                assert_eq!(bits(old), bits(new));
+            }
+        }
+
+        assert_eq!(c.sanitize(Colour::RED), 0);
+    }
+
+    #[test]
+    fn encoded_srgb() {
+        let mut c = Canvas::new(2, 1);
+        c[(1, 0)] = Colour::WHITE * 0.5;
+
+        let encoded = c.encoded_srgb();
+
+        assert_eq!(encoded[(0, 0)], Colour::BLACK);
+        assert_eq!(encoded[(1, 0)], (Colour::WHITE * 0.5).linear_to_srgb());
+        assert_eq!(encoded.decoded_srgb()[(1, 0)], Colour::WHITE * 0.5);
+        assert_eq!(
+            c.decoded_srgb()[(1, 0)],
+            (Colour::WHITE * 0.5).srgb_to_linear()
+        );
+    }
+
+    #[test]
+    fn blend_with() {
+        use crate::colour::BlendMode;
+
+        let mut base = Canvas::new_with_colour(2, 2, Colour::new(0.2, 0.4, 0.6));
+        base[(1, 1)] = Colour::WHITE;
+        let mut top = Canvas::new_with_colour(2, 2, Colour::WHITE * 0.5);
+        top[(0, 1)] = Colour::RED;
+
+        let blended = base.blend_with(&top, BlendMode::Multiply).unwrap();
+
+        for (i, pixel) in blended.iter().enumerate() {
+            let expected = base.vec()[i].blend(&top.vec()[i], BlendMode::Multiply);
+            assert_eq!(*pixel, expected);
+        }
+        assert!(base
+            .blend_with(&Canvas::new(3, 2), BlendMode::Normal)
+            .is_err());
+    }
+
+    #[test]
+    fn enumerate_pixels() {
+        let mut c = Canvas::new(4, 3);
+        for x in 0..4 {
+            for y in 0..3 {
+                c[(x, y)] = Colour::new(x as f64, y as f64, 0.0);
+            }
+        }
+
+        assert_eq!(c.enumerate_pixels().count(), 12);
+        for (x, y, colour) in c.enumerate_pixels() {
+            assert_eq!(*colour, c[(x, y)]);
+        }
+
+        // Row-major, so y is the outer loop
+        let order: Vec<_> = c
+            .enumerate_pixels()
+            .map(|(x, y, _)| (x, y))
+            .take(5)
+            .collect();
+        assert_eq!(order, vec![(0, 0), (1, 0), (2, 0), (3, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn enumerate_pixels_mut() {
+        let mut c = Canvas::new(3, 2);
+        for (x, y, colour) in c.enumerate_pixels_mut() {
+            *colour = Colour::new(x as f64, y as f64, 1.0);
+        }
+        assert_eq!(c[(2, 1)], Colour::new(2.0, 1.0, 1.0));
+        assert_eq!(c[(1, 0)], Colour::new(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn rows() {
+        let mut c = Canvas::new(5, 3);
+        c[(2, 1)] = Colour::WHITE;
+
+        assert_eq!(c.rows().count(), 3);
+        assert!(c.rows().all(|row| row.len() == 5));
+        assert_eq!(c.rows().nth(1).unwrap()[2], Colour::WHITE);
+
+        for (y, row) in c.rows_mut().enumerate() {
+            row[0] = Colour::new(0.0, y as f64, 0.0);
+        }
+        assert_eq!(c[(0, 2)], Colour::new(0.0, 2.0, 0.0));
+
+        assert_eq!(Canvas::new(0, 3).rows().count(), 0);
+    }
+
+    #[test]
+    fn flipped_vertical() {
+        let mut c = Canvas::new(2, 3);
+        c[(0, 0)] = Colour::WHITE;
+        c[(1, 1)] = Colour::new(0.5, 0.0, 0.0);
+
+        let flipped = c.flipped_vertical();
+        assert_eq!(flipped[(0, 2)], Colour::WHITE);
+        assert_eq!(flipped[(1, 1)], Colour::new(0.5, 0.0, 0.0));
+        assert_eq!(flipped[(0, 0)], Colour::BLACK);
+        assert!(flipped.flipped_vertical().iter().eq(c.iter()));
+    }
+
+    #[test]
+    fn gamma_corrected() {
+        let mut c = Canvas::new_with_colour(2, 1, Colour::new(0.25, 1.0, 0.0));
+        c[(1, 0)] = Colour::new(-1.0, 4.0, 0.5);
+
+        let g = c.gamma_corrected(2.0);
+        assert_eq!(g[(0, 0)], Colour::new(0.5, 1.0, 0.0));
+        assert_eq!(g[(1, 0)], Colour::new(0.0, 2.0, 0.5f64.sqrt()));
+        assert_eq!(c.gamma_corrected(1.0)[(1, 0)], Colour::new(0.0, 4.0, 0.5));
+    }
+
+    #[test]
+    fn resized() {
+        let mut c = Canvas::new(2, 1);
+        c[(1, 0)] = Colour::WHITE;
+
+        // Doubling puts the new pixels a quarter of the way between the old ones
+        let wide = c.resized(4, 1);
+        assert_eq!(wide[(0, 0)], Colour::BLACK);
+        assert_eq!(wide[(1, 0)], Colour::WHITE * 0.25);
+        assert_eq!(wide[(2, 0)], Colour::WHITE * 0.75);
+        assert_eq!(wide[(3, 0)], Colour::WHITE);
+
+        let tall = c.resized(2, 3);
+        assert_eq!((tall.width, tall.height), (2, 3));
+        assert!(tall.rows().all(|row| row == c.vec().as_slice()));
+
+        let same = Canvas::new_with_colour(3, 3, Colour::new(0.1, 0.2, 0.3));
+        assert!(same.resized(3, 3).iter().eq(same.iter()));
+        assert!(same.resized(1, 1)[(0, 0)] == Colour::new(0.1, 0.2, 0.3));
+        assert_eq!(Canvas::new(0, 0).resized(2, 2).iter().count(), 4);
+    }
+
+    #[test]
+    fn bmp() {
+        let mut c = Canvas::new(2, 2);
+        c[(0, 0)] = Colour::new(1.0, 0.0, 0.0);
+        c[(1, 1)] = Colour::new(0.0, 0.0, 1.0);
+
+        let bmp = c.into_bmp(false);
+        // Rows of 6 bytes padded to 8
+        assert_eq!(bmp.len(), 54 + 2 * 8);
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(u32::from_le_bytes(bmp[2..6].try_into().unwrap()), 70);
+        assert_eq!(i32::from_le_bytes(bmp[18..22].try_into().unwrap()), 2);
+        assert_eq!(i32::from_le_bytes(bmp[22..26].try_into().unwrap()), 2);
+        // Bottom row first, as BGR
+        assert_eq!(&bmp[54..62], &[0, 0, 0, 255, 0, 0, 0, 0]);
+        assert_eq!(&bmp[62..70], &[0, 0, 255, 0, 0, 0, 0, 0]);
+
+        let grey = Canvas::new_with_colour(1, 1, Colour::WHITE * 0.5);
+        assert_eq!(&grey.into_bmp(true)[54..57], &[188, 188, 188]);
+    }
+
     mod ppm {
-        use crate::{canvas::Canvas, colour::Colour};
+        use crate::{
+            canvas::{Canvas, PpmOptions},
+            colour::Colour,
+            error::RayTracerError,
+        };
 
         #[test]
         fn header() {
-            let ppm = Canvas::new(5, 3).into_ppm();
+            let ppm = Canvas::new(5, 3).into_ppm(false);
             let header: Vec<_> = ppm.lines().take(3).collect();
             assert_eq!(header[0], "P3");
             assert_eq!(header[1], "5 3");
@@ -163,7 +877,7 @@ mod test {
             c[(2, 1)] = Colour::new(0.0, 0.5, 0.0);
             c[(4, 2)] = Colour::new(-0.5, 0.0, 1.0);
 
-            let ppm = c.into_ppm();
+            let ppm = c.into_ppm(false);
             let data: Vec<_> = ppm.lines().skip(3).take(3).collect();
 
             assert_eq!(data[0], "255 0 0 0 0 0 0 0 0 0 0 0 0 0 0");
@@ -175,7 +889,7 @@ mod test {
         fn complex_data() {
             let c = Canvas::new_with_colour(10, 2, Colour::new(1.0, 0.8, 0.6));
 
-            let ppm = c.into_ppm();
+            let ppm = c.into_ppm(false);
             let data: Vec<_> = ppm.lines().skip(3).collect();
             let expected: Vec<&str> =
                 "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204          
@@ -188,5 +902,340 @@ mod test {
 
             assert_eq!(data, expected)
         }
+
+        #[test]
+        fn ends_with_newline() {
+            assert!(Canvas::new(5, 3).into_ppm(false).ends_with("0 0 0\n"));
+            assert_eq!(Canvas::new(0, 0).into_ppm(false), "P3\n0 0\n255\n");
+        }
+
+        #[test]
+        fn exactly_70() {
+            // 17 values of "255" and one of "10" is 17 * 4 + 2 = 70 characters with the
+            // spaces, which just fits
+            let mut c = Canvas::new_with_colour(6, 1, Colour::WHITE);
+            c[(5, 0)] = Colour::new(1.0, 1.0, 10.0 / 255.0);
+            let ppm = c.into_ppm(false);
+            let data: Vec<_> = ppm.lines().skip(3).collect();
+            assert_eq!(data.len(), 1);
+            assert_eq!(data[0].len(), 70);
+            assert!(data[0].ends_with("255 10"));
+
+            // One more character and the last value goes on a line of its own
+            c[(5, 0)] = Colour::new(1.0, 1.0, 100.0 / 255.0);
+            let ppm = c.into_ppm(false);
+            let data: Vec<_> = ppm.lines().skip(3).collect();
+            assert_eq!(data, [&"255 ".repeat(17)[..67], "100"]);
+        }
+
+        #[test]
+        fn single_pixel() {
+            let c = Canvas::new_with_colour(1, 1, Colour::new(1.0, 0.0, 0.2));
+            assert_eq!(c.into_ppm(false), "P3\n1 1\n255\n255 0 51\n");
+        }
+
+        #[test]
+        fn one_wide() {
+            let mut c = Canvas::new(1, 4);
+            for y in 0..4 {
+                c[(0, y)] = Colour::from_u8(y as u8, 0, 255);
+            }
+            let ppm = c.into_ppm(false);
+            let data: Vec<_> = ppm.lines().skip(3).collect();
+            assert_eq!(data, ["0 0 255", "1 0 255", "2 0 255", "3 0 255"]);
+        }
+
+        #[test]
+        fn no_line_too_long() {
+            let mut c = Canvas::new(23, 3);
+            for (x, y, pixel) in c.enumerate_pixels_mut() {
+                *pixel = Colour::from_u8((x * 11) as u8, (y * 90) as u8, 7);
+            }
+            let ppm = c.into_ppm(false);
+            assert!(ppm.lines().all(|l| l.len() <= 70));
+            assert_eq!(
+                Canvas::from_ppm(ppm.as_bytes()).unwrap().into_ppm(false),
+                ppm
+            );
+        }
+
+        #[test]
+        fn srgb() {
+            let c = Canvas::new_with_colour(1, 1, Colour::WHITE * 0.5);
+
+            assert!(c.into_ppm(false).ends_with("128 128 128\n"));
+            // 0.7354 * 255 = 187.5
+            assert!(c.into_ppm(true).ends_with("188 188 188\n"));
+            assert_eq!(
+                c.into_ppm_binary(true),
+                c.encoded_srgb().into_ppm_binary(false)
+            );
+        }
+
+        #[test]
+        fn binary_matches_ascii() {
+            let mut c = Canvas::new(64, 4);
+            for x in 0..64 {
+                for y in 0..4 {
+                    let v = x as f64 / 63.0;
+                    c[(x, y)] = Colour::new(v, 1.0 - v, v * 1.5 - 0.25);
+                }
+            }
+
+            let ascii: Vec<u8> = c
+                .into_ppm(false)
+                .lines()
+                .skip(3)
+                .flat_map(|l| l.split_whitespace())
+                .map(|n| n.parse().unwrap())
+                .collect();
+            let binary = c.into_ppm_binary(false);
+            let header = b"P6 64 4 255\n".len();
+
+            assert_eq!(&binary[header..], &ascii[..]);
+            assert!(ascii.contains(&255));
+        }
+
+        #[test]
+        fn round_trips() {
+            let mut c = Canvas::new(3, 2);
+            c[(0, 0)] = Colour::new(1.0, 0.0, 0.0);
+            c[(2, 1)] = Colour::new(0.2, 0.4, 0.6);
+
+            for ppm in [c.into_ppm(false).into_bytes(), c.into_ppm_binary(false)] {
+                let read = Canvas::from_ppm(&ppm).unwrap();
+                assert_eq!((read.width, read.height), (3, 2));
+                assert!(read.iter().eq(c.iter()));
+            }
+        }
+
+        #[test]
+        fn reads_comments_and_wide_values() {
+            let read =
+                Canvas::from_ppm(b"P3\n# made by hand\n2 1 # width height\n1000\n1000 0 500 0 0 0")
+                    .unwrap();
+            assert_eq!(read[(0, 0)], Colour::new(1.0, 0.0, 0.5));
+
+            let mut wide = b"P6 1 1 65535\n".to_vec();
+            wide.extend([0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+            let read = Canvas::from_ppm(&wide).unwrap();
+            assert_eq!(read[(0, 0)], Colour::new(1.0, 32768.0 / 65535.0, 0.0));
+        }
+
+        fn options(maxval: u16, binary: bool) -> PpmOptions {
+            PpmOptions {
+                maxval,
+                binary,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn maxval_header() {
+            let c = Canvas::new(2, 1);
+            let plain = c.into_ppm_with(options(1023, false));
+            assert!(plain.starts_with(b"P3\n2 1\n1023\n"));
+            let binary = c.into_ppm_with(options(65535, true));
+            assert!(binary.starts_with(b"P6 2 1 65535\n"));
+            // There's no maxval 0
+            assert!(c.into_ppm_with(options(0, true)).starts_with(b"P6 2 1 1\n"));
+        }
+
+        #[test]
+        fn two_byte_samples() {
+            let c = Canvas::new_with_colour(1, 1, Colour::new(1.0, 0.5, -0.1));
+            let ppm = c.into_ppm_with(options(65535, true));
+            let header = b"P6 1 1 65535\n".len();
+            // 0.5 * 65535 = 32767.5, which rounds up
+            assert_eq!(&ppm[header..], &[0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+
+            // and still one byte each up to 255
+            let ppm = c.into_ppm_with(options(100, true));
+            assert_eq!(&ppm[b"P6 1 1 100\n".len()..], &[100, 50, 0]);
+        }
+
+        #[test]
+        fn ascii_maxval_1023() {
+            let mut c = Canvas::new(2, 1);
+            c[(0, 0)] = Colour::new(1.0, 0.25, 2.0);
+            c[(1, 0)] = Colour::new(0.5, 0.0, 0.1);
+            let ppm = String::from_utf8(c.into_ppm_with(options(1023, false))).unwrap();
+            assert_eq!(ppm, "P3\n2 1\n1023\n1023 256 1023 512 0 102\n");
+        }
+
+        #[test]
+        fn maxval_255_is_unchanged() {
+            let mut c = Canvas::new(30, 3);
+            for (x, y, pixel) in c.enumerate_pixels_mut() {
+                *pixel = Colour::new(x as f64 / 30.0, y as f64 / 3.0, 1.5);
+            }
+            for encode_srgb in [false, true] {
+                let with = |binary| {
+                    c.into_ppm_with(PpmOptions {
+                        binary,
+                        encode_srgb,
+                        ..Default::default()
+                    })
+                };
+                assert_eq!(with(false), c.into_ppm(encode_srgb).into_bytes());
+                assert_eq!(with(true), c.into_ppm_binary(encode_srgb));
+            }
+            assert_eq!(
+                Canvas::new(0, 2).into_ppm_with(PpmOptions::default()),
+                Canvas::new(0, 2).into_ppm(false).into_bytes()
+            );
+        }
+
+        #[test]
+        fn wide_round_trips() {
+            let mut c = Canvas::new(3, 2);
+            c[(0, 0)] = Colour::new(1.0, 0.0, 0.0);
+            c[(2, 1)] = Colour::new(0.2, 0.4, 0.6);
+            c[(1, 1)] = Colour::new(0.1234, 0.5678, 0.9);
+
+            for maxval in [1023, 65535] {
+                for binary in [false, true] {
+                    let read = Canvas::from_ppm(&c.into_ppm_with(options(maxval, binary))).unwrap();
+                    assert_eq!((read.width, read.height), (3, 2));
+                    // Off by no more than half a step
+                    for (a, b) in read.iter().zip(c.iter()) {
+                        let off = [a.red - b.red, a.green - b.green, a.blue - b.blue];
+                        assert!(
+                            off.iter().all(|d| d.abs() <= 0.5 / maxval as f64),
+                            "{a:?} {b:?}"
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn rejects_bad_files() {
+            for bad in [
+                &b""[..],
+                b"P5 1 1 255\n\0",
+                b"P3 x 1 255 0 0 0",
+                b"P3 1 1 0 0 0 0",
+                b"P3 1 1 255 0 0",
+                b"P3 1 1 255 0 256 0",
+                b"P6 2 2 255\n\0\0\0",
+                b"P6 99999999999 99999999999 255\n",
+            ] {
+                assert!(matches!(
+                    Canvas::from_ppm(bad),
+                    Err(RayTracerError::InvalidImage { .. })
+                ));
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod write {
+        use std::io::{self, Write};
+
+        use crate::{canvas::Canvas, colour::Colour};
+
+        fn gradient(width: usize, height: usize) -> Canvas {
+            let mut c = Canvas::new(width, height);
+            for (x, y, pixel) in c.enumerate_pixels_mut() {
+                *pixel = Colour::new(x as f64 / width as f64, y as f64 / height as f64, 0.3);
+            }
+            c
+        }
+
+        #[test]
+        fn same_as_into() {
+            let mut odd = Canvas::new(10, 2);
+            odd[(0, 1)] = Colour::new(1.5, -0.5, 0.5);
+            for c in [Canvas::new(0, 0), Canvas::new(5, 3), odd, gradient(64, 4)] {
+                for srgb in [false, true] {
+                    let mut out = vec![];
+                    c.write_ppm(srgb, &mut out).unwrap();
+                    assert_eq!(out, c.into_ppm(srgb).into_bytes());
+
+                    let mut out = vec![];
+                    c.write_ppm_binary(srgb, &mut out).unwrap();
+                    assert_eq!(out, c.into_ppm_binary(srgb));
+
+                    let mut out = vec![];
+                    c.write_png(srgb, &mut out).unwrap();
+                    assert_eq!(out, c.into_png(srgb));
+                }
+            }
+        }
+
+        /// Takes `left` bytes, then fails
+        struct Failing {
+            left: usize,
+        }
+
+        impl Write for Failing {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.left == 0 {
+                    return Err(io::Error::other("disk full"));
+                }
+                let n = buf.len().min(self.left);
+                self.left -= n;
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn errors_are_passed_on() {
+            let c = gradient(40, 30);
+            for left in [0, 5, 100, 1000] {
+                for result in [
+                    c.write_ppm(false, Failing { left }),
+                    c.write_ppm_binary(false, Failing { left }),
+                    c.write_png(false, Failing { left }),
+                ] {
+                    assert_eq!(result.unwrap_err().to_string(), "disk full");
+                }
+            }
+            // and plenty of room is fine
+            assert!(c.write_png(false, Failing { left: 1 << 20 }).is_ok());
+        }
+
+        /// Remembers the most it was given in one go
+        #[derive(Default)]
+        struct Biggest {
+            total: usize,
+            biggest: usize,
+        }
+
+        impl Write for Biggest {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.total += buf.len();
+                self.biggest = self.biggest.max(buf.len());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn big_canvas_goes_a_bit_at_a_time() {
+            let c = gradient(1000, 400);
+            // At most "255 " for each value of a row, or a single deflate block
+            let mut out = Biggest::default();
+            c.write_ppm(false, &mut out).unwrap();
+            assert!(out.biggest <= 1000 * 3 * 4, "{}", out.biggest);
+            assert!(out.total > 1000 * 400 * 3);
+
+            let mut out = Biggest::default();
+            c.write_ppm_binary(false, &mut out).unwrap();
+            assert_eq!(out.biggest, 1000 * 3);
+
+            let mut out = Biggest::default();
+            c.write_png(false, &mut out).unwrap();
+            assert_eq!(out.biggest, 0xffff);
+            assert_eq!(out.total, c.into_png(false).len());
+        }
     }
 }
@@ -0,0 +1,194 @@
+//! Blurring, and the glow around bright things that's built on it. See Canvas::bloom.
+
+use crate::{canvas::Canvas, colour::Colour, prelude::*};
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+
+/// `2 * radius + 1` weights of a Gaussian with a standard deviation of half the radius, from
+/// -radius to radius, adding up to 1. Radius 0 is just [1.0].
+pub fn gaussian_kernel(radius: usize) -> Vec<f64> {
+    if radius == 0 {
+        return vec![1.0];
+    }
+    let sigma = radius as f64 / 2.0;
+    let r = radius as isize;
+    let weights: Vec<f64> = (-r..=r)
+        .map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+impl Canvas {
+    /// A copy blurred by gaussian_kernel(radius), across then down. Past the edges the edge
+    /// pixels carry on.
+    pub fn gaussian_blur(&self, radius: usize) -> Canvas {
+        let kernel = gaussian_kernel(radius);
+        let across = convolve(self, &kernel, true);
+        convolve(&across, &kernel, false)
+    }
+
+    /// Makes bright things glow. Pixels with luminance over `threshold` are blurred by
+    /// `radius` and added back on top, times `intensity`. Works best before tone mapping, on
+    /// the render as it came out.
+    pub fn bloom(&self, threshold: f64, radius: usize, intensity: f64) -> Canvas {
+        let mut bright = self.clone();
+        for pixel in bright.iter_mut() {
+            if pixel.luminance() <= threshold {
+                *pixel = Colour::BLACK;
+            }
+        }
+        let glow = bright.gaussian_blur(radius);
+
+        let mut out = self.clone();
+        for (pixel, glow) in out.iter_mut().zip(glow.iter()) {
+            *pixel += *glow * intensity;
+        }
+        out
+    }
+}
+
+/// One pass of a separable blur, along rows if `horizontal` and columns otherwise
+fn convolve(canvas: &Canvas, kernel: &[f64], horizontal: bool) -> Canvas {
+    let radius = (kernel.len() / 2) as isize;
+    let (width, height) = (canvas.width as isize, canvas.height as isize);
+    let mut out = canvas.clone();
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let (x, y) = (x as isize, y as isize);
+        *pixel = kernel
+            .iter()
+            .enumerate()
+            .fold(Colour::BLACK, |acc, (i, weight)| {
+                let offset = i as isize - radius;
+                let (sx, sy) = if horizontal {
+                    ((x + offset).clamp(0, width - 1), y)
+                } else {
+                    (x, (y + offset).clamp(0, height - 1))
+                };
+                acc + canvas[(sx as usize, sy as usize)] * *weight
+            });
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{canvas::Canvas, colour::Colour, math::float};
+
+    use super::gaussian_kernel;
+
+    fn close(a: &Canvas, b: &Canvas, epsilon: f64) -> bool {
+        a.iter().zip(b.iter()).all(|(a, b)| {
+            (a.red - b.red).abs() <= epsilon
+                && (a.green - b.green).abs() <= epsilon
+                && (a.blue - b.blue).abs() <= epsilon
+        })
+    }
+
+    fn total(canvas: &Canvas) -> Colour {
+        canvas.iter().fold(Colour::BLACK, |acc, c| acc + *c)
+    }
+
+    #[test]
+    fn kernel() {
+        assert_eq!(gaussian_kernel(0), vec![1.0]);
+        for radius in 1..6 {
+            let kernel = gaussian_kernel(radius);
+            assert_eq!(kernel.len(), 2 * radius + 1);
+            assert!(float::equal(kernel.iter().sum(), 1.0));
+            for i in 0..radius {
+                assert_eq!(kernel[i], kernel[2 * radius - i]);
+                assert!(kernel[i] < kernel[i + 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn no_intensity_no_bloom() {
+        let mut canvas = Canvas::new_with_colour(9, 7, Colour::new(0.2, 0.4, 0.6));
+        canvas[(4, 3)] = Colour::new(5.0, 4.0, 3.0);
+        canvas[(0, 0)] = Colour::new(2.0, 0.0, 1.0);
+        let bloomed = canvas.bloom(0.5, 3, 0.0);
+        assert!(canvas.iter().zip(bloomed.iter()).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn hot_pixel() {
+        let radius = 3;
+        let mut canvas = Canvas::new(15, 15);
+        canvas[(7, 7)] = Colour::new(10.0, 5.0, 2.0);
+        let bloomed = canvas.bloom(1.0, radius, 0.5);
+
+        // The pixel itself keeps its light, with half again spread around it
+        let added = total(&bloomed) - total(&canvas);
+        assert_eq!(added, canvas[(7, 7)] * 0.5);
+        assert!(bloomed[(7, 7)].red > canvas[(7, 7)].red);
+
+        // Each pixel of the blob gets the weights across and down
+        let kernel = gaussian_kernel(radius);
+        for (x, y, pixel) in bloomed.enumerate_pixels() {
+            let (dx, dy) = (x as isize - 7, y as isize - 7);
+            let mirrored = [(-dx, dy), (dx, -dy), (dy, dx)]
+                .map(|(dx, dy)| bloomed[((7 + dx) as usize, (7 + dy) as usize)]);
+            for other in mirrored {
+                assert_eq!(*pixel, other, "{x} {y}");
+            }
+            if dx.abs() > 3 || dy.abs() > 3 {
+                assert_eq!(*pixel, Colour::BLACK);
+            } else if (dx, dy) != (0, 0) {
+                let weight = kernel[(dx + 3) as usize] * kernel[(dy + 3) as usize];
+                assert_eq!(*pixel, canvas[(7, 7)] * (0.5 * weight));
+            }
+        }
+    }
+
+    #[test]
+    fn threshold() {
+        // Dim on the left, a bright column on the right
+        let mut canvas = Canvas::new_with_colour(12, 5, Colour::new(0.3, 0.3, 0.3));
+        for y in 0..5 {
+            canvas[(10, y)] = Colour::new(3.0, 3.0, 3.0);
+        }
+        let bloomed = canvas.bloom(0.9, 2, 1.0);
+        for (x, y, pixel) in bloomed.enumerate_pixels() {
+            if x < 8 {
+                assert_eq!(pixel.red.to_bits(), canvas[(x, y)].red.to_bits());
+            } else {
+                assert!(pixel.red > canvas[(x, y)].red);
+            }
+        }
+        // Nothing's over a threshold that high
+        let untouched = canvas.bloom(10.0, 2, 1.0);
+        assert!(close(&untouched, &canvas, 0.0));
+    }
+
+    #[test]
+    fn matches_2d_convolution() {
+        let (width, height, radius) = (7, 5, 2);
+        let mut canvas = Canvas::new(width, height);
+        for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+            *pixel = Colour::new(
+                ((x * 7 + y * 3) % 11) as f64 / 10.0,
+                ((x * y) % 5) as f64,
+                (x + y) as f64 / 12.0,
+            );
+        }
+
+        let kernel = gaussian_kernel(radius);
+        let r = radius as isize;
+        let mut naive = Canvas::new(width, height);
+        for (x, y, pixel) in naive.enumerate_pixels_mut() {
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let sx = (x as isize + dx).clamp(0, width as isize - 1) as usize;
+                    let sy = (y as isize + dy).clamp(0, height as isize - 1) as usize;
+                    let weight = kernel[(dx + r) as usize] * kernel[(dy + r) as usize];
+                    *pixel += canvas[(sx, sy)] * weight;
+                }
+            }
+        }
+        assert!(close(&canvas.gaussian_blur(radius), &naive, 1e-12));
+        assert!(close(&canvas.gaussian_blur(0), &canvas, 0.0));
+    }
+}
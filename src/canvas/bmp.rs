@@ -0,0 +1,42 @@
+//! 24 bit uncompressed BMPs, which anything on Windows will open
+
+use crate::{canvas::Canvas, prelude::*};
+
+const FILE_HEADER: usize = 14;
+const INFO_HEADER: usize = 40;
+
+pub(super) fn encode(canvas: &Canvas) -> Vec<u8> {
+    // Rows are padded out to a multiple of 4 bytes
+    let row = (canvas.width * 3).next_multiple_of(4);
+    let size = FILE_HEADER + INFO_HEADER + row * canvas.height;
+
+    let mut out = Vec::with_capacity(size);
+    out.extend(b"BM");
+    out.extend((size as u32).to_le_bytes());
+    out.extend([0; 4]);
+    out.extend(((FILE_HEADER + INFO_HEADER) as u32).to_le_bytes());
+
+    out.extend((INFO_HEADER as u32).to_le_bytes());
+    out.extend((canvas.width as i32).to_le_bytes());
+    // Positive height means the rows are stored bottom to top
+    out.extend((canvas.height as i32).to_le_bytes());
+    // One plane, 24 bits a pixel, no compression
+    out.extend(1u16.to_le_bytes());
+    out.extend(24u16.to_le_bytes());
+    out.extend(0u32.to_le_bytes());
+    out.extend(((row * canvas.height) as u32).to_le_bytes());
+    // 72 dpi either way, no palette
+    out.extend(2835u32.to_le_bytes());
+    out.extend(2835u32.to_le_bytes());
+    out.extend([0; 8]);
+
+    for pixels in canvas.vec().chunks(canvas.width.max(1)).rev() {
+        let start = out.len();
+        for pixel in pixels {
+            let [r, g, b] = pixel.to_u8();
+            out.extend([b, g, r]);
+        }
+        out.resize(start + row, 0);
+    }
+    out
+}
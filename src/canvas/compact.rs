@@ -0,0 +1,318 @@
+//! A canvas that only keeps what gets written out, 8 bits a channel. Three bytes a pixel rather
+//! than Colour's 24, for renders too big to hold in f64s.
+
+use core::ops::{Index, IndexMut};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{colour::Colour, prelude::*};
+
+use super::{
+    checked_index, index_or_panic, png, ppm_string, stream_ppm_binary, to_vec, Canvas,
+    PixelSurface, Rgb8Rows,
+};
+
+/// Like Canvas, but with every pixel quantized (Colour::to_u8) as it's set. There's no
+/// encode_srgb when writing it out, that's decided up front by new or new_srgb since it has to
+/// happen before the rounding.
+#[derive(Clone)]
+pub struct CanvasU8 {
+    pub width: usize,
+    pub height: usize,
+    srgb: bool,
+    data: Vec<[u8; 3]>,
+}
+
+impl CanvasU8 {
+    /// A black canvas that keeps the linear values it's given
+    /// ```
+    /// # use raytracer::canvas::CanvasU8;
+    /// let canvas = CanvasU8::new(10, 10);
+    /// assert_eq!(canvas.iter().count(), 100);
+    /// ```
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            srgb: false,
+            data: vec![[0; 3]; width * height],
+        }
+    }
+
+    /// A black canvas that sRGB encodes everything set on it, what Canvas's writers do with
+    /// encode_srgb
+    pub fn new_srgb(width: usize, height: usize) -> Self {
+        Self {
+            srgb: true,
+            ..Self::new(width, height)
+        }
+    }
+
+    /// Whether colours are sRGB encoded on the way in, see new_srgb
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    /// The bytes `colour` would be stored as
+    pub fn quantize(&self, colour: Colour) -> [u8; 3] {
+        if self.srgb {
+            colour.linear_to_srgb().to_u8()
+        } else {
+            colour.to_u8()
+        }
+    }
+
+    /// The pixel at (x, y), None if that's off the canvas
+    pub fn get(&self, x: usize, y: usize) -> Option<&[u8; 3]> {
+        checked_index(self.width, self.height, x, y).map(|i| &self.data[i])
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut [u8; 3]> {
+        checked_index(self.width, self.height, x, y).map(|i| &mut self.data[i])
+    }
+
+    /// Quantize and set the pixel at (x, y) if it's on the canvas, quietly doing nothing if it
+    /// isn't. Returns whether it was set.
+    pub fn set(&mut self, x: usize, y: usize, colour: Colour) -> bool {
+        let bytes = self.quantize(colour);
+        match self.get_mut(x, y) {
+            Some(pixel) => {
+                *pixel = bytes;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn vec(&self) -> &Vec<[u8; 3]> {
+        &self.data
+    }
+
+    /// Row by row from 0,0, the same as Canvas::iter
+    pub fn iter(&self) -> impl Iterator<Item = &[u8; 3]> {
+        self.data.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut [u8; 3]> {
+        self.data.iter_mut()
+    }
+
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = (usize, usize, &[u8; 3])> {
+        self.rows()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, c)| (x, y, c)))
+    }
+
+    pub fn enumerate_pixels_mut(&mut self) -> impl Iterator<Item = (usize, usize, &mut [u8; 3])> {
+        self.rows_mut()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter_mut().enumerate().map(move |(x, c)| (x, y, c)))
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[[u8; 3]]> {
+        self.data.chunks(self.width.max(1))
+    }
+
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [[u8; 3]]> {
+        self.data.chunks_mut(self.width.max(1))
+    }
+
+    /// The bytes back as colours, on a full Canvas. Still sRGB encoded if this was.
+    pub fn to_canvas(&self) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            data: self.data.iter().map(|&bytes| Colour::from(bytes)).collect(),
+        }
+    }
+}
+
+/// Writing image files. The bytes are already what goes in the file, so these just copy them.
+impl CanvasU8 {
+    pub fn into_ppm(&self) -> String {
+        ppm_string(&self.rgb8_rows())
+    }
+
+    pub fn into_ppm_binary(&self) -> Vec<u8> {
+        to_vec(|put| stream_ppm_binary(&self.rgb8_rows(), put))
+    }
+
+    pub fn into_png(&self) -> Vec<u8> {
+        to_vec(|put| png::stream(&self.rgb8_rows(), put))
+    }
+
+    fn rgb8_rows(&self) -> Rgb8Rows<'_> {
+        let width = self.width;
+        Rgb8Rows {
+            width,
+            height: self.height,
+            row: Box::new(move |y, out| {
+                out.extend_from_slice(self.data[y * width..(y + 1) * width].as_flattened())
+            }),
+        }
+    }
+}
+
+/// See Canvas's write_ functions
+#[cfg(feature = "std")]
+impl CanvasU8 {
+    pub fn write_ppm(&self, w: impl io::Write) -> io::Result<()> {
+        super::write_with(w, |put| super::stream_ppm(&self.rgb8_rows(), put))
+    }
+
+    pub fn write_ppm_binary(&self, w: impl io::Write) -> io::Result<()> {
+        super::write_with(w, |put| stream_ppm_binary(&self.rgb8_rows(), put))
+    }
+
+    pub fn write_png(&self, w: impl io::Write) -> io::Result<()> {
+        super::write_with(w, |put| png::stream(&self.rgb8_rows(), put))
+    }
+}
+
+impl PixelSurface for CanvasU8 {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn put(&mut self, x: usize, y: usize, colour: Colour) -> bool {
+        self.set(x, y, colour)
+    }
+}
+
+/// Panics if (x, y) is off the canvas, see get for a version that doesn't
+impl Index<(usize, usize)> for CanvasU8 {
+    type Output = [u8; 3];
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        &self.data[index_or_panic(self.width, self.height, x, y)]
+    }
+}
+
+impl IndexMut<(usize, usize)> for CanvasU8 {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        let i = index_or_panic(self.width, self.height, x, y);
+        &mut self.data[i]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::mem::size_of;
+
+    use crate::{canvas::Canvas, colour::Colour};
+
+    use super::CanvasU8;
+
+    fn gradient(width: usize, height: usize) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        for (x, y, pixel) in c.enumerate_pixels_mut() {
+            *pixel = Colour::new(x as f64 / width as f64, y as f64 / height as f64, 1.2);
+        }
+        c
+    }
+
+    fn compact(c: &Canvas, srgb: bool) -> CanvasU8 {
+        let mut out = if srgb {
+            CanvasU8::new_srgb(c.width, c.height)
+        } else {
+            CanvasU8::new(c.width, c.height)
+        };
+        for (x, y, colour) in c.enumerate_pixels() {
+            assert!(out.set(x, y, *colour));
+        }
+        out
+    }
+
+    #[test]
+    fn footprint() {
+        assert_eq!(size_of::<[u8; 3]>(), 3);
+        assert_eq!(size_of::<Colour>(), 24);
+        let c = CanvasU8::new(12, 6);
+        assert_eq!(c.vec().len() * size_of::<[u8; 3]>(), 12 * 6 * 3);
+    }
+
+    #[test]
+    fn quantizes_on_set() {
+        let mut c = CanvasU8::new(2, 1);
+        c.set(1, 0, Colour::new(0.5, 1.5, -0.2));
+        assert_eq!(c[(1, 0)], [128, 255, 0]);
+        assert_eq!(c[(0, 0)], [0, 0, 0]);
+
+        let mut srgb = CanvasU8::new_srgb(1, 1);
+        srgb.set(0, 0, Colour::WHITE * 0.5);
+        assert_eq!(srgb[(0, 0)], [188, 188, 188]);
+    }
+
+    #[test]
+    fn same_files_as_canvas() {
+        for c in [Canvas::new(0, 0), Canvas::new(0, 3), gradient(30, 4)] {
+            for srgb in [false, true] {
+                let u8s = compact(&c, srgb);
+                assert_eq!(u8s.into_ppm(), c.into_ppm(srgb));
+                assert_eq!(u8s.into_ppm_binary(), c.into_ppm_binary(srgb));
+                assert_eq!(u8s.into_png(), c.into_png(srgb));
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_matches_into() {
+        let c = compact(&gradient(20, 3), true);
+        let mut out = vec![];
+        c.write_ppm(&mut out).unwrap();
+        assert_eq!(out, c.into_ppm().into_bytes());
+
+        let mut out = vec![];
+        c.write_ppm_binary(&mut out).unwrap();
+        assert_eq!(out, c.into_ppm_binary());
+
+        let mut out = vec![];
+        c.write_png(&mut out).unwrap();
+        assert_eq!(out, c.into_png());
+    }
+
+    #[test]
+    fn same_layout_as_canvas() {
+        let c = gradient(4, 3);
+        let u8s = compact(&c, false);
+
+        assert!(u8s
+            .iter()
+            .eq(c.iter().map(|c| c.to_u8()).collect::<Vec<_>>().iter()));
+        assert!(u8s
+            .enumerate_pixels()
+            .map(|(x, y, bytes)| (x, y, *bytes))
+            .eq(c.enumerate_pixels().map(|(x, y, c)| (x, y, c.to_u8()))));
+        assert_eq!(u8s.rows().count(), c.rows().count());
+        assert!(u8s.rows().all(|row| row.len() == 4));
+        assert_eq!(CanvasU8::new(0, 3).rows().count(), 0);
+
+        for (x, y) in [(0, 0), (3, 0), (0, 2), (3, 2)] {
+            assert_eq!(u8s.get(x, y), Some(&c[(x, y)].to_u8()));
+            assert_eq!(u8s[(x, y)], c[(x, y)].to_u8());
+        }
+        for (x, y) in [(4, 0), (0, 3), (usize::MAX, 0)] {
+            assert_eq!(u8s.get(x, y), None);
+            assert!(c.get(x, y).is_none());
+        }
+
+        let mut u8s = u8s;
+        assert!(!u8s.set(4, 0, Colour::WHITE));
+        for (x, y, bytes) in u8s.enumerate_pixels_mut() {
+            *bytes = [x as u8, y as u8, 7];
+        }
+        assert_eq!(u8s[(3, 1)], [3, 1, 7]);
+        assert_eq!(u8s.to_canvas()[(3, 1)], Colour::from([3, 1, 7]));
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel (3, 0) is outside a 3x2 canvas")]
+    fn index_past_row_end() {
+        let mut c = CanvasU8::new(3, 2);
+        c[(3, 0)] = [255; 3];
+    }
+}
@@ -0,0 +1,321 @@
+//! Smoothing out sampling noise without smearing edges, see Canvas::denoise
+
+use crate::{canvas::Canvas, colour::Colour, math::tuple::vector, prelude::*};
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+
+/// Knobs for Canvas::denoise, a bilateral filter: each pixel becomes a weighted average of those
+/// around it, with the weights falling off both with distance and with how different their
+/// colour is. So noise gets averaged away but a jump in colour mostly stays put.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DenoiseSettings {
+    /// How far, in pixels, neighbours still count. Pixels out to twice this are looked at.
+    pub spatial_sigma: f64,
+    /// How different a neighbour's colour can be and still count, as a distance in RGB
+    pub colour_sigma: f64,
+    /// With guides, neighbours whose normals are at an angle with a cosine below this are on
+    /// some other surface and left out
+    pub normal_threshold: f64,
+    /// With guides, neighbours whose depth is further off than this fraction of the deeper of
+    /// the two are left out
+    pub depth_threshold: f64,
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        Self {
+            spatial_sigma: 2.0,
+            colour_sigma: 0.2,
+            normal_threshold: 0.9,
+            depth_threshold: 0.1,
+        }
+    }
+}
+
+/// Normal and depth buffers for the same image, see Camera::render_normals and
+/// Camera::render_depth
+struct Guides<'a> {
+    normals: &'a Canvas,
+    depth: &'a Canvas,
+}
+
+impl Canvas {
+    /// A copy with the noise smoothed out, see DenoiseSettings
+    pub fn denoise(&self, settings: DenoiseSettings) -> Canvas {
+        bilateral(self, None, settings)
+    }
+
+    /// denoise, but never averaging across the edges of things, as found from `normals` and
+    /// `depth` rather than from the (noisy) colours. Those are as Camera::render_normals and
+    /// Camera::render_depth give them, and need to be the same size as this.
+    pub fn denoise_guided(
+        &self,
+        normals: &Canvas,
+        depth: &Canvas,
+        settings: DenoiseSettings,
+    ) -> Result<Canvas, String> {
+        for (name, guide) in [("normals", normals), ("depth", depth)] {
+            if (guide.width, guide.height) != (self.width, self.height) {
+                return Err(format!(
+                    "{name} is {}x{}, the image is {}x{}",
+                    guide.width, guide.height, self.width, self.height
+                ));
+            }
+        }
+        Ok(bilateral(self, Some(Guides { normals, depth }), settings))
+    }
+
+    /// A copy with lone fireflies taken out: any pixel with more than `factor` times the
+    /// luminance of the brightest of its neighbours is swapped for the colour with the median
+    /// luminance of the 3x3 block around it. Going by the brightest neighbour rather than the
+    /// median means a small highlight a few pixels across keeps its edges.
+    pub fn despeckle(&self, factor: f64) -> Canvas {
+        let mut out = self.clone();
+        for (x, y, pixel) in out.enumerate_pixels_mut() {
+            let mut around = [Colour::BLACK; 9];
+            let mut n = 0;
+            let mut brightest = f64::NEG_INFINITY;
+            for ny in y.saturating_sub(1)..=(y + 1).min(self.height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(self.width - 1) {
+                    let colour = self[(nx, ny)];
+                    around[n] = colour;
+                    n += 1;
+                    if (nx, ny) != (x, y) {
+                        brightest = brightest.max(colour.luminance());
+                    }
+                }
+            }
+            // Written this way round so an infinite factor next to black (NaN) isn't one
+            let speckle = pixel.luminance() > factor * brightest.max(0.0);
+            if n == 1 || !speckle {
+                continue;
+            }
+            let around = &mut around[..n];
+            around.sort_by(|a, b| a.luminance().total_cmp(&b.luminance()));
+            *pixel = around[(n - 1) / 2];
+        }
+        out
+    }
+}
+
+fn bilateral(canvas: &Canvas, guides: Option<Guides>, settings: DenoiseSettings) -> Canvas {
+    let radius = (2.0 * settings.spatial_sigma).ceil().max(0.0) as isize;
+    let (width, height) = (canvas.width as isize, canvas.height as isize);
+    let mut out = canvas.clone();
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let here = canvas[(x, y)];
+        let (mut sum, mut total) = (Colour::BLACK, 0.0);
+        for ny in (y as isize - radius).max(0)..=(y as isize + radius).min(height - 1) {
+            for nx in (x as isize - radius).max(0)..=(x as isize + radius).min(width - 1) {
+                let (nx, ny) = (nx as usize, ny as usize);
+                if let Some(guides) = &guides {
+                    if !guides.same_surface((x, y), (nx, ny), settings) {
+                        continue;
+                    }
+                }
+                let there = canvas[(nx, ny)];
+                let (dx, dy) = (nx as f64 - x as f64, ny as f64 - y as f64);
+                let difference = there - here;
+                let weight = falloff(dx * dx + dy * dy, settings.spatial_sigma)
+                    * falloff(
+                        difference.red * difference.red
+                            + difference.green * difference.green
+                            + difference.blue * difference.blue,
+                        settings.colour_sigma,
+                    );
+                sum += there * weight;
+                total += weight;
+            }
+        }
+        // The pixel itself always counts fully, so total is never 0
+        *pixel = sum / total;
+    }
+    out
+}
+
+/// A Gaussian of a squared distance. A sigma of 0 lets nothing but an exact match through.
+fn falloff(distance_squared: f64, sigma: f64) -> f64 {
+    if distance_squared == 0.0 {
+        1.0
+    } else if sigma <= 0.0 {
+        0.0
+    } else {
+        (-distance_squared / (2.0 * sigma * sigma)).exp()
+    }
+}
+
+impl Guides<'_> {
+    fn same_surface(
+        &self,
+        a: (usize, usize),
+        b: (usize, usize),
+        settings: DenoiseSettings,
+    ) -> bool {
+        let normal = |p| {
+            let c: Colour = self.normals[p];
+            vector(c.red, c.green, c.blue)
+        };
+        let (na, nb) = (normal(a), normal(b));
+        // Misses have no normal, and only go with other misses
+        let (missed_a, missed_b) = (na.magnitude() == 0.0, nb.magnitude() == 0.0);
+        if missed_a || missed_b {
+            return missed_a && missed_b;
+        }
+        if na.normalize().dot(&nb.normalize()) < settings.normal_threshold {
+            return false;
+        }
+        let (da, db) = (self.depth[a].red, self.depth[b].red);
+        (da - db).abs() <= settings.depth_threshold * da.max(db)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{canvas::Canvas, colour::Colour, math::rng::Rng};
+
+    use super::DenoiseSettings;
+
+    /// `base`, with every channel nudged by up to `amount` either way
+    fn noisy(width: usize, height: usize, base: impl Fn(usize) -> f64, amount: f64) -> Canvas {
+        let mut rng = Rng::new(3);
+        let mut canvas = Canvas::new(width, height);
+        for (x, _, pixel) in canvas.enumerate_pixels_mut() {
+            let mut channel = || base(x) + (rng.next_f64() * 2.0 - 1.0) * amount;
+            *pixel = Colour::new(channel(), channel(), channel());
+        }
+        canvas
+    }
+
+    fn variance(canvas: &Canvas) -> f64 {
+        let n = canvas.vec().len() as f64;
+        let mean = canvas.iter().map(|c| c.red).sum::<f64>() / n;
+        canvas.iter().map(|c| (c.red - mean).powi(2)).sum::<f64>() / n
+    }
+
+    /// The average red of column `x`
+    fn column(canvas: &Canvas, x: usize) -> f64 {
+        (0..canvas.height).map(|y| canvas[(x, y)].red).sum::<f64>() / canvas.height as f64
+    }
+
+    fn same(a: &Canvas, b: &Canvas) -> bool {
+        a.iter().zip(b.iter()).all(|(a, b)| {
+            [(a.red, b.red), (a.green, b.green), (a.blue, b.blue)]
+                .iter()
+                .all(|(a, b)| a.to_bits() == b.to_bits())
+        })
+    }
+
+    #[test]
+    fn flat_noise() {
+        let canvas = noisy(24, 24, |_| 0.5, 0.1);
+        let denoised = canvas.denoise(DenoiseSettings {
+            colour_sigma: 0.5,
+            ..Default::default()
+        });
+        let (before, after) = (variance(&canvas), variance(&denoised));
+        assert!(after * 10.0 < before, "{before} {after}");
+    }
+
+    /// Dim on the left half, bright on the right, with guides saying they're two walls at an
+    /// angle to each other, all 5 away. Returned with the normals and depth.
+    fn corner() -> (Canvas, Canvas, Canvas) {
+        let (width, height) = (16, 12);
+        let canvas = noisy(width, height, |x| if x < 8 { 0.3 } else { 0.5 }, 0.1);
+        let mut normals = Canvas::new(width, height);
+        for (x, _, normal) in normals.enumerate_pixels_mut() {
+            *normal = if x < 8 {
+                Colour::new(1.0, 0.0, 0.0)
+            } else {
+                Colour::new(0.0, 0.0, -1.0)
+            };
+        }
+        let depth = Canvas::new_with_colour(width, height, Colour::new(5.0, 5.0, 5.0));
+        (canvas, normals, depth)
+    }
+
+    #[test]
+    fn guided_edges() {
+        let (canvas, normals, depth) = corner();
+        // Loose enough on colour that on its own it blurs right over the edge
+        let settings = DenoiseSettings {
+            colour_sigma: 1.0,
+            ..Default::default()
+        };
+        let step = |canvas: &Canvas| column(canvas, 8) - column(canvas, 7);
+        assert!((step(&canvas) - 0.2).abs() < 0.05);
+
+        let guided = canvas.denoise_guided(&normals, &depth, settings).unwrap();
+        assert!((step(&guided) - 0.2).abs() < 0.03, "{}", step(&guided));
+        assert!(variance(&guided) > 0.0);
+        assert!(step(&canvas.denoise(settings)) < 0.1);
+
+        // A step in depth works the same
+        let mut depth = depth;
+        for (x, _, d) in depth.enumerate_pixels_mut() {
+            if x >= 8 {
+                *d = Colour::new(9.0, 9.0, 9.0);
+            }
+        }
+        let flat = Canvas::new_with_colour(16, 12, Colour::new(0.0, 1.0, 0.0));
+        let guided = canvas.denoise_guided(&flat, &depth, settings).unwrap();
+        assert!((step(&guided) - 0.2).abs() < 0.03, "{}", step(&guided));
+
+        assert!(canvas
+            .denoise_guided(&Canvas::new(3, 3), &depth, settings)
+            .is_err());
+    }
+
+    #[test]
+    fn no_sigma_no_change() {
+        let canvas = noisy(10, 8, |x| x as f64 / 10.0, 0.2);
+        for (spatial_sigma, colour_sigma) in [(0.0, 0.2), (1e-9, 0.2), (2.0, 0.0), (2.0, 1e-12)] {
+            let settings = DenoiseSettings {
+                spatial_sigma,
+                colour_sigma,
+                ..Default::default()
+            };
+            assert!(same(&canvas.denoise(settings), &canvas), "{settings:?}");
+        }
+    }
+
+    #[test]
+    fn despeckle() {
+        let mut canvas = noisy(12, 10, |_| 0.2, 0.05);
+        canvas[(3, 4)] = Colour::new(40.0, 30.0, 50.0);
+        // A real highlight, bright all over
+        for y in 5..8 {
+            for x in 7..10 {
+                canvas[(x, y)] = Colour::new(5.0, 5.0, 4.0);
+            }
+        }
+
+        let despeckled = canvas.despeckle(4.0);
+        let hot = despeckled[(3, 4)];
+        assert!(hot.max_channel() < 0.3, "{hot:?}");
+        // It's one of the neighbours' colours
+        assert!((2..5).any(|x| (3..6).any(|y| canvas[(x, y)] == hot)));
+        let others: Vec<_> = canvas
+            .enumerate_pixels()
+            .filter(|&(x, y, _)| (x, y) != (3, 4))
+            .collect();
+        for (x, y, colour) in others {
+            assert_eq!(despeckled[(x, y)].red.to_bits(), colour.red.to_bits());
+        }
+
+        // Nothing stands out that much
+        assert!(same(&canvas.despeckle(1000.0), &canvas));
+        assert!(same(&Canvas::new(1, 1).despeckle(2.0), &Canvas::new(1, 1)));
+    }
+
+    #[test]
+    fn deterministic() {
+        let (canvas, normals, depth) = corner();
+        let settings = DenoiseSettings::default();
+        assert!(same(&canvas.denoise(settings), &canvas.denoise(settings)));
+        assert!(same(
+            &canvas.denoise_guided(&normals, &depth, settings).unwrap(),
+            &canvas.denoise_guided(&normals, &depth, settings).unwrap()
+        ));
+    }
+}
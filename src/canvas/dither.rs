@@ -0,0 +1,232 @@
+//! Rounding to 8 bits a channel without banding, see Canvas::quantized
+
+use crate::{canvas::Canvas, colour::Colour, prelude::*};
+
+/// How to spread the rounding error when writing out 8 bit channels. Smooth gradients only
+/// have 256 levels to work with, so without help they come out as visible bands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DitherMode {
+    /// Plain rounding, as Colour::to_u8 does
+    #[default]
+    None,
+    /// Each pixel rounds up or down depending on its place in a repeating 8x8 Bayer pattern,
+    /// which turns bands into a fine, even texture. The same pixel always dithers the same.
+    Ordered,
+    /// Floyd-Steinberg error diffusion: whatever a pixel's rounded off is added onto its
+    /// neighbours still to come, going back and forth along alternate rows. Less regular, and
+    /// keeps the overall brightness.
+    FloydSteinberg,
+}
+
+/// The classic recursive Bayer matrix, thresholds 0 to 63
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+pub(super) fn quantize(canvas: &Canvas, mode: DitherMode) -> Canvas {
+    let mut out = canvas.clone();
+    match mode {
+        DitherMode::None => {
+            for pixel in out.iter_mut() {
+                *pixel = pixel.map(|c| level((c * 255.0).round()));
+            }
+        }
+        DitherMode::Ordered => {
+            for (x, y, pixel) in out.enumerate_pixels_mut() {
+                // Somewhere strictly between 0 and 1, so a channel already on a level stays put
+                let threshold = (BAYER[y % 8][x % 8] as f64 + 0.5) / 64.0;
+                *pixel = pixel.map(|c| level((c * 255.0 + threshold - 0.5).round()));
+            }
+        }
+        DitherMode::FloydSteinberg => floyd_steinberg(&mut out),
+    }
+    out
+}
+
+/// A level from 0 to 255, back as 0 to 1
+fn level(value: f64) -> f64 {
+    value.clamp(0.0, 255.0) / 255.0
+}
+
+fn floyd_steinberg(canvas: &mut Canvas) {
+    let (width, height) = (canvas.width, canvas.height);
+    // Per channel, out of 255, including what's been pushed on from pixels before
+    let mut values: Vec<[f64; 3]> = canvas
+        .iter()
+        .map(|c| [c.red, c.green, c.blue].map(|c| c.clamp(0.0, 1.0) * 255.0))
+        .collect();
+
+    for y in 0..height {
+        let forwards = y % 2 == 0;
+        for i in 0..width {
+            let x = if forwards { i } else { width - 1 - i };
+            let here = values[y * width + x];
+            let rounded = here.map(|v| v.round().clamp(0.0, 255.0));
+            canvas[(x, y)] = Colour::new(rounded[0], rounded[1], rounded[2]) / 255.0;
+
+            let error = [0, 1, 2].map(|c| here[c] - rounded[c]);
+            // Ahead along the row, and the three below, mirrored on the way back
+            let ahead = if forwards { x + 1 } else { x.wrapping_sub(1) };
+            let behind = if forwards { x.wrapping_sub(1) } else { x + 1 };
+            for (nx, ny, weight) in [
+                (ahead, y, 7.0),
+                (behind, y + 1, 3.0),
+                (x, y + 1, 5.0),
+                (ahead, y + 1, 1.0),
+            ] {
+                if nx < width && ny < height {
+                    let value = &mut values[ny * width + nx];
+                    for c in 0..3 {
+                        value[c] += error[c] * weight / 16.0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{canvas::Canvas, colour::Colour};
+
+    use super::DitherMode;
+
+    /// `width` wide, from black on the left to white on the right
+    fn gradient(width: usize, height: usize) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        for (x, _, pixel) in canvas.enumerate_pixels_mut() {
+            let v = x as f64 / (width - 1) as f64;
+            *pixel = Colour::new(v, v, v);
+        }
+        canvas
+    }
+
+    fn bytes(canvas: &Canvas) -> Vec<u8> {
+        canvas.iter().flat_map(Colour::to_u8).collect()
+    }
+
+    /// The longest run of the same red byte along any row
+    fn longest_run(canvas: &Canvas) -> usize {
+        canvas
+            .rows()
+            .map(|row| {
+                let reds: Vec<_> = row.iter().map(|c| c.to_u8()[0]).collect();
+                reds.chunk_by(|a, b| a == b).map(<[u8]>::len).max().unwrap()
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn none_is_plain_rounding() {
+        let mut canvas = gradient(300, 2);
+        canvas[(0, 1)] = Colour::new(-1.0, 2.0, 0.5 / 255.0);
+        let quantized = canvas.quantized(DitherMode::None);
+        assert_eq!(bytes(&quantized), bytes(&canvas));
+        // Already on a level, so it stays exactly there
+        assert_eq!(
+            bytes(&quantized.quantized(DitherMode::None)),
+            bytes(&canvas)
+        );
+        assert_eq!(quantized[(0, 1)], Colour::new(0.0, 1.0, 1.0 / 255.0));
+
+        // And the writers are untouched
+        for encode_srgb in [false, true] {
+            let none = DitherMode::None;
+            assert_eq!(
+                canvas.into_ppm_dithered(encode_srgb, none),
+                canvas.into_ppm(encode_srgb)
+            );
+            assert_eq!(
+                canvas.into_ppm_binary_dithered(encode_srgb, none),
+                canvas.into_ppm_binary(encode_srgb)
+            );
+            assert_eq!(
+                canvas.into_png_dithered(encode_srgb, none),
+                canvas.into_png(encode_srgb)
+            );
+            assert_eq!(
+                canvas.into_bmp_dithered(encode_srgb, none),
+                canvas.into_bmp(encode_srgb)
+            );
+        }
+    }
+
+    #[test]
+    fn ordered_breaks_up_bands() {
+        let canvas = gradient(2048, 8);
+        let plain = longest_run(&canvas.quantized(DitherMode::None));
+        let dithered = canvas.quantized(DitherMode::Ordered);
+        assert!(plain >= 8, "{plain}");
+        assert!(longest_run(&dithered) < plain, "{}", longest_run(&dithered));
+
+        // Levels that are already exact don't move
+        let flat = Canvas::new_with_colour(16, 16, Colour::from_u8(10, 128, 255));
+        assert_eq!(bytes(&flat.quantized(DitherMode::Ordered)), bytes(&flat));
+        // Half way between two levels is half one, half the other
+        let half = Canvas::new_with_colour(8, 8, Colour::new(100.5, 0.0, 0.0) / 255.0);
+        let ups = half
+            .quantized(DitherMode::Ordered)
+            .iter()
+            .filter(|c| c.to_u8()[0] == 101)
+            .count();
+        assert_eq!(ups, 32);
+    }
+
+    #[test]
+    fn floyd_steinberg_keeps_brightness() {
+        let mut canvas = gradient(257, 19);
+        for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+            pixel.green = ((x * y) % 97) as f64 / 96.0;
+            pixel.blue = 0.3;
+        }
+        let total = |canvas: &Canvas| canvas.iter().fold(Colour::BLACK, |acc, c| acc + *c);
+        let before = total(&canvas);
+        let after = total(&canvas.quantized(DitherMode::FloydSteinberg));
+        for (before, after) in [
+            (before.red, after.red),
+            (before.green, after.green),
+            (before.blue, after.blue),
+        ] {
+            // A few levels over the whole image, from the error pushed off its edges
+            assert!(((after - before) * 255.0).abs() < 4.0, "{before} {after}");
+        }
+        // Plain rounding takes every 0.3 up to 77 / 255
+        let plain = total(&canvas.quantized(DitherMode::None));
+        assert!((plain.blue - before.blue) * 255.0 > 1000.0);
+    }
+
+    #[test]
+    fn deterministic() {
+        let mut canvas = gradient(64, 64);
+        for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+            pixel.blue = ((x + 3 * y) % 17) as f64 / 16.0;
+        }
+        for mode in [
+            DitherMode::None,
+            DitherMode::Ordered,
+            DitherMode::FloydSteinberg,
+        ] {
+            assert_eq!(
+                bytes(&canvas.quantized(mode)),
+                bytes(&canvas.quantized(mode)),
+                "{mode:?}"
+            );
+            assert_eq!(
+                canvas.into_png_dithered(false, mode),
+                canvas.into_png_dithered(false, mode)
+            );
+        }
+        assert_ne!(
+            bytes(&canvas.quantized(DitherMode::Ordered)),
+            bytes(&canvas.quantized(DitherMode::FloydSteinberg))
+        );
+    }
+}
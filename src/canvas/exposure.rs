@@ -0,0 +1,144 @@
+//! Brightening (or darkening) a whole render so it isn't too dim to see, see Canvas::auto_exposure
+
+use crate::{canvas::Canvas, prelude::*};
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+
+/// The grey a typical scene averages out to, what auto exposure aims for unless told otherwise
+pub const MIDDLE_GREY: f64 = 0.18;
+
+/// How a render's brightness is adjusted before tone mapping, see RenderSettings::exposure
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Exposure {
+    /// Leave it as rendered
+    #[default]
+    Off,
+    /// Multiply every pixel by this
+    Manual(f64),
+    /// Scale it so its log-average luminance comes out as `target`, see Canvas::auto_exposure
+    Auto { target: f64 },
+}
+
+impl Exposure {
+    pub const AUTO: Self = Exposure::Auto {
+        target: MIDDLE_GREY,
+    };
+
+    /// What `canvas` gets multiplied by
+    pub fn scale_for(&self, canvas: &Canvas) -> f64 {
+        match *self {
+            Exposure::Off => 1.0,
+            Exposure::Manual(scale) => scale,
+            Exposure::Auto { target } => canvas.auto_exposure(target),
+        }
+    }
+}
+
+impl Canvas {
+    /// How many pixels have a luminance in each of `bins` equal slices of 0..1. Anything
+    /// brighter than 1 counts in the last one, and anything below 0 in the first.
+    pub fn luminance_histogram(&self, bins: usize) -> Vec<usize> {
+        let mut counts = vec![0; bins];
+        if bins == 0 {
+            return counts;
+        }
+        for pixel in self.iter() {
+            let l = pixel.luminance();
+            // NaN goes to the first bin too, as casting it gives 0
+            let bin = (l * bins as f64).floor().clamp(0.0, (bins - 1) as f64) as usize;
+            counts[bin] += 1;
+        }
+        counts
+    }
+
+    /// What to multiply the canvas by (see exposed) for the geometric mean of its luminance to
+    /// be `target_grey`. Black (and NaN/infinite) pixels are left out, since a single one
+    /// would take the mean to 0, and an all black canvas gets 1.
+    pub fn auto_exposure(&self, target_grey: f64) -> f64 {
+        let (sum, count) = self
+            .iter()
+            .map(|pixel| pixel.luminance())
+            .filter(|l| *l > 0.0 && l.is_finite())
+            .fold((0.0, 0), |(sum, count), l| (sum + l.ln(), count + 1));
+        if count == 0 {
+            return 1.0;
+        }
+        target_grey / (sum / count as f64).exp()
+    }
+
+    /// A copy with every pixel multiplied by `scale`
+    pub fn exposed(&self, scale: f64) -> Canvas {
+        let mut out = self.clone();
+        for pixel in out.iter_mut() {
+            *pixel *= scale;
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{canvas::Canvas, colour::Colour, math::float};
+
+    use super::{Exposure, MIDDLE_GREY};
+
+    fn grey(l: f64) -> Colour {
+        Colour::new(l, l, l)
+    }
+
+    #[test]
+    fn uniform_grey() {
+        let c = Canvas::new_with_colour(4, 3, grey(0.09));
+        let scale = c.auto_exposure(MIDDLE_GREY);
+        assert!(float::equal(scale, 2.0), "{scale}");
+        assert_eq!(c.exposed(scale)[(2, 2)], grey(0.18));
+    }
+
+    #[test]
+    fn log_average() {
+        // The geometric mean of 0.1 and 0.4 is 0.2
+        let mut c = Canvas::new_with_colour(2, 1, grey(0.1));
+        c[(1, 0)] = grey(0.4);
+        assert!(float::equal(c.auto_exposure(0.2), 1.0));
+    }
+
+    #[test]
+    fn black_left_out() {
+        let mut c = Canvas::new_with_colour(4, 4, grey(0.09));
+        for x in 0..4 {
+            c[(x, 0)] = Colour::BLACK;
+        }
+        c[(0, 1)] = grey(f64::NAN);
+        assert!(float::equal(c.auto_exposure(MIDDLE_GREY), 2.0));
+
+        assert_eq!(Canvas::new(3, 3).auto_exposure(MIDDLE_GREY), 1.0);
+        assert_eq!(Canvas::new(0, 0).auto_exposure(MIDDLE_GREY), 1.0);
+    }
+
+    #[test]
+    fn histogram() {
+        // A gradient across 10 pixels, each in the middle of a tenth
+        let mut c = Canvas::new(10, 2);
+        for (x, _, pixel) in c.enumerate_pixels_mut() {
+            *pixel = grey((x as f64 + 0.5) / 10.0);
+        }
+        assert_eq!(c.luminance_histogram(5), vec![4, 4, 4, 4, 4]);
+        assert_eq!(c.luminance_histogram(10), vec![2; 10]);
+        assert_eq!(c.luminance_histogram(1), vec![20]);
+        assert!(c.luminance_histogram(0).is_empty());
+
+        c[(0, 0)] = grey(3.0);
+        c[(1, 0)] = grey(-1.0);
+        assert_eq!(c.luminance_histogram(2), vec![9, 11]);
+    }
+
+    #[test]
+    fn scale_for() {
+        let c = Canvas::new_with_colour(2, 2, grey(0.36));
+        assert_eq!(Exposure::Off.scale_for(&c), 1.0);
+        assert_eq!(Exposure::Manual(3.0).scale_for(&c), 3.0);
+        assert!(float::equal(Exposure::AUTO.scale_for(&c), 0.5));
+    }
+}
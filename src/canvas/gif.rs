@@ -0,0 +1,545 @@
+//! Animated GIFs, for turning a run of frames into something that plays anywhere. Each frame
+//! gets its own palette of up to 256 colours, picked by median cut, and the animation loops
+//! forever.
+
+use std::{collections::HashMap, fmt, io, io::Write};
+
+use crate::canvas::Canvas;
+
+/// A GIF palette can't hold any more than this
+const MAX_COLOURS: usize = 256;
+/// LZW codes never get wider than 12 bits
+const MAX_CODE_SIZE: u8 = 12;
+
+#[derive(Debug)]
+pub enum GifError {
+    Io(io::Error),
+    /// A frame that isn't the size of the first one
+    SizeMismatch {
+        frame: usize,
+        expected: (usize, usize),
+        got: (usize, usize),
+    },
+    /// Widths and heights have to fit in a u16, and not be 0
+    BadSize {
+        width: usize,
+        height: usize,
+    },
+    /// A GIF has to have at least one frame in it
+    NoFrames,
+}
+
+impl fmt::Display for GifError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GifError::Io(e) => write!(f, "writing gif: {e}"),
+            GifError::SizeMismatch {
+                frame,
+                expected: (ew, eh),
+                got: (w, h),
+            } => write!(
+                f,
+                "gif frame {frame} is {w}x{h}, the first frame is {ew}x{eh}"
+            ),
+            GifError::BadSize { width, height } => {
+                write!(f, "gif frames can't be {width}x{height}")
+            }
+            GifError::NoFrames => write!(f, "a gif needs at least one frame"),
+        }
+    }
+}
+
+impl std::error::Error for GifError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GifError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GifError {
+    fn from(value: io::Error) -> Self {
+        GifError::Io(value)
+    }
+}
+
+/// All of `frames` as a looping animation, `delay_cs` hundredths of a second apart
+pub fn encode(frames: &[Canvas], delay_cs: u16, writer: impl Write) -> Result<(), GifError> {
+    let mut encoder = GifEncoder::new(writer, delay_cs);
+    for frame in frames {
+        encoder.add_frame(frame)?;
+    }
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Writes frames out as they're added, so they don't all have to be kept around. The first
+/// frame decides the size of the animation, `finish` to write the end of it.
+/// ```
+/// # use raytracer::canvas::{Canvas, gif::GifEncoder};
+/// let mut gif = GifEncoder::new(vec![], 10).with_dithering(true);
+/// for _ in 0..3 {
+///     gif.add_frame(&Canvas::new(4, 4)).unwrap();
+/// }
+/// let bytes = gif.finish().unwrap();
+/// assert!(bytes.starts_with(b"GIF89a"));
+/// ```
+pub struct GifEncoder<W: Write> {
+    writer: W,
+    delay_cs: u16,
+    dither: bool,
+    size: Option<(usize, usize)>,
+    frames: usize,
+}
+
+impl<W: Write> GifEncoder<W> {
+    pub fn new(writer: W, delay_cs: u16) -> Self {
+        Self {
+            writer,
+            delay_cs,
+            dither: false,
+            size: None,
+            frames: 0,
+        }
+    }
+
+    /// Floyd-Steinberg dither frames down to their palettes. Smoother gradients, noisier flat
+    /// areas, and bigger files.
+    pub fn with_dithering(mut self, dither: bool) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    pub fn add_frame(&mut self, canvas: &Canvas) -> Result<(), GifError> {
+        let size = (canvas.width, canvas.height);
+        match self.size {
+            None => {
+                self.write_header(size)?;
+                self.size = Some(size);
+            }
+            Some(expected) if expected != size => {
+                return Err(GifError::SizeMismatch {
+                    frame: self.frames,
+                    expected,
+                    got: size,
+                })
+            }
+            Some(_) => (),
+        }
+
+        let pixels: Vec<[u8; 3]> = canvas.iter().map(|c| c.to_u8()).collect();
+        let palette = median_cut(&pixels);
+        let indices = if self.dither {
+            dither(&pixels, canvas.width, &palette)
+        } else {
+            map_to(&pixels, &palette)
+        };
+        // Smallest power of two that fits the palette, GIF needs at least 2 entries
+        let bits = (palette.len().max(2) - 1).ilog2() as u8 + 1;
+
+        let w = &mut self.writer;
+        // Graphic control extension: no disposal, no transparency, just the delay
+        w.write_all(&[0x21, 0xf9, 4, 0])?;
+        w.write_all(&self.delay_cs.to_le_bytes())?;
+        w.write_all(&[0, 0])?;
+
+        // Image descriptor, the whole screen with a local colour table
+        w.write_all(&[0x2c, 0, 0, 0, 0])?;
+        w.write_all(&(canvas.width as u16).to_le_bytes())?;
+        w.write_all(&(canvas.height as u16).to_le_bytes())?;
+        w.write_all(&[0x80 | (bits - 1)])?;
+        let mut table = palette.concat();
+        table.resize(3 << bits, 0);
+        w.write_all(&table)?;
+
+        let min_code_size = bits.max(2);
+        w.write_all(&[min_code_size])?;
+        for block in lzw(&indices, min_code_size).chunks(255) {
+            w.write_all(&[block.len() as u8])?;
+            w.write_all(block)?;
+        }
+        w.write_all(&[0])?;
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Ends the file, handing back the writer
+    pub fn finish(mut self) -> Result<W, GifError> {
+        if self.size.is_none() {
+            return Err(GifError::NoFrames);
+        }
+        self.writer.write_all(&[0x3b])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_header(&mut self, (width, height): (usize, usize)) -> Result<(), GifError> {
+        let bad_size = || GifError::BadSize { width, height };
+        let w = u16::try_from(width).map_err(|_| bad_size())?;
+        let h = u16::try_from(height).map_err(|_| bad_size())?;
+        if w == 0 || h == 0 {
+            return Err(bad_size());
+        }
+
+        self.writer.write_all(b"GIF89a")?;
+        // Logical screen, no global colour table (every frame has its own)
+        self.writer.write_all(&w.to_le_bytes())?;
+        self.writer.write_all(&h.to_le_bytes())?;
+        self.writer.write_all(&[0, 0, 0])?;
+        // Loop forever
+        self.writer
+            .write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+        Ok(())
+    }
+}
+
+/// Up to MAX_COLOURS colours that stand in for `pixels`. Exact if there aren't more than that
+/// to start with, otherwise boxes of colours are split on their widest channel at the median
+/// until there are enough, and each box averaged.
+fn median_cut(pixels: &[[u8; 3]]) -> Vec<[u8; 3]> {
+    let mut colours = pixels.to_vec();
+    colours.sort_unstable();
+    let mut counted: Vec<([u8; 3], usize)> = vec![];
+    for colour in colours {
+        match counted.last_mut() {
+            Some((last, count)) if *last == colour => *count += 1,
+            _ => counted.push((colour, 1)),
+        }
+    }
+    if counted.len() <= MAX_COLOURS {
+        return counted.into_iter().map(|(c, _)| c).collect();
+    }
+
+    // Ranges of `counted`
+    let mut boxes = Vec::with_capacity(MAX_COLOURS);
+    boxes.push(0..counted.len());
+    while boxes.len() < MAX_COLOURS {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .flat_map(|(i, b)| {
+                let colours = &counted[b.clone()];
+                (0..3).map(move |channel| {
+                    let values = colours.iter().map(|(c, _)| c[channel]);
+                    let spread = values.clone().max().unwrap() - values.min().unwrap();
+                    (spread, i, channel)
+                })
+            })
+            .max();
+        let Some((_, i, channel)) = widest else {
+            break;
+        };
+
+        let range = boxes[i].clone();
+        let colours = &mut counted[range.clone()];
+        colours.sort_unstable_by_key(|(c, _)| c[channel]);
+        let total: usize = colours.iter().map(|(_, n)| n).sum();
+        let mut seen = 0;
+        let median = colours
+            .iter()
+            .position(|(_, n)| {
+                seen += n;
+                seen * 2 >= total
+            })
+            .unwrap();
+        // Both halves need something in them
+        let split = range.start + (median + 1).clamp(1, colours.len() - 1);
+
+        boxes[i] = range.start..split;
+        boxes.push(split..range.end);
+    }
+
+    boxes
+        .into_iter()
+        .map(|b| {
+            let colours = &counted[b];
+            let total: usize = colours.iter().map(|(_, n)| n).sum();
+            core::array::from_fn(|channel| {
+                let sum: usize = colours.iter().map(|(c, n)| c[channel] as usize * n).sum();
+                ((sum + total / 2) / total) as u8
+            })
+        })
+        .collect()
+}
+
+fn nearest(colour: [f32; 3], palette: &[[u8; 3]]) -> u8 {
+    palette
+        .iter()
+        .map(|p| {
+            (0..3)
+                .map(|c| (colour[c] - p[c] as f32).powi(2))
+                .sum::<f32>()
+        })
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap()
+        .0 as u8
+}
+
+fn map_to(pixels: &[[u8; 3]], palette: &[[u8; 3]]) -> Vec<u8> {
+    let mut cache = HashMap::new();
+    pixels
+        .iter()
+        .map(|p| {
+            *cache
+                .entry(*p)
+                .or_insert_with(|| nearest(p.map(f32::from), palette))
+        })
+        .collect()
+}
+
+fn dither(pixels: &[[u8; 3]], width: usize, palette: &[[u8; 3]]) -> Vec<u8> {
+    let mut wanted: Vec<[f32; 3]> = pixels.iter().map(|p| p.map(f32::from)).collect();
+    let height = pixels.len() / width;
+    let mut indices = Vec::with_capacity(pixels.len());
+
+    for y in 0..height {
+        for x in 0..width {
+            let colour = wanted[y * width + x].map(|c| c.clamp(0.0, 255.0));
+            let index = nearest(colour, palette);
+            indices.push(index);
+
+            let got = palette[index as usize];
+            let error: [f32; 3] = core::array::from_fn(|c| colour[c] - got[c] as f32);
+            let mut spread = |dx: isize, dy: usize, amount: f32| {
+                let (x, y) = (x as isize + dx, y + dy);
+                if (0..width as isize).contains(&x) && y < height {
+                    let pixel = &mut wanted[y * width + x as usize];
+                    for c in 0..3 {
+                        pixel[c] += error[c] * amount;
+                    }
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+    indices
+}
+
+/// GIF flavoured LZW: variable width codes (growing to 12 bits, then starting over), packed
+/// least significant bit first
+fn lzw(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut out = vec![];
+    let mut bits = 0u32;
+    let mut pending = 0u8;
+    let mut emit = |code: u16, size: u8| {
+        bits |= (code as u32) << pending;
+        pending += size;
+        while pending >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            pending -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut size = min_code_size + 1;
+    emit(clear, size);
+
+    let mut indices = indices.iter();
+    if let Some(&first) = indices.next() {
+        let mut prefix = first as u16;
+        for &index in indices {
+            if let Some(&code) = table.get(&(prefix, index)) {
+                prefix = code;
+                continue;
+            }
+
+            emit(prefix, size);
+            if next < 1 << MAX_CODE_SIZE {
+                table.insert((prefix, index), next);
+                next += 1;
+                // The decoder is an entry behind, so it widens a code later than we add one
+                if next > 1 << size && size < MAX_CODE_SIZE {
+                    size += 1;
+                }
+            } else {
+                emit(clear, size);
+                table.clear();
+                next = end + 1;
+                size = min_code_size + 1;
+            }
+            prefix = index as u16;
+        }
+
+        emit(prefix, size);
+        // The decoder adds an entry for that last code too, which can widen the end code
+        if next == 1 << size && size < MAX_CODE_SIZE {
+            size += 1;
+        }
+    }
+
+    emit(end, size);
+    if pending > 0 {
+        out.push(bits as u8);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{canvas::Canvas, colour::Colour};
+
+    use super::{encode, median_cut, GifEncoder, GifError, MAX_COLOURS};
+
+    /// Every frame as RGB bytes, with its palette and delay
+    fn decode(bytes: &[u8]) -> Vec<(Vec<[u8; 3]>, Vec<u8>, u16)> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes).unwrap();
+        let mut frames = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            let pixels = frame.buffer.chunks(4).map(|p| [p[0], p[1], p[2]]).collect();
+            let palette = frame.palette.clone().unwrap();
+            frames.push((pixels, palette, frame.delay));
+        }
+        assert_eq!(decoder.repeat(), gif::Repeat::Infinite);
+        frames
+    }
+
+    fn gradient(width: usize, height: usize) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        for (x, y, c) in canvas.enumerate_pixels_mut() {
+            *c = Colour::new(
+                x as f64 / width as f64,
+                y as f64 / height as f64,
+                (x + y) as f64 / (width + height) as f64,
+            );
+        }
+        canvas
+    }
+
+    #[test]
+    fn two_frames() {
+        let red = Colour::new(1.0, 0.0, 0.0);
+        let blue = Colour::new(0.0, 0.0, 1.0);
+        let mut first = Canvas::new(4, 4);
+        first[(1, 2)] = red;
+        let mut second = Canvas::new_with_colour(4, 4, blue);
+        second[(3, 0)] = Colour::WHITE;
+        second[(0, 3)] = red;
+
+        let mut bytes = vec![];
+        encode(&[first.clone(), second.clone()], 7, &mut bytes).unwrap();
+        let frames = decode(&bytes);
+
+        assert_eq!(frames.len(), 2);
+        for ((pixels, _, delay), canvas) in frames.iter().zip([first, second]) {
+            assert_eq!(*delay, 7);
+            let expected: Vec<_> = canvas.iter().map(|c| c.to_u8()).collect();
+            assert_eq!(*pixels, expected);
+        }
+    }
+
+    #[test]
+    fn loops() {
+        let mut bytes = vec![];
+        encode(&[Canvas::new(1, 1)], 0, &mut bytes).unwrap();
+        assert!(bytes.starts_with(b"GIF89a"));
+        assert!(bytes
+            .windows(19)
+            .any(|w| w == b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00"));
+        assert_eq!(bytes.last(), Some(&0x3b));
+    }
+
+    #[test]
+    fn palette_limits() {
+        // 32 * 32 different colours
+        let canvas = gradient(32, 32);
+        let palette = median_cut(&canvas.iter().map(|c| c.to_u8()).collect::<Vec<_>>());
+        assert_eq!(palette.len(), MAX_COLOURS);
+
+        for dither in [false, true] {
+            let mut encoder = GifEncoder::new(vec![], 0).with_dithering(dither);
+            encoder.add_frame(&canvas).unwrap();
+            let frames = decode(&encoder.finish().unwrap());
+            let (pixels, palette, _) = &frames[0];
+            assert_eq!(palette.len(), MAX_COLOURS * 3);
+
+            // Close, if not exact
+            for (got, wanted) in pixels.iter().zip(canvas.iter()) {
+                let wanted = wanted.to_u8();
+                for c in 0..3 {
+                    assert!(got[c].abs_diff(wanted[c]) < 48, "{got:?} {wanted:?}");
+                }
+            }
+        }
+
+        // Only as big as it needs to be
+        let mut bytes = vec![];
+        let mut two = Canvas::new(3, 1);
+        two[(0, 0)] = Colour::WHITE;
+        encode(&[two], 0, &mut bytes).unwrap();
+        assert_eq!(decode(&bytes)[0].1, [0, 0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn mismatched_frames() {
+        let mut bytes = vec![];
+        let err = encode(
+            &[Canvas::new(4, 4), Canvas::new(4, 4), Canvas::new(4, 3)],
+            0,
+            &mut bytes,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            GifError::SizeMismatch {
+                frame: 2,
+                expected: (4, 4),
+                got: (4, 3)
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "gif frame 2 is 4x3, the first frame is 4x4"
+        );
+    }
+
+    #[test]
+    fn bad_sizes() {
+        assert!(matches!(encode(&[], 0, vec![]), Err(GifError::NoFrames)));
+        assert!(matches!(
+            encode(&[Canvas::new(0, 3)], 0, vec![]),
+            Err(GifError::BadSize {
+                width: 0,
+                height: 3
+            })
+        ));
+        assert!(matches!(
+            encode(&[Canvas::new(70_000, 1)], 0, vec![]),
+            Err(GifError::BadSize { .. })
+        ));
+    }
+
+    #[test]
+    fn long_lzw() {
+        // Enough noise to fill the code table a few times over, with palettes of every size
+        let mut state = 1u32;
+        for bits in 1..=8 {
+            let colours: Vec<Colour> = (0..1 << bits)
+                .map(|i| Colour::from_u8(i as u8, 255 - i as u8, (i * 7) as u8))
+                .collect();
+            let mut canvas = Canvas::new(300, 300);
+            for c in canvas.iter_mut() {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                *c = colours[(state >> 16) as usize % colours.len()];
+            }
+
+            let mut bytes = vec![];
+            encode(&[canvas.clone()], 0, &mut bytes).unwrap();
+            let expected: Vec<_> = canvas.iter().map(|c| c.to_u8()).collect();
+            assert_eq!(decode(&bytes)[0].0, expected, "{bits} bits");
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Just enough PNG to write a canvas out: 8 bit RGB, no filtering, and the image data in
+//! uncompressed ("stored") deflate blocks. Files come out a bit bigger than the PPM, but every
+//! viewer opens them.
+
+use crate::prelude::*;
+
+use super::Rgb8Rows;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// The most a stored deflate block can hold
+const BLOCK: usize = 0xffff;
+
+/// Hands the file to `put` a block of image data at a time, so there's never more than about
+/// 64K of it held here whatever the size of the canvas
+pub(super) fn stream<E>(
+    image: &Rgb8Rows,
+    put: &mut dyn FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut header = vec![];
+    header.extend((image.width as u32).to_be_bytes());
+    header.extend((image.height as u32).to_be_bytes());
+    // 8 bits a channel, RGB, default compression/filtering, not interlaced
+    header.extend([8, 2, 0, 0, 0]);
+
+    put(&SIGNATURE)?;
+    chunk(put, b"IHDR", &header)?;
+
+    // The image data all goes in the one IDAT chunk. Stored blocks are the data with 5 bytes
+    // of framing each (and there's always at least one), so its length is known up front.
+    let raw_len = (image.width * 3 + 1) * image.height;
+    let blocks = raw_len.div_ceil(BLOCK).max(1);
+    put(&((2 + 5 * blocks + raw_len + 4) as u32).to_be_bytes())?;
+    let mut crc = Crc32::default();
+    let mut idat = |bytes: &[u8]| {
+        crc.update(bytes);
+        put(bytes)
+    };
+    idat(b"IDAT")?;
+    // Deflate with a 32K window, no preset dictionary, "fastest"
+    idat(&[0x78, 0x01])?;
+
+    let mut adler = Adler32::default();
+    let mut block = Vec::with_capacity(raw_len.min(BLOCK * 2));
+    let mut left = raw_len;
+    if raw_len == 0 {
+        stored_block(&mut idat, &[], true)?;
+    }
+    // Every row, even for a 0 wide image, which still has its filter bytes
+    for y in 0..image.height {
+        // Every row starts with its filter type, 0 being none
+        block.push(0);
+        (image.row)(y, &mut block);
+        while block.len() >= BLOCK {
+            left -= BLOCK;
+            adler.update(&block[..BLOCK]);
+            stored_block(&mut idat, &block[..BLOCK], left == 0)?;
+            block.drain(..BLOCK);
+        }
+    }
+    if !block.is_empty() {
+        adler.update(&block);
+        stored_block(&mut idat, &block, true)?;
+    }
+    idat(&adler.finish().to_be_bytes())?;
+    put(&crc.finish().to_be_bytes())?;
+
+    chunk(put, b"IEND", &[])
+}
+
+fn chunk<E>(
+    put: &mut dyn FnMut(&[u8]) -> Result<(), E>,
+    kind: &[u8; 4],
+    data: &[u8],
+) -> Result<(), E> {
+    let mut crc = Crc32::default();
+    crc.update(kind);
+    crc.update(data);
+    put(&(data.len() as u32).to_be_bytes())?;
+    put(kind)?;
+    put(data)?;
+    put(&crc.finish().to_be_bytes())
+}
+
+fn stored_block<E>(
+    put: &mut impl FnMut(&[u8]) -> Result<(), E>,
+    data: &[u8],
+    last: bool,
+) -> Result<(), E> {
+    let len = data.len() as u16;
+    put(&[last as u8])?;
+    put(&len.to_le_bytes())?;
+    put(&(!len).to_le_bytes())?;
+    put(data)
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+#[cfg(any(feature = "std", test))]
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::default();
+    crc.update(data);
+    crc.finish()
+}
+
+/// A CRC-32 over data that comes in bits
+struct Crc32(u32);
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Crc32(!0)
+    }
+}
+
+impl Crc32 {
+    fn update(&mut self, data: &[u8]) {
+        self.0 = data.iter().fold(self.0, |c, &b| {
+            CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8)
+        });
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+/// zlib's checksum, also over data that comes in bits
+struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Adler32 { a: 1, b: 0 }
+    }
+}
+
+impl Adler32 {
+    const MOD: u32 = 65521;
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.a = (self.a + byte as u32) % Self::MOD;
+            self.b = (self.b + self.a) % Self::MOD;
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{canvas::Canvas, colour::Colour};
+
+    use super::{crc32, Adler32, BLOCK, SIGNATURE};
+
+    fn adler32(data: &[u8]) -> u32 {
+        let mut adler = Adler32::default();
+        adler.update(data);
+        adler.finish()
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+        assert_eq!(adler32(b""), 1);
+    }
+
+    /// The image data, filter bytes and all, as the IDAT should hold it
+    fn raw(c: &Canvas) -> Vec<u8> {
+        c.rows()
+            .flat_map(|row| [0].into_iter().chain(row.iter().flat_map(|c| c.to_u8())))
+            .collect()
+    }
+
+    /// The IDAT chunk's data, which comes right after the IHDR
+    fn idat(png: &[u8]) -> &[u8] {
+        let len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let crc = u32::from_be_bytes(png[41 + len..45 + len].try_into().unwrap());
+        assert_eq!(crc, crc32(&png[37..41 + len]));
+        &png[41..41 + len]
+    }
+
+    #[test]
+    fn stored_blocks() {
+        // 70,000 bytes of rows, so two blocks with the first ending part way into a row
+        let mut c = Canvas::new(3333, 7);
+        for (x, y, pixel) in c.enumerate_pixels_mut() {
+            *pixel = Colour::new(x as f64 / 3333.0, y as f64 / 7.0, 0.5);
+        }
+        let data = raw(&c);
+        assert_eq!(data.len(), 70_000);
+        let png = c.into_png(false);
+        let z = idat(&png);
+
+        // Header, two blocks with 5 bytes of framing each, then the checksum
+        assert_eq!(z.len(), 2 + 5 + 0xffff + 5 + (70_000 - 0xffff) + 4);
+        assert_eq!(&z[2..7], &[0, 0xff, 0xff, 0, 0]);
+        let second = 7 + 0xffff;
+        assert_eq!(z[second], 1);
+        assert_eq!(&z[7..second], &data[..BLOCK]);
+        assert_eq!(&z[second + 5..z.len() - 4], &data[BLOCK..]);
+        assert_eq!(&z[z.len() - 4..], &adler32(&data).to_be_bytes());
+
+        // Still one (empty, final) block with nothing in it
+        assert_eq!(idat(&Canvas::new(0, 0).into_png(false)).len(), 2 + 5 + 4);
+        // and a 0 wide canvas still has a filter byte for each of its rows
+        assert_eq!(
+            idat(&Canvas::new(0, 3).into_png(false)).len(),
+            2 + 5 + 3 + 4
+        );
+    }
+
+    #[test]
+    fn exact_blocks() {
+        // 1456 * 3 + 1 = 4369 bytes a row, 15 rows of which is exactly one block
+        for (height, blocks) in [(15, 1), (30, 2)] {
+            let c = Canvas::new_with_colour(1456, height, Colour::WHITE);
+            let png = c.into_png(false);
+            let z = idat(&png);
+            assert_eq!(z.len(), 2 + blocks * (5 + BLOCK) + 4);
+            let last = 2 + (blocks - 1) * (5 + BLOCK);
+            assert_eq!(z[last], 1);
+            assert_eq!(&z[z.len() - 4..], &adler32(&raw(&c)).to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn encode() {
+        let mut c = Canvas::new(2, 1);
+        c[(1, 0)] = Colour::new(1.0, 0.5, 0.0);
+        let png = c.into_png(false);
+
+        assert_eq!(&png[..8], &SIGNATURE);
+        // IHDR: length 13, then width 2 and height 1
+        assert_eq!(&png[8..16], &[0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(
+            &png[png.len() - 12..],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+
+        // The one row, uncompressed: filter byte then the two pixels
+        let row = [0, 0, 0, 0, 255, 128, 0];
+        assert!(png.windows(row.len()).any(|w| w == row));
+    }
+}
@@ -0,0 +1,101 @@
+//! Reading PPMs back in, both the plain (P3) and binary (P6) kinds, with any maximum value up
+//! to 65535 and comments wherever the format allows them.
+
+use crate::{canvas::Canvas, colour::Colour, error::RayTracerError, prelude::*};
+
+pub(super) fn decode(data: &[u8]) -> Result<Canvas, RayTracerError> {
+    let invalid = |reason| RayTracerError::InvalidImage { reason };
+    let mut header = Header { data, at: 0 };
+
+    let magic = header.token().ok_or(invalid("empty file"))?;
+    let binary = match magic {
+        b"P3" => false,
+        b"P6" => true,
+        _ => return Err(invalid("not a P3 or P6 PPM")),
+    };
+    let mut number = |what| {
+        header
+            .token()
+            .and_then(|t| core::str::from_utf8(t).ok()?.parse::<usize>().ok())
+            .ok_or(invalid(what))
+    };
+    let width = number("bad width")?;
+    let height = number("bad height")?;
+    let max = number("bad maximum value")?;
+    if !(1..=65535).contains(&max) {
+        return Err(invalid("maximum value must be 1 to 65535"));
+    }
+    let samples = width
+        .checked_mul(height)
+        .and_then(|p| p.checked_mul(3))
+        .ok_or(invalid("impossibly large"))?;
+
+    let values: Vec<usize> = if binary {
+        // Exactly one whitespace byte between the header and the pixels
+        let start = header.at + 1;
+        let wide = max > 255;
+        let raw = samples
+            .checked_mul(if wide { 2 } else { 1 })
+            .and_then(|size| data.get(start..start.checked_add(size)?))
+            .ok_or(invalid("not enough pixel data"))?;
+        if wide {
+            raw.chunks(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                .collect()
+        } else {
+            raw.iter().map(|b| *b as usize).collect()
+        }
+    } else {
+        // Not trusting the header for how much to allocate, every value takes 2 bytes at least
+        let mut values = Vec::with_capacity(samples.min(data.len() / 2));
+        for _ in 0..samples {
+            values.push(number("bad or missing pixel value")?);
+        }
+        values
+    };
+    if values.iter().any(|v| *v > max) {
+        return Err(invalid("pixel value over the maximum"));
+    }
+
+    let mut canvas = Canvas::new(width, height);
+    let scale = max as f64;
+    for (pixel, rgb) in canvas.iter_mut().zip(values.chunks(3)) {
+        *pixel = Colour::new(
+            rgb[0] as f64 / scale,
+            rgb[1] as f64 / scale,
+            rgb[2] as f64 / scale,
+        );
+    }
+    Ok(canvas)
+}
+
+struct Header<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Header<'a> {
+    /// The next whitespace separated token, skipping `#` comments
+    fn token(&mut self) -> Option<&'a [u8]> {
+        loop {
+            match self.data.get(self.at)? {
+                b'#' => {
+                    while self.data.get(self.at).is_some_and(|b| *b != b'\n') {
+                        self.at += 1;
+                    }
+                }
+                b if b.is_ascii_whitespace() => self.at += 1,
+                _ => break,
+            }
+        }
+        let start = self.at;
+        while self
+            .data
+            .get(self.at)
+            .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'#')
+        {
+            self.at += 1;
+        }
+        Some(&self.data[start..self.at])
+    }
+}
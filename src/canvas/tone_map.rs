@@ -0,0 +1,138 @@
+//! Squeezing HDR renders into 0 to 1, see Canvas::tone_mapped
+
+use crate::{canvas::Canvas, colour::Colour};
+
+/// How colours brighter than white are brought back into range. Renders easily go over 1.0
+/// where lights are bright or add up, and the writers just clip that. These all work on linear
+/// colours, before any sRGB encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ToneMap {
+    /// Clip each channel to 0..=1, which is what writing out does anyway
+    #[default]
+    Clamp,
+    /// L / (1 + L) on the luminance, with the colour scaled to match so its hue stays put.
+    /// Nothing ever quite reaches white.
+    Reinhard,
+    /// Reinhard, but luminance `white_point` comes out exactly white (and anything over it
+    /// brighter still, to be clipped).
+    ReinhardExtended { white_point: f64 },
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve, per channel. Gives a bit more
+    /// contrast than Reinhard, and desaturates the very brightest parts.
+    AcesApprox,
+}
+
+impl ToneMap {
+    pub fn apply(&self, colour: Colour) -> Colour {
+        match *self {
+            ToneMap::Clamp => colour.clamp(0.0, 1.0),
+            ToneMap::Reinhard => with_luminance(colour, |l| l / (1.0 + l)),
+            ToneMap::ReinhardExtended { white_point } => with_luminance(colour, |l| {
+                l * (1.0 + l / (white_point * white_point)) / (1.0 + l)
+            }),
+            ToneMap::AcesApprox => colour.map(|x| {
+                let x = x.max(0.0);
+                ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+            }),
+        }
+    }
+}
+
+/// `colour` scaled so its luminance is `curve` of what it was
+fn with_luminance(colour: Colour, curve: impl Fn(f64) -> f64) -> Colour {
+    let colour = colour.map(|c| c.max(0.0));
+    let luminance = colour.luminance();
+    if luminance <= 0.0 {
+        return colour;
+    }
+    colour * (curve(luminance) / luminance)
+}
+
+impl Canvas {
+    /// A copy with every pixel tone mapped by `op`
+    pub fn tone_mapped(&self, op: ToneMap) -> Canvas {
+        let mut out = self.clone();
+        for pixel in out.iter_mut() {
+            *pixel = op.apply(*pixel);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{canvas::Canvas, colour::Colour, math::float};
+
+    use super::ToneMap;
+
+    fn grey(l: f64) -> Colour {
+        Colour::new(l, l, l)
+    }
+
+    #[test]
+    fn reinhard_leaves_dim_colours_alone() {
+        for l in [0.0, 0.001, 0.005, 0.01] {
+            let mapped = ToneMap::Reinhard.apply(grey(l));
+            assert!((mapped.red - l).abs() <= l * l + 1e-12, "{l} {mapped:?}");
+        }
+        assert_eq!(ToneMap::Reinhard.apply(Colour::BLACK), Colour::BLACK);
+    }
+
+    #[test]
+    fn never_quite_white() {
+        let mut last = 0.0;
+        for l in [1.0, 10.0, 100.0, 1e4, 1e8] {
+            for op in [ToneMap::Reinhard, ToneMap::AcesApprox] {
+                let mapped = op.apply(grey(l)).luminance();
+                assert!(mapped <= 1.0, "{op:?} {l} {mapped}");
+            }
+            let mapped = ToneMap::Reinhard.apply(grey(l)).luminance();
+            assert!(mapped < 1.0 && mapped > last, "{l} {mapped}");
+            last = mapped;
+        }
+        assert!(last > 0.999);
+        assert!(ToneMap::AcesApprox.apply(grey(1e8)).red > 0.99);
+    }
+
+    #[test]
+    fn white_point() {
+        for white_point in [1.0, 2.5, 16.0] {
+            let op = ToneMap::ReinhardExtended { white_point };
+            let white = op.apply(grey(white_point));
+            assert!(float::equal(white.luminance(), 1.0), "{white:?}");
+            assert!(op.apply(grey(white_point * 0.5)).luminance() < 1.0);
+        }
+        // Same as plain Reinhard when the white point's far off
+        let far = ToneMap::ReinhardExtended { white_point: 1e9 };
+        assert_eq!(far.apply(grey(3.0)), ToneMap::Reinhard.apply(grey(3.0)));
+    }
+
+    #[test]
+    fn keeps_hue() {
+        let colour = Colour::new(4.0, 2.0, 0.5);
+        for op in [
+            ToneMap::Reinhard,
+            ToneMap::ReinhardExtended { white_point: 3.0 },
+        ] {
+            let mapped = op.apply(colour);
+            assert!(mapped.luminance() < colour.luminance());
+            assert!(float::equal(mapped.red / mapped.green, 2.0), "{op:?}");
+            assert!(float::equal(mapped.green / mapped.blue, 4.0), "{op:?}");
+        }
+    }
+
+    #[test]
+    fn clamp_is_what_gets_written() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas[(0, 0)] = Colour::new(1.5, 0.5, -0.25);
+        canvas[(1, 0)] = Colour::new(0.2, 0.4, 0.6);
+        canvas[(2, 1)] = grey(30.0);
+        let clamped = canvas.tone_mapped(ToneMap::Clamp);
+        assert_eq!(clamped[(0, 0)], Colour::new(1.0, 0.5, 0.0));
+        assert_eq!(clamped[(1, 0)], canvas[(1, 0)]);
+        for encode_srgb in [false, true] {
+            assert_eq!(clamped.into_ppm(encode_srgb), canvas.into_ppm(encode_srgb));
+            assert_eq!(clamped.into_png(encode_srgb), canvas.into_png(encode_srgb));
+        }
+    }
+}
@@ -1,8 +1,18 @@
-use std::ops::{Add, Div, Mul, Sub};
+use core::{
+    borrow::Borrow,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub},
+    str::FromStr,
+};
 
-use crate::math::float::equal;
+use crate::math::{
+    float::equal,
+    tuple::{call_args, parse_triple},
+};
+use crate::prelude::*;
 
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Colour {
     pub red: f64,
     pub green: f64,
@@ -49,23 +59,157 @@ impl Colour {
         Self::new(red as f64, green as f64, blue as f64)
     }
 
+    pub fn from_u8(red: u8, green: u8, blue: u8) -> Self {
+        Self::new(red as f64, green as f64, blue as f64) / 255.0
+    }
+
+    /// Each channel scaled to 0..=255, rounded then clamped. Halves round up, so a channel of
+    /// exactly 0.5 / 255 becomes 1. Everything that writes bytes out goes through this.
+    pub fn to_u8(&self) -> [u8; 3] {
+        self.quantize(255).map(|c| c as u8)
+    }
+
+    /// to_u8, but scaled to 0..=`max` instead, for files with more than 8 bits a channel
+    pub fn quantize(&self, max: u16) -> [u16; 3] {
+        let max = max as f64;
+        [self.red, self.green, self.blue].map(|c| (c * max).round().clamp(0.0, max) as u16)
+    }
+
+    pub fn to_rgba8(&self, alpha: u8) -> [u8; 4] {
+        let [r, g, b] = self.to_u8();
+        [r, g, b, alpha]
+    }
+
+    /// 0xAARRGGBB with the alpha fully opaque, what most framebuffers want
+    pub fn to_u32_argb(&self) -> u32 {
+        let [r, g, b] = self.to_u8();
+        u32::from_be_bytes([0xff, r, g, b])
+    }
+
     pub fn to_ppm(&self) -> String {
-        const MAX_NUM: f64 = 255.0;
-        format!(
-            "{} {} {}",
-            (self.red * MAX_NUM).round().clamp(0.0, MAX_NUM) as u64,
-            (self.green * MAX_NUM).round().clamp(0.0, MAX_NUM) as u64,
-            (self.blue * MAX_NUM).round().clamp(0.0, MAX_NUM) as u64,
+        let [r, g, b] = self.to_u8();
+        format!("{r} {g} {b}")
+    }
+
+    /// `self` at t = 0, `other` at t = 1. t isn't clamped, so outside of 0..=1 it carries on
+    /// past either end.
+    pub fn lerp(&self, other: Colour, t: f64) -> Colour {
+        *self + (other - *self) * t
+    }
+
+    /// Applies `f` to each channel
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Colour {
+        Colour::new(f(self.red), f(self.green), f(self.blue))
+    }
+
+    /// Weighted average of `colours`. The weights don't need to add up to 1, they're divided
+    /// by their total, but there has to be one per colour and the total can't be 0.
+    pub fn mix(colours: &[Colour], weights: &[f64]) -> Result<Colour, String> {
+        if colours.len() != weights.len() {
+            return Err(format!(
+                "{} colours but {} weights",
+                colours.len(),
+                weights.len()
+            ));
+        }
+
+        let total: f64 = weights.iter().sum();
+        if total == 0.0 {
+            return Err("weights add up to 0".to_string());
+        }
+
+        Ok(colours
+            .iter()
+            .zip(weights)
+            .map(|(c, w)| c * w)
+            .sum::<Colour>()
+            / total)
+    }
+
+    /// No NaN or infinite channels
+    pub fn is_finite(&self) -> bool {
+        self.red.is_finite() && self.green.is_finite() && self.blue.is_finite()
+    }
+
+    /// Each channel clamped to min..=max. Panics if min > max, like f64::clamp.
+    pub fn clamp(&self, min: f64, max: f64) -> Colour {
+        assert!(min <= max, "clamp min {min} is above max {max}");
+        self.map(|c| c.clamp(min, max))
+    }
+
+    pub fn max_channel(&self) -> f64 {
+        self.red.max(self.green).max(self.blue)
+    }
+
+    /// Composites `other` on top of `self`, see BlendMode. Both are clamped to 0..=1 first.
+    pub fn blend(&self, other: &Colour, mode: BlendMode) -> Colour {
+        let (base, top) = (self.clamp(0.0, 1.0), other.clamp(0.0, 1.0));
+        let channel = |a: f64, b: f64| match mode {
+            BlendMode::Normal => b,
+            BlendMode::Multiply => a * b,
+            BlendMode::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+            BlendMode::Overlay if a < 0.5 => 2.0 * a * b,
+            BlendMode::Overlay => 1.0 - 2.0 * (1.0 - a) * (1.0 - b),
+            BlendMode::Darken => a.min(b),
+            BlendMode::Lighten => a.max(b),
+        };
+        Colour::new(
+            channel(base.red, top.red),
+            channel(base.green, top.green),
+            channel(base.blue, top.blue),
         )
     }
 
+    /// Encodes with the sRGB transfer function, which is what image viewers expect. The
+    /// renderer itself works in linear light.
+    pub fn linear_to_srgb(&self) -> Colour {
+        self.map(|c| {
+            if c <= 0.0031308 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        })
+    }
+
+    /// Undoes linear_to_srgb, eg for colours picked in an image editor
+    pub fn srgb_to_linear(&self) -> Colour {
+        self.map(|c| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    }
+
+    /// Perceived brightness with the Rec. 709 weights. This is meant for linear values, which
+    /// is what the renderer works in, an sRGB encoded colour would need decoding first.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
+
+    /// A grey with the same luminance
+    pub fn to_greyscale(&self) -> Colour {
+        let l = self.luminance();
+        Colour::new(l, l, l)
+    }
+
+    /// `#rrggbb`, lowercase, with channels clamped to 0..=1 first
+    pub fn to_hex_string(&self) -> String {
+        let [r, g, b] = self.to_u8();
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+
+    /// Same bytes as to_ppm writes as text
     pub fn to_binary_ppm(&self) -> [u8; 3] {
-        const MAX_NUM: f64 = 256.0;
-        [
-            (self.red * MAX_NUM).round().clamp(0.0, MAX_NUM) as u8,
-            (self.green * MAX_NUM).round().clamp(0.0, MAX_NUM) as u8,
-            (self.blue * MAX_NUM).round().clamp(0.0, MAX_NUM) as u8,
-        ]
+        self.to_u8()
+    }
+}
+
+impl From<[u8; 3]> for Colour {
+    fn from([red, green, blue]: [u8; 3]) -> Self {
+        Colour::from_u8(red, green, blue)
     }
 }
 
@@ -75,12 +219,188 @@ impl Mul<i32> for Colour {
         self * rhs as f64
     }
 }
+
+impl Mul<Colour> for f64 {
+    type Output = Colour;
+    fn mul(self, rhs: Colour) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl Mul<Colour> for i32 {
+    type Output = Colour;
+    fn mul(self, rhs: Colour) -> Self::Output {
+        rhs * self
+    }
+}
+
+/// The reference forms of the above, for adding up colours without copying them out first
+macro_rules! by_ref {
+    ($trait:ident, $funcname:ident, $lhs:ty, $rhs:ty) => {
+        impl $trait<$rhs> for &$lhs {
+            type Output = Colour;
+            fn $funcname(self, rhs: $rhs) -> Self::Output {
+                (*self).$funcname(rhs)
+            }
+        }
+
+        impl $trait<&$rhs> for $lhs {
+            type Output = Colour;
+            fn $funcname(self, rhs: &$rhs) -> Self::Output {
+                self.$funcname(*rhs)
+            }
+        }
+
+        impl $trait<&$rhs> for &$lhs {
+            type Output = Colour;
+            fn $funcname(self, rhs: &$rhs) -> Self::Output {
+                (*self).$funcname(*rhs)
+            }
+        }
+    };
+}
+
+by_ref!(Add, add, Colour, Colour);
+by_ref!(Sub, sub, Colour, Colour);
+by_ref!(Mul, mul, Colour, Colour);
+by_ref!(Mul, mul, Colour, f64);
+by_ref!(Div, div, Colour, f64);
+by_ref!(Mul, mul, f64, Colour);
+
+impl AddAssign for Colour {
+    fn add_assign(&mut self, rhs: Colour) {
+        *self = *self + rhs;
+    }
+}
+
+impl AddAssign<&Colour> for Colour {
+    fn add_assign(&mut self, rhs: &Colour) {
+        *self = *self + *rhs;
+    }
+}
+
+impl MulAssign<f64> for Colour {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = *self * rhs;
+    }
+}
+
+impl DivAssign<f64> for Colour {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
+
+impl Sum for Colour {
+    fn sum<I: Iterator<Item = Colour>>(iter: I) -> Self {
+        iter.fold(Colour::BLACK, |acc, c| acc + c)
+    }
+}
+
+impl<'a> Sum<&'a Colour> for Colour {
+    fn sum<I: Iterator<Item = &'a Colour>>(iter: I) -> Self {
+        iter.fold(Colour::BLACK, |acc, c| acc + *c)
+    }
+}
+
+impl Colour {
+    /// The mean of `colours`, or black if there aren't any. Exactly the sum divided by the
+    /// count, as adding them up by hand would give.
+    pub fn average<C: Borrow<Colour>>(colours: impl IntoIterator<Item = C>) -> Colour {
+        let (total, count) = colours
+            .into_iter()
+            .fold((Colour::BLACK, 0), |(total, count), c| {
+                (total + *c.borrow(), count + 1)
+            });
+        if count == 0 {
+            Colour::BLACK
+        } else {
+            total / count as f64
+        }
+    }
+}
+
 impl PartialEq for Colour {
     fn eq(&self, other: &Self) -> bool {
         equal(self.red, other.red) && equal(self.green, other.green) && equal(self.blue, other.blue)
     }
 }
 
+/// How Colour::blend puts one colour on top of another, per channel, with a the bottom colour
+/// and b the top. These are the usual image editor ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// b
+    #[default]
+    Normal,
+    /// a * b, darkens
+    Multiply,
+    /// 1 - (1 - a) * (1 - b), lightens
+    Screen,
+    /// Multiply where a < 0.5, screen elsewhere, each doubled
+    Overlay,
+    /// min(a, b)
+    Darken,
+    /// max(a, b)
+    Lighten,
+}
+
+/// Parses `#rgb`, `#rrggbb`, `#rrggbbaa` (the alpha is ignored), `rrggbb` or the name of one
+/// of the constants below, ignoring case.
+impl FromStr for Colour {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        match lower.as_str() {
+            "red" => return Ok(Colour::RED),
+            "green" => return Ok(Colour::GREEN),
+            "blue" => return Ok(Colour::BLUE),
+            "black" => return Ok(Colour::BLACK),
+            "white" => return Ok(Colour::WHITE),
+            _ => {}
+        }
+
+        // Channels as plain numbers, `rgb(r, g, b)`, `r, g, b` or `r g b`
+        let components = call_args(&lower, "rgb").or_else(|| {
+            (lower.contains(',') || lower.contains(char::is_whitespace)).then_some(lower.as_str())
+        });
+        if let Some(components) = components {
+            let [r, g, b] = parse_triple(components, ["red", "green", "blue"])?;
+            return Ok(Colour::new(r, g, b));
+        }
+
+        let digits = match lower.strip_prefix('#') {
+            Some(digits) => digits,
+            None if lower.len() == 6 && lower.bytes().all(|b| b.is_ascii_hexdigit()) => &lower,
+            None => {
+                return Err(format!(
+                    "unknown colour {s:?}, expected a name, #rrggbb or r, g, b"
+                ))
+            }
+        };
+        if let Some(bad) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(format!("{bad:?} in colour {s:?} isn't a hex digit"));
+        }
+
+        let channel = |hex: &str| u8::from_str_radix(hex, 16).unwrap() as f64 / 255.0;
+        match digits.len() {
+            // #rgb is short for #rrggbb
+            3 => {
+                let [r, g, b] = [0, 1, 2].map(|i| channel(&digits[i..=i].repeat(2)));
+                Ok(Colour::new(r, g, b))
+            }
+            6 | 8 => Ok(Colour::new(
+                channel(&digits[0..2]),
+                channel(&digits[2..4]),
+                channel(&digits[4..6]),
+            )),
+            n => Err(format!(
+                "colour {s:?} has {n} hex digits, expected 3, 6 or 8"
+            )),
+        }
+    }
+}
+
 /// Namespaced colour defaults for ease of use
 impl Colour {
     pub const RED: Colour = Colour::newi(1, 0, 0);
@@ -121,6 +441,410 @@ mod test {
         assert_eq!(Colour::new(0.2, 0.3, 0.4) * 2, Colour::new(0.4, 0.6, 0.8));
     }
 
+    #[test]
+    fn mul_scalar_left() {
+        let c = Colour::new(0.2, 0.3, 0.4);
+        assert_eq!(2.0 * c, c * 2.0);
+        assert_eq!(2 * c, c * 2);
+        assert_eq!(-1 * c, Colour::new(-0.2, -0.3, -0.4));
+    }
+
+    mod sums {
+        use crate::colour::Colour;
+
+        /// Bit for bit, not within float::EPSILON like ==
+        fn exactly(a: Colour, b: Colour) -> bool {
+            (a.red, a.green, a.blue) == (b.red, b.green, b.blue)
+        }
+
+        const SAMPLES: [Colour; 4] = [
+            Colour::new(0.1, 0.2, 0.3),
+            Colour::new(0.7, 0.0, 1.0),
+            Colour::new(0.25, 0.5, 0.125),
+            Colour::new(1.0 / 3.0, 0.9, 0.05),
+        ];
+
+        // Taking the references is the point, this is checking they match the owned forms
+        #[allow(clippy::op_ref)]
+        #[test]
+        fn by_ref() {
+            let (a, b) = (SAMPLES[0], SAMPLES[3]);
+            for c in [&a + b, a + &b, &a + &b] {
+                assert!(exactly(c, a + b));
+            }
+            for c in [&a - b, a - &b, &a - &b] {
+                assert!(exactly(c, a - b));
+            }
+            for c in [&a * b, a * &b, &a * &b] {
+                assert!(exactly(c, a * b));
+            }
+            for c in [&a * 0.3, a * &0.3, &a * &0.3, 0.3 * &a, &0.3 * a, &0.3 * &a] {
+                assert!(exactly(c, a * 0.3));
+            }
+            for c in [&a / 3.0, a / &3.0, &a / &3.0] {
+                assert!(exactly(c, a / 3.0));
+            }
+        }
+
+        #[test]
+        fn compound_assignment() {
+            let (a, b) = (SAMPLES[1], SAMPLES[2]);
+            let mut c = a;
+            c += b;
+            assert!(exactly(c, a + b));
+            c += &b;
+            assert!(exactly(c, a + b + b));
+
+            let mut c = a;
+            c *= 0.3;
+            assert!(exactly(c, a * 0.3));
+            c /= 7.0;
+            assert!(exactly(c, a * 0.3 / 7.0));
+        }
+
+        #[test]
+        fn sum() {
+            let by_hand = SAMPLES[0] + SAMPLES[1] + SAMPLES[2] + SAMPLES[3];
+            assert!(exactly(SAMPLES.into_iter().sum(), by_hand));
+            assert!(exactly(SAMPLES.iter().sum(), by_hand));
+            assert_eq!(by_hand, Colour::new(7.0 / 3.0 - 0.95, 1.6, 1.475));
+            assert!(exactly([Colour::WHITE; 0].iter().sum(), Colour::BLACK));
+        }
+
+        #[test]
+        fn average() {
+            let by_hand = (SAMPLES[0] + SAMPLES[1] + SAMPLES[2] + SAMPLES[3]) / 4.0;
+            assert!(exactly(Colour::average(SAMPLES), by_hand));
+            assert!(exactly(Colour::average(SAMPLES), by_hand));
+            assert_eq!(
+                Colour::average([Colour::RED, Colour::BLUE]),
+                Colour::new(0.5, 0.0, 0.5)
+            );
+            assert_eq!(Colour::average([Colour::WHITE]), Colour::WHITE);
+            // Nothing to average is black, rather than NaN from 0 / 0
+            assert!(exactly(
+                Colour::average(Vec::<Colour>::new()),
+                Colour::BLACK
+            ));
+        }
+    }
+
+    mod helpers {
+        use crate::colour::Colour;
+
+        #[test]
+        fn lerp() {
+            let a = Colour::new(0.2, 0.4, 0.6);
+            let b = Colour::new(1.0, 0.0, 0.2);
+
+            assert_eq!(a.lerp(b, 0.0), a);
+            assert_eq!(a.lerp(b, 1.0), b);
+            assert_eq!(a.lerp(b, 0.5), Colour::new(0.6, 0.2, 0.4));
+            // Not clamped
+            assert_eq!(a.lerp(b, 2.0), Colour::new(1.8, -0.4, -0.2));
+        }
+
+        #[test]
+        fn map_gamma() {
+            let c = Colour::new(0.25, 1.0, 0.0).map(|v| v.powf(1.0 / 2.0));
+            assert_eq!(c, Colour::new(0.5, 1.0, 0.0));
+        }
+
+        #[test]
+        fn mix() {
+            let colours = [Colour::RED, Colour::GREEN, Colour::BLUE];
+
+            let uniform = Colour::mix(&colours, &[1.0, 1.0, 1.0]).unwrap();
+            assert_eq!(uniform, Colour::WHITE / 3.0);
+
+            let weighted = Colour::mix(&colours, &[2.0, 1.0, 1.0]).unwrap();
+            assert_eq!(weighted, Colour::new(0.5, 0.25, 0.25));
+        }
+
+        #[test]
+        fn mix_errors() {
+            let colours = [Colour::RED, Colour::GREEN];
+
+            assert_eq!(
+                Colour::mix(&colours, &[1.0]),
+                Err("2 colours but 1 weights".to_string())
+            );
+            assert!(Colour::mix(&colours, &[1.0, -1.0]).is_err());
+        }
+    }
+
+    mod validation {
+        use crate::colour::Colour;
+
+        #[test]
+        fn finite() {
+            assert!(Colour::new(0.5, 100.0, -3.0).is_finite());
+            assert!(!Colour::new(f64::NAN, 0.0, 0.0).is_finite());
+            assert!(!Colour::new(0.0, f64::INFINITY, 0.0).is_finite());
+            assert!(!Colour::new(0.0, 0.0, f64::NEG_INFINITY).is_finite());
+        }
+
+        #[test]
+        fn clamp() {
+            let c = Colour::new(-1.0, 0.5, 7.0);
+            assert_eq!(c.clamp(0.0, 1.0), Colour::new(0.0, 0.5, 1.0));
+            assert_eq!(c.clamp(0.0, 10.0), Colour::new(0.0, 0.5, 7.0));
+        }
+
+        #[test]
+        #[should_panic = "min 1 is above max 0"]
+        fn clamp_inverted() {
+            Colour::WHITE.clamp(1.0, 0.0);
+        }
+
+        #[test]
+        fn max_channel() {
+            assert_eq!(Colour::new(0.2, 0.9, 0.4).max_channel(), 0.9);
+            assert_eq!(Colour::new(-2.0, -1.0, -3.0).max_channel(), -1.0);
+        }
+    }
+
+    mod blend {
+        use crate::colour::{BlendMode, Colour};
+
+        const MODES: [BlendMode; 6] = [
+            BlendMode::Normal,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Overlay,
+            BlendMode::Darken,
+            BlendMode::Lighten,
+        ];
+
+        #[test]
+        fn identities() {
+            let c = Colour::new(0.2, 0.5, 0.9);
+
+            assert_eq!(c.blend(&Colour::WHITE, BlendMode::Multiply), c);
+            assert_eq!(c.blend(&Colour::BLACK, BlendMode::Screen), c);
+            assert_eq!(Colour::BLACK.blend(&c, BlendMode::Normal), c);
+            assert_eq!(c.blend(&Colour::WHITE, BlendMode::Darken), c);
+            assert_eq!(c.blend(&Colour::BLACK, BlendMode::Lighten), c);
+        }
+
+        #[test]
+        fn symmetric() {
+            let a = Colour::new(0.2, 0.5, 0.9);
+            let b = Colour::new(0.7, 0.1, 0.4);
+            for mode in [
+                BlendMode::Multiply,
+                BlendMode::Screen,
+                BlendMode::Darken,
+                BlendMode::Lighten,
+            ] {
+                assert_eq!(a.blend(&b, mode), b.blend(&a, mode), "{mode:?}");
+            }
+        }
+
+        #[test]
+        fn overlay() {
+            let top = Colour::WHITE * 0.5;
+
+            // Overlaying mid grey leaves things as they are, on either side of 0.5
+            for a in [0.0, 0.25, 0.49, 0.5, 0.75, 1.0] {
+                let base = Colour::WHITE * a;
+                assert_eq!(base.blend(&top, BlendMode::Overlay), base);
+            }
+
+            let top = Colour::WHITE * 0.8;
+            assert_eq!(
+                (Colour::WHITE * 0.4).blend(&top, BlendMode::Overlay),
+                Colour::WHITE * 0.64
+            );
+            assert_eq!(
+                (Colour::WHITE * 0.5).blend(&top, BlendMode::Overlay),
+                Colour::WHITE * 0.8
+            );
+        }
+
+        #[test]
+        fn clamps_inputs() {
+            let bright = Colour::new(3.0, -1.0, 0.5);
+            for mode in MODES {
+                assert_eq!(
+                    bright.blend(&Colour::WHITE, mode),
+                    Colour::new(1.0, 0.0, 0.5).blend(&Colour::WHITE, mode)
+                );
+            }
+        }
+    }
+
+    mod srgb {
+        use crate::colour::Colour;
+
+        #[test]
+        fn breakpoints() {
+            let below = Colour::WHITE * 0.003;
+            assert_eq!(below.linear_to_srgb(), below * 12.92);
+
+            let c = Colour::WHITE * 0.0031308;
+            assert!((c.linear_to_srgb().red - 0.04045).abs() < 1e-4);
+            assert_eq!(Colour::WHITE.linear_to_srgb(), Colour::WHITE);
+            assert_eq!(Colour::BLACK.linear_to_srgb(), Colour::BLACK);
+
+            let below = Colour::WHITE * 0.04;
+            assert_eq!(below.srgb_to_linear(), below / 12.92);
+        }
+
+        #[test]
+        fn mid_grey() {
+            let encoded = (Colour::WHITE * 0.5).linear_to_srgb();
+            assert!((encoded.red - 0.7354).abs() < 1e-4, "{encoded:?}");
+        }
+
+        #[test]
+        fn round_trip() {
+            for i in 0..=100 {
+                let c = Colour::new(i as f64 / 100.0, 1.0 - i as f64 / 100.0, 0.5);
+                let there_and_back = c.linear_to_srgb().srgb_to_linear();
+                assert!((there_and_back.red - c.red).abs() < 1e-12);
+                assert!((there_and_back.green - c.green).abs() < 1e-12);
+            }
+        }
+    }
+
+    mod luminance {
+        use crate::colour::Colour;
+
+        #[test]
+        fn weights() {
+            assert_eq!(Colour::WHITE.luminance(), 1.0);
+            assert_eq!(Colour::BLACK.luminance(), 0.0);
+            assert_eq!(Colour::RED.luminance(), 0.2126);
+            assert_eq!(Colour::GREEN.luminance(), 0.7152);
+            assert_eq!(Colour::BLUE.luminance(), 0.0722);
+        }
+
+        #[test]
+        fn greyscale() {
+            let grey = Colour::new(0.3, 0.3, 0.3);
+            assert_eq!(grey.to_greyscale(), grey);
+
+            let c = Colour::new(0.5, 0.25, 1.0).to_greyscale();
+            assert_eq!(c, Colour::WHITE * (0.1063 + 0.1788 + 0.0722));
+        }
+    }
+
+    mod bytes {
+        use crate::colour::Colour;
+
+        #[test]
+        fn round_trip() {
+            for v in 0..=255 {
+                let c = Colour::from_u8(v, 255 - v, v / 2);
+                assert_eq!(c.to_u8(), [v, 255 - v, v / 2]);
+                assert_eq!(Colour::from([v, 255 - v, v / 2]), c);
+            }
+        }
+
+        #[test]
+        fn clamped() {
+            assert_eq!(Colour::new(-0.5, 1.5, f64::INFINITY).to_u8(), [0, 255, 255]);
+        }
+
+        #[test]
+        fn halves_round_up() {
+            let c = Colour::new(0.5, 1.5, 254.5) / 255.0;
+            assert_eq!(c.to_u8(), [1, 2, 255]);
+
+            let just_under = Colour::new(0.49, 1.49, 254.49) / 255.0;
+            assert_eq!(just_under.to_u8(), [0, 1, 254]);
+        }
+
+        #[test]
+        fn quantize() {
+            let c = Colour::new(0.5, -1.0, 2.0);
+            assert_eq!(c.quantize(65535), [32768, 0, 65535]);
+            assert_eq!(c.quantize(1023), [512, 0, 1023]);
+            assert_eq!(c.quantize(255), c.to_u8().map(u16::from));
+        }
+
+        #[test]
+        fn packing() {
+            let c = Colour::from_u8(0x12, 0x34, 0x56);
+
+            assert_eq!(c.to_rgba8(0x78), [0x12, 0x34, 0x56, 0x78]);
+            assert_eq!(c.to_u32_argb(), 0xff123456);
+        }
+
+        #[test]
+        fn agrees_with_ppm() {
+            for i in -10..=1010 {
+                let v = i as f64 / 1000.0;
+                let c = Colour::new(v, 1.0 - v, v * 0.5);
+                let [r, g, b] = c.to_u8();
+                assert_eq!(c.to_ppm(), format!("{r} {g} {b}"));
+            }
+        }
+    }
+
+    mod parse {
+        use crate::colour::Colour;
+
+        #[test]
+        fn hex() {
+            let orange = Colour::new(1.0, 0x88 as f64 / 255.0, 0.0);
+
+            assert_eq!("#ff8800".parse(), Ok(orange));
+            assert_eq!("ff8800".parse(), Ok(orange));
+            assert_eq!("#f80".parse(), Ok(orange));
+            assert_eq!("#ff880080".parse(), Ok(orange));
+            assert_eq!("  #FF8800 ".parse(), Ok(orange));
+        }
+
+        #[test]
+        fn names() {
+            assert_eq!("red".parse(), Ok(Colour::RED));
+            assert_eq!("Green".parse(), Ok(Colour::GREEN));
+            assert_eq!("BLUE".parse(), Ok(Colour::BLUE));
+            assert_eq!("black".parse(), Ok(Colour::BLACK));
+            assert_eq!("white".parse(), Ok(Colour::WHITE));
+        }
+
+        #[test]
+        fn components() {
+            let c = Colour::new(1.0, 0.5, 0.25);
+
+            assert_eq!("rgb(1, 0.5, 0.25)".parse(), Ok(c));
+            assert_eq!("RGB( 1,0.5,0.25 )".parse(), Ok(c));
+            assert_eq!("1, 0.5, 0.25".parse(), Ok(c));
+            assert_eq!("1 0.5 2.5e-1".parse(), Ok(c));
+            // Not clamped, same as Colour::new
+            assert_eq!("2, -1, 0".parse(), Ok(Colour::new(2.0, -1.0, 0.0)));
+
+            let err = "rgb(1, 0.5, blue)".parse::<Colour>().unwrap_err();
+            assert!(err.starts_with("blue in"), "{err}");
+            let err = "1 0.5".parse::<Colour>().unwrap_err();
+            assert!(err.contains("(red, green, blue)"), "{err}");
+        }
+
+        #[test]
+        fn round_trip() {
+            for hex in ["#000000", "#ffffff", "#ff8800", "#0a1b2c", "#7f8081"] {
+                let c: Colour = hex.parse().unwrap();
+                assert_eq!(c.to_hex_string(), hex);
+            }
+            assert_eq!(Colour::new(-1.0, 0.5, 2.0).to_hex_string(), "#0080ff");
+        }
+
+        #[test]
+        fn errors() {
+            let err = |s: &str| s.parse::<Colour>().unwrap_err();
+
+            assert!(err("#ff88").contains("4 hex digits"), "{}", err("#ff88"));
+            assert!(err("#ff880").contains("5 hex digits"));
+            assert!(err("#gg8800").contains("'g'"));
+            assert!(err("purple").contains("unknown colour"));
+            assert!(err("fff").contains("unknown colour"));
+            assert!(err("").contains("unknown colour"));
+        }
+    }
+
     mod ppm {
         use crate::colour::Colour;
 
@@ -134,5 +858,16 @@ mod test {
             let c = Colour::new(-1.0, 0.5, 2.0);
             assert_eq!(c.to_ppm(), "0 128 255")
         }
+
+        #[test]
+        fn binary() {
+            assert_eq!(Colour::WHITE.to_binary_ppm(), [255, 255, 255]);
+            assert_eq!((Colour::WHITE * 0.5).to_binary_ppm(), [128, 128, 128]);
+            assert_eq!(Colour::new(-1.0, -0.001, 0.0).to_binary_ppm(), [0, 0, 0]);
+            assert_eq!(
+                Colour::new(1.001, 2.0, 1e9).to_binary_ppm(),
+                [255, 255, 255]
+            );
+        }
     }
 }
@@ -0,0 +1,96 @@
+//! The error type for things that used to assert/expect their way out.
+
+use core::fmt;
+
+use crate::{intersection::ObjectId, math::tuple::Tuple, world::ValidationIssue};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RayTracerError {
+    /// `what` should have been a point (w = 1)
+    NotAPoint { what: &'static str, got: Tuple },
+    /// `what` should have been a vector (w = 0)
+    NotAVector { what: &'static str, got: Tuple },
+    /// `what` had to be normalized, but has no length (or an infinite/NaN one)
+    ZeroLengthVector { what: &'static str, got: Tuple },
+    /// `what`'s transform has no inverse, so there's no way back to its local space
+    NonInvertibleTransform { what: &'static str },
+    /// `what`'s radius is zero, negative or not a number, which turns its normals inside out
+    /// (or into NaN)
+    InvalidRadius { what: &'static str, got: f64 },
+    /// A CameraConfig that can't make a usable camera
+    InvalidCamera { reason: &'static str },
+    /// Matrix data that doesn't fill a width x height matrix
+    MatrixDimensions {
+        width: usize,
+        height: usize,
+        len: usize,
+    },
+    /// Image data that couldn't be read, see Canvas::from_ppm
+    InvalidImage { reason: &'static str },
+    /// A row buffer that isn't exactly one row long, see Camera::render_row
+    RowLength { expected: usize, got: usize },
+    /// A row past the bottom of the image
+    RowOutOfRange { row: usize, height: usize },
+    /// A PixelSurface that isn't the size of the image, see Camera::render_to
+    SurfaceSize {
+        expected: (usize, usize),
+        got: (usize, usize),
+    },
+    /// An object in the World that can't be rendered, see World::validate
+    BadObject {
+        object: ObjectId,
+        issue: ValidationIssue,
+    },
+}
+
+impl fmt::Display for RayTracerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RayTracerError::NotAPoint { what, got } => {
+                write!(f, "{what} must be a point, got {got:?}")
+            }
+            RayTracerError::NotAVector { what, got } => {
+                write!(f, "{what} must be a vector, got {got:?}")
+            }
+            RayTracerError::ZeroLengthVector { what, got } => {
+                write!(f, "{what} can't be normalized, got {got:?}")
+            }
+            RayTracerError::NonInvertibleTransform { what } => {
+                write!(f, "{what} transform is not invertible")
+            }
+            RayTracerError::InvalidRadius { what, got } => {
+                write!(f, "{what} radius must be positive and finite, got {got}")
+            }
+            RayTracerError::InvalidCamera { reason } => write!(f, "invalid camera: {reason}"),
+            RayTracerError::MatrixDimensions { width, height, len } => write!(
+                f,
+                "a {width}x{height} matrix needs {} values, got {len}",
+                width * height
+            ),
+            RayTracerError::InvalidImage { reason } => write!(f, "invalid image: {reason}"),
+            RayTracerError::RowLength { expected, got } => {
+                write!(f, "a row is {expected} pixels, got a buffer of {got}")
+            }
+            RayTracerError::RowOutOfRange { row, height } => {
+                write!(f, "row {row} is outside an image {height} high")
+            }
+            RayTracerError::SurfaceSize { expected, got } => write!(
+                f,
+                "a {}x{} image can't go on a {}x{} surface",
+                expected.0, expected.1, got.0, got.1
+            ),
+            RayTracerError::BadObject { object, issue } => {
+                let problem = match issue {
+                    ValidationIssue::NonFiniteTransform { .. } => "a non-finite transform",
+                    ValidationIssue::NonInvertibleTransform { .. } => "a non-invertible transform",
+                    ValidationIssue::BadMaterial { error, .. } => {
+                        return write!(f, "object {}: {error}", object.index());
+                    }
+                };
+                write!(f, "object {} has {problem}", object.index())
+            }
+        }
+    }
+}
+
+impl core::error::Error for RayTracerError {}
@@ -0,0 +1,632 @@
+//! glTF 2.0 loading (.gltf with embedded or external buffers, and .glb), only meshes made of
+//! triangles. Sparse accessors, animation, skins, textures and cameras are all ignored.
+
+use std::{fmt, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+    colour::Colour,
+    materials::Material,
+    math::{matrix::Matrix, tuple::Tuple},
+    mesh::{face_normal, Mesh},
+    pattern::solid::SolidPattern,
+    trace,
+};
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The file is valid JSON (or GLB) but not a glTF file we can make sense of
+    Invalid(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io(e) => write!(f, "reading gltf: {e}"),
+            GltfError::Json(e) => write!(f, "gltf json: {e}"),
+            GltfError::Invalid(message) => write!(f, "invalid gltf: {message}"),
+            GltfError::Unsupported(message) => write!(f, "unsupported gltf: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GltfError::Io(e) => Some(e),
+            GltfError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GltfError {
+    fn from(value: std::io::Error) -> Self {
+        GltfError::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for GltfError {
+    fn from(value: serde_json::Error) -> Self {
+        GltfError::Json(value)
+    }
+}
+
+fn invalid(message: impl Into<String>) -> GltfError {
+    GltfError::Invalid(message.into())
+}
+
+/// Every triangle primitive in the default scene, with the transform of the node it's under
+/// (parents included) and its material. Turn them into shapes with Mesh::into_group, then
+/// set_transform and set_material.
+///
+/// Only baseColorFactor's rgb is used for colour. The rest of pbrMetallicRoughness is guessed
+/// at, with r = roughnessFactor and m = metallicFactor:
+///
+/// - specular 0.1 + 0.9 * (1 - r)
+/// - shininess 10 + 290 * (1 - r), as Material::metal
+/// - diffuse 0.9 - 0.6 * m, so the default for non metals and Material::metal's for metals
+/// - reflective 0.8 * m * (1 - r)
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<(Mesh, Matrix, Material)>, GltfError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path)?;
+    from_bytes(&data, path.parent())
+}
+
+/// `dir` is where relative buffer URIs are looked for.
+fn from_bytes(data: &[u8], dir: Option<&Path>) -> Result<Vec<(Mesh, Matrix, Material)>, GltfError> {
+    trace::span!(DEBUG, "load_gltf", bytes = data.len());
+    let (json, bin) = if data.starts_with(b"glTF") {
+        split_glb(data)?
+    } else {
+        (data, None)
+    };
+    let doc: Value = serde_json::from_slice(json)?;
+
+    let buffers = array(&doc["buffers"])
+        .iter()
+        .map(|buffer| match buffer["uri"].as_str() {
+            Some(uri) => match uri.strip_prefix("data:") {
+                Some(data_uri) => {
+                    let (_, encoded) = data_uri
+                        .split_once(";base64,")
+                        .ok_or_else(|| invalid("only base64 data URIs are supported"))?;
+                    decode_base64(encoded)
+                }
+                None => Ok(std::fs::read(dir.unwrap_or(Path::new(".")).join(uri))?),
+            },
+            None => bin
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| invalid("buffer without a uri and no GLB binary chunk")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let loader = Loader { doc: &doc, buffers };
+    let roots: Vec<usize> = match doc["scenes"].get(doc["scene"].as_u64().unwrap_or(0) as usize) {
+        Some(scene) => indices(&scene["nodes"])?,
+        // No scenes, so everything that isn't somebody's child
+        None => {
+            let nodes = array(&doc["nodes"]).len();
+            let mut children = vec![];
+            for node in array(&doc["nodes"]) {
+                children.extend(indices(&node["children"])?);
+            }
+            (0..nodes).filter(|n| !children.contains(n)).collect()
+        }
+    };
+
+    let mut out = vec![];
+    for root in roots {
+        loader.node(root, &Matrix::default(), &mut out, 0)?;
+    }
+
+    Ok(out)
+}
+
+/// The JSON and BIN chunks of a .glb
+fn split_glb(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    let u32_at = |at: usize| {
+        data.get(at..at + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| invalid("glb truncated"))
+    };
+    if u32_at(4)? != 2 {
+        return Err(GltfError::Unsupported(format!(
+            "glb version {}",
+            u32_at(4)?
+        )));
+    }
+
+    let mut chunks = vec![];
+    let mut at = 12;
+    while at < data.len().min(u32_at(8)?) {
+        let (length, kind) = (u32_at(at)?, u32_at(at + 4)?);
+        let chunk = data
+            .get(at + 8..at + 8 + length)
+            .ok_or_else(|| invalid("glb chunk truncated"))?;
+        chunks.push((kind, chunk));
+        at += 8 + length;
+    }
+
+    const JSON: usize = 0x4E4F534A;
+    const BIN: usize = 0x004E4942;
+    let find = |wanted| chunks.iter().find(|(k, _)| *k == wanted).map(|(_, c)| *c);
+    let json = find(JSON).ok_or_else(|| invalid("glb without a JSON chunk"))?;
+    Ok((json, find(BIN)))
+}
+
+struct Loader<'a> {
+    doc: &'a Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Loader<'_> {
+    fn node(
+        &self,
+        index: usize,
+        parent: &Matrix,
+        out: &mut Vec<(Mesh, Matrix, Material)>,
+        depth: usize,
+    ) -> Result<(), GltfError> {
+        // Nodes are meant to be a tree, this stops a cycle going forever
+        if depth > 64 {
+            return Err(invalid("node hierarchy too deep, is there a cycle?"));
+        }
+        let node = self.get("nodes", index)?;
+        let transform = parent * &node_transform(node)?;
+
+        if let Some(mesh) = node["mesh"].as_u64() {
+            let mesh = self.get("meshes", mesh as usize)?;
+            for primitive in array(&mesh["primitives"]) {
+                // 4 is TRIANGLES, and the default
+                if primitive["mode"].as_u64().unwrap_or(4) != 4 {
+                    continue;
+                }
+                out.push((
+                    self.primitive(primitive)?,
+                    transform.clone(),
+                    self.material(&primitive["material"])?,
+                ));
+            }
+        }
+
+        for child in indices(&node["children"])? {
+            self.node(child, &transform, out, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+    fn get(&self, kind: &str, index: usize) -> Result<&Value, GltfError> {
+        self.doc[kind]
+            .get(index)
+            .ok_or_else(|| invalid(format!("no {kind} {index}")))
+    }
+
+    fn primitive(&self, primitive: &Value) -> Result<Mesh, GltfError> {
+        let attributes = &primitive["attributes"];
+        let position = attributes["POSITION"]
+            .as_u64()
+            .ok_or_else(|| invalid("primitive without POSITION"))?;
+
+        let mut mesh = Mesh::default();
+        for p in self.accessor(position as usize, 3)?.chunks_exact(3) {
+            mesh.vertices.push(Tuple::point(p[0], p[1], p[2]));
+        }
+        if let Some(normal) = attributes["NORMAL"].as_u64() {
+            for n in self.accessor(normal as usize, 3)?.chunks_exact(3) {
+                mesh.normals
+                    .push(Tuple::vector(n[0], n[1], n[2]).normalize());
+            }
+        }
+
+        let corners: Vec<usize> = match primitive["indices"].as_u64() {
+            Some(accessor) => self
+                .accessor(accessor as usize, 1)?
+                .into_iter()
+                .map(|i| i as usize)
+                .collect(),
+            None => (0..mesh.vertices.len()).collect(),
+        };
+        for face in corners.chunks_exact(3) {
+            let face = [face[0], face[1], face[2]];
+            if face.iter().any(|&i| i >= mesh.vertices.len()) {
+                return Err(invalid(format!("index out of range in {face:?}")));
+            }
+            // Faces with no area are dropped
+            if let Some(normal) = face_normal(face.map(|i| mesh.vertices[i])) {
+                mesh.faces.push(face);
+                mesh.face_normals.push(normal);
+            }
+        }
+
+        Ok(mesh)
+    }
+
+    /// The accessor's values, flattened. `components` is how many each element should have.
+    fn accessor(&self, index: usize, components: usize) -> Result<Vec<f64>, GltfError> {
+        let accessor = self.get("accessors", index)?;
+        if accessor.get("sparse").is_some() {
+            return Err(GltfError::Unsupported("sparse accessors".to_string()));
+        }
+
+        let expected = match components {
+            1 => "SCALAR",
+            3 => "VEC3",
+            _ => unreachable!("only scalars and vec3s are read"),
+        };
+        if accessor["type"].as_str() != Some(expected) {
+            return Err(invalid(format!("accessor {index} should be {expected}")));
+        }
+
+        let component_type = accessor["componentType"].as_u64().unwrap_or(0);
+        let size = match component_type {
+            5120 | 5121 => 1, // (unsigned) byte
+            5122 | 5123 => 2, // (unsigned) short
+            5125 | 5126 => 4, // unsigned int, float
+            other => return Err(invalid(format!("component type {other}"))),
+        };
+        let count = accessor["count"].as_u64().unwrap_or(0) as usize;
+
+        let view = self.get(
+            "bufferViews",
+            accessor["bufferView"]
+                .as_u64()
+                .ok_or_else(|| GltfError::Unsupported("accessors without a bufferView".into()))?
+                as usize,
+        )?;
+        let buffer = self
+            .buffers
+            .get(view["buffer"].as_u64().unwrap_or(0) as usize)
+            .ok_or_else(|| invalid("bufferView's buffer doesn't exist"))?;
+        let view_start = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let view_length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+        let offset = accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let stride = view["byteStride"]
+            .as_u64()
+            .map(|s| s as usize)
+            .unwrap_or(size * components);
+
+        // All of it has to fit in the view, and the view in the buffer, before anything's
+        // allocated, since count can be anything
+        let runs_off = || invalid(format!("accessor {index} runs off its buffer"));
+        let view_end = view_start.checked_add(view_length).ok_or_else(runs_off)?;
+        if view_end > buffer.len() {
+            return Err(invalid(format!(
+                "bufferView for accessor {index} runs off its buffer"
+            )));
+        }
+        if count > 0 {
+            let end = (count - 1)
+                .checked_mul(stride)
+                .and_then(|n| n.checked_add(offset))
+                .and_then(|n| n.checked_add(components * size))
+                .ok_or_else(runs_off)?;
+            if end > view_length {
+                return Err(runs_off());
+            }
+        }
+        let start = view_start + offset;
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                // Can't overflow or go past the end, that's all checked above
+                let at = start + element * stride + component * size;
+                let bytes = &buffer[at..at + size];
+                values.push(match component_type {
+                    5120 => bytes[0] as i8 as f64,
+                    5121 => bytes[0] as f64,
+                    5122 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    5123 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    5125 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    _ => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                });
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// See load for how these are mapped
+    fn material(&self, index: &Value) -> Result<Material, GltfError> {
+        let Some(index) = index.as_u64() else {
+            return Ok(Material::default());
+        };
+        let pbr = &self.get("materials", index as usize)?["pbrMetallicRoughness"];
+
+        let colour = match floats(&pbr["baseColorFactor"])?.as_slice() {
+            [] => Colour::WHITE,
+            [r, g, b, _] => Colour::new(*r, *g, *b),
+            _ => return Err(invalid("baseColorFactor should have 4 values")),
+        };
+        let metallic = pbr["metallicFactor"]
+            .as_f64()
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        let roughness = pbr["roughnessFactor"]
+            .as_f64()
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        let smooth = 1.0 - roughness;
+
+        Ok(Material {
+            pattern: Box::new(SolidPattern(colour)),
+            specular: 0.1 + 0.9 * smooth,
+            shininess: 10.0 + 290.0 * smooth,
+            diffuse: 0.9 - 0.6 * metallic,
+            reflective: 0.8 * metallic * smooth,
+            ..Default::default()
+        })
+    }
+}
+
+/// A node's `matrix`, or its translation * rotation * scale
+fn node_transform(node: &Value) -> Result<Matrix, GltfError> {
+    let matrix = floats(&node["matrix"])?;
+    if !matrix.is_empty() {
+        if matrix.len() != 16 {
+            return Err(invalid("node matrix should have 16 values"));
+        }
+        // glTF is column major, Matrix row major
+        return Ok(Matrix::new_with_data(4, 4, matrix).transpose());
+    }
+
+    let mut transform = Matrix::default();
+    match floats(&node["translation"])?.as_slice() {
+        [] => {}
+        [x, y, z] => transform = transform * Matrix::translation(*x, *y, *z),
+        _ => return Err(invalid("node translation should have 3 values")),
+    }
+    match floats(&node["rotation"])?.as_slice() {
+        [] => {}
+        [x, y, z, w] => transform = transform * quaternion(*x, *y, *z, *w),
+        _ => return Err(invalid("node rotation should have 4 values")),
+    }
+    match floats(&node["scale"])?.as_slice() {
+        [] => {}
+        [x, y, z] => transform = transform * Matrix::scaling(*x, *y, *z),
+        _ => return Err(invalid("node scale should have 3 values")),
+    }
+
+    Ok(transform)
+}
+
+/// Rotation matrix for a (unit) quaternion
+fn quaternion(x: f64, y: f64, z: f64, w: f64) -> Matrix {
+    Matrix::new_with_data(
+        4,
+        4,
+        vec![
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - z * w),
+            2.0 * (x * z + y * w),
+            0.0,
+            2.0 * (x * y + z * w),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - x * w),
+            0.0,
+            2.0 * (x * z - y * w),
+            2.0 * (y * z + x * w),
+            1.0 - 2.0 * (x * x + y * y),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        ],
+    )
+}
+
+/// An optional array, empty if it's missing
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// An optional array of numbers, empty if it's missing
+fn floats(value: &Value) -> Result<Vec<f64>, GltfError> {
+    match value {
+        Value::Null => Ok(vec![]),
+        Value::Array(items) => items
+            .iter()
+            .map(|v| {
+                v.as_f64()
+                    .ok_or_else(|| invalid(format!("{v} isn't a number")))
+            })
+            .collect(),
+        other => Err(invalid(format!("{other} isn't an array"))),
+    }
+}
+
+/// An optional array of indexes, empty if it's missing
+fn indices(value: &Value) -> Result<Vec<usize>, GltfError> {
+    Ok(floats(value)?.into_iter().map(|f| f as usize).collect())
+}
+
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, GltfError> {
+    let sextet = |c: u8| -> Result<u32, GltfError> {
+        Ok(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(invalid(format!("bad base64 character {:?}", c as char))),
+        } as u32)
+    };
+
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.chunks(4) {
+        let mut bits = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            bits |= sextet(c)? << (18 - 6 * i);
+        }
+        let bytes = bits.to_be_bytes();
+        // A chunk of n characters holds n - 1 bytes
+        out.extend(&bytes[1..chunk.len()]);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_3};
+
+    use crate::{
+        camera::Camera,
+        math::tuple::{pointi, vectori},
+        shape::ShapeBase,
+        world::World,
+    };
+
+    use super::*;
+
+    const QUADS: &str = include_str!("../models/quads.gltf");
+
+    fn quads() -> Vec<(Mesh, Matrix, Material)> {
+        load(Path::new(env!("CARGO_MANIFEST_DIR")).join("models/quads.gltf")).unwrap()
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64("TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64("TQ==").unwrap(), b"M");
+        assert!(decode_base64("TW!u").is_err());
+    }
+
+    #[test]
+    fn node_transforms() {
+        let meshes = quads();
+
+        // The node outside the scene isn't included
+        assert_eq!(meshes.len(), 2);
+        let root = Matrix::translationi(0, 0, 5);
+        assert_eq!(
+            meshes[0].1,
+            &root * &(Matrix::rotation_z(FRAC_PI_2) * Matrix::scalingi(2, 2, 2))
+        );
+        assert_eq!(meshes[1].1, root * Matrix::translationi(1, -2, 0));
+    }
+
+    #[test]
+    fn triangles() {
+        for (mesh, _, _) in quads() {
+            assert_eq!(mesh.len(), 2);
+            assert_eq!(mesh.vertices.len(), 4);
+            assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3]]);
+            assert_eq!(mesh.normals, vec![vectori(0, 0, 1); 4]);
+            assert_eq!(mesh.face_normals[0], vectori(0, 0, 1));
+        }
+    }
+
+    #[test]
+    fn material() {
+        let (_, _, material) = &quads()[0];
+
+        assert_eq!(material.colour(), Some(Colour::RED));
+        assert_eq!(material.diffuse, 0.9);
+        assert_eq!(material.specular, 0.55);
+        assert_eq!(material.shininess, 155.0);
+        assert_eq!(material.reflective, 0.0);
+    }
+
+    #[test]
+    fn glb() {
+        // Same model, with the buffer moved into the binary chunk
+        let mut doc: Value = serde_json::from_str(QUADS).unwrap();
+        let uri = doc["buffers"][0]["uri"].as_str().unwrap();
+        let bin = decode_base64(uri.split_once(",").unwrap().1).unwrap();
+        doc["buffers"][0].as_object_mut().unwrap().remove("uri");
+
+        let mut json = serde_json::to_vec(&doc).unwrap();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut glb = b"glTF".to_vec();
+        glb.extend(2u32.to_le_bytes());
+        glb.extend(((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        for (kind, chunk) in [(b"JSON", &json), (b"BIN\0", &bin)] {
+            glb.extend((chunk.len() as u32).to_le_bytes());
+            glb.extend(kind);
+            glb.extend(chunk);
+        }
+
+        let from_glb = from_bytes(&glb, None).unwrap();
+        assert_eq!(from_glb, quads());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            from_bytes(b"{ not json", None),
+            Err(GltfError::Json(_))
+        ));
+
+        let sparse = QUADS.replace(
+            "\"type\": \"SCALAR\"",
+            "\"type\": \"SCALAR\", \"sparse\": {}",
+        );
+        assert!(matches!(
+            from_bytes(sparse.as_bytes(), None),
+            Err(GltfError::Unsupported(_))
+        ));
+
+        // Found out before trying to make room for them all
+        for count in ["4000000000000", "18446744073709551615", "5"] {
+            let huge = QUADS.replacen("\"count\": 4,", &format!("\"count\": {count},"), 1);
+            assert!(
+                matches!(
+                    from_bytes(huge.as_bytes(), None),
+                    Err(GltfError::Invalid(message)) if message.contains("runs off")
+                ),
+                "{count}"
+            );
+        }
+        let long_view = QUADS.replacen("\"byteLength\": 48", "\"byteLength\": 4800", 1);
+        assert!(matches!(
+            from_bytes(long_view.as_bytes(), None),
+            Err(GltfError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn render() {
+        let objects = quads()
+            .into_iter()
+            .map(|(mesh, transform, material)| {
+                let mut group = mesh.into_group();
+                group.set_transform(transform);
+                group.set_material(material);
+                Box::new(group) as Box<dyn crate::shape::Shape>
+            })
+            .collect();
+        let w = World::default().with_objects(objects);
+        let c = Camera::new_with_transform(
+            21,
+            21,
+            FRAC_PI_3,
+            Matrix::view_transform(pointi(0, 0, -1), pointi(0, 0, 5), vectori(0, 1, 0)),
+        );
+
+        let image = c.render(&w);
+
+        let mut lit = 0;
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let pixel = image[(x, y)];
+                if pixel != Colour::BLACK {
+                    // Red, plus some white from highlights
+                    assert!(pixel.red > pixel.green && pixel.green == pixel.blue);
+                    lit += 1;
+                }
+            }
+        }
+        // The quads cover 5 of the 48 or so square units visible at z = 5
+        assert!((30..60).contains(&lit), "{lit}");
+    }
+}
@@ -1,53 +1,209 @@
+use core::cmp::Ordering;
+
 use crate::{
-    math::{float::EPSILON, tuple::Tuple},
-    ray::Ray,
-    shape::Shape,
+    math::{
+        float::{self, EPSILON},
+        point::{Point, Vector},
+        tuple::Tuple,
+    },
+    prelude::*,
+    ray::{Ray, RayKind},
+    shape::{find_in, Shape, ShapeId},
+    world::World,
 };
 
 #[derive(Clone, Copy, Debug)]
 pub struct Intersection<'a> {
     pub t: f64,
     pub object: &'a dyn Shape,
+    /// Where on the surface the hit landed, for shapes that care (0 otherwise)
+    pub u: f64,
+    pub v: f64,
 }
 
 pub struct IntersectionComputions<'a> {
     pub object: &'a dyn Shape,
     pub t: f64,
     pub point: Tuple,
+    /// `point`, in the object's own space (ie, through its inverse transform)
+    pub object_point: Tuple,
     pub over_point: Tuple,
+    /// Just below the surface, where refracted rays start
+    pub under_point: Tuple,
     pub eye_vector: Tuple,
+    /// The normal used for shading, after any bump mapping
     pub normal_vector: Tuple,
+    /// The actual surface normal (still flipped to face the eye), over/under_point use this
+    pub geometric_normal: Tuple,
+    pub reflect_vector: Tuple,
     pub inside: bool,
+    /// Refractive indices of the material being exited and entered respectively
+    pub n1: f64,
+    pub n2: f64,
+    /// What kind of ray made this hit, and how deep in the recursion it was
+    pub ray_kind: RayKind,
+    pub depth: usize,
+    ray: Ray,
 }
 
 impl<'a> Intersection<'a> {
+    /// prepare_computations without the rest of the intersections along the ray. Refraction
+    /// needs those to know what we're exiting, so this assumes the ray came from open air.
     pub fn prepare_computations(&self, ray: Ray) -> IntersectionComputions<'a> {
-        let point = ray.position(self.t);
-        let normal_vector = self.object.normal_at(point);
-        let eye_vector = -ray.direction;
+        self.prepare_computations_with(ray, &[*self])
+    }
+
+    /// `xs` is every intersection along `ray` (sorted, and including this one), used to work
+    /// out which objects the hit is inside of for the refractive indices.
+    pub fn prepare_computations_with(
+        &self,
+        ray: Ray,
+        xs: &[Intersection<'a>],
+    ) -> IntersectionComputions<'a> {
+        self.prepare_computations_with_bias(ray, xs, EPSILON)
+    }
+
+    /// prepare_computations_with, but with over_point and under_point `bias` off the surface
+    /// rather than EPSILON. See World::with_bias.
+    pub fn prepare_computations_with_bias(
+        &self,
+        ray: Ray,
+        xs: &[Intersection<'a>],
+        bias: f64,
+    ) -> IntersectionComputions<'a> {
+        let (n1, n2) = self.refractive_indices(xs);
+        let point = ray.position(self.t).tuple();
+        let inverted = self
+            .object
+            .transform()
+            .inverse()
+            .expect("intersections only exist for invertible transforms");
+        let object_point = &inverted * point;
+        let normal_vector = self.object.normal_at_hit(object_point, self, &inverted);
+        let eye_vector = -ray.direction.tuple();
         let inside = normal_vector.dot(&eye_vector) < 0.0;
 
-        let normal_vector = if inside {
+        let geometric_normal = if inside {
             -normal_vector
         } else {
             normal_vector
         };
+        let normal_vector = self
+            .object
+            .material()
+            .perturb_normal(geometric_normal, object_point);
 
         IntersectionComputions {
             object: self.object,
             t: self.t,
             point,
-            over_point: point + normal_vector * EPSILON,
+            object_point,
+            over_point: point + geometric_normal * bias,
+            under_point: point - geometric_normal * bias,
             eye_vector,
             normal_vector,
+            geometric_normal,
+            reflect_vector: ray.direction.tuple().reflect(&normal_vector),
             inside,
+            n1,
+            n2,
+            ray_kind: ray.kind,
+            depth: ray.depth,
+            ray,
+        }
+    }
+
+    fn refractive_indices(&self, xs: &[Intersection<'a>]) -> (f64, f64) {
+        let index_of = |containers: &[&dyn Shape]| {
+            containers
+                .last()
+                .map_or(1.0, |o| o.material().refractive_index)
+        };
+
+        let mut containers: Vec<&dyn Shape> = vec![];
+        let mut n1 = 1.0;
+        for i in xs {
+            if i == self {
+                n1 = index_of(&containers);
+            }
+
+            match containers.iter().position(|&o| o == i.object) {
+                Some(pos) => {
+                    containers.remove(pos);
+                }
+                None => containers.push(i.object),
+            }
+
+            if i == self {
+                return (n1, index_of(&containers));
+            }
+        }
+
+        (n1, index_of(&containers))
+    }
+}
+
+impl IntersectionComputions<'_> {
+    /// The ray bouncing off of this hit, one level deeper than the one that made it.
+    pub fn reflected_ray(&self) -> Ray {
+        self.ray.bounce(
+            Point::from_tuple(self.over_point),
+            Vector::from_tuple(self.reflect_vector),
+            RayKind::Reflection,
+        )
+    }
+
+    /// The ray continuing through the surface, per Snell's law. None under total internal
+    /// reflection.
+    pub fn refracted_ray(&self) -> Option<Ray> {
+        let n_ratio = self.n1 / self.n2;
+        let cos_i = self.eye_vector.dot(&self.normal_vector);
+        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+        if sin2_t > 1.0 {
+            return None;
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let direction = self.normal_vector * (n_ratio * cos_i - cos_t) - self.eye_vector * n_ratio;
+
+        Some(self.ray.bounce(
+            Point::from_tuple(self.under_point),
+            Vector::from_tuple(direction),
+            RayKind::Refraction,
+        ))
+    }
+
+    /// Schlick's approximation of the Fresnel reflectance, how much light is reflected rather
+    /// than refracted at this hit.
+    pub fn schlick(&self) -> f64 {
+        let mut cos = self.eye_vector.dot(&self.normal_vector);
+        if self.n1 > self.n2 {
+            let n = self.n1 / self.n2;
+            let sin2_t = n.powi(2) * (1.0 - cos.powi(2));
+            if sin2_t > 1.0 {
+                return 1.0;
+            }
+
+            cos = (1.0 - sin2_t).sqrt();
         }
+
+        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
     }
 }
 
 impl<'a> Intersection<'a> {
     pub fn new(t: f64, object: &'a dyn Shape) -> Self {
-        Self { t, object }
+        Self::new_with_uv(t, object, 0.0, 0.0)
+    }
+
+    pub fn new_with_uv(t: f64, object: &'a dyn Shape, u: f64, v: f64) -> Self {
+        Self { t, object, u, v }
+    }
+
+    /// Same object, and t values within `eps` of each other.
+    pub fn approx_eq(&self, other: &Self, eps: f64) -> bool {
+        float::equal_within(self.t, other.t, eps) && self.object == other.object
     }
 }
 
@@ -57,18 +213,141 @@ impl PartialEq for Intersection<'_> {
     }
 }
 
-pub trait IntersectVec {
-    fn hit(&self) -> Option<Intersection<'_>>;
+/// Wraps an Intersection so that it can be used with sort/dedup/BTreeSet and friends.
+///
+/// Ordering (and equality!) is purely on t via f64::total_cmp, the object is ignored, so two
+/// different objects hit at the same t compare equal. Sorting is stable, so those keep the
+/// order they came in.
+#[derive(Clone, Copy, Debug)]
+pub struct OrderedIntersection<'a>(pub Intersection<'a>);
+
+impl PartialEq for OrderedIntersection<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedIntersection<'_> {}
+
+impl PartialOrd for OrderedIntersection<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
-impl IntersectVec for Vec<Intersection<'_>> {
-    fn hit(&self) -> Option<Intersection<'_>> {
+impl Ord for OrderedIntersection<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.t.total_cmp(&other.0.t)
+    }
+}
+
+impl<'a> From<Intersection<'a>> for OrderedIntersection<'a> {
+    fn from(value: Intersection<'a>) -> Self {
+        Self(value)
+    }
+}
+
+/// A stable handle to an object in a World, see World::resolve.
+///
+/// Remembers both where the object was and which object it was, so if the world's objects are
+/// shuffled or removed the id goes stale (and resolves to None) rather than silently pointing
+/// at some other shape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectId {
+    index: usize,
+    id: ShapeId,
+}
+
+impl ObjectId {
+    pub(crate) fn new(index: usize, object: &dyn Shape) -> Self {
+        Self {
+            index,
+            id: object.id(),
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// `object`, or whichever of its children this refers to
+    pub(crate) fn within<'a>(&self, object: &'a dyn Shape) -> Option<&'a dyn Shape> {
+        if self.id == object.id() {
+            Some(object)
+        } else {
+            find_in(object.children(), self.id)
+        }
+    }
+}
+
+/// An owned version of Intersection. Refers to its object by ObjectId rather than by
+/// reference, so it can be kept around (or sent elsewhere) independent of the World.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HitRecord {
+    pub t: f64,
+    pub object: ObjectId,
+    pub u: f64,
+    pub v: f64,
+}
+
+impl HitRecord {
+    pub fn new(intersection: &Intersection, object: ObjectId) -> Self {
+        Self {
+            t: intersection.t,
+            object,
+            u: intersection.u,
+            v: intersection.v,
+        }
+    }
+
+    /// The same as IntersectVec::hit, for records.
+    pub fn hit(records: &[HitRecord]) -> Option<HitRecord> {
+        records
+            .iter()
+            .filter(|x| x.t.is_finite() && x.t >= 0.0)
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+            .copied()
+    }
+
+    /// Turn this back into a borrowed Intersection against `world`. None if the id is stale.
+    pub fn resolve<'w>(&self, world: &'w World) -> Option<Intersection<'w>> {
+        world
+            .resolve(self.object)
+            .map(|object| Intersection::new_with_uv(self.t, object, self.u, self.v))
+    }
+
+    /// Intersection::prepare_computations, for records. None if the id is stale.
+    pub fn prepare_computations<'w>(
+        &self,
+        ray: Ray,
+        world: &'w World,
+    ) -> Option<IntersectionComputions<'w>> {
+        self.resolve(world).map(|i| i.prepare_computations(ray))
+    }
+}
+
+pub trait IntersectVec<'a> {
+    fn hit(&self) -> Option<Intersection<'a>>;
+
+    /// Collapse runs of intersections whose t values are within `eps` of the first in the run,
+    /// keeping the first. Expects the intersections to already be sorted by t. Objects are not
+    /// compared, the point is to drop coincident surfaces from different shapes.
+    fn dedup_within(&mut self, eps: f64);
+}
+
+impl<'a> IntersectVec<'a> for Vec<Intersection<'a>> {
+    fn hit(&self) -> Option<Intersection<'a>> {
         // Performance wise, this is probably not great, but meh.
+        // Non-finite t values are dropped, total_cmp would otherwise happily pick a NaN.
         self.iter()
-            .filter(|&&x| x.t >= 0.0)
+            .filter(|&&x| x.t.is_finite() && x.t >= 0.0)
             .min_by(|&&a, &&b| a.t.total_cmp(&b.t))
             .copied()
     }
+
+    fn dedup_within(&mut self, eps: f64) {
+        self.dedup_by(|next, kept| float::equal_within(next.t, kept.t, eps))
+    }
 }
 
 #[cfg(test)]
@@ -108,6 +387,20 @@ mod test {
         assert_eq!(xs.hit(), None)
     }
 
+    #[test]
+    fn hit_ignores_non_finite() {
+        let s = Sphere::default();
+        let i1 = Intersection::new(f64::NAN, &s);
+        let i2 = Intersection::new(3.0, &s);
+        let i3 = Intersection::new(f64::INFINITY, &s);
+        let i4 = Intersection::new(-f64::NAN, &s);
+
+        let xs = vec![i1, i2, i3, i4];
+
+        assert_eq!(xs.hit().expect("should exist"), i2);
+        assert_eq!(vec![i1, i3, i4].hit(), None);
+    }
+
     #[test]
     fn hit_2() {
         let s = Sphere::default();
@@ -121,18 +414,184 @@ mod test {
         assert_eq!(xs.hit().expect("should exist"), i4)
     }
 
+    mod ordering {
+        use crate::{math::matrix::Matrix, ray::RayIntersect};
+
+        use super::*;
+
+        #[test]
+        fn approx_eq() {
+            let s = Sphere::default();
+            let other = Sphere::default();
+            let i = Intersection::new(1.0, &s);
+
+            assert!(i.approx_eq(&Intersection::new(1.0 + EPSILON / 2.0, &s), EPSILON));
+            assert!(i.approx_eq(&Intersection::new(1.0 - EPSILON / 2.0, &s), EPSILON));
+            assert!(!i.approx_eq(&Intersection::new(1.0 + EPSILON * 2.0, &s), EPSILON));
+            assert!(!i.approx_eq(&Intersection::new(1.0, &other), EPSILON));
+            assert!(i.approx_eq(&Intersection::new(1.05, &s), 0.1));
+        }
+
+        #[test]
+        fn sort_is_stable() {
+            let (a, b, c) = (Sphere::default(), Sphere::default(), Sphere::default());
+            let mut xs: Vec<OrderedIntersection> = vec![
+                Intersection::new(2.0, &a).into(),
+                Intersection::new(1.0, &b).into(),
+                Intersection::new(2.0, &c).into(),
+                Intersection::new(1.0, &a).into(),
+            ];
+
+            xs.sort();
+
+            let got: Vec<_> = xs.into_iter().map(|o| o.0).collect();
+            assert_eq!(
+                got,
+                vec![
+                    Intersection::new(1.0, &b),
+                    Intersection::new(1.0, &a),
+                    Intersection::new(2.0, &a),
+                    Intersection::new(2.0, &c),
+                ]
+            )
+        }
+
+        #[test]
+        fn nan_sorts_last() {
+            let s = Sphere::default();
+            let mut xs: Vec<OrderedIntersection> = vec![
+                Intersection::new(f64::NAN, &s).into(),
+                Intersection::new(1.0, &s).into(),
+            ];
+
+            xs.sort();
+
+            assert_eq!(xs[0].0.t, 1.0);
+            assert!(xs[1].0.t.is_nan());
+        }
+
+        #[test]
+        fn dedup_overlapping_spheres() {
+            let a = Sphere::new_with_transform(Matrix::translationi(0, 0, 1));
+            let b = Sphere::new_with_transform(Matrix::translationi(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+
+            let mut xs: Vec<_> = a
+                .intersect(r)
+                .unwrap()
+                .into_iter()
+                .chain(b.intersect(r).unwrap())
+                .collect();
+            xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+            assert_eq!(xs.len(), 4);
+
+            xs.dedup_within(EPSILON);
+
+            assert_eq!(xs.len(), 2);
+            assert_eq!(xs[0], Intersection::new(5.0, &a));
+            assert_eq!(xs[1], Intersection::new(7.0, &a));
+        }
+    }
+
+    mod bump {
+        use std::f64::consts::FRAC_PI_2;
+
+        use crate::{
+            colour::Colour,
+            materials::Material,
+            math::{
+                matrix::Matrix,
+                tuple::{vectori, Tuple},
+            },
+            pattern::{pattern_base, stripe::StripePattern, Pattern},
+            shape::plane::Plane,
+        };
+
+        use super::*;
+
+        /// Ripples along x
+        #[derive(Debug, Clone, PartialEq, Default)]
+        struct SineBump {
+            transform: Matrix,
+        }
+
+        pattern_base!(SineBump);
+
+        impl Pattern for SineBump {
+            fn pattern_at(&self, point: Tuple) -> Colour {
+                Colour::new(point.x.sin(), 0.0, 0.0)
+            }
+        }
+
+        fn bumpy(pattern: impl Pattern + 'static) -> Plane {
+            Plane::new(
+                Matrix::default(),
+                Material {
+                    normal_perturbation: Some(Box::new(pattern)),
+                    ..Default::default()
+                },
+            )
+        }
+
+        fn comps_at(plane: &Plane, x: f64) -> IntersectionComputions<'_> {
+            let r = Ray::new(Point::new(x, 1.0, 0.0), Vector::newi(0, -1, 0));
+            Intersection::new(1.0, plane).prepare_computations(r)
+        }
+
+        #[test]
+        fn zero_changes_nothing() {
+            let plain = Plane::default();
+            let zero = bumpy(StripePattern::new(Colour::BLACK, Colour::BLACK));
+            let flat = comps_at(&plain, 0.3);
+            let bumped = comps_at(&zero, 0.3);
+
+            assert_eq!(bumped.normal_vector, flat.normal_vector);
+            assert_eq!(bumped.reflect_vector, flat.reflect_vector);
+            assert_eq!(bumped.over_point, flat.over_point);
+        }
+
+        #[test]
+        fn sine_tilts() {
+            let plane = bumpy(SineBump::default());
+
+            // A plane's tangent is +x, see Tuple::orthonormal_basis
+            assert_eq!(vectori(0, 1, 0).orthonormal_basis().0, vectori(1, 0, 0));
+
+            let peak = comps_at(&plane, FRAC_PI_2);
+            assert_eq!(peak.normal_vector, vectori(1, 1, 0).normalize());
+            let trough = comps_at(&plane, -FRAC_PI_2);
+            assert_eq!(trough.normal_vector, vectori(-1, 1, 0).normalize());
+            let flat = comps_at(&plane, 0.0);
+            assert_eq!(flat.normal_vector, vectori(0, 1, 0));
+        }
+
+        #[test]
+        fn offsets_use_geometric_normal() {
+            let plane = bumpy(SineBump::default());
+            let c = comps_at(&plane, FRAC_PI_2);
+
+            assert_eq!(c.geometric_normal, vectori(0, 1, 0));
+            assert_eq!(c.over_point, c.point + c.geometric_normal * EPSILON);
+        }
+    }
+
     mod computations {
-        use crate::math::{
-            float::EPSILON,
-            matrix::Matrix,
-            tuple::{pointi, vectori},
+        use std::f64::consts::SQRT_2;
+
+        use crate::{
+            math::{
+                float::EPSILON,
+                matrix::Matrix,
+                tuple::{point, pointi, vector, vectori},
+            },
+            shape::plane::Plane,
         };
 
         use super::*;
 
         #[test]
         fn precompute() {
-            let r = Ray::new(pointi(0, 0, -5), vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
             let shape = Sphere::default();
             let intersection = Intersection::new(4.0, &shape);
 
@@ -147,7 +606,7 @@ mod test {
 
         #[test]
         fn precompute_outside() {
-            let ray = Ray::new(pointi(0, 0, -5), vectori(0, 0, 1));
+            let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
             let s = &Sphere::default();
             let i = Intersection::new(4.0, s);
 
@@ -158,7 +617,7 @@ mod test {
 
         #[test]
         fn precompute_inside() {
-            let ray = Ray::new(pointi(0, 0, 0), vectori(0, 0, 1));
+            let ray = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1));
             let s = &Sphere::default();
             let i = Intersection::new(1.0, s);
 
@@ -170,9 +629,177 @@ mod test {
             assert!(comps.inside);
         }
 
+        #[test]
+        fn precompute_reflect_vector() {
+            let shape = Plane::default();
+            let ray = Ray::new(
+                Point::new(0.0, 1.0, -1.0),
+                Vector::new(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+            );
+            let i = Intersection::new(SQRT_2, &shape);
+
+            let comps = i.prepare_computations(ray);
+
+            assert_eq!(
+                comps.reflect_vector,
+                vector(0.0, SQRT_2 / 2.0, SQRT_2 / 2.0)
+            );
+        }
+
+        #[test]
+        fn primary_provenance() {
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let shape = Sphere::default();
+
+            let comps = Intersection::new(4.0, &shape).prepare_computations(r);
+
+            assert_eq!(comps.ray_kind, RayKind::Primary);
+            assert_eq!(comps.depth, 0);
+
+            let reflected = comps.reflected_ray();
+            assert_eq!(reflected.kind, RayKind::Reflection);
+            assert_eq!(reflected.depth, 1);
+        }
+
+        fn glass_sphere(transform: Matrix, refractive_index: f64) -> Sphere {
+            let mut s = Sphere::new_with_transform(transform);
+            s.material.transparency = 1.0;
+            s.material.refractive_index = refractive_index;
+            s
+        }
+
+        #[test]
+        fn refractive_indices() {
+            let a = glass_sphere(Matrix::scalingi(2, 2, 2), 1.5);
+            let b = glass_sphere(Matrix::translation(0.0, 0.0, -0.25), 2.0);
+            let c = glass_sphere(Matrix::translation(0.0, 0.0, 0.25), 2.5);
+            let r = Ray::new(Point::newi(0, 0, -4), Vector::newi(0, 0, 1));
+            let xs = vec![
+                Intersection::new(2.0, &a),
+                Intersection::new(2.75, &b),
+                Intersection::new(3.25, &c),
+                Intersection::new(4.75, &b),
+                Intersection::new(5.25, &c),
+                Intersection::new(6.0, &a),
+            ];
+
+            let expected = [
+                (1.0, 1.5),
+                (1.5, 2.0),
+                (2.0, 2.5),
+                (2.5, 2.5),
+                (2.5, 1.5),
+                (1.5, 1.0),
+            ];
+
+            for (i, (n1, n2)) in expected.into_iter().enumerate() {
+                let comps = xs[i].prepare_computations_with(r, &xs);
+                assert_eq!((comps.n1, comps.n2), (n1, n2), "broke for {i}");
+            }
+        }
+
+        #[test]
+        fn without_intersections_assumes_air() {
+            let s = glass_sphere(Default::default(), 1.5);
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+
+            let comps = Intersection::new(4.0, &s).prepare_computations(r);
+
+            assert_eq!((comps.n1, comps.n2), (1.0, 1.5));
+        }
+
+        #[test]
+        fn under_point() {
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let s = glass_sphere(Matrix::translationi(0, 0, 1), 1.5);
+            let i = Intersection::new(5.0, &s);
+
+            let comps = i.prepare_computations_with(r, &[i]);
+
+            assert!(comps.under_point.z > EPSILON / 2.0);
+            assert!(comps.point.z < comps.under_point.z);
+        }
+
+        #[test]
+        fn schlick_total_internal_reflection() {
+            let s = glass_sphere(Default::default(), 1.5);
+            let r = Ray::new(Point::new(0.0, 0.0, SQRT_2 / 2.0), Vector::newi(0, 1, 0));
+            let xs = vec![
+                Intersection::new(-SQRT_2 / 2.0, &s),
+                Intersection::new(SQRT_2 / 2.0, &s),
+            ];
+
+            let comps = xs[1].prepare_computations_with(r, &xs);
+
+            assert_eq!(comps.schlick(), 1.0);
+            assert!(comps.refracted_ray().is_none());
+        }
+
+        #[test]
+        fn schlick_perpendicular() {
+            let s = glass_sphere(Default::default(), 1.5);
+            let r = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 1, 0));
+            let xs = vec![Intersection::new(-1.0, &s), Intersection::new(1.0, &s)];
+
+            let comps = xs[1].prepare_computations_with(r, &xs);
+
+            assert!(float::equal(comps.schlick(), 0.04));
+        }
+
+        #[test]
+        fn schlick_small_angle() {
+            let s = glass_sphere(Default::default(), 1.5);
+            let r = Ray::new(Point::new(0.0, 0.99, -2.0), Vector::newi(0, 0, 1));
+            let xs = vec![Intersection::new(1.8589, &s)];
+
+            let comps = xs[0].prepare_computations_with(r, &xs);
+
+            assert!(float::equal(comps.schlick(), 0.48873));
+        }
+
+        #[test]
+        fn refracted_ray_provenance() {
+            let s = glass_sphere(Default::default(), 1.5);
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+
+            let refracted = Intersection::new(4.0, &s)
+                .prepare_computations(r)
+                .refracted_ray()
+                .expect("straight in can't totally internally reflect");
+
+            assert_eq!(refracted.kind, RayKind::Refraction);
+            assert_eq!(refracted.depth, 1);
+            assert_eq!(refracted.direction, vectori(0, 0, 1));
+        }
+
+        #[test]
+        fn object_point_translated() {
+            let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let shape = Sphere::new_with_transform(Matrix::translationi(2, 0, 0));
+            let i = Intersection::new(5.0, &shape);
+
+            let comps = i.prepare_computations(ray);
+
+            assert_eq!(comps.point, pointi(0, 0, 0));
+            assert_eq!(comps.object_point, pointi(-2, 0, 0));
+        }
+
+        #[test]
+        fn object_point_scaled() {
+            let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let shape = Sphere::new_with_transform(Matrix::scalingi(2, 2, 2));
+            let i = Intersection::new(3.0, &shape);
+
+            let comps = i.prepare_computations(ray);
+
+            assert_eq!(comps.point, pointi(0, 0, -2));
+            assert_eq!(comps.object_point, pointi(0, 0, -1));
+            assert_eq!(comps.normal_vector, shape.normal_at(comps.point, &i));
+        }
+
         #[test]
         fn precompute_offset_point() {
-            let ray = Ray::new(pointi(0, 0, -5), vectori(0, 0, 1));
+            let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
             let shape = Sphere::new_with_transform(Matrix::translationi(0, 0, 1));
             let i = Intersection::new(5.0, &shape);
 
@@ -181,5 +808,24 @@ mod test {
             assert!(comps.over_point.z < -EPSILON / 2.0);
             assert!(comps.point.z > comps.over_point.z);
         }
+
+        #[test]
+        fn offset_by_bias() {
+            let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let shape = Sphere::new_with_transform(Matrix::translationi(0, 0, 1));
+            let i = Intersection::new(5.0, &shape);
+
+            for bias in [1e-9, EPSILON, 1e-3, 0.5] {
+                let comps = i.prepare_computations_with_bias(ray, &[i], bias);
+                assert_eq!(comps.point, pointi(0, 0, 0));
+                assert_eq!(comps.over_point, point(0.0, 0.0, -bias), "{bias}");
+                assert_eq!(comps.under_point, point(0.0, 0.0, bias), "{bias}");
+            }
+
+            let default = i.prepare_computations(ray);
+            let with_epsilon = i.prepare_computations_with_bias(ray, &[i], EPSILON);
+            assert_eq!(default.over_point.z, with_epsilon.over_point.z);
+            assert_eq!(default.under_point.z, with_epsilon.under_point.z);
+        }
     }
 }
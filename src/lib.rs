@@ -1,10 +1,45 @@
+//! Without the (default) `std` feature this is `no_std` + `alloc`, see Cargo.toml for what
+//! goes missing.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod animation;
 pub mod camera;
+pub mod cancel;
 pub mod canvas;
 pub mod colour;
+pub mod error;
+#[cfg(feature = "gltf")]
+pub mod gltf;
 pub mod intersection;
 pub mod lights;
 pub mod materials;
 pub mod math;
+pub mod medium;
+pub mod mesh;
+#[cfg(feature = "std")]
+pub mod obj;
+#[cfg(feature = "std")]
+pub mod output;
+pub mod pattern;
+pub mod photon_map;
+#[cfg(feature = "std")]
+pub mod ply;
+mod prelude;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod progress;
 pub mod ray;
+pub mod scene;
+pub mod scenes;
 pub mod shape;
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod stl;
+#[cfg(all(test, feature = "std"))]
+mod test_util;
+mod trace;
+#[cfg(feature = "std")]
+pub mod watch;
 pub mod world;
@@ -1,10 +1,70 @@
-use std::fmt::Debug;
+use core::{any::Any, fmt::Debug};
 
-use crate::{colour::Colour, math::tuple::Tuple};
+use crate::prelude::*;
+use crate::{
+    colour::Colour,
+    math::{
+        point::{Point, Vector},
+        sampling,
+        tuple::Tuple,
+    },
+    ray::Ray,
+};
 
-pub trait Light: Debug {
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+
+pub mod rigs;
+
+pub trait Light: Debug + Send + Sync {
     fn intensity(&self) -> &Colour;
     fn position(&self) -> &Tuple;
+    /// For getting the concrete light back out of a `dyn Light`, see downcast_ref
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Whether all the light comes from one point, which nothing random will ever hit. Only
+    /// lights that aren't get sampled and hit, see sample, pdf and intersect.
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    /// A direction from `point` towards the light, picked with `u` (two numbers in [0, 1)).
+    /// None for delta lights, or if `point`'s inside the light.
+    fn sample(&self, _point: Tuple, _u: (f64, f64)) -> Option<LightSample> {
+        None
+    }
+
+    /// The odds of sample picking `direction` from `point` (per steradian), 0 if it can't
+    fn pdf(&self, _point: Tuple, _direction: Tuple) -> f64 {
+        0.0
+    }
+
+    /// How far along `ray` its surface is, if the ray hits it
+    fn intersect(&self, _ray: &Ray) -> Option<f64> {
+        None
+    }
+}
+
+/// One direction towards a light with some size to it, see Light::sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    pub direction: Tuple,
+    /// How far it is to the light that way
+    pub distance: f64,
+    /// The odds of having picked `direction`, per steradian
+    pub pdf: f64,
+}
+
+impl<'a> dyn Light + 'a {
+    /// The light as a `T`, if that's what it is
+    pub fn downcast_ref<T: Light + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    pub fn downcast_mut<T: Light + 'static>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -34,13 +94,137 @@ impl Light for PointLight {
     fn position(&self) -> &Tuple {
         &self.position
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A glowing ball. Path tracing samples it like an area light, and rays that hit it see
+/// `intensity`, the light coming off its surface. Everywhere else it's a point light at its
+/// centre. It isn't an object, so nothing else in the world sees it or is shadowed by it.
+#[derive(Debug, Clone, Copy)]
+pub struct SphereLight {
+    pub intensity: Colour,
+    pub centre: Tuple,
+    pub radius: f64,
+}
+
+impl SphereLight {
+    pub fn new(intensity: Colour, centre: Tuple, radius: f64) -> SphereLight {
+        SphereLight {
+            intensity,
+            centre,
+            radius,
+        }
+    }
+
+    pub fn new_boxed(intensity: Colour, centre: Tuple, radius: f64) -> Box<SphereLight> {
+        Box::new(SphereLight::new(intensity, centre, radius))
+    }
+
+    /// Cos of the half angle the sphere covers, seen from `point`. None inside it.
+    fn cos_max(&self, point: Tuple) -> Option<f64> {
+        let distance_squared = (self.centre - point).dot(&(self.centre - point));
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return None;
+        }
+        Some((1.0 - radius_squared / distance_squared).max(0.0).sqrt())
+    }
+}
+
+impl Light for SphereLight {
+    fn intensity(&self) -> &Colour {
+        &self.intensity
+    }
+
+    fn position(&self) -> &Tuple {
+        &self.centre
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    /// Uniform over the cone the sphere fills, which doesn't waste any on the side facing away
+    fn sample(&self, point: Tuple, u: (f64, f64)) -> Option<LightSample> {
+        let cos_max = self.cos_max(point)?;
+        let to_centre = self.centre - point;
+        let direction = sampling::uniform_cone(to_centre.normalize(), cos_max, u);
+        // Right on the edge the ray can just miss, so the closest approach stands in
+        let distance = self
+            .intersect(&Ray::new(
+                Point::from_tuple(point),
+                Vector::from_tuple(direction),
+            ))
+            .unwrap_or_else(|| to_centre.dot(&direction));
+        Some(LightSample {
+            direction,
+            distance,
+            pdf: sampling::uniform_cone_pdf(cos_max),
+        })
+    }
+
+    fn pdf(&self, point: Tuple, direction: Tuple) -> f64 {
+        match self.cos_max(point) {
+            Some(cos_max)
+                if self
+                    .intersect(&Ray::new(
+                        Point::from_tuple(point),
+                        Vector::from_tuple(direction),
+                    ))
+                    .is_some() =>
+            {
+                sampling::uniform_cone_pdf(cos_max)
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        let to_origin = ray.origin.tuple() - self.centre;
+        let a = ray.direction.dot(&ray.direction);
+        let b = ray.direction.dot(&to_origin);
+        let c = to_origin.dot(&to_origin) - self.radius * self.radius;
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        [(-b - root) / a, (-b + root) / a]
+            .into_iter()
+            .find(|t| *t > 0.0)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{colour::Colour, math::tuple::ZERO};
+    use crate::{
+        colour::Colour,
+        math::{
+            float,
+            point::{Point, Vector},
+            rng::Rng,
+            sampling,
+            tuple::{pointi, vectori, ZERO, ZERO_POINT},
+        },
+        ray::Ray,
+    };
 
-    use super::PointLight;
+    use super::{Light, PointLight, SphereLight};
 
     #[test]
     fn construction_works() {
@@ -52,4 +236,66 @@ mod tests {
         assert_eq!(l.intensity, Colour::BLACK);
         assert_eq!(l.position, ZERO)
     }
+
+    #[test]
+    fn downcast() {
+        let mut boxed: Box<dyn Light> = PointLight::new_boxed(Colour::WHITE, ZERO);
+
+        assert_eq!(
+            boxed.downcast_ref::<PointLight>().unwrap().intensity,
+            Colour::WHITE
+        );
+        boxed.downcast_mut::<PointLight>().unwrap().intensity = Colour::RED;
+        assert_eq!(*boxed.intensity(), Colour::RED);
+    }
+
+    #[test]
+    fn point_lights_are_delta() {
+        let l = PointLight::new(Colour::WHITE, pointi(0, 5, 0));
+        assert!(l.is_delta());
+        assert_eq!(l.sample(ZERO_POINT, (0.5, 0.5)), None);
+        assert_eq!(l.pdf(ZERO_POINT, vectori(0, 1, 0)), 0.0);
+        assert_eq!(
+            l.intersect(&Ray::new(Point::ORIGIN, Vector::newi(0, 1, 0))),
+            None
+        );
+    }
+
+    #[test]
+    fn sphere_light() {
+        let l = SphereLight::new(Colour::WHITE, pointi(0, 5, 0), 3.0);
+        assert!(!l.is_delta());
+        assert_eq!(l.position(), &pointi(0, 5, 0));
+        assert_eq!(
+            l.intersect(&Ray::new(Point::ORIGIN, Vector::newi(0, 1, 0))),
+            Some(2.0)
+        );
+        assert_eq!(
+            l.intersect(&Ray::new(Point::ORIGIN, Vector::newi(0, -1, 0))),
+            None
+        );
+        assert_eq!(
+            l.intersect(&Ray::new(Point::ORIGIN, Vector::newi(1, 0, 0))),
+            None
+        );
+
+        // From 5 away a radius of 3 fills a cone with cos 4/5
+        let pdf = sampling::uniform_cone_pdf(0.8);
+        let mut rng = Rng::new(1);
+        for _ in 0..100 {
+            let sample = l
+                .sample(ZERO_POINT, (rng.next_f64(), rng.next_f64()))
+                .unwrap();
+            assert!(float::equal(sample.pdf, pdf));
+            assert!((2.0..=5.0).contains(&sample.distance), "{sample:?}");
+            let on_surface = ZERO_POINT + sample.direction * sample.distance - pointi(0, 5, 0);
+            assert!(float::equal(on_surface.magnitude(), 3.0));
+            assert!(float::equal(l.pdf(ZERO_POINT, sample.direction), pdf));
+        }
+        assert_eq!(l.pdf(ZERO_POINT, vectori(0, -1, 0)), 0.0);
+
+        // Nothing to pick from inside it
+        assert_eq!(l.sample(pointi(0, 4, 0), (0.5, 0.5)), None);
+        assert_eq!(l.pdf(pointi(0, 4, 0), vectori(0, 1, 0)), 0.0);
+    }
 }
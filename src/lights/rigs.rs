@@ -0,0 +1,227 @@
+//! Ready made arrangements of point lights. Each light gets an equal share of `colour`, so
+//! changing how many there are changes how soft the shadows are, not how bright the scene is.
+
+use crate::{
+    colour::Colour,
+    lights::{Light, PointLight},
+    math::{
+        matrix::Matrix,
+        tuple::{Tuple, ZERO_POINT},
+    },
+    prelude::*,
+};
+
+/// `count` lights evenly spaced around a circle. Untilted the circle is flat (around the y
+/// axis) with the first light on +z, `tilt` tips it about the x axis, in radians.
+pub fn ring(
+    center: Tuple,
+    radius: f64,
+    count: usize,
+    colour: Colour,
+    tilt: f64,
+) -> Vec<Box<dyn Light>> {
+    let step = 360.0 / count as f64;
+    let degrees = (0..count).map(|i| step * i as f64);
+    around(center, radius, degrees, count, colour, tilt)
+}
+
+/// ring, but only the part from `start` to `end` degrees (both ends included). One light
+/// goes halfway between them.
+pub fn arc(
+    center: Tuple,
+    radius: f64,
+    count: usize,
+    colour: Colour,
+    tilt: f64,
+    start: f64,
+    end: f64,
+) -> Vec<Box<dyn Light>> {
+    let step = if count > 1 {
+        (end - start) / (count - 1) as f64
+    } else {
+        0.0
+    };
+    let first = if count > 1 {
+        start
+    } else {
+        (start + end) / 2.0
+    };
+    let degrees = (0..count).map(|i| first + step * i as f64);
+    around(center, radius, degrees, count, colour, tilt)
+}
+
+/// `nu` by `nv` lights filling the parallelogram with a corner at `corner` and sides `uvec`
+/// and `vvec`, one in the middle of each cell.
+pub fn grid(
+    corner: Tuple,
+    uvec: Tuple,
+    vvec: Tuple,
+    nu: usize,
+    nv: usize,
+    colour: Colour,
+) -> Vec<Box<dyn Light>> {
+    let intensity = share(colour, nu * nv);
+    let (du, dv) = (uvec * (nu as f64).recip(), vvec * (nv as f64).recip());
+
+    (0..nv)
+        .flat_map(|v| (0..nu).map(move |u| (u, v)))
+        .map(|(u, v)| {
+            let position = corner + du * (u as f64 + 0.5) + dv * (v as f64 + 0.5);
+            PointLight::new_boxed(intensity, position) as Box<dyn Light>
+        })
+        .collect()
+}
+
+fn around(
+    center: Tuple,
+    radius: f64,
+    degrees: impl Iterator<Item = f64>,
+    count: usize,
+    colour: Colour,
+    tilt: f64,
+) -> Vec<Box<dyn Light>> {
+    let intensity = share(colour, count);
+    degrees
+        .map(|d| {
+            let position = Matrix::translation(0.0, 0.0, radius)
+                .rotate_y(d.to_radians())
+                .rotate_x(tilt)
+                .translate(center.x, center.y, center.z)
+                * ZERO_POINT;
+            PointLight::new_boxed(intensity, position) as Box<dyn Light>
+        })
+        .collect()
+}
+
+fn share(colour: Colour, count: usize) -> Colour {
+    colour / count.max(1) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_2;
+
+    use crate::{
+        colour::Colour,
+        lights::Light,
+        math::{
+            float,
+            matrix::Matrix,
+            tuple::{point, pointi, vectori, Tuple, ZERO_POINT},
+        },
+    };
+
+    use super::{arc, grid, ring};
+
+    fn positions(lights: &[Box<dyn Light>]) -> Vec<Tuple> {
+        lights.iter().map(|l| *l.position()).collect()
+    }
+
+    fn total(lights: &[Box<dyn Light>]) -> Colour {
+        lights
+            .iter()
+            .fold(Colour::BLACK, |sum, l| sum + *l.intensity())
+    }
+
+    #[test]
+    fn ring_of_four() {
+        let lights = ring(pointi(0, 5, 0), 2.0, 4, Colour::WHITE, 0.0);
+        assert_eq!(
+            positions(&lights),
+            vec![
+                pointi(0, 5, 2),
+                pointi(2, 5, 0),
+                pointi(0, 5, -2),
+                pointi(-2, 5, 0),
+            ]
+        );
+        assert_eq!(*lights[0].intensity(), Colour::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn total_intensity_is_constant() {
+        let colour = Colour::new(0.5, 1.0, 2.0);
+        for count in 1..10 {
+            let r = ring(ZERO_POINT, 1.0, count, colour, 0.3);
+            assert_eq!(r.len(), count);
+            assert_eq!(total(&r), colour);
+
+            let a = arc(ZERO_POINT, 1.0, count, colour, 0.0, 0.0, 90.0);
+            assert_eq!(total(&a), colour);
+
+            let g = grid(
+                ZERO_POINT,
+                vectori(1, 0, 0),
+                vectori(0, 1, 0),
+                count,
+                2,
+                colour,
+            );
+            assert_eq!(g.len(), count * 2);
+            assert_eq!(total(&g), colour);
+        }
+    }
+
+    #[test]
+    fn tilt() {
+        // Tipped all the way up, the ring is in the xy plane
+        let center = pointi(1, 2, 3);
+        let lights = ring(center, 2.0, 8, Colour::WHITE, FRAC_PI_2);
+        for p in positions(&lights) {
+            assert!(float::equal(p.z, center.z));
+            assert!(float::equal((p - center).magnitude(), 2.0));
+        }
+        // The first light swung from +z to -y
+        assert_eq!(*lights[0].position(), pointi(1, 0, 3));
+        assert_eq!(*lights[2].position(), pointi(3, 2, 3));
+    }
+
+    #[test]
+    fn arc_ends() {
+        let lights = arc(ZERO_POINT, 1.0, 3, Colour::WHITE, 0.0, 0.0, 90.0);
+        let expected = vec![
+            pointi(0, 0, 1),
+            point(0.5_f64.sqrt(), 0.0, 0.5_f64.sqrt()),
+            pointi(1, 0, 0),
+        ];
+        assert_eq!(positions(&lights), expected);
+
+        let single = arc(ZERO_POINT, 1.0, 1, Colour::WHITE, 0.0, 0.0, 180.0);
+        assert_eq!(positions(&single), vec![pointi(1, 0, 0)]);
+    }
+
+    #[test]
+    fn grid_cells() {
+        let lights = grid(
+            pointi(0, 10, 0),
+            vectori(4, 0, 0),
+            vectori(0, 0, 2),
+            2,
+            2,
+            Colour::WHITE,
+        );
+        assert_eq!(
+            positions(&lights),
+            vec![
+                point(1.0, 10.0, 0.5),
+                point(3.0, 10.0, 0.5),
+                point(1.0, 10.0, 1.5),
+                point(3.0, 10.0, 1.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_multiple_shadows_example() {
+        // What 7_8_multiple_shadows used to do by hand, with each light at full white
+        let lights = ring(point(0.0, 50.0, 0.0), 30.0, 8, Colour::WHITE * 8.0, 0.0);
+        for (i, l) in lights.iter().enumerate() {
+            let by_hand = Matrix::translation(0.0, 0.0, 30.0)
+                .rotate_y(((360.0 / 8.0) * i as f64).to_radians())
+                .translate(0.0, 50.0, 0.0)
+                * ZERO_POINT;
+            assert_eq!(*l.position(), by_hand);
+            assert_eq!(*l.intensity(), Colour::WHITE);
+        }
+    }
+}
@@ -1,3 +1,648 @@
-fn main() {
-    println!("Hello, world!");
+//! One renderer for everything. Given a scene file it renders that, given a model it's put on
+//! a floor, lit and framed, given nothing it renders the book's default world. `raytracer lint`
+//! checks scene files without rendering them, and `raytracer watch` re-renders one every time
+//! it's saved.
+
+use std::{
+    env,
+    ffi::OsStr,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use raytracer::{
+    camera::Camera,
+    cancel::{CancellationToken, RenderResult},
+    colour::Colour,
+    lights::PointLight,
+    materials::Material,
+    math::{
+        matrix::Matrix,
+        tuple::{point, pointi, vector, vectori, Tuple},
+    },
+    obj, ply,
+    scene::{self, RenderSettings, Scene, SceneDescription, Severity},
+    shape::{plane::Plane, Shape},
+    stl,
+    watch::{self, Debouncer, FileWatcher, WatchAction},
+    world::{World, MAX_DEPTH},
+};
+
+const USAGE: &str = "\
+usage: raytracer [SCENE] [options]
+       raytracer lint SCENE...
+       raytracer watch SCENE [options]
+
+SCENE is a scene file (.yaml, .yml or .json, see scenes/example.yaml) or a model: an .obj,
+.ply or .stl file (or .gltf/.glb with the gltf feature). Without one the book's default
+world is rendered. Scene files bring their own camera, so --width, --height and --fov don't
+apply to them.
+
+lint checks scene files for mistakes, and exits with 1 if any are errors.
+
+watch renders a scene file at preview quality (--samples 1 and --max-depth 2 at most), then
+again every time it changes, until stopped with Ctrl-C. A scene that doesn't load leaves the
+last image as it was.
+
+options:
+    --width N         image width in pixels (400)
+    --height N        image height in pixels (300)
+    --fov DEGREES     horizontal field of view, or vertical for tall images (60)
+    --out FILE        where to write the image, .ppm or .png (out/render.png)
+    --threads N       threads to render on (all of them)
+    --samples N       N x N rays per pixel, for anti-aliasing (1)
+    --max-depth N     how many reflections/refractions deep to go (5)
+    -h, --help        this";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ppm,
+    Png,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    scene: Option<PathBuf>,
+    width: usize,
+    height: usize,
+    /// In degrees
+    fov: f64,
+    out: PathBuf,
+    format: Format,
+    threads: usize,
+    samples: usize,
+    max_depth: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            width: 400,
+            height: 300,
+            fov: 60.0,
+            out: PathBuf::from("out/render.png"),
+            format: Format::Png,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            samples: 1,
+            max_depth: MAX_DEPTH,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Render(Options),
+    Lint(Vec<PathBuf>),
+    /// Options::scene is always a scene file
+    Watch(Options),
+    Help,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter().peekable();
+
+    if args.next_if(|a| a == "lint").is_some() {
+        let files: Vec<_> = args.collect();
+        if files.iter().any(|a| a == "-h" || a == "--help") {
+            return Ok(Command::Help);
+        }
+        if let Some(option) = files.iter().find(|a| a.starts_with('-')) {
+            return Err(format!("unknown option {option}"));
+        }
+        if files.is_empty() {
+            return Err("lint needs a scene file to check".into());
+        }
+        return Ok(Command::Lint(
+            files.into_iter().map(PathBuf::from).collect(),
+        ));
+    }
+    let watching = args.next_if(|a| a == "watch").is_some();
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help);
+        }
+        if !arg.starts_with('-') {
+            if let Some(scene) = &options.scene {
+                return Err(format!(
+                    "only one model at a time, got {} and {arg}",
+                    scene.display()
+                ));
+            }
+            options.scene = Some(arg.into());
+            continue;
+        }
+
+        // Both `--width 10` and `--width=10`
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_owned(), value.to_owned()),
+            None => {
+                let value = match arg.as_str() {
+                    "--width" | "--height" | "--fov" | "--out" | "--threads" | "--samples"
+                    | "--max-depth" => args.next(),
+                    _ => return Err(format!("unknown option {arg}")),
+                };
+                let value = value.ok_or(format!("{arg} needs a value"))?;
+                (arg, value)
+            }
+        };
+
+        match name.as_str() {
+            "--width" => options.width = positive(&name, &value)?,
+            "--height" => options.height = positive(&name, &value)?,
+            "--threads" => options.threads = positive(&name, &value)?,
+            "--samples" => options.samples = positive(&name, &value)?,
+            "--max-depth" => {
+                options.max_depth = value
+                    .parse()
+                    .map_err(|_| format!("{name} needs a whole number, got {value:?}"))?
+            }
+            "--fov" => {
+                options.fov = value
+                    .parse()
+                    .ok()
+                    .filter(|fov| 0.0 < *fov && *fov < 180.0)
+                    .ok_or(format!(
+                        "{name} needs an angle between 0 and 180 degrees, got {value:?}"
+                    ))?
+            }
+            "--out" => {
+                options.format = format_of(Path::new(&value))?;
+                options.out = value.into();
+            }
+            _ => return Err(format!("unknown option {name}")),
+        }
+    }
+
+    if watching {
+        if !options.scene.as_deref().is_some_and(is_scene_file) {
+            return Err("watch needs a scene file (.yaml, .yml or .json)".into());
+        }
+        return Ok(Command::Watch(options));
+    }
+    Ok(Command::Render(options))
+}
+
+fn positive(name: &str, value: &str) -> Result<usize, String> {
+    value.parse().ok().filter(|n| *n > 0).ok_or(format!(
+        "{name} needs a whole number above 0, got {value:?}"
+    ))
+}
+
+fn format_of(path: &Path) -> Result<Format, String> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some(e) if e.eq_ignore_ascii_case("ppm") => Ok(Format::Ppm),
+        Some(e) if e.eq_ignore_ascii_case("png") => Ok(Format::Png),
+        _ => Err(format!(
+            "can only write .ppm or .png images, not {}",
+            path.display()
+        )),
+    }
+}
+
+/// The model's objects, and the corners of a box around all of them
+type Model = (Vec<Box<dyn Shape>>, (Tuple, Tuple));
+
+fn load_model(path: &Path) -> Result<Model, String> {
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let open = || File::open(path).map_err(|e| format!("can't open {}: {e}", path.display()));
+    let failed = |e: &dyn std::fmt::Display| format!("can't load {}: {e}", path.display());
+
+    let (objects, vertices): (Vec<Box<dyn Shape>>, Vec<Tuple>) = match extension.as_str() {
+        "obj" => {
+            let model = obj::parse_obj(BufReader::new(open()?)).map_err(|e| failed(&e))?;
+            let vertices = model.vertices.clone();
+            (vec![Box::new(model.into_group())], vertices)
+        }
+        "ply" | "stl" => {
+            let mesh = if extension == "ply" {
+                ply::parse(BufReader::new(open()?)).map_err(|e| failed(&e))?
+            } else {
+                stl::parse(BufReader::new(open()?)).map_err(|e| failed(&e))?
+            };
+            let vertices = mesh.vertices.clone();
+            (vec![Box::new(mesh.into_group())], vertices)
+        }
+        #[cfg(feature = "gltf")]
+        "gltf" | "glb" => {
+            use raytracer::shape::ShapeBase;
+
+            let meshes = raytracer::gltf::load(path).map_err(|e| failed(&e))?;
+            let mut objects: Vec<Box<dyn Shape>> = vec![];
+            let mut vertices = vec![];
+            for (mesh, transform, material) in meshes {
+                vertices.extend(mesh.vertices.iter().map(|v| &transform * *v));
+                let mut group = mesh.into_group();
+                group.set_transform(transform);
+                group.set_material(material);
+                objects.push(Box::new(group));
+            }
+            (objects, vertices)
+        }
+        _ => {
+            return Err(format!(
+                "don't know how to load {}, scenes can be .yaml, .yml or .json and models \
+                 .obj, .ply or .stl{}",
+                path.display(),
+                if cfg!(feature = "gltf") {
+                    ", .gltf or .glb"
+                } else {
+                    ""
+                }
+            ))
+        }
+    };
+
+    let bounds = vertices
+        .iter()
+        .fold(None, |bounds: Option<(Tuple, Tuple)>, v| {
+            Some(match bounds {
+                None => (*v, *v),
+                Some((min, max)) => (
+                    point(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z)),
+                    point(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z)),
+                ),
+            })
+        });
+    let bounds = bounds.ok_or(format!("{} has nothing in it to render", path.display()))?;
+    Ok((objects, bounds))
+}
+
+fn is_scene_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|e| ["yaml", "yml", "json"].contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// A scene file, with any warnings about it printed
+fn load_scene(path: &Path) -> Result<Scene, String> {
+    let failed = |e: &dyn std::fmt::Display| format!("can't load {}: {e}", path.display());
+    let description = SceneDescription::load(path).map_err(|e| failed(&e))?;
+    for warning in scene::validate(&description)
+        .iter()
+        .filter(|i| i.severity == Severity::Warning)
+    {
+        eprintln!("{}: {warning}", path.display());
+    }
+    description.to_scene().map_err(|e| failed(&e))
+}
+
+fn build_scene(options: &Options) -> Result<Scene, String> {
+    let settings = RenderSettings {
+        max_depth: options.max_depth,
+        aa_samples: options.samples,
+        threads: options.threads,
+        ..Default::default()
+    };
+    if let Some(path) = options.scene.as_deref().filter(|p| is_scene_file(p)) {
+        return Ok(load_scene(path)?.with_settings(settings));
+    }
+
+    let fov = options.fov.to_radians();
+    let (world, from, to) = match &options.scene {
+        None => (World::default(), pointi(0, 0, -5), pointi(0, 0, 0)),
+        Some(path) => {
+            let (objects, (min, max)) = load_model(path)?;
+            let centre = point(
+                (min.x + max.x) / 2.0,
+                (min.y + max.y) / 2.0,
+                (min.z + max.z) / 2.0,
+            );
+            let radius = ((max - min).magnitude() / 2.0).max(1e-3);
+
+            // Far enough back that a sphere around the model fits the narrower way, looking
+            // down on it a little from the front and side
+            let aspect = options.width as f64 / options.height as f64;
+            let half_view = (fov / 2.0).tan();
+            let narrow = half_view.min(half_view / aspect).min(half_view * aspect);
+            let distance = radius / narrow.atan().sin() * 1.1;
+            let from = centre + vector(0.4, 0.5, -1.0).normalize() * distance;
+
+            let floor = Plane::new(Matrix::translation(0.0, min.y, 0.0), Material::default());
+            let light = PointLight::new_boxed(
+                Colour::WHITE,
+                centre + vector(-1.0, 2.0, -1.0).normalize() * distance * 1.5,
+            );
+            let mut world = World::new(objects, vec![light]);
+            world.add_object(Box::new(floor));
+            (world, from, centre)
+        }
+    };
+
+    let camera = Camera::try_new_with_transform(
+        options.width,
+        options.height,
+        fov,
+        Matrix::view_transform(from, to, vectori(0, 1, 0)),
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(Scene::new(world, camera).with_settings(settings))
+}
+
+/// Prints everything wrong with each file, true if none of it's an error
+fn lint(paths: &[PathBuf]) -> bool {
+    let mut clean = true;
+    for path in paths {
+        let issues = match SceneDescription::load(path) {
+            Ok(description) => scene::validate(&description),
+            Err(e) => {
+                println!("{}: error: {e}", path.display());
+                clean = false;
+                continue;
+            }
+        };
+        for issue in &issues {
+            println!("{}: {issue}", path.display());
+        }
+        if issues.is_empty() {
+            println!("{}: ok", path.display());
+        }
+        clean &= !issues.iter().any(|i| i.severity == Severity::Error);
+    }
+    clean
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let scene = build_scene(options)?;
+
+    let start = Instant::now();
+    let canvas = scene.render();
+    let elapsed = start.elapsed();
+
+    let bytes = match options.format {
+        Format::Ppm => canvas.into_ppm_binary(false),
+        Format::Png => canvas.into_png(false),
+    };
+    if let Some(dir) = options.out.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("can't create {}: {e}", dir.display()))?;
+    }
+    fs::write(&options.out, bytes)
+        .map_err(|e| format!("can't write {}: {e}", options.out.display()))?;
+
+    println!(
+        "{}x{} in {elapsed:.2?}, written to {}",
+        canvas.width,
+        canvas.height,
+        options.out.display()
+    );
+    Ok(())
+}
+
+/// How long a scene file has to go unchanged before it's rendered
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// How often the scene file's checked for changes
+const POLL: Duration = Duration::from_millis(50);
+/// Reflections and refractions while watching, deeper than this is slow to wait for
+const PREVIEW_DEPTH: usize = 2;
+
+/// Renders `options.scene` whenever it changes, forever
+fn watch(options: &Options) -> Result<(), String> {
+    let path = options.scene.as_deref().expect("watch needs a scene");
+    let mut watcher = FileWatcher::new(path);
+    let mut debouncer = Debouncer::new(DEBOUNCE);
+    // Render what's there to start with
+    debouncer.changed(Instant::now());
+    let mut rendering: Option<(CancellationToken, Instant, thread::JoinHandle<RenderResult>)> =
+        None;
+    if let Some(dir) = options.out.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("can't create {}: {e}", dir.display()))?;
+    }
+    println!("watching {}, Ctrl-C to stop", path.display());
+
+    loop {
+        let now = Instant::now();
+        if watcher.changed() && debouncer.changed(now) == WatchAction::Cancel {
+            if let Some((token, _, _)) = &rendering {
+                token.cancel();
+            }
+        }
+
+        if rendering.as_ref().is_some_and(|(_, _, r)| r.is_finished()) {
+            let (_, start, render) = rendering.take().unwrap();
+            debouncer.finished();
+            match render.join().map_err(|_| "render thread panicked")? {
+                RenderResult::Completed(canvas) => {
+                    let bytes = match options.format {
+                        Format::Ppm => canvas.into_ppm_binary(false),
+                        Format::Png => canvas.into_png(false),
+                    };
+                    match watch::write_atomic(&options.out, &bytes) {
+                        Ok(()) => println!(
+                            "{}x{} in {:.2?}, written to {}",
+                            canvas.width,
+                            canvas.height,
+                            start.elapsed(),
+                            options.out.display()
+                        ),
+                        Err(e) => eprintln!("error: can't write {}: {e}", options.out.display()),
+                    }
+                }
+                RenderResult::Cancelled(_) => println!("changed, starting again"),
+            }
+        }
+
+        if debouncer.poll(now) == WatchAction::Render {
+            match load_scene(path) {
+                Ok(scene) => {
+                    let scene = scene.with_settings(RenderSettings {
+                        max_depth: options.max_depth.min(PREVIEW_DEPTH),
+                        aa_samples: 1,
+                        threads: options.threads,
+                        ..Default::default()
+                    });
+                    let token = CancellationToken::new();
+                    let render = {
+                        let token = token.clone();
+                        thread::spawn(move || scene.render_cancellable(&token))
+                    };
+                    rendering = Some((token, now, render));
+                }
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    debouncer.finished();
+                }
+            }
+        }
+
+        thread::sleep(POLL);
+    }
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Lint(paths)) => {
+            if lint(&paths) {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Ok(Command::Watch(options)) => match watch(&options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Ok(Command::Render(options)) => match run(&options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{parse_args, Command, Format, Options};
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    fn options(args: &[&str]) -> Options {
+        match parse(args) {
+            Ok(Command::Render(options)) => options,
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(options(&[]), Options::default());
+        assert_eq!(parse(&["--help"]), Ok(Command::Help));
+        assert_eq!(parse(&["model.obj", "-h", "--bogus"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn everything() {
+        let o = options(&[
+            "teapot.obj",
+            "--width",
+            "64",
+            "--height=48",
+            "--fov",
+            "45.5",
+            "--out",
+            "renders/teapot.PPM",
+            "--threads=3",
+            "--samples",
+            "4",
+            "--max-depth",
+            "0",
+        ]);
+        assert_eq!(
+            o,
+            Options {
+                scene: Some(PathBuf::from("teapot.obj")),
+                width: 64,
+                height: 48,
+                fov: 45.5,
+                out: PathBuf::from("renders/teapot.PPM"),
+                format: Format::Ppm,
+                threads: 3,
+                samples: 4,
+                max_depth: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn lint() {
+        assert_eq!(
+            parse(&["lint", "a.yaml", "b.json"]),
+            Ok(Command::Lint(vec!["a.yaml".into(), "b.json".into()]))
+        );
+        assert_eq!(parse(&["lint", "a.yaml", "--help"]), Ok(Command::Help));
+        assert_eq!(
+            parse(&["lint"]),
+            Err("lint needs a scene file to check".to_owned())
+        );
+        assert_eq!(
+            parse(&["lint", "a.yaml", "--width=3"]),
+            Err("unknown option --width=3".to_owned())
+        );
+        // Only as the first argument
+        assert_eq!(options(&["--width=3", "lint"]).scene, Some("lint".into()));
+    }
+
+    #[test]
+    fn watch() {
+        let Ok(Command::Watch(o)) = parse(&["watch", "a.yaml", "--out=a.ppm", "--threads", "2"])
+        else {
+            panic!()
+        };
+        assert_eq!(o.scene, Some("a.yaml".into()));
+        assert_eq!((o.format, o.threads), (Format::Ppm, 2));
+
+        for args in [&["watch"][..], &["watch", "teapot.obj"]] {
+            assert_eq!(
+                parse(args),
+                Err("watch needs a scene file (.yaml, .yml or .json)".to_owned())
+            );
+        }
+        assert_eq!(parse(&["watch", "a.yaml", "-h"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn friendly_errors() {
+        for (args, error) in [
+            (&["--width"][..], "--width needs a value"),
+            (
+                &["--width", "wide"],
+                "--width needs a whole number above 0, got \"wide\"",
+            ),
+            (
+                &["--samples=0"],
+                "--samples needs a whole number above 0, got \"0\"",
+            ),
+            (
+                &["--max-depth", "-1"],
+                "--max-depth needs a whole number, got \"-1\"",
+            ),
+            (
+                &["--fov", "180"],
+                "--fov needs an angle between 0 and 180 degrees, got \"180\"",
+            ),
+            (
+                &["--out", "image.jpg"],
+                "can only write .ppm or .png images, not image.jpg",
+            ),
+            (
+                &["--out=image"],
+                "can only write .ppm or .png images, not image",
+            ),
+            (&["--colour", "red"], "unknown option --colour"),
+            (&["--colour=red"], "unknown option --colour"),
+            (
+                &["a.obj", "b.obj"],
+                "only one model at a time, got a.obj and b.obj",
+            ),
+        ] {
+            assert_eq!(parse(args), Err(error.to_owned()), "{args:?}");
+        }
+    }
 }
@@ -1,77 +1,770 @@
-use crate::{colour::Colour, lights::Light, math::tuple::Tuple};
+use core::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::{
+    colour::Colour,
+    lights::Light,
+    math::{float::EPSILON, tuple::Tuple},
+    pattern::{solid::SolidPattern, Pattern},
+    prelude::*,
+};
+
+/// How a surface reacts to light. Fields are all public, struct update syntax off of
+/// `Default::default()` (or one of the presets) is the intended way to build one, with
+/// [`Material::builder`] there if you want range checking. The middle sphere from chapter 7:
+/// ```
+/// # use raytracer::{colour::Colour, materials::Material, math::matrix::Matrix, shape::sphere::Sphere};
+/// let material = Material {
+///     diffuse: 0.7,
+///     specular: 0.3,
+///     ..Material::default().with_colour(Colour::new(0.1, 1.0, 0.5))
+/// };
+/// let middle = Sphere::new(Matrix::translation(-0.5, 1.0, 0.5), material);
+///
+/// assert_eq!(middle.material.diffuse, 0.7);
+/// assert!(middle.material.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
 pub struct Material {
-    pub colour: Colour,
+    /// The surface's colour, a SolidPattern (white by default) unless it varies. See
+    /// with_colour.
+    pub pattern: Box<dyn Pattern>,
+    /// Bump map. Sampled at the object space point, red and green nudge the shading normal
+    /// along the tangent and bitangent (from Tuple::orthonormal_basis), blue is ignored.
+    /// Black is no change.
+    pub normal_perturbation: Option<Box<dyn Pattern>>,
     pub ambient: f64,
     pub diffuse: f64,
     pub specular: f64,
     pub shininess: f64,
+    /// 0 for matte, 1 for a perfect mirror
+    pub reflective: f64,
+    /// 0 for opaque, 1 for fully see through
+    pub transparency: f64,
+    /// How much light bends entering the material, 1 for a vacuum (and near enough, air)
+    pub refractive_index: f64,
+    /// What to do when a ray hits the inside of the surface
+    pub sidedness: Sidedness,
+    /// When false the surface is lit as if nothing is ever in the way of a light, and shadow
+    /// rays aren't cast for it at all
+    pub receive_shadows: bool,
+    pub specular_model: SpecularModel,
+    pub diffuse_model: DiffuseModel,
+    pub shading: ShadingMode,
+    /// Light given off by the surface itself. Only path traced renders (see
+    /// scene::RenderMode) use it, the default mode ignores it.
+    pub emissive: Colour,
+}
+
+/// Where eye·normal drops below this, toon outlines are drawn. About 66 degrees off the normal.
+pub const OUTLINE_THRESHOLD: f64 = 0.4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShadingMode {
+    #[default]
+    Standard,
+    /// Cel shading. Diffuse is snapped up to one of `bands` levels, specular is either fully on
+    /// or off, and with `outline` set anywhere seen nearly edge on (see OUTLINE_THRESHOLD) is
+    /// just that colour.
+    Toon { bands: u8, outline: Option<Colour> },
+}
+
+impl ShadingMode {
+    fn diffuse_level(&self, level: f64) -> f64 {
+        match self {
+            ShadingMode::Standard => level,
+            ShadingMode::Toon { bands, .. } => {
+                let bands = (*bands).max(1) as f64;
+                (level * bands).ceil().min(bands) / bands
+            }
+        }
+    }
+
+    fn specular_level(&self, level: f64) -> f64 {
+        match self {
+            ShadingMode::Standard => level,
+            ShadingMode::Toon { .. } if level > 0.5 => 1.0,
+            ShadingMode::Toon { .. } => 0.0,
+        }
+    }
+
+    /// The outline colour, if this mode wants one where the surface has `eye_dot_normal`
+    pub fn outline_at(&self, eye_dot_normal: f64) -> Option<Colour> {
+        match self {
+            ShadingMode::Toon {
+                outline: Some(colour),
+                ..
+            } if eye_dot_normal < OUTLINE_THRESHOLD => Some(*colour),
+            _ => None,
+        }
+    }
+}
+
+/// How the diffuse term is worked out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DiffuseModel {
+    /// Plain cos of the angle to the light, what the book does. Looks like plastic.
+    #[default]
+    Lambert,
+    /// The qualitative Oren-Nayar model, for rough stuff like clay and concrete. Surfaces get
+    /// brighter when the light is behind the eye, especially at grazing angles. `sigma` is the
+    /// roughness in radians (the spread of the microfacet slopes), 0 is exactly Lambert.
+    OrenNayar { sigma: f64 },
+}
+
+impl DiffuseModel {
+    /// What to scale Lambert's diffuse term by. All vectors should be normalised.
+    fn factor(&self, light_vec: Tuple, eye_vec: Tuple, normal_vec: Tuple) -> f64 {
+        let sigma = match self {
+            DiffuseModel::Lambert => return 1.0,
+            DiffuseModel::OrenNayar { sigma } => *sigma,
+        };
+
+        let sigma2 = sigma * sigma;
+        let a = 1.0 - 0.5 * sigma2 / (sigma2 + 0.33);
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let cos_i = light_vec.dot(&normal_vec).clamp(-1.0, 1.0);
+        let cos_r = eye_vec.dot(&normal_vec).clamp(-1.0, 1.0);
+        let (theta_i, theta_r) = (cos_i.acos(), cos_r.acos());
+        let alpha = theta_i.max(theta_r);
+        let beta = theta_i.min(theta_r);
+
+        // cos of the difference in azimuth, from both vectors flattened onto the surface
+        let light_flat = light_vec - normal_vec * cos_i;
+        let eye_flat = eye_vec - normal_vec * cos_r;
+        let cos_phi = if light_flat.magnitude() < EPSILON || eye_flat.magnitude() < EPSILON {
+            0.0
+        } else {
+            light_flat.normalize().dot(&eye_flat.normalize()).max(0.0)
+        };
+
+        a + b * cos_phi * alpha.sin() * beta.tan()
+    }
+}
+
+/// How the specular highlight is worked out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SpecularModel {
+    /// The reflected light vector against the eye, what the book does
+    #[default]
+    Phong,
+    /// The vector halfway between the light and the eye against the normal. That angle is about
+    /// half of Phong's, so shininess is multiplied by 4 here to get roughly the same size of
+    /// highlight.
+    BlinnPhong,
+}
+
+/// Which sides of a surface get shaded. A hit is on the back when the eye is on the opposite
+/// side to the normal, see IntersectionComputions::inside.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sidedness {
+    /// Shade the back the same as the front, with the normal flipped
+    #[default]
+    TwoSided,
+    /// Backs get no surface lighting at all, only whatever is reflected or refracted
+    FrontOnly,
+    /// Backs are lit as normal, but with this colour instead of the material's colour/pattern
+    BackTinted(Colour),
 }
 
 impl Default for Material {
     fn default() -> Self {
         Material {
-            colour: Colour::newi(1, 1, 1),
+            pattern: Box::new(SolidPattern(Colour::WHITE)),
+            normal_perturbation: None,
             ambient: 0.1,
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            sidedness: Sidedness::TwoSided,
+            receive_shadows: true,
+            specular_model: SpecularModel::Phong,
+            diffuse_model: DiffuseModel::Lambert,
+            shading: ShadingMode::Standard,
+            emissive: Colour::BLACK,
         }
     }
 }
 
+/// Presets for the usual suspects. The numbers here are part of the API, changing them changes
+/// every render that uses them, so don't.
+impl Material {
+    /// Clear glass: transparency 1.0, refractive_index 1.5, reflective 0.9, diffuse 0.1,
+    /// ambient 0.0, specular 1.0, shininess 300.0. Colour is white.
+    pub fn glass() -> Self {
+        Material {
+            ambient: 0.0,
+            diffuse: 0.1,
+            specular: 1.0,
+            shininess: 300.0,
+            reflective: 0.9,
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..Default::default()
+        }
+    }
+
+    /// A perfect mirror: reflective 1.0, ambient 0.0, diffuse 0.0, specular 1.0,
+    /// shininess 300.0. All you see is the reflection and the highlight.
+    pub fn mirror() -> Self {
+        Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 1.0,
+            shininess: 300.0,
+            reflective: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// No highlights at all: the defaults with specular 0.0.
+    pub fn matte(colour: Colour) -> Self {
+        Material {
+            specular: 0.0,
+            ..Material::default().with_colour(colour)
+        }
+    }
+
+    /// roughness is clamped to 0..=1, with 0 being polished. Given s = 1 - roughness:
+    /// reflective 0.8 * s, shininess 10 + 290 * s, specular 1 - 0.5 * roughness,
+    /// diffuse 0.3, ambient 0.1.
+    pub fn metal(colour: Colour, roughness: f64) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        let smooth = 1.0 - roughness;
+        Material {
+            ambient: 0.1,
+            diffuse: 0.3,
+            specular: 1.0 - 0.5 * roughness,
+            shininess: 10.0 + 290.0 * smooth,
+            reflective: 0.8 * smooth,
+            ..Material::default().with_colour(colour)
+        }
+    }
+}
+
+/// A Material field outside of the range that makes sense for it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialError {
+    pub field: &'static str,
+    pub value: f64,
+    /// Human readable version of the allowed range, eg "[0, 1]"
+    pub expected: &'static str,
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "material {} must be in {}, got {}",
+            self.field, self.expected, self.value
+        )
+    }
+}
+
+impl core::error::Error for MaterialError {}
+
+fn check_unit(field: &'static str, value: f64) -> Result<(), MaterialError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(MaterialError {
+            field,
+            value,
+            expected: "[0, 1]",
+        })
+    }
+}
+
+fn check_shininess(value: f64) -> Result<(), MaterialError> {
+    if value > 0.0 {
+        Ok(())
+    } else {
+        Err(MaterialError {
+            field: "shininess",
+            value,
+            expected: "(0, inf)",
+        })
+    }
+}
+
+fn check_emissive(value: Colour) -> Result<(), MaterialError> {
+    match [value.red, value.green, value.blue]
+        .into_iter()
+        .find(|c| !(c.is_finite() && *c >= 0.0))
+    {
+        Some(bad) => Err(MaterialError {
+            field: "emissive",
+            value: bad,
+            expected: "[0, inf)",
+        }),
+        None => Ok(()),
+    }
+}
+
+fn check_refractive_index(value: f64) -> Result<(), MaterialError> {
+    if value >= 1.0 {
+        Ok(())
+    } else {
+        Err(MaterialError {
+            field: "refractive_index",
+            value,
+            expected: "[1, inf)",
+        })
+    }
+}
+
+impl Material {
+    /// `normal` run through normal_perturbation, if there is one
+    pub fn perturb_normal(&self, normal: Tuple, object_point: Tuple) -> Tuple {
+        let Some(bump) = &self.normal_perturbation else {
+            return normal;
+        };
+
+        let offset = bump.pattern_at_object(object_point);
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        (normal + tangent * offset.red + bitangent * offset.green).normalize()
+    }
+
+    /// The surface's own colour at `object_point`
+    pub fn colour_at(&self, object_point: Tuple) -> Colour {
+        self.pattern.pattern_at_object(object_point)
+    }
+
+    /// The material in a single colour, replacing whatever pattern it had
+    pub fn with_colour(mut self, colour: Colour) -> Self {
+        self.pattern = Box::new(SolidPattern(colour));
+        self
+    }
+
+    /// The colour, if it's the same everywhere (the pattern is a SolidPattern)
+    pub fn colour(&self) -> Option<Colour> {
+        self.pattern
+            .as_any()
+            .downcast_ref::<SolidPattern>()
+            .map(|solid| solid.0)
+    }
+
+    pub fn builder() -> MaterialBuilder {
+        MaterialBuilder::default()
+    }
+
+    /// Check every numeric field is in range, for materials that didn't come from the
+    /// builder. NaN fails everything.
+    pub fn validate(&self) -> Result<(), MaterialError> {
+        check_unit("ambient", self.ambient)?;
+        check_unit("diffuse", self.diffuse)?;
+        check_unit("specular", self.specular)?;
+        check_shininess(self.shininess)?;
+        check_unit("reflective", self.reflective)?;
+        check_unit("transparency", self.transparency)?;
+        check_refractive_index(self.refractive_index)?;
+        check_emissive(self.emissive)
+    }
+}
+
+/// Builds a Material starting from the defaults. Setters check their value straight away, the
+/// first bad one is what build() reports.
+#[derive(Debug, Clone, Default)]
+pub struct MaterialBuilder {
+    material: Material,
+    error: Option<MaterialError>,
+}
+
+impl MaterialBuilder {
+    fn check(mut self, res: Result<(), MaterialError>) -> Self {
+        if let Err(e) = res {
+            self.error.get_or_insert(e);
+        }
+        self
+    }
+
+    pub fn colour(mut self, colour: Colour) -> Self {
+        self.material = self.material.with_colour(colour);
+        self
+    }
+
+    pub fn pattern(mut self, pattern: impl Pattern + 'static) -> Self {
+        self.material.pattern = Box::new(pattern);
+        self
+    }
+
+    pub fn normal_perturbation(mut self, pattern: impl Pattern + 'static) -> Self {
+        self.material.normal_perturbation = Some(Box::new(pattern));
+        self
+    }
+
+    pub fn ambient(mut self, ambient: f64) -> Self {
+        self.material.ambient = ambient;
+        self.check(check_unit("ambient", ambient))
+    }
+
+    pub fn diffuse(mut self, diffuse: f64) -> Self {
+        self.material.diffuse = diffuse;
+        self.check(check_unit("diffuse", diffuse))
+    }
+
+    pub fn specular(mut self, specular: f64) -> Self {
+        self.material.specular = specular;
+        self.check(check_unit("specular", specular))
+    }
+
+    pub fn shininess(mut self, shininess: f64) -> Self {
+        self.material.shininess = shininess;
+        self.check(check_shininess(shininess))
+    }
+
+    pub fn reflective(mut self, reflective: f64) -> Self {
+        self.material.reflective = reflective;
+        self.check(check_unit("reflective", reflective))
+    }
+
+    pub fn transparency(mut self, transparency: f64) -> Self {
+        self.material.transparency = transparency;
+        self.check(check_unit("transparency", transparency))
+    }
+
+    pub fn refractive_index(mut self, refractive_index: f64) -> Self {
+        self.material.refractive_index = refractive_index;
+        self.check(check_refractive_index(refractive_index))
+    }
+
+    pub fn shading(mut self, shading: ShadingMode) -> Self {
+        self.material.shading = shading;
+        self
+    }
+
+    pub fn diffuse_model(mut self, diffuse_model: DiffuseModel) -> Self {
+        self.material.diffuse_model = diffuse_model;
+        self
+    }
+
+    pub fn specular_model(mut self, specular_model: SpecularModel) -> Self {
+        self.material.specular_model = specular_model;
+        self
+    }
+
+    pub fn receive_shadows(mut self, receive_shadows: bool) -> Self {
+        self.material.receive_shadows = receive_shadows;
+        self
+    }
+
+    pub fn sidedness(mut self, sidedness: Sidedness) -> Self {
+        self.material.sidedness = sidedness;
+        self
+    }
+
+    pub fn emissive(mut self, emissive: Colour) -> Self {
+        self.material.emissive = emissive;
+        self.check(check_emissive(emissive))
+    }
+
+    pub fn build(self) -> Result<Material, MaterialError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.material),
+        }
+    }
+}
+
+/// Material::lighting from one light, in pieces and before shadows. See
+/// Material::lighting_parts.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LightingParts {
+    pub ambient: Colour,
+    pub diffuse: Colour,
+    pub specular: Colour,
+}
+
 impl Material {
+    /// Phong lighting at a single point. `light_intensity` is how much of the light actually
+    /// reaches the point, 0 for fully shadowed up to 1 for unobstructed, and scales the diffuse
+    /// and specular parts. Ambient is always there.
     pub fn lighting(
         &self,
         light: &dyn Light,
         point: Tuple,
+        object_point: Tuple,
         eye_vec: Tuple,
         normal_vec: Tuple,
-        shadowed: bool,
+        light_intensity: f64,
     ) -> Colour {
-        let diffuse: Colour;
-        let specular: Colour;
+        if light_intensity <= 0.0 {
+            // Fast path, object (point) is between light and surface
+            return self.colour_at(object_point) * *light.intensity() * self.ambient;
+        }
+        let parts = self.lighting_parts(light, point, object_point, eye_vec, normal_vec);
+        parts.ambient + (parts.diffuse + parts.specular) * light_intensity
+    }
 
-        let effective_colour = self.colour * *light.intensity();
+    /// The ambient, diffuse and specular parts of lighting, as if nothing were in the way of
+    /// the light
+    pub fn lighting_parts(
+        &self,
+        light: &dyn Light,
+        point: Tuple,
+        object_point: Tuple,
+        eye_vec: Tuple,
+        normal_vec: Tuple,
+    ) -> LightingParts {
+        let effective_colour = self.colour_at(object_point) * *light.intensity();
         let light_vec = (*light.position() - point).normalize();
-        let ambient_light = effective_colour * self.ambient;
+        let ambient = effective_colour * self.ambient;
 
         let light_dot_normal = light_vec.dot(&normal_vec);
-        if light_dot_normal < 0.0 || shadowed {
-            // Fast path, object (point) is between light and surface
-            diffuse = Colour::BLACK;
-            specular = Colour::BLACK;
-        } else {
-            diffuse = effective_colour * self.diffuse * light_dot_normal;
-            let reflect_vec = (-light_vec).reflect(&normal_vec);
-            let reflect_dot_eye = reflect_vec.dot(&eye_vec);
-            specular = if reflect_dot_eye < 0.0 {
-                Colour::BLACK
-            } else {
-                let factor = reflect_dot_eye.powf(self.shininess);
-                *light.intensity() * self.specular * factor
-            }
+        if light_dot_normal < 0.0 {
+            // Facing away from the light
+            return LightingParts {
+                ambient,
+                ..Default::default()
+            };
         }
 
-        ambient_light + diffuse + specular
+        let level = self.shading.diffuse_level(
+            light_dot_normal * self.diffuse_model.factor(light_vec, eye_vec, normal_vec),
+        );
+        let diffuse = effective_colour * self.diffuse * level;
+        let (cos, shininess) = match self.specular_model {
+            SpecularModel::Phong => {
+                let reflect_vec = (-light_vec).reflect(&normal_vec);
+                (reflect_vec.dot(&eye_vec), self.shininess)
+            }
+            SpecularModel::BlinnPhong => {
+                let half_vec = (light_vec + eye_vec).normalize();
+                (half_vec.dot(&normal_vec), self.shininess * 4.0)
+            }
+        };
+        let specular = if cos < 0.0 {
+            Colour::BLACK
+        } else {
+            let factor = self.shading.specular_level(cos.powf(shininess));
+            *light.intensity() * self.specular * factor
+        };
+        LightingParts {
+            ambient,
+            diffuse,
+            specular,
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::colour::Colour;
+    use crate::{colour::Colour, pattern::solid::SolidPattern};
 
-    use super::Material;
+    use super::{DiffuseModel, Material, ShadingMode, Sidedness, SpecularModel};
 
     #[test]
     fn construction_works() {
         let c: Material = Default::default();
 
+        assert!(c.normal_perturbation.is_none());
         assert_eq!(c.ambient, 0.1);
         assert_eq!(c.specular, 0.9);
         assert_eq!(c.shininess, 200.0);
-        assert_eq!(c.colour, Colour::newi(1, 1, 1))
+        assert_eq!(c.reflective, 0.0);
+        assert_eq!(c.transparency, 0.0);
+        assert_eq!(c.refractive_index, 1.0);
+        assert_eq!(c.sidedness, Sidedness::TwoSided);
+        assert!(c.receive_shadows);
+        assert_eq!(c.specular_model, SpecularModel::Phong);
+        assert_eq!(c.diffuse_model, DiffuseModel::Lambert);
+        assert_eq!(c.shading, ShadingMode::Standard);
+        assert_eq!(c.colour(), Some(Colour::newi(1, 1, 1)))
+    }
+
+    mod presets {
+        use super::*;
+
+        #[test]
+        fn glass() {
+            let m = Material::glass();
+
+            assert_eq!(m.colour(), Some(Colour::WHITE));
+            assert_eq!(
+                (m.ambient, m.diffuse, m.specular, m.shininess),
+                (0.0, 0.1, 1.0, 300.0)
+            );
+            assert_eq!(
+                (m.reflective, m.transparency, m.refractive_index),
+                (0.9, 1.0, 1.5)
+            );
+        }
+
+        #[test]
+        fn mirror() {
+            let m = Material::mirror();
+
+            assert_eq!(
+                (m.ambient, m.diffuse, m.specular, m.shininess),
+                (0.0, 0.0, 1.0, 300.0)
+            );
+            assert_eq!(
+                (m.reflective, m.transparency, m.refractive_index),
+                (1.0, 0.0, 1.0)
+            );
+        }
+
+        #[test]
+        fn matte() {
+            let red = Colour::newi(1, 0, 0);
+            let m = Material::matte(red);
+
+            assert_eq!(m.specular, 0.0);
+            assert_eq!(
+                m,
+                Material {
+                    pattern: Box::new(SolidPattern(red)),
+                    specular: 0.0,
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn metal() {
+            let gold = Colour::new(1.0, 0.8, 0.3);
+
+            let polished = Material::metal(gold, 0.0);
+            assert_eq!(polished.colour(), Some(gold));
+            assert_eq!((polished.ambient, polished.diffuse), (0.1, 0.3));
+            assert_eq!(
+                (polished.specular, polished.shininess, polished.reflective),
+                (1.0, 300.0, 0.8)
+            );
+
+            let rough = Material::metal(gold, 1.0);
+            assert_eq!(
+                (rough.specular, rough.shininess, rough.reflective),
+                (0.5, 10.0, 0.0)
+            );
+
+            let half = Material::metal(gold, 0.5);
+            assert_eq!(
+                (half.specular, half.shininess, half.reflective),
+                (0.75, 155.0, 0.4)
+            );
+        }
+
+        #[test]
+        fn metal_clamps_roughness() {
+            let c = Colour::WHITE;
+
+            assert_eq!(Material::metal(c, -1.0), Material::metal(c, 0.0));
+            assert_eq!(Material::metal(c, 3.0), Material::metal(c, 1.0));
+        }
+
+        #[test]
+        fn struct_update() {
+            let tinted = Material {
+                pattern: Box::new(SolidPattern(Colour::new(0.8, 1.0, 0.8))),
+                refractive_index: 1.33,
+                ..Material::glass()
+            };
+
+            assert_eq!(tinted.colour(), Some(Colour::new(0.8, 1.0, 0.8)));
+            assert_eq!(tinted.refractive_index, 1.33);
+            assert_eq!(tinted.transparency, 1.0);
+            assert_eq!(tinted.reflective, 0.9);
+        }
+    }
+
+    mod builder {
+        use crate::pattern::stripe::StripePattern;
+
+        use super::*;
+
+        macro_rules! out_of_range {
+            ($name:ident, $field:ident, $value:expr) => {
+                #[test]
+                fn $name() {
+                    let err = Material::builder().$field($value).build().unwrap_err();
+                    assert_eq!(err.field, stringify!($field));
+
+                    let m = Material {
+                        $field: $value,
+                        ..Default::default()
+                    };
+                    assert_eq!(m.validate().unwrap_err().field, stringify!($field));
+                }
+            };
+        }
+
+        out_of_range!(ambient, ambient, 10.0);
+        out_of_range!(ambient_negative, ambient, -0.1);
+        out_of_range!(diffuse, diffuse, 1.5);
+        out_of_range!(specular, specular, -1.0);
+        out_of_range!(shininess, shininess, -3.0);
+        out_of_range!(shininess_zero, shininess, 0.0);
+        out_of_range!(reflective, reflective, 2.0);
+        out_of_range!(transparency, transparency, -0.5);
+        out_of_range!(refractive_index, refractive_index, 0.5);
+        out_of_range!(nan, diffuse, f64::NAN);
+        out_of_range!(emissive, emissive, Colour::new(1.0, -0.5, 0.0));
+        out_of_range!(
+            emissive_infinite,
+            emissive,
+            Colour::new(f64::INFINITY, 0.0, 0.0)
+        );
+
+        #[test]
+        fn valid_matches_literal() {
+            let built = Material::builder()
+                .colour(Colour::new(0.8, 1.0, 0.6))
+                .pattern(StripePattern::new(Colour::WHITE, Colour::BLACK))
+                .ambient(0.2)
+                .diffuse(0.7)
+                .specular(0.3)
+                .shininess(50.0)
+                .reflective(0.1)
+                .transparency(0.5)
+                .refractive_index(1.33)
+                .emissive(Colour::new(2.0, 2.0, 1.5))
+                .build();
+
+            let literal = Material {
+                pattern: Box::new(StripePattern::new(Colour::WHITE, Colour::BLACK)),
+                normal_perturbation: None,
+                ambient: 0.2,
+                diffuse: 0.7,
+                specular: 0.3,
+                shininess: 50.0,
+                reflective: 0.1,
+                transparency: 0.5,
+                refractive_index: 1.33,
+                sidedness: Default::default(),
+                receive_shadows: true,
+                specular_model: Default::default(),
+                diffuse_model: Default::default(),
+                shading: Default::default(),
+                emissive: Colour::new(2.0, 2.0, 1.5),
+            };
+
+            assert_eq!(built, Ok(literal));
+        }
+
+        #[test]
+        fn first_error_wins() {
+            let err = Material::builder()
+                .ambient(5.0)
+                .shininess(-1.0)
+                .build()
+                .unwrap_err();
+
+            assert_eq!(err.field, "ambient");
+            assert_eq!(err.to_string(), "material ambient must be in [0, 1], got 5");
+        }
+
+        #[test]
+        fn presets_are_valid() {
+            assert_eq!(Material::default().validate(), Ok(()));
+            assert_eq!(Material::glass().validate(), Ok(()));
+            assert_eq!(Material::mirror().validate(), Ok(()));
+            assert_eq!(Material::matte(Colour::WHITE).validate(), Ok(()));
+            assert_eq!(Material::metal(Colour::WHITE, 0.3).validate(), Ok(()));
+        }
     }
 
     mod lighting {
@@ -79,9 +772,10 @@ mod test {
 
         use crate::{
             colour::Colour,
-            lights::PointLight,
+            lights::{Light, PointLight},
             materials::Material,
-            math::tuple::{pointi, vectori, Tuple, ZERO_POINT},
+            math::tuple::{point, pointi, vector, vectori, Tuple, ZERO_POINT},
+            pattern::stripe::StripePattern,
         };
 
         #[test]
@@ -91,7 +785,7 @@ mod test {
             let normal_vec = vectori(0, 0, -1);
             let light = PointLight::new(Colour::newi(1, 1, 1), pointi(0, 0, -10));
 
-            let res = m.lighting(&light, position, eye_vec, normal_vec, false);
+            let res = m.lighting(&light, position, position, eye_vec, normal_vec, 1.0);
             assert_eq!(res, Colour::new(1.9, 1.9, 1.9))
         }
         #[test]
@@ -101,7 +795,7 @@ mod test {
             let normal_vec = vectori(0, 0, -1);
             let light = PointLight::new(Colour::newi(1, 1, 1), pointi(0, 0, -10));
 
-            let res = m.lighting(&light, position, eye_vec, normal_vec, false);
+            let res = m.lighting(&light, position, position, eye_vec, normal_vec, 1.0);
             assert_eq!(res, Colour::new(1.0, 1.0, 1.0))
         }
 
@@ -112,7 +806,7 @@ mod test {
             let normal_vec = vectori(0, 0, -1);
             let light = PointLight::new(Colour::newi(1, 1, 1), pointi(0, 10, -10));
 
-            let res = m.lighting(&light, position, eye_vec, normal_vec, false);
+            let res = m.lighting(&light, position, position, eye_vec, normal_vec, 1.0);
             assert_eq!(res, Colour::new(0.7364, 0.7364, 0.7364))
         }
 
@@ -123,7 +817,7 @@ mod test {
             let normal_vec = vectori(0, 0, -1);
             let light = PointLight::new(Colour::newi(1, 1, 1), pointi(0, 10, -10));
 
-            let res = m.lighting(&light, position, eye_vec, normal_vec, false);
+            let res = m.lighting(&light, position, position, eye_vec, normal_vec, 1.0);
             assert_eq!(res, Colour::new(1.6364, 1.6364, 1.6364))
         }
 
@@ -134,10 +828,270 @@ mod test {
             let normal_vec = vectori(0, 0, -1);
             let light = PointLight::new(Colour::newi(1, 1, 1), pointi(0, 0, 10));
 
-            let res = m.lighting(&light, position, eye_vec, normal_vec, false);
+            let res = m.lighting(&light, position, position, eye_vec, normal_vec, 1.0);
             assert_eq!(res, Colour::new(0.1, 0.1, 0.1))
         }
 
+        #[test]
+        fn with_pattern() {
+            let m = Material {
+                pattern: Box::new(StripePattern::new(Colour::WHITE, Colour::BLACK)),
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            };
+            let eye_vec = vectori(0, 0, -1);
+            let normal_vec = vectori(0, 0, -1);
+            let light = PointLight::new(Colour::WHITE, pointi(0, 0, -10));
+            let (p1, p2) = (point(0.9, 0.0, 0.0), point(1.1, 0.0, 0.0));
+
+            let c1 = m.lighting(&light, p1, p1, eye_vec, normal_vec, 1.0);
+            let c2 = m.lighting(&light, p2, p2, eye_vec, normal_vec, 1.0);
+
+            assert_eq!(c1, Colour::WHITE);
+            assert_eq!(c2, Colour::BLACK);
+        }
+
+        #[test]
+        fn pattern_uses_object_point() {
+            let m = Material {
+                pattern: Box::new(StripePattern::new(Colour::WHITE, Colour::BLACK)),
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            };
+            let light = PointLight::new(Colour::WHITE, pointi(0, 0, -10));
+
+            let c = m.lighting(
+                &light,
+                point(0.9, 0.0, 0.0),
+                point(1.1, 0.0, 0.0),
+                vectori(0, 0, -1),
+                vectori(0, 0, -1),
+                1.0,
+            );
+
+            assert_eq!(c, Colour::BLACK);
+        }
+
+        #[test]
+        fn clone_and_compare() {
+            let m = Material {
+                pattern: Box::new(StripePattern::new(Colour::WHITE, Colour::BLACK)),
+                ..Default::default()
+            };
+
+            assert_eq!(m.clone(), m);
+            assert_ne!(m, Material::default());
+            assert_ne!(
+                m,
+                Material {
+                    pattern: Box::new(StripePattern::new(Colour::BLACK, Colour::WHITE)),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn attenuated_by_intensity() {
+            let m = Material {
+                ambient: 0.1,
+                diffuse: 0.9,
+                specular: 0.0,
+                ..Default::default()
+            };
+            let light = PointLight::new(Colour::WHITE, pointi(0, 0, -10));
+            let (position, eye_vec, normal_vec) =
+                (pointi(0, 0, -1), vectori(0, 0, -1), vectori(0, 0, -1));
+
+            for (intensity, expected) in [(1.0, 1.0), (0.5, 0.55), (0.0, 0.1)] {
+                let res = m.lighting(&light, position, position, eye_vec, normal_vec, intensity);
+                assert_eq!(
+                    res,
+                    Colour::new(expected, expected, expected),
+                    "{intensity}"
+                );
+            }
+        }
+
+        #[test]
+        fn boxed_trait_object() {
+            let lights: Vec<Box<dyn Light>> =
+                vec![PointLight::new_boxed(Colour::WHITE, pointi(0, 0, -10))];
+            let m = Material::default();
+
+            let res = m.lighting(
+                &*lights[0],
+                ZERO_POINT,
+                ZERO_POINT,
+                vectori(0, 0, -1),
+                vectori(0, 0, -1),
+                1.0,
+            );
+            assert_eq!(res, Colour::new(1.9, 1.9, 1.9))
+        }
+
+        mod blinn_phong {
+            use crate::materials::SpecularModel;
+
+            use super::*;
+
+            fn both(eye_vec: Tuple, shininess: f64) -> (Colour, Colour) {
+                let phong = Material {
+                    shininess,
+                    ..Default::default()
+                };
+                let blinn = Material {
+                    specular_model: SpecularModel::BlinnPhong,
+                    ..phong.clone()
+                };
+                let light = PointLight::new(Colour::WHITE, pointi(0, 0, -10));
+                let normal_vec = vectori(0, 0, -1);
+
+                (
+                    phong.lighting(&light, ZERO_POINT, ZERO_POINT, eye_vec, normal_vec, 1.0),
+                    blinn.lighting(&light, ZERO_POINT, ZERO_POINT, eye_vec, normal_vec, 1.0),
+                )
+            }
+
+            #[test]
+            fn normal_incidence_matches_phong() {
+                let (phong, blinn) = both(vectori(0, 0, -1), 200.0);
+
+                assert_eq!(phong, Colour::new(1.9, 1.9, 1.9));
+                assert_eq!(blinn, phong);
+            }
+
+            #[test]
+            fn eye_45_degrees() {
+                // Phong sees cos(45)^10, Blinn cos(22.5)^40
+                let (phong, blinn) = both(vector(0.0, SQRT_2 / 2.0, -SQRT_2 / 2.0), 10.0);
+
+                assert_eq!(phong, Colour::new(1.02813, 1.02813, 1.02813));
+                assert_eq!(blinn, Colour::new(1.03792, 1.03792, 1.03792));
+            }
+        }
+
+        mod oren_nayar {
+            use crate::materials::DiffuseModel;
+
+            use super::*;
+
+            /// Diffuse only, light and eye both 60 degrees off the normal on the same side
+            fn grazing(diffuse_model: DiffuseModel) -> Colour {
+                let m = Material {
+                    ambient: 0.0,
+                    specular: 0.0,
+                    diffuse_model,
+                    ..Default::default()
+                };
+                let dir = vector(0.0, 3_f64.sqrt() / 2.0, -0.5);
+                let light = PointLight::new(Colour::WHITE, ZERO_POINT + dir * 10.0);
+
+                m.lighting(&light, ZERO_POINT, ZERO_POINT, dir, vectori(0, 0, -1), 1.0)
+            }
+
+            #[test]
+            fn sigma_zero_is_lambert() {
+                assert_eq!(
+                    grazing(DiffuseModel::OrenNayar { sigma: 0.0 }),
+                    grazing(DiffuseModel::Lambert)
+                );
+            }
+
+            #[test]
+            fn retro_reflective() {
+                let lambert = grazing(DiffuseModel::Lambert);
+                let rough = grazing(DiffuseModel::OrenNayar { sigma: 0.5 });
+
+                // A + B * sin(60) * tan(60) = A + 1.5B, with sigma^2 = 0.25
+                let a = 1.0 - 0.5 * 0.25 / 0.58;
+                let b = 0.45 * 0.25 / 0.34;
+                assert_eq!(lambert, Colour::new(0.45, 0.45, 0.45));
+                assert_eq!(rough, lambert * (a + 1.5 * b));
+            }
+
+            #[test]
+            fn light_opposite_eye_is_darker() {
+                // Different azimuths, the B term drops out and only A (< 1) is left
+                let m = Material {
+                    ambient: 0.0,
+                    specular: 0.0,
+                    diffuse_model: DiffuseModel::OrenNayar { sigma: 0.5 },
+                    ..Default::default()
+                };
+                let light = PointLight::new(Colour::WHITE, point(0.0, 10.0, -10.0));
+                let eye = vector(0.0, -SQRT_2 / 2.0, -SQRT_2 / 2.0);
+
+                let c = m.lighting(&light, ZERO_POINT, ZERO_POINT, eye, vectori(0, 0, -1), 1.0);
+                assert!(c.red < 0.9 * SQRT_2 / 2.0);
+            }
+        }
+
+        mod toon {
+            use crate::materials::{ShadingMode, OUTLINE_THRESHOLD};
+
+            use super::*;
+
+            const TOON: ShadingMode = ShadingMode::Toon {
+                bands: 2,
+                outline: None,
+            };
+
+            #[test]
+            fn bands() {
+                assert_eq!(TOON.diffuse_level(0.1), 0.5);
+                assert_eq!(TOON.diffuse_level(0.5), 0.5);
+                assert_eq!(TOON.diffuse_level(0.51), 1.0);
+                assert_eq!(TOON.diffuse_level(1.0), 1.0);
+                assert_eq!(TOON.diffuse_level(0.0), 0.0);
+            }
+
+            #[test]
+            fn hard_specular() {
+                assert_eq!(TOON.specular_level(0.3), 0.0);
+                assert_eq!(TOON.specular_level(0.7), 1.0);
+                assert_eq!(ShadingMode::Standard.specular_level(0.3), 0.3);
+            }
+
+            #[test]
+            fn outline() {
+                let mode = ShadingMode::Toon {
+                    bands: 2,
+                    outline: Some(Colour::BLACK),
+                };
+
+                assert_eq!(
+                    mode.outline_at(OUTLINE_THRESHOLD / 2.0),
+                    Some(Colour::BLACK)
+                );
+                assert_eq!(mode.outline_at(1.0), None);
+                assert_eq!(TOON.outline_at(0.0), None);
+            }
+
+            #[test]
+            fn lighting() {
+                let m = Material {
+                    shading: TOON,
+                    ..Default::default()
+                };
+                let light = PointLight::new(Colour::WHITE, pointi(0, 10, -10));
+
+                // Straight on, specular is ~0 and gets dropped, diffuse 0.707 snaps up to 1
+                let c = m.lighting(
+                    &light,
+                    ZERO_POINT,
+                    ZERO_POINT,
+                    vectori(0, 0, -1),
+                    vectori(0, 0, -1),
+                    1.0,
+                );
+                assert_eq!(c, Colour::new(1.0, 1.0, 1.0));
+            }
+        }
+
         #[test]
         fn in_shadow() {
             let (m, position): (Material, Tuple) = (Default::default(), ZERO_POINT);
@@ -145,7 +1099,7 @@ mod test {
             let normal_vec = vectori(0, 0, -1);
             let light = PointLight::new(Colour::newi(1, 1, 1), pointi(0, 0, -10));
 
-            let res = m.lighting(&light, position, eye_vec, normal_vec, true);
+            let res = m.lighting(&light, position, position, eye_vec, normal_vec, 0.0);
             assert_eq!(res, Colour::new(0.1, 0.1, 0.1))
         }
     }
@@ -1,3 +1,7 @@
 pub mod float;
 pub mod matrix;
+pub mod noise;
+pub mod point;
+pub mod rng;
+pub mod sampling;
 pub mod tuple;
@@ -2,7 +2,87 @@ pub const MAX_DIFF: f64 = 0.00001;
 pub const EPSILON: f64 = MAX_DIFF;
 
 pub fn equal(a: f64, b: f64) -> bool {
-    (a - b).abs() < MAX_DIFF
+    equal_within(a, b, MAX_DIFF)
+}
+
+/// equal, but with a caller chosen tolerance
+pub fn equal_within(a: f64, b: f64, eps: f64) -> bool {
+    (a - b).abs() < eps
+}
+
+/// f64's maths methods, which only exist with std. Without it these stand in (via libm), so
+/// `x.sqrt()` and friends read the same either way.
+#[cfg(not(feature = "std"))]
+pub(crate) trait FloatExt {
+    fn sqrt(self) -> f64;
+    fn powi(self, n: i32) -> f64;
+    fn powf(self, n: f64) -> f64;
+    fn sin(self) -> f64;
+    fn cos(self) -> f64;
+    fn tan(self) -> f64;
+    fn acos(self) -> f64;
+    fn atan2(self, other: f64) -> f64;
+    fn exp(self) -> f64;
+    fn ln(self) -> f64;
+    fn sin_cos(self) -> (f64, f64);
+    fn floor(self) -> f64;
+    fn ceil(self) -> f64;
+    fn round(self) -> f64;
+    fn rem_euclid(self, rhs: f64) -> f64;
+}
+
+#[cfg(not(feature = "std"))]
+impl FloatExt for f64 {
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, n as f64)
+    }
+    fn powf(self, n: f64) -> f64 {
+        libm::pow(self, n)
+    }
+    fn sin(self) -> f64 {
+        libm::sin(self)
+    }
+    fn cos(self) -> f64 {
+        libm::cos(self)
+    }
+    fn tan(self) -> f64 {
+        libm::tan(self)
+    }
+    fn acos(self) -> f64 {
+        libm::acos(self)
+    }
+    fn atan2(self, other: f64) -> f64 {
+        libm::atan2(self, other)
+    }
+    fn exp(self) -> f64 {
+        libm::exp(self)
+    }
+    fn ln(self) -> f64 {
+        libm::log(self)
+    }
+    fn sin_cos(self) -> (f64, f64) {
+        libm::sincos(self)
+    }
+    fn floor(self) -> f64 {
+        libm::floor(self)
+    }
+    fn ceil(self) -> f64 {
+        libm::ceil(self)
+    }
+    fn round(self) -> f64 {
+        libm::round(self)
+    }
+    fn rem_euclid(self, rhs: f64) -> f64 {
+        let r = self % rhs;
+        if r < 0.0 {
+            r + rhs.abs()
+        } else {
+            r
+        }
+    }
 }
 
 #[test]
@@ -1,15 +1,19 @@
-use std::{
+use alloc::borrow::Cow;
+use core::{
     ops::{Index, IndexMut, Mul},
     str::FromStr,
-    sync::LazyLock,
 };
 
 use super::{float, tuple::Tuple};
+use crate::error::RayTracerError;
+use crate::prelude::*;
+
 mod transform;
 
 #[derive(Clone, Debug)]
 pub struct Matrix {
-    data: Vec<f64>,
+    /// Borrowed only for constants like IDENTITY_4X4, anything that writes to it gets its own copy
+    data: Cow<'static, [f64]>,
     width: usize,
     height: usize,
 }
@@ -38,13 +42,30 @@ impl Matrix {
         Self::new_with_data(width, height, vec![0.0; width * height])
     }
 
+    /// Panicking version of try_new_with_data
     pub fn new_with_data(width: usize, height: usize, data: Vec<f64>) -> Self {
-        assert_eq!(width * height, data.len());
-        Self {
+        Self::try_new_with_data(width, height, data).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// `data` is row major, and must be exactly width * height long
+    pub fn try_new_with_data(
+        width: usize,
+        height: usize,
+        data: Vec<f64>,
+    ) -> Result<Self, RayTracerError> {
+        if width * height != data.len() {
+            return Err(RayTracerError::MatrixDimensions {
+                width,
+                height,
+                len: data.len(),
+            });
+        }
+
+        Ok(Self {
             width,
             height,
-            data,
-        }
+            data: data.into(),
+        })
     }
 
     pub fn new_with_datai(width: usize, height: usize, data: Vec<i32>) -> Self {
@@ -55,7 +76,7 @@ impl Matrix {
         (width * row) + col
     }
 
-    pub fn col(&self, col: usize) -> Ref {
+    pub fn col(&self, col: usize) -> Ref<'_> {
         Ref {
             data: &self.data[col..],
             stride: self.width,
@@ -63,7 +84,7 @@ impl Matrix {
         }
     }
 
-    pub fn row(&self, row: usize) -> Ref {
+    pub fn row(&self, row: usize) -> Ref<'_> {
         let start = self.width * row;
         Ref {
             data: &self.data[start..start + self.width],
@@ -86,6 +107,9 @@ impl Matrix {
 
     pub fn determinate(&self) -> f64 {
         match (self.width, self.height) {
+            // What's left of a 1x1 once its only row and column are taken out
+            (0, 0) => 1.0,
+            (1, 1) => self[0],
             (2, 2) => (self[0] * self[3]) - (self[1] * self[2]),
             _ => self
                 .row(0)
@@ -119,18 +143,41 @@ impl Matrix {
         }
     }
 
+    pub fn is_finite(&self) -> bool {
+        self.data.iter().all(|v| v.is_finite())
+    }
+
+    /// Exactly the identity, not just within epsilon of it
+    pub fn is_identity(&self) -> bool {
+        self.width == self.height
+            && self
+                .data
+                .iter()
+                .enumerate()
+                .all(|(i, v)| *v == if i % (self.width + 1) == 0 { 1.0 } else { 0.0 })
+    }
+
+    /// Only square matrices can be inverted, and then only if their determinate isn't 0
     pub fn can_invert(&self) -> bool {
-        self.determinate() != 0.0
+        self.width == self.height && self.determinate() != 0.0
     }
 
+    /// None unless can_invert
     pub fn inverse(&self) -> Option<Matrix> {
+        if self.width != self.height {
+            return None;
+        }
+        if self.is_identity() {
+            return Some(self.clone());
+        }
+
         let determinate = self.determinate();
 
         if determinate == 0.0 {
             return None;
         }
 
-        let mut out = Matrix::new(4, 4);
+        let mut out = Matrix::new(self.width, self.height);
         for row in 0..self.height {
             for col in 0..self.width {
                 let cofactor = self.cofactor(row, col);
@@ -142,15 +189,24 @@ impl Matrix {
 
         Some(out)
     }
+
+    /// The `size` x `size` identity, see IDENTITY_4X4 for the usual one
+    pub fn identity(size: usize) -> Matrix {
+        let mut out = Matrix::new(size, size);
+        for i in 0..size {
+            out[(i, i)] = 1.0;
+        }
+        out
+    }
 }
 
-pub static IDENTITY_4X4: LazyLock<Matrix> = LazyLock::new(|| Matrix {
+pub const IDENTITY_4X4: Matrix = Matrix {
     width: 4,
     height: 4,
-    data: vec![
+    data: Cow::Borrowed(&[
         1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
-    ],
-});
+    ]),
+};
 
 impl FromStr for Matrix {
     type Err = String;
@@ -275,17 +331,18 @@ impl PartialEq for Matrix {
 }
 
 macro_rules! indexer {
-    ($typ:ty, $self:ident, $index:ident, $impl:expr) => {
+    ($typ:ty, $self:ident, $index:ident, $offset:expr) => {
         impl Index<$typ> for Matrix {
             type Output = f64;
             fn index(&$self, $index: $typ) -> &Self::Output {
-                &$impl
+                &$self.data[$offset]
             }
         }
 
         impl IndexMut<$typ> for Matrix {
             fn index_mut(&mut $self, $index: $typ) -> &mut Self::Output {
-                &mut $impl
+                let offset = $offset;
+                &mut $self.data.to_mut()[offset]
             }
         }
     };
@@ -301,17 +358,47 @@ indexer!(
     (usize, usize),
     self,
     index,
-    self.data[Self::make_index(self.width, index.1, index.0)]
+    Self::make_index(self.width, index.1, index.0)
 );
 
-indexer!(usize, self, index, self.data[index]);
+indexer!(usize, self, index, index);
 
 #[cfg(test)]
 mod test {
-    use crate::math::{matrix::IDENTITY_4X4, tuple::Tuple};
+    use crate::{
+        error::RayTracerError,
+        math::{matrix::IDENTITY_4X4, tuple::Tuple},
+    };
 
     use super::Matrix;
 
+    #[test]
+    fn is_identity() {
+        assert!(IDENTITY_4X4.is_identity());
+        assert!(Matrix::new_with_datai(2, 2, vec![1, 0, 0, 1]).is_identity());
+        assert!(!Matrix::translationi(0, 0, 1).is_identity());
+        assert!(!Matrix::scaling(1.0 + 1e-9, 1.0, 1.0).is_identity());
+        assert!(!Matrix::new_with_datai(2, 1, vec![1, 0]).is_identity());
+        assert_eq!(IDENTITY_4X4.inverse(), Some(IDENTITY_4X4.clone()));
+    }
+
+    #[test]
+    fn try_new_with_data() {
+        let m = Matrix::try_new_with_data(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        assert_eq!(m, Matrix::new_with_datai(2, 3, vec![1, 2, 3, 4, 5, 6]));
+
+        let err = Matrix::try_new_with_data(2, 3, vec![1.0; 4]).unwrap_err();
+        assert_eq!(
+            err,
+            RayTracerError::MatrixDimensions {
+                width: 2,
+                height: 3,
+                len: 4
+            }
+        );
+        assert_eq!(err.to_string(), "a 2x3 matrix needs 6 values, got 4");
+    }
+
     #[test]
     fn parse() {
         let m: Matrix = "\
@@ -477,7 +564,7 @@ mod test {
             .parse()
             .unwrap();
 
-        assert_eq!(&m * &*IDENTITY_4X4, m);
+        assert_eq!(&m * &IDENTITY_4X4, m);
     }
 
     #[test]
@@ -501,7 +588,7 @@ mod test {
 
     #[test]
     fn transpose_ident() {
-        assert_eq!(IDENTITY_4X4.transpose(), *IDENTITY_4X4)
+        assert_eq!(IDENTITY_4X4.transpose(), IDENTITY_4X4)
     }
 
     #[test]
@@ -674,4 +761,80 @@ mod test {
         let c = &a * &b;
         assert_eq!(c * b.inverse().unwrap(), a)
     }
+
+    #[test]
+    fn inverse_small() {
+        let m = Matrix::new_with_datai(2, 2, vec![4, 7, 2, 6]);
+        let expected = Matrix::new_with_data(2, 2, vec![0.6, -0.7, -0.2, 0.4]);
+        assert_eq!(m.inverse(), Some(expected));
+
+        let m = Matrix::new_with_datai(3, 3, vec![2, 0, 1, 1, 3, 2, 1, 1, 1]);
+        // Determinate 2 * (3 - 2) - 0 + 1 * (1 - 3) = 0
+        assert!(!m.can_invert());
+        assert_eq!(m.inverse(), None);
+        let m = Matrix::new_with_datai(3, 3, vec![2, 0, 1, 1, 3, 2, 1, 1, 2]);
+        assert_eq!(m.determinate(), 6.0);
+        let expected = Matrix::new_with_data(
+            3,
+            3,
+            vec![4.0, 1.0, -3.0, 0.0, 3.0, -3.0, -2.0, -2.0, 6.0]
+                .into_iter()
+                .map(|v| v / 6.0)
+                .collect(),
+        );
+        assert_eq!(m.inverse(), Some(expected));
+
+        let m = Matrix::new_with_datai(1, 1, vec![4]);
+        assert_eq!(m.inverse(), Some(Matrix::new_with_data(1, 1, vec![0.25])));
+    }
+
+    #[test]
+    fn inverse_of_a_submatrix() {
+        // The rotation part of a transform is a 3x3, and so's its inverse
+        let rotation = Matrix::rotation_y(0.7).submatrix(3, 3);
+        let inverse = rotation.inverse().unwrap();
+        assert_eq!(inverse, Matrix::rotation_y(-0.7).submatrix(3, 3));
+        assert_eq!(rotation * inverse, Matrix::identity(3));
+    }
+
+    #[test]
+    fn inverse_times_itself() {
+        for (n, data) in [
+            (2, vec![3, -1, 5, 2]),
+            (3, vec![1, 2, 3, 0, 1, 4, 5, 6, 0]),
+            (
+                4,
+                vec![-5, 2, 6, -8, 1, -5, 1, 8, 7, 7, -6, -7, 1, -3, 7, 4],
+            ),
+        ] {
+            let m = Matrix::new_with_datai(n, n, data);
+            let inverse = m.inverse().unwrap();
+            assert_eq!(&m * &inverse, Matrix::identity(n), "{n}");
+            assert_eq!(inverse * m, Matrix::identity(n), "{n}");
+        }
+        assert_eq!(Matrix::identity(4), IDENTITY_4X4);
+        assert!(Matrix::identity(3).is_identity());
+    }
+
+    #[test]
+    fn only_square_matrices_invert() {
+        let wide = Matrix::new_with_datai(3, 2, vec![1, 0, 0, 0, 1, 0]);
+        assert!(!wide.can_invert());
+        assert_eq!(wide.inverse(), None);
+        let tall = Matrix::new_with_datai(2, 3, vec![1, 0, 0, 1, 0, 0]);
+        assert!(!tall.can_invert());
+        assert_eq!(tall.inverse(), None);
+
+        // can_invert always agrees with inverse
+        for m in [
+            Matrix::new_with_datai(2, 2, vec![1, 2, 2, 4]),
+            Matrix::new_with_datai(2, 2, vec![1, 2, 3, 4]),
+            Matrix::new_with_datai(3, 3, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]),
+            Matrix::scalingi(1, 0, 1),
+            Matrix::translationi(1, 2, 3),
+            IDENTITY_4X4,
+        ] {
+            assert_eq!(m.can_invert(), m.inverse().is_some(), "{m:?}");
+        }
+    }
 }
@@ -2,6 +2,7 @@ use crate::math::tuple::Tuple;
 
 use super::Matrix;
 use super::IDENTITY_4X4;
+use crate::prelude::*;
 impl Matrix {
     pub fn translation(x: f64, y: f64, z: f64) -> Self {
         let mut base = IDENTITY_4X4.clone();
@@ -312,7 +313,7 @@ mod test {
 
             let t = Matrix::view_transform(from, to, up);
 
-            assert_eq!(t, *IDENTITY_4X4);
+            assert_eq!(t, IDENTITY_4X4);
         }
 
         #[test]
@@ -0,0 +1,135 @@
+//! Smooth pseudo-random noise over 3D space, for patterns that shouldn't look too regular
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+use crate::math::rng::Rng;
+
+/// Ken Perlin's improved noise, with the permutation shuffled from a seed rather than his fixed
+/// one. The same seed always gives the same noise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Perlin {
+    /// A shuffle of 0..=255, twice over so lookups can run past the end
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut shuffled: [u8; 256] = core::array::from_fn(|i| i as u8);
+        for i in (1..shuffled.len()).rev() {
+            let j = (rng.next_f64() * (i + 1) as f64) as usize;
+            shuffled.swap(i, j);
+        }
+        Self {
+            permutation: core::array::from_fn(|i| shuffled[i % 256]),
+        }
+    }
+
+    /// Roughly -1 to 1, varying smoothly, and exactly 0 on whole numbered points
+    pub fn noise(&self, x: f64, y: f64, z: f64) -> f64 {
+        let p = &self.permutation;
+        let cell = |v: f64| (v.floor() as i64 & 255) as usize;
+        let (xi, yi, zi) = (cell(x), cell(y), cell(z));
+        let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        // Hashes for the cell's eight corners
+        let a = p[xi] as usize + yi;
+        let (aa, ab) = (p[a] as usize + zi, p[a + 1] as usize + zi);
+        let b = p[xi + 1] as usize + yi;
+        let (ba, bb) = (p[b] as usize + zi, p[b + 1] as usize + zi);
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p[ab], x, y - 1.0, z),
+                    grad(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+}
+
+/// 6t^5 - 15t^4 + 10t^3, which eases in and out of each cell
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// The dot product of (x, y, z) with one of 12 gradient directions, picked by `hash`
+fn grad(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod test {
+    use super::Perlin;
+
+    fn samples() -> impl Iterator<Item = (f64, f64, f64)> {
+        (0..500).map(|i| {
+            let i = i as f64;
+            (i * 0.37 - 40.0, i * 0.11, (i * 0.73).sin() * 9.0)
+        })
+    }
+
+    #[test]
+    fn repeatable() {
+        let (a, b, c) = (Perlin::new(7), Perlin::new(7), Perlin::new(8));
+        assert_eq!(a, b);
+        assert!(samples().all(|(x, y, z)| a.noise(x, y, z) == b.noise(x, y, z)));
+        assert!(samples().any(|(x, y, z)| a.noise(x, y, z) != c.noise(x, y, z)));
+    }
+
+    #[test]
+    fn in_range() {
+        let n = Perlin::new(1);
+        let values: Vec<_> = samples().map(|(x, y, z)| n.noise(x, y, z)).collect();
+        assert!(values.iter().all(|v| (-1.0..=1.0).contains(v)));
+        // and not stuck at 0
+        assert!(values.iter().any(|v| v.abs() > 0.1));
+    }
+
+    #[test]
+    fn zero_on_the_lattice() {
+        let n = Perlin::new(3);
+        for (x, y, z) in [(0.0, 0.0, 0.0), (1.0, 2.0, 3.0), (-5.0, 7.0, -1.0)] {
+            assert_eq!(n.noise(x, y, z), 0.0);
+        }
+    }
+
+    #[test]
+    fn smooth() {
+        let n = Perlin::new(5);
+        for (x, y, z) in samples() {
+            let step = (n.noise(x + 1e-4, y, z) - n.noise(x, y, z)).abs();
+            assert!(step < 1e-3, "{x} {y} {z}: {step}");
+        }
+    }
+}
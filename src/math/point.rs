@@ -0,0 +1,325 @@
+//! Points and vectors as their own types, so mixing them up is a compile error rather than a
+//! w of 2 somewhere down the line. Both deref to the Tuple underneath for everything that still
+//! works in tuples.
+//!
+//! ```compile_fail
+//! # use raytracer::math::point::Point;
+//! // Adding two points means nothing
+//! let _ = Point::newi(1, 2, 3) + Point::newi(4, 5, 6);
+//! ```
+//!
+//! ```compile_fail
+//! # use raytracer::math::point::{Point, Vector};
+//! // A vector doesn't move anywhere by adding a point to it
+//! let _: Vector = Vector::newi(1, 2, 3) + Point::newi(4, 5, 6);
+//! ```
+//!
+//! ```compile_fail
+//! # use raytracer::math::point::{Point, Vector};
+//! // Moving a point along a vector gets another point
+//! let _: Vector = Point::newi(1, 2, 3) - Vector::newi(4, 5, 6);
+//! ```
+
+use core::ops::{Add, Deref, Div, Mul, Neg, Sub};
+
+use super::{matrix::Matrix, tuple::Tuple};
+use crate::error::RayTracerError;
+
+/// A Tuple with w = 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Tuple", into = "Tuple"))]
+pub struct Point(Tuple);
+
+/// A Tuple with w = 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Tuple", into = "Tuple"))]
+pub struct Vector(Tuple);
+
+impl Point {
+    pub const ORIGIN: Point = Point::newi(0, 0, 0);
+
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Point(Tuple::point(x, y, z))
+    }
+
+    pub const fn newi(x: i32, y: i32, z: i32) -> Self {
+        Point(Tuple::pointi(x, y, z))
+    }
+
+    /// Panicking version of try_from, for tuples that are points by construction
+    pub fn from_tuple(tuple: Tuple) -> Self {
+        Self::try_from(tuple).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn tuple(self) -> Tuple {
+        self.0
+    }
+}
+
+impl Vector {
+    pub const ZERO: Vector = Vector::newi(0, 0, 0);
+
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Vector(Tuple::vector(x, y, z))
+    }
+
+    pub const fn newi(x: i32, y: i32, z: i32) -> Self {
+        Vector(Tuple::vectori(x, y, z))
+    }
+
+    /// Panicking version of try_from, for tuples that are vectors by construction
+    pub fn from_tuple(tuple: Tuple) -> Self {
+        Self::try_from(tuple).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn tuple(self) -> Tuple {
+        self.0
+    }
+
+    pub fn normalize(&self) -> Self {
+        Vector(self.0.normalize())
+    }
+
+    pub fn cross(&self, other: &Vector) -> Vector {
+        Vector::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn reflect(&self, normal: &Vector) -> Vector {
+        *self - *normal * (2.0 * self.dot(normal))
+    }
+}
+
+impl TryFrom<Tuple> for Point {
+    type Error = RayTracerError;
+
+    fn try_from(tuple: Tuple) -> Result<Self, Self::Error> {
+        if tuple.is_point() {
+            Ok(Point(tuple))
+        } else {
+            Err(RayTracerError::NotAPoint {
+                what: "tuple",
+                got: tuple,
+            })
+        }
+    }
+}
+
+impl TryFrom<Tuple> for Vector {
+    type Error = RayTracerError;
+
+    fn try_from(tuple: Tuple) -> Result<Self, Self::Error> {
+        if tuple.is_vector() {
+            Ok(Vector(tuple))
+        } else {
+            Err(RayTracerError::NotAVector {
+                what: "tuple",
+                got: tuple,
+            })
+        }
+    }
+}
+
+impl From<Point> for Tuple {
+    fn from(point: Point) -> Self {
+        point.0
+    }
+}
+
+impl From<Vector> for Tuple {
+    fn from(vector: Vector) -> Self {
+        vector.0
+    }
+}
+
+impl Deref for Point {
+    type Target = Tuple;
+
+    fn deref(&self) -> &Tuple {
+        &self.0
+    }
+}
+
+impl Deref for Vector {
+    type Target = Tuple;
+
+    fn deref(&self) -> &Tuple {
+        &self.0
+    }
+}
+
+impl PartialEq<Tuple> for Point {
+    fn eq(&self, other: &Tuple) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<Tuple> for Vector {
+    fn eq(&self, other: &Tuple) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Sub for Point {
+    type Output = Vector;
+    fn sub(self, rhs: Point) -> Vector {
+        Vector(self.0 - rhs.0)
+    }
+}
+
+impl Add<Vector> for Point {
+    type Output = Point;
+    fn add(self, rhs: Vector) -> Point {
+        Point(self.0 + rhs.0)
+    }
+}
+
+impl Sub<Vector> for Point {
+    type Output = Point;
+    fn sub(self, rhs: Vector) -> Point {
+        Point(self.0 - rhs.0)
+    }
+}
+
+impl Add for Vector {
+    type Output = Vector;
+    fn add(self, rhs: Vector) -> Vector {
+        Vector(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Vector {
+    type Output = Vector;
+    fn sub(self, rhs: Vector) -> Vector {
+        Vector(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Vector {
+    type Output = Vector;
+    fn neg(self) -> Vector {
+        Vector(-self.0)
+    }
+}
+
+impl Mul<f64> for Vector {
+    type Output = Vector;
+    fn mul(self, rhs: f64) -> Vector {
+        Vector(self.0 * rhs)
+    }
+}
+
+impl Mul<Vector> for f64 {
+    type Output = Vector;
+    fn mul(self, rhs: Vector) -> Vector {
+        rhs * self
+    }
+}
+
+impl Div<f64> for Vector {
+    type Output = Vector;
+    fn div(self, rhs: f64) -> Vector {
+        Vector(self.0 / rhs)
+    }
+}
+
+/// Transforms are all affine, so a point stays a point. w is kept at exactly 1 rather than
+/// whatever rounding in the bottom row of the matrix would make of it.
+impl Mul<Point> for &Matrix {
+    type Output = Point;
+    fn mul(self, rhs: Point) -> Point {
+        Point(Tuple {
+            w: 1.0,
+            ..self * rhs.0
+        })
+    }
+}
+
+/// As for points, w stays exactly 0
+impl Mul<Vector> for &Matrix {
+    type Output = Vector;
+    fn mul(self, rhs: Vector) -> Vector {
+        Vector(Tuple {
+            w: 0.0,
+            ..self * rhs.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::math::{
+        matrix::Matrix,
+        tuple::{point, pointi, vector, vectori, Tuple},
+    };
+
+    use super::{Point, Vector};
+
+    #[test]
+    fn algebra() {
+        let (a, b) = (Point::newi(3, 2, 1), Point::newi(5, 6, 7));
+        let v = Vector::newi(1, 2, 3);
+
+        assert_eq!(a - b, Vector::newi(-2, -4, -6));
+        assert_eq!(a + v, Point::newi(4, 4, 4));
+        assert_eq!(a - v, Point::newi(2, 0, -2));
+        assert_eq!(v + v, Vector::newi(2, 4, 6));
+        assert_eq!(v - v, Vector::ZERO);
+        assert_eq!(-v, Vector::newi(-1, -2, -3));
+        assert_eq!(v * 2.0, 2.0 * v);
+        assert_eq!(v / 2.0, Vector::new(0.5, 1.0, 1.5));
+    }
+
+    #[test]
+    fn same_as_tuples() {
+        let (a, b) = (Point::new(1.5, -2.0, 3.0), Vector::new(0.5, 1.0, -4.0));
+        let (ta, tb) = (point(1.5, -2.0, 3.0), vector(0.5, 1.0, -4.0));
+
+        assert_eq!(a + b, ta + tb);
+        assert_eq!(a - Point::ORIGIN, ta - pointi(0, 0, 0));
+        assert_eq!(b.normalize(), tb.normalize());
+        assert_eq!(b.magnitude(), tb.magnitude());
+        assert_eq!(b.dot(&Vector::newi(1, 2, 3)), tb.dot(&vectori(1, 2, 3)));
+        assert_eq!(b.cross(&Vector::newi(1, 2, 3)), tb.cross(&vectori(1, 2, 3)));
+        let n = Vector::newi(0, 1, 0);
+        assert_eq!(b.reflect(&n), tb.reflect(&vectori(0, 1, 0)));
+        // Deref gets at the tuple's fields and methods
+        assert_eq!((a.x, a.w, b.w), (1.5, 1.0, 0.0));
+        assert!(a.is_point() && b.is_vector());
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Point::try_from(pointi(1, 2, 3)), Ok(Point::newi(1, 2, 3)));
+        assert!(Point::try_from(vectori(1, 2, 3)).is_err());
+        assert_eq!(
+            Vector::try_from(vectori(1, 2, 3)),
+            Ok(Vector::newi(1, 2, 3))
+        );
+        assert!(Vector::try_from(pointi(1, 2, 3)).is_err());
+        assert_eq!(Tuple::from(Point::newi(1, 2, 3)), pointi(1, 2, 3));
+        assert_eq!(Vector::newi(1, 2, 3).tuple(), vectori(1, 2, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "must be a point")]
+    fn from_tuple_checks() {
+        Point::from_tuple(vectori(1, 2, 3));
+    }
+
+    #[test]
+    fn transformed() {
+        let m = Matrix::translationi(1, 2, 3).scale(2.0, 2.0, 2.0);
+        let (p, v) = (Point::newi(1, 1, 1), Vector::newi(1, 1, 1));
+
+        assert_eq!(&m * p, &m * pointi(1, 1, 1));
+        assert_eq!(&m * v, &m * vectori(1, 1, 1));
+        // Translation doesn't move vectors
+        assert_eq!(&m * v, Vector::newi(2, 2, 2));
+    }
+}
@@ -0,0 +1,96 @@
+//! A small seedable random number generator, for anything that has to come out the same every
+//! time from the same seed. Not for anything that needs to be unpredictable.
+
+/// xorshift64*, plenty random enough to scatter spheres
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Any seed's fine, 0 included (which xorshift on its own would be stuck at)
+    pub fn new(seed: u64) -> Self {
+        Self(if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        })
+    }
+
+    /// In [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// In [low, high)
+    pub fn between(&mut self, low: f64, high: f64) -> f64 {
+        low + self.next_f64() * (high - low)
+    }
+
+    /// The index of one of `weights`, picked in proportion to them. None if they're all 0 (or
+    /// there aren't any).
+    pub fn weighted(&mut self, weights: &[f64]) -> Option<usize> {
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = self.next_f64() * total;
+        for (i, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                return Some(i);
+            }
+            pick -= weight;
+        }
+        // Rounding can leave a sliver past the end
+        weights.iter().rposition(|w| *w > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rng;
+
+    #[test]
+    fn repeatable() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let a: Vec<_> = (0..10).map(|_| a.next_f64()).collect();
+        let b: Vec<_> = (0..10).map(|_| b.next_f64()).collect();
+        let c: Vec<_> = (0..10).map(|_| c.next_f64()).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        // Changing these changes every scene made from a seed
+        let mut rng = Rng::new(1);
+        assert_eq!(rng.next_f64().to_bits(), 0x3fd1_f933_92e2_5b36);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut zero = Rng::new(0);
+        let values: Vec<_> = (0..1000).map(|_| zero.next_f64()).collect();
+        assert!(values.iter().all(|v| (0.0..1.0).contains(v)));
+        assert!(values.iter().any(|v| *v < 0.1) && values.iter().any(|v| *v > 0.9));
+
+        let mut rng = Rng::new(7);
+        assert!((0..1000)
+            .map(|_| rng.between(-2.0, 3.0))
+            .all(|v| (-2.0..3.0).contains(&v)));
+    }
+
+    #[test]
+    fn weighted() {
+        let mut rng = Rng::new(5);
+        let mut counts = [0; 3];
+        for _ in 0..10_000 {
+            counts[rng.weighted(&[1.0, 0.0, 3.0]).unwrap()] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!((2300..2700).contains(&counts[0]), "{counts:?}");
+
+        assert_eq!(rng.weighted(&[]), None);
+        assert_eq!(rng.weighted(&[0.0, 0.0]), None);
+    }
+}
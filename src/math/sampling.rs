@@ -0,0 +1,594 @@
+//! Random directions and points, for anything that has to pick them with the right odds.
+
+use core::f64::consts::{PI, TAU};
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+use crate::math::{rng::Rng, tuple::Tuple};
+
+/// A point in the unit disc (z = 0), any spot as likely as any other
+pub fn uniform_disc(rng: &mut Rng) -> (f64, f64) {
+    let r = rng.next_f64().sqrt();
+    let (sin, cos) = (TAU * rng.next_f64()).sin_cos();
+    (r * cos, r * sin)
+}
+
+/// A unit vector pointing anywhere, every direction as likely as any other
+pub fn uniform_sphere(rng: &mut Rng) -> Tuple {
+    // Archimedes: height's uniform on a sphere, and so's the angle around
+    let z = 1.0 - 2.0 * rng.next_f64();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let (sin, cos) = (TAU * rng.next_f64()).sin_cos();
+    Tuple::vector(r * cos, r * sin, z)
+}
+
+/// A unit vector on the same side as `normal` (which has to be normalized), more likely the
+/// closer it is to `normal`: the odds are cos(angle to normal) / pi. Dividing a Lambertian
+/// surface's cos-weighted light by those odds cancels both out, which is why it's used for
+/// diffuse bounces.
+pub fn cosine_hemisphere(normal: Tuple, rng: &mut Rng) -> Tuple {
+    // Malley's method, a point in the disc lifted up onto the hemisphere above it
+    let (x, y) = uniform_disc(rng);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    let (tangent, bitangent) = normal.orthonormal_basis();
+    (tangent * x + bitangent * y + normal * z).normalize()
+}
+
+/// The odds of cosine_hemisphere picking `direction` (per steradian)
+pub fn cosine_hemisphere_pdf(normal: Tuple, direction: Tuple) -> f64 {
+    normal.dot(&direction).max(0.0) / PI
+}
+
+/// A unit vector within the cone around `axis` whose half angle has cosine `cos_max`, any
+/// direction in it as likely as any other. `u` is two numbers in [0, 1).
+pub fn uniform_cone(axis: Tuple, cos_max: f64, u: (f64, f64)) -> Tuple {
+    let cos = 1.0 - u.0 * (1.0 - cos_max);
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let (sin_phi, cos_phi) = (TAU * u.1).sin_cos();
+    let (tangent, bitangent) = axis.orthonormal_basis();
+    (tangent * (sin * cos_phi) + bitangent * (sin * sin_phi) + axis * cos).normalize()
+}
+
+/// The odds of uniform_cone picking any one direction in its cone (per steradian)
+pub fn uniform_cone_pdf(cos_max: f64) -> f64 {
+    1.0 / (TAU * (1.0 - cos_max))
+}
+
+/// How much to count a sample picked with odds `pdf` when the same thing could've been found
+/// another way with odds `other`, one sample each (Veach's power heuristic, with a power of 2).
+/// The two ways' weights add up to 1, so the total's still right, and whichever is likelier to
+/// find something gets the most say. Nothing else could've found it, `other` is 0, is a weight
+/// of 1.
+pub fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (pdf, other) = (pdf * pdf, other * other);
+    if pdf + other == 0.0 {
+        return 0.0;
+    }
+    pdf / (pdf + other)
+}
+
+/// Where sample points come from, for anything that needs a handful of them per pixel (so far
+/// just where in the pixel camera rays go). A sampler hands out the numbers for one sample of
+/// one pixel, one dimension at a time: the first next_2d is the point in the pixel, anything
+/// after that whatever the caller wants next.
+pub trait Sampler {
+    /// In [0, 1)
+    fn next_1d(&mut self) -> f64;
+    /// Both in [0, 1)
+    fn next_2d(&mut self) -> (f64, f64);
+    /// A sampler for sample `sample_index` of pixel (x, y), with the same settings as this one.
+    /// It only depends on those, so pixels can be done in any order, on any thread.
+    fn for_pixel(&self, x: usize, y: usize, sample_index: usize) -> Self
+    where
+        Self: Sized;
+}
+
+/// Plain random numbers, every sample independent of the others. Clumps, so it's the slowest to
+/// settle down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Independent {
+    seed: u64,
+    rng: Rng,
+}
+
+impl Independent {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Sampler for Independent {
+    fn next_1d(&mut self) -> f64 {
+        self.rng.next_f64()
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.rng.next_f64(), self.rng.next_f64())
+    }
+
+    fn for_pixel(&self, x: usize, y: usize, sample_index: usize) -> Self {
+        Self {
+            seed: self.seed,
+            rng: Rng::new(mix(self.seed, &[x as u64, y as u64, sample_index as u64])),
+        }
+    }
+}
+
+/// Splits each dimension into as many strata as there are samples (2D ones into a square grid
+/// of them) and puts one sample in each, so they can't clump. Which sample gets which stratum
+/// is shuffled differently for each pixel and dimension, so different dimensions don't line up.
+/// Samples past the last square number fall back to random.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stratified {
+    /// Anywhere in its stratum at random, rather than its middle
+    pub jitter: bool,
+    samples: usize,
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: u64,
+    rng: Rng,
+}
+
+impl Stratified {
+    /// For `samples` samples per pixel
+    pub fn new(samples: usize, jitter: bool, seed: u64) -> Self {
+        Self {
+            jitter,
+            samples: samples.max(1),
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Where in its stratum a sample goes
+    fn offset(&mut self) -> f64 {
+        if self.jitter {
+            self.rng.next_f64()
+        } else {
+            0.5
+        }
+    }
+
+    /// This sample's stratum out of `n`, for the current dimension
+    fn stratum(&mut self, n: usize) -> usize {
+        let (x, y) = self.pixel;
+        let key = mix(self.seed, &[x as u64, y as u64, self.dimension]);
+        self.dimension += 1;
+        permute(self.index, n, key)
+    }
+}
+
+impl Sampler for Stratified {
+    fn next_1d(&mut self) -> f64 {
+        let stratum = self.stratum(self.samples);
+        (stratum as f64 + self.offset()) / self.samples as f64
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let side = (self.samples as f64).sqrt() as usize;
+        let cells = side * side;
+        if self.index >= cells {
+            self.dimension += 1;
+            return (self.rng.next_f64(), self.rng.next_f64());
+        }
+        let cell = self.stratum(cells);
+        let (column, row) = (cell / side, cell % side);
+        let dx = (column as f64 + self.offset()) / side as f64;
+        let dy = (row as f64 + self.offset()) / side as f64;
+        (dx, dy)
+    }
+
+    fn for_pixel(&self, x: usize, y: usize, sample_index: usize) -> Self {
+        Self {
+            pixel: (x, y),
+            index: sample_index,
+            dimension: 0,
+            rng: Rng::new(mix(self.seed, &[x as u64, y as u64, sample_index as u64])),
+            ..self.clone()
+        }
+    }
+}
+
+/// The first few primes, one per Halton dimension
+const PRIMES: [u64; 8] = [2, 3, 5, 7, 11, 13, 17, 19];
+
+/// The Halton sequence: sample i is i's digits in a different prime base for each dimension,
+/// mirrored around the point. However many are taken they're spread out evenly, so it's good
+/// for stopping early. Every pixel's points are shifted by their own random amount (wrapping
+/// around), or neighbouring pixels would all be the same. Past the eighth dimension it's random.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Halton {
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: usize,
+    rng: Rng,
+}
+
+impl Halton {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Sampler for Halton {
+    fn next_1d(&mut self) -> f64 {
+        let Some(&base) = PRIMES.get(self.dimension) else {
+            return self.rng.next_f64();
+        };
+        let (x, y) = self.pixel;
+        let key = mix(self.seed, &[x as u64, y as u64, self.dimension as u64]);
+        self.dimension += 1;
+        let shift = Rng::new(key).next_f64();
+        let value = radical_inverse(base, self.index as u64) + shift;
+        if value >= 1.0 {
+            value - 1.0
+        } else {
+            value
+        }
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+
+    fn for_pixel(&self, x: usize, y: usize, sample_index: usize) -> Self {
+        Self {
+            seed: self.seed,
+            pixel: (x, y),
+            index: sample_index,
+            dimension: 0,
+            rng: Rng::new(mix(self.seed, &[x as u64, y as u64, sample_index as u64])),
+        }
+    }
+}
+
+/// `index` written out in `base`, digits mirrored after the point. 1, 2, 3 in base 2 are 0.5,
+/// 0.25, 0.75.
+pub fn radical_inverse(base: u64, mut index: u64) -> f64 {
+    let mut result = 0.0;
+    let mut scale = 1.0 / base as f64;
+    while index > 0 {
+        result += (index % base) as f64 * scale;
+        index /= base;
+        scale /= base as f64;
+    }
+    result
+}
+
+/// Which Sampler to use, for settings. See SamplerKind::sampler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SamplerKind {
+    Independent,
+    Stratified { jitter: bool },
+    Halton,
+}
+
+/// An even grid, the middle of each cell
+impl Default for SamplerKind {
+    fn default() -> Self {
+        Self::Stratified { jitter: false }
+    }
+}
+
+impl SamplerKind {
+    /// A sampler of this kind, for `samples` samples per pixel
+    pub fn sampler(&self, samples: usize, seed: u64) -> AnySampler {
+        match *self {
+            Self::Independent => AnySampler::Independent(Independent::new(seed)),
+            Self::Stratified { jitter } => {
+                AnySampler::Stratified(Stratified::new(samples, jitter, seed))
+            }
+            Self::Halton => AnySampler::Halton(Halton::new(seed)),
+        }
+    }
+}
+
+/// Whichever one SamplerKind picked
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnySampler {
+    Independent(Independent),
+    Stratified(Stratified),
+    Halton(Halton),
+}
+
+impl Sampler for AnySampler {
+    fn next_1d(&mut self) -> f64 {
+        match self {
+            Self::Independent(s) => s.next_1d(),
+            Self::Stratified(s) => s.next_1d(),
+            Self::Halton(s) => s.next_1d(),
+        }
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        match self {
+            Self::Independent(s) => s.next_2d(),
+            Self::Stratified(s) => s.next_2d(),
+            Self::Halton(s) => s.next_2d(),
+        }
+    }
+
+    fn for_pixel(&self, x: usize, y: usize, sample_index: usize) -> Self {
+        match self {
+            Self::Independent(s) => Self::Independent(s.for_pixel(x, y, sample_index)),
+            Self::Stratified(s) => Self::Stratified(s.for_pixel(x, y, sample_index)),
+            Self::Halton(s) => Self::Halton(s.for_pixel(x, y, sample_index)),
+        }
+    }
+}
+
+/// `seed` and `values` stirred together (splitmix64's finalizer after each)
+fn mix(seed: u64, values: &[u64]) -> u64 {
+    values.iter().fold(seed, |z, v| {
+        let mut z = (z ^ v.wrapping_mul(0x9e37_79b9_7f4a_7c15)).wrapping_add(0x632b_e59b_d9b4_e019);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+/// Where `i` goes in a shuffle of 0..n picked by `key`: i * a + b, with a sharing no factors with
+/// n so nothing lands in the same place twice
+fn permute(i: usize, n: usize, key: u64) -> usize {
+    if n <= 1 {
+        return 0;
+    }
+    let n64 = n as u64;
+    let mut a = (key % n64) | 1;
+    while gcd(a, n64) != 1 {
+        a += 1;
+    }
+    let b = (key >> 32) % n64;
+    ((i as u64 % n64 * a + b) % n64) as usize
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod test {
+    use crate::math::{
+        float,
+        rng::Rng,
+        tuple::{vector, vectori},
+    };
+
+    use super::{
+        cosine_hemisphere, cosine_hemisphere_pdf, power_heuristic, radical_inverse, uniform_cone,
+        uniform_cone_pdf, uniform_disc, uniform_sphere, Halton, Independent, Sampler, SamplerKind,
+        Stratified,
+    };
+
+    #[test]
+    fn disc() {
+        let mut rng = Rng::new(3);
+        let points: Vec<_> = (0..4000).map(|_| uniform_disc(&mut rng)).collect();
+        assert!(points.iter().all(|(x, y)| x * x + y * y <= 1.0));
+        // Uniform by area, so a quarter of them are within half the radius
+        let inner = points.iter().filter(|(x, y)| x * x + y * y < 0.25).count();
+        assert!((900..1100).contains(&inner), "{inner}");
+    }
+
+    #[test]
+    fn sphere() {
+        let mut rng = Rng::new(5);
+        let directions: Vec<_> = (0..4000).map(|_| uniform_sphere(&mut rng)).collect();
+        assert!(directions
+            .iter()
+            .all(|v| v.is_vector() && float::equal(v.magnitude(), 1.0)));
+        // Every half as likely as any other
+        for axis in [vectori(1, 0, 0), vectori(0, 1, 0), vectori(0, 0, 1)] {
+            let above = directions.iter().filter(|v| v.dot(&axis) > 0.0).count();
+            assert!((1900..2100).contains(&above), "{above}");
+        }
+        let total = directions.iter().fold(vectori(0, 0, 0), |acc, v| acc + *v);
+        assert!(
+            total.magnitude() / (directions.len() as f64) < 0.05,
+            "{total:?}"
+        );
+    }
+
+    #[test]
+    fn hemisphere() {
+        let mut rng = Rng::new(9);
+        for normal in [
+            vectori(0, 1, 0),
+            vectori(0, 0, -1),
+            vector(1.0, -2.0, 0.5).normalize(),
+        ] {
+            let cosines: Vec<_> = (0..4000)
+                .map(|_| {
+                    let v = cosine_hemisphere(normal, &mut rng);
+                    assert!(float::equal(v.magnitude(), 1.0));
+                    assert!(v.is_vector());
+                    v.dot(&normal)
+                })
+                .collect();
+            assert!(cosines.iter().all(|c| *c >= 0.0));
+            // The mean of cos is 2/3 for cos-weighted directions, 1/2 if they were uniform
+            let mean = cosines.iter().sum::<f64>() / cosines.len() as f64;
+            assert!((mean - 2.0 / 3.0).abs() < 0.02, "{mean}");
+        }
+    }
+
+    #[test]
+    fn cone() {
+        let mut rng = Rng::new(11);
+        let axis = vector(0.3, 1.0, -0.2).normalize();
+        let cos_max = 0.8;
+        let cosines: Vec<_> = (0..4000)
+            .map(|_| {
+                let v = uniform_cone(axis, cos_max, (rng.next_f64(), rng.next_f64()));
+                assert!(float::equal(v.magnitude(), 1.0));
+                v.dot(&axis)
+            })
+            .collect();
+        assert!(cosines.iter().all(|c| *c >= cos_max - 1e-12));
+        // Uniform by solid angle, so cos is uniform too
+        let mean = cosines.iter().sum::<f64>() / cosines.len() as f64;
+        assert!((mean - 0.9).abs() < 0.005, "{mean}");
+
+        // Odds over the whole cone add up to 1
+        let solid_angle = core::f64::consts::TAU * (1.0 - cos_max);
+        assert!(float::equal(uniform_cone_pdf(cos_max) * solid_angle, 1.0));
+    }
+
+    #[test]
+    fn hemisphere_pdf() {
+        let normal = vectori(0, 1, 0);
+        let pi = core::f64::consts::PI;
+        assert!(float::equal(
+            cosine_hemisphere_pdf(normal, normal),
+            1.0 / pi
+        ));
+        let diagonal = vector(1.0, 1.0, 0.0).normalize();
+        assert!(float::equal(
+            cosine_hemisphere_pdf(normal, diagonal),
+            diagonal.y / pi
+        ));
+        assert_eq!(cosine_hemisphere_pdf(normal, vectori(0, -1, 0)), 0.0);
+    }
+
+    #[test]
+    fn power_heuristic_weights() {
+        for (a, b) in [(1.0, 1.0), (0.2, 3.0), (5.0, 0.01)] {
+            assert!(float::equal(
+                power_heuristic(a, b) + power_heuristic(b, a),
+                1.0
+            ));
+        }
+        assert_eq!(power_heuristic(1.0, 1.0), 0.5);
+        assert!(power_heuristic(4.0, 1.0) > 4.0 / 5.0);
+        // Nothing else could've found it
+        assert_eq!(power_heuristic(0.3, 0.0), 1.0);
+        assert_eq!(power_heuristic(0.0, 0.3), 0.0);
+        assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn stratified_covers_every_stratum() {
+        for jitter in [false, true] {
+            let sampler = Stratified::new(16, jitter, 7);
+            for (x, y) in [(0, 0), (3, 9)] {
+                let mut cells = [0; 16];
+                let mut strata = [0; 16];
+                for i in 0..16 {
+                    let mut s = sampler.for_pixel(x, y, i);
+                    let (dx, dy) = s.next_2d();
+                    cells[(dx * 4.0) as usize * 4 + (dy * 4.0) as usize] += 1;
+                    strata[(s.next_1d() * 16.0) as usize] += 1;
+                }
+                assert_eq!(cells, [1; 16], "{jitter} {cells:?}");
+                assert_eq!(strata, [1; 16], "{jitter} {strata:?}");
+            }
+        }
+        // Without jitter it's the middle of each
+        let mut s = Stratified::new(4, false, 1).for_pixel(2, 2, 3);
+        let (dx, dy) = s.next_2d();
+        assert!([0.25, 0.75].contains(&dx) && [0.25, 0.75].contains(&dy));
+
+        // One past the grid is just somewhere
+        let (dx, dy) = Stratified::new(5, false, 1).for_pixel(0, 0, 4).next_2d();
+        assert!((0.0..1.0).contains(&dx) && (0.0..1.0).contains(&dy));
+    }
+
+    #[test]
+    fn halton() {
+        let base2: Vec<_> = (0..8).map(|i| radical_inverse(2, i)).collect();
+        assert_eq!(base2, [0.0, 0.5, 0.25, 0.75, 0.125, 0.625, 0.375, 0.875]);
+        let base3: Vec<_> = (1..6).map(|i| radical_inverse(3, i)).collect();
+        for (value, expected) in base3
+            .iter()
+            .zip([1.0, 2.0, 1.0 / 3.0, 4.0 / 3.0, 7.0 / 3.0])
+        {
+            assert!(float::equal(*value, expected / 3.0), "{value}");
+        }
+
+        // Shifted, but the gaps between a pixel's samples are still the sequence's
+        let sampler = Halton::new(3);
+        let first: Vec<_> = (0..4)
+            .map(|i| sampler.for_pixel(1, 2, i).next_1d())
+            .collect();
+        for (i, value) in first.iter().enumerate() {
+            let gap = (value - first[0]).rem_euclid(1.0);
+            assert!(float::equal(gap, base2[i]), "{i} {gap}");
+        }
+        // Each pixel's shifted differently
+        assert_ne!(
+            sampler.for_pixel(1, 2, 0).next_2d(),
+            sampler.for_pixel(2, 2, 0).next_2d()
+        );
+    }
+
+    #[test]
+    fn any_order() {
+        for kind in [
+            SamplerKind::Independent,
+            SamplerKind::Stratified { jitter: true },
+            SamplerKind::Halton,
+        ] {
+            let sampler = kind.sampler(16, 11);
+            let take = |(x, y, i)| {
+                let mut s = sampler.for_pixel(x, y, i);
+                (s.next_2d(), s.next_1d(), s.next_2d())
+            };
+            let order: Vec<_> = (0..4)
+                .flat_map(|x| (0..3).flat_map(move |y| (0..16).map(move |i| (x, y, i))))
+                .collect();
+            let forwards: Vec<_> = order.iter().copied().map(take).collect();
+            let backwards: Vec<_> = order.iter().rev().copied().map(take).collect();
+            assert!(forwards.iter().eq(backwards.iter().rev()), "{kind:?}");
+        }
+    }
+
+    /// How far off estimates of the area of a quarter disc in the unit square are, on average,
+    /// like working out how much of a pixel's covered by the edge of a shadow
+    fn coverage_error(kind: SamplerKind) -> f64 {
+        let sampler = kind.sampler(16, 5);
+        let exact = std::f64::consts::PI / 4.0;
+        let pixels = 200;
+        let total: f64 = (0..pixels)
+            .map(|x| {
+                let inside = (0..16)
+                    .filter(|&i| {
+                        let (dx, dy) = sampler.for_pixel(x, 0, i).next_2d();
+                        dx * dx + dy * dy < 1.0
+                    })
+                    .count();
+                (inside as f64 / 16.0 - exact).powi(2)
+            })
+            .sum();
+        total / pixels as f64
+    }
+
+    #[test]
+    fn less_variance_than_independent() {
+        let independent = coverage_error(SamplerKind::Independent);
+        let stratified = coverage_error(SamplerKind::Stratified { jitter: true });
+        let halton = coverage_error(SamplerKind::Halton);
+        assert!(stratified * 2.0 < independent, "{stratified} {independent}");
+        assert!(halton * 2.0 < independent, "{halton} {independent}");
+
+        let mut plain = Independent::new(5).for_pixel(0, 0, 0);
+        assert!((0..100).all(|_| (0.0..1.0).contains(&plain.next_1d())));
+    }
+}
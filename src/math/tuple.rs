@@ -1,7 +1,11 @@
 use core::f64;
-use std::ops::{Add, Mul, Neg, Sub};
+use core::{
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
 
 use super::{float::equal, matrix};
+use crate::prelude::*;
 
 pub const ZERO: Tuple = Tuple {
     x: 0.0,
@@ -14,6 +18,7 @@ pub const ZERO_VEC: Tuple = ZERO;
 pub const ZERO_POINT: Tuple = Tuple::pointi(0, 0, 0);
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tuple {
     pub x: f64,
     pub y: f64,
@@ -96,6 +101,19 @@ impl Tuple {
     pub fn reflect(&self, normal: &Self) -> Tuple {
         *self - *normal * 2 * self.dot(normal)
     }
+
+    /// Two vectors at right angles to this (normalised) one and each other, for a tangent space
+    /// around a normal. Duff et al's branchless version, so it's stable for any direction.
+    pub fn orthonormal_basis(&self) -> (Tuple, Tuple) {
+        let sign = 1_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+
+        (
+            Self::vector(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Self::vector(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
 }
 
 impl PartialEq for Tuple {
@@ -155,13 +173,73 @@ impl Mul<f64> for Tuple {
     }
 }
 
-impl Mul<u32> for Tuple {
+/// Signed so that `v * -2` works too. There's deliberately no u32 version alongside, with two
+/// integer impls a bare `v * 2` wouldn't know which one it meant.
+impl Mul<i32> for Tuple {
     type Output = Tuple;
-    fn mul(self, rhs: u32) -> Self::Output {
+    fn mul(self, rhs: i32) -> Self::Output {
         self * rhs as f64
     }
 }
 
+impl Div<f64> for Tuple {
+    type Output = Tuple;
+    fn div(self, rhs: f64) -> Self::Output {
+        Self {
+            x: self.x / rhs,
+            y: self.y / rhs,
+            z: self.z / rhs,
+            w: self.w / rhs,
+        }
+    }
+}
+
+impl Mul<Tuple> for f64 {
+    type Output = Tuple;
+    fn mul(self, rhs: Tuple) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl Mul<Tuple> for i32 {
+    type Output = Tuple;
+    fn mul(self, rhs: Tuple) -> Self::Output {
+        rhs * self
+    }
+}
+
+/// The same ops through a reference, it's Copy so they just deref
+macro_rules! by_ref {
+    ($trait:ident, $funcname:ident, $lhs:ty, $rhs:ty) => {
+        impl $trait<$rhs> for &$lhs {
+            type Output = Tuple;
+            fn $funcname(self, rhs: $rhs) -> Self::Output {
+                (*self).$funcname(rhs)
+            }
+        }
+
+        impl $trait<&$rhs> for $lhs {
+            type Output = Tuple;
+            fn $funcname(self, rhs: &$rhs) -> Self::Output {
+                self.$funcname(*rhs)
+            }
+        }
+
+        impl $trait<&$rhs> for &$lhs {
+            type Output = Tuple;
+            fn $funcname(self, rhs: &$rhs) -> Self::Output {
+                (*self).$funcname(*rhs)
+            }
+        }
+    };
+}
+
+by_ref!(Mul, mul, Tuple, f64);
+by_ref!(Mul, mul, Tuple, i32);
+by_ref!(Div, div, Tuple, f64);
+by_ref!(Mul, mul, f64, Tuple);
+by_ref!(Mul, mul, i32, Tuple);
+
 impl From<matrix::Ref<'_>> for Tuple {
     fn from(value: matrix::Ref) -> Self {
         Tuple {
@@ -173,6 +251,81 @@ impl From<matrix::Ref<'_>> for Tuple {
     }
 }
 
+/// Parsing. Accepts `point(x, y, z)`, `vector(x, y, z)` and bare `x, y, z` or `x y z`.
+/// Bare triples are points, use parse_vector where a vector is meant.
+impl FromStr for Tuple {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        if let Some(args) = call_args(trimmed, "vector") {
+            let [x, y, z] = parse_triple(args, ["x", "y", "z"])?;
+            return Ok(vector(x, y, z));
+        }
+
+        let args = call_args(trimmed, "point").unwrap_or(trimmed);
+        let [x, y, z] = parse_triple(args, ["x", "y", "z"])?;
+        Ok(point(x, y, z))
+    }
+}
+
+impl Tuple {
+    /// Like parse, but errors on `vector(...)` rather than returning one
+    pub fn parse_point(s: &str) -> Result<Tuple, String> {
+        let t: Tuple = s.parse()?;
+        if !t.is_point() {
+            return Err(format!("expected a point, got the vector {:?}", s.trim()));
+        }
+        Ok(t)
+    }
+
+    /// Like parse, but bare triples are vectors and `point(...)` is an error
+    pub fn parse_vector(s: &str) -> Result<Tuple, String> {
+        let trimmed = s.trim();
+        if call_args(trimmed, "point").is_some() {
+            return Err(format!("expected a vector, got the point {trimmed:?}"));
+        }
+
+        let args = call_args(trimmed, "vector").unwrap_or(trimmed);
+        let [x, y, z] = parse_triple(args, ["x", "y", "z"])?;
+        Ok(vector(x, y, z))
+    }
+}
+
+/// The inside of `name(...)`, if `s` is one
+pub(crate) fn call_args<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    s.strip_prefix(name)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+/// Three finite numbers separated by commas or whitespace. `names` are used to say which one
+/// was wrong.
+pub(crate) fn parse_triple(s: &str, names: [&str; 3]) -> Result<[f64; 3], String> {
+    let parts: Vec<&str> = if s.contains(',') {
+        s.split(',').map(str::trim).collect()
+    } else {
+        s.split_whitespace().collect()
+    };
+    if parts.len() != 3 {
+        return Err(format!(
+            "expected 3 values ({}), got {} in {s:?}",
+            names.join(", "),
+            parts.len()
+        ));
+    }
+
+    let mut out = [0.0; 3];
+    for ((value, part), name) in out.iter_mut().zip(&parts).zip(names) {
+        *value = match part.parse::<f64>() {
+            Ok(v) if v.is_finite() => v,
+            Ok(_) => return Err(format!("{name} in {s:?} isn't finite")),
+            Err(_) => return Err(format!("{name} in {s:?} isn't a number, got {part:?}")),
+        };
+    }
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::Tuple;
@@ -300,6 +453,61 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_mul_scalar_commutes() {
+        let t = Tuple::point(1.0, -2.0, 3.0);
+        assert_eq!(2.5 * t, t * 2.5);
+        assert_eq!(3 * t, t * 3);
+        // w scales too, just as it does on the right
+        assert_eq!((2.0 * t).w, 2.0);
+    }
+
+    #[test]
+    fn test_mul_negative_int() {
+        assert_eq!(
+            Tuple::vector(1.0, -2.0, 3.0) * -2,
+            Tuple::vector(-2.0, 4.0, -6.0)
+        );
+        assert_eq!(
+            -2 * Tuple::vector(1.0, 0.0, 0.0),
+            Tuple::vector(-2.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_div_scalar() {
+        let t = Tuple {
+            x: 1.0,
+            y: -2.0,
+            z: 3.0,
+            w: -4.0,
+        };
+        for divisor in [2.0, -0.5, 3.0, 7.0] {
+            assert_eq!(t / divisor, t * (1.0 / divisor));
+        }
+        assert_eq!((t / 2.0).w, -2.0);
+    }
+
+    // Taking the references is the point, this is checking they compile
+    #[allow(clippy::op_ref)]
+    #[test]
+    fn test_scalar_ops_by_ref() {
+        let t = Tuple::vector(1.0, 2.0, 3.0);
+        let (f, i) = (2.0, 2);
+        let expected = Tuple::vector(2.0, 4.0, 6.0);
+        assert_eq!(&t * f, expected);
+        assert_eq!(t * &f, expected);
+        assert_eq!(&t * &f, expected);
+        assert_eq!(&t * i, expected);
+        assert_eq!(&t * &i, expected);
+        assert_eq!(f * &t, expected);
+        assert_eq!(&f * t, expected);
+        assert_eq!(&f * &t, expected);
+        assert_eq!(&i * &t, expected);
+        assert_eq!(&t / 0.5, expected);
+        assert_eq!(&t / &0.5, expected);
+    }
+
     mod magnitude_tests {
         use super::*;
         macro_rules! mag_test {
@@ -364,6 +572,25 @@ mod tests {
         assert_eq!(Tuple::cross(&b, &a), Tuple::vectori(1, -2, 1))
     }
 
+    #[test]
+    fn orthonormal_basis() {
+        for n in [
+            Tuple::vectori(0, 1, 0),
+            Tuple::vectori(0, 0, -1),
+            Tuple::vectori(0, 0, 1),
+            Tuple::vectori(1, 2, 3).normalize(),
+            Tuple::vector(-0.3, 0.1, -0.9).normalize(),
+        ] {
+            let (t, b) = n.orthonormal_basis();
+
+            assert!(super::equal(t.magnitude(), 1.0));
+            assert!(super::equal(b.magnitude(), 1.0));
+            assert!(super::equal(t.dot(&n), 0.0), "{n:?}");
+            assert!(super::equal(b.dot(&n), 0.0), "{n:?}");
+            assert!(super::equal(t.dot(&b), 0.0), "{n:?}");
+        }
+    }
+
     mod reflect {
         use std::f64::consts::SQRT_2;
 
@@ -395,4 +622,72 @@ mod tests {
             Tuple::vectori(1, 0, 0)
         );
     }
+
+    mod parse {
+        use crate::math::tuple::{point, vector, Tuple};
+
+        #[test]
+        fn syntaxes() {
+            for s in [
+                "point(1, -2.5, 3)",
+                "point (1,-2.5,3)",
+                "1, -2.5, 3",
+                "1 -2.5 3",
+                "  1,\t-2.5 ,3  ",
+            ] {
+                assert_eq!(s.parse::<Tuple>(), Ok(point(1.0, -2.5, 3.0)), "{s}");
+            }
+            assert_eq!(
+                "vector( 0, 1, 0 )".parse::<Tuple>(),
+                Ok(vector(0.0, 1.0, 0.0))
+            );
+        }
+
+        #[test]
+        fn scientific() {
+            assert_eq!(
+                "1e3, -2.5E-2, .5".parse::<Tuple>(),
+                Ok(point(1000.0, -0.025, 0.5))
+            );
+        }
+
+        #[test]
+        fn point_and_vector() {
+            assert_eq!(Tuple::parse_point("0 1.5 -5"), Ok(point(0.0, 1.5, -5.0)));
+            assert_eq!(
+                Tuple::parse_point("point(0, 1.5, -5)"),
+                Ok(point(0.0, 1.5, -5.0))
+            );
+            assert!(Tuple::parse_point("vector(0, 1, 0)").is_err());
+
+            assert_eq!(Tuple::parse_vector("0, 1, 0"), Ok(vector(0.0, 1.0, 0.0)));
+            assert_eq!(
+                Tuple::parse_vector("vector(0, 1, 0)"),
+                Ok(vector(0.0, 1.0, 0.0))
+            );
+            assert!(Tuple::parse_vector("point(0, 1, 0)").is_err());
+        }
+
+        #[test]
+        fn errors() {
+            let err = |s: &str| s.parse::<Tuple>().unwrap_err();
+
+            assert_eq!(
+                err("1, two, 3"),
+                "y in \"1, two, 3\" isn't a number, got \"two\""
+            );
+            assert_eq!(err("1 2 nan"), "z in \"1 2 nan\" isn't finite");
+            assert_eq!(
+                err("1, 2"),
+                "expected 3 values (x, y, z), got 2 in \"1, 2\""
+            );
+            assert_eq!(
+                err("1 2 3 4"),
+                "expected 3 values (x, y, z), got 4 in \"1 2 3 4\""
+            );
+            assert!(err("1, 2,").starts_with("z in"));
+            assert!(err("point(1, 2, 3").starts_with("x in"));
+            assert!(err("").starts_with("expected 3 values"));
+        }
+    }
 }
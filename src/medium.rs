@@ -0,0 +1,135 @@
+//! Fog, smoke and the like: something filling the space between objects that light scatters
+//! off of, so beams of light show up in it. See World::with_medium.
+
+use core::f64::consts::PI;
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+
+/// How likely light is to be scattered off in each direction, by the angle it's turned through
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PhaseFunction {
+    /// Every way equally
+    #[default]
+    Isotropic,
+    /// Henyey-Greenstein, with g in (-1, 1): above 0 mostly onwards (a glow around lights you
+    /// look towards), below 0 mostly back.
+    HenyeyGreenstein(f64),
+}
+
+impl PhaseFunction {
+    /// The odds (per unit solid angle) of light going on at an angle with cosine `cos` to the
+    /// way it was going
+    pub fn at(&self, cos: f64) -> f64 {
+        match *self {
+            Self::Isotropic => 1.0 / (4.0 * PI),
+            Self::HenyeyGreenstein(g) => {
+                let g2 = g * g;
+                (1.0 - g2) / (4.0 * PI * (1.0 + g2 - 2.0 * g * cos).powf(1.5))
+            }
+        }
+    }
+}
+
+/// The same all the way through. Coefficients are per unit distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium {
+    /// How much light is bounced off in some other direction
+    pub scattering: f64,
+    /// How much light is soaked up
+    pub absorption: f64,
+    pub phase: PhaseFunction,
+    /// How far apart the points sampled along a ray are, at most
+    pub step: f64,
+    /// Steps along any one ray, at most. Long rays take longer steps instead.
+    pub max_steps: usize,
+    /// How far from the camera it reaches, so rays that hit nothing go through this much
+    pub max_distance: f64,
+}
+
+impl Medium {
+    pub fn new(scattering: f64, absorption: f64) -> Self {
+        Self {
+            scattering,
+            absorption,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_phase(mut self, phase: PhaseFunction) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    pub fn with_steps(mut self, step: f64, max_steps: usize) -> Self {
+        self.step = step;
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Scattering and absorption together, how quickly light going straight through fades
+    pub fn extinction(&self) -> f64 {
+        self.scattering + self.absorption
+    }
+
+    /// How much of the light makes it `distance` straight through
+    pub fn transmittance(&self, distance: f64) -> f64 {
+        (-self.extinction() * distance).exp()
+    }
+}
+
+/// Clear, nothing scatters or is absorbed
+impl Default for Medium {
+    fn default() -> Self {
+        Self {
+            scattering: 0.0,
+            absorption: 0.0,
+            phase: PhaseFunction::Isotropic,
+            step: 0.1,
+            max_steps: 200,
+            max_distance: 100.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use crate::math::float;
+
+    use super::{Medium, PhaseFunction};
+
+    #[test]
+    fn transmittance() {
+        let fog = Medium::new(0.2, 0.3);
+        assert_eq!(fog.extinction(), 0.5);
+        assert_eq!(fog.transmittance(0.0), 1.0);
+        assert!(float::equal(fog.transmittance(2.0), (-1.0_f64).exp()));
+        assert_eq!(Medium::default().transmittance(1000.0), 1.0);
+    }
+
+    #[test]
+    fn phase_functions_integrate_to_one() {
+        for phase in [
+            PhaseFunction::Isotropic,
+            PhaseFunction::HenyeyGreenstein(0.6),
+            PhaseFunction::HenyeyGreenstein(-0.3),
+        ] {
+            // Over the sphere, in bands of equal cos
+            let n = 10_000;
+            let total: f64 = (0..n)
+                .map(|i| {
+                    phase.at(-1.0 + 2.0 * (i as f64 + 0.5) / n as f64) * 2.0 * PI * 2.0 / n as f64
+                })
+                .sum();
+            assert!(float::equal_within(total, 1.0, 1e-3), "{phase:?} {total}");
+        }
+        let forward = PhaseFunction::HenyeyGreenstein(0.6);
+        assert!(forward.at(1.0) > forward.at(-1.0));
+        assert!(float::equal(
+            PhaseFunction::HenyeyGreenstein(0.0).at(0.3),
+            PhaseFunction::Isotropic.at(0.3)
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+//! A plain triangle mesh, what the model importers (other than OBJ, which has its own groups)
+//! hand back.
+
+use crate::{
+    colour::Colour,
+    materials::Material,
+    math::tuple::Tuple,
+    pattern::vertex_colour::VertexColourPattern,
+    prelude::*,
+    shape::{group::Group, smooth_triangle::SmoothTriangle, triangle::Triangle, Shape},
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Tuple>,
+    /// One per vertex, or empty if the file didn't have any
+    pub normals: Vec<Tuple>,
+    /// One per vertex, or empty
+    pub colours: Vec<Colour>,
+    /// Corners, as indexes into `vertices`
+    pub faces: Vec<[usize; 3]>,
+    /// One per face, always there
+    pub face_normals: Vec<Tuple>,
+}
+
+impl Mesh {
+    pub fn len(&self) -> usize {
+        self.faces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    /// The vertices of the `index`th face
+    pub fn triangle(&self, index: usize) -> [Tuple; 3] {
+        self.faces[index].map(|i| self.vertices[i])
+    }
+
+    /// A pattern blending the vertex colours across the `index`th face, if there are any. It
+    /// works in the space the vertices are in, which is each triangle's object space.
+    pub fn colour_pattern(&self, index: usize) -> Option<VertexColourPattern> {
+        if self.colours.is_empty() {
+            return None;
+        }
+
+        Some(VertexColourPattern::new(
+            self.triangle(index),
+            self.faces[index].map(|i| self.colours[i]),
+        ))
+    }
+
+    /// Every face in one group, smooth if there are vertex normals, flat otherwise. Vertex
+    /// colours end up as each triangle's pattern, so setting the group's material afterwards
+    /// loses them.
+    pub fn into_group(self) -> Group {
+        let mut group = Group::default();
+        for (index, face) in self.faces.iter().enumerate() {
+            let [p1, p2, p3] = self.triangle(index);
+            let mut shape: Box<dyn Shape> = if self.normals.is_empty() {
+                Box::new(Triangle::new(p1, p2, p3))
+            } else {
+                let [n1, n2, n3] = face.map(|i| self.normals[i]);
+                Box::new(SmoothTriangle::new(p1, p2, p3, n1, n2, n3))
+            };
+            if let Some(pattern) = self.colour_pattern(index) {
+                shape.set_material(Material {
+                    pattern: Box::new(pattern),
+                    ..Default::default()
+                });
+            }
+            group.add_child(shape);
+        }
+
+        group
+    }
+}
+
+/// The normal of a counter-clockwise wound face, or None if it has no area.
+/// Only the file loaders need this, and they all need std.
+#[cfg(feature = "std")]
+pub(crate) fn face_normal([p1, p2, p3]: [Tuple; 3]) -> Option<Tuple> {
+    let normal = (p2 - p1).cross(&(p3 - p1));
+    if normal.magnitude() < crate::math::float::EPSILON {
+        return None;
+    }
+
+    Some(normal.normalize())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::math::{
+        point::{Point, Vector},
+        tuple::{pointi, vectori},
+    };
+
+    use super::*;
+
+    #[test]
+    fn counter_clockwise_normal() {
+        let n = face_normal([pointi(0, 0, 0), pointi(1, 0, 0), pointi(0, 1, 0)]);
+        assert_eq!(n, Some(vectori(0, 0, 1)));
+
+        assert_eq!(
+            face_normal([pointi(0, 0, 0), pointi(1, 0, 0), pointi(2, 0, 0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn into_group() {
+        let mesh = Mesh {
+            vertices: vec![pointi(0, 0, 0), pointi(1, 0, 0), pointi(0, 1, 0)],
+            faces: vec![[0, 1, 2]],
+            face_normals: vec![vectori(0, 0, 1)],
+            ..Default::default()
+        };
+
+        let group = mesh.into_group();
+        assert_eq!(group.len(), 1);
+        assert!(format!("{:?}", group.children()[0]).starts_with("Triangle"));
+    }
+
+    #[test]
+    fn hit_normal_is_the_faces() {
+        use crate::{
+            intersection::IntersectVec,
+            math::tuple::{point, vector},
+            ray::{Ray, RayIntersect},
+        };
+
+        // Two faces far apart, with vertex normals that aren't their flat normals
+        let tilted = vector(1.0, 1.0, 0.0).normalize();
+        let mesh = Mesh {
+            vertices: vec![
+                pointi(0, 0, 0),
+                pointi(1, 0, 0),
+                pointi(0, 1, 0),
+                pointi(3, 0, 0),
+                pointi(3, 1, 0),
+                pointi(3, 0, 1),
+            ],
+            normals: vec![
+                vectori(0, 0, 1),
+                vectori(0, 0, 1),
+                vectori(0, 0, 1),
+                tilted,
+                tilted,
+                tilted,
+            ],
+            faces: vec![[0, 1, 2], [3, 4, 5]],
+            face_normals: vec![vectori(0, 0, 1), vectori(1, 0, 0)],
+            ..Default::default()
+        };
+        let group = mesh.into_group();
+
+        let ray = Ray::new(Point::new(5.0, 0.2, 0.2), Vector::newi(-1, 0, 0));
+        let hit = group.intersect(ray).unwrap().hit().unwrap();
+        let comps = hit.prepare_computations(ray);
+
+        assert_eq!(comps.point, point(3.0, 0.2, 0.2));
+        assert_eq!(comps.normal_vector, tilted);
+        assert_eq!(hit.object.normal_at(comps.point, &hit), tilted);
+    }
+}
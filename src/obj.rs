@@ -0,0 +1,806 @@
+//! Wavefront OBJ loading. Only the bits needed for plain meshes, `v`, `vn`, `vt`, `f` and `g`,
+//! everything else is skipped (and counted).
+
+use std::{fmt, io::BufRead};
+
+use crate::{
+    math::tuple::Tuple,
+    shape::{group::Group, smooth_triangle::SmoothTriangle, triangle::Triangle, Shape},
+    trace,
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(std::io::Error),
+    /// Something on `line` (1-based) made no sense
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(e) => write!(f, "reading obj: {e}"),
+            ObjError::Parse { line, message } => write!(f, "obj line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io(e) => Some(e),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ObjError {
+    fn from(value: std::io::Error) -> Self {
+        ObjError::Io(value)
+    }
+}
+
+/// A parsed OBJ file. Everything indexes into `vertices`/`normals`/`texture_coords` 0-based,
+/// unlike the file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjModel {
+    pub vertices: Vec<Tuple>,
+    pub normals: Vec<Tuple>,
+    /// Read but not used for anything yet
+    pub texture_coords: Vec<(f64, f64)>,
+    pub triangles: Vec<ObjTriangle>,
+    /// Named parts of the model in the order they first appear, only ones with faces
+    pub groups: Vec<ObjGroup>,
+    /// Lines that weren't understood and were skipped
+    pub ignored: usize,
+    /// Faces (or triangles out of a face) skipped for not having 3 distinct corners
+    pub degenerate: usize,
+}
+
+/// One corner of a face, from `v`, `v/vt`, `v//vn` or `v/vt/vn`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceVertex {
+    pub vertex: usize,
+    pub texture: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjTriangle {
+    pub corners: [FaceVertex; 3],
+}
+
+/// Faces under a `g name` line, or before any for [DEFAULT_GROUP]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjGroup {
+    pub name: String,
+    /// Indexes into `triangles`
+    pub triangles: Vec<usize>,
+}
+
+/// Where faces before any `g` line end up
+pub const DEFAULT_GROUP: &str = "default";
+
+impl ObjTriangle {
+    pub fn vertices(&self) -> [usize; 3] {
+        self.corners.map(|c| c.vertex)
+    }
+
+    /// Only if every corner has one
+    pub fn normals(&self) -> Option<[usize; 3]> {
+        let [a, b, c] = self.corners.map(|c| c.normal);
+        Some([a?, b?, c?])
+    }
+}
+
+impl ObjModel {
+    /// The vertices of the `index`th triangle
+    pub fn triangle(&self, index: usize) -> [Tuple; 3] {
+        self.triangles[index].vertices().map(|i| self.vertices[i])
+    }
+
+    pub fn group_names(&self) -> Vec<&str> {
+        self.groups.iter().map(|g| g.name.as_str()).collect()
+    }
+
+    /// Just the triangles of the part called `name`, eg to give it its own material before
+    /// putting it together with the rest.
+    pub fn group(&self, name: &str) -> Option<Group> {
+        let part = self.groups.iter().find(|g| g.name == name)?;
+        let mut group = Group::default();
+        for &index in &part.triangles {
+            group.add_child(self.shape(&self.triangles[index]));
+        }
+
+        Some(group)
+    }
+
+    /// The whole model ready to be transformed/given a material and dropped into a World, one
+    /// child group per named part. Triangles with normals at every corner are smooth, the rest
+    /// flat.
+    pub fn into_group(self) -> Group {
+        let mut group = Group::default();
+        for name in self.group_names() {
+            group.add_child(Box::new(self.group(name).expect("name came from groups")));
+        }
+
+        group
+    }
+
+    fn shape(&self, triangle: &ObjTriangle) -> Box<dyn Shape> {
+        let [p1, p2, p3] = triangle.vertices().map(|i| self.vertices[i]);
+        match triangle.normals() {
+            Some(normals) => {
+                let [n1, n2, n3] = normals.map(|i| self.normals[i]);
+                Box::new(SmoothTriangle::new(p1, p2, p3, n1, n2, n3))
+            }
+            None => Box::new(Triangle::new(p1, p2, p3)),
+        }
+    }
+
+    /// Fan triangulates a polygon, (v1, vi, vi+1) for each i, keeping the winding, into the
+    /// group called `group`.
+    fn add_face(&mut self, corners: &[FaceVertex], group: &str) {
+        let mut distinct: Vec<Tuple> = vec![];
+        for c in corners {
+            if !distinct.contains(&self.vertices[c.vertex]) {
+                distinct.push(self.vertices[c.vertex])
+            }
+        }
+        if distinct.len() < 3 {
+            self.degenerate += 1;
+            return;
+        }
+
+        for pair in corners[1..].windows(2) {
+            let triangle = ObjTriangle {
+                corners: [corners[0], pair[0], pair[1]],
+            };
+            let [a, b, c] = triangle.vertices().map(|i| self.vertices[i]);
+            if a == b || b == c || a == c {
+                self.degenerate += 1;
+                continue;
+            }
+
+            let index = self.triangles.len();
+            self.triangles.push(triangle);
+            match self.groups.iter_mut().find(|g| g.name == group) {
+                Some(existing) => existing.triangles.push(index),
+                None => self.groups.push(ObjGroup {
+                    name: group.to_string(),
+                    triangles: vec![index],
+                }),
+            }
+        }
+    }
+
+    /// Parse a corner like `3`, `-1/2` or `3//1`.
+    fn face_vertex(&self, corner: &str) -> Result<FaceVertex, String> {
+        let mut parts = corner.split('/');
+        let vertex = resolve_index(
+            "vertex",
+            parts.next().unwrap_or_default(),
+            self.vertices.len(),
+        )?;
+        // Texture coordinates aren't used for anything yet, so a bad one isn't worth failing
+        // the whole file over
+        let texture = parts
+            .next()
+            .and_then(|raw| resolve_index("texture", raw, self.texture_coords.len()).ok());
+        let normal = match parts.next() {
+            None | Some("") => None,
+            Some(raw) => Some(resolve_index("normal", raw, self.normals.len())?),
+        };
+
+        Ok(FaceVertex {
+            vertex,
+            texture,
+            normal,
+        })
+    }
+}
+
+pub fn parse_obj(reader: impl BufRead) -> Result<ObjModel, ObjError> {
+    trace::span!(DEBUG, "load_obj");
+    let mut model = ObjModel::default();
+    let mut group = DEFAULT_GROUP.to_string();
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = number + 1;
+        let error = |message: String| ObjError::Parse {
+            line: number,
+            message,
+        };
+
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("v") => {
+                let [x, y, z] = parse_floats::<3>("vertex", parts).map_err(error)?;
+                model.vertices.push(Tuple::point(x, y, z));
+            }
+            Some("vn") => {
+                let [x, y, z] = parse_floats::<3>("normal", parts).map_err(error)?;
+                model.normals.push(Tuple::vector(x, y, z).normalize());
+            }
+            Some("vt") => {
+                // v (and w) are optional, w isn't interesting
+                let coords = parse_float_list("texture coordinate", parts).map_err(error)?;
+                match coords[..] {
+                    [u] => model.texture_coords.push((u, 0.0)),
+                    [u, v] | [u, v, _] => model.texture_coords.push((u, v)),
+                    _ => {
+                        return Err(error(format!(
+                            "texture coordinate needs 1 to 3 values, got {}",
+                            coords.len()
+                        )))
+                    }
+                }
+            }
+            Some("f") => {
+                let corners = parts
+                    .map(|p| model.face_vertex(p))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                if corners.len() < 3 {
+                    return Err(error(format!(
+                        "faces need at least 3 vertices, got {}",
+                        corners.len()
+                    )));
+                }
+                model.add_face(&corners, &group);
+            }
+            Some("g") => {
+                // Names can have spaces in them, a bare `g` goes back to the default
+                let name = parts.collect::<Vec<_>>().join(" ");
+                group = if name.is_empty() {
+                    DEFAULT_GROUP.to_string()
+                } else {
+                    name
+                };
+            }
+            None => {} // Blank lines aren't worth counting
+            Some(_) => model.ignored += 1,
+        }
+    }
+
+    Ok(model)
+}
+
+fn parse_float_list<'a>(
+    kind: &str,
+    parts: impl Iterator<Item = &'a str>,
+) -> Result<Vec<f64>, String> {
+    parts
+        .map(|p| p.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("bad {kind}: {e}"))
+}
+
+fn parse_floats<'a, const N: usize>(
+    kind: &str,
+    parts: impl Iterator<Item = &'a str>,
+) -> Result<[f64; N], String> {
+    let values = parse_float_list(kind, parts)?;
+    let count = values.len();
+    values
+        .try_into()
+        .map_err(|_| format!("{kind} needs {N} values, got {count}"))
+}
+
+/// A face's reference to a `kind` (vertex/normal/...) as a 0-based index into the `count` seen
+/// so far. Negative indices count back from the latest.
+fn resolve_index(kind: &str, raw: &str, count: usize) -> Result<usize, String> {
+    let index: i64 = raw
+        .parse()
+        .map_err(|e| format!("bad {kind} index {raw:?}: {e}"))?;
+
+    let resolved = match index {
+        1.. => index - 1,
+        ..=-1 => count as i64 + index,
+        0 => return Err(format!("{kind} indices start at 1")),
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{kind} {index} out of range, only {count} defined"));
+    }
+
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::{FRAC_PI_3, FRAC_PI_6};
+
+    use crate::{
+        camera::Camera,
+        colour::Colour,
+        math::{
+            matrix::Matrix,
+            tuple::{pointi, vector, vectori},
+        },
+        pattern::solid::SolidPattern,
+        shape::{Shape, ShapeBase},
+        world::World,
+    };
+
+    use super::*;
+
+    fn vertex_indices(model: &ObjModel) -> Vec<[usize; 3]> {
+        model.triangles.iter().map(ObjTriangle::vertices).collect()
+    }
+
+    #[test]
+    fn gibberish() {
+        let file = "There was a young lady named Bright
+who traveled much faster than light.
+She set out one day
+in a relative way,
+and came back the previous night.";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(model.ignored, 5);
+        assert!(model.vertices.is_empty());
+    }
+
+    #[test]
+    fn vertices() {
+        let file = "v -1 1 0
+v -1.0000 0.5000 0.0000
+v 1 0 0
+v 1 1 0";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(
+            model.vertices,
+            vec![
+                Tuple::pointi(-1, 1, 0),
+                Tuple::point(-1.0, 0.5, 0.0),
+                Tuple::pointi(1, 0, 0),
+                Tuple::pointi(1, 1, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn triangles() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+f 1 2 3
+f 1 3 4";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(model.triangles.len(), 2);
+        assert_eq!(
+            model.triangle(0),
+            [pointi(-1, 1, 0), pointi(-1, 0, 0), pointi(1, 0, 0)]
+        );
+        assert_eq!(
+            model.triangle(1),
+            [pointi(-1, 1, 0), pointi(1, 0, 0), pointi(1, 1, 0)]
+        );
+        assert_eq!(model.ignored, 0);
+    }
+
+    #[test]
+    fn negative_and_slashed_indices() {
+        let file = "v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+vn 0 0 1
+f -3 -2 -1
+f 1/1/1 2//2 3/3";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(vertex_indices(&model), vec![[0, 1, 2], [0, 1, 2]]);
+    }
+
+    #[test]
+    fn ignored_lines() {
+        let file = "# a comment
+o thing
+v 0 0 0
+v 1 0 0
+vn 0 1 0
+v 0 1 0
+s off
+f 1 2 3";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(model.ignored, 3);
+        assert_eq!(model.triangles.len(), 1);
+    }
+
+    macro_rules! malformed {
+        ($name:ident, $file:expr, $line:expr) => {
+            #[test]
+            fn $name() {
+                match parse_obj($file.as_bytes()) {
+                    Err(ObjError::Parse { line, .. }) => assert_eq!(line, $line),
+                    other => panic!("expected a parse error, got {other:?}"),
+                }
+            }
+        };
+    }
+
+    malformed!(too_few_vertices, "v 0 0 0\nv 1 0 0\nf 1 2", 3);
+
+    #[test]
+    fn polygon() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+v 0 2 0
+
+f 1 2 3 4 5";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(
+            vertex_indices(&model),
+            vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]
+        );
+        assert_eq!(
+            model.triangle(2),
+            [pointi(-1, 1, 0), pointi(1, 1, 0), pointi(0, 2, 0)]
+        );
+    }
+
+    #[test]
+    fn quad() {
+        let file = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(vertex_indices(&model), vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn degenerate_skipped() {
+        let file = "v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 0 0
+f 1 2 2
+f 1 2 4
+f 1 2 3
+f 1 2 1 3";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        // Two whole faces are degenerate, and both halves of the last one fold back on
+        // themselves
+        assert_eq!(vertex_indices(&model), vec![[0, 1, 2]]);
+        assert_eq!(model.degenerate, 4);
+    }
+    malformed!(out_of_range, "v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 4", 5);
+    malformed!(negative_out_of_range, "v 0 0 0\nf -1 -2 -3", 2);
+    malformed!(zero_index, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2", 4);
+    malformed!(bad_vertex, "v 0 zero 0", 1);
+
+    #[test]
+    fn normals() {
+        let file = "vn 0 0 1
+vn 0.707 0 -0.707
+vn 1 2 3";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(
+            model.normals,
+            vec![
+                vectori(0, 0, 1),
+                vector(0.707, 0.0, -0.707).normalize(),
+                vectori(1, 2, 3).normalize(),
+            ]
+        );
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+
+vn -1 0 0
+vn 1 0 0
+vn 0 1 0
+
+f 1//3 2//1 3//2
+f 1/0/3 2/102/1 3/14/2";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(model.triangles[0].normals(), Some([2, 0, 1]));
+        assert_eq!(model.triangles[1], model.triangles[0]);
+
+        let group = model.into_group();
+        let t1 = format!("{:?}", group.children()[0].children()[0]);
+        assert!(t1.starts_with("SmoothTriangle"), "{t1}");
+    }
+
+    #[test]
+    fn texture_coords() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+vt 0.5
+vt 0.25 0.75
+vt 0 1 0
+f 1/1 2/2 3/3";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        assert_eq!(
+            model.texture_coords,
+            vec![(0.5, 0.0), (0.25, 0.75), (0.0, 1.0)]
+        );
+        assert_eq!(
+            model.triangles[0].corners.map(|c| c.texture),
+            [Some(0), Some(1), Some(2)]
+        );
+        assert_eq!(model.triangles[0].normals(), None);
+    }
+
+    #[test]
+    fn mixed_normals() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+v 0 -1 0
+vn 0 0 -1
+f 1//1 2//1 3//1
+f 2 3 4
+f 2//1 3 4//1";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+        let smooth: Vec<_> = model
+            .triangles
+            .iter()
+            .map(|t| t.normals().is_some())
+            .collect();
+        assert_eq!(smooth, vec![true, false, false]);
+
+        let group = model.into_group();
+        assert_eq!(group.children()[0].children().len(), 3);
+    }
+
+    malformed!(
+        normal_out_of_range,
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//2 3//1",
+        5
+    );
+
+    /// A UV sphere with `rings` x `segments` quads, normals included if `smooth`
+    fn sphere_obj(rings: usize, segments: usize, smooth: bool) -> String {
+        use std::f64::consts::{PI, TAU};
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        for ring in 0..=rings {
+            let theta = PI * ring as f64 / rings as f64;
+            for seg in 0..segments {
+                let phi = TAU * seg as f64 / segments as f64;
+                let (x, y, z) = (
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                writeln!(out, "v {x} {y} {z}\nvn {x} {y} {z}").unwrap();
+            }
+        }
+
+        let index = |ring: usize, seg: usize| ring * segments + seg % segments + 1;
+        for ring in 0..rings {
+            for seg in 0..segments {
+                let corners = [
+                    index(ring, seg),
+                    index(ring, seg + 1),
+                    index(ring + 1, seg + 1),
+                    index(ring + 1, seg),
+                ];
+                let corners: Vec<_> = corners
+                    .iter()
+                    .map(|i| {
+                        if smooth {
+                            format!("{i}//{i}")
+                        } else {
+                            i.to_string()
+                        }
+                    })
+                    .collect();
+                writeln!(out, "f {}", corners.join(" ")).unwrap();
+            }
+        }
+
+        out
+    }
+
+    /// The biggest jump in brightness between two neighbouring pixels that both hit the sphere.
+    /// Facet edges show up as big jumps.
+    fn biggest_step(smooth: bool) -> f64 {
+        let mut sphere = parse_obj(sphere_obj(6, 8, smooth).as_bytes())
+            .unwrap()
+            .into_group();
+        // Highlights are meant to be sharp, and low poly meshes shadow themselves along the
+        // terminator, only look at diffuse
+        sphere.set_material(crate::materials::Material {
+            specular: 0.0,
+            receive_shadows: false,
+            ..Default::default()
+        });
+        let w = World::default().with_objects(vec![Box::new(sphere)]);
+        let c = Camera::new_with_transform(
+            21,
+            21,
+            FRAC_PI_6,
+            Matrix::view_transform(pointi(0, 0, -4), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        let image = c.render(&w);
+
+        let mut step: f64 = 0.0;
+        for y in 0..image.height {
+            for x in 1..image.width {
+                let (a, b) = (image[(x - 1, y)], image[(x, y)]);
+                // Interpolated normals can face away from the eye right at the silhouette, so
+                // only look at the middle of the sphere
+                let (dx, dy) = (x as f64 - 10.0, y as f64 - 10.0);
+                if dx * dx + dy * dy < 49.0 {
+                    step = step.max((a.red - b.red).abs());
+                }
+            }
+        }
+        step
+    }
+
+    #[test]
+    fn smooth_sphere_has_no_facets() {
+        let (flat, smooth) = (biggest_step(false), biggest_step(true));
+
+        assert!(smooth < flat / 2.0, "flat {flat}, smooth {smooth}");
+    }
+
+    #[test]
+    fn into_group() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+f 1 3 4";
+
+        let group = parse_obj(file.as_bytes()).unwrap().into_group();
+
+        assert_eq!(group.len(), 1);
+        let default = &group.children()[0];
+        assert_eq!(default.children().len(), 2);
+        assert_eq!(default.children()[0].transform(), &Matrix::default());
+    }
+
+    const GROUPS: &str = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 3 4";
+
+    #[test]
+    fn named_groups() {
+        let model = parse_obj(GROUPS.as_bytes()).unwrap();
+
+        assert_eq!(model.ignored, 0);
+        assert_eq!(model.group_names(), vec!["FirstGroup", "SecondGroup"]);
+        assert_eq!(model.groups[0].triangles, vec![0]);
+        assert_eq!(model.groups[1].triangles, vec![1]);
+        assert_eq!(vertex_indices(&model), vec![[0, 1, 2], [0, 2, 3]]);
+
+        assert_eq!(model.group("FirstGroup").unwrap().len(), 1);
+        assert!(model.group("ThirdGroup").is_none());
+    }
+
+    #[test]
+    fn default_and_repeated_groups() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+g wheels
+g body
+f 1 3 4
+g wheels
+f 1 2 4
+g
+f 2 3 4";
+
+        let model = parse_obj(file.as_bytes()).unwrap();
+
+        // wheels has no faces when first seen, so comes after body
+        assert_eq!(model.group_names(), vec![DEFAULT_GROUP, "body", "wheels"]);
+        assert_eq!(model.groups[0].triangles, vec![0, 3]);
+    }
+
+    #[test]
+    fn into_group_nesting() {
+        let group = parse_obj(GROUPS.as_bytes()).unwrap().into_group();
+
+        assert_eq!(group.len(), 2);
+        for part in group.children() {
+            assert_eq!(part.children().len(), 1);
+        }
+    }
+
+    #[test]
+    fn material_per_group() {
+        let model = parse_obj(GROUPS.as_bytes()).unwrap();
+        let mut first = model.group("FirstGroup").unwrap();
+        first.set_material(crate::materials::Material {
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.0, 0.0))),
+            ..Default::default()
+        });
+        let mut whole = Group::new(Matrix::translationi(0, -1, 0) * Matrix::scalingi(2, 2, 2));
+        whole.add_child(Box::new(first));
+        whole.add_child(Box::new(model.group("SecondGroup").unwrap()));
+
+        let w = World::default().with_objects(vec![Box::new(whole)]);
+        let c = Camera::new_with_transform(
+            11,
+            11,
+            FRAC_PI_3,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        let image = c.render(&w);
+
+        // FirstGroup is the bottom left half of the square, SecondGroup the top right
+        let first = image[(3, 6)];
+        let second = image[(7, 4)];
+        assert!(first.red > 0.0);
+        assert_eq!(first, Colour::new(first.red, 0.0, 0.0));
+        assert!(second.red > 0.0);
+        assert_eq!(second, Colour::new(second.red, second.red, second.red));
+    }
+
+    #[test]
+    fn render_tetrahedron() {
+        let model = parse_obj(include_str!("../models/tetrahedron.obj").as_bytes()).unwrap();
+        assert_eq!(model.triangles.len(), 4);
+
+        let mut tetra = model.into_group();
+        tetra.set_transform(Matrix::translation(0.0, -0.5, 0.0));
+        tetra.set_material(crate::materials::Material {
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.2, 0.2))),
+            ..Default::default()
+        });
+
+        let w = World::default().with_objects(vec![Box::new(tetra)]);
+        let c = Camera::new_with_transform(
+            11,
+            11,
+            FRAC_PI_3,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+
+        let image = c.render(&w);
+
+        // The middle is the front faces, red, the corners miss
+        let middle = image[(5, 5)];
+        assert!(middle.red > middle.green && middle.green == middle.blue);
+        assert_eq!(image[(0, 0)], Colour::BLACK);
+        assert_eq!(image[(10, 0)], Colour::BLACK);
+    }
+}
@@ -0,0 +1,496 @@
+//! Writing animations out a frame at a time, as numbered image files in a directory.
+//!
+//! ```no_run
+//! # use raytracer::{canvas::Canvas, output::{FrameFormat, FrameWriter}};
+//! let mut frames = FrameWriter::new("out/frames", FrameFormat::Png)?
+//!     .with_template("spin_{:03}")?
+//!     .with_fps(30);
+//! for n in 0..90 {
+//!     // out/frames/spin_000.png, spin_001.png, ...
+//!     frames.write_frame(n, &Canvas::new(64, 64))?;
+//! }
+//! # Ok::<(), raytracer::output::FrameError>(())
+//! ```
+
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{canvas::Canvas, colour::Colour};
+
+/// Written next to the frames, see FrameWriter::manifest
+pub const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug)]
+pub enum FrameError {
+    Io(io::Error),
+    /// There's already a frame at this path, and FrameWriter::with_overwrite is off
+    Exists(PathBuf),
+    /// Templates need exactly one `{}` or `{:0N}` for the frame number
+    BadTemplate(String),
+    /// Every frame has to be the size of the first one
+    SizeMismatch {
+        frame: usize,
+        expected: (usize, usize),
+        got: (usize, usize),
+    },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Io(e) => write!(f, "writing frame: {e}"),
+            FrameError::Exists(path) => write!(f, "{} already exists", path.display()),
+            FrameError::BadTemplate(template) => write!(
+                f,
+                "frame template {template:?} needs one {{}} or {{:0N}} for the frame number"
+            ),
+            FrameError::SizeMismatch {
+                frame,
+                expected: (ew, eh),
+                got: (w, h),
+            } => write!(f, "frame {frame} is {w}x{h}, the first frame is {ew}x{eh}"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FrameError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FrameError {
+    fn from(value: io::Error) -> Self {
+        FrameError::Io(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    PpmAscii,
+    PpmBinary,
+    Png,
+}
+
+impl FrameFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::PpmAscii | FrameFormat::PpmBinary => "ppm",
+            FrameFormat::Png => "png",
+        }
+    }
+
+    fn encode(&self, canvas: &Canvas) -> Vec<u8> {
+        match self {
+            FrameFormat::PpmAscii => canvas.into_ppm(false).into_bytes(),
+            FrameFormat::PpmBinary => canvas.into_ppm_binary(false),
+            FrameFormat::Png => canvas.into_png(false),
+        }
+    }
+}
+
+/// A frame file name, split around the frame number
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Template {
+    before: String,
+    /// Zero pad the number to this many digits
+    width: usize,
+    after: String,
+}
+
+impl Template {
+    fn parse(template: &str) -> Result<Self, FrameError> {
+        let bad = || FrameError::BadTemplate(template.to_owned());
+        let (before, rest) = template.split_once('{').ok_or_else(bad)?;
+        let (spec, after) = rest.split_once('}').ok_or_else(bad)?;
+        if after.contains(['{', '}']) || before.contains('}') {
+            return Err(bad());
+        }
+
+        let width = match spec {
+            "" => 0,
+            _ => spec
+                .strip_prefix(":0")
+                .and_then(|w| w.parse().ok())
+                .ok_or_else(bad)?,
+        };
+        Ok(Self {
+            before: before.to_owned(),
+            width,
+            after: after.to_owned(),
+        })
+    }
+
+    fn name(&self, n: usize) -> String {
+        format!(
+            "{}{n:0width$}{}",
+            self.before,
+            self.after,
+            width = self.width
+        )
+    }
+}
+
+/// Writes canvases out as `<directory>/<template>.<extension>`, one per frame, and keeps a
+/// small manifest of the sequence up to date beside them. By default frames are named
+/// `frame_00000`, `frame_00001`, ... and existing files are overwritten.
+#[derive(Debug)]
+pub struct FrameWriter {
+    directory: PathBuf,
+    template: Template,
+    format: FrameFormat,
+    stamp: bool,
+    overwrite: bool,
+    fps: u32,
+    size: Option<(usize, usize)>,
+    written: usize,
+}
+
+impl FrameWriter {
+    /// Makes `directory` (and any parents) if it isn't there already
+    pub fn new(directory: impl AsRef<Path>, format: FrameFormat) -> Result<Self, FrameError> {
+        let directory = directory.as_ref().to_owned();
+        fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory,
+            template: Template::parse("frame_{:05}").unwrap(),
+            format,
+            stamp: false,
+            overwrite: true,
+            fps: 24,
+            size: None,
+            written: 0,
+        })
+    }
+
+    /// The file name, without the extension, with `{}` (or `{:05}` for zero padding) where
+    /// the frame number goes
+    pub fn with_template(mut self, template: &str) -> Result<Self, FrameError> {
+        self.template = Template::parse(template)?;
+        Ok(self)
+    }
+
+    /// Draw the frame number in the top left corner of each frame
+    pub fn with_stamp(mut self, stamp: bool) -> Self {
+        self.stamp = stamp;
+        self
+    }
+
+    /// Turned off, writing a frame that's already there is FrameError::Exists
+    pub fn with_overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Just a hint for whatever plays the frames back, it goes in the manifest
+    pub fn with_fps(mut self, fps: u32) -> Self {
+        self.fps = fps;
+        self
+    }
+
+    /// Where frame `n` goes
+    pub fn path(&self, n: usize) -> PathBuf {
+        self.directory.join(format!(
+            "{}.{}",
+            self.template.name(n),
+            self.format.extension()
+        ))
+    }
+
+    /// Where the manifest goes
+    pub fn manifest(&self) -> PathBuf {
+        self.directory.join(MANIFEST)
+    }
+
+    /// How many frames have been written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    pub fn write_frame(&mut self, n: usize, canvas: &Canvas) -> Result<PathBuf, FrameError> {
+        let size = (canvas.width, canvas.height);
+        if let Some(expected) = self.size.filter(|s| *s != size) {
+            return Err(FrameError::SizeMismatch {
+                frame: n,
+                expected,
+                got: size,
+            });
+        }
+
+        let bytes = if self.stamp {
+            let mut stamped = canvas.clone();
+            stamp(&mut stamped, n);
+            self.format.encode(&stamped)
+        } else {
+            self.format.encode(canvas)
+        };
+
+        let path = self.path(n);
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .create_new(!self.overwrite)
+            .open(&path)
+        {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(FrameError::Exists(path))
+            }
+            file => file?,
+        };
+        file.write_all(&bytes)?;
+
+        self.size = Some(size);
+        self.written += 1;
+        self.write_manifest()?;
+        Ok(path)
+    }
+
+    /// Rewritten after every frame, so it's right even if the render never finishes
+    fn write_manifest(&self) -> Result<(), FrameError> {
+        let (width, height) = self.size.unwrap_or_default();
+        let example = self.path(0);
+        let example = example.file_name().unwrap_or_default().to_string_lossy();
+        let manifest = format!(
+            "{{\n  \"frames\": {},\n  \"width\": {width},\n  \"height\": {height},\n  \"fps\": {},\n  \"first\": {example:?}\n}}\n",
+            self.written, self.fps
+        );
+        fs::write(self.manifest(), manifest)?;
+        Ok(())
+    }
+}
+
+/// 3x5 pixel digits, a row to a byte with the leftmost pixel in bit 2
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// White digits on a black box in the top left corner, clipped to the canvas
+fn stamp(canvas: &mut Canvas, n: usize) {
+    let digits = n.to_string();
+    // A pixel of border all round, and between the digits
+    let width = digits.len() * 4 + 1;
+    for y in 0..7.min(canvas.height) {
+        for x in 0..width.min(canvas.width) {
+            canvas[(x, y)] = Colour::BLACK;
+        }
+    }
+
+    for (i, digit) in digits.bytes().enumerate() {
+        let glyph = DIGITS[(digit - b'0') as usize];
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..3 {
+                let (x, y) = (1 + i * 4 + column, 1 + row);
+                if bits & (0b100 >> column) != 0 && x < canvas.width && y < canvas.height {
+                    canvas[(x, y)] = Colour::WHITE;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::{canvas::Canvas, colour::Colour, test_util::scratch_dir};
+
+    use super::{FrameError, FrameFormat, FrameWriter, Template};
+
+    fn frame(colour: Colour) -> Canvas {
+        Canvas::new_with_colour(3, 2, colour)
+    }
+
+    #[test]
+    fn templates() {
+        let t = Template::parse("frame_{:05}").unwrap();
+        assert_eq!(t.name(0), "frame_00000");
+        assert_eq!(t.name(42), "frame_00042");
+        assert_eq!(t.name(1234567), "frame_1234567");
+        assert_eq!(Template::parse("{}-shot").unwrap().name(7), "7-shot");
+        assert_eq!(Template::parse("a{:02}b").unwrap().name(3), "a03b");
+
+        for bad in [
+            "frame", "frame_{", "f_{:5}", "f_{:0x}", "{}{}", "}{}", "{:03}}",
+        ] {
+            assert!(
+                matches!(Template::parse(bad), Err(FrameError::BadTemplate(t)) if t == bad),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn naming_and_directories() {
+        let dir = scratch_dir("frames-naming");
+        let nested = dir.join("a/b");
+        let mut writer = FrameWriter::new(&nested, FrameFormat::PpmBinary).unwrap();
+        assert!(nested.is_dir());
+
+        for n in [0, 1, 10, 99_999] {
+            let path = writer.write_frame(n, &frame(Colour::WHITE)).unwrap();
+            assert_eq!(path, writer.path(n));
+        }
+        let mut names: Vec<_> = fs::read_dir(&nested)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "frame_00000.ppm",
+                "frame_00001.ppm",
+                "frame_00010.ppm",
+                "frame_99999.ppm",
+                "manifest.json"
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn formats() {
+        let dir = scratch_dir("frames-formats");
+        let red = Colour::new(1.0, 0.0, 0.0);
+
+        for format in [
+            FrameFormat::PpmAscii,
+            FrameFormat::PpmBinary,
+            FrameFormat::Png,
+        ] {
+            let mut writer = FrameWriter::new(&dir, format).unwrap();
+            let path = writer.write_frame(3, &frame(red)).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            match format {
+                FrameFormat::PpmAscii => {
+                    let text = String::from_utf8(bytes).unwrap();
+                    assert!(text.starts_with("P3\n3 2\n255\n255 0 0"), "{text}");
+                }
+                FrameFormat::PpmBinary => {
+                    let header = b"P6 3 2 255\n";
+                    assert_eq!(&bytes[..header.len()], header);
+                    assert_eq!(&bytes[header.len()..], [255, 0, 0].repeat(6));
+                }
+                FrameFormat::Png => {
+                    assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+                    assert_eq!(&bytes[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+                }
+            }
+            assert_eq!(path.extension().unwrap(), format.extension());
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn overwriting() {
+        let dir = scratch_dir("frames-overwrite");
+        let mut writer = FrameWriter::new(&dir, FrameFormat::PpmBinary).unwrap();
+        writer.write_frame(0, &frame(Colour::BLACK)).unwrap();
+        writer.write_frame(0, &frame(Colour::WHITE)).unwrap();
+        assert!(fs::read(writer.path(0)).unwrap().ends_with(&[255; 18]));
+
+        let mut careful = FrameWriter::new(&dir, FrameFormat::PpmBinary)
+            .unwrap()
+            .with_overwrite(false);
+        let err = careful.write_frame(0, &frame(Colour::BLACK)).unwrap_err();
+        assert!(matches!(&err, FrameError::Exists(p) if *p == writer.path(0)));
+        // Left alone
+        assert!(fs::read(writer.path(0)).unwrap().ends_with(&[255; 18]));
+        careful.write_frame(1, &frame(Colour::BLACK)).unwrap();
+        assert_eq!(careful.written(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn manifest() {
+        let dir = scratch_dir("frames-manifest");
+        let mut writer = FrameWriter::new(&dir, FrameFormat::Png)
+            .unwrap()
+            .with_template("shot{}")
+            .unwrap()
+            .with_fps(30);
+        for n in 0..4 {
+            writer.write_frame(n, &frame(Colour::BLACK)).unwrap();
+        }
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(writer.manifest()).unwrap()).unwrap();
+        assert_eq!(
+            manifest,
+            serde_json::json!({
+                "frames": 4,
+                "width": 3,
+                "height": 2,
+                "fps": 30,
+                "first": "shot0.png",
+            })
+        );
+
+        let err = writer.write_frame(4, &Canvas::new(2, 2)).unwrap_err();
+        assert!(matches!(
+            err,
+            FrameError::SizeMismatch {
+                frame: 4,
+                expected: (3, 2),
+                got: (2, 2)
+            }
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stamps() {
+        let dir = scratch_dir("frames-stamp");
+        let mut writer = FrameWriter::new(&dir, FrameFormat::PpmBinary)
+            .unwrap()
+            .with_stamp(true);
+        let grey = Colour::new(0.5, 0.5, 0.5);
+        let path = writer
+            .write_frame(17, &Canvas::new_with_colour(12, 8, grey))
+            .unwrap();
+
+        let bytes = fs::read(path).unwrap();
+        let pixels: Vec<&[u8]> = bytes[b"P6 12 8 255\n".len()..].chunks(3).collect();
+        let at = |x: usize, y: usize| pixels[y * 12 + x];
+        // The border, then the top of the 1 and the 7
+        assert_eq!(at(0, 0), [0, 0, 0]);
+        assert_eq!(
+            &[at(1, 1), at(2, 1), at(3, 1)],
+            &[[0, 0, 0], [255; 3], [0, 0, 0]]
+        );
+        assert_eq!(&[at(5, 1), at(6, 1), at(7, 1)], &[[255; 3]; 3]);
+        // Untouched outside the box
+        assert_eq!(at(9, 0), [128; 3]);
+        assert_eq!(at(0, 7), [128; 3]);
+
+        // Too small for the whole stamp is fine too
+        writer.write_frame(12345, &Canvas::new(2, 2)).unwrap_err();
+        let mut tiny = FrameWriter::new(&dir, FrameFormat::Png)
+            .unwrap()
+            .with_stamp(true);
+        tiny.write_frame(12345, &Canvas::new(2, 2)).unwrap();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
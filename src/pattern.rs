@@ -0,0 +1,108 @@
+use core::{any::Any, fmt::Debug};
+
+use crate::prelude::*;
+use crate::{
+    colour::Colour,
+    math::{matrix::Matrix, tuple::Tuple},
+    shape::Shape,
+};
+
+pub mod image;
+pub mod perturbed;
+pub mod radial_gradient;
+pub mod solid;
+pub mod stripe;
+#[cfg(test)]
+mod test_pattern;
+pub mod uv_checkers;
+pub mod vertex_colour;
+
+/// Boilerplate every pattern needs so that Materials can be cloned and compared, see
+/// pattern_base!
+pub trait PatternBase {
+    /// Where the pattern sits on its object, pattern_at is in the space this transforms from
+    fn transform(&self) -> &Matrix;
+    fn set_transform(&mut self, transform: Matrix);
+    fn box_clone(&self) -> Box<dyn Pattern>;
+    fn as_any(&self) -> &dyn Any;
+    fn pattern_eq(&self, other: &dyn Pattern) -> bool;
+}
+
+pub trait Pattern: Debug + Send + Sync + PatternBase {
+    /// The colour of the pattern at `point`, in pattern space.
+    fn pattern_at(&self, point: Tuple) -> Colour;
+
+    /// One colour standing in for the whole pattern, for when it's too far down a chain of
+    /// reflections to be worth working out (see FLAT_PATTERN_DEPTH). Whatever's at the origin
+    /// unless a pattern knows better.
+    fn average(&self) -> Colour {
+        self.pattern_at(Tuple::point(0.0, 0.0, 0.0))
+    }
+
+    /// The colour at `object_point`, taken into pattern space by the inverse of the pattern's
+    /// transform. Like shapes, one that won't invert is treated as no transform at all.
+    fn pattern_at_object(&self, object_point: Tuple) -> Colour {
+        if self.transform().is_identity() {
+            return self.pattern_at(object_point);
+        }
+        self.pattern_at(&self.transform().inverse().unwrap_or_default() * object_point)
+    }
+
+    /// The colour at `world_point` on `shape`, going through the shape's transform then the
+    /// pattern's
+    fn pattern_at_shape(&self, shape: &dyn Shape, world_point: Tuple) -> Colour {
+        let object_point = &shape.transform().inverse().unwrap_or_default() * world_point;
+        self.pattern_at_object(object_point)
+    }
+}
+
+impl Clone for Box<dyn Pattern> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl PartialEq for dyn Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern_eq(other)
+    }
+}
+
+// Without this, #[derive(PartialEq)] on a struct with a Box<dyn Pattern> field tries to move
+// out of the box (rust-lang/rust#31740)
+impl PartialEq<&Self> for Box<dyn Pattern> {
+    fn eq(&self, other: &&Self) -> bool {
+        self.pattern_eq(&***other)
+    }
+}
+
+macro_rules! pattern_base {
+    ($name:ident) => {
+        impl $crate::pattern::PatternBase for $name {
+            fn transform(&self) -> &$crate::math::matrix::Matrix {
+                &self.transform
+            }
+
+            fn set_transform(&mut self, transform: $crate::math::matrix::Matrix) {
+                self.transform = transform
+            }
+
+            fn box_clone(&self) -> alloc::boxed::Box<dyn $crate::pattern::Pattern> {
+                alloc::boxed::Box::new(self.clone())
+            }
+
+            fn as_any(&self) -> &dyn core::any::Any {
+                self
+            }
+
+            fn pattern_eq(&self, other: &dyn $crate::pattern::Pattern) -> bool {
+                other
+                    .as_any()
+                    .downcast_ref::<$name>()
+                    .is_some_and(|o| o == self)
+            }
+        }
+    };
+}
+
+pub(crate) use pattern_base;
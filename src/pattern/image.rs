@@ -0,0 +1,440 @@
+use alloc::sync::Arc;
+use core::{
+    f64::consts::{PI, TAU},
+    fmt,
+};
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+use crate::{
+    canvas::Canvas,
+    colour::Colour,
+    error::RayTracerError,
+    math::{matrix::Matrix, tuple::Tuple},
+};
+
+use super::{pattern_base, Pattern};
+
+/// How points are turned into (u, v) texture coordinates, both 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UvMapping {
+    /// Around a sphere at the origin: u is the longitude (going anticlockwise looking down y),
+    /// v the latitude from the south pole up. Equirectangular images wrap it exactly.
+    #[default]
+    Spherical,
+    /// x and z, repeating every unit
+    Planar,
+}
+
+impl UvMapping {
+    pub fn uv(&self, point: Tuple) -> (f64, f64) {
+        match self {
+            Self::Spherical => spherical_map(point),
+            Self::Planar => (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0)),
+        }
+    }
+}
+
+/// `point`'s longitude and latitude on a sphere around the origin, scaled to 0..1, see
+/// UvMapping::Spherical. (0, 0, -1) is at (0, 0.5), and the origin itself gets that too.
+pub fn spherical_map(point: Tuple) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let radius = (point - Tuple::point(0.0, 0.0, 0.0)).magnitude();
+    if radius == 0.0 {
+        return (0.0, 0.5);
+    }
+    let phi = (point.y / radius).clamp(-1.0, 1.0).acos();
+    (1.0 - (theta / TAU + 0.5), 1.0 - phi / PI)
+}
+
+/// How a colour's picked between texel centres
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureFilter {
+    /// Whichever texel it's in, blocky close up
+    #[default]
+    Nearest,
+    /// The four texels around it, blended by how close each one is
+    Bilinear,
+}
+
+/// What's past the edges of the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// The edge texels go on forever
+    #[default]
+    Clamp,
+    /// The image tiles, so the right edge carries on into the left (around a sphere, say)
+    Repeat,
+}
+
+/// An image wrapped onto a shape. u goes left to right across the image and v bottom to top.
+/// The image is shared, so cloning the pattern (and its material) is cheap.
+#[derive(Clone)]
+pub struct ImagePattern {
+    pub image: Arc<Canvas>,
+    pub mapping: UvMapping,
+    pub filter: TextureFilter,
+    pub wrap: WrapMode,
+    pub transform: Matrix,
+}
+
+impl ImagePattern {
+    pub fn new(image: Canvas, mapping: UvMapping) -> Self {
+        Self {
+            image: Arc::new(image),
+            mapping,
+            filter: TextureFilter::default(),
+            wrap: WrapMode::default(),
+            transform: Matrix::default(),
+        }
+    }
+
+    /// From a PPM file, which like most image files is sRGB encoded, so it's decoded back to the
+    /// linear colours lighting works in. Use new for a canvas that's linear already, say a render.
+    pub fn from_ppm(data: &[u8], mapping: UvMapping) -> Result<Self, RayTracerError> {
+        Ok(Self::new(Canvas::from_ppm(data)?.decoded_srgb(), mapping))
+    }
+
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// The colour at (u, v). Texel (x, y)'s centre is at ((x + 0.5) / width, 1 - (y + 0.5) /
+    /// height).
+    pub fn uv_at(&self, u: f64, v: f64) -> Colour {
+        let (width, height) = (self.image.width, self.image.height);
+        if width == 0 || height == 0 {
+            return Colour::BLACK;
+        }
+        let x = u * width as f64;
+        let y = (1.0 - v) * height as f64;
+
+        match self.filter {
+            TextureFilter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            TextureFilter::Bilinear => {
+                // Measured from the texel centres up and to the left
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = self.texel(x0, y0).lerp(self.texel(x0 + 1, y0), fx);
+                let bottom = self.texel(x0, y0 + 1).lerp(self.texel(x0 + 1, y0 + 1), fx);
+                top.lerp(bottom, fy)
+            }
+        }
+    }
+
+    /// The texel at (x, y), which can be off the image
+    fn texel(&self, x: i64, y: i64) -> Colour {
+        let wrap = |n: i64, size: usize| match self.wrap {
+            WrapMode::Clamp => n.clamp(0, size as i64 - 1) as usize,
+            WrapMode::Repeat => n.rem_euclid(size as i64) as usize,
+        };
+        self.image[(wrap(x, self.image.width), wrap(y, self.image.height))]
+    }
+}
+
+impl fmt::Debug for ImagePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImagePattern")
+            .field("width", &self.image.width)
+            .field("height", &self.image.height)
+            .field("mapping", &self.mapping)
+            .field("filter", &self.filter)
+            .field("wrap", &self.wrap)
+            .field("transform", &self.transform)
+            .finish()
+    }
+}
+
+impl PartialEq for ImagePattern {
+    fn eq(&self, other: &Self) -> bool {
+        (Arc::ptr_eq(&self.image, &other.image)
+            || (self.image.width == other.image.width && self.image.vec() == other.image.vec()))
+            && self.mapping == other.mapping
+            && self.filter == other.filter
+            && self.wrap == other.wrap
+            && self.transform == other.transform
+    }
+}
+
+pattern_base!(ImagePattern);
+
+impl Pattern for ImagePattern {
+    fn pattern_at(&self, point: Tuple) -> Colour {
+        let (u, v) = self.mapping.uv(point);
+        self.uv_at(u, v)
+    }
+
+    /// From an 8 x 8 grid of lookups across the image rather than every texel, it only has to
+    /// be roughly right
+    fn average(&self) -> Colour {
+        let samples = (0..64).map(|i| {
+            let (x, y) = ((i % 8) as f64, (i / 8) as f64);
+            self.uv_at((x + 0.5) / 8.0, (y + 0.5) / 8.0)
+        });
+        samples.sum::<Colour>() / 64.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_3;
+
+    use crate::{
+        camera::Camera,
+        canvas::Canvas,
+        colour::Colour,
+        lights::PointLight,
+        materials::Material,
+        math::{
+            float,
+            matrix::Matrix,
+            tuple::{point, pointi, vectori},
+        },
+        pattern::Pattern,
+        scene::Scene,
+        shape::sphere::Sphere,
+        world::World,
+    };
+
+    use super::{ImagePattern, TextureFilter, UvMapping, WrapMode};
+
+    /// 4 x 2: red, green, blue, white along the top and black, red, green, blue underneath
+    fn image() -> Canvas {
+        let mut canvas = Canvas::new(4, 2);
+        for (x, colour) in [Colour::RED, Colour::GREEN, Colour::BLUE, Colour::WHITE]
+            .into_iter()
+            .enumerate()
+        {
+            canvas[(x, 0)] = colour;
+        }
+        for (x, colour) in [Colour::BLACK, Colour::RED, Colour::GREEN, Colour::BLUE]
+            .into_iter()
+            .enumerate()
+        {
+            canvas[(x, 1)] = colour;
+        }
+        canvas
+    }
+
+    fn pattern(filter: TextureFilter, wrap: WrapMode) -> ImagePattern {
+        ImagePattern::new(image(), UvMapping::Planar)
+            .with_filter(filter)
+            .with_wrap(wrap)
+    }
+
+    /// The middle of texel (x, y)
+    fn centre(x: usize, y: usize) -> (f64, f64) {
+        ((x as f64 + 0.5) / 4.0, 1.0 - (y as f64 + 0.5) / 2.0)
+    }
+
+    #[test]
+    fn texel_centres() {
+        let image = image();
+        for wrap in [WrapMode::Clamp, WrapMode::Repeat] {
+            let nearest = pattern(TextureFilter::Nearest, wrap);
+            let bilinear = pattern(TextureFilter::Bilinear, wrap);
+            for x in 0..4 {
+                for y in 0..2 {
+                    let (u, v) = centre(x, y);
+                    assert_eq!(nearest.uv_at(u, v), image[(x, y)]);
+                    assert_eq!(bilinear.uv_at(u, v), image[(x, y)], "{x} {y} {wrap:?}");
+                }
+            }
+        }
+        // Anywhere in a texel is that texel, for nearest
+        let nearest = pattern(TextureFilter::Nearest, WrapMode::Clamp);
+        assert_eq!(nearest.uv_at(0.01, 0.99), Colour::RED);
+        assert_eq!(nearest.uv_at(0.49, 0.01), Colour::RED);
+    }
+
+    #[test]
+    fn midpoints() {
+        let bilinear = pattern(TextureFilter::Bilinear, WrapMode::Clamp);
+        // Between red and green along the top
+        assert_eq!(bilinear.uv_at(0.25, 0.75), Colour::new(0.5, 0.5, 0.0));
+        // Between green above and red below
+        assert_eq!(bilinear.uv_at(0.375, 0.5), Colour::new(0.5, 0.5, 0.0));
+        // In the middle of four
+        assert_eq!(
+            bilinear.uv_at(0.5, 0.5),
+            (Colour::GREEN + Colour::BLUE + Colour::RED + Colour::GREEN) / 4.0
+        );
+        // A quarter of the way from blue to white
+        let (u, v) = centre(2, 0);
+        assert_eq!(
+            bilinear.uv_at(u + 0.0625, v),
+            Colour::BLUE.lerp(Colour::WHITE, 0.25)
+        );
+    }
+
+    #[test]
+    fn seam() {
+        // On the left edge of the image, half way between the last column and the first
+        let repeat = pattern(TextureFilter::Bilinear, WrapMode::Repeat);
+        let (_, v) = centre(0, 0);
+        assert_eq!(repeat.uv_at(0.0, v), Colour::WHITE.lerp(Colour::RED, 0.5));
+        assert_eq!(repeat.uv_at(1.0, v), Colour::WHITE.lerp(Colour::RED, 0.5));
+        // Top to bottom too
+        let (u, _) = centre(0, 0);
+        assert_eq!(repeat.uv_at(u, 1.0), Colour::RED.lerp(Colour::BLACK, 0.5));
+
+        // Going right around a sphere, there's no jump where the image's edges meet
+        let globe = ImagePattern::new(image(), UvMapping::Spherical)
+            .with_filter(TextureFilter::Bilinear)
+            .with_wrap(WrapMode::Repeat);
+        let (u, v) = UvMapping::Spherical.uv(pointi(0, 0, -1));
+        assert!(u == 0.0 || u == 1.0, "{u}");
+        assert_eq!(v, 0.5);
+        let either_side =
+            [point(-0.001, 0.0, -1.0), point(0.001, 0.0, -1.0)].map(|p| globe.pattern_at(p));
+        let jump = either_side[0] - either_side[1];
+        assert!(
+            jump.red.abs() + jump.green.abs() + jump.blue.abs() < 0.01,
+            "{either_side:?}"
+        );
+    }
+
+    #[test]
+    fn clamp() {
+        let clamp = pattern(TextureFilter::Bilinear, WrapMode::Clamp);
+        let (_, v) = centre(0, 0);
+        // Past the left of the first column's centre is all first column
+        assert_eq!(clamp.uv_at(0.0, v), Colour::RED);
+        assert_eq!(clamp.uv_at(-3.0, v), Colour::RED);
+        assert_eq!(clamp.uv_at(1.0, v), Colour::WHITE);
+        assert_eq!(clamp.uv_at(0.0, 2.0), Colour::RED);
+        assert_eq!(clamp.uv_at(1.0, -1.0), Colour::BLUE);
+
+        let nearest = pattern(TextureFilter::Nearest, WrapMode::Clamp);
+        assert_eq!(nearest.uv_at(1.0, 1.0), Colour::WHITE);
+        assert_eq!(nearest.uv_at(-0.5, 0.0), Colour::BLACK);
+        let nearest = pattern(TextureFilter::Nearest, WrapMode::Repeat);
+        assert_eq!(nearest.uv_at(1.0, 1.0), Colour::RED);
+        assert_eq!(nearest.uv_at(-0.1, 0.9), Colour::WHITE);
+    }
+
+    #[test]
+    fn smoother_render() {
+        let render = |filter| {
+            let material = Material {
+                pattern: Box::new(
+                    ImagePattern::new(image(), UvMapping::Spherical)
+                        .with_filter(filter)
+                        .with_wrap(WrapMode::Repeat),
+                ),
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            };
+            let world = World::new(
+                vec![Box::new(Sphere::new_with_material(material))],
+                vec![PointLight::new_boxed(Colour::WHITE, pointi(0, 0, -10))],
+            );
+            let camera = Camera::new_with_transform(
+                24,
+                24,
+                FRAC_PI_3,
+                Matrix::view_transform(point(0.0, 0.0, -1.5), pointi(0, 0, 0), vectori(0, 1, 0)),
+            );
+            Scene::new(world, camera).render()
+        };
+
+        // Close enough that the sphere fills the view. The biggest jump between neighbouring
+        // pixels, and how many different colours
+        let roughness = |canvas: &Canvas| {
+            let mut biggest: f64 = 0.0;
+            let mut colours = vec![];
+            for y in 0..canvas.height {
+                for x in 0..canvas.width {
+                    let c = canvas[(x, y)];
+                    if !colours.contains(&c) {
+                        colours.push(c);
+                    }
+                    if x + 1 < canvas.width {
+                        let d = canvas[(x + 1, y)] - c;
+                        biggest = biggest.max(d.red.abs() + d.green.abs() + d.blue.abs());
+                    }
+                }
+            }
+            (biggest, colours.len())
+        };
+        let (nearest_jump, nearest_colours) = roughness(&render(TextureFilter::Nearest));
+        let (bilinear_jump, bilinear_colours) = roughness(&render(TextureFilter::Bilinear));
+        assert!(nearest_colours <= 8, "{nearest_colours}");
+        assert!(bilinear_colours > nearest_colours * 4, "{bilinear_colours}");
+        assert!(
+            bilinear_jump < nearest_jump / 2.0,
+            "{bilinear_jump} {nearest_jump}"
+        );
+    }
+
+    #[test]
+    fn spherical_map() {
+        let half = core::f64::consts::FRAC_1_SQRT_2;
+        for (p, expected) in [
+            (pointi(0, 0, -1), (0.0, 0.5)),
+            (pointi(1, 0, 0), (0.25, 0.5)),
+            (pointi(0, 0, 1), (0.5, 0.5)),
+            (pointi(-1, 0, 0), (0.75, 0.5)),
+            (pointi(0, 1, 0), (0.5, 1.0)),
+            (pointi(0, -1, 0), (0.5, 0.0)),
+            (point(half, half, 0.0), (0.25, 0.75)),
+        ] {
+            let (u, v) = super::spherical_map(p);
+            assert!(
+                float::equal(u, expected.0) && float::equal(v, expected.1),
+                "{p:?} {u} {v}"
+            );
+            assert_eq!(UvMapping::Spherical.uv(p), (u, v));
+        }
+        // Only the direction matters
+        assert_eq!(
+            super::spherical_map(pointi(0, 3, 0)),
+            super::spherical_map(pointi(0, 1, 0))
+        );
+    }
+
+    #[test]
+    fn average() {
+        // The sample grid lines up with the 4 x 2 texels, so it's exact here
+        let image = image();
+        let mean = image.iter().sum::<Colour>() / 8.0;
+        assert_eq!(
+            pattern(TextureFilter::Nearest, WrapMode::Clamp).average(),
+            mean
+        );
+    }
+
+    #[test]
+    fn from_ppm_decodes() {
+        // Written out sRGB encoded as usual, read back it's the linear colours again, give or
+        // take the 8 bit rounding
+        let mut linear = Canvas::new(2, 1);
+        linear[(0, 0)] = Colour::new(0.5, 0.2, 0.05);
+        linear[(1, 0)] = Colour::WHITE * 0.214;
+        let file = linear.into_ppm_binary(true);
+
+        let texture = ImagePattern::from_ppm(&file, UvMapping::Planar).unwrap();
+        for x in 0..2 {
+            let read = texture.image[(x, 0)];
+            let diff = read - linear[(x, 0)];
+            assert!(
+                diff.red.abs().max(diff.green.abs()).max(diff.blue.abs()) < 0.005,
+                "{read:?}"
+            );
+        }
+        // Not read as if the file were linear, which would be far too bright
+        assert!(Canvas::from_ppm(&file).unwrap()[(1, 0)].red > 0.45);
+
+        assert!(ImagePattern::from_ppm(b"P9", UvMapping::Planar).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    colour::Colour,
+    math::{
+        matrix::Matrix,
+        noise::Perlin,
+        tuple::{vector, Tuple},
+    },
+    prelude::*,
+};
+
+use super::{pattern_base, Pattern};
+
+/// Another pattern, looked up at a point jittered by Perlin noise. Stripes come out as marble,
+/// checkers go wavy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerturbedPattern {
+    pub pattern: Box<dyn Pattern>,
+    /// Roughly how far the point can move along each axis. The axes are jittered separately, so
+    /// overall it can move up to about √3 times this.
+    pub amplitude: f64,
+    /// How quickly the noise changes, higher is busier
+    pub frequency: f64,
+    pub transform: Matrix,
+    noise: Perlin,
+}
+
+impl PerturbedPattern {
+    /// The same seed always jitters the same way
+    pub fn new(pattern: impl Pattern + 'static, amplitude: f64, frequency: f64, seed: u64) -> Self {
+        Self {
+            pattern: Box::new(pattern),
+            amplitude,
+            frequency,
+            transform: Matrix::default(),
+            noise: Perlin::new(seed),
+        }
+    }
+}
+
+pattern_base!(PerturbedPattern);
+
+impl Pattern for PerturbedPattern {
+    fn pattern_at(&self, point: Tuple) -> Colour {
+        let (x, y, z) = (
+            point.x * self.frequency,
+            point.y * self.frequency,
+            point.z * self.frequency,
+        );
+        // Offset for y and z, so the three aren't all the same noise
+        let jitter = vector(
+            self.noise.noise(x, y, z),
+            self.noise.noise(x + 31.7, y + 47.3, z + 13.1),
+            self.noise.noise(x - 19.9, y + 5.3, z - 71.5),
+        );
+        self.pattern
+            .pattern_at_object(point + jitter * self.amplitude)
+    }
+
+    /// Jittering doesn't change how much of each colour there is
+    fn average(&self) -> Colour {
+        self.pattern.average()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        math::{matrix::Matrix, tuple::point},
+        pattern::{stripe::StripePattern, Pattern, PatternBase},
+    };
+
+    use super::PerturbedPattern;
+
+    fn stripes() -> StripePattern {
+        StripePattern::new(Colour::WHITE, Colour::BLACK)
+    }
+
+    fn points() -> impl Iterator<Item = crate::math::tuple::Tuple> {
+        (0..200).map(|i| {
+            let i = i as f64;
+            point(i * 0.13 - 10.0, i * 0.07, i * 0.29 - 20.0)
+        })
+    }
+
+    #[test]
+    fn zero_amplitude_changes_nothing() {
+        let mut inner = stripes();
+        inner.set_transform(Matrix::scaling(0.3, 1.0, 1.0));
+        let p = PerturbedPattern::new(inner.clone(), 0.0, 2.0, 9);
+
+        for at in points() {
+            assert_eq!(p.pattern_at(at), inner.pattern_at_object(at), "{at:?}");
+        }
+    }
+
+    #[test]
+    fn jitters() {
+        let p = PerturbedPattern::new(stripes(), 0.5, 1.5, 9);
+        assert!(points().any(|at| p.pattern_at(at) != stripes().pattern_at(at)));
+    }
+
+    #[test]
+    fn repeatable() {
+        let a = PerturbedPattern::new(stripes(), 0.5, 1.5, 9);
+        let b = PerturbedPattern::new(stripes(), 0.5, 1.5, 9);
+        let c = PerturbedPattern::new(stripes(), 0.5, 1.5, 10);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(points().all(|at| a.pattern_at(at) == b.pattern_at(at)));
+    }
+
+    #[test]
+    fn own_transform() {
+        let mut p = PerturbedPattern::new(stripes(), 0.0, 1.0, 1);
+        p.set_transform(Matrix::translationi(1, 0, 0));
+        assert_eq!(p.pattern_at_object(point(0.5, 0.0, 0.0)), Colour::BLACK);
+    }
+}
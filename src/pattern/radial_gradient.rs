@@ -0,0 +1,89 @@
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+use crate::{
+    colour::Colour,
+    math::{matrix::Matrix, tuple::Tuple},
+};
+
+use super::{pattern_base, Pattern};
+
+/// Fades from `a` to `b` going out from the y axis, starting over at `a` every unit. Rings, but
+/// smooth rather than banded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadialGradientPattern {
+    pub a: Colour,
+    pub b: Colour,
+    pub transform: Matrix,
+}
+
+impl RadialGradientPattern {
+    pub fn new(a: Colour, b: Colour) -> Self {
+        Self {
+            a,
+            b,
+            transform: Matrix::default(),
+        }
+    }
+}
+
+pattern_base!(RadialGradientPattern);
+
+impl Pattern for RadialGradientPattern {
+    fn pattern_at(&self, point: Tuple) -> Colour {
+        let distance = (point.x * point.x + point.z * point.z).sqrt();
+        let fraction = distance - distance.floor();
+        self.a.lerp(self.b, fraction)
+    }
+
+    fn average(&self) -> Colour {
+        (self.a + self.b) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        math::{
+            matrix::Matrix,
+            tuple::{point, pointi},
+        },
+        pattern::{Pattern, PatternBase},
+    };
+
+    use super::RadialGradientPattern;
+
+    fn gradient() -> RadialGradientPattern {
+        RadialGradientPattern::new(Colour::WHITE, Colour::BLACK)
+    }
+
+    #[test]
+    fn by_radius() {
+        let p = gradient();
+
+        assert_eq!(p.pattern_at(pointi(0, 0, 0)), Colour::WHITE);
+        assert_eq!(p.pattern_at(point(0.5, 0.0, 0.0)), Colour::WHITE * 0.5);
+        assert_eq!(p.pattern_at(point(0.0, 0.0, 0.25)), Colour::WHITE * 0.75);
+        // and starting over at 1
+        assert_eq!(p.pattern_at(pointi(1, 0, 0)), Colour::WHITE);
+        assert_eq!(p.pattern_at(point(0.6, 0.0, 0.8)), Colour::WHITE);
+    }
+
+    #[test]
+    fn constant_in_y() {
+        let p = gradient();
+
+        for y in [-3.0, 0.0, 2.5] {
+            assert_eq!(p.pattern_at(point(0.5, y, 0.0)), Colour::WHITE * 0.5);
+        }
+    }
+
+    #[test]
+    fn respects_transform() {
+        let mut p = gradient();
+        p.set_transform(Matrix::scalingi(2, 2, 2));
+
+        assert_eq!(p.pattern_at_object(pointi(1, 0, 0)), Colour::WHITE * 0.5);
+        assert_eq!(p.pattern_at_object(pointi(2, 0, 0)), Colour::WHITE);
+    }
+}
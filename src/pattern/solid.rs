@@ -0,0 +1,91 @@
+use core::any::Any;
+
+use crate::{
+    colour::Colour,
+    math::{
+        matrix::{Matrix, IDENTITY_4X4},
+        tuple::Tuple,
+    },
+    prelude::*,
+};
+
+use super::{Pattern, PatternBase};
+
+/// One colour everywhere, what a Material's pattern is unless it's given another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolidPattern(pub Colour);
+
+static IDENTITY: Matrix = IDENTITY_4X4;
+
+/// Not pattern_base!, as a solid colour looks the same however it's moved there's no transform
+/// worth keeping. It's always the identity and setting it does nothing.
+impl PatternBase for SolidPattern {
+    fn transform(&self) -> &Matrix {
+        &IDENTITY
+    }
+
+    fn set_transform(&mut self, _transform: Matrix) {}
+
+    fn box_clone(&self) -> Box<dyn Pattern> {
+        Box::new(*self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn pattern_eq(&self, other: &dyn Pattern) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<SolidPattern>()
+            .is_some_and(|o| o == self)
+    }
+}
+
+impl Pattern for SolidPattern {
+    fn pattern_at(&self, _point: Tuple) -> Colour {
+        self.0
+    }
+
+    fn average(&self) -> Colour {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        math::{matrix::Matrix, tuple::point},
+        pattern::{stripe::StripePattern, Pattern, PatternBase},
+    };
+
+    use super::SolidPattern;
+
+    #[test]
+    fn everywhere() {
+        let p = SolidPattern(Colour::RED);
+        for at in [point(0.0, 0.0, 0.0), point(1.5, -3.0, 7.2)] {
+            assert_eq!(p.pattern_at(at), Colour::RED);
+            assert_eq!(p.pattern_at_object(at), Colour::RED);
+        }
+    }
+
+    #[test]
+    fn transform_is_ignored() {
+        let mut p = SolidPattern(Colour::RED);
+        p.set_transform(Matrix::scalingi(0, 0, 0));
+        assert!(p.transform().is_identity());
+    }
+
+    #[test]
+    fn equality() {
+        let red: Box<dyn Pattern> = Box::new(SolidPattern(Colour::RED));
+        let also_red: Box<dyn Pattern> = Box::new(SolidPattern(Colour::RED));
+        let blue: Box<dyn Pattern> = Box::new(SolidPattern(Colour::BLUE));
+        let stripes: Box<dyn Pattern> = Box::new(StripePattern::new(Colour::RED, Colour::RED));
+        assert!(*red == *also_red);
+        assert!(*red != *blue);
+        assert!(*red != *stripes);
+    }
+}
@@ -0,0 +1,174 @@
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+use crate::{
+    colour::Colour,
+    math::{matrix::Matrix, tuple::Tuple},
+};
+
+use super::{pattern_base, Pattern};
+
+/// Alternates between `a` and `b` every unit along x.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StripePattern {
+    pub a: Colour,
+    pub b: Colour,
+    pub transform: Matrix,
+}
+
+impl StripePattern {
+    pub fn new(a: Colour, b: Colour) -> Self {
+        Self {
+            a,
+            b,
+            transform: Matrix::default(),
+        }
+    }
+}
+
+pattern_base!(StripePattern);
+
+impl Pattern for StripePattern {
+    fn pattern_at(&self, point: Tuple) -> Colour {
+        if point.x.floor().rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+
+    fn average(&self) -> Colour {
+        (self.a + self.b) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        math::tuple::{point, pointi},
+        pattern::Pattern,
+    };
+
+    use super::StripePattern;
+
+    fn stripes() -> StripePattern {
+        StripePattern::new(Colour::WHITE, Colour::BLACK)
+    }
+
+    #[test]
+    fn construct() {
+        let p = stripes();
+
+        assert_eq!(p.a, Colour::WHITE);
+        assert_eq!(p.b, Colour::BLACK);
+    }
+
+    #[test]
+    fn constant_in_y() {
+        let p = stripes();
+
+        assert_eq!(p.pattern_at(pointi(0, 0, 0)), Colour::WHITE);
+        assert_eq!(p.pattern_at(pointi(0, 1, 0)), Colour::WHITE);
+        assert_eq!(p.pattern_at(pointi(0, 2, 0)), Colour::WHITE);
+    }
+
+    #[test]
+    fn constant_in_z() {
+        let p = stripes();
+
+        assert_eq!(p.pattern_at(pointi(0, 0, 0)), Colour::WHITE);
+        assert_eq!(p.pattern_at(pointi(0, 0, 1)), Colour::WHITE);
+        assert_eq!(p.pattern_at(pointi(0, 0, 2)), Colour::WHITE);
+    }
+
+    #[test]
+    fn alternates_in_x() {
+        let p = stripes();
+
+        assert_eq!(p.pattern_at(pointi(0, 0, 0)), Colour::WHITE);
+        assert_eq!(p.pattern_at(point(0.9, 0.0, 0.0)), Colour::WHITE);
+        assert_eq!(p.pattern_at(pointi(1, 0, 0)), Colour::BLACK);
+        assert_eq!(p.pattern_at(point(-0.1, 0.0, 0.0)), Colour::BLACK);
+        assert_eq!(p.pattern_at(pointi(-1, 0, 0)), Colour::BLACK);
+        assert_eq!(p.pattern_at(point(-1.1, 0.0, 0.0)), Colour::WHITE);
+    }
+
+    mod transforms {
+        use crate::{
+            materials::Material,
+            math::matrix::Matrix,
+            pattern::PatternBase,
+            shape::{sphere::Sphere, ShapeBase},
+        };
+
+        use super::*;
+
+        #[test]
+        fn identity_by_default() {
+            assert!(stripes().transform().is_identity());
+
+            let mut p = stripes();
+            p.set_transform(Matrix::translationi(1, 2, 3));
+            assert_eq!(p.transform(), &Matrix::translationi(1, 2, 3));
+        }
+
+        #[test]
+        fn object_transform() {
+            let mut object = Sphere::default();
+            object.set_transform(Matrix::scalingi(2, 2, 2));
+
+            let c = stripes().pattern_at_shape(&object, point(1.5, 0.0, 0.0));
+            assert_eq!(c, Colour::WHITE);
+        }
+
+        #[test]
+        fn pattern_transform() {
+            let object = Sphere::default();
+            let mut p = stripes();
+            p.set_transform(Matrix::scalingi(2, 2, 2));
+
+            assert_eq!(
+                p.pattern_at_shape(&object, point(1.5, 0.0, 0.0)),
+                Colour::WHITE
+            );
+        }
+
+        #[test]
+        fn both_transforms() {
+            let mut object = Sphere::default();
+            object.set_transform(Matrix::scalingi(2, 2, 2));
+            let mut p = stripes();
+            p.set_transform(Matrix::translation(0.5, 0.0, 0.0));
+
+            assert_eq!(
+                p.pattern_at_shape(&object, point(2.5, 0.0, 0.0)),
+                Colour::WHITE
+            );
+            // Without the pattern's translation that's in a black stripe
+            assert_eq!(
+                stripes().pattern_at_shape(&object, point(2.5, 0.0, 0.0)),
+                Colour::BLACK
+            );
+        }
+
+        #[test]
+        fn material_uses_pattern_transform() {
+            let mut p = stripes();
+            p.set_transform(Matrix::translationi(1, 0, 0));
+            let m = Material {
+                pattern: Box::new(p),
+                ..Default::default()
+            };
+
+            assert_eq!(m.colour_at(point(0.5, 0.0, 0.0)), Colour::BLACK);
+            assert_eq!(m.colour_at(point(1.5, 0.0, 0.0)), Colour::WHITE);
+        }
+
+        #[test]
+        fn non_invertible_is_ignored() {
+            let mut p = stripes();
+            p.set_transform(Matrix::scalingi(0, 1, 1));
+            assert_eq!(p.pattern_at_object(point(1.5, 0.0, 0.0)), Colour::BLACK);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{
+    colour::Colour,
+    math::{matrix::Matrix, tuple::Tuple},
+};
+
+use super::{pattern_base, Pattern};
+
+/// Colours each point with its own coordinates, so tests can see exactly which point a pattern
+/// was asked about
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TestPattern {
+    pub transform: Matrix,
+}
+
+pattern_base!(TestPattern);
+
+impl Pattern for TestPattern {
+    fn pattern_at(&self, point: Tuple) -> Colour {
+        Colour::new(point.x, point.y, point.z)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        math::{matrix::Matrix, tuple::point},
+        pattern::{Pattern, PatternBase},
+        shape::{sphere::Sphere, ShapeBase},
+    };
+
+    use super::TestPattern;
+
+    #[test]
+    fn default_transform() {
+        assert!(TestPattern::default().transform().is_identity());
+    }
+
+    #[test]
+    fn assign_transform() {
+        let mut p = TestPattern::default();
+        p.set_transform(Matrix::translationi(1, 2, 3));
+        assert_eq!(p.transform(), &Matrix::translationi(1, 2, 3));
+    }
+
+    #[test]
+    fn object_transform() {
+        let mut object = Sphere::default();
+        object.set_transform(Matrix::scalingi(2, 2, 2));
+
+        let c = TestPattern::default().pattern_at_shape(&object, point(2.0, 3.0, 4.0));
+        assert_eq!(c, Colour::new(1.0, 1.5, 2.0));
+    }
+
+    #[test]
+    fn pattern_transform() {
+        let object = Sphere::default();
+        let mut p = TestPattern::default();
+        p.set_transform(Matrix::scalingi(2, 2, 2));
+
+        let c = p.pattern_at_shape(&object, point(2.0, 3.0, 4.0));
+        assert_eq!(c, Colour::new(1.0, 1.5, 2.0));
+    }
+
+    #[test]
+    fn both_transforms() {
+        let mut object = Sphere::default();
+        object.set_transform(Matrix::scalingi(2, 2, 2));
+        let mut p = TestPattern::default();
+        p.set_transform(Matrix::translation(0.5, 1.0, 1.5));
+
+        let c = p.pattern_at_shape(&object, point(2.5, 3.0, 3.5));
+        assert_eq!(c, Colour::new(0.75, 0.5, 0.25));
+    }
+
+    #[test]
+    fn singular_transform() {
+        // Like shapes, a pattern transform that won't invert counts as no transform
+        let mut p = TestPattern::default();
+        p.set_transform(Matrix::scaling(1.0, 0.0, 1.0));
+
+        let c = p.pattern_at_shape(&Sphere::default(), point(1.0, 2.0, 3.0));
+        assert_eq!(c, Colour::new(1.0, 2.0, 3.0));
+    }
+}
@@ -0,0 +1,156 @@
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+use crate::{
+    colour::Colour,
+    math::{matrix::Matrix, tuple::Tuple},
+};
+
+use super::{image::UvMapping, pattern_base, Pattern};
+
+/// A `width` by `height` checkerboard in (u, v), wrapped onto the shape by `mapping`. `a` is in
+/// the corner at (0, 0). On a sphere, twice as many across as up makes the squares square at
+/// the equator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UvCheckers {
+    pub width: usize,
+    pub height: usize,
+    pub a: Colour,
+    pub b: Colour,
+    pub mapping: UvMapping,
+    pub transform: Matrix,
+}
+
+impl UvCheckers {
+    /// Mapped spherically, see with_mapping for others
+    pub fn new(width: usize, height: usize, a: Colour, b: Colour) -> Self {
+        Self {
+            width,
+            height,
+            a,
+            b,
+            mapping: UvMapping::Spherical,
+            transform: Matrix::default(),
+        }
+    }
+
+    pub fn with_mapping(mut self, mapping: UvMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    /// The colour at (u, v)
+    pub fn uv_at(&self, u: f64, v: f64) -> Colour {
+        let u = (u * self.width as f64).floor();
+        let v = (v * self.height as f64).floor();
+        if (u + v).rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+pattern_base!(UvCheckers);
+
+impl Pattern for UvCheckers {
+    fn pattern_at(&self, point: Tuple) -> Colour {
+        let (u, v) = self.mapping.uv(point);
+        self.uv_at(u, v)
+    }
+
+    fn average(&self) -> Colour {
+        (self.a + self.b) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_8;
+
+    use crate::{
+        colour::Colour,
+        math::{
+            matrix::Matrix,
+            tuple::{point, pointi, Tuple},
+        },
+        pattern::{image::UvMapping, Pattern},
+        shape::{sphere::Sphere, ShapeBase},
+    };
+
+    use super::UvCheckers;
+
+    fn globe() -> UvCheckers {
+        UvCheckers::new(16, 8, Colour::BLACK, Colour::WHITE)
+    }
+
+    #[test]
+    fn uv_at() {
+        let checkers = UvCheckers::new(2, 2, Colour::BLACK, Colour::WHITE);
+        assert_eq!(checkers.uv_at(0.0, 0.0), Colour::BLACK);
+        assert_eq!(checkers.uv_at(0.5, 0.0), Colour::WHITE);
+        assert_eq!(checkers.uv_at(0.0, 0.5), Colour::WHITE);
+        assert_eq!(checkers.uv_at(0.5, 0.5), Colour::BLACK);
+        assert_eq!(checkers.uv_at(1.0, 1.0), Colour::BLACK);
+        // Past 0..1 it carries on alternating
+        assert_eq!(checkers.uv_at(-0.25, 0.0), Colour::WHITE);
+    }
+
+    #[test]
+    fn spherical() {
+        for (p, expected) in [
+            (point(0.4315, 0.4670, 0.7719), Colour::WHITE),
+            (point(-0.9654, 0.2552, -0.0534), Colour::BLACK),
+            (point(0.1039, 0.7090, 0.6975), Colour::WHITE),
+            (point(-0.4986, -0.7856, -0.3663), Colour::BLACK),
+            (point(-0.0317, -0.9395, 0.3411), Colour::BLACK),
+            (point(0.4809, -0.7721, 0.4154), Colour::BLACK),
+            (point(0.0285, -0.9612, -0.2745), Colour::BLACK),
+            (point(-0.5734, -0.2162, -0.7903), Colour::WHITE),
+            (point(0.7688, -0.1470, 0.6223), Colour::BLACK),
+            (point(-0.7652, 0.2175, 0.6060), Colour::BLACK),
+        ] {
+            assert_eq!(globe().pattern_at(p), expected, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn square_at_the_equator() {
+        // Walking east or north from (0, 0, -1), the first change of colour is the same angle
+        // away
+        let first_change = |step: &dyn Fn(f64) -> Tuple| {
+            let start = globe().pattern_at(step(0.001));
+            (1..1000)
+                .map(|i| i as f64 * 0.001)
+                .find(|&angle| globe().pattern_at(step(angle)) != start)
+                .unwrap()
+        };
+        let east = first_change(&|angle| point(-angle.sin(), 0.0, -angle.cos()));
+        let north = first_change(&|angle| point(0.0, angle.sin(), -angle.cos()));
+        assert!((east - FRAC_PI_8).abs() < 0.002, "{east}");
+        assert!((north - FRAC_PI_8).abs() < 0.002, "{north}");
+    }
+
+    #[test]
+    fn on_a_shape() {
+        // The sphere's transform is undone before mapping, so the same checker comes up at the
+        // same place on a bigger, moved globe
+        let mut object = Sphere::default();
+        object.set_transform(Matrix::translationi(0, 5, 0).scale(3.0, 3.0, 3.0));
+        let p = point(-0.5734, -0.2162, -0.7903);
+        let world_point = object.transform() * p;
+        assert_eq!(
+            globe().pattern_at_shape(&object, world_point),
+            globe().pattern_at(p)
+        );
+    }
+
+    #[test]
+    fn planar() {
+        let checkers =
+            UvCheckers::new(2, 2, Colour::BLACK, Colour::WHITE).with_mapping(UvMapping::Planar);
+        assert_eq!(checkers.pattern_at(point(0.25, 0.0, 0.25)), Colour::BLACK);
+        assert_eq!(checkers.pattern_at(point(0.75, 0.0, 0.25)), Colour::WHITE);
+        assert_eq!(checkers.pattern_at(point(1.25, 7.0, 0.75)), Colour::WHITE);
+        assert_eq!(checkers.pattern_at(pointi(0, 0, 0)), Colour::BLACK);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    colour::Colour,
+    math::{matrix::Matrix, tuple::Tuple},
+};
+
+use super::{pattern_base, Pattern};
+
+/// Blends the colours at a triangle's corners across it, for meshes with per vertex colours.
+/// Points off the triangle's plane are projected onto it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexColourPattern {
+    pub corners: [Tuple; 3],
+    pub colours: [Colour; 3],
+    pub transform: Matrix,
+}
+
+impl VertexColourPattern {
+    pub fn new(corners: [Tuple; 3], colours: [Colour; 3]) -> Self {
+        Self {
+            corners,
+            colours,
+            transform: Matrix::default(),
+        }
+    }
+}
+
+pattern_base!(VertexColourPattern);
+
+impl Pattern for VertexColourPattern {
+    fn pattern_at(&self, point: Tuple) -> Colour {
+        let [p1, p2, p3] = self.corners;
+        let (e1, e2, to_point) = (p2 - p1, p3 - p1, point - p1);
+
+        let (d11, d12, d22) = (e1.dot(&e1), e1.dot(&e2), e2.dot(&e2));
+        let (d1p, d2p) = (e1.dot(&to_point), e2.dot(&to_point));
+        let denominator = d11 * d22 - d12 * d12;
+        if denominator == 0.0 {
+            return self.colours[0];
+        }
+
+        let u = (d22 * d1p - d12 * d2p) / denominator;
+        let v = (d11 * d2p - d12 * d1p) / denominator;
+        Colour::mix(&self.colours, &[1.0 - u - v, u, v]).expect("one weight per corner")
+    }
+
+    fn average(&self) -> Colour {
+        self.colours.iter().sum::<Colour>() / 3.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        math::tuple::{point, pointi},
+        pattern::Pattern,
+    };
+
+    use super::VertexColourPattern;
+
+    fn rgb() -> VertexColourPattern {
+        VertexColourPattern::new(
+            [pointi(0, 0, 0), pointi(1, 0, 0), pointi(0, 1, 0)],
+            [Colour::RED, Colour::GREEN, Colour::BLUE],
+        )
+    }
+
+    #[test]
+    fn corners() {
+        let p = rgb();
+
+        assert_eq!(p.pattern_at(pointi(0, 0, 0)), Colour::RED);
+        assert_eq!(p.pattern_at(pointi(1, 0, 0)), Colour::GREEN);
+        assert_eq!(p.pattern_at(pointi(0, 1, 0)), Colour::BLUE);
+    }
+
+    #[test]
+    fn blends() {
+        let p = rgb();
+
+        assert_eq!(
+            p.pattern_at(point(0.5, 0.5, 0.0)),
+            Colour::new(0.0, 0.5, 0.5)
+        );
+        assert_eq!(
+            p.pattern_at(point(0.25, 0.25, 3.0)),
+            Colour::new(0.5, 0.25, 0.25)
+        );
+    }
+}
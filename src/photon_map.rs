@@ -0,0 +1,317 @@
+//! Where photons fired from the lights landed, for finding the ones near a point quickly. See
+//! World::caustic_photons, which fills one in.
+
+use alloc::collections::BinaryHeap;
+use core::{cmp::Ordering, f64::consts::PI};
+
+use crate::{colour::Colour, math::tuple::Tuple, prelude::*};
+
+/// A bit of light that's landed on a surface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Photon {
+    pub position: Tuple,
+    /// Which way it was going when it landed, normalized
+    pub direction: Tuple,
+    pub power: Colour,
+    /// How far it's come from its light
+    pub travelled: f64,
+}
+
+/// The photons in a KD-tree: each range of them is split in two around the middle one, on
+/// whichever axis they're most spread out along.
+#[derive(Debug, Clone, Default)]
+pub struct PhotonMap {
+    /// In tree order, the root's the middle one
+    photons: Vec<Photon>,
+    /// The axis each photon splits its range on
+    axes: Vec<u8>,
+    emitted: Colour,
+}
+
+impl PhotonMap {
+    /// `emitted` is the power of everything the lights gave off, landed or not
+    pub fn new(mut photons: Vec<Photon>, emitted: Colour) -> Self {
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+        Self {
+            photons,
+            axes,
+            emitted,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Every photon, in no particular order
+    pub fn photons(&self) -> &[Photon] {
+        &self.photons
+    }
+
+    pub fn emitted(&self) -> Colour {
+        self.emitted
+    }
+
+    /// Up to `k` photons no further than `radius` from `point`, nearest first
+    pub fn nearest(&self, point: Tuple, k: usize, radius: f64) -> Vec<&Photon> {
+        if k == 0 {
+            return vec![];
+        }
+        let mut found = BinaryHeap::with_capacity(k + 1);
+        self.search(0, self.photons.len(), point, k, radius * radius, &mut found);
+        found
+            .into_sorted_vec()
+            .into_iter()
+            .map(|c| &self.photons[c.index])
+            .collect()
+    }
+
+    fn search(
+        &self,
+        start: usize,
+        end: usize,
+        point: Tuple,
+        k: usize,
+        max_distance2: f64,
+        found: &mut BinaryHeap<Candidate>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let middle = (start + end) / 2;
+        let photon = &self.photons[middle];
+        let axis = self.axes[middle];
+        let offset = along(point, axis) - along(photon.position, axis);
+        let (near, far) = if offset < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+
+        self.search(near.0, near.1, point, k, max_distance2, found);
+
+        let distance2 = distance2(point, photon.position);
+        if distance2 <= max_distance2 {
+            found.push(Candidate {
+                distance2,
+                index: middle,
+            });
+            if found.len() > k {
+                found.pop();
+            }
+        }
+
+        // The far side's only worth a look if the splitting plane's closer than the furthest
+        // photon kept so far
+        let furthest = match found.peek() {
+            Some(c) if found.len() == k => c.distance2,
+            _ => max_distance2,
+        };
+        if offset * offset <= furthest {
+            self.search(far.0, far.1, point, k, max_distance2, found);
+        }
+    }
+
+    /// How much light's landed per unit area around `point`, from the `k` photons nearest it
+    /// (within `radius`) that arrived on the side `normal` faces. Lights here don't get dimmer
+    /// with distance, so each photon's power is scaled back up by how far it's travelled to
+    /// match: unfocused, that comes out the same as the light shining straight on.
+    pub fn irradiance(&self, point: Tuple, normal: Tuple, k: usize, radius: f64) -> Colour {
+        let nearest = self.nearest(point, k, radius);
+        let area_radius2 = match nearest.last() {
+            Some(furthest) if nearest.len() == k => distance2(point, furthest.position),
+            _ => radius * radius,
+        };
+        if area_radius2 <= 0.0 {
+            return Colour::BLACK;
+        }
+        let total = nearest
+            .iter()
+            .filter(|p| p.direction.dot(&normal) < 0.0)
+            .map(|p| p.power * (p.travelled * p.travelled))
+            .sum::<Colour>();
+        total / (PI * area_radius2)
+    }
+}
+
+fn build(photons: &mut [Photon], axes: &mut [u8]) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let axis = widest_axis(photons);
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| {
+        along(a.position, axis).total_cmp(&along(b.position, axis))
+    });
+    axes[middle] = axis;
+
+    let (below, above) = photons.split_at_mut(middle);
+    let (below_axes, above_axes) = axes.split_at_mut(middle);
+    build(below, below_axes);
+    build(&mut above[1..], &mut above_axes[1..]);
+}
+
+fn widest_axis(photons: &[Photon]) -> u8 {
+    let mut low = [f64::INFINITY; 3];
+    let mut high = [f64::NEG_INFINITY; 3];
+    for photon in photons {
+        for axis in 0..3 {
+            let value = along(photon.position, axis as u8);
+            low[axis] = low[axis].min(value);
+            high[axis] = high[axis].max(value);
+        }
+    }
+    (0..3u8)
+        .max_by(|a, b| {
+            let extent = |axis: u8| high[axis as usize] - low[axis as usize];
+            extent(*a).total_cmp(&extent(*b))
+        })
+        .unwrap()
+}
+
+fn along(point: Tuple, axis: u8) -> f64 {
+    match axis {
+        0 => point.x,
+        1 => point.y,
+        _ => point.z,
+    }
+}
+
+fn distance2(a: Tuple, b: Tuple) -> f64 {
+    let d = a - b;
+    d.dot(&d)
+}
+
+/// A photon that might be one of the nearest, the heap keeps the furthest on top
+struct Candidate {
+    distance2: f64,
+    index: usize,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance2.total_cmp(&other.distance2)
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        math::{
+            float,
+            rng::Rng,
+            tuple::{point, pointi, vectori, Tuple},
+        },
+    };
+
+    use super::{Photon, PhotonMap};
+
+    fn photon(position: Tuple) -> Photon {
+        Photon {
+            position,
+            direction: vectori(0, -1, 0),
+            power: Colour::new(1.0, 0.5, 0.25),
+            travelled: 1.0,
+        }
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let mut rng = Rng::new(11);
+        let mut random_point = || {
+            point(
+                rng.between(-5.0, 5.0),
+                rng.between(-1.0, 1.0),
+                rng.between(-5.0, 5.0),
+            )
+        };
+        let photons: Vec<_> = (0..500).map(|_| photon(random_point())).collect();
+        let map = PhotonMap::new(photons.clone(), Colour::WHITE);
+        assert_eq!(map.len(), 500);
+
+        for _ in 0..50 {
+            let query = random_point();
+            for (k, radius) in [(1, 10.0), (8, 1.0), (40, 2.0), (600, 1.5)] {
+                let mut expected: Vec<_> = photons
+                    .iter()
+                    .map(|p| (p.position - query).magnitude())
+                    .filter(|d| *d <= radius)
+                    .collect();
+                expected.sort_by(f64::total_cmp);
+                expected.truncate(k);
+
+                let found: Vec<_> = map
+                    .nearest(query, k, radius)
+                    .iter()
+                    .map(|p| (p.position - query).magnitude())
+                    .collect();
+                assert_eq!(found, expected, "k {k}, radius {radius}");
+            }
+        }
+    }
+
+    #[test]
+    fn small_maps() {
+        let empty = PhotonMap::new(vec![], Colour::BLACK);
+        assert!(empty.is_empty());
+        assert!(empty.nearest(pointi(0, 0, 0), 5, 1.0).is_empty());
+        assert_eq!(
+            empty.irradiance(pointi(0, 0, 0), vectori(0, 1, 0), 5, 1.0),
+            Colour::BLACK
+        );
+
+        let one = PhotonMap::new(vec![photon(pointi(1, 0, 0))], Colour::WHITE);
+        assert_eq!(one.nearest(pointi(0, 0, 0), 5, 1.0).len(), 1);
+        assert!(one.nearest(pointi(0, 0, 0), 5, 0.5).is_empty());
+        assert!(one.nearest(pointi(1, 0, 0), 0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn irradiance() {
+        // A grid of photons 0.1 apart, so 100 per unit area
+        let photons: Vec<_> = (-20..20)
+            .flat_map(|x| (-20..20).map(move |z| point(x as f64 * 0.1, 0.0, z as f64 * 0.1)))
+            .map(|position| Photon {
+                power: Colour::new(0.01, 0.02, 0.04),
+                travelled: 0.5,
+                ..photon(position)
+            })
+            .collect();
+        let map = PhotonMap::new(photons, Colour::WHITE);
+
+        // 100 per unit area, each 0.01 * 0.5^2 once travelled's taken into account
+        let up = vectori(0, 1, 0);
+        let got = map.irradiance(pointi(0, 0, 0), up, 1000, 0.5);
+        assert!((got.red - 0.25).abs() < 0.025, "{got:?}");
+        // Same again with just the nearest 20, within the radius they cover
+        let nearest = map.irradiance(pointi(0, 0, 0), up, 20, 0.5);
+        assert!((nearest.red - 0.25).abs() < 0.1, "{nearest:?}");
+        assert!(float::equal(got.green, got.red * 2.0));
+
+        // They all came down from above, so there's nothing underneath
+        assert_eq!(
+            map.irradiance(pointi(0, 0, 0), vectori(0, -1, 0), 1000, 0.5),
+            Colour::BLACK
+        );
+    }
+}
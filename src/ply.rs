@@ -0,0 +1,570 @@
+//! PLY loading, ASCII and binary little endian.
+//!
+//! Vertices can have x/y/z, nx/ny/nz and red/green/blue, faces a `vertex_indices` list. Any other
+//! properties (or elements) are read past and dropped.
+
+use std::{fmt, io::BufRead};
+
+use crate::{
+    colour::Colour,
+    math::tuple::Tuple,
+    mesh::{face_normal, Mesh},
+    trace,
+};
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(std::io::Error),
+    /// Something on `line` (1-based) of the header made no sense
+    Header {
+        line: usize,
+        message: String,
+    },
+    /// The data after the header didn't match it
+    Body(String),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(e) => write!(f, "reading ply: {e}"),
+            PlyError::Header { line, message } => write!(f, "ply header line {line}: {message}"),
+            PlyError::Body(message) => write!(f, "ply data: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlyError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PlyError {
+    fn from(value: std::io::Error) -> Self {
+        PlyError::Io(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Scalar::F32 | Scalar::F64)
+    }
+
+    fn read_le(self, bytes: &[u8]) -> f64 {
+        match self {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::U16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PropertyKind {
+    Scalar(Scalar),
+    List { count: Scalar, item: Scalar },
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn find(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|p| p.name == name)
+    }
+}
+
+/// One property of one row
+enum Value {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+pub fn parse(mut reader: impl BufRead) -> Result<Mesh, PlyError> {
+    trace::span!(DEBUG, "load_ply");
+    let (format, elements) = parse_header(&mut reader)?;
+    let mut body = match format {
+        Format::Ascii => {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            Body::Ascii(
+                text.split_whitespace()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )
+        }
+        Format::BinaryLittleEndian => {
+            let mut data = vec![];
+            reader.read_to_end(&mut data)?;
+            Body::Binary(data, 0)
+        }
+    };
+
+    let mut mesh = Mesh::default();
+    let mut polygons = vec![];
+    for element in &elements {
+        let rows = (0..element.count)
+            .map(|_| {
+                element
+                    .properties
+                    .iter()
+                    .map(|p| body.value(p.kind))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut mesh, element, &rows)?,
+            "face" => {
+                let list = element
+                    .find("vertex_indices")
+                    .or_else(|| element.find("vertex_index"))
+                    .ok_or_else(|| PlyError::Body("faces without vertex_indices".to_string()))?;
+                for row in rows {
+                    match &row[list] {
+                        Value::List(indices) => polygons.push(indices.clone()),
+                        Value::Scalar(_) => {
+                            return Err(PlyError::Body("vertex_indices isn't a list".to_string()))
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Faces can come before vertices, so these wait until everything's read
+    for polygon in polygons {
+        add_polygon(&mut mesh, &polygon)?;
+    }
+
+    Ok(mesh)
+}
+
+fn parse_header(reader: &mut impl BufRead) -> Result<(Format, Vec<Element>), PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+
+    let mut number = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        number += 1;
+        let error = |message: String| PlyError::Header {
+            line: number,
+            message,
+        };
+
+        if reader.read_line(&mut line)? == 0 {
+            return Err(error("file ended before end_header".to_string()));
+        }
+        let mut parts = line.split_whitespace();
+        let keyword = parts.next();
+        if number == 1 {
+            if keyword != Some("ply") {
+                return Err(error("not a ply file".to_string()));
+            }
+            continue;
+        }
+
+        match keyword {
+            Some("format") => {
+                format = Some(match parts.next() {
+                    Some("ascii") => Format::Ascii,
+                    Some("binary_little_endian") => Format::BinaryLittleEndian,
+                    other => return Err(error(format!("unsupported format {other:?}"))),
+                })
+            }
+            Some("element") => {
+                let (Some(name), Some(count)) = (parts.next(), parts.next()) else {
+                    return Err(error("element needs a name and count".to_string()));
+                };
+                elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|e| error(format!("bad element count {count}: {e}")))?,
+                    properties: vec![],
+                });
+            }
+            Some("property") => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| error("property before any element".to_string()))?;
+                let words: Vec<_> = parts.collect();
+                let scalar = |name: &str| {
+                    Scalar::parse(name).ok_or_else(|| error(format!("unknown type {name}")))
+                };
+                let (kind, name) = match words[..] {
+                    ["list", count, item, name] => (
+                        PropertyKind::List {
+                            count: scalar(count)?,
+                            item: scalar(item)?,
+                        },
+                        name,
+                    ),
+                    [ty, name] => (PropertyKind::Scalar(scalar(ty)?), name),
+                    _ => return Err(error(format!("bad property {}", line.trim()))),
+                };
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind,
+                });
+            }
+            Some("end_header") => break,
+            Some("comment" | "obj_info") | None => {}
+            Some(other) => return Err(error(format!("unknown header line {other}"))),
+        }
+    }
+
+    let format = format.ok_or_else(|| PlyError::Header {
+        line: number,
+        message: "no format line".to_string(),
+    })?;
+    Ok((format, elements))
+}
+
+enum Body {
+    Ascii(std::vec::IntoIter<String>),
+    /// The data and how far through it we are
+    Binary(Vec<u8>, usize),
+}
+
+impl Body {
+    fn scalar(&mut self, ty: Scalar) -> Result<f64, PlyError> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens
+                    .next()
+                    .ok_or_else(|| PlyError::Body("file ended early".to_string()))?;
+                token
+                    .parse()
+                    .map_err(|e| PlyError::Body(format!("bad number {token}: {e}")))
+            }
+            Body::Binary(data, position) => {
+                let bytes = data
+                    .get(*position..*position + ty.size())
+                    .ok_or_else(|| PlyError::Body("file ended early".to_string()))?;
+                *position += ty.size();
+                Ok(ty.read_le(bytes))
+            }
+        }
+    }
+
+    fn value(&mut self, kind: PropertyKind) -> Result<Value, PlyError> {
+        Ok(match kind {
+            PropertyKind::Scalar(ty) => Value::Scalar(self.scalar(ty)?),
+            PropertyKind::List { count, item } => {
+                let count = self.scalar(count)? as usize;
+                Value::List(
+                    (0..count)
+                        .map(|_| self.scalar(item))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+        })
+    }
+}
+
+fn read_vertices(mesh: &mut Mesh, element: &Element, rows: &[Vec<Value>]) -> Result<(), PlyError> {
+    let find_all = |names: [&str; 3]| -> Option<[usize; 3]> {
+        let [a, b, c] = names.map(|n| element.find(n));
+        Some([a?, b?, c?])
+    };
+    let position = find_all(["x", "y", "z"])
+        .ok_or_else(|| PlyError::Body("vertices need x, y and z".to_string()))?;
+    let normal = find_all(["nx", "ny", "nz"]);
+    let colour = find_all(["red", "green", "blue"]);
+    // Integer colours are 0-255, float ones 0-1
+    let colour_scale = match colour.map(|[r, ..]| element.properties[r].kind) {
+        Some(PropertyKind::Scalar(ty)) if !ty.is_float() => 1.0 / 255.0,
+        _ => 1.0,
+    };
+
+    for row in rows {
+        let get = |indices: [usize; 3]| -> Result<[f64; 3], PlyError> {
+            let [a, b, c] = indices.map(|i| match row[i] {
+                Value::Scalar(v) => Ok(v),
+                Value::List(_) => Err(PlyError::Body(format!(
+                    "vertex {} is a list",
+                    element.properties[i].name
+                ))),
+            });
+            Ok([a?, b?, c?])
+        };
+
+        let [x, y, z] = get(position)?;
+        mesh.vertices.push(Tuple::point(x, y, z));
+        if let Some(normal) = normal {
+            let [x, y, z] = get(normal)?;
+            mesh.normals.push(Tuple::vector(x, y, z).normalize());
+        }
+        if let Some(colour) = colour {
+            let [r, g, b] = get(colour)?;
+            mesh.colours.push(Colour::new(r, g, b) * colour_scale);
+        }
+    }
+
+    Ok(())
+}
+
+/// Fan triangulates, dropping triangles with no area
+fn add_polygon(mesh: &mut Mesh, polygon: &[f64]) -> Result<(), PlyError> {
+    if polygon.len() < 3 {
+        return Err(PlyError::Body(format!(
+            "faces need at least 3 vertices, got {}",
+            polygon.len()
+        )));
+    }
+
+    let indices = polygon
+        .iter()
+        .map(|&i| {
+            if i < 0.0 || i as usize >= mesh.vertices.len() {
+                return Err(PlyError::Body(format!(
+                    "vertex {i} out of range, there are {}",
+                    mesh.vertices.len()
+                )));
+            }
+            Ok(i as usize)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for pair in indices[1..].windows(2) {
+        let face = [indices[0], pair[0], pair[1]];
+        if let Some(normal) = face_normal(face.map(|i| mesh.vertices[i])) {
+            mesh.faces.push(face);
+            mesh.face_normals.push(normal);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        math::tuple::{pointi, vectori},
+        shape::Shape,
+    };
+
+    use super::*;
+
+    /// A unit cube with a colour per corner, quads wound counter-clockwise from outside
+    const CUBE_VERTICES: [([i32; 3], [u8; 3]); 8] = [
+        ([0, 0, 0], [0, 0, 0]),
+        ([1, 0, 0], [255, 0, 0]),
+        ([1, 1, 0], [255, 255, 0]),
+        ([0, 1, 0], [0, 255, 0]),
+        ([0, 0, 1], [0, 0, 255]),
+        ([1, 0, 1], [255, 0, 255]),
+        ([1, 1, 1], [255, 255, 255]),
+        ([0, 1, 1], [0, 255, 255]),
+    ];
+    const CUBE_FACES: [[u32; 4]; 6] = [
+        [0, 3, 2, 1],
+        [4, 5, 6, 7],
+        [0, 1, 5, 4],
+        [3, 7, 6, 2],
+        [0, 4, 7, 3],
+        [1, 2, 6, 5],
+    ];
+
+    const HEADER: &str = "element vertex 8
+property float x
+property float y
+property float z
+property float confidence
+property uchar red
+property uchar green
+property uchar blue
+element face 6
+property list uchar int vertex_indices
+end_header
+";
+
+    fn ascii_cube() -> String {
+        let mut out = format!("ply\nformat ascii 1.0\ncomment made by hand\n{HEADER}");
+        for ([x, y, z], [r, g, b]) in CUBE_VERTICES {
+            out += &format!("{x} {y} {z} 0.5 {r} {g} {b}\n");
+        }
+        for [a, b, c, d] in CUBE_FACES {
+            out += &format!("4 {a} {b} {c} {d}\n");
+        }
+        out
+    }
+
+    fn binary_cube() -> Vec<u8> {
+        let mut out = format!("ply\nformat binary_little_endian 1.0\n{HEADER}").into_bytes();
+        for (position, colour) in CUBE_VERTICES {
+            for c in position {
+                out.extend((c as f32).to_le_bytes());
+            }
+            out.extend(0.5f32.to_le_bytes());
+            out.extend(colour);
+        }
+        for face in CUBE_FACES {
+            out.push(4);
+            for i in face {
+                out.extend((i as i32).to_le_bytes());
+            }
+        }
+        out
+    }
+
+    fn check_cube(mesh: &Mesh) {
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.vertices[6], pointi(1, 1, 1));
+        assert!(mesh.normals.is_empty());
+        assert_eq!(mesh.colours[2], Colour::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.colours[4], Colour::BLUE);
+
+        // Each quad is fanned into two triangles
+        assert_eq!(mesh.len(), 12);
+        assert_eq!(&mesh.faces[..2], &[[0, 3, 2], [0, 2, 1]]);
+        assert_eq!(mesh.face_normals[0], vectori(0, 0, -1));
+        assert_eq!(mesh.face_normals[11], vectori(1, 0, 0));
+    }
+
+    #[test]
+    fn ascii() {
+        check_cube(&parse(ascii_cube().as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn binary() {
+        let mesh = parse(&binary_cube()[..]).unwrap();
+        check_cube(&mesh);
+        assert_eq!(mesh, parse(ascii_cube().as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn polygons_and_unknown_elements() {
+        let file = "ply
+format ascii 1.0
+element vertex 5
+property double x
+property double y
+property double z
+property float nx
+property float ny
+property float nz
+element material 1
+property list uchar float weights
+property short id
+element face 1
+property list uchar uint vertex_index
+end_header
+0 0 0 0 0 2
+2 0 0 0 0 1
+3 2 0 0 0 1
+1 3 0 0 0 1
+-1 2 0 0 0 1
+3 0.1 0.2 0.3 7
+5 0 1 2 3 4
+";
+
+        let mesh = parse(file.as_bytes()).unwrap();
+
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        assert_eq!(mesh.normals[0], vectori(0, 0, 1));
+        assert!(mesh.colours.is_empty());
+        assert_eq!(mesh.into_group().len(), 3);
+    }
+
+    #[test]
+    fn colours_become_patterns() {
+        let mesh = parse(ascii_cube().as_bytes()).unwrap();
+        let pattern = mesh.colour_pattern(0).unwrap();
+        assert_eq!(
+            pattern.colours,
+            [Colour::BLACK, Colour::GREEN, Colour::new(1.0, 1.0, 0.0)]
+        );
+
+        let group = mesh.into_group();
+        let material = group.children()[0].material();
+        assert!(material.colour().is_none());
+    }
+
+    #[test]
+    fn errors() {
+        let truncated = &binary_cube()[..binary_cube().len() - 3];
+        assert!(matches!(parse(truncated), Err(PlyError::Body(_))));
+
+        let not_ply = parse(&b"solid stl\n"[..]);
+        assert!(matches!(not_ply, Err(PlyError::Header { line: 1, .. })));
+
+        let big_endian = "ply\nformat binary_big_endian 1.0\nend_header\n";
+        assert!(matches!(
+            parse(big_endian.as_bytes()),
+            Err(PlyError::Header { line: 2, .. })
+        ));
+
+        let out_of_range = ascii_cube().replace("4 1 2 6 5", "4 1 2 6 8");
+        match parse(out_of_range.as_bytes()) {
+            Err(PlyError::Body(message)) => assert!(message.contains("out of range")),
+            other => panic!("{other:?}"),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! The bits of std's prelude the core needs, so it also builds without std. Modules that use
+//! Vec, Box, String etc. pull these in with `use crate::prelude::*;`
+
+pub(crate) use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use crate::math::float::FloatExt;
@@ -0,0 +1,337 @@
+//! A window that shows a render while it happens. The picture comes in blocky first and sharpens
+//! up: the first pass traces one ray per 8x8 block, then 4x4, 2x2, and finally every pixel, each
+//! pass only tracing the pixels earlier ones didn't.
+//!
+//! Esc closes the window (stopping the render), S saves what's there so far to
+//! `out/preview_000.png`, `out/preview_001.png`, ...
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+
+use crate::{camera::Camera, cancel::CancellationToken, canvas::Canvas, world::World};
+
+/// Block sizes of each pass, coarse to fine. Each has to divide the one before.
+const PASSES: [usize; 4] = [8, 4, 2, 1];
+
+#[derive(Debug)]
+pub enum PreviewError {
+    Window(minifb::Error),
+}
+
+impl fmt::Display for PreviewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreviewError::Window(e) => write!(f, "preview window: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PreviewError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PreviewError::Window(e) => Some(e),
+        }
+    }
+}
+
+impl From<minifb::Error> for PreviewError {
+    fn from(value: minifb::Error) -> Self {
+        PreviewError::Window(value)
+    }
+}
+
+/// Opens a window the size of the camera and renders `world` into it on other threads, until
+/// the render's done and the window's closed. Closing it early cancels the render.
+pub fn show(camera: &Camera, world: &World) -> Result<(), PreviewError> {
+    let (width, height) = (camera.hsize, camera.vsize);
+    let mut window = Window::new(
+        "raytracer preview (esc: close, s: save)",
+        width,
+        height,
+        WindowOptions::default(),
+    )?;
+    window.set_target_fps(30);
+
+    let canvas = Arc::new(Mutex::new(Canvas::new(width, height)));
+    let token = CancellationToken::new();
+    let render = {
+        let (camera, world) = (camera.clone(), world.snapshot());
+        let (canvas, token) = (canvas.clone(), token.clone());
+        thread::spawn(move || render_passes(&camera, &world, &canvas, &token))
+    };
+
+    let mut preview = Preview::new(canvas.clone(), "out");
+    let mut buffer = vec![];
+    let mut finished = false;
+    'open: while window.is_open() {
+        for key in window.get_keys_pressed(KeyRepeat::No) {
+            match preview.on_key(key) {
+                Some(Event::Close) => break 'open,
+                Some(Event::Saved(path)) => {
+                    window.set_title(&format!("raytracer preview, saved {}", path.display()))
+                }
+                Some(Event::SaveFailed(e)) => {
+                    window.set_title(&format!("raytracer preview, couldn't save: {e}"))
+                }
+                None => (),
+            }
+        }
+        if !finished && render.is_finished() {
+            finished = true;
+            window.set_title("raytracer preview, done (esc: close, s: save)");
+        }
+
+        framebuffer(&canvas.lock().unwrap(), &mut buffer);
+        window.update_with_buffer(&buffer, width, height)?;
+    }
+
+    token.cancel();
+    // Only fails if a render thread panicked, which it's already said all about
+    let _ = render.join();
+    Ok(())
+}
+
+/// `canvas` as 0RGB pixels (the top byte is ignored, really), row by row, which is what
+/// minifb and most other framebuffers want. Reuses `buffer`.
+pub fn framebuffer(canvas: &Canvas, buffer: &mut Vec<u32>) {
+    buffer.clear();
+    buffer.extend(canvas.iter().map(|c| c.to_u32_argb()));
+}
+
+/// What a key press turned into
+#[derive(Debug)]
+enum Event {
+    Close,
+    Saved(PathBuf),
+    SaveFailed(io::Error),
+}
+
+/// The window's state, minus the window
+struct Preview {
+    canvas: Arc<Mutex<Canvas>>,
+    directory: PathBuf,
+    next: usize,
+}
+
+impl Preview {
+    fn new(canvas: Arc<Mutex<Canvas>>, directory: impl AsRef<Path>) -> Self {
+        Self {
+            canvas,
+            directory: directory.as_ref().to_owned(),
+            next: 0,
+        }
+    }
+
+    fn on_key(&mut self, key: Key) -> Option<Event> {
+        match key {
+            Key::Escape => Some(Event::Close),
+            Key::S => Some(match self.save() {
+                Ok(path) => Event::Saved(path),
+                Err(e) => Event::SaveFailed(e),
+            }),
+            _ => None,
+        }
+    }
+
+    /// To the first preview_NNN.png that isn't taken
+    fn save(&mut self) -> io::Result<PathBuf> {
+        // Copied out so the render isn't held up by the encoding
+        let png = {
+            let canvas = self.canvas.lock().unwrap().clone();
+            canvas.into_png(false)
+        };
+
+        fs::create_dir_all(&self.directory)?;
+        loop {
+            let path = self.directory.join(format!("preview_{:03}.png", self.next));
+            self.next += 1;
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    io::Write::write_all(&mut file, &png)?;
+                    return Ok(path);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Renders into `canvas` a pass at a time (see PASSES), spread over every core, stopping as
+/// soon as `token` is cancelled.
+fn render_passes(
+    camera: &Camera,
+    world: &World,
+    canvas: &Mutex<Canvas>,
+    token: &CancellationToken,
+) {
+    let (width, height) = (camera.hsize, camera.vsize);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut previous: Option<usize> = None;
+
+    for block in PASSES {
+        let next_row = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| loop {
+                    let y = next_row.fetch_add(1, Ordering::Relaxed) * block;
+                    if y >= height {
+                        break;
+                    }
+
+                    // The pixels that were the corner of a block last pass are already done
+                    let row: Vec<_> = (0..width)
+                        .step_by(block)
+                        .filter(|x| {
+                            previous.is_none_or(|p| !x.is_multiple_of(p) || !y.is_multiple_of(p))
+                        })
+                        .take_while(|_| !token.is_cancelled())
+                        .map(|x| (x, world.colour_at(camera.ray_for_pixel(x, y))))
+                        .collect();
+
+                    let mut canvas = canvas.lock().unwrap();
+                    for (x, colour) in row {
+                        for by in y..(y + block).min(height) {
+                            for bx in x..(x + block).min(width) {
+                                canvas[(bx, by)] = colour;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        if token.is_cancelled() {
+            return;
+        }
+        previous = Some(block);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use minifb::Key;
+
+    use crate::{
+        camera::Camera,
+        cancel::CancellationToken,
+        canvas::Canvas,
+        colour::Colour,
+        math::{
+            matrix::Matrix,
+            tuple::{pointi, vectori},
+        },
+        test_util::scratch_dir,
+        world::World,
+    };
+
+    use super::{framebuffer, render_passes, Event, Preview};
+
+    fn camera(width: usize, height: usize) -> Camera {
+        Camera::new_with_transform(
+            width,
+            height,
+            core::f64::consts::FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        )
+    }
+
+    #[test]
+    fn framebuffers() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas[(1, 0)] = Colour::new(1.0, 0.0, 0.0);
+        canvas[(0, 1)] = Colour::new(0.0, 0.5, 1.0);
+        canvas[(1, 1)] = Colour::new(2.0, -1.0, 0.2);
+
+        let mut buffer = vec![1, 2, 3, 4, 5, 6];
+        framebuffer(&canvas, &mut buffer);
+        assert_eq!(buffer, [0xff000000, 0xffff0000, 0xff0080ff, 0xffff0033]);
+    }
+
+    #[test]
+    fn keys() {
+        let dir = scratch_dir("preview-keys");
+        let canvas = Arc::new(Mutex::new(Canvas::new_with_colour(3, 2, Colour::WHITE)));
+        let mut preview = Preview::new(canvas.clone(), &dir);
+
+        assert!(preview.on_key(Key::A).is_none());
+        assert!(matches!(preview.on_key(Key::Escape), Some(Event::Close)));
+
+        // Something already in the way gets skipped over, not overwritten
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("preview_001.png"), "taken").unwrap();
+        let mut saved = vec![];
+        for _ in 0..2 {
+            match preview.on_key(Key::S) {
+                Some(Event::Saved(path)) => saved.push(path),
+                other => panic!("{other:?}"),
+            }
+            canvas.lock().unwrap()[(0, 0)] = Colour::BLACK;
+        }
+        assert_eq!(
+            saved,
+            [dir.join("preview_000.png"), dir.join("preview_002.png")]
+        );
+        assert_eq!(fs::read(dir.join("preview_001.png")).unwrap(), b"taken");
+
+        // Each is the canvas as it was at the time
+        let first = fs::read(&saved[0]).unwrap();
+        assert_eq!(
+            first,
+            Canvas::new_with_colour(3, 2, Colour::WHITE).into_png(false)
+        );
+        assert_ne!(fs::read(&saved[1]).unwrap(), first);
+
+        fs::remove_dir_all(&dir).unwrap();
+        // Somewhere it can't write
+        let mut stuck = Preview::new(canvas, dir.join("preview_000.png/nope"));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("preview_000.png"), "").unwrap();
+        assert!(matches!(stuck.on_key(Key::S), Some(Event::SaveFailed(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn passes_end_up_at_the_full_render() {
+        let (camera, world) = (camera(13, 10), World::default());
+        let canvas = Mutex::new(Canvas::new(13, 10));
+        render_passes(&camera, &world, &canvas, &CancellationToken::new());
+
+        let expected = camera.render(&world);
+        assert!(canvas.into_inner().unwrap().iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn cancelled_passes() {
+        let (camera, world) = (camera(8, 8), World::default());
+        let canvas = Mutex::new(Canvas::new(8, 8));
+        let token = CancellationToken::new();
+        token.cancel();
+        render_passes(&camera, &world, &canvas, &token);
+
+        assert!(canvas
+            .into_inner()
+            .unwrap()
+            .iter()
+            .all(|c| *c == Colour::BLACK));
+        assert_eq!(world.stats.snapshot().primary_rays, 0);
+    }
+}
@@ -0,0 +1,90 @@
+//! Hearing about how far along a render is. Sinks are only ever called from the thread that
+//! asked for the render (never the workers), so they don't need to be Sync and can happily
+//! use Cells and RefCells.
+
+#[cfg(feature = "std")]
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{self, Receiver, Sender};
+
+pub trait ProgressSink {
+    /// `done` of `total` pixels are finished. Called (a lot) as they come in, `done` never
+    /// goes backwards.
+    fn on_progress(&self, done: usize, total: usize);
+
+    /// The render is over. Called once, including for renders that were cancelled.
+    fn on_complete(&self);
+}
+
+/// Doesn't want to know
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn on_progress(&self, _done: usize, _total: usize) {}
+
+    fn on_complete(&self) {}
+}
+
+/// `done / total` on stdout every thousand pixels, overwriting itself
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutProgress;
+
+#[cfg(feature = "std")]
+impl ProgressSink for StdoutProgress {
+    fn on_progress(&self, done: usize, total: usize) {
+        if done.is_multiple_of(1000) {
+            print!("{done} / {total}\r");
+        }
+    }
+
+    fn on_complete(&self) {
+        println!();
+    }
+}
+
+/// What ChannelProgress sends
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    Update { done: usize, total: usize },
+    Complete,
+}
+
+/// Sends progress down a channel, say to a GUI thread. To not flood it, only when the whole
+/// percentage changes.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ChannelProgress {
+    tx: Sender<Progress>,
+    percent: Cell<Option<usize>>,
+}
+
+#[cfg(feature = "std")]
+impl ChannelProgress {
+    pub fn new() -> (Self, Receiver<Progress>) {
+        let (tx, rx) = mpsc::channel();
+        let sink = Self {
+            tx,
+            percent: Cell::new(None),
+        };
+        (sink, rx)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ProgressSink for ChannelProgress {
+    fn on_progress(&self, done: usize, total: usize) {
+        let percent = done * 100 / total.max(1);
+        if self.percent.get() != Some(percent) {
+            self.percent.set(Some(percent));
+            // Nobody listening any more is their business
+            let _ = self.tx.send(Progress::Update { done, total });
+        }
+    }
+
+    fn on_complete(&self) {
+        let _ = self.tx.send(Progress::Complete);
+    }
+}
@@ -1,46 +1,141 @@
-
 use crate::{
+    error::RayTracerError,
     intersection::Intersection,
     math::{
         matrix::Matrix,
-        tuple::{Tuple, ZERO_POINT, ZERO_VEC},
+        point::{Point, Vector},
+        tuple::Tuple,
     },
+    prelude::*,
 };
 
+/// What spawned a ray. Primary rays come from the camera, everything else from a hit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RayKind {
+    #[default]
+    Primary,
+    Reflection,
+    Refraction,
+    Shadow,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Ray {
-    pub origin: Tuple,
-    pub direction: Tuple,
+    pub origin: Point,
+    pub direction: Vector,
+    pub kind: RayKind,
+    /// How many bounces deep this ray is, 0 for primary rays
+    pub depth: usize,
+    /// How long `direction` was before new_normalized made it unit length, None if it was
+    /// used as given. See original_t.
+    pub original_length: Option<f64>,
 }
 
 impl Default for Ray {
     fn default() -> Self {
-        Self::new(ZERO_POINT, ZERO_VEC)
+        Self::new(Point::ORIGIN, Vector::ZERO)
     }
 }
 
 impl Ray {
-    pub fn new(origin: Tuple, direction: Tuple) -> Self {
-        assert!(origin.is_point());
-        assert!(direction.is_vector());
+    pub fn new(origin: Point, direction: Vector) -> Self {
+        Self {
+            origin,
+            direction,
+            kind: RayKind::Primary,
+            depth: 0,
+            original_length: None,
+        }
+    }
+
+    /// new, for an origin and direction that are only tuples. Fails if they aren't a point and
+    /// a vector.
+    pub fn try_new(origin: Tuple, direction: Tuple) -> Result<Self, RayTracerError> {
+        if !origin.is_point() {
+            return Err(RayTracerError::NotAPoint {
+                what: "ray origin",
+                got: origin,
+            });
+        }
+        if !direction.is_vector() {
+            return Err(RayTracerError::NotAVector {
+                what: "ray direction",
+                got: direction,
+            });
+        }
+
+        Ok(Self::new(
+            Point::from_tuple(origin),
+            Vector::from_tuple(direction),
+        ))
+    }
+
+    /// Panicking version of try_new, which is what new used to be
+    #[deprecated(note = "use Ray::new with a Point and a Vector, or try_new")]
+    pub fn from_tuples(origin: Tuple, direction: Tuple) -> Self {
+        Self::try_new(origin, direction).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// new, but with `direction` scaled to unit length so `t` (for position, and on every
+    /// intersection) is a distance in world units. The length it had is kept in
+    /// original_length. Fails for a zero, infinite or NaN direction.
+    pub fn new_normalized(origin: Point, direction: Vector) -> Result<Self, RayTracerError> {
+        let length = direction.magnitude();
+        if !length.is_normal() {
+            return Err(RayTracerError::ZeroLengthVector {
+                what: "ray direction",
+                got: direction.tuple(),
+            });
+        }
+
+        Ok(Self {
+            original_length: Some(length),
+            ..Self::new(origin, direction.normalize())
+        })
+    }
 
-        Self { origin, direction }
+    /// A ray spawned from a hit made by this one, one level deeper.
+    pub fn bounce(&self, origin: Point, direction: Vector, kind: RayKind) -> Self {
+        Self {
+            kind,
+            depth: self.depth + 1,
+            ..Self::new(origin, direction)
+        }
+    }
+
+    pub fn with_kind(self, kind: RayKind) -> Self {
+        Self { kind, ..self }
     }
 }
 
 impl Ray {
-    pub fn position(&self, dst: f64) -> Tuple {
-        self.origin + (self.direction * dst)
+    /// The point `dst` lengths of `direction` along the ray. Only a distance when direction is
+    /// unit length, as it is for new_normalized and camera rays.
+    pub fn position(&self, dst: f64) -> Point {
+        self.origin + self.direction * dst
     }
 
     pub fn transform(&self, matrix: &Matrix) -> Self {
-        Self::new(matrix * self.origin, matrix * self.direction)
+        Self {
+            origin: matrix * self.origin,
+            direction: matrix * self.direction,
+            ..*self
+        }
+    }
+
+    /// `t` along this ray as a `t` along the direction it was made with, before new_normalized
+    /// got to it. The same t for rays that weren't normalized.
+    pub fn original_t(&self, t: f64) -> f64 {
+        match self.original_length {
+            Some(length) => t / length,
+            None => t,
+        }
     }
 }
 
 // Used by shape
 pub trait RayIntersect {
-    fn intersect(&self, ray: Ray) -> Option<Vec<Intersection>>;
+    fn intersect(&self, ray: Ray) -> Option<Vec<Intersection<'_>>>;
 }
 
 #[cfg(test)]
@@ -48,35 +143,161 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn try_new() {
+        let (origin, direction) = (Tuple::pointi(1, 2, 3), Tuple::vectori(0, 1, 0));
+        let r = Ray::try_new(origin, direction).unwrap();
+        assert_eq!(r.origin, origin);
+        assert_eq!(r.direction, direction);
+
+        assert_eq!(
+            Ray::try_new(direction, direction).unwrap_err(),
+            RayTracerError::NotAPoint {
+                what: "ray origin",
+                got: direction
+            }
+        );
+        assert_eq!(
+            Ray::try_new(origin, origin).unwrap_err(),
+            RayTracerError::NotAVector {
+                what: "ray direction",
+                got: origin
+            }
+        );
+    }
+
+    #[test]
+    fn new_normalized() {
+        let origin = Point::newi(1, 2, 3);
+        let r = Ray::new_normalized(origin, Vector::newi(0, 3, 4)).unwrap();
+        assert_eq!(r.direction, Vector::new(0.0, 0.6, 0.8));
+        assert_eq!(r.original_length, Some(5.0));
+        // 5 world units along is 1 of the original direction
+        assert_eq!(r.position(5.0), Point::newi(1, 5, 7));
+        assert_eq!(r.original_t(5.0), 1.0);
+
+        assert_eq!(
+            Ray::new(origin, Vector::newi(0, 3, 4)).original_length,
+            None
+        );
+
+        for bad in [
+            Vector::newi(0, 0, 0),
+            Vector::new(f64::NAN, 0.0, 1.0),
+            Vector::new(f64::INFINITY, 0.0, 0.0),
+        ] {
+            // Not comparing got, NaN never equals itself
+            assert!(matches!(
+                Ray::new_normalized(origin, bad),
+                Err(RayTracerError::ZeroLengthVector {
+                    what: "ray direction",
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn intersection_t_convention() {
+        use crate::shape::{sphere::Sphere, ShapeBase};
+
+        // The book's ray, and the same one with a longer direction
+        let s = Sphere::default();
+        let ts = |r: Ray| -> Vec<f64> { s.intersect(r).unwrap().iter().map(|i| i.t).collect() };
+        let origin = Point::newi(0, 0, -5);
+
+        assert_eq!(ts(Ray::new(origin, Vector::newi(0, 0, 1))), vec![4.0, 6.0]);
+        // As given, t is in lengths of direction
+        assert_eq!(ts(Ray::new(origin, Vector::newi(0, 0, 2))), vec![2.0, 3.0]);
+        // Normalized, it's back to the book's distances
+        let r = Ray::new_normalized(origin, Vector::newi(0, 0, 2)).unwrap();
+        assert_eq!(ts(r), vec![4.0, 6.0]);
+        assert_eq!(r.original_t(4.0), 2.0);
+
+        // and transforming into object space doesn't change what t means
+        let mut scaled = Sphere::default();
+        scaled.set_transform(Matrix::scalingi(2, 2, 2));
+        let hits = scaled.intersect(r).unwrap();
+        assert_eq!(r.position(hits[0].t), Point::newi(0, 0, -2));
+    }
+
+    #[test]
+    #[should_panic(expected = "ray direction must be a vector")]
+    #[allow(deprecated)]
+    fn from_tuples_panics() {
+        Ray::from_tuples(Tuple::pointi(0, 0, 0), Tuple::pointi(0, 0, 1));
+    }
+
     #[test]
     fn position() {
-        let r = Ray::new(Tuple::pointi(2, 3, 4), Tuple::vectori(1, 0, 0));
+        let r = Ray::new(Point::newi(2, 3, 4), Vector::newi(1, 0, 0));
 
         assert_eq!(r.position(0.0), r.origin);
-        assert_eq!(r.position(1.0), Tuple::pointi(3, 3, 4));
-        assert_eq!(r.position(1.0), Tuple::pointi(3, 3, 4));
-        assert_eq!(r.position(-1.0), Tuple::pointi(1, 3, 4));
-        assert_eq!(r.position(2.5), Tuple::point(4.5, 3.0, 4.0));
+        assert_eq!(r.position(1.0), Point::newi(3, 3, 4));
+        assert_eq!(r.position(1.0), Point::newi(3, 3, 4));
+        assert_eq!(r.position(-1.0), Point::newi(1, 3, 4));
+        assert_eq!(r.position(2.5), Point::new(4.5, 3.0, 4.0));
     }
 
     #[test]
     fn translate() {
-        let r = Ray::new(Tuple::pointi(1, 2, 3), Tuple::vectori(0, 1, 0));
+        let r = Ray::new(Point::newi(1, 2, 3), Vector::newi(0, 1, 0));
         let m = &Matrix::translationi(3, 4, 5);
 
         let res = r.transform(m);
 
-        assert_eq!(res.origin, Tuple::pointi(4, 6, 8));
-        assert_eq!(res.direction, Tuple::vectori(0, 1, 0));
+        assert_eq!(res.origin, Point::newi(4, 6, 8));
+        assert_eq!(res.direction, Vector::newi(0, 1, 0));
     }
+    #[test]
+    fn new_is_primary() {
+        let r = Ray::new(Point::newi(1, 2, 3), Vector::newi(0, 1, 0));
+
+        assert_eq!(r.kind, RayKind::Primary);
+        assert_eq!(r.depth, 0);
+    }
+
+    #[test]
+    fn bounce() {
+        let r = Ray::new(Point::newi(1, 2, 3), Vector::newi(0, 1, 0));
+        let b = r.bounce(
+            Point::newi(0, 0, 0),
+            Vector::newi(1, 0, 0),
+            RayKind::Reflection,
+        );
+        let bb = b.bounce(
+            Point::newi(0, 0, 0),
+            Vector::newi(1, 0, 0),
+            RayKind::Refraction,
+        );
+
+        assert_eq!((b.kind, b.depth), (RayKind::Reflection, 1));
+        assert_eq!((bb.kind, bb.depth), (RayKind::Refraction, 2));
+        assert_eq!(b.origin, Point::newi(0, 0, 0));
+    }
+
+    #[test]
+    fn transform_keeps_provenance() {
+        let r = Ray::new(Point::newi(1, 2, 3), Vector::newi(0, 1, 0)).bounce(
+            Point::newi(1, 2, 3),
+            Vector::newi(0, 1, 0),
+            RayKind::Reflection,
+        );
+
+        let res = r.transform(&Matrix::translationi(3, 4, 5));
+
+        assert_eq!(res.kind, RayKind::Reflection);
+        assert_eq!(res.depth, 1);
+    }
+
     #[test]
     fn scale() {
-        let r = Ray::new(Tuple::pointi(1, 2, 3), Tuple::vectori(0, 1, 0));
+        let r = Ray::new(Point::newi(1, 2, 3), Vector::newi(0, 1, 0));
         let m = &Matrix::scalingi(2, 3, 4);
 
         let res = r.transform(m);
 
-        assert_eq!(res.origin, Tuple::pointi(2, 6, 12));
-        assert_eq!(res.direction, Tuple::vectori(0, 3, 0));
+        assert_eq!(res.origin, Point::newi(2, 6, 12));
+        assert_eq!(res.direction, Vector::newi(0, 3, 0));
     }
 }
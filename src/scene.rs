@@ -0,0 +1,903 @@
+//! Everything needed to produce an image: what's in it, where it's seen from and how to render it.
+
+use crate::{
+    camera::Camera,
+    cancel::{CancellationToken, RenderResult, UNRENDERED},
+    canvas::{Canvas, Exposure, ToneMap},
+    colour::Colour,
+    math::{
+        rng::Rng,
+        sampling::{Sampler, SamplerKind},
+    },
+    photon_map::PhotonMap,
+    trace,
+    world::{World, MAX_DEPTH},
+};
+
+mod adaptive;
+#[cfg(feature = "std")]
+mod description;
+#[cfg(feature = "std")]
+mod validate;
+#[cfg(feature = "std")]
+mod yaml;
+
+#[cfg(feature = "std")]
+pub use description::{SceneDescription, SceneError};
+#[cfg(feature = "std")]
+pub use validate::{validate, SceneIssue, Severity};
+
+/// Knobs for Scene::render. The defaults render exactly what Camera::render does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RenderSettings {
+    /// How many bounces deep reflections and refractions are followed
+    pub max_depth: usize,
+    /// Rays per pixel along each axis, so 3 is a 3x3 grid of 9 rays averaged together. 1 is a
+    /// single ray through the middle of the pixel.
+    pub aa_samples: usize,
+    /// Rays per light when working out shadows. Point lights are either visible or not, so for
+    /// now this changes nothing.
+    pub shadow_samples: usize,
+    /// What rays that hit nothing see
+    pub background: Colour,
+    /// Threads to render on, 0 and 1 both mean "just this one". Ignored without std.
+    pub threads: usize,
+    /// Scales the whole render brighter or darker before tone mapping, see Canvas::auto_exposure.
+    /// Off leaves it as rendered.
+    pub exposure: Exposure,
+    /// Brings anything brighter than white back into range, before any sRGB encoding. See
+    /// Canvas::tone_mapped, Clamp leaves the canvas as rendered.
+    pub tone_map: ToneMap,
+    /// sRGB encode the finished canvas, see Canvas::encoded_srgb
+    pub encode_srgb: bool,
+    pub mode: RenderMode,
+    /// Where path tracing's (and caustic photons') random numbers start from. The same seed
+    /// renders the same image, however many threads it's on.
+    pub seed: u64,
+    pub caustics: CausticSettings,
+    /// Adaptive sampling, off at 0. Otherwise every pixel gets at least `min_samples` rays,
+    /// spread across it by `sampler`, with more sent `min_samples` at a time until the 95%
+    /// confidence interval of its brightness is within this fraction of it, or there have been
+    /// `max_samples`. That takes the place of aa_samples, or samples_per_pixel when path
+    /// tracing. See Scene::render_sample_counts for where the rays went.
+    pub noise_threshold: f64,
+    pub min_samples: usize,
+    pub max_samples: usize,
+    /// When path tracing, the most light (in any one channel) one bounce after the first can
+    /// add, see World::path_traced_colour_at_clamped. None doesn't clamp.
+    pub max_sample_radiance: Option<f64>,
+    /// Canvas::despeckle the finished render with this factor, before tone mapping
+    pub despeckle: Option<f64>,
+    /// Where in each pixel rays go, when there's more than one. The default's an even grid.
+    pub sampler: SamplerKind,
+}
+
+impl RenderSettings {
+    pub const DEFAULT: Self = Self {
+        max_depth: MAX_DEPTH,
+        aa_samples: 1,
+        shadow_samples: 1,
+        background: Colour::BLACK,
+        threads: 1,
+        exposure: Exposure::Off,
+        tone_map: ToneMap::Clamp,
+        encode_srgb: false,
+        mode: RenderMode::Whitted,
+        seed: 0,
+        caustics: CausticSettings::OFF,
+        noise_threshold: 0.0,
+        min_samples: 4,
+        max_samples: 64,
+        max_sample_radiance: None,
+        despeckle: None,
+        sampler: SamplerKind::Stratified { jitter: false },
+    };
+}
+
+/// Photon mapped caustics, so glass and mirrors focus light onto what's around them instead of
+/// only casting shadows. Before rendering, photons are fired from every light, and wherever one
+/// lands after a reflection or refraction it adds to the light there (see
+/// World::caustic_photons). Only the Whitted mode uses them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CausticSettings {
+    /// Fired from each light, most of which miss anything shiny. 0 turns caustics off.
+    pub photons: usize,
+    /// How many of the photons nearest a point are averaged for the light there. More is
+    /// smoother and blurrier.
+    pub nearest: usize,
+    /// How far away from a point photons can still count towards its light
+    pub radius: f64,
+}
+
+impl CausticSettings {
+    pub const OFF: Self = Self {
+        photons: 0,
+        nearest: 50,
+        radius: 0.25,
+    };
+}
+
+impl Default for CausticSettings {
+    fn default() -> Self {
+        Self::OFF
+    }
+}
+
+/// How the colour of each pixel is worked out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RenderMode {
+    /// What the book does: direct light from every light, plus mirror reflections and
+    /// refractions. Diffuse surfaces never light each other, ambient stands in for that.
+    #[default]
+    Whitted,
+    /// Averages `samples_per_pixel` random paths through each pixel, bouncing off diffuse
+    /// surfaces too, so colours bleed onto their surroundings and emissive materials light
+    /// things. Noisy unless there's plenty of samples. `max_bounces` replaces max_depth and
+    /// samples_per_pixel replaces aa_samples (each path goes through a spot in the pixel picked
+    /// by the sampler, unless there's only one), see World::path_traced_colour_at.
+    PathTraced {
+        samples_per_pixel: usize,
+        max_bounces: usize,
+    },
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+pub struct Scene {
+    pub world: World,
+    pub camera: Camera,
+    pub settings: RenderSettings,
+}
+
+impl Scene {
+    pub fn new(world: World, camera: Camera) -> Self {
+        Self {
+            world,
+            camera,
+            settings: RenderSettings::DEFAULT,
+        }
+    }
+
+    pub fn with_settings(mut self, settings: RenderSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn render(&self) -> Canvas {
+        self.render_cancellable(&CancellationToken::new())
+            .into_canvas()
+    }
+
+    /// render, but giving up once `token` is cancelled. Every thread checks it before each
+    /// pixel, whatever none of them got to is left as cancel::UNRENDERED.
+    pub fn render_cancellable(&self, token: &CancellationToken) -> RenderResult {
+        trace::span!(DEBUG, "scene_render", settings = ?self.settings);
+        let caustics = (self.settings.caustics.photons > 0
+            && self.settings.mode == RenderMode::Whitted)
+            .then(|| {
+                self.world.caustic_photons(
+                    self.settings.caustics.photons,
+                    self.settings.max_depth,
+                    self.settings.seed,
+                )
+            });
+        let (mut canvas, completed) = self.render_canvas(caustics.as_ref(), token);
+
+        if let Some(factor) = self.settings.despeckle {
+            canvas = canvas.despeckle(factor);
+        }
+        if self.settings.exposure != Exposure::Off {
+            canvas = canvas.exposed(self.settings.exposure.scale_for(&canvas));
+        }
+        // Writing out clamps anyway, so that one's left to it
+        if self.settings.tone_map != ToneMap::Clamp {
+            canvas = canvas.tone_mapped(self.settings.tone_map);
+        }
+        if self.settings.encode_srgb {
+            canvas = canvas.encoded_srgb();
+        }
+        if completed {
+            RenderResult::Completed(canvas)
+        } else {
+            RenderResult::Cancelled(canvas)
+        }
+    }
+
+    /// The canvas, and whether every pixel of it got rendered before `token` was cancelled
+    fn render_canvas(
+        &self,
+        caustics: Option<&PhotonMap>,
+        token: &CancellationToken,
+    ) -> (Canvas, bool) {
+        let (width, height) = (self.camera.hsize, self.camera.vsize);
+        let mut canvas = Canvas::new_with_colour(width, height, UNRENDERED);
+
+        #[cfg(feature = "std")]
+        if self.settings.threads > 1 {
+            let completed = crate::camera::render_pixels(
+                width,
+                height,
+                self.settings.threads,
+                token,
+                &crate::progress::NoProgress,
+                |x, y| Some(self.pixel_colour(x, y, caustics)),
+                |x, y, colour| canvas[(x, y)] = colour,
+            );
+            return (canvas, completed);
+        }
+
+        // Column by column, the order render_pixels hands them out in
+        for x in 0..width {
+            for y in 0..height {
+                if token.is_cancelled() {
+                    return (canvas, false);
+                }
+                canvas[(x, y)] = self.pixel_colour(x, y, caustics);
+            }
+        }
+        (canvas, true)
+    }
+
+    fn pixel_colour(&self, x: usize, y: usize, caustics: Option<&PhotonMap>) -> Colour {
+        if self.settings.noise_threshold > 0.0 {
+            return self.adaptive_pixel(x, y, caustics).0;
+        }
+        if let RenderMode::PathTraced {
+            samples_per_pixel,
+            max_bounces,
+        } = self.settings.mode
+        {
+            return self.path_traced_pixel(x, y, samples_per_pixel, max_bounces);
+        }
+
+        let n = self.settings.aa_samples.max(1);
+        if n == 1 {
+            return self.world.colour_at_with_caustics(
+                self.camera.ray_for_pixel(x, y),
+                &self.settings,
+                caustics,
+            );
+        }
+
+        // n x n rays, by default one through the middle of each of n x n sub-pixels
+        let samples = n * n;
+        let sampler = self.settings.sampler.sampler(samples, self.settings.seed);
+        Colour::average((0..samples).map(|i| {
+            let (dx, dy) = sampler.for_pixel(x, y, i).next_2d();
+            let ray = self.camera.ray_for_subpixel(x, y, dx, dy);
+            self.world
+                .colour_at_with_caustics(ray, &self.settings, caustics)
+        }))
+    }
+
+    fn path_traced_pixel(&self, x: usize, y: usize, samples: usize, max_bounces: usize) -> Colour {
+        // Each pixel gets its own numbers, so threads can take them in any order
+        let mut rng = Rng::new(pixel_seed(self.settings.seed, x, y));
+        let samples = samples.max(1);
+        let sampler = self.settings.sampler.sampler(samples, self.settings.seed);
+        Colour::average((0..samples).map(|i| {
+            let ray = if samples == 1 {
+                self.camera.ray_for_pixel(x, y)
+            } else {
+                let (dx, dy) = sampler.for_pixel(x, y, i).next_2d();
+                self.camera.ray_for_subpixel(x, y, dx, dy)
+            };
+            self.world.path_traced_colour_at_clamped(
+                ray,
+                max_bounces,
+                self.settings.background,
+                self.settings.max_sample_radiance,
+                &mut rng,
+            )
+        }))
+    }
+}
+
+/// `seed` mixed up with the pixel (splitmix64's finalizer), so neighbours' numbers look nothing
+/// alike
+fn pixel_seed(seed: u64, x: usize, y: usize) -> u64 {
+    let mut z = seed
+        ^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_3};
+
+    use crate::{
+        camera::Camera,
+        cancel::{CancellationToken, UNRENDERED},
+        canvas::{Canvas, Exposure, ToneMap, MIDDLE_GREY},
+        colour::Colour,
+        lights::PointLight,
+        materials::Material,
+        math::{
+            matrix::{Matrix, IDENTITY_4X4},
+            sampling::SamplerKind,
+            tuple::{point, pointi, vectori},
+        },
+        shape::{plane::Plane, sphere::Sphere, Shape},
+        world::World,
+    };
+
+    use super::{CausticSettings, RenderMode, RenderSettings, Scene};
+
+    fn scene() -> Scene {
+        let camera = Camera::new_with_transform(
+            21,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        Scene::new(World::default(), camera)
+    }
+
+    fn same(a: &Canvas, b: &Canvas) -> bool {
+        a.width == b.width
+            && a.height == b.height
+            && a.iter().zip(b.iter()).all(|(a, b)| {
+                a.red.to_bits() == b.red.to_bits()
+                    && a.green.to_bits() == b.green.to_bits()
+                    && a.blue.to_bits() == b.blue.to_bits()
+            })
+    }
+
+    #[test]
+    fn default_matches_camera_render() {
+        let s = scene();
+        assert!(same(&s.render(), &s.camera.render(&s.world)));
+
+        let threaded = scene().with_settings(RenderSettings {
+            threads: 4,
+            ..Default::default()
+        });
+        assert!(same(&threaded.render(), &s.render()));
+    }
+
+    #[test]
+    fn aa_samples() {
+        let plain = scene().render();
+        let aa = scene()
+            .with_settings(RenderSettings {
+                aa_samples: 3,
+                ..Default::default()
+            })
+            .render();
+
+        // Each pixel is the average of a 3x3 grid of rays across it
+        let s = scene();
+        let mut expected = Colour::BLACK;
+        for dx in [1.0 / 6.0, 0.5, 5.0 / 6.0] {
+            for dy in [1.0 / 6.0, 0.5, 5.0 / 6.0] {
+                expected += s.world.colour_at(s.camera.ray_for_subpixel(10, 5, dx, dy));
+            }
+        }
+        assert_eq!(aa[(10, 5)], expected / 9.0);
+        assert_ne!(aa[(10, 5)], plain[(10, 5)]);
+        // The corner is all background
+        assert_eq!(aa[(0, 0)], Colour::BLACK);
+
+        // Somewhere along the edge of the sphere pixels are only partly covered, which without
+        // AA are either fully lit or black
+        let partial = (0..21)
+            .flat_map(|x| (0..11).map(move |y| (x, y)))
+            .find(|&p| plain[p] == Colour::BLACK && aa[p] != Colour::BLACK);
+        assert!(partial.is_some());
+    }
+
+    #[test]
+    fn samplers() {
+        let render = |sampler, aa_samples, seed| {
+            scene()
+                .with_settings(RenderSettings {
+                    aa_samples,
+                    sampler,
+                    seed,
+                    ..Default::default()
+                })
+                .render()
+        };
+        // Near enough the real thing, to compare with
+        let reference = render(SamplerKind::default(), 8, 0);
+        let error = |sampler| {
+            let seeds = 3;
+            let total: f64 = (0..seeds)
+                .map(|seed| {
+                    let canvas = render(sampler, 4, seed);
+                    canvas
+                        .iter()
+                        .zip(reference.iter())
+                        .map(|(a, b)| (a.red - b.red).powi(2))
+                        .sum::<f64>()
+                })
+                .sum();
+            total / seeds as f64
+        };
+        // Most of the picture's the same however it's sampled, it's the edge of the sphere where
+        // it matters
+        let independent = error(SamplerKind::Independent);
+        let stratified = error(SamplerKind::Stratified { jitter: true });
+        let halton = error(SamplerKind::Halton);
+        assert!(stratified < independent, "{stratified} {independent}");
+        assert!(halton < independent, "{halton} {independent}");
+
+        // Samplers don't care what order pixels come in
+        let threaded = scene().with_settings(RenderSettings {
+            aa_samples: 3,
+            sampler: SamplerKind::Halton,
+            threads: 3,
+            ..Default::default()
+        });
+        assert!(same(&threaded.render(), &render(SamplerKind::Halton, 3, 0)));
+    }
+
+    #[test]
+    fn background_and_gamma() {
+        let grey = Colour::new(0.2, 0.2, 0.2);
+        let s = scene().with_settings(RenderSettings {
+            background: grey,
+            ..Default::default()
+        });
+        let c = s.render();
+        assert_eq!(c[(0, 0)], grey);
+        assert_eq!(c[(10, 5)], scene().render()[(10, 5)]);
+
+        let encoded = scene()
+            .with_settings(RenderSettings {
+                encode_srgb: true,
+                ..Default::default()
+            })
+            .render();
+        assert_eq!(encoded[(10, 5)], scene().render().encoded_srgb()[(10, 5)]);
+    }
+
+    #[test]
+    fn tone_mapping() {
+        let render = |tone_map, encode_srgb| {
+            scene()
+                .with_settings(RenderSettings {
+                    tone_map,
+                    encode_srgb,
+                    ..Default::default()
+                })
+                .render()
+        };
+        // Clamp is the default, and leaves the render alone
+        assert!(same(&render(ToneMap::Clamp, false), &scene().render()));
+
+        // Mapped before encoding
+        let plain = scene().render();
+        let op = ToneMap::Reinhard;
+        let mapped = render(op, true);
+        assert_eq!(mapped[(10, 5)], op.apply(plain[(10, 5)]).linear_to_srgb());
+        assert!(mapped[(10, 5)].red < plain[(10, 5)].linear_to_srgb().red);
+    }
+
+    #[test]
+    fn exposure() {
+        let render = |exposure| {
+            scene()
+                .with_settings(RenderSettings {
+                    exposure,
+                    tone_map: ToneMap::Reinhard,
+                    ..Default::default()
+                })
+                .render()
+        };
+        let plain = scene().render();
+        let mapped = plain.tone_mapped(ToneMap::Reinhard);
+
+        // Off is the default, and leaves the render alone
+        assert!(same(&render(Exposure::Off), &mapped));
+        let manual = render(Exposure::Manual(2.0));
+        assert!(same(
+            &manual,
+            &plain.exposed(2.0).tone_mapped(ToneMap::Reinhard)
+        ));
+
+        // Applied before tone mapping
+        let auto = render(Exposure::AUTO);
+        let scale = plain.auto_exposure(MIDDLE_GREY);
+        assert_ne!(scale, 1.0);
+        assert!(same(
+            &auto,
+            &plain.exposed(scale).tone_mapped(ToneMap::Reinhard)
+        ));
+    }
+
+    #[test]
+    fn cancellable() {
+        let token = CancellationToken::new();
+        let threaded = scene().with_settings(RenderSettings {
+            threads: 3,
+            ..Default::default()
+        });
+        let result = threaded.render_cancellable(&token);
+        assert!(result.is_completed());
+        assert!(same(result.canvas(), &scene().render()));
+
+        token.cancel();
+        for s in [scene(), threaded] {
+            let result = s.render_cancellable(&token);
+            assert!(!result.is_completed());
+            assert!(result.canvas().iter().all(|c| *c == UNRENDERED));
+        }
+    }
+
+    fn path_traced(samples_per_pixel: usize, max_bounces: usize, seed: u64) -> RenderSettings {
+        RenderSettings {
+            mode: RenderMode::PathTraced {
+                samples_per_pixel,
+                max_bounces,
+            },
+            seed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn furnace() {
+        // Inside a closed shell where everything glows with e and reflects a of what lands on
+        // it, every bounce adds another a times as much again: e / (1 - a) in total
+        let glowing = Material {
+            emissive: Colour::new(0.5, 0.25, 0.1),
+            diffuse: 0.5,
+            ambient: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        };
+        let world = World::new(
+            vec![
+                Box::new(Sphere::new(
+                    Matrix::scaling(10.0, 10.0, 10.0),
+                    glowing.clone(),
+                )),
+                Box::new(Sphere::new(Matrix::translation(0.0, 0.0, 3.0), glowing)),
+            ],
+            vec![],
+        );
+        let camera = Camera::new_with_transform(
+            8,
+            8,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        let canvas = Scene::new(world, camera)
+            .with_settings(path_traced(64, 100, 1))
+            .render();
+
+        let mean = canvas.iter().fold(Colour::BLACK, |acc, c| acc + *c) / 64.0;
+        let expected = Colour::new(1.0, 0.5, 0.2);
+        for (got, want) in [
+            (mean.red, expected.red),
+            (mean.green, expected.green),
+            (mean.blue, expected.blue),
+        ] {
+            assert!((got - want).abs() < want * 0.03, "{mean:?}");
+        }
+    }
+
+    #[test]
+    fn path_tracing_is_deterministic() {
+        let render = |seed, threads| {
+            scene()
+                .with_settings(RenderSettings {
+                    threads,
+                    ..path_traced(4, 3, seed)
+                })
+                .render()
+        };
+        let first = render(5, 1);
+        assert!(same(&first, &render(5, 1)));
+        assert!(same(&first, &render(5, 3)));
+        assert!(!same(&first, &render(6, 1)));
+    }
+
+    #[test]
+    fn zero_bounces_is_direct_light() {
+        let whitted = scene().render();
+        let direct = scene().with_settings(path_traced(1, 0, 3)).render();
+        assert!(same(&whitted, &direct));
+
+        // Where light does bounce back, a bounce adds to it
+        let whitted = cornell_box().render();
+        let direct = cornell_box().with_settings(path_traced(1, 0, 3)).render();
+        assert!(same(&whitted, &direct));
+        let bounced = cornell_box().with_settings(path_traced(1, 1, 3)).render();
+        assert!(!same(&whitted, &bounced));
+    }
+
+    /// A small, very bright ball just above a matte floor, and no lights. All the floor gets
+    /// is the odd path that happens to bounce into the ball.
+    fn firefly() -> Scene {
+        let bulb = Material {
+            emissive: Colour::new(500.0, 500.0, 500.0),
+            ..Default::default()
+        };
+        let world = World::new(
+            vec![
+                Box::new(Plane::new(
+                    IDENTITY_4X4,
+                    Material::matte(Colour::new(0.8, 0.8, 0.8)),
+                )),
+                Box::new(Sphere::new(
+                    Matrix::scaling(0.4, 0.4, 0.4).translate(0.0, 0.6, 0.0),
+                    bulb,
+                )),
+            ],
+            vec![],
+        );
+        let camera = Camera::new_with_transform(
+            16,
+            16,
+            FRAC_PI_3,
+            Matrix::view_transform(point(0.0, 1.5, -2.5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        Scene::new(world, camera)
+    }
+
+    #[test]
+    fn firefly_clamping() {
+        let scene = firefly();
+        let normals = scene.camera.render_normals(&scene.world);
+        // Pixels with nothing but floor around them, so no ray across them sees the ball
+        let floor = |x: usize, y: usize| {
+            (x.saturating_sub(1)..=(x + 1).min(15)).all(|x| {
+                (y.saturating_sub(1)..=(y + 1).min(15))
+                    .all(|y| normals[(x, y)] == Colour::new(0.0, 1.0, 0.0))
+            })
+        };
+        let render = |max_sample_radiance| {
+            firefly()
+                .with_settings(RenderSettings {
+                    max_sample_radiance,
+                    ..path_traced(4, 1, 5)
+                })
+                .render()
+        };
+
+        let brightest_floor = |canvas: &Canvas| {
+            canvas
+                .enumerate_pixels()
+                .filter(|&(x, y, _)| floor(x, y))
+                .map(|(_, _, c)| c.max_channel())
+                .fold(0.0, f64::max)
+        };
+        let plain = render(None);
+        assert!(
+            brightest_floor(&plain) > 50.0,
+            "{}",
+            brightest_floor(&plain)
+        );
+
+        let clamped = render(Some(1.0));
+        assert!(brightest_floor(&clamped) <= 1.0 + 1e-9);
+        // The ball itself is seen directly, so it's as bright as ever
+        assert!(clamped.iter().any(|c| c.red >= 500.0));
+    }
+
+    #[test]
+    fn firefly_defaults() {
+        assert_eq!(RenderSettings::DEFAULT.max_sample_radiance, None);
+        assert_eq!(RenderSettings::DEFAULT.despeckle, None);
+        let render = |max_sample_radiance, despeckle| {
+            firefly()
+                .with_settings(RenderSettings {
+                    max_sample_radiance,
+                    despeckle,
+                    ..path_traced(2, 2, 5)
+                })
+                .render()
+        };
+        let plain = render(None, None);
+        assert!(same(
+            &plain,
+            &render(Some(f64::INFINITY), Some(f64::INFINITY))
+        ));
+        assert!(!same(&plain, &render(None, Some(2.0))));
+    }
+
+    /// A white sphere in a white box with a red wall on the left
+    fn cornell_box() -> Scene {
+        let matte = |colour| Material {
+            ambient: 0.0,
+            ..Material::matte(colour)
+        };
+        let white = Colour::new(0.8, 0.8, 0.8);
+        let wall = |transform: Matrix, colour| -> Box<dyn Shape> {
+            Box::new(Plane::new(transform, matte(colour)))
+        };
+        let world = World::new(
+            vec![
+                wall(Matrix::translation(0.0, 0.0, 0.0), white),
+                wall(Matrix::translation(0.0, 4.0, 0.0), white),
+                wall(
+                    Matrix::rotation_z(FRAC_PI_2).translate(-2.0, 0.0, 0.0),
+                    Colour::new(0.9, 0.05, 0.05),
+                ),
+                wall(
+                    Matrix::rotation_z(FRAC_PI_2).translate(2.0, 0.0, 0.0),
+                    white,
+                ),
+                wall(
+                    Matrix::rotation_x(FRAC_PI_2).translate(0.0, 0.0, 3.0),
+                    white,
+                ),
+                Box::new(Sphere::new_at(point(-0.6, 1.0, 0.5), 1.0)),
+            ],
+            vec![PointLight::new_boxed(Colour::WHITE, point(0.0, 3.5, -1.0))],
+        );
+        let camera = Camera::new_with_transform(
+            16,
+            16,
+            FRAC_PI_3,
+            Matrix::view_transform(
+                point(0.0, 2.0, -6.0),
+                point(0.0, 1.5, 0.0),
+                vectori(0, 1, 0),
+            ),
+        );
+        Scene::new(world, camera)
+    }
+
+    #[test]
+    fn colour_bleeding() {
+        let scene = cornell_box();
+        // Pixels showing the left of the sphere, the side facing the red wall
+        let left: Vec<_> = (0..16)
+            .flat_map(|x| (0..16).map(move |y| (x, y)))
+            .filter(|&(x, y)| {
+                scene
+                    .world
+                    .first_hit(scene.camera.ray_for_pixel(x, y))
+                    .is_some_and(|(hit, at, _)| {
+                        hit.object.downcast_ref::<Sphere>().is_some() && at.x < -0.8
+                    })
+            })
+            .collect();
+        assert!(left.len() >= 4, "{left:?}");
+        let total = |canvas: &Canvas| left.iter().fold(Colour::BLACK, |acc, p| acc + canvas[*p]);
+
+        // Without bounces a white sphere under a white light is grey all over
+        let whitted = total(&scene.render());
+        assert!((whitted.red - whitted.green).abs() < 1e-9, "{whitted:?}");
+
+        let scene = scene.with_settings(path_traced(12, 2, 1));
+        let path_traced = total(&scene.render());
+        assert!(path_traced.red > path_traced.green * 1.1, "{path_traced:?}");
+        assert!(path_traced.red > whitted.red);
+    }
+
+    /// A glass ball hanging over a floor, lit from straight above, seen from low down so the
+    /// floor underneath it shows
+    fn glass_ball() -> Scene {
+        let world = World::new(
+            vec![
+                Box::new(Plane::new(IDENTITY_4X4, Material::matte(Colour::WHITE))),
+                Box::new(Sphere::new(
+                    Matrix::translation(0.0, 2.0, 0.0),
+                    Material::glass(),
+                )),
+            ],
+            vec![PointLight::new_boxed(Colour::WHITE, pointi(0, 5, 0))],
+        );
+        let camera = Camera::new_with_transform(
+            9,
+            9,
+            FRAC_PI_3,
+            Matrix::view_transform(point(0.0, 1.0, -6.0), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        Scene::new(world, camera)
+    }
+
+    fn caustics(photons: usize) -> RenderSettings {
+        RenderSettings {
+            caustics: CausticSettings {
+                photons,
+                ..CausticSettings::OFF
+            },
+            seed: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_photons_no_caustics() {
+        let scene = glass_ball();
+        assert!(same(
+            &scene.render(),
+            &glass_ball().with_settings(caustics(0)).render()
+        ));
+        // Nothing there to focus the light either
+        assert!(same(
+            &self::scene().render(),
+            &self::scene().with_settings(caustics(5000)).render()
+        ));
+    }
+
+    #[test]
+    fn caustic_under_glass() {
+        let scene = glass_ball();
+        let plain = scene.render();
+        let centre = (4, 4);
+        let (hit, at, _) = scene
+            .world
+            .first_hit(scene.camera.ray_for_pixel(centre.0, centre.1))
+            .unwrap();
+        assert!(hit.object.downcast_ref::<Plane>().is_some());
+        assert!((at - pointi(0, 0, 0)).magnitude() < 0.5);
+        let with_caustics = scene.with_settings(caustics(50_000)).render();
+
+        // In the ball's shadow without, and brighter than the lit floor around it with
+        let lit = plain[(4, 8)];
+        assert!(plain[centre].red < 0.2, "{:?}", plain[centre]);
+        assert!(
+            with_caustics[centre].red > lit.red,
+            "{:?} {lit:?}",
+            with_caustics[centre]
+        );
+        // Everywhere else is near enough the same
+        assert!(
+            (with_caustics[(4, 8)].red - lit.red).abs() < 0.05,
+            "{:?}",
+            with_caustics[(4, 8)]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let settings = RenderSettings {
+            max_depth: 3,
+            aa_samples: 4,
+            shadow_samples: 8,
+            background: Colour::new(0.1, 0.2, 0.3),
+            threads: 6,
+            exposure: Exposure::Auto { target: 0.2 },
+            tone_map: ToneMap::ReinhardExtended { white_point: 4.0 },
+            encode_srgb: true,
+            mode: RenderMode::PathTraced {
+                samples_per_pixel: 64,
+                max_bounces: 4,
+            },
+            seed: 7,
+            caustics: CausticSettings {
+                photons: 10_000,
+                nearest: 20,
+                radius: 0.5,
+            },
+            noise_threshold: 0.02,
+            min_samples: 8,
+            max_samples: 256,
+            max_sample_radiance: Some(10.0),
+            despeckle: Some(4.0),
+            sampler: SamplerKind::Stratified { jitter: true },
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        let back: RenderSettings = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, settings);
+
+        // Anything left out is the default
+        let partial: RenderSettings = serde_json::from_str(r#"{"aa_samples": 2}"#).unwrap();
+        assert_eq!(
+            partial,
+            RenderSettings {
+                aa_samples: 2,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Adaptive sampling: pixels keep getting more rays only while they're still noisy. See
+//! RenderSettings::noise_threshold.
+
+use crate::{
+    canvas::Canvas,
+    colour::Colour,
+    math::{rng::Rng, sampling::Sampler},
+    photon_map::PhotonMap,
+};
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+
+use super::{pixel_seed, RenderMode, Scene};
+
+/// The mean and variance of a stream of numbers, updated one at a time (Welford's method, which
+/// doesn't lose precision the way summing squares does)
+#[derive(Debug, Clone, Copy, Default)]
+struct RunningStats {
+    count: usize,
+    mean: f64,
+    /// Sum of squared differences from the mean
+    m2: f64,
+}
+
+impl RunningStats {
+    fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Of the samples, so divided by count - 1
+    fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// Half the width of the 95% confidence interval for the mean
+    fn confidence(&self) -> f64 {
+        if self.count == 0 {
+            return f64::INFINITY;
+        }
+        1.96 * (self.variance() / self.count as f64).sqrt()
+    }
+}
+
+/// Dark pixels are judged as if they were at least this bright, or the tiniest bit of noise in
+/// them would count as a lot
+const DARKEST: f64 = 0.01;
+
+impl Scene {
+    /// The colour of pixel (x, y), and how many rays that took. Rays are spread across the
+    /// pixel by the sampler and sent in waves of min_samples, and once the 95% confidence interval of the
+    /// pixel's luminance is within noise_threshold of it (or there've been max_samples) it's
+    /// done.
+    pub(super) fn adaptive_pixel(
+        &self,
+        x: usize,
+        y: usize,
+        caustics: Option<&PhotonMap>,
+    ) -> (Colour, usize) {
+        let settings = &self.settings;
+        let mut rng = Rng::new(pixel_seed(settings.seed, x, y));
+        let max = settings.max_samples.max(1);
+        let wave = settings.min_samples.clamp(1, max);
+
+        let sampler = settings.sampler.sampler(max, settings.seed);
+
+        let mut stats = RunningStats::default();
+        let mut total = Colour::BLACK;
+        while stats.count < max {
+            for _ in 0..wave.min(max - stats.count) {
+                let (dx, dy) = sampler.for_pixel(x, y, stats.count).next_2d();
+                let ray = self.camera.ray_for_subpixel(x, y, dx, dy);
+                let colour = match settings.mode {
+                    RenderMode::Whitted => {
+                        self.world.colour_at_with_caustics(ray, settings, caustics)
+                    }
+                    RenderMode::PathTraced { max_bounces, .. } => {
+                        self.world.path_traced_colour_at_clamped(
+                            ray,
+                            max_bounces,
+                            settings.background,
+                            settings.max_sample_radiance,
+                            &mut rng,
+                        )
+                    }
+                };
+                total += colour;
+                stats.push(colour.luminance());
+            }
+            if stats.confidence() <= settings.noise_threshold * stats.mean.max(DARKEST) {
+                break;
+            }
+        }
+        (total / stats.count as f64, stats.count)
+    }
+
+    /// Debug AOV: how many rays each pixel took with adaptive sampling, as a raw (unscaled)
+    /// grey. Renders the whole thing again to find out, on this thread, and without caustics.
+    pub fn render_sample_counts(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.camera.hsize, self.camera.vsize);
+        for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+            let count = self.adaptive_pixel(x, y, None).1 as f64;
+            *pixel = Colour::new(count, count, count);
+        }
+        canvas
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_2;
+
+    use crate::{
+        camera::Camera,
+        math::{
+            float,
+            matrix::Matrix,
+            tuple::{pointi, vectori},
+        },
+        scene::{RenderMode, RenderSettings, Scene},
+        world::World,
+    };
+
+    use super::RunningStats;
+
+    fn scene(min_samples: usize, max_samples: usize, noise_threshold: f64) -> Scene {
+        let camera = Camera::new_with_transform(
+            21,
+            11,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+        Scene::new(World::default(), camera).with_settings(RenderSettings {
+            min_samples,
+            max_samples,
+            noise_threshold,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn running_stats() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let mut stats = RunningStats::default();
+        assert_eq!(stats.confidence(), f64::INFINITY);
+        for v in values {
+            stats.push(v);
+        }
+        assert_eq!(stats.count, 8);
+        assert!(float::equal(stats.mean, 5.0));
+        assert!(float::equal(stats.variance(), 32.0 / 7.0));
+        assert!(float::equal(
+            stats.confidence(),
+            1.96 * (32.0_f64 / 7.0 / 8.0).sqrt()
+        ));
+    }
+
+    #[test]
+    fn flat_stops_early() {
+        let scene = scene(4, 64, 0.05);
+        let counts = scene.render_sample_counts();
+        assert_eq!((counts.width, counts.height), (21, 11));
+        for pixel in counts.iter() {
+            assert!((4.0..=64.0).contains(&pixel.red), "{pixel:?}");
+            assert_eq!(pixel.red.fract(), 0.0);
+        }
+        // Plain background in the corners
+        assert_eq!(counts[(0, 0)].red, 4.0);
+        assert_eq!(counts[(20, 10)].red, 4.0);
+
+        // Along the edge of the sphere some of the rays miss, so it takes a lot more to settle
+        let edge = (0..21).map(|x| counts[(x, 5)].red).fold(0.0, f64::max);
+        assert!(edge >= 16.0, "{edge}");
+    }
+
+    #[test]
+    fn matches_fixed_sampling() {
+        let threshold = 0.05;
+        let adaptive = scene(4, 64, threshold).render();
+        let fixed = scene(64, 64, threshold).render();
+        let mut outside = 0;
+        for (a, f) in adaptive.iter().zip(fixed.iter()) {
+            let (a, f) = (a.luminance(), f.luminance());
+            if (a - f).abs() > 2.0 * threshold * f.max(0.01) {
+                outside += 1;
+            }
+        }
+        // It's a 95% confidence interval, so now and then it's unlucky
+        assert!(outside <= 21 * 11 / 20, "{outside}");
+
+        // The same again, on any number of threads
+        let threaded = scene(4, 64, threshold).with_settings(RenderSettings {
+            threads: 3,
+            ..scene(4, 64, threshold).settings
+        });
+        assert!(threaded
+            .render()
+            .iter()
+            .zip(adaptive.iter())
+            .all(|(a, b)| a.red.to_bits() == b.red.to_bits()));
+    }
+
+    #[test]
+    fn path_traced() {
+        let base = scene(4, 16, 0.05);
+        let scene = Scene {
+            settings: RenderSettings {
+                mode: RenderMode::PathTraced {
+                    samples_per_pixel: 1,
+                    max_bounces: 2,
+                },
+                ..base.settings
+            },
+            ..base
+        };
+        // Every bounce off the sphere is random, so it's noisy all over, but the background
+        // still isn't
+        let counts = scene.render_sample_counts();
+        assert_eq!(counts[(0, 0)].red, 4.0);
+        assert!(counts.iter().any(|c| c.red == 16.0));
+        assert!(counts.iter().all(|c| (4.0..=16.0).contains(&c.red)));
+    }
+}
@@ -0,0 +1,914 @@
+//! Reading scene files and building Scenes out of them.
+
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use crate::{
+    camera::{Camera, CameraConfig},
+    colour::Colour,
+    error::RayTracerError,
+    lights::{Light, PointLight},
+    materials::{Material, MaterialBuilder},
+    math::{
+        matrix::{Matrix, IDENTITY_4X4},
+        tuple::{point, vector},
+    },
+    pattern::{solid::SolidPattern, stripe::StripePattern},
+    shape::{plane::Plane, sphere::Sphere, Shape},
+    world::World,
+};
+
+use super::{
+    validate::{SceneIssue, Severity},
+    yaml::{self, Node, Value},
+    Scene,
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    /// Not even YAML
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// Readable, but with errors, see scene::validate. Only the errors, not the warnings.
+    Invalid(Vec<SceneIssue>),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "reading scene: {e}"),
+            SceneError::Syntax { line, message } => write!(f, "scene line {line}: {message}"),
+            SceneError::Invalid(issues) => {
+                write!(f, "scene has {} error(s)", issues.len())?;
+                for issue in issues {
+                    write!(f, "\n  {issue}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(value: io::Error) -> Self {
+        SceneError::Io(value)
+    }
+}
+
+/// A scene file that's been read but not built. They're YAML, in the format from the book's
+/// bonus chapters (or the same thing as JSON): a list of items, each either something to `add`
+/// or a named value to `define` for later ones to use.
+///
+/// ```yaml
+/// - add: camera
+///   width: 400
+///   height: 200
+///   field-of-view: 1.047   # radians
+///   from: [0, 1.5, -5]
+///   to: [0, 1, 0]
+///   up: [0, 1, 0]
+///
+/// - add: light
+///   at: [-10, 10, -10]
+///   intensity: [1, 1, 1]   # or "#ffffff", "white", ...
+///
+/// - define: shiny
+///   value:
+///     color: [1, 0.2, 1]
+///     specular: 0.9
+///
+/// - define: shiny-green
+///   extend: shiny
+///   value:
+///     color: green
+///
+/// - add: sphere
+///   material: shiny-green
+///   transform:
+///     - [scale, 0.5, 0.5, 0.5]
+///     - [translate, 0, 1, 0]
+/// ```
+///
+/// Shapes are `sphere` and `plane`. Materials take color (or colour), ambient, diffuse,
+/// specular, shininess, reflective, transparency, refractive-index and a stripes pattern:
+/// `pattern: {type: stripes, colors: [white, black]}`. Transforms are lists of translate,
+/// scale, rotate-x, rotate-y, rotate-z and shear, applied first to last, and can include the
+/// names of defined transforms. A shape without a material gets the `default-material` define,
+/// if there is one. `material: glass` and `material: mirror` are Material's presets, unless a
+/// define has taken the name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneDescription {
+    root: Node,
+}
+
+impl SceneDescription {
+    pub fn parse(text: &str) -> Result<Self, SceneError> {
+        let root = yaml::parse(text).map_err(|e| SceneError::Syntax {
+            line: e.line,
+            message: e.message,
+        })?;
+        Ok(Self { root })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// The scene, unless scene::validate finds any errors. Warnings don't stop it.
+    pub fn to_scene(&self) -> Result<Scene, SceneError> {
+        let (scene, issues) = self.interpret();
+        match scene {
+            Some(scene) => Ok(scene),
+            None => Err(SceneError::Invalid(
+                issues
+                    .into_iter()
+                    .filter(|i| i.severity == Severity::Error)
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Builds the scene and checks it in one go, so the two can't disagree. The scene is only
+    /// there if nothing's an error.
+    pub(super) fn interpret(&self) -> (Option<Scene>, Vec<SceneIssue>) {
+        let mut interpreter = Interpreter::default();
+        interpreter.items(&self.root);
+
+        let Interpreter {
+            mut issues,
+            had_camera,
+            camera,
+            objects,
+            lights,
+            ..
+        } = interpreter;
+        if !had_camera {
+            issues.push(SceneIssue {
+                severity: Severity::Error,
+                line: None,
+                path: String::new(),
+                message: "there's no camera, add one with `- add: camera`".into(),
+            });
+        }
+
+        issues.sort_by_key(|i| i.line.unwrap_or(usize::MAX));
+        let scene = match camera {
+            Some((camera, _)) if !issues.iter().any(|i| i.severity == Severity::Error) => {
+                Some(Scene::new(World::new(objects, lights), camera))
+            }
+            _ => None,
+        };
+        (scene, issues)
+    }
+}
+
+#[derive(Default)]
+struct Interpreter {
+    /// Defines so far, with any `extend` already merged in
+    defines: HashMap<String, Node>,
+    issues: Vec<SceneIssue>,
+    /// While above zero, issues aren't recorded. Defines are checked once where they're
+    /// defined, not again everywhere they're used.
+    quiet: usize,
+    /// Even if it wasn't a usable one
+    had_camera: bool,
+    camera: Option<(Camera, usize)>,
+    objects: Vec<Box<dyn Shape>>,
+    lights: Vec<Box<dyn Light>>,
+}
+
+/// Every key in `entries` that isn't one of these
+fn unknown_keys<'a>(
+    entries: &'a [(String, Node)],
+    known: &'a [&str],
+) -> impl Iterator<Item = &'a (String, Node)> {
+    entries
+        .iter()
+        .filter(|(key, _)| !known.contains(&key.as_str()))
+}
+
+fn get<'a>(entries: &'a [(String, Node)], key: &str) -> Option<&'a Node> {
+    entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+impl Interpreter {
+    fn issue(&mut self, severity: Severity, line: usize, path: &str, message: impl Into<String>) {
+        if self.quiet == 0 {
+            self.issues.push(SceneIssue {
+                severity,
+                line: Some(line),
+                path: path.to_owned(),
+                message: message.into(),
+            });
+        }
+    }
+
+    fn error(&mut self, line: usize, path: &str, message: impl Into<String>) {
+        self.issue(Severity::Error, line, path, message);
+    }
+
+    fn warn_unknown(&mut self, entries: &[(String, Node)], path: &str, known: &[&str]) {
+        for (key, node) in unknown_keys(entries, known) {
+            let message = format!("unknown key `{key}`, ignored");
+            self.issue(Severity::Warning, node.line, &join(path, key), message);
+        }
+    }
+
+    fn items(&mut self, root: &Node) {
+        let items = match &root.value {
+            Value::List(items) => items,
+            Value::Null => return,
+            _ => {
+                let message = "a scene is a list of `add:` and `define:` items";
+                return self.error(root.line, "", message);
+            }
+        };
+
+        for item in items {
+            let Value::Map(entries) = &item.value else {
+                self.error(item.line, "", "expected `add:` or `define:` here");
+                continue;
+            };
+            match (get(entries, "add"), get(entries, "define")) {
+                (Some(_), Some(_)) => {
+                    self.error(item.line, "", "an item can't both `add` and `define`")
+                }
+                (Some(add), None) => self.add(add, entries),
+                (None, Some(define)) => self.define(define, entries),
+                (None, None) => self.error(item.line, "", "expected `add:` or `define:` here"),
+            }
+        }
+    }
+
+    fn add(&mut self, add: &Node, entries: &[(String, Node)]) {
+        let Value::String(kind) = &add.value else {
+            return self.error(add.line, "add", "`add` needs the name of what to add");
+        };
+        match kind.as_str() {
+            "camera" => {
+                self.had_camera = true;
+                self.camera(add.line, entries)
+            }
+            "light" => self.light(add.line, entries),
+            "sphere" | "plane" => self.shape(kind, entries),
+            _ => self.error(
+                add.line,
+                kind,
+                format!("can't add `{kind}`, only camera, light, sphere or plane"),
+            ),
+        }
+    }
+
+    fn define(&mut self, define: &Node, entries: &[(String, Node)]) {
+        let Value::String(name) = &define.value else {
+            return self.error(define.line, "define", "`define` needs a name");
+        };
+        self.warn_unknown(entries, name, &["define", "value", "extend"]);
+        let Some(value) = get(entries, "value") else {
+            return self.error(define.line, name, "a define needs a `value`");
+        };
+
+        let value = match get(entries, "extend") {
+            None => value.clone(),
+            Some(Node {
+                value: Value::String(base_name),
+                line,
+            }) => {
+                let path = join(name, "extend");
+                let Some(base) = self.lookup(base_name, *line, &path).cloned() else {
+                    return;
+                };
+                let kinds = (base.kind(), value.kind());
+                match (base.value, &value.value) {
+                    // Anything in `value` replaces what's in the base
+                    (Value::Map(mut merged), Value::Map(entries)) => {
+                        merged.retain(|(k, _)| get(entries, k).is_none());
+                        merged.extend(entries.iter().cloned());
+                        Node {
+                            value: Value::Map(merged),
+                            line: value.line,
+                        }
+                    }
+                    (Value::List(mut merged), Value::List(items)) => {
+                        merged.extend(items.iter().cloned());
+                        Node {
+                            value: Value::List(merged),
+                            line: value.line,
+                        }
+                    }
+                    _ => {
+                        let message =
+                            format!("`{base_name}` is {} and this is {}", kinds.0, kinds.1);
+                        return self.error(*line, &path, message);
+                    }
+                }
+            }
+            Some(other) => {
+                let message = "`extend` needs the name of a define";
+                return self.error(other.line, &join(name, "extend"), message);
+            }
+        };
+
+        // Checked here, once, as whatever it looks like
+        match &value.value {
+            Value::Map(_) => {
+                self.material(&value, name);
+            }
+            Value::List(_) => {
+                self.transform(&value, name);
+            }
+            _ => {}
+        }
+        self.defines.insert(name.clone(), value);
+    }
+
+    /// A define by name, which has to be defined before it's used
+    fn lookup(&mut self, name: &str, line: usize, path: &str) -> Option<&Node> {
+        if !self.defines.contains_key(name) {
+            self.error(line, path, format!("`{name}` isn't defined (yet)"));
+        }
+        self.defines.get(name)
+    }
+
+    fn camera(&mut self, line: usize, entries: &[(String, Node)]) {
+        let path = "camera";
+        let keys = [
+            "add",
+            "width",
+            "height",
+            "field-of-view",
+            "from",
+            "to",
+            "up",
+        ];
+        self.warn_unknown(entries, path, &keys);
+
+        let required = |this: &mut Self, key: &str| {
+            let node = get(entries, key);
+            if node.is_none() {
+                this.error(line, path, format!("the camera needs `{key}`"));
+            }
+            node
+        };
+        let width = required(self, "width").and_then(|n| self.size(n, "camera.width"));
+        let height = required(self, "height").and_then(|n| self.size(n, "camera.height"));
+        let fov =
+            required(self, "field-of-view").and_then(|n| self.number(n, "camera.field-of-view"));
+        let from = required(self, "from").and_then(|n| self.triple(n, "camera.from"));
+        let to = required(self, "to").and_then(|n| self.triple(n, "camera.to"));
+        let up = required(self, "up").and_then(|n| self.triple(n, "camera.up"));
+        let (
+            Some(width),
+            Some(height),
+            Some(fov),
+            Some([fx, fy, fz]),
+            Some([tx, ty, tz]),
+            Some([ux, uy, uz]),
+        ) = (width, height, fov, from, to, up)
+        else {
+            return;
+        };
+
+        let config = CameraConfig {
+            width,
+            height,
+            fov,
+            from: point(fx, fy, fz),
+            to: point(tx, ty, tz),
+            up: vector(ux, uy, uz),
+        };
+        match Camera::from_config(&config) {
+            Ok(camera) => {
+                if let Some((_, first)) = self.camera {
+                    let message = format!("replaces the camera on line {first}");
+                    self.issue(Severity::Warning, line, path, message);
+                }
+                self.camera = Some((camera, line));
+            }
+            Err(RayTracerError::InvalidCamera { reason }) => self.error(line, path, reason),
+            Err(e) => self.error(line, path, e.to_string()),
+        }
+    }
+
+    fn light(&mut self, line: usize, entries: &[(String, Node)]) {
+        let path = "light";
+        self.warn_unknown(entries, path, &["add", "at", "intensity"]);
+
+        let at = match get(entries, "at") {
+            Some(node) => self.triple(node, "light.at"),
+            None => {
+                self.error(line, path, "the light needs `at`");
+                None
+            }
+        };
+        let intensity = match get(entries, "intensity") {
+            Some(node) => {
+                let intensity = self.colour(node, "light.intensity");
+                if intensity.is_some_and(|i| i.max_channel() <= 0.0) {
+                    let message = "intensity is zero, so it won't light anything";
+                    self.issue(Severity::Warning, node.line, "light.intensity", message);
+                }
+                intensity
+            }
+            None => {
+                self.error(line, path, "the light needs `intensity`");
+                None
+            }
+        };
+
+        if let (Some([x, y, z]), Some(intensity)) = (at, intensity) {
+            self.lights
+                .push(PointLight::new_boxed(intensity, point(x, y, z)));
+        }
+    }
+
+    fn shape(&mut self, kind: &str, entries: &[(String, Node)]) {
+        self.warn_unknown(entries, kind, &["add", "material", "transform"]);
+        let line = get(entries, "add").map_or(0, |n| n.line);
+
+        let material = match get(entries, "material") {
+            Some(node) => self.material(node, &join(kind, "material")),
+            None => match self.defines.get("default-material").cloned() {
+                Some(default) => {
+                    self.quiet += 1;
+                    let material = self.material(&default, "default-material");
+                    self.quiet -= 1;
+                    material
+                }
+                None => {
+                    let message = "no material, and no `default-material` defined to fall back on";
+                    self.issue(Severity::Warning, line, kind, message);
+                    Some(Material::default())
+                }
+            },
+        };
+        let transform = match get(entries, "transform") {
+            Some(node) => self.transform(node, &join(kind, "transform")),
+            None => Some(IDENTITY_4X4.clone()),
+        };
+
+        if let (Some(material), Some(transform)) = (material, transform) {
+            self.objects.push(match kind {
+                "sphere" => Box::new(Sphere::new(transform, material)),
+                _ => Box::new(Plane::new(transform, material)),
+            });
+        }
+    }
+
+    /// Either inline, the name of a define, or `glass` or `mirror` for those presets unless
+    /// they've been defined as something else
+    fn material(&mut self, node: &Node, path: &str) -> Option<Material> {
+        let entries = match &node.value {
+            Value::Map(entries) => entries,
+            Value::String(name) => {
+                if !self.defines.contains_key(name) {
+                    match name.as_str() {
+                        "glass" => return Some(Material::glass()),
+                        "mirror" => return Some(Material::mirror()),
+                        _ => {}
+                    }
+                }
+                let define = self.lookup(name, node.line, path)?.clone();
+                self.quiet += 1;
+                let material = self.material(&define, name);
+                self.quiet -= 1;
+                return material;
+            }
+            _ => {
+                let message = format!("expected a material or its name, got {}", node.kind());
+                self.error(node.line, path, message);
+                return None;
+            }
+        };
+
+        let keys = [
+            "color",
+            "colour",
+            "ambient",
+            "diffuse",
+            "specular",
+            "shininess",
+            "reflective",
+            "transparency",
+            "refractive-index",
+            "pattern",
+        ];
+        self.warn_unknown(entries, path, &keys);
+
+        // Each number goes through MaterialBuilder on its own, so every bad one is reported
+        let mut material = Some(Material::default());
+        let mut set = |this: &mut Self,
+                       key: &str,
+                       check: fn(MaterialBuilder, f64) -> MaterialBuilder,
+                       field: fn(&mut Material) -> &mut f64| {
+            let Some(node) = get(entries, key) else {
+                return;
+            };
+            let path = join(path, key);
+            let value = this.number(node, &path).filter(|v| {
+                let checked = check(Material::builder(), *v).build();
+                if let Err(e) = &checked {
+                    this.error(node.line, &path, e.to_string());
+                }
+                checked.is_ok()
+            });
+            match (value, &mut material) {
+                (Some(value), Some(material)) => *field(material) = value,
+                _ => material = None,
+            }
+        };
+        set(self, "ambient", MaterialBuilder::ambient, |m| {
+            &mut m.ambient
+        });
+        set(self, "diffuse", MaterialBuilder::diffuse, |m| {
+            &mut m.diffuse
+        });
+        set(self, "specular", MaterialBuilder::specular, |m| {
+            &mut m.specular
+        });
+        set(self, "shininess", MaterialBuilder::shininess, |m| {
+            &mut m.shininess
+        });
+        set(self, "reflective", MaterialBuilder::reflective, |m| {
+            &mut m.reflective
+        });
+        set(self, "transparency", MaterialBuilder::transparency, |m| {
+            &mut m.transparency
+        });
+        set(
+            self,
+            "refractive-index",
+            MaterialBuilder::refractive_index,
+            |m| &mut m.refractive_index,
+        );
+
+        for key in ["color", "colour"] {
+            if let Some(node) = get(entries, key) {
+                match (self.colour(node, &join(path, key)), &mut material) {
+                    (Some(colour), Some(material)) => {
+                        material.pattern = Box::new(SolidPattern(colour))
+                    }
+                    _ => material = None,
+                }
+            }
+        }
+        if let Some(node) = get(entries, "pattern") {
+            match (self.pattern(node, &join(path, "pattern")), &mut material) {
+                (Some(pattern), Some(material)) => material.pattern = Box::new(pattern),
+                _ => material = None,
+            }
+        }
+        material
+    }
+
+    fn pattern(&mut self, node: &Node, path: &str) -> Option<StripePattern> {
+        let Value::Map(entries) = &node.value else {
+            let message = format!("expected a pattern, got {}", node.kind());
+            self.error(node.line, path, message);
+            return None;
+        };
+        self.warn_unknown(entries, path, &["type", "colors", "colours"]);
+
+        match get(entries, "type").map(|n| &n.value) {
+            Some(Value::String(kind)) if kind == "stripes" => {}
+            Some(Value::String(kind)) => {
+                let message = format!("unknown pattern `{kind}`, only stripes so far");
+                self.error(node.line, path, message);
+                return None;
+            }
+            _ => {
+                self.error(node.line, path, "the pattern needs a `type`");
+                return None;
+            }
+        }
+        let colours = get(entries, "colors").or_else(|| get(entries, "colours"));
+        let Some(Node {
+            value: Value::List(colours),
+            line,
+        }) = colours
+        else {
+            self.error(node.line, path, "stripes need a list of two `colors`");
+            return None;
+        };
+        let path = join(path, "colors");
+        if colours.len() != 2 {
+            self.error(*line, &path, "stripes need exactly two colours");
+            return None;
+        }
+        let a = self.colour(&colours[0], &path);
+        let b = self.colour(&colours[1], &path);
+        Some(StripePattern::new(a?, b?))
+    }
+
+    /// A list of operations and names of defined lists of them, first applied first
+    fn transform(&mut self, node: &Node, path: &str) -> Option<Matrix> {
+        let matrix = self.operations(node, path)?;
+        if !matrix.is_finite() || !matrix.can_invert() {
+            let message = "isn't invertible, so nothing can be seen through it";
+            self.error(node.line, path, message);
+            return None;
+        }
+        Some(matrix)
+    }
+
+    fn operations(&mut self, node: &Node, path: &str) -> Option<Matrix> {
+        let Value::List(operations) = &node.value else {
+            let message = format!("expected a list of transforms, got {}", node.kind());
+            self.error(node.line, path, message);
+            return None;
+        };
+
+        let mut matrix = Some(IDENTITY_4X4.clone());
+        for operation in operations {
+            let next = match &operation.value {
+                Value::String(name) => {
+                    let define = self.lookup(name, operation.line, path).cloned();
+                    self.quiet += 1;
+                    let next = define.and_then(|define| self.operations(&define, name));
+                    self.quiet -= 1;
+                    next
+                }
+                Value::List(parts) => self.operation(parts, operation.line, path),
+                _ => {
+                    let message = "expected something like `[translate, 1, 2, 3]`";
+                    self.error(operation.line, path, message);
+                    None
+                }
+            };
+            matrix = match (matrix, next) {
+                (Some(matrix), Some(next)) => Some(next * matrix),
+                _ => None,
+            };
+        }
+        matrix
+    }
+
+    fn operation(&mut self, parts: &[Node], line: usize, path: &str) -> Option<Matrix> {
+        let Some(Value::String(name)) = parts.first().map(|n| &n.value) else {
+            self.error(
+                line,
+                path,
+                "a transform starts with what it is, like translate",
+            );
+            return None;
+        };
+        let mut args = vec![];
+        for part in &parts[1..] {
+            args.push(self.number(part, path)?);
+        }
+
+        let wanted = match name.as_str() {
+            "translate" | "scale" => 3,
+            "rotate-x" | "rotate-y" | "rotate-z" => 1,
+            "shear" => 6,
+            _ => {
+                let message = format!(
+                    "unknown transform `{name}`, can be translate, scale, rotate-x, rotate-y, \
+                     rotate-z or shear"
+                );
+                self.error(line, path, message);
+                return None;
+            }
+        };
+        if args.len() != wanted {
+            let message = format!("{name} takes {wanted} numbers, got {}", args.len());
+            self.error(line, path, message);
+            return None;
+        }
+        Some(match (name.as_str(), &args[..]) {
+            ("translate", &[x, y, z]) => Matrix::translation(x, y, z),
+            ("scale", &[x, y, z]) => Matrix::scaling(x, y, z),
+            ("rotate-x", &[r]) => Matrix::rotation_x(r),
+            ("rotate-y", &[r]) => Matrix::rotation_y(r),
+            ("rotate-z", &[r]) => Matrix::rotation_z(r),
+            (_, &[xy, xz, yx, yz, zx, zy]) => Matrix::shearing(xy, xz, yx, yz, zx, zy),
+            _ => unreachable!("argument count checked above"),
+        })
+    }
+
+    fn number(&mut self, node: &Node, path: &str) -> Option<f64> {
+        match node.value {
+            Value::Number(n) if n.is_finite() => Some(n),
+            _ => {
+                let message = format!("expected a number, got {}", node.kind());
+                self.error(node.line, path, message);
+                None
+            }
+        }
+    }
+
+    fn size(&mut self, node: &Node, path: &str) -> Option<usize> {
+        match node.value {
+            Value::Number(n) if n >= 1.0 && n.fract() == 0.0 && n <= u32::MAX as f64 => {
+                Some(n as usize)
+            }
+            _ => {
+                let message = "expected a whole number above 0";
+                self.error(node.line, path, message);
+                None
+            }
+        }
+    }
+
+    fn triple(&mut self, node: &Node, path: &str) -> Option<[f64; 3]> {
+        if let Value::List(items) = &node.value {
+            if items.len() == 3 {
+                let [x, y, z] = [0, 1, 2].map(|i| self.number(&items[i], path));
+                return Some([x?, y?, z?]);
+            }
+        }
+        let message = format!("expected a list of three numbers, got {}", node.kind());
+        self.error(node.line, path, message);
+        None
+    }
+
+    /// `[r, g, b]`, or anything Colour's FromStr takes
+    fn colour(&mut self, node: &Node, path: &str) -> Option<Colour> {
+        if let Value::String(s) = &node.value {
+            return s
+                .parse()
+                .map_err(|e: String| self.error(node.line, path, e))
+                .ok();
+        }
+        self.triple(node, path)
+            .map(|[r, g, b]| Colour::new(r, g, b))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        lights::PointLight,
+        materials::Material,
+        math::{matrix::Matrix, tuple::point},
+        pattern::{solid::SolidPattern, stripe::StripePattern},
+        shape::{plane::Plane, sphere::Sphere},
+    };
+
+    use super::{SceneDescription, SceneError};
+
+    #[test]
+    fn builds_the_example() {
+        let scene = SceneDescription::parse(include_str!("../../scenes/example.yaml"))
+            .unwrap()
+            .to_scene()
+            .unwrap();
+
+        assert_eq!((scene.camera.hsize, scene.camera.vsize), (400, 200));
+        assert_eq!(scene.camera.fov, 1.047);
+        let light = scene.world.lights().next().unwrap();
+        assert_eq!(
+            light.downcast_ref::<PointLight>().unwrap().position,
+            point(-10.0, 10.0, -10.0)
+        );
+        assert_eq!(*light.intensity(), Colour::WHITE);
+
+        let objects: Vec<_> = scene.world.objects().collect();
+        assert_eq!(objects.len(), 4);
+
+        // Shapes with no material get default-material
+        let default = Material {
+            pattern: Box::new(SolidPattern(Colour::new(1.0, 0.9, 0.9))),
+            specular: 0.0,
+            ..Default::default()
+        };
+        let floor = objects[0].downcast_ref::<Plane>().unwrap();
+        assert_eq!(
+            floor.material,
+            Material {
+                pattern: Box::new(StripePattern::new(
+                    Colour::WHITE,
+                    Colour::new(0.6, 0.6, 0.6)
+                )),
+                ..Default::default()
+            }
+        );
+        assert_eq!(*objects[3].material(), default);
+
+        // extend keeps the base's values
+        let middle = objects[1].downcast_ref::<Sphere>().unwrap();
+        assert_eq!(
+            middle.material,
+            Material {
+                pattern: Box::new(SolidPattern(Colour::new(0.1, 1.0, 0.5))),
+                diffuse: 0.7,
+                specular: 0.3,
+                ..Default::default()
+            }
+        );
+        assert_eq!(middle.transform, Matrix::translation(-0.5, 1.0, 0.5));
+
+        // Transforms go first to last, defines included
+        assert_eq!(
+            *objects[3].transform(),
+            Matrix::translation(-1.5, 0.33, -0.75)
+                * Matrix::scaling(0.66, 0.66, 0.66)
+                * Matrix::scaling(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn json() {
+        let json = r#"[
+            {"add": "camera", "width": 8, "height": 4, "field-of-view": 1,
+             "from": [0, 0, -5], "to": [0, 0, 0], "up": [0, 1, 0]},
+            {"add": "light", "at": [0, 5, -5], "intensity": [1, 1, 1]},
+            {"add": "sphere", "material": {"color": [1, 0, 0]},
+             "transform": [["rotate-y", 1], ["shear", 1, 0, 0, 0, 0, 0]]}
+        ]"#;
+        let scene = SceneDescription::parse(json).unwrap().to_scene().unwrap();
+        let sphere = scene.world.object(0).unwrap();
+        assert_eq!(sphere.material().colour(), Some(Colour::RED));
+        assert_eq!(
+            *sphere.transform(),
+            Matrix::shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0) * Matrix::rotation_y(1.0)
+        );
+        assert_eq!(scene.render().width, 8);
+    }
+
+    #[test]
+    fn material_presets() {
+        let scene = |items: &str| {
+            let json = format!(
+                r#"[
+                {{"add": "camera", "width": 4, "height": 4, "field-of-view": 1,
+                 "from": [0, 0, -5], "to": [0, 0, 0], "up": [0, 1, 0]}},
+                {{"add": "light", "at": [0, 5, -5], "intensity": [1, 1, 1]}},
+                {items}
+            ]"#
+            );
+            SceneDescription::parse(&json).unwrap().to_scene()
+        };
+
+        for (name, preset) in [("glass", Material::glass()), ("mirror", Material::mirror())] {
+            let found = scene(&format!(r#"{{"add": "sphere", "material": "{name}"}}"#)).unwrap();
+            assert_eq!(*found.world.object(0).unwrap().material(), preset, "{name}");
+        }
+
+        // A define of the same name wins
+        let redefined = scene(
+            r#"{"define": "glass", "value": {"color": [1, 0, 0]}},
+               {"add": "sphere", "material": "glass"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            redefined.world.object(0).unwrap().material().colour(),
+            Some(Colour::RED)
+        );
+
+        let unknown = scene(r#"{"add": "sphere", "material": "plastic"}"#)
+            .err()
+            .unwrap();
+        assert!(
+            unknown.to_string().contains("`plastic` isn't defined"),
+            "{unknown}"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let invalid = SceneDescription::parse(include_str!("../../scenes/lint_issues.yaml"))
+            .unwrap()
+            .to_scene()
+            .err()
+            .unwrap();
+        // Only the errors
+        let SceneError::Invalid(issues) = &invalid else {
+            panic!("{invalid}")
+        };
+        assert_eq!(issues.len(), 4);
+        assert!(invalid.to_string().starts_with(
+            "scene has 4 error(s)\n  line 3: error: camera: from and to must be different\n"
+        ));
+
+        let syntax = SceneDescription::parse("- a: [1, 2").err().unwrap();
+        assert_eq!(
+            syntax.to_string(),
+            "scene line 1: the file ends in the middle of a value"
+        );
+        assert!(matches!(
+            SceneDescription::load("no/such/scene.yaml"),
+            Err(SceneError::Io(_))
+        ));
+    }
+}
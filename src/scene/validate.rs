@@ -0,0 +1,190 @@
+//! Finding the mistakes in a scene file before anything's rendered.
+
+use std::fmt;
+
+use super::description::SceneDescription;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Renders, but probably not how it was meant to
+    Warning,
+    /// Won't render at all
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// One thing validate found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneIssue {
+    pub severity: Severity,
+    /// Counting from 1, None when it's about the whole file
+    pub line: Option<usize>,
+    /// Where in the item it's about, like `sphere.material.diffuse` (or `name.diffuse` inside
+    /// a define). Empty for the whole item or file.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SceneIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        write!(f, "{}: ", self.severity)?;
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Everything wrong with a scene file, by line. Errors are what SceneDescription::to_scene
+/// would fail on: transforms that can't be inverted, materials out of range, unusable cameras,
+/// names used without being defined, and anything of the wrong type or missing. Warnings are
+/// unknown keys (typos, usually), lights too dim to see and shapes left with the default
+/// material.
+pub fn validate(description: &SceneDescription) -> Vec<SceneIssue> {
+    description.interpret().1
+}
+
+#[cfg(test)]
+mod test {
+    use crate::scene::SceneDescription;
+
+    use super::{validate, SceneIssue, Severity};
+
+    fn issue(severity: Severity, line: usize, path: &str, message: &str) -> SceneIssue {
+        SceneIssue {
+            severity,
+            line: Some(line),
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    #[test]
+    fn one_of_each() {
+        let description =
+            SceneDescription::parse(include_str!("../../scenes/lint_issues.yaml")).unwrap();
+
+        use Severity::*;
+        assert_eq!(
+            validate(&description),
+            [
+                issue(Error, 3, "camera", "from and to must be different"),
+                issue(
+                    Warning,
+                    13,
+                    "light.intensity",
+                    "intensity is zero, so it won't light anything"
+                ),
+                issue(
+                    Error,
+                    18,
+                    "glowing.diffuse",
+                    "material diffuse must be in [0, 1], got 1.5"
+                ),
+                issue(
+                    Error,
+                    22,
+                    "sphere.transform",
+                    "isn't invertible, so nothing can be seen through it"
+                ),
+                issue(
+                    Warning,
+                    25,
+                    "plane",
+                    "no material, and no `default-material` defined to fall back on"
+                ),
+                issue(Warning, 26, "plane.shiny", "unknown key `shiny`, ignored"),
+                issue(Error, 29, "sphere.material", "`shiny` isn't defined (yet)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn clean() {
+        let description =
+            SceneDescription::parse(include_str!("../../scenes/example.yaml")).unwrap();
+        assert_eq!(validate(&description), []);
+    }
+
+    #[test]
+    fn whole_file() {
+        let issues = validate(&SceneDescription::parse("").unwrap());
+        assert_eq!(
+            issues,
+            [SceneIssue {
+                severity: Severity::Error,
+                line: None,
+                path: String::new(),
+                message: "there's no camera, add one with `- add: camera`".into()
+            }]
+        );
+        assert_eq!(
+            issues[0].to_string(),
+            "error: there's no camera, add one with `- add: camera`"
+        );
+
+        let issues = validate(&SceneDescription::parse("add: camera").unwrap());
+        assert_eq!(
+            issues[0].to_string(),
+            "line 1: error: a scene is a list of `add:` and `define:` items"
+        );
+    }
+
+    #[test]
+    fn types_and_names() {
+        let yaml = "\
+- add: camera
+  width: 10.5
+  height: 10
+  field-of-view: [1]
+  from: [0, 0]
+  to: [0, 0, 0]
+- add: cube
+- define: a
+  extend: nope
+  value: {}
+- add: sphere
+  transform:
+    - [spin, 1]
+    - [translate, 1]
+    - nope
+  material:
+    colour: not-a-colour
+    pattern: {type: checkers}
+";
+        let issues: Vec<_> = validate(&SceneDescription::parse(yaml).unwrap())
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "line 1: error: camera: the camera needs `up`",
+                "line 2: error: camera.width: expected a whole number above 0",
+                "line 4: error: camera.field-of-view: expected a number, got a list",
+                "line 5: error: camera.from: expected a list of three numbers, got a list",
+                "line 7: error: cube: can't add `cube`, only camera, light, sphere or plane",
+                "line 9: error: a.extend: `nope` isn't defined (yet)",
+                "line 13: error: sphere.transform: unknown transform `spin`, can be translate, \
+                 scale, rotate-x, rotate-y, rotate-z or shear",
+                "line 14: error: sphere.transform: translate takes 3 numbers, got 1",
+                "line 15: error: sphere.transform: `nope` isn't defined (yet)",
+                "line 17: error: sphere.material.colour: unknown colour \"not-a-colour\", \
+                 expected a name, #rrggbb or r, g, b",
+                "line 18: error: sphere.material.pattern: unknown pattern `checkers`, only \
+                 stripes so far",
+            ]
+        );
+    }
+}
@@ -0,0 +1,629 @@
+//! Just enough YAML for scene files: block mappings and lists, flow `[...]` and `{...}` (over as
+//! many lines as they like, so plain JSON reads too), quoted and plain scalars and `#` comments.
+//! No anchors, tags, multi-line strings or multiple documents.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Node>),
+    /// In the order they're in the file
+    Map(Vec<(String, Node)>),
+}
+
+/// A value and the line it's on, counting from 1. Map values are on their key's line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub value: Value,
+    pub line: usize,
+}
+
+impl Node {
+    /// What sort of value this is, for error messages
+    pub fn kind(&self) -> &'static str {
+        match self.value {
+            Value::Null => "nothing",
+            Value::Bool(_) => "true/false",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::List(_) => "a list",
+            Value::Map(_) => "a map",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Node, SyntaxError> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        at: 0,
+        line: 1,
+        line_start: 0,
+    };
+
+    parser.skip_blank();
+    if parser.peek().is_none() {
+        return Ok(Node {
+            value: Value::Null,
+            line: 1,
+        });
+    }
+    let root = parser.block(parser.column())?;
+    parser.skip_blank();
+    if parser.peek().is_some() {
+        return Err(parser.error("expected the end of the file, check the indentation"));
+    }
+    Ok(root)
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    at: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.at).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.text.get(self.at + offset).copied()
+    }
+
+    /// Everything moves forward through here, to keep count of lines
+    fn bump(&mut self) {
+        if self.peek() == Some(b'\n') {
+            self.line += 1;
+            self.line_start = self.at + 1;
+        }
+        self.at += 1;
+    }
+
+    fn column(&self) -> usize {
+        self.at - self.line_start
+    }
+
+    fn error(&self, message: impl Into<String>) -> SyntaxError {
+        SyntaxError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn skip_space(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\r')) {
+            self.bump();
+        }
+    }
+
+    /// Spaces, newlines and comments
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\r' | b'\n') => self.bump(),
+                Some(b'#') => {
+                    while self.peek().is_some_and(|b| b != b'\n') {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Past the end of the line, which can only have a comment left on it
+    fn end_line(&mut self) -> Result<(), SyntaxError> {
+        self.skip_space();
+        match self.peek() {
+            None | Some(b'\n' | b'#') => {
+                self.skip_blank();
+                Ok(())
+            }
+            Some(b) => Err(self.error(format!("unexpected {:?}", b as char))),
+        }
+    }
+
+    fn at_line_end(&self) -> bool {
+        matches!(self.peek(), None | Some(b'\n' | b'\r' | b'#'))
+    }
+
+    fn starts_list_item(&self) -> bool {
+        self.peek() == Some(b'-') && matches!(self.peek_at(1), None | Some(b' ' | b'\n' | b'\r'))
+    }
+
+    /// Whether the rest of the line is `key: ...`
+    fn starts_key(&self) -> bool {
+        let rest = &self.text[self.at..];
+        let rest = &rest[..rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len())];
+        if let Some(b'[' | b'{') = rest.first() {
+            return false;
+        }
+        if let Some(quote @ (b'"' | b'\'')) = rest.first() {
+            return rest[1..]
+                .iter()
+                .position(|b| b == quote)
+                .is_some_and(|end| rest[end + 2..].trim_ascii_start().starts_with(b":"));
+        }
+        rest.windows(2)
+            .take_while(|w| w != b" #")
+            .any(|w| w[0] == b':' && w[1].is_ascii_whitespace())
+            || rest.trim_ascii_end().ends_with(b":")
+    }
+
+    /// A list, map or single value starting at the current position, which is at `indent`
+    fn block(&mut self, indent: usize) -> Result<Node, SyntaxError> {
+        if self.starts_list_item() {
+            self.list(indent)
+        } else if self.starts_key() {
+            self.map(indent)
+        } else {
+            self.inline()
+        }
+    }
+
+    fn list(&mut self, indent: usize) -> Result<Node, SyntaxError> {
+        let line = self.line;
+        let mut items = vec![];
+        loop {
+            let item_line = self.line;
+            self.bump();
+            self.skip_space();
+            let item = if self.at_line_end() {
+                self.skip_blank();
+                if self.peek().is_some() && self.column() > indent {
+                    self.block(self.column())?
+                } else {
+                    Node {
+                        value: Value::Null,
+                        line: item_line,
+                    }
+                }
+            } else {
+                // `- key: value` is a map lined up with the first key
+                self.block(self.column())?
+            };
+            items.push(item);
+
+            self.skip_blank();
+            if self.peek().is_none() || self.column() < indent {
+                break;
+            }
+            if self.column() > indent {
+                return Err(self.error("indented more than the list item before"));
+            }
+            if !self.starts_list_item() {
+                break;
+            }
+        }
+        Ok(Node {
+            value: Value::List(items),
+            line,
+        })
+    }
+
+    fn map(&mut self, indent: usize) -> Result<Node, SyntaxError> {
+        let line = self.line;
+        let mut entries: Vec<(String, Node)> = vec![];
+        loop {
+            let key_line = self.line;
+            let key = self.key(false)?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(self.error(format!("{key:?} is already set")));
+            }
+            self.skip_space();
+
+            let mut value = if self.at_line_end() {
+                self.skip_blank();
+                if self.peek().is_some() && self.column() > indent {
+                    self.block(self.column())?
+                } else if self.peek().is_some()
+                    && self.column() == indent
+                    && self.starts_list_item()
+                {
+                    // YAML lets a list under a key line up with the key
+                    self.list(indent)?
+                } else {
+                    Node {
+                        value: Value::Null,
+                        line: key_line,
+                    }
+                }
+            } else {
+                self.inline()?
+            };
+            value.line = key_line;
+            entries.push((key, value));
+
+            self.skip_blank();
+            if self.peek().is_none() || self.column() < indent {
+                break;
+            }
+            if self.column() > indent {
+                return Err(self.error("indented more than the key before"));
+            }
+            if self.starts_list_item() {
+                return Err(self.error("a list item can't line up with a key"));
+            }
+        }
+        Ok(Node {
+            value: Value::Map(entries),
+            line,
+        })
+    }
+
+    /// A key and the colon after it. `flow` keys are in `{...}`, where the colon doesn't
+    /// need a space after it.
+    fn key(&mut self, flow: bool) -> Result<String, SyntaxError> {
+        let key = match self.peek() {
+            Some(quote @ (b'"' | b'\'')) => self.quoted(quote)?,
+            _ => {
+                let start = self.at;
+                while let Some(b) = self.peek() {
+                    let ends = b == b':'
+                        && (flow
+                            || matches!(
+                                self.peek_at(1),
+                                None | Some(b' ' | b'\t' | b'\r' | b'\n')
+                            ));
+                    if ends || b == b'\n' || (flow && matches!(b, b',' | b'}')) {
+                        break;
+                    }
+                    self.bump();
+                }
+                self.slice(start).trim_end().to_owned()
+            }
+        };
+        self.skip_space();
+        if self.peek() != Some(b':') {
+            return Err(self.error(format!("expected a `:` after {key:?}")));
+        }
+        self.bump();
+        Ok(key)
+    }
+
+    /// A value that's all on this line (unless it's a flow list or map)
+    fn inline(&mut self) -> Result<Node, SyntaxError> {
+        let node = match self.peek() {
+            Some(b'[' | b'{' | b'"' | b'\'') => self.flow()?,
+            _ => {
+                let line = self.line;
+                let start = self.at;
+                while self.peek().is_some_and(|b| b != b'\n')
+                    && !(self.peek() == Some(b'#') && self.text[self.at - 1].is_ascii_whitespace())
+                {
+                    self.bump();
+                }
+                Node {
+                    value: plain(self.slice(start).trim()),
+                    line,
+                }
+            }
+        };
+        self.end_line()?;
+        Ok(node)
+    }
+
+    /// Anything in flow style, `[...]`, `{...}`, or a scalar inside one of those
+    fn flow(&mut self) -> Result<Node, SyntaxError> {
+        let line = self.line;
+        let value = match self.peek() {
+            Some(b'[') => {
+                self.bump();
+                let mut items = vec![];
+                loop {
+                    self.skip_blank();
+                    if self.peek() == Some(b']') {
+                        self.bump();
+                        break;
+                    }
+                    items.push(self.flow()?);
+                    if !self.flow_separator(b']')? {
+                        break;
+                    }
+                }
+                Value::List(items)
+            }
+            Some(b'{') => {
+                self.bump();
+                let mut entries: Vec<(String, Node)> = vec![];
+                loop {
+                    self.skip_blank();
+                    if self.peek() == Some(b'}') {
+                        self.bump();
+                        break;
+                    }
+                    let key_line = self.line;
+                    let key = self.key(true)?;
+                    if entries.iter().any(|(k, _)| *k == key) {
+                        return Err(self.error(format!("{key:?} is already set")));
+                    }
+                    self.skip_blank();
+                    let mut value = self.flow()?;
+                    value.line = key_line;
+                    entries.push((key, value));
+                    if !self.flow_separator(b'}')? {
+                        break;
+                    }
+                }
+                Value::Map(entries)
+            }
+            Some(quote @ (b'"' | b'\'')) => Value::String(self.quoted(quote)?),
+            Some(_) => {
+                let start = self.at;
+                while self
+                    .peek()
+                    .is_some_and(|b| !matches!(b, b',' | b']' | b'}' | b'\n' | b'#'))
+                {
+                    self.bump();
+                }
+                plain(self.slice(start).trim())
+            }
+            None => return Err(self.error("the file ends in the middle of a value")),
+        };
+        Ok(Node { value, line })
+    }
+
+    /// After an item in a flow list or map, true if there's another to come
+    fn flow_separator(&mut self, close: u8) -> Result<bool, SyntaxError> {
+        self.skip_blank();
+        match self.peek() {
+            Some(b',') => {
+                self.bump();
+                Ok(true)
+            }
+            Some(b) if b == close => {
+                self.bump();
+                Ok(false)
+            }
+            None => Err(self.error("the file ends in the middle of a value")),
+            _ => Err(self.error(format!("expected `,` or `{}`", close as char))),
+        }
+    }
+
+    fn quoted(&mut self, quote: u8) -> Result<String, SyntaxError> {
+        let line = self.line;
+        self.bump();
+        let mut bytes = vec![];
+        loop {
+            let Some(b) = self.peek() else {
+                return Err(SyntaxError {
+                    line,
+                    message: "string never ends".into(),
+                });
+            };
+            self.bump();
+            match b {
+                // '' is a ' in single quotes
+                b'\'' if quote == b'\'' && self.peek() == Some(b'\'') => {
+                    self.bump();
+                    bytes.push(b'\'');
+                }
+                b if b == quote => break,
+                b'\\' if quote == b'"' => {
+                    let escaped = self.peek().ok_or(self.error("string never ends"))?;
+                    self.bump();
+                    match escaped {
+                        b'n' => bytes.push(b'\n'),
+                        b't' => bytes.push(b'\t'),
+                        b'r' => bytes.push(b'\r'),
+                        b'u' => {
+                            let hex = self.text.get(self.at..self.at + 4).unwrap_or_default();
+                            let c = std::str::from_utf8(hex)
+                                .ok()
+                                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or(self.error("bad \\u escape"))?;
+                            for _ in 0..4 {
+                                self.bump();
+                            }
+                            bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        b'"' | b'\\' | b'/' => bytes.push(escaped),
+                        other => {
+                            return Err(self.error(format!("unknown escape \\{}", other as char)))
+                        }
+                    }
+                }
+                b => bytes.push(b),
+            }
+        }
+        // Only ever split at ASCII, so it's still UTF-8
+        Ok(String::from_utf8(bytes).expect("split a character"))
+    }
+
+    fn slice(&self, start: usize) -> &str {
+        // Only ever split at ASCII, so it's still UTF-8
+        std::str::from_utf8(&self.text[start..self.at]).expect("split a character")
+    }
+}
+
+/// An unquoted scalar, which might be a number, boolean or null
+fn plain(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" => return Value::Null,
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    // f64 would also take words like "inf" and "nan", which are much more likely to be names
+    if text.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.')) {
+        if let Ok(n) = text.parse() {
+            return Value::Number(n);
+        }
+    }
+    Value::String(text.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Node, Value};
+
+    fn number(n: f64, line: usize) -> Node {
+        Node {
+            value: Value::Number(n),
+            line,
+        }
+    }
+
+    fn string(s: &str, line: usize) -> Node {
+        Node {
+            value: Value::String(s.into()),
+            line,
+        }
+    }
+
+    #[test]
+    fn block_style() {
+        let yaml = "\
+# a comment
+- add: camera
+  width: 100
+  from: [ -6, 6.5, -10 ]   # trailing comment
+
+- define: white
+  value:
+    color: [1, 1, 1]
+    name: 'it''s \"white\"'
+  extend:
+  - a
+  -  b
+";
+        let root = parse(yaml).unwrap();
+        let Value::List(items) = root.value else {
+            panic!("{root:?}")
+        };
+        assert_eq!(items.len(), 2);
+
+        assert_eq!(
+            items[0],
+            Node {
+                value: Value::Map(vec![
+                    ("add".into(), string("camera", 2)),
+                    ("width".into(), number(100.0, 3)),
+                    (
+                        "from".into(),
+                        Node {
+                            value: Value::List(vec![
+                                number(-6.0, 4),
+                                number(6.5, 4),
+                                number(-10.0, 4)
+                            ]),
+                            line: 4
+                        }
+                    ),
+                ]),
+                line: 2
+            }
+        );
+
+        let Value::Map(define) = &items[1].value else {
+            panic!()
+        };
+        assert_eq!(define[1].0, "value");
+        assert_eq!(define[1].1.line, 7);
+        let Value::Map(value) = &define[1].1.value else {
+            panic!()
+        };
+        assert_eq!(value[1], ("name".into(), string("it's \"white\"", 9)));
+        assert_eq!(
+            define[2].1.value,
+            Value::List(vec![string("a", 11), string("b", 12)])
+        );
+    }
+
+    #[test]
+    fn json() {
+        let json = r##"[
+  {"add": "light", "at": [1, 2.5e1, -3],
+   "intensity": "#ffffff", "shadow": true, "note": null,
+   "escaped": "a\"b\\cé"},
+  {}
+]"##;
+        let root = parse(json).unwrap();
+        let Value::List(items) = root.value else {
+            panic!()
+        };
+        let Value::Map(light) = &items[0].value else {
+            panic!()
+        };
+        assert_eq!(light[1].1.line, 2);
+        assert_eq!(
+            light[1].1.value,
+            Value::List(vec![number(1.0, 2), number(25.0, 2), number(-3.0, 2)])
+        );
+        assert_eq!(light[2], ("intensity".into(), string("#ffffff", 3)));
+        assert_eq!(light[3].1.value, Value::Bool(true));
+        assert_eq!(light[4].1.value, Value::Null);
+        assert_eq!(light[5].1.value, Value::String("a\"b\\cé".into()));
+        assert_eq!(items[1].value, Value::Map(vec![]));
+
+        let one_line = parse(r#"[{"a": 1, "b": [2]}]"#).unwrap();
+        assert_eq!(
+            one_line.value,
+            Value::List(vec![Node {
+                value: Value::Map(vec![
+                    ("a".into(), number(1.0, 1)),
+                    (
+                        "b".into(),
+                        Node {
+                            value: Value::List(vec![number(2.0, 1)]),
+                            line: 1
+                        }
+                    )
+                ]),
+                line: 1
+            }])
+        );
+    }
+
+    #[test]
+    fn scalars() {
+        for (text, value) in [
+            ("1", Value::Number(1.0)),
+            ("-0.5", Value::Number(-0.5)),
+            ("inf", Value::String("inf".into())),
+            ("1.2.3", Value::String("1.2.3".into())),
+            ("~", Value::Null),
+            ("false", Value::Bool(false)),
+            ("rotate-x", Value::String("rotate-x".into())),
+            ("\"3\"", Value::String("3".into())),
+        ] {
+            assert_eq!(parse(text).unwrap().value, value, "{text}");
+        }
+        assert_eq!(parse("  \n# nothing\n").unwrap().value, Value::Null);
+    }
+
+    #[test]
+    fn errors() {
+        for (text, line) in [
+            ("- a: 1\n   b: 2", 2),
+            ("a: 1\n  b: 2", 2),
+            ("a: 1\na: 2", 2),
+            ("a: [1, 2\nb: 3", 2),
+            ("a: \"open", 1),
+            ("- a\nb: 1", 2),
+            ("a: 1\n- b", 2),
+            ("a: [1] 2", 1),
+            ("a: {b 1}", 1),
+        ] {
+            assert_eq!(parse(text).unwrap_err().line, line, "{text:?}");
+        }
+    }
+}
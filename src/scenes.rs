@@ -0,0 +1,208 @@
+//! Ready made scenes, for benchmarks and screenshots.
+
+use core::f64::consts::FRAC_PI_3;
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+use crate::{
+    camera::Camera,
+    colour::Colour,
+    lights::PointLight,
+    materials::Material,
+    math::{
+        matrix::{Matrix, IDENTITY_4X4},
+        rng::Rng,
+        tuple::{point, vectori, Tuple},
+    },
+    prelude::*,
+    scene::Scene,
+    shape::{plane::Plane, sphere::Sphere, Shape},
+    world::World,
+};
+
+/// How often random_spheres picks each kind of material, out of 1
+const WEIGHTS: [(Kind, f64); 3] = [(Kind::Matte, 0.7), (Kind::Metal, 0.2), (Kind::Glass, 0.1)];
+const MIN_RADIUS: f64 = 0.2;
+const MAX_RADIUS: f64 = 0.6;
+/// Tries at placing a sphere before the field it's in gets bigger
+const ATTEMPTS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Matte,
+    Metal,
+    Glass,
+}
+
+/// The book cover: `count` spheres of all sizes resting on a floor, none touching, seven in ten
+/// matte, two metal and one glass. The same seed always gives the same scene. The camera is
+/// 400x225 looking across the lot, see Camera::try_new_with_transform for other sizes.
+pub fn random_spheres(seed: u64, count: usize) -> Scene {
+    let mut rng = Rng::new(seed);
+    let weights = WEIGHTS.map(|(_, weight)| weight);
+
+    // Spread out so that (on average) a fifth of the floor is covered, then placing by trial
+    // and error rarely needs more than a few goes
+    let average_area = core::f64::consts::PI * 0.4 * 0.4;
+    let mut extent = (count as f64 * average_area * 5.0).sqrt().max(2.0) / 2.0;
+
+    let mut placed: Vec<(Tuple, f64)> = Vec::with_capacity(count);
+    let mut objects: Vec<Box<dyn Shape>> = vec![Box::new(Plane::new(
+        IDENTITY_4X4,
+        Material {
+            reflective: 0.1,
+            ..Material::matte(Colour::new(0.5, 0.5, 0.5))
+        },
+    ))];
+    while placed.len() < count {
+        // Smaller ones are more likely
+        let radius = MIN_RADIUS + (MAX_RADIUS - MIN_RADIUS) * rng.next_f64().powi(2);
+        let spot = (0..ATTEMPTS)
+            .map(|_| {
+                point(
+                    rng.between(-extent, extent),
+                    radius,
+                    rng.between(-extent, extent),
+                )
+            })
+            .find(|centre| {
+                placed
+                    .iter()
+                    .all(|(other, r)| (*centre - *other).magnitude() > radius + r)
+            });
+        let Some(centre) = spot else {
+            extent *= 1.1;
+            continue;
+        };
+
+        let kind = WEIGHTS[rng.weighted(&weights).unwrap()].0;
+        let material = match kind {
+            Kind::Matte => Material::matte(Colour::new(
+                rng.next_f64() * rng.next_f64(),
+                rng.next_f64() * rng.next_f64(),
+                rng.next_f64() * rng.next_f64(),
+            )),
+            Kind::Metal => Material::metal(
+                Colour::new(
+                    rng.between(0.5, 1.0),
+                    rng.between(0.5, 1.0),
+                    rng.between(0.5, 1.0),
+                ),
+                rng.between(0.0, 0.5),
+            ),
+            Kind::Glass => Material::glass(),
+        };
+        let mut sphere = Sphere::new_at(centre, radius);
+        sphere.material = material;
+        objects.push(Box::new(sphere));
+        placed.push((centre, radius));
+    }
+
+    let light = PointLight::new_boxed(
+        Colour::WHITE,
+        point(-extent, extent * 2.0 + 5.0, -extent * 2.0),
+    );
+    let camera = Camera::new_with_transform(
+        400,
+        225,
+        FRAC_PI_3,
+        Matrix::view_transform(
+            point(0.0, extent * 0.6 + 1.0, -extent * 1.6 - 2.0),
+            point(0.0, 0.0, 0.0),
+            vectori(0, 1, 0),
+        ),
+    );
+    Scene::new(World::new(objects, vec![light]), camera)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{camera::Camera, colour::Colour, shape::sphere::Sphere};
+
+    use super::{random_spheres, Kind, WEIGHTS};
+
+    fn spheres(seed: u64, count: usize) -> Vec<Sphere> {
+        let scene = random_spheres(seed, count);
+        scene
+            .world
+            .objects()
+            .skip(1)
+            .map(|s| {
+                let sphere = s.downcast_ref::<Sphere>().unwrap();
+                let mut copy = Sphere::new_at(sphere.center, sphere.radius);
+                copy.material = sphere.material.clone();
+                copy
+            })
+            .collect()
+    }
+
+    fn kind(sphere: &Sphere) -> Kind {
+        if sphere.material.transparency > 0.0 {
+            Kind::Glass
+        } else if sphere.material.reflective > 0.0 {
+            Kind::Metal
+        } else {
+            Kind::Matte
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        let same = |a: &[Sphere], b: &[Sphere]| {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(a, b)| {
+                    a.center == b.center && a.radius == b.radius && a.material == b.material
+                })
+        };
+        let first = spheres(7, 40);
+        assert_eq!(first.len(), 40);
+        assert!(same(&first, &spheres(7, 40)));
+        assert!(!same(&first, &spheres(8, 40)));
+
+        let a = random_spheres(7, 40);
+        let b = random_spheres(7, 40);
+        assert_eq!(a.camera.transform, b.camera.transform);
+        assert_eq!(a.world.objects().len(), 41);
+    }
+
+    #[test]
+    fn no_overlaps() {
+        let spheres = spheres(3, 300);
+        for (i, a) in spheres.iter().enumerate() {
+            // Resting on the floor
+            assert_eq!(a.center.y, a.radius);
+            for b in &spheres[i + 1..] {
+                assert!((a.center - b.center).magnitude() > a.radius + b.radius);
+            }
+        }
+    }
+
+    #[test]
+    fn proportions() {
+        let spheres = spheres(11, 3000);
+        for (expected, weight) in WEIGHTS {
+            let share = spheres.iter().filter(|s| kind(s) == expected).count() as f64 / 3000.0;
+            assert!((share - weight).abs() < 0.03, "{expected:?}: {share}");
+        }
+        assert!(spheres.iter().all(|s| s.material.validate().is_ok()));
+    }
+
+    #[test]
+    fn renders() {
+        let mut scene = random_spheres(1, 12);
+        scene.camera = Camera::try_new_with_transform(
+            24,
+            14,
+            scene.camera.fov,
+            scene.camera.transform.clone(),
+        )
+        .unwrap();
+        let canvas = scene.render();
+
+        assert_eq!((canvas.width, canvas.height), (24, 14));
+        assert!(canvas.iter().all(|c| c.is_finite()));
+        let lit = canvas.iter().filter(|c| **c != Colour::BLACK).count();
+        assert!(lit > 24 * 14 / 2, "{lit}");
+        assert!(canvas.iter().any(|c| *c != canvas[(0, 13)]));
+    }
+}
@@ -1,50 +1,157 @@
-use uuid::Uuid;
+use core::{
+    any::Any,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use crate::{
     intersection::Intersection,
     materials::Material,
     math::{matrix::Matrix, tuple::Tuple},
+    prelude::*,
     ray::{Ray, RayIntersect},
 };
 
+pub mod group;
 pub mod plane;
+pub mod smooth_triangle;
 pub mod sphere;
+#[cfg(test)]
 mod test_shape;
+pub mod triangle;
 
 pub trait ShapeBase {
-    fn id(&self) -> Uuid;
+    fn id(&self) -> ShapeId;
     fn transform(&self) -> &Matrix;
     fn material(&self) -> &Material;
     fn set_transform(&mut self, transform: Matrix);
     fn set_material(&mut self, material: Material);
+    /// For getting the concrete shape back out of a `dyn Shape`, see downcast_ref
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
-pub trait Shape: std::fmt::Debug + ShapeBase {
-    fn local_interception(&self, local_space_ray: Ray) -> Option<Vec<Intersection>>;
+pub trait Shape: core::fmt::Debug + ShapeBase + Send + Sync {
+    fn local_interception(&self, local_space_ray: Ray) -> Option<Vec<Intersection<'_>>>;
     fn local_normal_at(&self, point: Tuple) -> Tuple;
-    fn normal_at(&self, point: Tuple) -> Tuple {
-        let inverted = &self.transform().inverse().unwrap();
-        let local_point = inverted * point;
-        let local_normal = self.local_normal_at(local_point);
 
-        let mut world_point = inverted.transpose() * local_normal;
-        world_point.w = 0.0;
+    /// The world space normal at `point`, where `hit` struck this shape. Shapes only care
+    /// about the hit through local_normal_at_hit.
+    ///
+    /// A shape whose transform won't invert is never hit, but if asked anyway it answers as if
+    /// it had no transform at all, rather than panicking.
+    fn normal_at(&self, point: Tuple, hit: &Intersection) -> Tuple {
+        let inverted = &self.transform().inverse().unwrap_or_default();
+        self.normal_at_hit(inverted * point, hit, inverted)
+    }
+
+    /// normal_at without a hit, for when there isn't one (smooth triangles fall back to their
+    /// flat normal). Same fallback for a non-invertible transform.
+    fn normal_at_point(&self, point: Tuple) -> Tuple {
+        let inverted = &self.transform().inverse().unwrap_or_default();
+        self.normal_at_object_point(inverted * point, inverted)
+    }
+
+    /// normal_at, for callers that already have the object space point and the inverted
+    /// transform in hand (saves inverting the transform again)
+    fn normal_at_object_point(&self, object_point: Tuple, inverted: &Matrix) -> Tuple {
+        to_world_normal(self.local_normal_at(object_point), inverted)
+    }
+
+    /// For shapes whose normal depends on more than the point, smooth triangles interpolate
+    /// using the hit's u/v.
+    fn local_normal_at_hit(&self, point: Tuple, _hit: &Intersection) -> Tuple {
+        self.local_normal_at(point)
+    }
+
+    /// normal_at_object_point, but through local_normal_at_hit
+    fn normal_at_hit(&self, object_point: Tuple, hit: &Intersection, inverted: &Matrix) -> Tuple {
+        to_world_normal(self.local_normal_at_hit(object_point, hit), inverted)
+    }
 
-        world_point.normalize()
+    /// Put this shape under `parent`'s transform, see Group
+    fn bake_transform(&mut self, parent: &Matrix) {
+        let transform = parent * self.transform();
+        self.set_transform(transform);
     }
+
+    /// Shapes inside of this one, only groups have any
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &[]
+    }
+}
+
+fn to_world_normal(local_normal: Tuple, inverted: &Matrix) -> Tuple {
+    let mut world_normal = inverted.transpose() * local_normal;
+    world_normal.w = 0.0;
+
+    world_normal.normalize()
 }
 
+/// Which shape is which. Handed out in the order shapes are made, counting up from 1, so no two
+/// shapes made in the same process share one and the same scene built the same way gets the
+/// same ids every run. Clones keep the id of the shape they're cloned from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShapeId(u64);
+
+impl ShapeId {
+    /// The next id, never given out before
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        ShapeId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+/// A fresh id, so a shape made with `..Default::default()` is still its own shape
+impl Default for ShapeId {
+    fn default() -> Self {
+        Self::next()
+    }
+}
+
+/// The shape with the given id in `shapes`, looking inside groups too
+pub fn find_in(shapes: &[Box<dyn Shape>], id: ShapeId) -> Option<&dyn Shape> {
+    shapes.iter().find_map(|s| {
+        if s.id() == id {
+            Some(&**s)
+        } else {
+            find_in(s.children(), id)
+        }
+    })
+}
+
+impl<'a> dyn Shape + 'a {
+    /// The shape as a `T`, if that's what it is
+    pub fn downcast_ref<T: Shape + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    pub fn downcast_mut<T: Shape + 'static>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+}
+
+/// The t on every intersection is in terms of `ray` exactly as it was passed in. The local ray
+/// is the same ray transformed, direction included and NOT renormalized, so a t means the same
+/// point in both spaces. Whether t is a distance is up to the caller: it is if the direction
+/// is unit length (camera rays, Ray::new_normalized), otherwise it's in lengths of direction.
 impl<T: ?Sized> RayIntersect for T
 where
     T: Shape,
 {
-    fn intersect(&self, ray: crate::ray::Ray) -> Option<Vec<crate::intersection::Intersection>> {
-        let local_ray = ray.transform(
-            &self
-                .transform()
-                .inverse()
-                .expect("transform must be invertable"),
-        );
+    fn intersect(
+        &self,
+        ray: crate::ray::Ray,
+    ) -> Option<Vec<crate::intersection::Intersection<'_>>> {
+        // A degenerate (non-invertible) transform squashes the shape flat, there is nothing
+        // sensible to hit. World::validate will flag these before a render.
+        if self.transform().is_identity() {
+            return self.local_interception(ray);
+        }
+        let local_ray = ray.transform(&self.transform().inverse()?);
         self.local_interception(local_ray)
     }
 }
@@ -58,7 +165,7 @@ impl PartialEq for &dyn Shape {
 macro_rules! shape_base {
     ($name:ident) => {
         impl ShapeBase for $name {
-            fn id(&self) -> uuid::Uuid {
+            fn id(&self) -> ShapeId {
                 self._id
             }
             fn material(&self) -> &Material {
@@ -75,6 +182,14 @@ macro_rules! shape_base {
             fn set_transform(&mut self, transform: Matrix) {
                 self.transform = transform
             }
+
+            fn as_any(&self) -> &dyn core::any::Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+                self
+            }
         }
     };
 }
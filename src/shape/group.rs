@@ -0,0 +1,240 @@
+use crate::{
+    intersection::Intersection,
+    materials::Material,
+    math::{matrix::Matrix, tuple::Tuple},
+    prelude::*,
+    ray::{Ray, RayIntersect},
+};
+
+use super::{Shape, ShapeBase, ShapeId};
+
+/// A bunch of shapes that are transformed (and given materials) together.
+///
+/// Hits are against the children directly, and shading only ever looks at the shape that was
+/// hit, so rather than children knowing about their parent the group's transform is baked into
+/// every child's transform as it changes. Likewise set_material sets every child's material.
+#[derive(Debug)]
+pub struct Group {
+    _id: ShapeId,
+    transform: Matrix,
+    material: Material,
+    children: Vec<Box<dyn Shape>>,
+}
+
+impl Group {
+    pub fn new(transform: Matrix) -> Self {
+        Self {
+            _id: ShapeId::next(),
+            transform,
+            material: Default::default(),
+            children: vec![],
+        }
+    }
+
+    /// Adds `child`, putting it under this group's transform.
+    pub fn add_child(&mut self, mut child: Box<dyn Shape>) {
+        child.bake_transform(&self.transform);
+        self.children.push(child)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl ShapeBase for Group {
+    fn id(&self) -> ShapeId {
+        self._id
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Re-bakes every child. If the current transform can't be inverted there's no way to get
+    /// the children back out from under it, so the new one goes on top and they stay squashed
+    /// flat (and so invisible, like the group).
+    fn set_transform(&mut self, transform: Matrix) {
+        let undo = self.transform.inverse().unwrap_or_default();
+        let delta = &transform * &undo;
+        for child in &mut self.children {
+            child.bake_transform(&delta);
+        }
+        self.transform = transform;
+    }
+
+    fn set_material(&mut self, material: Material) {
+        for child in &mut self.children {
+            child.set_material(material.clone());
+        }
+        self.material = material;
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+        self
+    }
+}
+
+impl Shape for Group {
+    fn local_interception(&self, local_space_ray: Ray) -> Option<Vec<Intersection<'_>>> {
+        // Children already have our transform baked in, they want the original ray back
+        let ray = local_space_ray.transform(&self.transform);
+        let mut xs: Vec<_> = self
+            .children
+            .iter()
+            .flat_map(|c| c.intersect(ray).unwrap_or_default())
+            .collect();
+
+        if xs.is_empty() {
+            return None;
+        }
+
+        xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+        Some(xs)
+    }
+
+    fn local_normal_at(&self, _: Tuple) -> Tuple {
+        unreachable!("groups are never hit themselves, only their children")
+    }
+
+    fn bake_transform(&mut self, parent: &Matrix) {
+        for child in &mut self.children {
+            child.bake_transform(parent);
+        }
+        self.transform = parent * &self.transform;
+    }
+
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_2;
+
+    use crate::{
+        materials::Material,
+        math::{
+            point::{Point, Vector},
+            {matrix::Matrix, tuple::point},
+        },
+        ray::{Ray, RayIntersect},
+        shape::{sphere::Sphere, Shape, ShapeBase},
+    };
+
+    use super::Group;
+
+    #[test]
+    fn empty() {
+        let g = Group::default();
+
+        assert!(g.is_empty());
+        assert_eq!(g.transform(), &Matrix::default());
+        assert!(g
+            .local_interception(Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1)))
+            .is_none());
+    }
+
+    #[test]
+    fn intersect_children() {
+        let mut g = Group::default();
+        let s1 = Sphere::default();
+        let s2 = Sphere::new_with_transform(Matrix::translationi(0, 0, -3));
+        let s3 = Sphere::new_with_transform(Matrix::translationi(5, 0, 0));
+        let ids = [s1.id(), s2.id()];
+        g.add_child(Box::new(s1));
+        g.add_child(Box::new(s2));
+        g.add_child(Box::new(s3));
+
+        let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+        let xs = g.local_interception(r).unwrap();
+
+        let hit: Vec<_> = xs.iter().map(|i| i.object.id()).collect();
+        assert_eq!(hit, vec![ids[1], ids[1], ids[0], ids[0]]);
+    }
+
+    #[test]
+    fn transformed() {
+        let mut g = Group::new(Matrix::scalingi(2, 2, 2));
+        g.add_child(Box::new(Sphere::new_with_transform(Matrix::translationi(
+            5, 0, 0,
+        ))));
+
+        let r = Ray::new(Point::newi(10, 0, -10), Vector::newi(0, 0, 1));
+        assert_eq!(g.intersect(r).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn set_transform_after_adding() {
+        let mut g = Group::default();
+        g.add_child(Box::new(Sphere::new_with_transform(Matrix::translationi(
+            5, 0, 0,
+        ))));
+        g.set_transform(Matrix::scalingi(2, 2, 2));
+
+        let r = Ray::new(Point::newi(10, 0, -10), Vector::newi(0, 0, 1));
+        assert_eq!(g.intersect(r).unwrap().len(), 2);
+        assert_eq!(g.transform(), &Matrix::scalingi(2, 2, 2));
+    }
+
+    #[test]
+    fn nested_normal() {
+        // The book's "finding the normal on a child object"
+        let mut g1 = Group::new(Matrix::rotation_y(FRAC_PI_2));
+        let mut g2 = Group::new(Matrix::scalingi(1, 2, 3));
+        g2.add_child(Box::new(Sphere::new_with_transform(Matrix::translationi(
+            5, 0, 0,
+        ))));
+        g1.add_child(Box::new(g2));
+
+        let child = &*g1.children()[0].children()[0];
+        let n = child.normal_at_point(point(1.7321, 1.1547, -5.5774));
+
+        assert_eq!(n, crate::math::tuple::vector(0.2857, 0.42854, -0.85716));
+    }
+
+    #[test]
+    fn material_applies_to_children() {
+        let mut g = Group::default();
+        g.add_child(Box::new(Sphere::default()));
+        let m = Material {
+            ambient: 0.5,
+            ..Default::default()
+        };
+
+        g.set_material(m.clone());
+
+        assert_eq!(g.children()[0].material(), &m);
+    }
+
+    #[test]
+    fn set_transform_when_squashed() {
+        let mut g = Group::new(Matrix::scaling(0.0, 1.0, 1.0));
+        g.add_child(Box::new(Sphere::default()));
+        g.set_transform(Matrix::scalingi(2, 2, 2));
+
+        // The child is still squashed, so there is still nothing to hit
+        let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+        assert!(g.intersect(r).is_none());
+        assert!(g.children()[0].intersect(r).is_none());
+    }
+}
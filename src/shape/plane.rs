@@ -1,18 +1,17 @@
 // Is it a bird? No, no it is not
 
-use uuid::Uuid;
-
 use crate::{
     intersection::Intersection,
     materials::Material,
     math::{float::EPSILON, matrix::Matrix, tuple::vectori},
+    prelude::*,
 };
 
-use super::{shape_base, Shape, ShapeBase};
+use super::{shape_base, Shape, ShapeBase, ShapeId};
 
 #[derive(Debug, Clone)]
 pub struct Plane {
-    _id: uuid::Uuid,
+    _id: ShapeId,
     pub transform: Matrix,
     pub material: Material,
 }
@@ -20,9 +19,8 @@ pub struct Plane {
 shape_base!(Plane);
 impl Plane {
     pub fn new(transform: Matrix, material: Material) -> Self {
-        //uuid
         Self {
-            _id: Uuid::new_v4(),
+            _id: ShapeId::next(),
             transform,
             material,
         }
@@ -47,11 +45,12 @@ impl Shape for Plane {
     fn local_interception(
         &self,
         local_space_ray: crate::ray::Ray,
-    ) -> Option<Vec<crate::intersection::Intersection>> {
+    ) -> Option<Vec<crate::intersection::Intersection<'_>>> {
         if local_space_ray.direction.y.abs() < EPSILON {
             None
         } else {
             let t = -local_space_ray.origin.y / local_space_ray.direction.y;
+            debug_assert!(t.is_finite(), "plane produced non-finite t ({t})");
             Some(vec![Intersection::new(t, self)])
         }
     }
@@ -65,7 +64,10 @@ impl Shape for Plane {
 mod test {
 
     use crate::{
-        math::tuple::{pointi, vectori},
+        math::{
+            point::{Point, Vector},
+            tuple::{pointi, vectori},
+        },
         ray::Ray,
         shape::Shape,
     };
@@ -89,7 +91,7 @@ mod test {
     fn intercept_parallel() {
         let p = Plane::default();
 
-        let r = Ray::new(pointi(0, 10, 0), vectori(0, 0, 1));
+        let r = Ray::new(Point::newi(0, 10, 0), Vector::newi(0, 0, 1));
 
         let xs = p.local_interception(r);
         assert!(xs.is_none())
@@ -99,7 +101,7 @@ mod test {
     fn intercept_coplanar() {
         let p = Plane::default();
 
-        let r = Ray::new(pointi(0, 0, 0), vectori(0, 0, 1));
+        let r = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1));
 
         let xs = p.local_interception(r);
         assert!(xs.is_none())
@@ -109,7 +111,7 @@ mod test {
     fn intercept_above() {
         let p = Plane::default();
 
-        let r = Ray::new(pointi(0, 1, 0), vectori(0, -1, 0));
+        let r = Ray::new(Point::newi(0, 1, 0), Vector::newi(0, -1, 0));
 
         let xs = p.local_interception(r).unwrap();
         assert_eq!(xs[0].t, 1.0);
@@ -120,7 +122,7 @@ mod test {
     fn intercept_below() {
         let p = Plane::default();
 
-        let r = Ray::new(pointi(0, -1, 0), vectori(0, 1, 0));
+        let r = Ray::new(Point::newi(0, -1, 0), Vector::newi(0, 1, 0));
 
         let xs = p.local_interception(r).unwrap();
         assert_eq!(xs[0].t, 1.0);
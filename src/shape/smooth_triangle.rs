@@ -0,0 +1,122 @@
+use crate::{
+    intersection::Intersection,
+    materials::Material,
+    math::{matrix::Matrix, tuple::Tuple},
+    prelude::*,
+    ray::Ray,
+    shape::{shape_base, ShapeBase, ShapeId},
+};
+
+use super::{triangle::intersect_triangle, Shape};
+
+/// A triangle with a normal at each corner, interpolated across the face so that meshes don't
+/// look faceted.
+#[derive(Debug, Clone)]
+pub struct SmoothTriangle {
+    _id: ShapeId,
+    pub transform: Matrix,
+    pub material: Material,
+
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub n1: Tuple,
+    pub n2: Tuple,
+    pub n3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+}
+
+impl SmoothTriangle {
+    pub fn new(p1: Tuple, p2: Tuple, p3: Tuple, n1: Tuple, n2: Tuple, n3: Tuple) -> Self {
+        Self {
+            _id: ShapeId::next(),
+            transform: Default::default(),
+            material: Default::default(),
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            e1: p2 - p1,
+            e2: p3 - p1,
+        }
+    }
+}
+
+shape_base!(SmoothTriangle);
+
+impl Shape for SmoothTriangle {
+    fn local_interception(&self, local_space_ray: Ray) -> Option<Vec<Intersection<'_>>> {
+        let (t, u, v) = intersect_triangle(self.p1, self.e1, self.e2, local_space_ray)?;
+        Some(vec![Intersection::new_with_uv(t, self, u, v)])
+    }
+
+    /// Without a hit to go on, there's only the flat face normal
+    fn local_normal_at(&self, _: Tuple) -> Tuple {
+        self.e2.cross(&self.e1).normalize()
+    }
+
+    fn local_normal_at_hit(&self, _: Tuple, hit: &Intersection) -> Tuple {
+        self.n2 * hit.u + self.n3 * hit.v + self.n1 * (1.0 - hit.u - hit.v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        intersection::{IntersectVec, Intersection},
+        math::{
+            point::{Point, Vector},
+            tuple::{pointi, vector, vectori},
+        },
+        ray::{Ray, RayIntersect},
+        shape::Shape,
+    };
+
+    use super::SmoothTriangle;
+
+    fn triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            pointi(0, 1, 0),
+            pointi(-1, 0, 0),
+            pointi(1, 0, 0),
+            vectori(0, 1, 0),
+            vectori(-1, 0, 0),
+            vectori(1, 0, 0),
+        )
+    }
+
+    #[test]
+    fn uv() {
+        let t = triangle();
+        let r = Ray::new(Point::new(-0.2, 0.3, -2.0), Vector::newi(0, 0, 1));
+
+        let xs = t.local_interception(r).unwrap();
+
+        assert!((xs[0].u - 0.45).abs() < 1e-5);
+        assert!((xs[0].v - 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn interpolated_normal() {
+        let t = triangle();
+        let i = Intersection::new_with_uv(1.0, &t, 0.45, 0.25);
+
+        let n = t.normal_at(pointi(0, 0, 0), &i);
+
+        assert_eq!(n, vector(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn prepare_computations() {
+        let t = triangle();
+        let r = Ray::new(Point::new(-0.2, 0.3, -2.0), Vector::newi(0, 0, 1));
+        let xs = t.intersect(r).unwrap();
+
+        let comps = xs.hit().unwrap().prepare_computations(r);
+
+        assert_eq!(comps.normal_vector, vector(-0.5547, 0.83205, 0.0));
+    }
+}
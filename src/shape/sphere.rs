@@ -1,34 +1,70 @@
-use uuid::Uuid;
-
 use crate::{
+    error::RayTracerError,
     intersection::Intersection,
     materials::Material,
     math::{
         matrix::Matrix,
-        tuple::{Tuple, ZERO},
+        tuple::{Tuple, ZERO_POINT},
     },
+    prelude::*,
     ray::Ray,
-    shape::{shape_base, ShapeBase},
+    shape::{shape_base, ShapeBase, ShapeId},
 };
 
 use super::Shape;
 
 /// Its a sphere. What do you want from me?
+///
+/// Unit sized at the origin unless made with new_at. The transform applies on top of
+/// center/radius.
 #[derive(Debug, PartialEq)]
 pub struct Sphere {
-    _id: Uuid,
+    _id: ShapeId,
     pub transform: Matrix,
     pub material: Material,
+    pub center: Tuple,
+    pub radius: f64,
 }
 
 impl Sphere {
     pub fn new(transform: Matrix, material: Material) -> Self {
-        //uuid
         Self {
-            _id: Uuid::new_v4(),
+            _id: ShapeId::next(),
             transform,
             material,
+            center: ZERO_POINT,
+            radius: 1.0,
+        }
+    }
+
+    /// A sphere placed directly rather than via a transform. Same thing as a unit sphere
+    /// scaled by radius and translated to center, but with an identity transform there's no
+    /// inverse to compute when intersecting. Fails if center isn't a point, or radius isn't
+    /// positive and finite.
+    pub fn try_new_at(center: Tuple, radius: f64) -> Result<Self, RayTracerError> {
+        if !center.is_point() {
+            return Err(RayTracerError::NotAPoint {
+                what: "sphere center",
+                got: center,
+            });
+        }
+        if !(radius > 0.0 && radius.is_finite()) {
+            return Err(RayTracerError::InvalidRadius {
+                what: "sphere",
+                got: radius,
+            });
         }
+
+        Ok(Self {
+            center,
+            radius,
+            ..Default::default()
+        })
+    }
+
+    /// Panicking version of try_new_at
+    pub fn new_at(center: Tuple, radius: f64) -> Self {
+        Self::try_new_at(center, radius).unwrap_or_else(|e| panic!("{e}"))
     }
 
     pub fn new_with_transform(transform: Matrix) -> Self {
@@ -50,26 +86,34 @@ shape_base!(Sphere);
 
 impl Shape for Sphere {
     fn local_normal_at(&self, point: Tuple) -> Tuple {
-        point - ZERO // At any point, the vector for the normal is the exact opposite of the point (as a vec)
+        // The normal points straight out from the center, dividing by the radius keeps it unit length
+        (point - self.center) * self.radius.recip()
     }
 
-    fn local_interception(&self, local_space_ray: Ray) -> Option<Vec<Intersection>> {
+    fn local_interception(&self, local_space_ray: Ray) -> Option<Vec<Intersection<'_>>> {
         let ray = local_space_ray;
-        let s2r = ray.origin - Tuple::pointi(0, 0, 0);
+        let s2r = ray.origin.tuple() - self.center;
 
         let a = ray.direction.dot(&ray.direction);
         let b = 2.0 * ray.direction.dot(&s2r);
-        let c = s2r.dot(&s2r) - 1.0;
+        let c = s2r.dot(&s2r) - self.radius.powi(2);
 
         let discriminant = b.powi(2) - 4.0 * a * c;
-        if discriminant < 0.0 {
+        // A NaN discriminant (degenerate ray) is a miss too
+        if discriminant.is_nan() || discriminant < 0.0 || a == 0.0 {
             return None;
         }
 
         let disroot = discriminant.sqrt();
+        let (t1, t2) = ((-b - disroot) / (2.0 * a), (-b + disroot) / (2.0 * a));
+        debug_assert!(
+            t1.is_finite() && t2.is_finite(),
+            "sphere produced non-finite t ({t1}, {t2})"
+        );
+
         Some(vec![
-            Intersection::new((-b - disroot) / (2.0 * a), self),
-            Intersection::new((-b + disroot) / (2.0 * a), self),
+            Intersection::new(t1, self),
+            Intersection::new(t2, self),
         ])
     }
 }
@@ -82,6 +126,7 @@ mod test {
     use crate::{
         math::{
             matrix::{Matrix, IDENTITY_4X4},
+            point::{Point, Vector},
             tuple::Tuple,
         },
         ray::{Ray, RayIntersect},
@@ -111,7 +156,7 @@ mod test {
                 #[test]
                 fn $name() {
                     let s = Sphere::new_with_transform($transform);
-                    let n = s.normal_at($inp);
+                    let n = s.normal_at_point($inp);
 
                     assert!(n.is_vector());
                     assert_eq!(n, $out);
@@ -170,7 +215,7 @@ mod test {
         use super::*;
         #[test]
         fn two_points() {
-            let r = Ray::new(Tuple::pointi(0, 0, -5), Tuple::vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
             let s = Sphere::default();
 
             let xs = s.intersect(r).unwrap();
@@ -182,7 +227,7 @@ mod test {
 
         #[test]
         fn tangent() {
-            let r = Ray::new(Tuple::pointi(0, 1, -5), Tuple::vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 1, -5), Vector::newi(0, 0, 1));
             let s = Sphere::default();
 
             let xs = s.intersect(r).unwrap();
@@ -194,7 +239,7 @@ mod test {
 
         #[test]
         fn none() {
-            let r = Ray::new(Tuple::pointi(0, 2, -5), Tuple::vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 2, -5), Vector::newi(0, 0, 1));
             let s = Sphere::default();
 
             let xs = s.intersect(r);
@@ -203,7 +248,7 @@ mod test {
 
         #[test]
         fn center() {
-            let r = Ray::new(Tuple::pointi(0, 0, 0), Tuple::vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1));
             let s = Sphere::default();
 
             let xs = s.intersect(r).unwrap();
@@ -215,7 +260,7 @@ mod test {
 
         #[test]
         fn behind() {
-            let r = Ray::new(Tuple::pointi(0, 0, 5), Tuple::vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 0, 5), Vector::newi(0, 0, 1));
             let s = Sphere::default();
 
             let xs = s.intersect(r).unwrap();
@@ -227,7 +272,7 @@ mod test {
 
         #[test]
         fn scaled() {
-            let r = Ray::new(Tuple::pointi(0, 0, -5), Tuple::vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
             let s = Sphere::new_with_transform(Matrix::scalingi(2, 2, 2));
 
             let xs = s.intersect(r).expect("Did not get expected intersections");
@@ -238,11 +283,158 @@ mod test {
         }
         #[test]
         fn translated() {
-            let r = Ray::new(Tuple::pointi(0, 0, -5), Tuple::vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
             let s: Sphere = Sphere::new_with_transform(Matrix::translationi(5, 0, 0));
 
             let xs: Option<Vec<crate::intersection::Intersection<'_>>> = s.intersect(r);
             assert!(xs.is_none())
         }
     }
+
+    mod new_at {
+        use crate::{
+            colour::Colour,
+            error::RayTracerError,
+            lights::PointLight,
+            math::point::{Point, Vector},
+            math::tuple::point,
+            pattern::solid::SolidPattern,
+            world::World,
+        };
+
+        use super::*;
+
+        fn pair() -> (Sphere, Sphere) {
+            (
+                Sphere::new_at(point(2.0, 0.0, 0.0), 3.0),
+                Sphere::new_with_transform(
+                    Matrix::translationi(2, 0, 0) * Matrix::scalingi(3, 3, 3),
+                ),
+            )
+        }
+
+        fn rays() -> Vec<Ray> {
+            vec![
+                Ray::new(Point::new(0.0, 0.0, -10.0), Vector::new(0.0, 0.0, 1.0)),
+                Ray::new(Point::new(-10.0, 1.0, 0.5), Vector::new(1.0, 0.0, 0.0)),
+                Ray::new(
+                    Point::new(2.0, 0.0, 0.0),
+                    Vector::new(0.3, 0.4, 0.5).normalize(),
+                ),
+                Ray::new(Point::new(0.0, 5.0, -10.0), Vector::new(0.2, -0.3, 1.0)),
+                Ray::new(Point::new(10.0, 10.0, 10.0), Vector::new(0.0, 1.0, 0.0)),
+            ]
+        }
+
+        #[test]
+        fn intersections_match_transformed() {
+            let (direct, transformed) = pair();
+
+            for r in rays() {
+                let ts = |s: &Sphere| {
+                    s.intersect(r)
+                        .map(|xs| xs.iter().map(|i| i.t).collect::<Vec<_>>())
+                };
+                match (ts(&direct), ts(&transformed)) {
+                    (Some(a), Some(b)) => {
+                        assert_eq!(a.len(), b.len());
+                        for (a, b) in a.iter().zip(b.iter()) {
+                            assert!(crate::math::float::equal(*a, *b), "{a} != {b}");
+                        }
+                    }
+                    (a, b) => assert_eq!(a.is_none(), b.is_none(), "{r:?}"),
+                }
+            }
+        }
+
+        #[test]
+        fn normals_match_transformed() {
+            let (direct, transformed) = pair();
+
+            for p in [
+                point(5.0, 0.0, 0.0),
+                point(2.0, 3.0, 0.0),
+                point(2.0 + SQRT_2 * 1.5, 0.0, -SQRT_2 * 1.5),
+            ] {
+                let n = direct.normal_at_point(p);
+                assert_eq!(n, transformed.normal_at_point(p));
+                assert_eq!(n, n.normalize());
+            }
+        }
+
+        #[test]
+        fn bad_radius() {
+            for radius in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+                let err = Sphere::try_new_at(point(1.0, 0.0, 0.0), radius).unwrap_err();
+                assert!(
+                    matches!(err, RayTracerError::InvalidRadius { what: "sphere", got } if got.to_bits() == radius.to_bits()),
+                    "{err}"
+                );
+            }
+            assert_eq!(
+                Sphere::try_new_at(point(1.0, 0.0, 0.0), -1.0)
+                    .unwrap_err()
+                    .to_string(),
+                "sphere radius must be positive and finite, got -1"
+            );
+
+            let err = Sphere::try_new_at(Tuple::vector(1.0, 0.0, 0.0), 1.0).unwrap_err();
+            assert!(matches!(
+                err,
+                RayTracerError::NotAPoint {
+                    what: "sphere center",
+                    ..
+                }
+            ));
+        }
+
+        #[test]
+        fn transform_composes() {
+            let mut s = Sphere::new_at(point(1.0, 0.0, 0.0), 2.0);
+            s.transform = Matrix::translationi(0, 0, 5);
+            let r = Ray::new(Point::new(1.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+
+            let xs = s.intersect(r).unwrap();
+            assert_eq!((xs[0].t, xs[1].t), (3.0, 7.0));
+        }
+
+        fn world(s: Sphere, light: Tuple) -> World {
+            let mut floor = Sphere::new_at(point(0.0, -1003.0, 0.0), 1000.0);
+            floor.material.pattern = Box::new(SolidPattern(Colour::new(0.5, 0.6, 0.7)));
+            World::new(
+                vec![Box::new(s), Box::new(floor)],
+                vec![PointLight::new_boxed(Colour::WHITE, light)],
+            )
+        }
+
+        #[test]
+        fn shading_matches_transformed() {
+            let (direct, transformed) = pair();
+            let light = point(-10.0, 10.0, -10.0);
+            let (direct, transformed) = (world(direct, light), world(transformed, light));
+
+            for r in rays().into_iter().chain([Ray::new(
+                Point::new(8.0, 5.0, -10.0),
+                Vector::new(0.0, -0.8, 1.0),
+            )]) {
+                assert_eq!(direct.colour_at(r), transformed.colour_at(r), "{r:?}");
+            }
+        }
+
+        #[test]
+        fn shadows_match_transformed() {
+            let (direct, transformed) = pair();
+            let light = point(2.0, 10.0, 0.0);
+            let (direct, transformed) = (world(direct, light), world(transformed, light));
+
+            // On the floor, straight under the sphere and well off to the side of it
+            for (p, shadowed) in [
+                (point(2.0, -3.0, 0.0), true),
+                (point(-8.0, -3.0, 0.0), false),
+            ] {
+                assert_eq!(direct.is_shadowed(p), shadowed);
+                assert_eq!(transformed.is_shadowed(p), shadowed);
+            }
+        }
+    }
 }
@@ -4,7 +4,7 @@ use crate::{
     materials::Material,
     math::{matrix::Matrix, tuple::Tuple},
     ray::Ray,
-    shape::{shape_base, ShapeBase},
+    shape::{shape_base, ShapeBase, ShapeId},
 };
 
 use super::Shape;
@@ -12,7 +12,7 @@ use super::Shape;
 /// Not really used anywhere, mostly used o verify implementations (if any) in Shape
 #[derive(Debug, Default)]
 pub struct TestShape {
-    _id: uuid::Uuid,
+    _id: ShapeId,
     pub transform: Matrix,
     pub material: Material,
 
@@ -29,7 +29,7 @@ impl Shape for TestShape {
     fn local_interception(
         &self,
         local_space_ray: Ray,
-    ) -> Option<Vec<crate::intersection::Intersection>> {
+    ) -> Option<Vec<crate::intersection::Intersection<'_>>> {
         self.saved_ray.lock().unwrap().replace(local_space_ray);
 
         None
@@ -45,10 +45,11 @@ mod test {
     use crate::{
         math::{
             matrix::{Matrix, IDENTITY_4X4},
+            point::{Point, Vector},
             tuple::{point, pointi, vector, vectori},
         },
         ray::{Ray, RayIntersect},
-        shape::{Shape, ShapeBase},
+        shape::{plane::Plane, sphere::Sphere, Shape, ShapeBase, ShapeId},
     };
 
     use super::TestShape;
@@ -56,7 +57,7 @@ mod test {
     #[test]
     fn verify_construct() {
         let s: &dyn Shape = &TestShape::default();
-        assert_eq!(s.transform(), &*IDENTITY_4X4);
+        assert_eq!(s.transform(), &IDENTITY_4X4);
     }
 
     #[test]
@@ -69,7 +70,7 @@ mod test {
 
     #[test]
     fn ray_intersect_scaled() {
-        let r = Ray::new(pointi(0, 0, -5), vectori(0, 0, 1));
+        let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
         let mut s = TestShape::default();
         s.set_transform(Matrix::scalingi(2, 2, 2));
 
@@ -81,7 +82,7 @@ mod test {
     }
     #[test]
     fn ray_intersect_translated() {
-        let r = Ray::new(pointi(0, 0, -5), vectori(0, 0, 1));
+        let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
         let mut s = TestShape::default();
         s.set_transform(Matrix::translationi(5, 0, 0));
 
@@ -97,7 +98,7 @@ mod test {
         let mut s = TestShape::default();
         s.set_transform(Matrix::translationi(0, 1, 0));
 
-        let normal = s.normal_at(point(0.0, 1.70711, -FRAC_1_SQRT_2));
+        let normal = s.normal_at_point(point(0.0, 1.70711, -FRAC_1_SQRT_2));
 
         assert_eq!(normal, vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2))
     }
@@ -107,7 +108,7 @@ mod test {
         let mut s = TestShape::default();
         s.set_transform(Matrix::rotation_z(PI / 5.0).scale(1.0, 0.5, 1.0));
 
-        let normal = s.normal_at(point(
+        let normal = s.normal_at_point(point(
             0.0,
             (2.0_f64.sqrt()) / 2.0,
             -((2.0_f64.sqrt()) / 2.0),
@@ -115,4 +116,56 @@ mod test {
 
         assert_eq!(normal, vector(0.0, 0.97014, -0.24254))
     }
+
+    #[test]
+    fn singular_transform() {
+        let mut s = TestShape::default();
+        s.set_transform(Matrix::scaling(1.0, 0.0, 1.0));
+
+        // Never gets as far as the shape, the ray can't be taken into object space
+        let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+        assert!(s.intersect(r).is_none());
+        let saved = *s.saved_ray.lock().unwrap().borrow();
+        assert_eq!(saved.origin, Ray::default().origin);
+
+        // The normal is as if there were no transform
+        assert_eq!(s.normal_at_point(pointi(0, 2, 0)), vectori(0, 1, 0));
+    }
+
+    #[test]
+    fn ids_count_up() {
+        let shapes: Vec<Box<dyn Shape>> = (0..1000)
+            .map(|i| match i % 3 {
+                0 => Box::new(Sphere::default()) as Box<dyn Shape>,
+                1 => Box::new(Plane::default()),
+                _ => Box::new(TestShape::default()),
+            })
+            .collect();
+        // Other tests make shapes at the same time, so only the order's certain
+        assert!(shapes.windows(2).all(|w| w[0].id() < w[1].id()));
+    }
+
+    #[test]
+    fn ids_are_unique_across_threads() {
+        let ids: Vec<Vec<ShapeId>> = std::thread::scope(|s| {
+            let threads: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| (0..500).map(|_| Sphere::default().id()).collect()))
+                .collect();
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+        let all: std::collections::HashSet<_> = ids.iter().flatten().collect();
+        assert_eq!(all.len(), 2000);
+        for thread in &ids {
+            assert!(thread.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn equal_by_id() {
+        let (a, b) = (Plane::default(), Plane::default());
+        let copy = a.clone();
+        // Identical but for the id isn't the same shape, a clone is
+        assert!(&a as &dyn Shape != &b as &dyn Shape);
+        assert!(&a as &dyn Shape == &copy as &dyn Shape);
+    }
 }
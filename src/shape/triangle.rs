@@ -0,0 +1,160 @@
+use crate::{
+    intersection::Intersection,
+    materials::Material,
+    math::{float::EPSILON, matrix::Matrix, tuple::Tuple},
+    prelude::*,
+    ray::Ray,
+    shape::{shape_base, ShapeBase, ShapeId},
+};
+
+use super::Shape;
+
+/// A flat triangle between three points. Hits carry the barycentric u/v of the hit point.
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    _id: ShapeId,
+    pub transform: Matrix,
+    pub material: Material,
+
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+    pub normal: Tuple,
+}
+
+impl Triangle {
+    pub fn new(p1: Tuple, p2: Tuple, p3: Tuple) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Self {
+            _id: ShapeId::next(),
+            transform: Default::default(),
+            material: Default::default(),
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(&e1).normalize(),
+        }
+    }
+}
+
+shape_base!(Triangle);
+
+impl Shape for Triangle {
+    fn local_normal_at(&self, _: Tuple) -> Tuple {
+        self.normal
+    }
+
+    fn local_interception(&self, local_space_ray: Ray) -> Option<Vec<Intersection<'_>>> {
+        let (t, u, v) = intersect_triangle(self.p1, self.e1, self.e2, local_space_ray)?;
+        Some(vec![Intersection::new_with_uv(t, self, u, v)])
+    }
+}
+
+/// Möller–Trumbore, giving (t, u, v). Shared with SmoothTriangle.
+pub(crate) fn intersect_triangle(
+    p1: Tuple,
+    e1: Tuple,
+    e2: Tuple,
+    ray: Ray,
+) -> Option<(f64, f64, f64)> {
+    let dir_cross_e2 = ray.direction.tuple().cross(&e2);
+    let det = e1.dot(&dir_cross_e2);
+    if det.abs() < EPSILON {
+        return None; // Parallel
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = ray.origin.tuple() - p1;
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(&e1);
+    let v = f * ray.direction.dot(&origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some((f * e2.dot(&origin_cross_e1), u, v))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        math::{
+            point::{Point, Vector},
+            tuple::{point, pointi, vectori},
+        },
+        ray::Ray,
+        shape::Shape,
+    };
+
+    use super::Triangle;
+
+    fn triangle() -> Triangle {
+        Triangle::new(pointi(0, 1, 0), pointi(-1, 0, 0), pointi(1, 0, 0))
+    }
+
+    #[test]
+    fn construction() {
+        let t = triangle();
+
+        assert_eq!(t.e1, vectori(-1, -1, 0));
+        assert_eq!(t.e2, vectori(1, -1, 0));
+        assert_eq!(t.normal, vectori(0, 0, -1));
+    }
+
+    #[test]
+    fn normal() {
+        let t = triangle();
+
+        for p in [
+            point(0.0, 0.5, 0.0),
+            point(-0.5, 0.75, 0.0),
+            point(0.5, 0.25, 0.0),
+        ] {
+            assert_eq!(t.local_normal_at(p), t.normal);
+        }
+    }
+
+    macro_rules! miss {
+        ($name:ident, $origin:expr, $direction:expr) => {
+            #[test]
+            fn $name() {
+                let r = Ray::new(Point::from_tuple($origin), Vector::from_tuple($direction));
+                assert!(triangle().local_interception(r).is_none());
+            }
+        };
+    }
+
+    miss!(parallel, pointi(0, -1, -2), vectori(0, 1, 0));
+    miss!(past_p1_p3, pointi(1, 1, -2), vectori(0, 0, 1));
+    miss!(past_p1_p2, point(-1.0, 1.0, -2.0), vectori(0, 0, 1));
+    miss!(past_p2_p3, point(0.0, -1.0, -2.0), vectori(0, 0, 1));
+
+    #[test]
+    fn hit() {
+        let t = triangle();
+        let r = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::newi(0, 0, 1));
+
+        let xs = t.local_interception(r).unwrap();
+
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+        assert_eq!((xs[0].u, xs[0].v), (0.25, 0.25));
+    }
+
+    #[test]
+    fn hit_from_behind() {
+        let t = triangle();
+        let r = Ray::new(Point::new(0.0, 0.5, 2.0), Vector::new(0.0, 0.0, -1.0));
+
+        assert_eq!(t.local_interception(r).unwrap()[0].t, 2.0);
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64 as Counter;
+#[cfg(not(target_has_atomic = "64"))]
+use core::sync::atomic::AtomicUsize as Counter;
+use core::sync::atomic::Ordering;
+
+use crate::ray::RayKind;
+
+/// Counters for the work a World does. Atomic so that render threads can all bump them
+/// through a shared &World (or its snapshots), exact ordering between them doesn't matter. On
+/// targets without 64 bit atomics they're pointer sized, and wrap a lot sooner.
+#[derive(Debug, Default)]
+pub struct Stats {
+    primary_rays: Counter,
+    reflection_rays: Counter,
+    refraction_rays: Counter,
+    shadow_rays: Counter,
+    intersection_tests: Counter,
+}
+
+/// A plain copy of Stats at some point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StatsSnapshot {
+    pub primary_rays: u64,
+    pub reflection_rays: u64,
+    pub refraction_rays: u64,
+    pub shadow_rays: u64,
+    /// Ray vs individual shape tests
+    pub intersection_tests: u64,
+}
+
+impl StatsSnapshot {
+    pub fn total_rays(&self) -> u64 {
+        self.primary_rays + self.reflection_rays + self.refraction_rays + self.shadow_rays
+    }
+}
+
+// Counter is only a usize on some targets
+#[allow(clippy::unnecessary_cast)]
+fn read(counter: &Counter) -> u64 {
+    counter.load(Ordering::Relaxed) as u64
+}
+
+impl Stats {
+    pub(crate) fn record_ray(&self, kind: RayKind) {
+        let counter = match kind {
+            RayKind::Primary => &self.primary_rays,
+            RayKind::Reflection => &self.reflection_rays,
+            RayKind::Refraction => &self.refraction_rays,
+            RayKind::Shadow => &self.shadow_rays,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_intersection_tests(&self, count: usize) {
+        self.intersection_tests
+            .fetch_add(count as _, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            primary_rays: read(&self.primary_rays),
+            reflection_rays: read(&self.reflection_rays),
+            refraction_rays: read(&self.refraction_rays),
+            shadow_rays: read(&self.shadow_rays),
+            intersection_tests: read(&self.intersection_tests),
+        }
+    }
+
+    pub fn reset(&self) {
+        for counter in [
+            &self.primary_rays,
+            &self.reflection_rays,
+            &self.refraction_rays,
+            &self.shadow_rays,
+            &self.intersection_tests,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_and_reset() {
+        let stats = Stats::default();
+        stats.record_ray(RayKind::Primary);
+        stats.record_ray(RayKind::Shadow);
+        stats.record_ray(RayKind::Shadow);
+        stats.record_intersection_tests(3);
+
+        let snap = stats.snapshot();
+        assert_eq!(
+            snap,
+            StatsSnapshot {
+                primary_rays: 1,
+                shadow_rays: 2,
+                intersection_tests: 3,
+                ..Default::default()
+            }
+        );
+        assert_eq!(snap.total_rays(), 3);
+
+        stats.reset();
+        assert_eq!(stats.snapshot(), StatsSnapshot::default());
+    }
+}
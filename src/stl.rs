@@ -0,0 +1,393 @@
+//! STL loading, both the binary and ASCII flavours.
+//!
+//! STL has no shared vertices, every facet gets three of its own.
+
+use std::{fmt, io::Read};
+
+use crate::{
+    math::tuple::Tuple,
+    mesh::{face_normal, Mesh},
+    trace,
+};
+
+const HEADER: usize = 80;
+const FACET: usize = 50;
+
+#[derive(Debug)]
+pub enum StlError {
+    Io(std::io::Error),
+    /// A binary file that's shorter than its triangle count says it should be
+    Truncated {
+        expected: usize,
+        got: usize,
+    },
+    /// Something on `line` (1-based) of an ASCII file made no sense
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(e) => write!(f, "reading stl: {e}"),
+            StlError::Truncated { expected, got } => {
+                write!(f, "stl truncated, expected {expected} bytes, got {got}")
+            }
+            StlError::Parse { line, message } => write!(f, "stl line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for StlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StlError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for StlError {
+    fn from(value: std::io::Error) -> Self {
+        StlError::Io(value)
+    }
+}
+
+/// Reads either kind of STL. Binary files are allowed to start with "solid" too (plenty of
+/// exporters do), so anything whose length matches its triangle count is taken as binary first,
+/// and anything with a NUL in it is never ASCII.
+///
+/// Facet normals that are zero or otherwise useless are worked out from the vertices instead,
+/// facets with no area are dropped.
+pub fn parse(mut reader: impl Read) -> Result<Mesh, StlError> {
+    trace::span!(DEBUG, "load_stl");
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    if binary_length(&data) == Some(data.len()) {
+        return parse_binary(&data);
+    }
+
+    if data.trim_ascii_start().starts_with(b"solid") && !data.contains(&0) {
+        return parse_ascii(&data);
+    }
+
+    parse_binary(&data)
+}
+
+/// How long a binary file with this header's triangle count should be
+fn binary_length(data: &[u8]) -> Option<usize> {
+    let count = data.get(HEADER..HEADER + 4)?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    Some(HEADER + 4 + count * FACET)
+}
+
+fn parse_binary(data: &[u8]) -> Result<Mesh, StlError> {
+    let expected = binary_length(data).unwrap_or(HEADER + 4);
+    if data.len() < expected {
+        return Err(StlError::Truncated {
+            expected,
+            got: data.len(),
+        });
+    }
+
+    let mut mesh = Mesh::default();
+    for facet in data[HEADER + 4..expected].chunks_exact(FACET) {
+        let mut floats = facet
+            .chunks_exact(4)
+            .take(12)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64);
+        let mut next = || {
+            let [x, y, z] = [(); 3].map(|_| floats.next().unwrap());
+            (x, y, z)
+        };
+
+        let (nx, ny, nz) = next();
+        let corners = [(); 3].map(|_| {
+            let (x, y, z) = next();
+            Tuple::point(x, y, z)
+        });
+        add_facet(&mut mesh, Tuple::vector(nx, ny, nz), corners);
+    }
+
+    Ok(mesh)
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Mesh, StlError> {
+    let text = String::from_utf8_lossy(data);
+    let mut tokens = Tokens {
+        tokens: text
+            .lines()
+            .enumerate()
+            .flat_map(|(number, line)| line.split_whitespace().map(move |t| (number + 1, t)))
+            .collect(),
+        position: 0,
+        last_line: text.lines().count(),
+    };
+
+    let solid_line = tokens.expect("solid")?;
+    // Skip the (optional, possibly multi word) name
+    while tokens.peek_line() == Some(solid_line) {
+        tokens.position += 1;
+    }
+
+    let mut mesh = Mesh::default();
+    loop {
+        let (line, keyword) = tokens.next("facet or endsolid")?;
+        match keyword {
+            "endsolid" => break,
+            "facet" => {}
+            _ => {
+                return Err(StlError::Parse {
+                    line,
+                    message: format!("expected facet or endsolid, got {keyword}"),
+                })
+            }
+        }
+
+        tokens.expect("normal")?;
+        let normal = tokens.triple()?;
+        tokens.expect("outer")?;
+        tokens.expect("loop")?;
+        let corners = [(); 3].map(|_| -> Result<Tuple, StlError> {
+            tokens.expect("vertex")?;
+            let (x, y, z) = tokens.triple()?;
+            Ok(Tuple::point(x, y, z))
+        });
+        let [a, b, c] = corners;
+        let corners = [a?, b?, c?];
+        tokens.expect("endloop")?;
+        tokens.expect("endfacet")?;
+
+        add_facet(
+            &mut mesh,
+            Tuple::vector(normal.0, normal.1, normal.2),
+            corners,
+        );
+    }
+
+    Ok(mesh)
+}
+
+/// Whitespace separated words of an ASCII STL, with the line each came from
+struct Tokens<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    position: usize,
+    last_line: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn peek_line(&self) -> Option<usize> {
+        self.tokens.get(self.position).map(|(line, _)| *line)
+    }
+
+    fn next(&mut self, wanted: &str) -> Result<(usize, &'a str), StlError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or_else(|| StlError::Parse {
+                line: self.last_line,
+                message: format!("expected {wanted}, file ended"),
+            })?;
+        self.position += 1;
+        Ok(*token)
+    }
+
+    /// Returns the line `wanted` was on
+    fn expect(&mut self, wanted: &str) -> Result<usize, StlError> {
+        match self.next(wanted)? {
+            (line, t) if t == wanted => Ok(line),
+            (line, t) => Err(StlError::Parse {
+                line,
+                message: format!("expected {wanted}, got {t}"),
+            }),
+        }
+    }
+
+    fn triple(&mut self) -> Result<(f64, f64, f64), StlError> {
+        let [x, y, z] = [(); 3].map(|_| -> Result<f64, StlError> {
+            let (line, t) = self.next("a number")?;
+            t.parse().map_err(|e| StlError::Parse {
+                line,
+                message: format!("bad number {t}: {e}"),
+            })
+        });
+        Ok((x?, y?, z?))
+    }
+}
+
+fn add_facet(mesh: &mut Mesh, normal: Tuple, corners: [Tuple; 3]) {
+    let Some(computed) = face_normal(corners) else {
+        return;
+    };
+
+    let usable = [normal.x, normal.y, normal.z].iter().all(|c| c.is_finite())
+        && normal.magnitude() > crate::math::float::EPSILON;
+    let normal = if usable { normal.normalize() } else { computed };
+
+    let first = mesh.vertices.len();
+    mesh.vertices.extend(corners);
+    mesh.faces.push([first, first + 1, first + 2]);
+    mesh.face_normals.push(normal);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::math::tuple::{pointi, vector, vectori};
+
+    use super::*;
+
+    type Facet = ([i32; 3], [[i32; 3]; 3]);
+
+    /// A unit cube, two facets a side, wound counter-clockwise from outside
+    const CUBE: [Facet; 12] = [
+        ([0, 0, -1], [[0, 0, 0], [0, 1, 0], [1, 1, 0]]),
+        ([0, 0, -1], [[0, 0, 0], [1, 1, 0], [1, 0, 0]]),
+        ([0, 0, 1], [[0, 0, 1], [1, 0, 1], [1, 1, 1]]),
+        ([0, 0, 1], [[0, 0, 1], [1, 1, 1], [0, 1, 1]]),
+        ([0, -1, 0], [[0, 0, 0], [1, 0, 0], [1, 0, 1]]),
+        ([0, -1, 0], [[0, 0, 0], [1, 0, 1], [0, 0, 1]]),
+        ([0, 1, 0], [[0, 1, 0], [0, 1, 1], [1, 1, 1]]),
+        ([0, 1, 0], [[0, 1, 0], [1, 1, 1], [1, 1, 0]]),
+        ([-1, 0, 0], [[0, 0, 0], [0, 0, 1], [0, 1, 1]]),
+        ([-1, 0, 0], [[0, 0, 0], [0, 1, 1], [0, 1, 0]]),
+        ([1, 0, 0], [[1, 0, 0], [1, 1, 0], [1, 1, 1]]),
+        ([1, 0, 0], [[1, 0, 0], [1, 1, 1], [1, 0, 1]]),
+    ];
+
+    fn ascii(facets: &[Facet]) -> String {
+        let mut out = "solid a cube\n".to_string();
+        for ([nx, ny, nz], corners) in facets {
+            out += &format!("  facet normal {nx} {ny} {nz}\n    outer loop\n");
+            for [x, y, z] in corners {
+                out += &format!("      vertex {x} {y} {z}\n");
+            }
+            out += "    endloop\n  endfacet\n";
+        }
+        out + "endsolid a cube\n"
+    }
+
+    fn binary(header: &[u8], facets: &[Facet]) -> Vec<u8> {
+        let mut out = header.to_vec();
+        out.resize(HEADER, b' ');
+        out.extend((facets.len() as u32).to_le_bytes());
+        for (normal, corners) in facets {
+            for v in [*normal, corners[0], corners[1], corners[2]] {
+                for c in v {
+                    out.extend((c as f32).to_le_bytes());
+                }
+            }
+            out.extend([0, 0]); // Attribute byte count
+        }
+        out
+    }
+
+    fn check_cube(mesh: &Mesh) {
+        assert_eq!(mesh.len(), 12);
+        assert_eq!(mesh.vertices.len(), 36);
+        for (i, ([nx, ny, nz], [a, b, c])) in CUBE.iter().enumerate() {
+            assert_eq!(mesh.face_normals[i], vectori(*nx, *ny, *nz));
+            assert_eq!(
+                mesh.triangle(i),
+                [a, b, c].map(|&[x, y, z]| pointi(x, y, z))
+            );
+            // The fixture itself should be wound the right way
+            assert_eq!(face_normal(mesh.triangle(i)), Some(mesh.face_normals[i]));
+        }
+    }
+
+    #[test]
+    fn ascii_cube() {
+        let mesh = parse(ascii(&CUBE).as_bytes()).unwrap();
+        check_cube(&mesh);
+    }
+
+    #[test]
+    fn binary_cube() {
+        let mesh = parse(&binary(b"made by hand", &CUBE)[..]).unwrap();
+        check_cube(&mesh);
+        assert_eq!(mesh, parse(ascii(&CUBE).as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn binary_starting_with_solid() {
+        let mesh = parse(&binary(b"solid cube exported by something", &CUBE)[..]).unwrap();
+        check_cube(&mesh);
+    }
+
+    #[test]
+    fn garbage_normals_recomputed() {
+        let mut facets = CUBE;
+        facets[0].0 = [0, 0, 0];
+        let mut data = binary(b"", &facets);
+        // Second facet's normal x becomes NaN
+        data[HEADER + 4 + FACET..][..4].copy_from_slice(&f32::NAN.to_le_bytes());
+
+        let mesh = parse(&data[..]).unwrap();
+        check_cube(&mesh);
+    }
+
+    #[test]
+    fn unnormalised_normal() {
+        let mut facets = CUBE;
+        facets[0].0 = [0, 0, -5];
+        let mesh = parse(ascii(&facets).as_bytes()).unwrap();
+        assert_eq!(mesh.face_normals[0], vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn degenerate_facet_dropped() {
+        let mut facets = CUBE;
+        facets[0].1 = [[0, 0, 0], [1, 0, 0], [2, 0, 0]];
+        assert_eq!(parse(ascii(&facets).as_bytes()).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn truncated_binary() {
+        for header in [&b"binary"[..], b"solid but binary"] {
+            let data = binary(header, &CUBE);
+            let short = &data[..data.len() - 10];
+
+            match parse(short) {
+                Err(StlError::Truncated { expected, got }) => {
+                    assert_eq!((expected, got), (data.len(), data.len() - 10))
+                }
+                other => panic!("{other:?}"),
+            }
+        }
+
+        assert!(matches!(
+            parse(&b"tiny"[..]),
+            Err(StlError::Truncated {
+                expected: 84,
+                got: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated_ascii() {
+        let text = ascii(&CUBE);
+        let short = &text[..text.len() / 2];
+        let lines = short.lines().count();
+
+        match parse(short.as_bytes()) {
+            Err(StlError::Parse { line, .. }) => assert_eq!(line, lines),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn bad_ascii() {
+        let text = ascii(&CUBE).replacen("vertex 0 1 0", "vertex 0 one 0", 1);
+        match parse(text.as_bytes()) {
+            Err(StlError::Parse { line, message }) => {
+                assert_eq!(line, 5);
+                assert!(message.contains("one"), "{message}");
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! Helpers shared by the tests of the library, the binaries and tests/. Those are all separate
+//! crates, so the binaries and tests/ pull this file in with `#[path]`.
+
+use std::{env, fs, path::PathBuf};
+
+/// A fresh, empty directory for one test. `name` should be unique across every test there is,
+/// the process id keeps runs side by side apart.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("raytracer-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,135 @@
+//! Instrumentation hooks. With the `tracing` feature these are tracing's span!/event!, without
+//! it they expand to nothing at all, arguments included.
+
+/// Enter a span until the end of the enclosing block, eg `trace::span!(DEBUG, "render", x = 1)`
+macro_rules! span {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($args)*).entered();
+    };
+}
+
+/// eg `trace::event!(DEBUG, object = index, "skipped")`
+macro_rules! event {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($args)*);
+    };
+}
+
+pub(crate) use {event, span};
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use std::{
+        f64::consts::FRAC_PI_2,
+        fmt::Debug,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    use crate::{
+        camera::Camera,
+        materials::Material,
+        math::{
+            matrix::Matrix,
+            tuple::{pointi, vectori},
+        },
+        shape::{group::Group, sphere::Sphere},
+        world::World,
+    };
+
+    /// Writes down the name of every span opened and the message of every event
+    #[derive(Default)]
+    struct Capture {
+        seen: Arc<Mutex<Vec<String>>>,
+        next_id: AtomicU64,
+    }
+
+    struct Message<'a>(&'a mut String);
+
+    impl Visit for Message<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            if field.name() == "message" {
+                *self.0 = format!("{value:?}");
+            }
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(span.metadata().name().to_owned());
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut message = String::new();
+            event.record(&mut Message(&mut message));
+            self.seen.lock().unwrap().push(message);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn render_is_instrumented() {
+        let camera = Camera::new_with_transform(
+            3,
+            3,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        );
+
+        let capture = Capture::default();
+        let seen = capture.seen.clone();
+        tracing::subscriber::with_default(capture, || {
+            let mut world = World::default();
+            // Squashed flat, it has no inverse and can't be hit. That's said as it's added, not
+            // on every ray.
+            world.add_object(Box::new(Sphere::new(
+                Matrix::scaling(0.0, 0.0, 0.0),
+                Material::default(),
+            )));
+            // As is one inside a group, though the group itself is fine
+            let mut group = Group::default();
+            group.add_child(Box::new(Sphere::new_with_transform(Matrix::scaling(
+                1.0, 0.0, 1.0,
+            ))));
+            world.add_object(Box::new(group));
+            camera.render(&world)
+        });
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0], "non-invertible transform, object skipped");
+        assert_eq!(seen[1], "non-invertible transform, object skipped");
+        assert_eq!(seen[2], "render");
+        assert!(seen.iter().any(|s| s == "colour_at"));
+        assert_eq!(
+            seen.iter()
+                .filter(|s| *s == "non-invertible transform, object skipped")
+                .count(),
+            2
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Re-rendering a file whenever it's saved. Changes are found by polling its modification time
+//! and size, which is plenty for one file and needs nothing from the OS.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Notices when a file's been changed, created or deleted since it was last looked at
+#[derive(Debug, Clone)]
+pub struct FileWatcher {
+    path: PathBuf,
+    last: Option<(SystemTime, u64)>,
+}
+
+impl FileWatcher {
+    /// Whatever's there now counts as already seen
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_owned();
+        let last = stamp(&path);
+        Self { path, last }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file's different to last time this was called
+    pub fn changed(&mut self) -> bool {
+        let now = stamp(&self.path);
+        let changed = now != self.last;
+        self.last = now;
+        changed
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// What Debouncer says to do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAction {
+    Wait,
+    /// Start rendering, and call Debouncer::finished once it's done
+    Render,
+    /// The render that's running is out of date, stop it
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    Rendering,
+    /// Rendering, but already told to cancel
+    Cancelling,
+}
+
+/// Decides when to render as changes come in. Editors often save in a few steps (or the user
+/// saves a few times in a row), so a render only starts once there's been no change for
+/// `quiet`. A change during a render cancels it, and the next one starts once things are quiet
+/// again. Times are passed in, so it's driven the same way in tests as for real.
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+    quiet: Duration,
+    last_change: Option<Instant>,
+    state: State,
+}
+
+impl Debouncer {
+    pub fn new(quiet: Duration) -> Self {
+        Self {
+            quiet,
+            last_change: None,
+            state: State::Idle,
+        }
+    }
+
+    /// The file changed at `now`. Only says Cancel once per render.
+    pub fn changed(&mut self, now: Instant) -> WatchAction {
+        self.last_change = Some(now);
+        if self.state == State::Rendering {
+            self.state = State::Cancelling;
+            WatchAction::Cancel
+        } else {
+            WatchAction::Wait
+        }
+    }
+
+    /// Whether it's time to render yet, call this regularly
+    pub fn poll(&mut self, now: Instant) -> WatchAction {
+        match self.last_change {
+            Some(at)
+                if self.state == State::Idle && now.saturating_duration_since(at) >= self.quiet =>
+            {
+                self.last_change = None;
+                self.state = State::Rendering;
+                WatchAction::Render
+            }
+            _ => WatchAction::Wait,
+        }
+    }
+
+    /// The render poll asked for is over, whether it finished, was cancelled or never got
+    /// started
+    pub fn finished(&mut self) {
+        self.state = State::Idle;
+    }
+
+    pub fn is_rendering(&self) -> bool {
+        self.state != State::Idle
+    }
+}
+
+/// Writes `bytes` to a temporary file beside `path` and renames it over the top, so anything
+/// reading `path` sees either the old contents or the new, never half of them. On failure
+/// `path` is left as it was.
+pub fn write_atomic(path: impl AsRef<Path>, bytes: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+
+    let result = fs::write(&temp, bytes).and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        time::{Duration, Instant},
+    };
+
+    use crate::test_util::scratch_dir;
+
+    use super::{write_atomic, Debouncer, FileWatcher, WatchAction};
+
+    #[test]
+    fn debounce() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut d = Debouncer::new(Duration::from_millis(100));
+        assert_eq!(d.poll(at(0)), WatchAction::Wait);
+
+        // A burst of saves only renders once they've stopped
+        assert_eq!(d.changed(at(0)), WatchAction::Wait);
+        assert_eq!(d.changed(at(50)), WatchAction::Wait);
+        assert_eq!(d.poll(at(120)), WatchAction::Wait);
+        assert_eq!(d.changed(at(140)), WatchAction::Wait);
+        assert_eq!(d.poll(at(239)), WatchAction::Wait);
+        assert_eq!(d.poll(at(240)), WatchAction::Render);
+        assert!(d.is_rendering());
+        assert_eq!(d.poll(at(1000)), WatchAction::Wait);
+
+        d.finished();
+        assert!(!d.is_rendering());
+        assert_eq!(d.poll(at(2000)), WatchAction::Wait);
+    }
+
+    #[test]
+    fn cancel() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut d = Debouncer::new(Duration::from_millis(100));
+        d.changed(at(0));
+        assert_eq!(d.poll(at(100)), WatchAction::Render);
+
+        // Cancelled straight away, not once things go quiet
+        assert_eq!(d.changed(at(150)), WatchAction::Cancel);
+        assert_eq!(d.changed(at(160)), WatchAction::Wait);
+        // Nothing new starts until the old one's actually stopped
+        assert_eq!(d.poll(at(500)), WatchAction::Wait);
+        d.finished();
+        assert_eq!(d.poll(at(501)), WatchAction::Render);
+
+        // Changes while it's idle never cancel
+        d.finished();
+        assert_eq!(d.changed(at(600)), WatchAction::Wait);
+    }
+
+    #[test]
+    fn file_watcher() {
+        let dir = scratch_dir("watch-watcher");
+        let path = dir.join("scene.yaml");
+        let mut watcher = FileWatcher::new(&path);
+        assert!(!watcher.changed());
+
+        fs::write(&path, "one").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        // Same modification time (at least on coarse filesystems), different size
+        fs::write(&path, "three").unwrap();
+        assert!(watcher.changed());
+
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn atomic() {
+        let dir = scratch_dir("watch-atomic");
+        let path = dir.join("out.png");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Can't be renamed over a directory with things in it, the temp file's tidied up
+        let blocked = dir.join("blocked");
+        fs::create_dir_all(blocked.join("inside")).unwrap();
+        assert!(write_atomic(&blocked, b"nope").is_err());
+        assert!(blocked.join("inside").is_dir());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        assert!(write_atomic(dir.join("missing/out.png"), b"nope").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
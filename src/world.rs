@@ -1,68 +1,577 @@
+use alloc::{collections::BinaryHeap, sync::Arc};
+use core::{cmp::Ordering, ops::Deref};
+
 use crate::{
     colour::Colour,
-    intersection::{IntersectVec, Intersection, IntersectionComputions},
+    error::RayTracerError,
+    intersection::{HitRecord, IntersectVec, Intersection, IntersectionComputions, ObjectId},
     lights::{Light, PointLight},
-    materials::Material,
+    materials::{Material, MaterialError, Sidedness},
     math::{
+        float::EPSILON,
         matrix::Matrix,
+        point::{Point, Vector},
         tuple::{pointi, Tuple},
     },
-    ray::{Ray, RayIntersect},
+    medium::Medium,
+    pattern::solid::SolidPattern,
+    photon_map::PhotonMap,
+    prelude::*,
+    ray::{Ray, RayIntersect, RayKind},
+    scene::RenderSettings,
     shape::{sphere::Sphere, Shape},
+    stats::Stats,
+    trace,
 };
 
+mod caustics;
+mod fog;
+mod passes;
+mod path_trace;
+
+use passes::PassCollector;
+pub use passes::{PassColours, PassId};
+
+/// How many bounces deep colour_at will follow reflections before giving up.
+pub const MAX_DEPTH: usize = 5;
+
+/// Past this many bounces a patterned surface is shaded with the pattern's average colour,
+/// that far down a reflection the detail can't be made out anyway
+pub const FLAT_PATTERN_DEPTH: usize = 2;
+
+/// Objects and lights are reference counted so they can be shared, with another world or with
+/// a snapshot. Something that's shared can't be changed through a World: object_mut gives None
+/// for it and objects_mut skips it, until whatever else holds it lets go.
+///
+/// Nothing is worked out from the objects ahead of time and kept, every ray looks at them as
+/// they are. So a change to one, through object_mut, objects_mut or a shared shape's own
+/// interior mutability, shows up from the next ray on.
 #[derive(Debug)]
 pub struct World {
-    pub objects: Vec<Box<dyn Shape>>,
-    pub light: Vec<Box<dyn Light>>,
+    objects: Vec<Arc<dyn Shape>>,
+    light: Vec<Arc<dyn Light>>,
+    /// What fills the space between objects, if anything
+    medium: Option<Medium>,
+    /// How far off a surface rays leaving it start, so they don't hit it again. See with_bias.
+    bias: f64,
+    /// Rays cast and intersection tests done against this world so far, including by its
+    /// snapshots
+    pub stats: Arc<Stats>,
+}
+
+/// Problems with a World that would otherwise only show up as garbage (or panics) mid-render.
+/// `index` is the offending object's position in `World::objects()`, or for something inside a
+/// group, the group's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValidationIssue {
+    NonFiniteTransform { index: usize },
+    NonInvertibleTransform { index: usize },
+    BadMaterial { index: usize, error: MaterialError },
+}
+
+/// Building, inspecting and editing the contents of the world
+impl World {
+    pub fn new(objects: Vec<Box<dyn Shape>>, lights: Vec<Box<dyn Light>>) -> Self {
+        Self {
+            objects: vec![],
+            light: lights.into_iter().map(Arc::from).collect(),
+            medium: None,
+            bias: EPSILON,
+            stats: Default::default(),
+        }
+        .with_objects(objects)
+    }
+
+    /// This world, with `objects` in place of whatever it had
+    pub fn with_objects(mut self, objects: Vec<Box<dyn Shape>>) -> Self {
+        self.objects.clear();
+        for object in objects {
+            self.add_object(object);
+        }
+        self
+    }
+
+    /// This world, with `lights` in place of whatever it had
+    pub fn with_lights(mut self, lights: Vec<Box<dyn Light>>) -> Self {
+        self.light = lights.into_iter().map(Arc::from).collect();
+        self
+    }
+
+    /// This world, filled with `medium`. Only what's seen straight from the camera is looked at
+    /// through it, reflections and refractions see clear air.
+    pub fn with_medium(mut self, medium: Medium) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+
+    pub fn medium(&self) -> Option<&Medium> {
+        self.medium.as_ref()
+    }
+
+    pub fn set_medium(&mut self, medium: Option<Medium>) {
+        self.medium = medium;
+    }
+
+    /// This world, with reflection, refraction and shadow rays starting `bias` off the surface
+    /// they leave (see IntersectionComputions::over_point) instead of EPSILON. EPSILON suits
+    /// scenes measured in units or so. Much bigger ones need a bigger bias to keep surfaces from
+    /// shadowing themselves, and tiny ones a smaller one so light doesn't leak where things
+    /// touch.
+    pub fn with_bias(mut self, bias: f64) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    pub fn bias(&self) -> f64 {
+        self.bias
+    }
+
+    pub fn set_bias(&mut self, bias: f64) {
+        self.bias = bias;
+    }
+
+    /// Every top level object, in order. Their index here is what ObjectId and
+    /// ValidationIssue refer to.
+    pub fn objects(&self) -> impl ExactSizeIterator<Item = &dyn Shape> {
+        self.into_iter()
+    }
+
+    /// Every object that isn't shared, see World
+    pub fn objects_mut(&mut self) -> impl Iterator<Item = &mut dyn Shape> {
+        self.objects
+            .iter_mut()
+            .filter_map(|o| Arc::get_mut(o).map(|o| o as &mut dyn Shape))
+    }
+
+    pub fn object(&self, index: usize) -> Option<&dyn Shape> {
+        self.objects.get(index).map(|o| &**o)
+    }
+
+    /// None if there's no such object, or if it's shared
+    pub fn object_mut(&mut self, index: usize) -> Option<&mut dyn Shape> {
+        self.objects
+            .get_mut(index)
+            .and_then(|o| Arc::get_mut(o).map(|o| o as &mut dyn Shape))
+    }
+
+    /// A handle on the object at `index`, for putting the same object in another world
+    /// (add_shared_object) without copying it
+    pub fn shared_object(&self, index: usize) -> Option<Arc<dyn Shape>> {
+        self.objects.get(index).cloned()
+    }
+
+    /// Objects whose transform can't be rendered with are never hit. With the tracing feature,
+    /// that's reported here rather than on every ray that misses them.
+    pub fn add_object(&mut self, object: Box<dyn Shape>) {
+        self.add_shared_object(object.into());
+    }
+
+    pub fn add_shared_object(&mut self, object: Arc<dyn Shape>) {
+        note_skipped(self.objects.len(), &*object);
+        self.objects.push(object);
+    }
+
+    /// Takes the object at `index` out of the world, everything after it shifts down one.
+    /// Panics if there's no such object, like Vec::remove.
+    pub fn remove_object(&mut self, index: usize) -> Arc<dyn Shape> {
+        self.objects.remove(index)
+    }
+
+    /// Keep only the objects `keep` returns true for, eg
+    /// `world.retain_objects(|o| o.material().transparency == 0.0)`
+    pub fn retain_objects(&mut self, mut keep: impl FnMut(&dyn Shape) -> bool) {
+        self.objects.retain(|o| keep(&**o));
+    }
+
+    pub fn lights(&self) -> impl ExactSizeIterator<Item = &dyn Light> {
+        self.light.iter().map(|l| &**l)
+    }
+
+    /// Every light that isn't shared, see World
+    pub fn lights_mut(&mut self) -> impl Iterator<Item = &mut dyn Light> {
+        self.light
+            .iter_mut()
+            .filter_map(|l| Arc::get_mut(l).map(|l| l as &mut dyn Light))
+    }
+
+    pub fn add_light(&mut self, light: Box<dyn Light>) {
+        self.light.push(light.into());
+    }
+
+    /// A read only copy of this world as it is now, for handing to render threads. Only the
+    /// lists are copied, the objects and lights themselves (and stats) are shared, so it's
+    /// cheap however heavy they are. Changes to the world afterwards don't show up in the
+    /// snapshot, and while it's around the world can't change anything it shares.
+    pub fn snapshot(&self) -> WorldView {
+        WorldView(World {
+            objects: self.objects.clone(),
+            light: self.light.clone(),
+            medium: self.medium,
+            bias: self.bias,
+            stats: self.stats.clone(),
+        })
+    }
+}
+
+/// See World::snapshot. Everything a World can do without `&mut`.
+#[derive(Debug)]
+pub struct WorldView(World);
+
+impl Clone for WorldView {
+    fn clone(&self) -> Self {
+        self.0.snapshot()
+    }
+}
+
+impl Deref for WorldView {
+    type Target = World;
+
+    fn deref(&self) -> &World {
+        &self.0
+    }
+}
+
+impl<'a> IntoIterator for &'a World {
+    type Item = &'a dyn Shape;
+    type IntoIter = core::iter::Map<
+        core::slice::Iter<'a, Arc<dyn Shape>>,
+        fn(&'a Arc<dyn Shape>) -> &'a dyn Shape,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.objects.iter().map(|o| &**o)
+    }
+}
+
+impl IntoIterator for World {
+    type Item = Arc<dyn Shape>;
+    type IntoIter = alloc::vec::IntoIter<Arc<dyn Shape>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.objects.into_iter()
+    }
+}
+
+fn transform_issue(index: usize, object: &dyn Shape) -> Option<ValidationIssue> {
+    if !object.transform().is_finite() {
+        Some(ValidationIssue::NonFiniteTransform { index })
+    } else if !object.transform().can_invert() {
+        Some(ValidationIssue::NonInvertibleTransform { index })
+    } else {
+        None
+    }
 }
 
-// SAFETY: Safe because we only ever read from Shape and Light after construct.
-// This does however imply that Shape and Light are always safe to read from using their methods.
-// we can be "sure" of this because Shape and Light dont have Mut references.
-unsafe impl Send for World {}
-unsafe impl Sync for World {}
+/// Everything wrong with `object` and, if it's a group, everything in it
+fn object_issues(index: usize, object: &dyn Shape, issues: &mut Vec<ValidationIssue>) {
+    issues.extend(transform_issue(index, object));
+    if let Err(error) = object.material().validate() {
+        issues.push(ValidationIssue::BadMaterial { index, error });
+    }
+    for child in object.children() {
+        object_issues(index, &**child, issues);
+    }
+}
 
 impl World {
-    pub fn intersect_world(&self, ray: Ray) -> Vec<Intersection> {
+    /// Check every object, and everything inside groups, for transforms we can't render with
+    /// and materials out of range. Objects with bad transforms are skipped by intersect_world,
+    /// so they'd silently vanish from the image.
+    pub fn validate(&self) -> Result<(), Vec<ValidationIssue>> {
+        let mut issues = vec![];
+        for (index, o) in self.objects.iter().enumerate() {
+            object_issues(index, &**o, &mut issues);
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// The first problem validate would report, as an error naming the object. Meant to be called
+    /// once before rendering (see Camera::try_render), not per ray.
+    pub fn check_renderable(&self) -> Result<(), RayTracerError> {
+        for (index, o) in self.objects.iter().enumerate() {
+            let mut issues = vec![];
+            object_issues(index, &**o, &mut issues);
+            if let Some(&issue) = issues.first() {
+                return Err(RayTracerError::BadObject {
+                    object: ObjectId::new(index, &**o),
+                    issue,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn intersect_world(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        self.record(ray);
         let mut xs: Vec<_> = self
             .objects
             .iter()
-            .flat_map(|s| s.intersect(ray).unwrap_or_default())
+            .flat_map(|s| object_hits(&**s, ray))
+            .filter(|i| i.t.is_finite())
             .collect();
 
         xs.sort_by(|a, b| a.t.total_cmp(&b.t));
         xs
     }
 
-    pub fn shade_hit(&self, comps: IntersectionComputions) -> Colour {
-        let count = self.light.len() as f64;
-        self.light
+    /// The id for the object at `index`, if there is one.
+    pub fn object_id(&self, index: usize) -> Option<ObjectId> {
+        self.objects.get(index).map(|o| ObjectId::new(index, &**o))
+    }
+
+    /// Look up an object by id. None if the id is stale (the object was removed or moved).
+    pub fn resolve(&self, id: ObjectId) -> Option<&dyn Shape> {
+        self.objects.get(id.index()).and_then(|o| id.within(&**o))
+    }
+
+    /// intersect_world, but returning owned records that don't borrow from the world.
+    pub fn intersect_world_ids(&self, ray: Ray) -> Vec<HitRecord> {
+        self.record(ray);
+        let mut xs: Vec<_> = self
+            .objects
             .iter()
-            .map(|l| {
-                comps.object.material().lighting(
-                    &**l,
-                    comps.over_point,
-                    comps.eye_vector,
-                    comps.normal_vector,
-                    self.is_shadowed_by(&**l, comps.over_point),
-                )
+            .enumerate()
+            .flat_map(|(index, s)| {
+                // The id is of whatever was actually hit, which for groups is a child
+                object_hits(&**s, ray)
+                    .into_iter()
+                    .map(move |i| HitRecord::new(&i, ObjectId::new(index, i.object)))
             })
-            .reduce(|acc, c| acc + (c / count))
-            .unwrap()
+            .filter(|i| i.t.is_finite())
+            .collect();
+
+        xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+        xs
+    }
+
+    /// Every hit along `ray` in ascending t order, exactly what intersect_world would give in the
+    /// same order. Each object is still intersected (and its hits sorted) up front; only the
+    /// merge across objects happens as you iterate, so stopping after the first few surfaces
+    /// saves the full sort, not the intersection tests.
+    pub fn trace(&self, ray: Ray) -> impl Iterator<Item = Intersection<'_>> {
+        self.record(ray);
+        let per_object: Vec<_> = self
+            .objects
+            .iter()
+            .map(|s| {
+                let mut xs: Vec<_> = object_hits(&**s, ray)
+                    .into_iter()
+                    .filter(|i| i.t.is_finite())
+                    .collect();
+                xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+                xs
+            })
+            .collect();
+
+        let heap = per_object
+            .iter()
+            .enumerate()
+            .filter_map(|(object, xs)| {
+                xs.first().map(|&hit| TraceEntry {
+                    hit,
+                    object,
+                    next: 1,
+                })
+            })
+            .collect();
+
+        Trace { per_object, heap }
+    }
+
+    pub fn shade_hit(&self, comps: IntersectionComputions) -> Colour {
+        self.shade_hit_with(comps, &RenderSettings::DEFAULT, None)
+    }
+
+    /// shade_hit, added up into whichever PassCollector's wanted
+    fn shade_hit_with<P: PassCollector>(
+        &self,
+        comps: IntersectionComputions,
+        settings: &RenderSettings,
+        caustics: Option<&PhotonMap>,
+    ) -> P {
+        let material = comps.object.material();
+        if let Some(outline) = material
+            .shading
+            .outline_at(comps.eye_vector.dot(&comps.normal_vector))
+        {
+            return P::only(PassId::Emission, outline);
+        }
+
+        let surface = match (comps.inside, material.sidedness) {
+            (true, Sidedness::FrontOnly) => P::default(),
+            (true, Sidedness::BackTinted(colour)) => self.surface_colour(
+                &material.clone().with_colour(colour),
+                &comps,
+                settings,
+                caustics,
+            ),
+            _ if comps.depth > FLAT_PATTERN_DEPTH && material.colour().is_none() => self
+                .surface_colour(
+                    &material.clone().with_colour(material.pattern.average()),
+                    &comps,
+                    settings,
+                    caustics,
+                ),
+            _ => self.surface_colour(material, &comps, settings, caustics),
+        };
+
+        let mut reflected = self.reflected_colour_with(&comps, settings, caustics);
+        let mut refracted = self.refracted_colour_with(&comps, settings, caustics);
+        if material.reflective > 0.0 && material.transparency > 0.0 {
+            let reflectance = comps.schlick();
+            reflected *= reflectance;
+            refracted *= 1.0 - reflectance;
+        }
+
+        surface + P::only(PassId::Reflection, reflected) + P::only(PassId::Refraction, refracted)
+    }
+
+    /// Direct lighting from every light added up, plus whatever `caustics` has landed here
+    fn surface_colour<P: PassCollector>(
+        &self,
+        material: &Material,
+        comps: &IntersectionComputions,
+        settings: &RenderSettings,
+        caustics: Option<&PhotonMap>,
+    ) -> P {
+        let caustic = match caustics {
+            Some(photons) if material.diffuse > 0.0 => {
+                let irradiance = photons.irradiance(
+                    comps.point,
+                    comps.normal_vector,
+                    settings.caustics.nearest,
+                    settings.caustics.radius,
+                );
+                material.colour_at(comps.object_point) * irradiance * material.diffuse
+            }
+            _ => Colour::BLACK,
+        };
+
+        let direct = self.light.iter().fold(P::default(), |acc, l| {
+            let visible = if material.receive_shadows {
+                self.light_intensity_at(&**l, comps.over_point)
+            } else {
+                1.0
+            };
+            acc + P::lit(material, &**l, comps, visible)
+        });
+        direct + P::only(PassId::Diffuse, caustic)
     }
 
     pub fn colour_at(&self, ray: Ray) -> Colour {
+        self.colour_at_with(ray, &RenderSettings::DEFAULT)
+    }
+
+    /// colour_at, but with the recursion limit and background from `settings`
+    pub fn colour_at_with(&self, ray: Ray, settings: &RenderSettings) -> Colour {
+        self.colour_at_with_caustics(ray, settings, None)
+    }
+
+    /// colour_at_with, adding in the caustics from `caustics` (see caustic_photons) wherever
+    /// they've landed
+    pub fn colour_at_with_caustics(
+        &self,
+        ray: Ray,
+        settings: &RenderSettings,
+        caustics: Option<&PhotonMap>,
+    ) -> Colour {
+        self.colour_at_into(ray, settings, caustics)
+    }
+
+    /// The one place a ray's colour is worked out, added up into whichever PassCollector's
+    /// wanted. Only the top level is split into passes, what's seen in reflections and through
+    /// refractions is followed as a plain Colour.
+    fn colour_at_into<P: PassCollector>(
+        &self,
+        ray: Ray,
+        settings: &RenderSettings,
+        caustics: Option<&PhotonMap>,
+    ) -> P {
+        trace::span!(TRACE, "colour_at", depth = ray.depth);
+        if ray.depth > settings.max_depth {
+            return P::default();
+        }
+
         let xs = self.intersect_world(ray);
         let hit = xs.hit();
+        let colour = match hit {
+            Some(hit) => {
+                let comps = hit.prepare_computations_with_bias(ray, &xs, self.bias);
+                self.shade_hit_with(comps, settings, caustics)
+            }
+            None => P::only(PassId::Background, settings.background),
+        };
+
+        match &self.medium {
+            Some(medium) if ray.kind == RayKind::Primary => {
+                let distance = hit
+                    .map_or(f64::INFINITY, |hit| hit.t * ray.direction.magnitude())
+                    .min(medium.max_distance);
+                let (transmittance, scattered) = self.medium_along(ray, distance, medium);
+                colour * transmittance + P::only(PassId::Volume, scattered)
+            }
+            _ => colour,
+        }
+    }
+
+    pub fn reflected_colour(&self, comps: &IntersectionComputions) -> Colour {
+        self.reflected_colour_with(comps, &RenderSettings::DEFAULT, None)
+    }
+
+    fn reflected_colour_with(
+        &self,
+        comps: &IntersectionComputions,
+        settings: &RenderSettings,
+        caustics: Option<&PhotonMap>,
+    ) -> Colour {
+        let reflective = comps.object.material().reflective;
+        if reflective == 0.0 || comps.depth >= settings.max_depth {
+            return Colour::BLACK;
+        }
 
-        if xs.hit().is_none() {
+        self.colour_at_into::<Colour>(comps.reflected_ray(), settings, caustics) * reflective
+    }
+
+    pub fn refracted_colour(&self, comps: &IntersectionComputions) -> Colour {
+        self.refracted_colour_with(comps, &RenderSettings::DEFAULT, None)
+    }
+
+    fn refracted_colour_with(
+        &self,
+        comps: &IntersectionComputions,
+        settings: &RenderSettings,
+        caustics: Option<&PhotonMap>,
+    ) -> Colour {
+        let transparency = comps.object.material().transparency;
+        if transparency == 0.0 || comps.depth >= settings.max_depth {
             return Colour::BLACK;
         }
 
-        let hit = hit.unwrap();
+        match comps.refracted_ray() {
+            Some(ray) => self.colour_at_into::<Colour>(ray, settings, caustics) * transparency,
+            None => Colour::BLACK, // Total internal reflection
+        }
+    }
+
+    /// Debugging aid: the depth of the deepest surface hit along `ray`'s reflection chain.
+    /// 0 for a plain hit (or a miss), 1 if a reflection off of that hit landed somewhere, etc.
+    pub fn bounce_depth_at(&self, ray: Ray) -> usize {
+        let Some(hit) = self.closest_hit(ray) else {
+            return ray.depth.saturating_sub(1);
+        };
+
+        let comps = hit.prepare_computations_with_bias(ray, &[hit], self.bias);
+        if comps.object.material().reflective == 0.0 || comps.depth >= MAX_DEPTH {
+            return comps.depth;
+        }
 
-        self.shade_hit(hit.prepare_computations(ray))
+        self.bounce_depth_at(comps.reflected_ray())
     }
 
     pub fn is_shadowed(&self, point: Tuple) -> bool {
@@ -72,44 +581,205 @@ impl World {
     fn is_shadowed_by(&self, light: &dyn Light, point: Tuple) -> bool {
         let v = *light.position() - point;
         let distance = v.magnitude();
-        let direction = v.normalize();
-        let xs = self.intersect_world(Ray::new(point, direction));
+        // Right at the light there's nothing in between, and no direction to look in
+        if distance == 0.0 {
+            return false;
+        }
 
-        let hit = xs.hit();
+        let ray = Ray::new(Point::from_tuple(point), Vector::from_tuple(v.normalize()))
+            .with_kind(RayKind::Shadow);
+        self.any_hit_between(ray, 0.0, distance)
+    }
+
+    /// How much of `light` reaches `point`, as passed to Material::lighting. Point lights are all
+    /// or nothing.
+    fn light_intensity_at(&self, light: &dyn Light, point: Tuple) -> f64 {
+        if self.is_shadowed_by(light, point) {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Backs World::trace. Holds each object's (sorted) hits and a heap of the next unseen hit for
+/// each object.
+struct Trace<'a> {
+    per_object: Vec<Vec<Intersection<'a>>>,
+    heap: BinaryHeap<TraceEntry<'a>>,
+}
+
+struct TraceEntry<'a> {
+    hit: Intersection<'a>,
+    object: usize,
+    next: usize,
+}
+
+impl Ord for TraceEntry<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap, so this is backwards. Ties go to the earlier object, which
+        // is what intersect_world's stable sort does.
+        other
+            .hit
+            .t
+            .total_cmp(&self.hit.t)
+            .then(other.object.cmp(&self.object))
+    }
+}
+
+impl PartialOrd for TraceEntry<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for TraceEntry<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TraceEntry<'_> {}
+
+impl<'a> Iterator for Trace<'a> {
+    type Item = Intersection<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let TraceEntry { hit, object, next } = self.heap.pop()?;
+
+        if let Some(&following) = self.per_object[object].get(next) {
+            self.heap.push(TraceEntry {
+                hit: following,
+                object,
+                next: next + 1,
+            });
+        }
+
+        Some(hit)
+    }
+}
+
+/// Queries that don't need shading, for visibility checks and the like.
+impl World {
+    /// Distance along `ray` to the nearest surface in front of its origin. This is a real
+    /// distance, so a non-normalized direction doesn't skew it.
+    pub fn distance_to_first_hit(&self, ray: Ray) -> Option<f64> {
+        self.closest_hit(ray)
+            .map(|hit| hit.t * ray.direction.magnitude())
+    }
+
+    /// The nearest hit along `ray`, along with the world-space point it lands on and the
+    /// surface normal there (flipped to face back along the ray, as with shading).
+    pub fn first_hit(&self, ray: Ray) -> Option<(Intersection<'_>, Tuple, Tuple)> {
+        self.closest_hit(ray).map(|hit| {
+            let comps = hit.prepare_computations(ray);
+            (hit, comps.point, comps.normal_vector)
+        })
+    }
+
+    /// Whether anything sits strictly between `a` and `b`. Surfaces within EPSILON of either
+    /// end don't count, so points lying exactly on a surface can still see each other.
+    pub fn line_of_sight(&self, a: Tuple, b: Tuple) -> bool {
+        let v = b - a;
+        let distance = v.magnitude();
+        if distance <= 2.0 * EPSILON {
+            return true;
+        }
+
+        let ray = Ray::new(Point::from_tuple(a), Vector::from_tuple(v.normalize()));
+        !self.any_hit_between(ray, EPSILON, distance - EPSILON)
+    }
+
+    fn record(&self, ray: Ray) {
+        self.stats.record_ray(ray.kind);
+        self.stats.record_intersection_tests(self.objects.len());
+    }
+
+    fn closest_hit(&self, ray: Ray) -> Option<Intersection<'_>> {
+        self.intersect_world(ray).hit()
+    }
+
+    /// Is there any hit with `t_min <= t < t_max`? Bails on the first one found, no sorting.
+    fn any_hit_between(&self, ray: Ray, t_min: f64, t_max: f64) -> bool {
+        let blocker = self.objects.iter().position(|s| {
+            s.intersect(ray)
+                .unwrap_or_default()
+                .iter()
+                .any(|i| i.t.is_finite() && i.t >= t_min && i.t < t_max)
+        });
+
+        // Counted once per ray, like record, rather than once per object tested
+        self.stats.record_ray(ray.kind);
+        self.stats
+            .record_intersection_tests(blocker.map_or(self.objects.len(), |i| i + 1));
+        blocker.is_some()
+    }
+}
 
-        hit.is_some_and(|hits| hits.t < distance)
+/// Everything `object` says `ray` hits. None is either a miss or an object whose transform
+/// won't invert, which is left out of the picture rather than failing the whole ray.
+fn object_hits(object: &dyn Shape, ray: Ray) -> Vec<Intersection<'_>> {
+    object.intersect(ray).unwrap_or_default()
+}
+
+/// An object whose transform can't be rendered with is never hit, nor is something in a group
+/// with one, which is worth a word when someone's listening. Said once as it's added, rather than on every ray that misses it.
+fn note_skipped(index: usize, object: &dyn Shape) {
+    if !cfg!(feature = "tracing") {
+        return;
+    }
+    let mut issues = vec![];
+    object_issues(index, object, &mut issues);
+    // Out of range materials still get rendered, just oddly
+    for _issue in issues
+        .into_iter()
+        .filter(|i| !matches!(i, ValidationIssue::BadMaterial { .. }))
+    {
+        trace::event!(
+            DEBUG,
+            object = index,
+            issue = ?_issue,
+            "non-invertible transform, object skipped"
+        );
     }
 }
 
 impl Default for World {
     fn default() -> Self {
         let s1_mat = Material {
-            colour: Colour::new(0.8, 1.0, 0.6),
+            pattern: Box::new(SolidPattern(Colour::new(0.8, 1.0, 0.6))),
             diffuse: 0.7,
             specular: 0.2,
             ..Default::default()
         };
         World {
             objects: vec![
-                Box::new(Sphere::new_with_material(s1_mat)),
-                Box::new(Sphere::new_with_transform(Matrix::scaling(0.5, 0.5, 0.5))),
+                Arc::new(Sphere::new_with_material(s1_mat)),
+                Arc::new(Sphere::new_with_transform(Matrix::scaling(0.5, 0.5, 0.5))),
             ],
-            light: vec![Box::new(PointLight::new(
+            light: vec![Arc::new(PointLight::new(
                 Colour::newi(1, 1, 1),
                 pointi(-10, 10, -10),
             ))],
+            medium: None,
+            bias: EPSILON,
+            stats: Default::default(),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
     use crate::{
         colour::Colour,
         math::{
             matrix::Matrix,
+            point::{Point, Vector},
             tuple::{pointi, vectori},
         },
+        pattern::solid::SolidPattern,
         ray::Ray,
         world::World,
     };
@@ -120,7 +790,10 @@ mod test {
 
         assert_eq!(w.objects[0].material().diffuse, 0.7);
         assert_eq!(w.objects[0].material().specular, 0.2);
-        assert_eq!(w.objects[0].material().colour, Colour::new(0.8, 1.0, 0.6));
+        assert_eq!(
+            w.objects[0].material().colour(),
+            Some(Colour::new(0.8, 1.0, 0.6))
+        );
 
         assert_eq!(w.objects[1].transform(), &Matrix::scaling(0.5, 0.5, 0.5));
 
@@ -128,10 +801,34 @@ mod test {
         assert_eq!(w.light[0].position(), &pointi(-10, 10, -10));
     }
 
+    #[test]
+    fn downcast_objects() {
+        use crate::shape::{plane::Plane, sphere::Sphere};
+
+        let mut w = World::default();
+        let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+        let before = w.colour_at(ray);
+
+        let sphere = w.objects[0].downcast_ref::<Sphere>().unwrap();
+        assert_eq!(sphere.material.colour(), Some(Colour::new(0.8, 1.0, 0.6)));
+        assert!(w.objects[0].downcast_ref::<Plane>().is_none());
+
+        w.object_mut(0)
+            .unwrap()
+            .downcast_mut::<Sphere>()
+            .unwrap()
+            .material
+            .pattern = Box::new(SolidPattern(Colour::RED));
+        let after = w.colour_at(ray);
+
+        assert_ne!(before, after);
+        assert_eq!(after, Colour::new(after.red, 0.0, 0.0));
+    }
+
     #[test]
     fn intersect_world() {
         let world = World::default();
-        let ray = Ray::new(pointi(0, 0, -5), vectori(0, 0, 1));
+        let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
 
         let xs = world.intersect_world(ray);
 
@@ -143,57 +840,832 @@ mod test {
             assert_eq!(got.t, want, "broke for {i}")
         }
     }
-    mod shading {
+    mod queries {
         use crate::{
-            intersection::Intersection, lights::PointLight, math::tuple::point,
+            lights::PointLight,
+            math::{
+                point::{Point, Vector},
+                tuple::{point, Tuple},
+            },
             shape::sphere::Sphere,
         };
 
         use super::*;
 
+        macro_rules! line_of_sight_test {
+            ($name:ident, $point:expr, $shadowed:expr) => {
+                #[test]
+                fn $name() {
+                    let w = World::default();
+                    let light = *w.light[0].position();
+
+                    assert_eq!(w.line_of_sight($point, light), !$shadowed);
+                    assert_eq!(w.line_of_sight(light, $point), !$shadowed);
+                    assert_eq!(w.is_shadowed($point), $shadowed);
+                }
+            };
+        }
+
+        // Same matrix as the shadow tests below
+        line_of_sight_test!(unshadowed, pointi(0, 10, 0), false);
+        line_of_sight_test!(shadowed, pointi(10, -10, 10), true);
+        line_of_sight_test!(behind_light, pointi(-20, 20, -20), false);
+        line_of_sight_test!(between_light_object, pointi(-2, 2, -2), false);
+        line_of_sight_test!(at_the_light, pointi(-10, 10, -10), false);
+
         #[test]
-        fn outside() {
+        fn distance_to_default_sphere() {
             let w = World::default();
-            let ray = Ray::new(pointi(0, 0, -5), vectori(0, 0, 1));
-            let shape = &*w.objects[0];
-            let i = Intersection::new(4.0, shape);
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
 
-            let comps = i.prepare_computations(ray);
-            let c = w.shade_hit(comps);
+            assert_eq!(w.distance_to_first_hit(r), Some(4.0));
+        }
 
-            assert_eq!(c, Colour::new(0.38066, 0.47583, 0.2855));
+        #[test]
+        fn distance_is_scaled_by_direction() {
+            let w = World::default();
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 2));
+
+            assert_eq!(w.distance_to_first_hit(r), Some(4.0));
         }
 
         #[test]
-        fn inside() {
-            let w = World {
-                light: vec![Box::new(PointLight::new(
-                    Colour::WHITE,
-                    point(0.0, 0.25, 0.0),
-                ))],
-                ..World::default()
-            };
-            let ray = Ray::new(pointi(0, 0, 0), vectori(0, 0, 1));
-            let shape = &*w.objects[1];
-            let i = Intersection::new(0.5, shape);
+        fn distance_miss() {
+            let w = World::default();
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 1, 0));
 
-            let comps = i.prepare_computations(ray);
-            let c = w.shade_hit(comps);
+            assert_eq!(w.distance_to_first_hit(r), None);
+            assert!(w.first_hit(r).is_none());
+        }
 
-            assert_eq!(c, Colour::new(0.90498, 0.90498, 0.90498))
+        #[test]
+        fn first_hit() {
+            let w = World::default();
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+
+            let (hit, point, normal) = w.first_hit(r).expect("should hit");
+
+            assert_eq!(hit.t, 4.0);
+            assert_eq!(hit.object, &*w.objects[0]);
+            assert_eq!(point, pointi(0, 0, -1));
+            assert_eq!(normal, vectori(0, 0, -1));
         }
 
         #[test]
-        fn shadowed_hit() {
+        fn endpoints_on_surfaces() {
             let w = World {
-                light: vec![PointLight::new_boxed(Colour::WHITE, pointi(0, 0, -10))],
+                objects: vec![Arc::new(Sphere::default())],
+                light: vec![Arc::new(PointLight::new(Colour::WHITE, pointi(0, 0, -10)))],
+                ..Default::default()
+            };
+
+            // Both ends sit on the sphere, nothing else is in the way.
+            assert!(w.line_of_sight(pointi(0, 0, -1), pointi(0, 0, 1)));
+            assert!(w.line_of_sight(pointi(0, 0, -1), pointi(0, 0, -5)));
+            assert!(!w.line_of_sight(pointi(0, 0, -5), pointi(0, 0, 5)));
+        }
+
+        #[test]
+        fn same_point() {
+            let w = World::default();
+            let p: Tuple = point(0.0, 0.0, -1.0);
+
+            assert!(w.line_of_sight(p, p));
+        }
+    }
+
+    mod ids {
+        use crate::{
+            camera::Camera,
+            intersection::HitRecord,
+            math::point::{Point, Vector},
+            shape::{group::Group, sphere::Sphere},
+        };
+
+        use super::*;
+
+        fn colour_at_ids(w: &World, r: Ray) -> Colour {
+            match HitRecord::hit(&w.intersect_world_ids(r)) {
+                Some(hit) => w.shade_hit(hit.prepare_computations(r, w).unwrap()),
+                None => Colour::BLACK,
+            }
+        }
+
+        #[test]
+        fn matches_borrowed() {
+            let w = World::default();
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+
+            let ids = w.intersect_world_ids(r);
+            let borrowed = w.intersect_world(r);
+
+            assert_eq!(ids.len(), borrowed.len());
+            for (id, b) in ids.iter().zip(borrowed) {
+                assert_eq!(id.resolve(&w).unwrap(), b);
+            }
+        }
+
+        #[test]
+        fn inside_groups() {
+            let mut g = Group::new(Matrix::translationi(0, 0, 1));
+            g.add_child(Box::new(Sphere::default()));
+            let w = World {
+                objects: vec![Arc::new(g)],
+                ..Default::default()
+            };
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+
+            let hit = HitRecord::hit(&w.intersect_world_ids(r)).unwrap();
+            let object = w.resolve(hit.object).unwrap();
+
+            assert_eq!(object.id(), w.objects[0].children()[0].id());
+            assert_eq!(hit.t, 5.0);
+            assert_eq!(colour_at_ids(&w, r), w.colour_at(r));
+        }
+
+        #[test]
+        fn identical_image() {
+            let w = World::default();
+            let c = Camera::new_with_transform(
+                11,
+                11,
+                std::f64::consts::FRAC_PI_2,
+                Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+            );
+
+            for y in 0..c.vsize {
+                for x in 0..c.hsize {
+                    let r = c.ray_for_pixel(x, y);
+                    assert_eq!(colour_at_ids(&w, r), w.colour_at(r), "pixel {x}, {y}");
+                }
+            }
+        }
+
+        #[test]
+        fn records_outlive_world() {
+            struct Collected {
+                records: Vec<HitRecord>,
+            }
+
+            let collected = {
+                let w = World::default();
+                let mut collected = Collected { records: vec![] };
+                for x in [-0.5, 0.0, 0.5] {
+                    let r = Ray::new(Point::new(x, 0.0, -5.0), Vector::newi(0, 0, 1));
+                    collected.records.extend(w.intersect_world_ids(r));
+                }
+                collected
+            };
+
+            assert_eq!(collected.records.len(), 12);
+            assert!(collected.records.iter().all(|r| r.t > 0.0));
+        }
+
+        #[test]
+        fn stale_ids() {
+            let mut w = World::default();
+            w.objects.push(Arc::new(Sphere::default()));
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let records = w.intersect_world_ids(r);
+            let first = w.object_id(0).unwrap();
+            let last = w.object_id(2).unwrap();
+
+            assert!(w.resolve(first).is_some());
+            assert!(w.resolve(last).is_some());
+
+            w.objects.remove(0);
+
+            assert!(w.resolve(first).is_none());
+            assert!(w.resolve(last).is_none());
+            assert!(records
+                .iter()
+                .any(|rec| rec.prepare_computations(r, &w).is_none()));
+            assert_eq!(w.object_id(2), None);
+        }
+    }
+
+    mod trace {
+        use crate::{
+            math::{
+                matrix::Matrix,
+                point::{Point, Vector},
+            },
+            shape::{sphere::Sphere, Shape},
+        };
+
+        use super::*;
+
+        fn assert_matches_intersect_world(w: &World, r: Ray) {
+            let want = w.intersect_world(r);
+            let got: Vec<_> = w.trace(r).collect();
+
+            assert_eq!(got.len(), want.len());
+            for (g, w) in got.iter().zip(want.iter()) {
+                assert_eq!(g, w);
+            }
+        }
+
+        #[test]
+        fn default_world() {
+            let w = World::default();
+            assert_matches_intersect_world(
+                &w,
+                Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1)),
+            );
+            assert_matches_intersect_world(
+                &w,
+                Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1)),
+            );
+            assert_matches_intersect_world(
+                &w,
+                Ray::new(Point::new(0.2, 0.3, -5.0), Vector::newi(0, 0, 1)),
+            );
+        }
+
+        #[test]
+        fn line_of_spheres() {
+            // Added in reverse so that object order and t order disagree
+            let w = World {
+                objects: (0..10)
+                    .rev()
+                    .map(|i| {
+                        Arc::new(Sphere::new_with_transform(Matrix::translationi(
+                            0,
+                            0,
+                            i * 3,
+                        ))) as Arc<dyn Shape>
+                    })
+                    .collect(),
+                ..Default::default()
+            };
+
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            assert_eq!(w.trace(r).count(), 20);
+            assert_matches_intersect_world(&w, r);
+        }
+
+        #[test]
+        fn coincident_objects() {
+            let w = World {
+                objects: vec![Arc::new(Sphere::default()), Arc::new(Sphere::default())],
+                ..Default::default()
+            };
+
+            assert_matches_intersect_world(
+                &w,
+                Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1)),
+            );
+        }
+
+        #[test]
+        fn early_termination() {
+            let w = World::default();
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+
+            let first = w.trace(r).next().expect("should hit");
+            assert_eq!(first.t, 4.0);
+            assert_eq!(w.trace(r).take(2).count(), 2);
+        }
+
+        #[test]
+        fn empty_world() {
+            let w = World {
+                objects: vec![],
+                ..Default::default()
+            };
+
+            assert!(w
+                .trace(Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1)))
+                .next()
+                .is_none());
+        }
+    }
+
+    mod contents {
+        use crate::{
+            lights::PointLight,
+            materials::Material,
+            math::point::{Point, Vector},
+            shape::sphere::Sphere,
+        };
+
+        use super::*;
+
+        fn glassy_world() -> World {
+            let mut w = World::default();
+            w.add_object(Box::new(Sphere::new(
+                Matrix::translationi(0, 0, -3),
+                Material::glass(),
+            )));
+            w
+        }
+
+        #[test]
+        fn iterators() {
+            let mut w = glassy_world();
+            w.add_light(PointLight::new_boxed(Colour::WHITE, pointi(10, 10, -10)));
+
+            assert_eq!(w.objects().len(), 3);
+            assert_eq!(w.objects_mut().count(), 3);
+            assert_eq!((&w).into_iter().count(), 3);
+            assert_eq!(w.lights().len(), 2);
+            assert_eq!(w.lights_mut().count(), 2);
+            for (index, o) in w.objects().enumerate() {
+                let resolved = w.object_id(index).and_then(|id| w.resolve(id));
+                assert_eq!(resolved.map(|r| r.id()), Some(o.id()));
+            }
+
+            let ids: Vec<_> = w.objects().map(|o| o.id()).collect();
+            let owned: Vec<_> = w.into_iter().map(|o| o.id()).collect();
+            assert_eq!(ids, owned);
+        }
+
+        #[test]
+        fn retain() {
+            let mut w = glassy_world();
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let through_glass = w.colour_at(r);
+
+            w.retain_objects(|o| o.material().transparency == 0.0);
+
+            assert_eq!(w.objects().len(), 2);
+            assert!(w.objects().all(|o| o.material().transparency == 0.0));
+            assert_ne!(w.colour_at(r), through_glass);
+            assert_eq!(w.colour_at(r), World::default().colour_at(r));
+        }
+
+        #[test]
+        fn remove_and_reinsert() {
+            let mut w = World::default();
+            let inner_id = w.object(1).unwrap().id();
+
+            let inner = w.remove_object(1);
+            assert_eq!(inner.id(), inner_id);
+            assert_eq!(w.objects().len(), 1);
+            assert!(w.object(1).is_none());
+
+            let mut other = World::default().with_objects(vec![]);
+            other.add_shared_object(inner);
+            assert_eq!(other.object(0).unwrap().id(), inner_id);
+            assert_eq!(
+                other.object(0).unwrap().transform(),
+                &Matrix::scaling(0.5, 0.5, 0.5)
+            );
+        }
+
+        #[test]
+        fn edit_in_place() {
+            let mut w = World::default();
+            for o in w.objects_mut() {
+                o.set_material(Material::default());
+            }
+
+            assert!(w.objects().all(|o| o.material() == &Material::default()));
+        }
+    }
+
+    mod sharing {
+        use std::sync::Arc;
+
+        use crate::{
+            lights::PointLight,
+            materials::Material,
+            math::point::{Point, Vector},
+            shape::{group::Group, sphere::Sphere},
+        };
+
+        use super::*;
+
+        #[test]
+        fn two_worlds_one_sphere() {
+            let first = World::default();
+            let sphere = first.shared_object(0).unwrap();
+            let mut second = World::new(
+                vec![],
+                vec![PointLight::new_boxed(Colour::WHITE, pointi(-10, 10, -10))],
+            );
+            second.add_shared_object(sphere.clone());
+            assert!(Arc::ptr_eq(&sphere, &second.shared_object(0).unwrap()));
+
+            // The second world is just the outer sphere of the first, lit the same way
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            assert_eq!(first.colour_at(r), Colour::new(0.38066, 0.47583, 0.2855));
+            assert_eq!(second.colour_at(r), first.colour_at(r));
+
+            let alone = World::default().with_objects(vec![Box::new(Sphere::new_with_material(
+                first.object(0).unwrap().material().clone(),
+            ))]);
+            let glancing = Ray::new(Point::new(0.0, 0.9, -5.0), Vector::newi(0, 0, 1));
+            assert_eq!(second.colour_at(glancing), alone.colour_at(glancing));
+        }
+
+        #[test]
+        fn snapshots_share_geometry() {
+            let mut heavy = Group::default();
+            for i in 0..1000 {
+                heavy.add_child(Box::new(Sphere::new_with_transform(Matrix::translationi(
+                    i, 0, 0,
+                ))));
+            }
+            let mut w = World::default();
+            w.add_object(Box::new(heavy));
+
+            let view = w.snapshot();
+            assert_eq!(view.objects().len(), 3);
+            // The same objects, not copies of them
+            for index in 0..3 {
+                let (ours, theirs) = (w.shared_object(index), view.shared_object(index));
+                assert!(Arc::ptr_eq(&ours.unwrap(), &theirs.unwrap()));
+            }
+            let again = view.clone();
+            assert_eq!(Arc::strong_count(&w.objects[2]), 3);
+            drop((view, again));
+            assert_eq!(Arc::strong_count(&w.objects[2]), 1);
+        }
+
+        #[test]
+        fn mutation_after_snapshot() {
+            let mut w = World::default();
+            let view = w.snapshot();
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let before = view.colour_at(r);
+
+            // Shared, so it can't be changed under the snapshot
+            assert!(w.object_mut(0).is_none());
+            assert_eq!(w.objects_mut().count(), 0);
+            assert_eq!(w.lights_mut().count(), 0);
+
+            // but the world's own lists are its own
+            w.remove_object(0);
+            w.add_object(Box::new(Sphere::new_with_material(Material {
+                pattern: Box::new(SolidPattern(Colour::RED)),
+                ..Default::default()
+            })));
+            assert_eq!(view.objects().len(), 2);
+            assert_eq!(view.colour_at(r), before);
+            assert_ne!(w.colour_at(r), before);
+
+            // and once the snapshot's gone everything is editable again
+            drop(view);
+            assert!(w.object_mut(0).is_some());
+            assert_eq!(w.objects_mut().count(), 2);
+        }
+
+        #[test]
+        fn snapshot_stats_count() {
+            let w = World::default();
+            let view = w.snapshot();
+            view.colour_at(Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1)));
+
+            assert_eq!(w.stats.snapshot().primary_rays, 1);
+        }
+    }
+
+    mod validate {
+        use crate::{
+            error::RayTracerError,
+            math::point::{Point, Vector},
+            shape::{sphere::Sphere, Shape},
+            world::ValidationIssue,
+        };
+
+        use super::*;
+
+        #[test]
+        fn default_is_valid() {
+            assert_eq!(World::default().validate(), Ok(()))
+        }
+
+        #[test]
+        fn reports_offending_objects() {
+            let mut w = World::default();
+            w.objects
+                .push(Arc::new(Sphere::new_with_transform(Matrix::scaling(
+                    1.0, 0.0, 1.0,
+                ))));
+            w.objects
+                .push(Arc::new(Sphere::new_with_transform(Matrix::translation(
+                    f64::NAN,
+                    0.0,
+                    0.0,
+                ))));
+
+            assert_eq!(
+                w.validate(),
+                Err(vec![
+                    ValidationIssue::NonInvertibleTransform { index: 2 },
+                    ValidationIssue::NonFiniteTransform { index: 3 },
+                ])
+            )
+        }
+
+        #[test]
+        fn looks_inside_groups() {
+            use crate::{
+                materials::{Material, MaterialError},
+                shape::group::Group,
+            };
+
+            let mut g = Group::default();
+            g.add_child(Box::new(Sphere::new_with_transform(Matrix::scaling(
+                1.0, 0.0, 1.0,
+            ))));
+            g.add_child(Box::new(Sphere::new_with_material(Material {
+                diffuse: 2.0,
+                ..Default::default()
+            })));
+            let mut w = World::default();
+            w.add_object(Box::new(g));
+
+            assert_eq!(
+                w.validate(),
+                Err(vec![
+                    ValidationIssue::NonInvertibleTransform { index: 2 },
+                    ValidationIssue::BadMaterial {
+                        index: 2,
+                        error: MaterialError {
+                            field: "diffuse",
+                            value: 2.0,
+                            expected: "[0, 1]",
+                        },
+                    },
+                ])
+            );
+            assert_eq!(
+                w.check_renderable().unwrap_err().to_string(),
+                "object 2 has a non-invertible transform"
+            );
+        }
+
+        #[test]
+        fn zero_scaled_sphere_does_not_poison_colour_at() {
+            let mut w = World::default();
+            let flat: Box<dyn Shape> =
+                Box::new(Sphere::new_with_transform(Matrix::scaling(0.0, 1.0, 1.0)));
+            w.objects.insert(0, flat.into());
+
+            let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            assert!(w.intersect_world(r).iter().all(|i| i.t.is_finite()));
+            assert_eq!(w.colour_at(r), Colour::new(0.38066, 0.47583, 0.2855))
+        }
+
+        #[test]
+        fn check_renderable() {
+            let mut w = World::default();
+            assert_eq!(w.check_renderable(), Ok(()));
+
+            w.objects
+                .push(Arc::new(Sphere::new_with_transform(Matrix::scaling(
+                    1.0, 0.0, 1.0,
+                ))));
+            let err = w.check_renderable().unwrap_err();
+
+            assert_eq!(
+                err,
+                RayTracerError::BadObject {
+                    object: w.object_id(2).unwrap(),
+                    issue: ValidationIssue::NonInvertibleTransform { index: 2 },
+                }
+            );
+            assert_eq!(err.to_string(), "object 2 has a non-invertible transform");
+        }
+    }
+
+    mod shading {
+        use crate::{
+            intersection::Intersection,
+            lights::PointLight,
+            materials::Material,
+            math::{
+                point::{Point, Vector},
+                {float::EPSILON, tuple::point},
+            },
+            shape::{plane::Plane, sphere::Sphere},
+        };
+
+        use super::*;
+
+        /// The default world's outer sphere sitting on a floor, all scaled up by `scale`, and
+        /// what it looks like along a fan of rays from in front
+        fn scaled_scene(scale: f64, bias: f64) -> Vec<Colour> {
+            let at = |x: f64, y: f64, z: f64| point(x * scale, y * scale, z * scale);
+            let w = World::new(
+                vec![
+                    Box::new(Sphere::new_with_transform(Matrix::scaling(
+                        scale, scale, scale,
+                    ))),
+                    Box::new(Plane::new(
+                        Matrix::translation(0.0, -scale, 0.0),
+                        Default::default(),
+                    )),
+                ],
+                vec![Box::new(PointLight::new(
+                    Colour::WHITE,
+                    at(-10.0, 10.0, -10.0),
+                ))],
+            )
+            .with_bias(bias);
+
+            let from = at(0.0, 0.0, -5.0);
+            let mut colours = vec![];
+            for x in 0..16 {
+                for y in 0..16 {
+                    let to = at(x as f64 / 2.5 - 3.0, y as f64 / 7.5 - 1.5, 0.0);
+                    colours.push(w.colour_at(Ray::new(
+                        Point::from_tuple(from),
+                        Vector::from_tuple((to - from).normalize()),
+                    )));
+                }
+            }
+            colours
+        }
+
+        #[test]
+        fn bias_scales_with_the_scene() {
+            assert_eq!(World::default().bias(), EPSILON);
+            let unit = scaled_scene(1.0, EPSILON);
+
+            // f64 has plenty of room, it takes a very big scene before the hit points are
+            // further out than EPSILON and surfaces start shadowing themselves
+            let scale = 1e11;
+            let acne = scaled_scene(scale, EPSILON);
+            let differ = acne.iter().zip(&unit).filter(|(a, b)| a != b).count();
+            assert!(differ > 10, "{differ}");
+
+            assert_eq!(scaled_scene(scale, EPSILON * scale), unit);
+            assert_eq!(scaled_scene(1000.0, EPSILON * 1000.0), unit);
+        }
+
+        #[test]
+        fn bias_smaller_than_the_scene() {
+            // A floor under a ceiling closer than EPSILON, with the light above it all. The
+            // default bias puts the floor's over_point above the ceiling, so light leaks in.
+            let gap = EPSILON / 10.0;
+            let w = World::new(
+                vec![
+                    Box::new(Plane::default()),
+                    Box::new(Plane::new(
+                        Matrix::translation(0.0, gap, 0.0),
+                        Default::default(),
+                    )),
+                ],
+                vec![Box::new(PointLight::new(Colour::WHITE, pointi(0, 10, 0)))],
+            );
+            let r = Ray::new(Point::new(0.0, gap / 2.0, 0.0), Vector::newi(0, -1, 0));
+            let lit = w.colour_at(r);
+            let w = w.with_bias(gap / 100.0);
+            let dark = w.colour_at(r);
+
+            assert!(dark.red < lit.red);
+            assert_eq!(dark, Colour::WHITE * Material::default().ambient);
+        }
+
+        #[test]
+        fn outside() {
+            let w = World::default();
+            let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let shape = &*w.objects[0];
+            let i = Intersection::new(4.0, shape);
+
+            let comps = i.prepare_computations(ray);
+            let c = w.shade_hit(comps);
+
+            assert_eq!(c, Colour::new(0.38066, 0.47583, 0.2855));
+        }
+
+        #[test]
+        fn inside() {
+            let w = World {
+                light: vec![Arc::new(PointLight::new(
+                    Colour::WHITE,
+                    point(0.0, 0.25, 0.0),
+                ))],
+                ..World::default()
+            };
+            let ray = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1));
+            let shape = &*w.objects[1];
+            let i = Intersection::new(0.5, shape);
+
+            let comps = i.prepare_computations(ray);
+            let c = w.shade_hit(comps);
+
+            assert_eq!(c, Colour::new(0.90498, 0.90498, 0.90498))
+        }
+
+        mod sidedness {
+            use crate::{
+                materials::{Material, Sidedness},
+                math::point::{Point, Vector},
+                pattern::stripe::StripePattern,
+            };
+
+            use super::*;
+
+            fn inside(sidedness: Sidedness) -> Colour {
+                let mut w = World {
+                    light: vec![Arc::new(PointLight::new(
+                        Colour::WHITE,
+                        point(0.0, 0.25, 0.0),
+                    ))],
+                    ..World::default()
+                };
+                let mut inner = w.objects[1].material().clone();
+                inner.sidedness = sidedness;
+                w.object_mut(1).unwrap().set_material(inner);
+
+                let ray = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1));
+                let comps = Intersection::new(0.5, &*w.objects[1]).prepare_computations(ray);
+                assert!(comps.inside);
+
+                w.shade_hit(comps)
+            }
+
+            #[test]
+            fn two_sided() {
+                assert_eq!(
+                    inside(Sidedness::TwoSided),
+                    Colour::new(0.90498, 0.90498, 0.90498)
+                );
+            }
+
+            #[test]
+            fn front_only() {
+                assert_eq!(inside(Sidedness::FrontOnly), Colour::BLACK);
+            }
+
+            #[test]
+            fn back_tinted() {
+                assert_eq!(
+                    inside(Sidedness::BackTinted(Colour::newi(1, 0, 0))),
+                    Colour::new(0.90498, 0.0, 0.0)
+                );
+            }
+
+            #[test]
+            fn back_tinted_replaces_pattern() {
+                let mut w = World::default();
+                let mut m = w.objects[1].material().clone();
+                m.pattern = Box::new(StripePattern::new(Colour::WHITE, Colour::WHITE));
+                m.sidedness = Sidedness::BackTinted(Colour::BLACK);
+                w.object_mut(1).unwrap().set_material(m);
+
+                let ray = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1));
+                let comps = Intersection::new(0.5, &*w.objects[1]).prepare_computations(ray);
+
+                // Black only picks up the specular highlight, which is grey
+                let c = w.shade_hit(comps);
+                assert_eq!(c.red, c.green);
+                assert_eq!(c.green, c.blue);
+            }
+
+            fn look_at_skydome(sidedness: Sidedness) -> Colour {
+                // Camera (and light) in the middle of a big sphere, so everything we see of
+                // it is the back
+                let w = World {
+                    light: vec![Arc::new(PointLight::new(Colour::WHITE, pointi(0, 0, 0)))],
+                    objects: vec![Arc::new(Sphere::new(
+                        Matrix::scalingi(10, 10, 10),
+                        Material {
+                            pattern: Box::new(SolidPattern(Colour::new(0.4, 0.6, 1.0))),
+                            ambient: 1.0,
+                            diffuse: 0.0,
+                            specular: 0.0,
+                            sidedness,
+                            ..Default::default()
+                        },
+                    ))],
+                    ..Default::default()
+                };
+
+                w.colour_at(Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1)))
+            }
+
+            #[test]
+            fn skydome() {
+                assert_eq!(
+                    look_at_skydome(Sidedness::TwoSided),
+                    Colour::new(0.4, 0.6, 1.0)
+                );
+                assert_eq!(look_at_skydome(Sidedness::FrontOnly), Colour::BLACK);
+                assert_eq!(
+                    look_at_skydome(Sidedness::BackTinted(Colour::new(1.0, 0.5, 0.0))),
+                    Colour::new(1.0, 0.5, 0.0)
+                );
+            }
+        }
+
+        #[test]
+        fn shadowed_hit() {
+            let w = World {
+                light: vec![Arc::new(PointLight::new(Colour::WHITE, pointi(0, 0, -10)))],
                 objects: vec![
-                    Box::new(Sphere::default()),
-                    Box::new(Sphere::new_with_transform(Matrix::translationi(0, 0, 10))),
+                    Arc::new(Sphere::default()),
+                    Arc::new(Sphere::new_with_transform(Matrix::translationi(0, 0, 10))),
                 ],
+                ..Default::default()
             };
 
-            let r = Ray::new(pointi(0, 0, 5), vectori(0, 0, 1));
+            let r = Ray::new(Point::newi(0, 0, 5), Vector::newi(0, 0, 1));
             let i = Intersection::new(4.0, &*w.objects[1]);
 
             let comps = i.prepare_computations(r);
@@ -201,15 +1673,121 @@ mod test {
             assert_eq!(w.shade_hit(comps), Colour::new(0.1, 0.1, 0.1));
         }
 
+        #[test]
+        fn lights_add_up() {
+            let one = World::default();
+            let ray = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+            let single = one.colour_at(ray);
+
+            // A second light in the same place is twice the light, every part of it
+            let light = PointLight::new_boxed(Colour::WHITE, pointi(-10, 10, -10));
+            let mut two = World::default();
+            two.add_light(light.clone());
+            assert_eq!(two.colour_at(ray), single * 2.0);
+
+            // Which is the same as one light twice as bright
+            let brighter = World::default().with_lights(vec![PointLight::new_boxed(
+                Colour::WHITE * 2.0,
+                pointi(-10, 10, -10),
+            )]);
+            assert_eq!(brighter.colour_at(ray), single * 2.0);
+
+            // And a light that's in the dark adds nothing
+            let mut dark = World::default();
+            dark.add_light(PointLight::new_boxed(Colour::BLACK, pointi(10, 10, -10)));
+            assert_eq!(dark.colour_at(ray), single);
+        }
+
+        #[test]
+        fn ring_of_lights() {
+            use crate::{camera::Camera, lights::rigs, materials::Material, shape::plane::Plane};
+
+            // A matte floor under eight lights sharing one white light's worth between them.
+            // Right under the middle of the ring they're all at the same angle, so it's lit as
+            // one white light there would: ambient 0.1, plus diffuse 0.9 times the cos of the
+            // angle to the lights, 5 / sqrt(29).
+            let floor = Material {
+                specular: 0.0,
+                ..Default::default()
+            };
+            let world = World::new(
+                vec![Box::new(Plane::new(Matrix::default(), floor))],
+                rigs::ring(pointi(0, 5, 0), 2.0, 8, Colour::WHITE, 0.0),
+            );
+            let camera = Camera::new_with_transform(
+                11,
+                11,
+                core::f64::consts::FRAC_PI_2,
+                Matrix::view_transform(pointi(0, 1, -3), pointi(0, 0, 0), vectori(0, 1, 0)),
+            );
+            let probe = camera.render(&world)[(5, 5)];
+            let expected = 0.1 + 0.9 * 5.0 / 29.0_f64.sqrt();
+            assert_eq!(probe, Colour::new(expected, expected, expected));
+        }
+
+        mod receive_shadows {
+            use crate::{
+                intersection::IntersectionComputions,
+                math::point::{Point, Vector},
+                stats::StatsSnapshot,
+            };
+
+            use super::*;
+
+            fn shadowed(receive_shadows: bool) -> World {
+                let mut behind = Sphere::new_with_transform(Matrix::translationi(0, 0, 10));
+                behind.material.receive_shadows = receive_shadows;
+                World {
+                    light: vec![Arc::new(PointLight::new(Colour::WHITE, pointi(0, 0, -10)))],
+                    objects: vec![Arc::new(Sphere::default()), Arc::new(behind)],
+                    ..Default::default()
+                }
+            }
+
+            fn comps(w: &World) -> IntersectionComputions<'_> {
+                let r = Ray::new(Point::newi(0, 0, 5), Vector::newi(0, 0, 1));
+                Intersection::new(4.0, &*w.objects[1]).prepare_computations(r)
+            }
+
+            #[test]
+            fn lit_as_if_unshadowed() {
+                let w = shadowed(false);
+
+                assert_eq!(w.shade_hit(comps(&w)), Colour::new(1.9, 1.9, 1.9));
+            }
+
+            #[test]
+            fn no_shadow_rays() {
+                let w = shadowed(false);
+                w.shade_hit(comps(&w));
+
+                assert_eq!(w.stats.snapshot(), StatsSnapshot::default());
+            }
+
+            #[test]
+            fn default_unchanged() {
+                let w = shadowed(true);
+
+                assert_eq!(w.shade_hit(comps(&w)), Colour::new(0.1, 0.1, 0.1));
+                assert_eq!(w.stats.snapshot().shadow_rays, 1);
+                // The sphere in front is the first object, so the other's never tested
+                assert_eq!(w.stats.snapshot().intersection_tests, 1);
+            }
+        }
+
         mod colour_at {
-            use crate::{materials::Material, shape::sphere::Sphere};
+            use crate::{
+                materials::Material,
+                math::point::{Point, Vector},
+                shape::sphere::Sphere,
+            };
 
             use super::*;
 
             #[test]
             fn miss() {
                 let w = World::default();
-                let r = Ray::new(pointi(0, 0, -5), vectori(0, 1, 0));
+                let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 1, 0));
 
                 assert_eq!(w.colour_at(r), Colour::BLACK)
             }
@@ -217,7 +1795,7 @@ mod test {
             #[test]
             fn hit() {
                 let w = World::default();
-                let r = Ray::new(pointi(0, 0, -5), vectori(0, 0, 1));
+                let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
 
                 assert_eq!(w.colour_at(r), Colour::new(0.38066, 0.47583, 0.2855))
             }
@@ -226,20 +1804,306 @@ mod test {
             fn hit_behind() {
                 let w = World {
                     objects: vec![
-                        Box::new(Sphere::new_with_material(Material {
+                        Arc::new(Sphere::new_with_material(Material {
                             ambient: 1.0,
                             ..Material::default()
                         })),
-                        Box::new(Sphere::new_with_material(Material {
+                        Arc::new(Sphere::new_with_material(Material {
                             ambient: 1.0,
                             ..Material::default()
                         })),
                     ],
                     ..Default::default()
                 };
-                let ray = Ray::new(point(0.0, 0.0, 0.75), vectori(0, 0, -1));
+                let ray = Ray::new(Point::new(0.0, 0.0, 0.75), Vector::newi(0, 0, -1));
+
+                assert_eq!(Some(w.colour_at(ray)), w.objects[1].material().colour())
+            }
+        }
+
+        mod reflection {
+            use std::f64::consts::SQRT_2;
+
+            use crate::{
+                intersection::Intersection,
+                materials::Material,
+                math::point::{Point, Vector},
+                pattern::stripe::StripePattern,
+                ray::RayKind,
+                shape::{plane::Plane, Shape},
+                world::MAX_DEPTH,
+            };
+
+            use super::*;
+
+            fn with_mirror_floor(reflective: f64) -> World {
+                let mut w = World::default();
+                w.objects.push(Arc::new(Plane::new(
+                    Matrix::translationi(0, -1, 0),
+                    Material {
+                        reflective,
+                        ..Default::default()
+                    },
+                )));
+                w
+            }
+
+            fn floor_ray() -> Ray {
+                Ray::new(
+                    Point::new(0.0, 0.0, -3.0),
+                    Vector::new(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+                )
+            }
+
+            #[test]
+            fn non_reflective() {
+                let mut w = World::default();
+                let mut inner = Sphere::new_with_transform(Matrix::scaling(0.5, 0.5, 0.5));
+                inner.material.ambient = 1.0;
+                w.objects[1] = Arc::new(inner);
+
+                let r = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1));
+                let comps = Intersection::new(1.0, &*w.objects[1]).prepare_computations(r);
+
+                assert_eq!(w.reflected_colour(&comps), Colour::BLACK);
+            }
+
+            #[test]
+            fn reflective() {
+                let w = with_mirror_floor(0.5);
+                let comps =
+                    Intersection::new(SQRT_2, &*w.objects[2]).prepare_computations(floor_ray());
+
+                // The book has (0.19032, 0.2379, 0.14274), the difference is down to our smaller
+                // EPSILON nudging over_point a little less.
+                assert_eq!(
+                    w.reflected_colour(&comps),
+                    Colour::new(0.19033, 0.23792, 0.14275)
+                );
+            }
+
+            #[test]
+            fn shade_hit_reflective() {
+                let w = with_mirror_floor(0.5);
+                let comps =
+                    Intersection::new(SQRT_2, &*w.objects[2]).prepare_computations(floor_ray());
+
+                // Book: (0.87677, 0.92436, 0.82918), see above
+                assert_eq!(w.shade_hit(comps), Colour::new(0.87676, 0.92434, 0.82917));
+            }
+
+            #[test]
+            fn at_max_depth() {
+                let w = with_mirror_floor(0.5);
+                let mut r = floor_ray();
+                r.depth = MAX_DEPTH;
+                let comps = Intersection::new(SQRT_2, &*w.objects[2]).prepare_computations(r);
+
+                assert_eq!(w.reflected_colour(&comps), Colour::BLACK);
+            }
+
+            #[test]
+            fn mutually_reflective_terminates() {
+                let mirror = Material {
+                    reflective: 1.0,
+                    ..Default::default()
+                };
+                let w = World {
+                    light: vec![Arc::new(PointLight::new(Colour::WHITE, pointi(0, 0, 0)))],
+                    objects: vec![
+                        Arc::new(Plane::new(Matrix::translationi(0, -1, 0), mirror.clone())),
+                        Arc::new(Plane::new(
+                            Matrix::rotation_x(std::f64::consts::PI).translate(0.0, 1.0, 0.0),
+                            mirror,
+                        )) as Arc<dyn Shape>,
+                    ],
+                    ..Default::default()
+                };
+
+                // Just needs to come back at all
+                w.colour_at(Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 1, 0)));
+            }
+
+            #[test]
+            fn patterns_flatten_past_depth_2() {
+                // A striped floor seen down a corridor of mirrors at z = -1 and 1, which only
+                // reflect, after going back and forth between them some number of times
+                let mirror = |z| {
+                    Arc::new(Plane::new(
+                        Matrix::rotation_x(core::f64::consts::FRAC_PI_2).translate(0.0, 0.0, z),
+                        Material {
+                            reflective: 1.0,
+                            ambient: 0.0,
+                            diffuse: 0.0,
+                            specular: 0.0,
+                            ..Material::default().with_colour(Colour::BLACK)
+                        },
+                    )) as Arc<dyn Shape>
+                };
+                let floor = Plane::new(
+                    Matrix::translationi(0, -1, 0),
+                    Material {
+                        pattern: Box::new(StripePattern::new(Colour::WHITE, Colour::BLACK)),
+                        ambient: 1.0,
+                        diffuse: 0.0,
+                        specular: 0.0,
+                        ..Default::default()
+                    },
+                );
+                let w = World {
+                    light: vec![Arc::new(PointLight::new(Colour::WHITE, pointi(0, 0, 0)))],
+                    objects: vec![mirror(-1.0), mirror(1.0), Arc::new(floor)],
+                    ..Default::default()
+                };
+                // Going down 1 in every `run` along z, so the floor's hit after run / 2 bounces,
+                // in the stripe that x is in
+                let seen = |x: f64, run: f64| {
+                    let ray = Ray::new(Point::new(x, 0.0, 0.0), Vector::new(0.0, -1.0, run));
+                    w.colour_at(ray)
+                };
+
+                // 2 bounces, the stripes still show
+                assert_eq!(seen(0.5, 4.0), Colour::WHITE);
+                assert_eq!(seen(1.5, 4.0), Colour::BLACK);
+                // 3, just the stripes' average
+                assert_eq!(seen(0.5, 6.0), Colour::WHITE * 0.5);
+                assert_eq!(seen(1.5, 6.0), Colour::WHITE * 0.5);
+            }
+
+            #[test]
+            fn single_bounce_provenance() {
+                let w = with_mirror_floor(1.0);
+                let comps =
+                    Intersection::new(SQRT_2, &*w.objects[2]).prepare_computations(floor_ray());
+                assert_eq!((comps.ray_kind, comps.depth), (RayKind::Primary, 0));
+
+                let bounced = comps.reflected_ray();
+                let (hit, _, _) = w.first_hit(bounced).expect("should see the sphere");
+                let comps = hit.prepare_computations(bounced);
+
+                assert_eq!((comps.ray_kind, comps.depth), (RayKind::Reflection, 1));
+            }
+
+            #[test]
+            fn bounce_depth() {
+                let w = with_mirror_floor(1.0);
+
+                // Straight at the sphere, the floor, and the floor reflecting off into nothing
+                let sphere = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+                let away = Ray::new(
+                    Point::new(0.0, 0.0, -3.0),
+                    Vector::new(0.0, -0.5, -1.0).normalize(),
+                );
+
+                assert_eq!(w.bounce_depth_at(sphere), 0);
+                assert_eq!(w.bounce_depth_at(floor_ray()), 1);
+                assert_eq!(w.bounce_depth_at(away), 0);
+            }
+        }
+
+        mod refraction {
+            use std::f64::consts::SQRT_2;
+
+            use crate::{
+                intersection::Intersection,
+                materials::Material,
+                math::point::{Point, Vector},
+                shape::plane::Plane,
+                world::MAX_DEPTH,
+            };
+
+            use super::*;
+
+            fn glass_outer() -> World {
+                let mut w = World::default();
+                w.objects[0] = Arc::new(Sphere::new_with_material(Material {
+                    transparency: 1.0,
+                    refractive_index: 1.5,
+                    ..w.objects[0].material().clone()
+                }));
+                w
+            }
+
+            #[test]
+            fn opaque() {
+                let w = World::default();
+                let r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+                let xs = w.intersect_world(r);
+
+                let comps = xs[0].prepare_computations_with(r, &xs);
+                assert_eq!(w.refracted_colour(&comps), Colour::BLACK);
+            }
+
+            #[test]
+            fn at_max_depth() {
+                let w = glass_outer();
+                let mut r = Ray::new(Point::newi(0, 0, -5), Vector::newi(0, 0, 1));
+                r.depth = MAX_DEPTH;
+                let xs = w.intersect_world(r);
+
+                let comps = xs[0].prepare_computations_with(r, &xs);
+                assert_eq!(w.refracted_colour(&comps), Colour::BLACK);
+            }
+
+            #[test]
+            fn total_internal_reflection() {
+                let w = glass_outer();
+                let r = Ray::new(Point::new(0.0, 0.0, SQRT_2 / 2.0), Vector::newi(0, 1, 0));
+                let xs = vec![
+                    Intersection::new(-SQRT_2 / 2.0, &*w.objects[0]),
+                    Intersection::new(SQRT_2 / 2.0, &*w.objects[0]),
+                ];
+
+                // Inside the sphere, so the second intersection is the one we care about
+                let comps = xs[1].prepare_computations_with(r, &xs);
+                assert_eq!(w.refracted_colour(&comps), Colour::BLACK);
+            }
+
+            fn with_glass_floor(reflective: f64) -> World {
+                let mut w = World::default();
+                w.objects.push(Arc::new(Plane::new(
+                    Matrix::translationi(0, -1, 0),
+                    Material {
+                        reflective,
+                        transparency: 0.5,
+                        refractive_index: 1.5,
+                        ..Default::default()
+                    },
+                )));
+                w.objects.push(Arc::new(Sphere::new(
+                    Matrix::translation(0.0, -3.5, -0.5),
+                    Material {
+                        pattern: Box::new(SolidPattern(Colour::newi(1, 0, 0))),
+                        ambient: 0.5,
+                        ..Default::default()
+                    },
+                )));
+                w
+            }
+
+            fn floor_ray() -> Ray {
+                Ray::new(
+                    Point::new(0.0, 0.0, -3.0),
+                    Vector::new(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+                )
+            }
+
+            #[test]
+            fn shade_hit_transparent() {
+                let w = with_glass_floor(0.0);
+                let xs = vec![Intersection::new(SQRT_2, &*w.objects[2])];
+
+                let comps = xs[0].prepare_computations_with(floor_ray(), &xs);
+                assert_eq!(w.shade_hit(comps), Colour::new(0.93642, 0.68642, 0.68642));
+            }
+
+            #[test]
+            fn shade_hit_reflective_transparent() {
+                let w = with_glass_floor(0.5);
+                let xs = vec![Intersection::new(SQRT_2, &*w.objects[2])];
 
-                assert_eq!(w.colour_at(ray), w.objects[1].material().colour)
+                let comps = xs[0].prepare_computations_with(floor_ray(), &xs);
+                assert_eq!(w.shade_hit(comps), Colour::new(0.93391, 0.69643, 0.69243));
             }
         }
 
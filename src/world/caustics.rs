@@ -0,0 +1,215 @@
+//! Caustics, light focused onto a surface by something shiny or see-through. Whitted rendering
+//! only follows rays back from the eye, so glass just casts a shadow. Photons are fired the
+//! other way, from the lights, to find where it all ends up. See scene::CausticSettings.
+
+use core::f64::consts::PI;
+
+use crate::{
+    colour::Colour,
+    intersection::IntersectVec,
+    math::{
+        point::{Point, Vector},
+        rng::Rng,
+        sampling,
+    },
+    photon_map::{Photon, PhotonMap},
+    prelude::*,
+    ray::Ray,
+};
+
+use super::World;
+
+impl World {
+    /// Fires `photons_per_light` photons in random directions from each light. Those that hit
+    /// something reflective or transparent (and are reflected or refracted by it) are followed
+    /// for up to `max_depth` bounces, and land on any diffuse surface they reach after that.
+    /// Photons that go straight to a diffuse surface, or miss everything, are dropped: that's
+    /// direct light, which is already taken care of.
+    ///
+    /// Whether a photon's reflected, refracted or soaks in is picked at random, in proportion
+    /// to how much of each the material does, so those that carry on keep all their power.
+    pub fn caustic_photons(
+        &self,
+        photons_per_light: usize,
+        max_depth: usize,
+        seed: u64,
+    ) -> PhotonMap {
+        let mut rng = Rng::new(seed);
+        let mut landed = vec![];
+        let mut emitted = Colour::BLACK;
+        if photons_per_light == 0 {
+            return PhotonMap::new(landed, emitted);
+        }
+
+        for light in &self.light {
+            // Point lights shine the same every way, so a whole sphere's worth
+            let total = *light.intensity() * (4.0 * PI);
+            emitted += total;
+            let power = total / photons_per_light as f64;
+
+            for _ in 0..photons_per_light {
+                let direction = sampling::uniform_sphere(&mut rng);
+                let ray = Ray::new(
+                    Point::from_tuple(*light.position()),
+                    Vector::from_tuple(direction),
+                );
+                self.trace_photon(ray, power, max_depth, &mut rng, &mut landed);
+            }
+        }
+        PhotonMap::new(landed, emitted)
+    }
+
+    fn trace_photon(
+        &self,
+        ray: Ray,
+        mut power: Colour,
+        max_depth: usize,
+        rng: &mut Rng,
+        landed: &mut Vec<Photon>,
+    ) {
+        let mut ray = ray;
+        let mut travelled = 0.0;
+        for bounce in 0..=max_depth {
+            let xs = self.intersect_world(ray);
+            let Some(hit) = xs.hit() else {
+                return;
+            };
+            travelled += hit.t * ray.direction.magnitude();
+            let comps = hit.prepare_computations_with_bias(ray, &xs, self.bias);
+            let material = comps.object.material();
+
+            if bounce > 0 && material.diffuse > 0.0 {
+                landed.push(Photon {
+                    position: comps.point,
+                    direction: ray.direction.normalize().tuple(),
+                    power,
+                    travelled,
+                });
+            }
+            if bounce == max_depth {
+                return;
+            }
+
+            // The same split as shade_hit's
+            let (reflect, refract) = match (material.reflective, material.transparency) {
+                (r, t) if r > 0.0 && t > 0.0 => {
+                    let reflectance = comps.schlick();
+                    (r * reflectance, t * (1.0 - reflectance))
+                }
+                (r, t) => (r, t),
+            };
+            let total = reflect + refract;
+            let survival = total.min(1.0);
+            if survival <= 0.0 || rng.next_f64() >= survival {
+                return;
+            }
+            power *= total / survival;
+
+            ray = if rng.next_f64() * total < reflect {
+                comps.reflected_ray()
+            } else {
+                match comps.refracted_ray() {
+                    Some(ray) => ray,
+                    None => return,
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        lights::PointLight,
+        materials::Material,
+        math::{
+            float,
+            matrix::{Matrix, IDENTITY_4X4},
+            tuple::pointi,
+        },
+        shape::{plane::Plane, sphere::Sphere},
+        world::World,
+    };
+
+    /// A light in the middle of a clear ball, inside a big matte one
+    fn lamp(transparency: f64) -> World {
+        let clear = Material {
+            transparency,
+            refractive_index: 1.0,
+            diffuse: 0.0,
+            ..Default::default()
+        };
+        World::new(
+            vec![
+                Box::new(Sphere::new(IDENTITY_4X4, clear)),
+                Box::new(Sphere::new(
+                    Matrix::scaling(5.0, 5.0, 5.0),
+                    Material::default(),
+                )),
+            ],
+            vec![PointLight::new_boxed(
+                Colour::new(1.0, 0.5, 0.25),
+                pointi(0, 0, 0),
+            )],
+        )
+    }
+
+    #[test]
+    fn deposits() {
+        // Every photon goes straight through the clear ball and lands on the outer one
+        let map = lamp(1.0).caustic_photons(1000, 5, 1);
+        assert_eq!(map.len(), 1000);
+        assert_eq!(
+            map.emitted(),
+            Colour::new(1.0, 0.5, 0.25) * (4.0 * std::f64::consts::PI)
+        );
+        let landed = map
+            .photons()
+            .iter()
+            .fold(Colour::BLACK, |acc, p| acc + p.power);
+        assert_eq!(landed, map.emitted());
+        for photon in map.photons() {
+            assert!((photon.travelled - 5.0).abs() < 1e-4);
+            assert!(float::equal(
+                (photon.position - pointi(0, 0, 0)).magnitude(),
+                5.0
+            ));
+        }
+
+        // Half get through, with all their power, so half the power lands
+        let map = lamp(0.5).caustic_photons(2000, 5, 1);
+        assert!((900..1100).contains(&map.len()), "{}", map.len());
+        let landed = map
+            .photons()
+            .iter()
+            .fold(Colour::BLACK, |acc, p| acc + p.power);
+        let fraction = landed.red / map.emitted().red;
+        assert!((fraction - 0.5).abs() < 0.05, "{fraction}");
+        assert!(float::equal(
+            map.photons()[0].power.red,
+            map.emitted().red / 2000.0
+        ));
+
+        // Same seed, same photons
+        assert_eq!(
+            lamp(0.5).caustic_photons(2000, 5, 1).photons(),
+            map.photons()
+        );
+    }
+
+    #[test]
+    fn only_caustics() {
+        // Nothing but diffuse surfaces, so no photon's been anywhere interesting first
+        let world = World::new(
+            vec![Box::new(Plane::default())],
+            vec![PointLight::new_boxed(Colour::WHITE, pointi(0, 1, 0))],
+        );
+        assert!(world.caustic_photons(1000, 5, 1).is_empty());
+
+        assert!(lamp(1.0).caustic_photons(0, 5, 1).is_empty());
+        // Out of bounces before reaching the outer ball
+        assert!(lamp(1.0).caustic_photons(100, 0, 1).is_empty());
+        assert_eq!(lamp(1.0).caustic_photons(100, 1, 1).len(), 100);
+    }
+}
@@ -0,0 +1,190 @@
+//! Looking through a Medium: ray marching from the camera out to whatever it sees. See
+//! World::with_medium.
+
+use crate::{colour::Colour, medium::Medium, ray::Ray};
+
+#[cfg(not(feature = "std"))]
+use crate::math::float::FloatExt;
+
+use super::World;
+
+impl World {
+    /// What's at `distance` along `ray` through `medium`, in two parts: how much of it gets
+    /// through, and the light scattered towards the eye on the way. That's sampled at evenly
+    /// spaced points, each lit by every light that isn't blocked from it, which is what shows
+    /// the shafts of light past anything in the way.
+    pub(super) fn medium_along(&self, ray: Ray, distance: f64, medium: &Medium) -> (f64, Colour) {
+        if distance <= 0.0 {
+            return (1.0, Colour::BLACK);
+        }
+        let extinction = medium.extinction();
+        let direction = ray.direction.normalize().tuple();
+        let steps = ((distance / medium.step).ceil() as usize).clamp(1, medium.max_steps.max(1));
+        let step = distance / steps as f64;
+
+        // What gets straight through one step, and how much of the light scattered along it
+        // makes it out (exactly, rather than as if it were all at the middle)
+        let through = (-extinction * step).exp();
+        let escapes = if extinction > 0.0 {
+            (1.0 - through) / extinction
+        } else {
+            step
+        };
+
+        let mut transmittance = 1.0;
+        let mut scattered = Colour::BLACK;
+        for i in 0..steps {
+            let point = ray.origin.tuple() + direction * ((i as f64 + 0.5) * step);
+            let lit = self.light.iter().fold(Colour::BLACK, |acc, light| {
+                let visible = self.light_intensity_at(&**light, point);
+                if visible <= 0.0 {
+                    return acc;
+                }
+                let to_light = (*light.position() - point).normalize();
+                acc + *light.intensity() * (medium.phase.at(direction.dot(&to_light)) * visible)
+            });
+            scattered += lit * (medium.scattering * transmittance * escapes);
+            transmittance *= through;
+        }
+        (transmittance, scattered)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_2;
+
+    use crate::{
+        camera::Camera,
+        canvas::Canvas,
+        colour::Colour,
+        lights::PointLight,
+        math::{
+            matrix::Matrix,
+            point::{Point, Vector},
+            tuple::{point, pointi, vectori},
+        },
+        medium::Medium,
+        ray::{Ray, RayKind},
+        scene::{RenderSettings, Scene},
+        shape::{plane::Plane, triangle::Triangle, Shape},
+        world::World,
+    };
+
+    fn same(a: &Canvas, b: &Canvas) -> bool {
+        a.iter().zip(b.iter()).all(|(a, b)| {
+            a.red.to_bits() == b.red.to_bits() && a.blue.to_bits() == b.blue.to_bits()
+        })
+    }
+
+    fn camera() -> Camera {
+        Camera::new_with_transform(
+            11,
+            5,
+            FRAC_PI_2,
+            Matrix::view_transform(pointi(0, 0, -5), pointi(0, 0, 0), vectori(0, 1, 0)),
+        )
+    }
+
+    #[test]
+    fn clear_air() {
+        let render = |world| Scene::new(world, camera()).render();
+        let plain = render(World::default());
+        assert!(same(
+            &plain,
+            &render(World::default().with_medium(Medium::default()))
+        ));
+        assert!(!same(
+            &plain,
+            &render(World::default().with_medium(Medium::new(0.1, 0.0)))
+        ));
+    }
+
+    #[test]
+    fn transmittance() {
+        // Soaks light up without scattering any, so the wall just gets darker
+        let wall = |medium| {
+            let world = World::new(
+                vec![Box::new(Plane::new(
+                    Matrix::rotation_x(FRAC_PI_2).translate(0.0, 0.0, 4.0),
+                    Default::default(),
+                ))],
+                vec![PointLight::new_boxed(Colour::WHITE, pointi(0, 0, -10))],
+            );
+            match medium {
+                Some(medium) => world.with_medium(medium),
+                None => world,
+            }
+        };
+        let ray = Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1));
+        let clear = wall(None).colour_at(ray);
+        let fog = Medium::new(0.0, 0.3);
+        let foggy = wall(Some(fog)).colour_at(ray);
+        assert_eq!(foggy, clear * (-0.3_f64 * 4.0).exp());
+        assert_eq!(foggy, clear * fog.transmittance(4.0));
+
+        // Only the camera's rays go through it
+        let bounced =
+            Ray::new(Point::newi(0, 0, 0), Vector::newi(0, 0, 1)).with_kind(RayKind::Reflection);
+        assert_eq!(wall(Some(fog)).colour_at(bounced), clear);
+    }
+
+    /// A light well behind a wall with a slit down the middle, x in (-0.5, 0.5), so the shaft
+    /// through it stays narrow
+    fn slit(medium: Option<Medium>) -> Scene {
+        let z = 2.0;
+        let panel = |x0: f64, x1: f64| -> [Box<dyn Shape>; 2] {
+            [
+                Box::new(Triangle::new(
+                    point(x0, -10.0, z),
+                    point(x1, -10.0, z),
+                    point(x1, 10.0, z),
+                )),
+                Box::new(Triangle::new(
+                    point(x0, -10.0, z),
+                    point(x1, 10.0, z),
+                    point(x0, 10.0, z),
+                )),
+            ]
+        };
+        let mut world = World::new(
+            panel(-20.0, -0.5)
+                .into_iter()
+                .chain(panel(0.5, 20.0))
+                .collect(),
+            vec![PointLight::new_boxed(Colour::WHITE, pointi(0, 0, 20))],
+        );
+        world.set_medium(medium.map(|m| m.with_steps(0.25, 100)));
+        Scene::new(world, camera())
+    }
+
+    #[test]
+    fn light_shafts() {
+        let fog = Medium::new(0.2, 0.05);
+        let scene = slit(Some(fog));
+        let canvas = scene.render();
+        let clear = slit(None).render();
+
+        // Straight through the slit is just black without fog, and the wall's dimly lit by
+        // ambient either side
+        assert_eq!(clear[(5, 2)], Colour::BLACK);
+        assert!(clear[(0, 2)].red > 0.0);
+        // The fog in front of the slit's lit up, either side's in the wall's shadow so it only
+        // dims the wall
+        for y in 0..5 {
+            assert!(canvas[(5, y)].red > 0.01, "{:?}", canvas[(5, y)]);
+            for x in [0, 1, 9, 10] {
+                assert!(canvas[(x, y)].red < clear[(x, y)].red, "{x} {y}");
+                assert!(canvas[(5, y)].red > canvas[(x, y)].red * 2.0, "{x} {y}");
+            }
+        }
+
+        // March the same every time, on any number of threads
+        let threaded = slit(Some(fog)).with_settings(RenderSettings {
+            threads: 3,
+            ..Default::default()
+        });
+        assert!(same(&canvas, &scene.render()));
+        assert!(same(&canvas, &threaded.render()));
+    }
+}
@@ -0,0 +1,241 @@
+//! The colour along a ray split up by where the light came from, for rendering passes that a
+//! compositor can put back together. See Camera::render_passes.
+
+use core::ops::{Add, Div, Index, IndexMut, Mul};
+
+use crate::{
+    colour::Colour, intersection::IntersectionComputions, lights::Light, materials::Material,
+    ray::Ray, scene::RenderSettings,
+};
+
+use super::World;
+
+/// One part of the picture. Added together they're the whole thing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PassId {
+    Ambient,
+    /// Direct diffuse light as if nothing cast shadows, plus any caustics
+    Diffuse,
+    /// Direct highlights as if nothing cast shadows
+    Specular,
+    /// The diffuse and specular light that shadows take away, so never positive
+    Shadow,
+    /// Everything seen in reflections
+    Reflection,
+    /// Everything seen through transparent things
+    Refraction,
+    /// Colour that isn't lit by anything. Material::emissive is only for path tracing, so here
+    /// it's just toon outlines.
+    Emission,
+    /// Where the ray didn't hit anything
+    Background,
+    /// Light scattered towards the eye by the world's medium
+    Volume,
+}
+
+impl PassId {
+    pub const ALL: [PassId; 9] = [
+        PassId::Ambient,
+        PassId::Diffuse,
+        PassId::Specular,
+        PassId::Shadow,
+        PassId::Reflection,
+        PassId::Refraction,
+        PassId::Emission,
+        PassId::Background,
+        PassId::Volume,
+    ];
+
+    /// Lower case, for file names and the like
+    pub fn name(&self) -> &'static str {
+        match self {
+            PassId::Ambient => "ambient",
+            PassId::Diffuse => "diffuse",
+            PassId::Specular => "specular",
+            PassId::Shadow => "shadow",
+            PassId::Reflection => "reflection",
+            PassId::Refraction => "refraction",
+            PassId::Emission => "emission",
+            PassId::Background => "background",
+            PassId::Volume => "volume",
+        }
+    }
+}
+
+/// A colour for each pass, see World::colour_at_passes
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PassColours([Colour; PassId::ALL.len()]);
+
+impl PassColours {
+    /// Only `colour` in `pass`, black everywhere else
+    pub fn only(pass: PassId, colour: Colour) -> Self {
+        let mut colours = PassColours::default();
+        colours[pass] = colour;
+        colours
+    }
+
+    /// All the passes added up, which is what colour_at would've given
+    pub fn total(&self) -> Colour {
+        self.0.iter().sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (PassId, Colour)> + '_ {
+        PassId::ALL.into_iter().zip(self.0.iter().copied())
+    }
+}
+
+impl Index<PassId> for PassColours {
+    type Output = Colour;
+
+    fn index(&self, pass: PassId) -> &Colour {
+        &self.0[pass as usize]
+    }
+}
+
+impl IndexMut<PassId> for PassColours {
+    fn index_mut(&mut self, pass: PassId) -> &mut Colour {
+        &mut self.0[pass as usize]
+    }
+}
+
+impl Add for PassColours {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a += b;
+        }
+        self
+    }
+}
+
+impl Mul<f64> for PassColours {
+    type Output = Self;
+
+    fn mul(self, factor: f64) -> Self {
+        PassColours(self.0.map(|c| c * factor))
+    }
+}
+
+impl Div<f64> for PassColours {
+    type Output = Self;
+
+    fn div(self, divisor: f64) -> Self {
+        PassColours(self.0.map(|c| c / divisor))
+    }
+}
+
+/// What World's shading adds up into: a plain Colour for colour_at, or PassColours to keep the
+/// passes apart for colour_at_passes
+pub(super) trait PassCollector:
+    Copy + Default + Add<Output = Self> + Mul<f64, Output = Self>
+{
+    /// `colour`, counted towards `pass`
+    fn only(pass: PassId, colour: Colour) -> Self;
+
+    /// Direct light from `light`, `visible` being how much of it gets past anything in the way
+    fn lit(
+        material: &Material,
+        light: &dyn Light,
+        comps: &IntersectionComputions,
+        visible: f64,
+    ) -> Self;
+}
+
+impl PassCollector for Colour {
+    fn only(_: PassId, colour: Colour) -> Self {
+        colour
+    }
+
+    fn lit(
+        material: &Material,
+        light: &dyn Light,
+        comps: &IntersectionComputions,
+        visible: f64,
+    ) -> Self {
+        material.lighting(
+            light,
+            comps.over_point,
+            comps.object_point,
+            comps.eye_vector,
+            comps.normal_vector,
+            visible,
+        )
+    }
+}
+
+impl PassCollector for PassColours {
+    fn only(pass: PassId, colour: Colour) -> Self {
+        PassColours::only(pass, colour)
+    }
+
+    fn lit(
+        material: &Material,
+        light: &dyn Light,
+        comps: &IntersectionComputions,
+        visible: f64,
+    ) -> Self {
+        let parts = material.lighting_parts(
+            light,
+            comps.over_point,
+            comps.object_point,
+            comps.eye_vector,
+            comps.normal_vector,
+        );
+        let mut colours = PassColours::default();
+        colours[PassId::Ambient] = parts.ambient;
+        colours[PassId::Diffuse] = parts.diffuse;
+        colours[PassId::Specular] = parts.specular;
+        colours[PassId::Shadow] = (parts.diffuse + parts.specular) * (visible - 1.0);
+        colours
+    }
+}
+
+impl World {
+    /// colour_at, split into passes. Reflections and refractions are followed just as
+    /// colour_at does, each adding up to one colour in its pass.
+    pub fn colour_at_passes(&self, ray: Ray) -> PassColours {
+        self.colour_at_into(ray, &RenderSettings::DEFAULT, None)
+    }
+
+    /// shade_hit, split into passes
+    pub fn shade_hit_passes(&self, comps: IntersectionComputions) -> PassColours {
+        self.shade_hit_with(comps, &RenderSettings::DEFAULT, None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{colour::Colour, math::point::Point, ray::Ray, world::World};
+
+    use super::{PassColours, PassId};
+
+    #[test]
+    fn pass_colours() {
+        let mut colours = PassColours::only(PassId::Diffuse, Colour::new(0.5, 0.25, 0.0));
+        colours[PassId::Shadow] = Colour::new(-0.25, 0.0, 0.0);
+        assert_eq!(colours.total(), Colour::new(0.25, 0.25, 0.0));
+        assert_eq!((colours * 2.0)[PassId::Diffuse], Colour::new(1.0, 0.5, 0.0));
+        assert_eq!(
+            (colours + colours / 2.0).total(),
+            Colour::new(0.375, 0.375, 0.0)
+        );
+        assert_eq!(colours.iter().count(), PassId::ALL.len());
+        for (i, pass) in PassId::ALL.into_iter().enumerate() {
+            assert_eq!(pass as usize, i);
+        }
+    }
+
+    #[test]
+    fn adds_up_to_colour_at() {
+        let world = World::default();
+        for (from, to) in [
+            (Point::newi(0, 0, -5), Point::ORIGIN),
+            (Point::ORIGIN, Point::newi(0, 0, 1)),
+            (Point::newi(0, 0, -5), Point::newi(0, 5, 0)),
+        ] {
+            let ray = Ray::new(from, (to - from).normalize());
+            assert_eq!(world.colour_at_passes(ray).total(), world.colour_at(ray));
+        }
+    }
+}
@@ -0,0 +1,470 @@
+//! Path tracing, for light that's bounced off other surfaces on its way to the eye. See
+//! scene::RenderMode::PathTraced.
+
+use core::f64::consts::PI;
+
+use crate::{
+    colour::Colour,
+    intersection::{IntersectVec, IntersectionComputions},
+    lights::Light,
+    materials::{Material, Sidedness},
+    math::{
+        point::{Point, Vector},
+        rng::Rng,
+        sampling,
+        tuple::Tuple,
+    },
+    ray::{Ray, RayKind},
+};
+
+use super::World;
+
+/// Paths are kept going for at least this many bounces before Russian roulette can end them
+const ROULETTE_AFTER: usize = 3;
+/// The best odds of a path surviving roulette, so even the brightest ones end eventually
+const MAX_SURVIVAL: f64 = 0.95;
+
+impl World {
+    /// One random path's worth of light coming back along `ray`. At every hit that's the
+    /// surface's own emission and the direct light from one light picked at random (lit as
+    /// colour_at would), then the path carries on in one direction: a diffuse bounce picked by
+    /// sampling::cosine_hemisphere, a mirror reflection or a refraction, with odds set by the
+    /// material. Rays that escape see `background`.
+    ///
+    /// Lights with a surface (like lights::SphereLight) are also found by bouncing into them,
+    /// and the two ways of finding them are weighted so they don't count twice, see
+    /// trace_path.
+    ///
+    /// After `max_bounces` bounces, or at random once a path's contributing little (Russian
+    /// roulette), it stops. Any one path is noisy, it's the average of many that's right.
+    pub fn path_traced_colour_at(
+        &self,
+        ray: Ray,
+        max_bounces: usize,
+        background: Colour,
+        rng: &mut Rng,
+    ) -> Colour {
+        self.path_traced_colour_at_clamped(ray, max_bounces, background, None, rng)
+    }
+
+    /// path_traced_colour_at, but with whatever each bounce after the first adds toned down
+    /// (keeping its hue) to at most `max_radiance` in any channel. That's biased, it throws
+    /// light away, but it stops the odd path that finds something tiny and bright from leaving
+    /// a pixel far brighter than its neighbours. What the camera sees directly is left alone.
+    pub fn path_traced_colour_at_clamped(
+        &self,
+        ray: Ray,
+        max_bounces: usize,
+        background: Colour,
+        max_radiance: Option<f64>,
+        rng: &mut Rng,
+    ) -> Colour {
+        self.trace_path(
+            ray,
+            max_bounces,
+            background,
+            max_radiance,
+            Strategies::Both,
+            rng,
+        )
+    }
+
+    /// Lights that aren't delta lights (see Light::is_delta) can be found two ways from a
+    /// diffuse hit: by sampling the light, or by a diffuse bounce that happens to hit it. Both
+    /// are tried, and each is weighted by sampling::power_heuristic against the odds of the
+    /// other finding the same thing, so whichever's better at it counts most. Delta lights can
+    /// only ever be found by sampling them, so they always count in full.
+    fn trace_path(
+        &self,
+        ray: Ray,
+        max_bounces: usize,
+        background: Colour,
+        max_radiance: Option<f64>,
+        strategies: Strategies,
+        rng: &mut Rng,
+    ) -> Colour {
+        let clamp = |bounce: usize, colour: Colour| match max_radiance {
+            Some(max) if bounce > 0 && colour.max_channel() > max => {
+                colour * (max / colour.max_channel())
+            }
+            _ => colour,
+        };
+        let mut radiance = Colour::BLACK;
+        // What's left of the light after all the bounces so far
+        let mut throughput = Colour::WHITE;
+        let mut ray = ray;
+        // Where the last diffuse bounce was from, and the odds of it going the way it did. None
+        // for camera rays and mirror or glass bounces, which no light sample could've matched.
+        let mut diffuse_from: Option<(Tuple, f64)> = None;
+
+        for bounce in 0.. {
+            let xs = self.intersect_world(ray);
+            let hit = xs.hit();
+            if let Some((light, t)) = self.light_hit(&ray) {
+                if hit.is_none_or(|h| t < h.t) {
+                    let weight = match diffuse_from {
+                        None => 1.0,
+                        Some(_) if strategies == Strategies::Lights => 0.0,
+                        Some(_) if strategies == Strategies::Bounces => 1.0,
+                        Some((point, pdf)) => {
+                            let light_pdf = light.pdf(point, ray.direction.normalize().tuple());
+                            sampling::power_heuristic(pdf, light_pdf * self.light_odds())
+                        }
+                    };
+                    return radiance + clamp(bounce, throughput * *light.intensity() * weight);
+                }
+            }
+            let Some(hit) = hit else {
+                return radiance + clamp(bounce, throughput * background);
+            };
+            let comps = hit.prepare_computations_with_bias(ray, &xs, self.bias);
+            let material = comps.object.material();
+            let mut found = throughput * material.emissive;
+
+            let tinted;
+            let lit = match (comps.inside, material.sidedness) {
+                (true, Sidedness::FrontOnly) => None,
+                (true, Sidedness::BackTinted(colour)) => {
+                    tinted = material.clone().with_colour(colour);
+                    Some(&tinted)
+                }
+                _ => Some(material),
+            };
+
+            let albedo = lit.map_or(Colour::BLACK, |m| {
+                m.colour_at(comps.object_point) * m.diffuse
+            });
+            let (reflect, refract) = match (material.reflective, material.transparency) {
+                // Like colour_at, glass splits between the two by the Fresnel term
+                (r, t) if r > 0.0 && t > 0.0 => {
+                    let reflectance = comps.schlick();
+                    (reflectance, 1.0 - reflectance)
+                }
+                (r, t) => (r, t),
+            };
+            // Each way on is picked in proportion to how much it carries, then scaled back up
+            // by the odds of having picked it
+            let weights = [albedo.max_channel(), reflect, refract];
+            let total: f64 = weights.iter().sum();
+            let diffuse_odds = if total > 0.0 { weights[0] / total } else { 0.0 };
+
+            if let Some(lit) = lit {
+                // There's no bounce after the last one to find lights with
+                let bouncing =
+                    bounce < max_bounces && strategies != Strategies::Lights && diffuse_odds > 0.0;
+                let direct = self.sampled_light(
+                    lit,
+                    &comps,
+                    albedo,
+                    bouncing.then_some(diffuse_odds),
+                    strategies,
+                    rng,
+                );
+                found += throughput * direct;
+            }
+            radiance += clamp(bounce, found);
+            if bounce == max_bounces {
+                break;
+            }
+
+            if bounce >= ROULETTE_AFTER {
+                let survival = throughput.max_channel().min(MAX_SURVIVAL);
+                if rng.next_f64() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+
+            diffuse_from = None;
+            ray = match rng.weighted(&weights) {
+                Some(0) => {
+                    throughput = throughput * albedo * (total / weights[0]);
+                    let direction = sampling::cosine_hemisphere(comps.normal_vector, rng);
+                    let pdf = sampling::cosine_hemisphere_pdf(comps.normal_vector, direction);
+                    diffuse_from = Some((comps.over_point, diffuse_odds * pdf));
+                    ray.bounce(
+                        Point::from_tuple(comps.over_point),
+                        Vector::from_tuple(direction),
+                        RayKind::Reflection,
+                    )
+                }
+                Some(1) => {
+                    throughput = throughput * reflect * (total / weights[1]);
+                    comps.reflected_ray()
+                }
+                Some(_) => {
+                    throughput = throughput * refract * (total / weights[2]);
+                    match comps.refracted_ray() {
+                        Some(ray) => ray,
+                        None => break,
+                    }
+                }
+                // Black, and neither reflects nor refracts: nothing carries on
+                None => break,
+            };
+        }
+        radiance
+    }
+
+    /// The nearest light with a surface along `ray`, and how far along it is
+    fn light_hit(&self, ray: &Ray) -> Option<(&dyn Light, f64)> {
+        self.light
+            .iter()
+            .filter_map(|l| Some((&**l, l.intersect(ray)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// The odds of sampled_light picking any one light
+    fn light_odds(&self) -> f64 {
+        1.0 / self.light.len().max(1) as f64
+    }
+
+    /// Direct light at the hit from one light, picked at random and scaled up by the odds of
+    /// that. Light from every light added up, on average.
+    ///
+    /// Delta lights are lit as colour_at would. Other lights are sampled, lighting `albedo` as
+    /// a plain diffuse surface, and weighted against a diffuse bounce (taken with odds
+    /// `diffuse_odds`) finding the same light. None if there won't be a bounce to find it.
+    fn sampled_light(
+        &self,
+        material: &Material,
+        comps: &IntersectionComputions,
+        albedo: Colour,
+        diffuse_odds: Option<f64>,
+        strategies: Strategies,
+        rng: &mut Rng,
+    ) -> Colour {
+        let light = match self.light.len() {
+            0 => return Colour::BLACK,
+            1 => &self.light[0],
+            n => &self.light[((rng.next_f64() * n as f64) as usize).min(n - 1)],
+        };
+        let count = self.light.len() as f64;
+        if light.is_delta() {
+            let lit = material.lighting(
+                &**light,
+                comps.over_point,
+                comps.object_point,
+                comps.eye_vector,
+                comps.normal_vector,
+                if material.receive_shadows {
+                    self.light_intensity_at(&**light, comps.over_point)
+                } else {
+                    1.0
+                },
+            );
+            return lit * count;
+        }
+        if strategies == Strategies::Bounces {
+            return Colour::BLACK;
+        }
+
+        let u = (rng.next_f64(), rng.next_f64());
+        let Some(sample) = light.sample(comps.over_point, u) else {
+            return Colour::BLACK;
+        };
+        let cos = comps.normal_vector.dot(&sample.direction);
+        if cos <= 0.0 || sample.pdf <= 0.0 {
+            return Colour::BLACK;
+        }
+        if material.receive_shadows {
+            let shadow = Ray::new(
+                Point::from_tuple(comps.over_point),
+                Vector::from_tuple(sample.direction),
+            )
+            .with_kind(RayKind::Shadow);
+            if self.any_hit_between(shadow, 0.0, sample.distance) {
+                return Colour::BLACK;
+            }
+        }
+        // The odds of having gone this way, picking the light included
+        let pdf = sample.pdf * self.light_odds();
+        let weight = diffuse_odds.map_or(1.0, |odds| {
+            let bounce_pdf =
+                odds * sampling::cosine_hemisphere_pdf(comps.normal_vector, sample.direction);
+            sampling::power_heuristic(pdf, bounce_pdf)
+        });
+        *light.intensity() * albedo * (cos / PI * weight / pdf)
+    }
+}
+
+/// Which ways trace_path finds lights that aren't delta lights. Only ever both outside of
+/// tests, the others are there to compare against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Strategies {
+    Both,
+    /// Only by sampling them
+    #[cfg_attr(not(test), allow(dead_code))]
+    Lights,
+    /// Only by bouncing into them
+    #[cfg_attr(not(test), allow(dead_code))]
+    Bounces,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        colour::Colour,
+        lights::SphereLight,
+        materials::Material,
+        math::{
+            matrix::{Matrix, IDENTITY_4X4},
+            point::{Point, Vector},
+            rng::Rng,
+            tuple::{point, pointi, Tuple},
+        },
+        ray::Ray,
+        shape::{plane::Plane, sphere::Sphere},
+        world::World,
+    };
+
+    use super::Strategies;
+
+    fn matte(diffuse: f64) -> Material {
+        Material {
+            diffuse,
+            ambient: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        }
+    }
+
+    /// The mean and variance of `samples` paths' luminance, starting from `from` towards `to`
+    fn estimate(
+        world: &World,
+        from: Tuple,
+        to: Tuple,
+        strategies: Strategies,
+        max_bounces: usize,
+        samples: usize,
+    ) -> (f64, f64) {
+        let ray = Ray::new(
+            Point::from_tuple(from),
+            Vector::from_tuple((to - from).normalize()),
+        );
+        let mut rng = Rng::new(17);
+        let values: Vec<f64> = (0..samples)
+            .map(|_| {
+                world
+                    .trace_path(ray, max_bounces, Colour::BLACK, None, strategies, &mut rng)
+                    .luminance()
+            })
+            .collect();
+        let mean = values.iter().sum::<f64>() / samples as f64;
+        let variance =
+            values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / (samples - 1) as f64;
+        (mean, variance)
+    }
+
+    /// A matte floor reflecting half of what lands on it, under a ball of light
+    fn lit_floor(height: f64, radius: f64, brightness: f64) -> World {
+        World::new(
+            vec![Box::new(Plane::new(IDENTITY_4X4, matte(0.5)))],
+            vec![SphereLight::new_boxed(
+                Colour::WHITE * brightness,
+                point(0.0, height, 0.0),
+                radius,
+            )],
+        )
+    }
+
+    #[test]
+    fn unbiased_under_a_sphere_light() {
+        // Right under a ball of radiance L, with sin of its half angle s, a diffuse surface
+        // reflecting a gets pi L s^2 and sends back a L s^2
+        let world = lit_floor(2.0, 1.0, 4.0);
+        let expected = 0.5 * 4.0 * 0.25;
+        for strategies in [Strategies::Both, Strategies::Lights, Strategies::Bounces] {
+            let (mean, _) = estimate(
+                &world,
+                pointi(0, 1, -1),
+                pointi(0, 0, 0),
+                strategies,
+                1,
+                20000,
+            );
+            assert!((mean - expected).abs() < 0.02, "{strategies:?} {mean}");
+        }
+    }
+
+    #[test]
+    fn furnace() {
+        // A ball of light radius r in the middle of a closed shell radius R that reflects a:
+        // with s = r / R the shell's lit by L s^2 directly and by itself everywhere else, so it
+        // glows with a L s^2 / (1 - a (1 - s^2))
+        let world = World::new(
+            vec![Box::new(Sphere::new(
+                Matrix::scaling(10.0, 10.0, 10.0),
+                matte(0.5),
+            ))],
+            vec![SphereLight::new_boxed(
+                Colour::WHITE * 10.0,
+                pointi(0, 0, 0),
+                2.0,
+            )],
+        );
+        let expected = 0.5 * 10.0 * 0.04 / (1.0 - 0.5 * 0.96);
+        for strategies in [Strategies::Both, Strategies::Lights, Strategies::Bounces] {
+            let (mean, _) = estimate(
+                &world,
+                pointi(0, 5, -5),
+                pointi(0, 5, -10),
+                strategies,
+                100,
+                2000,
+            );
+            assert!(
+                (mean - expected).abs() < expected * 0.03,
+                "{strategies:?} {mean} {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn less_noise_than_either_alone() {
+        // A big light low over the floor. Sampling it wastes a lot on the far side that the
+        // floor hardly sees, bouncing misses it out towards the horizon, and between them
+        // they do better than either.
+        let world = lit_floor(3.0, 2.9, 1.0);
+        for x in [0.0, 0.5, 1.0] {
+            let [both, lights, bounces] =
+                [Strategies::Both, Strategies::Lights, Strategies::Bounces].map(|strategies| {
+                    estimate(
+                        &world,
+                        point(x, 0.01, -0.3),
+                        point(x, 0.0, 0.0),
+                        strategies,
+                        1,
+                        4000,
+                    )
+                    .1
+                });
+            assert!(
+                both < lights && both < bounces,
+                "{x} {both} {lights} {bounces}"
+            );
+        }
+    }
+
+    #[test]
+    fn point_lights_are_only_sampled() {
+        // Nothing but a delta light, so the weights all come out as 1 and it's the same as
+        // only ever sampling the lights, down to the last bit
+        let world = World::default();
+        for (from, to) in [
+            (Point::newi(0, 0, -5), Point::ORIGIN),
+            (Point::newi(-1, 1, -5), Point::ORIGIN),
+        ] {
+            let ray = Ray::new(from, (to - from).normalize());
+            let (mut a, mut b) = (Rng::new(3), Rng::new(3));
+            for _ in 0..50 {
+                let both = world.trace_path(ray, 4, Colour::BLACK, None, Strategies::Both, &mut a);
+                let lights =
+                    world.trace_path(ray, 4, Colour::BLACK, None, Strategies::Lights, &mut b);
+                assert_eq!(both.red.to_bits(), lights.red.to_bits());
+                assert_eq!(both.green.to_bits(), lights.green.to_bits());
+                assert_eq!(both.blue.to_bits(), lights.blue.to_bits());
+            }
+        }
+    }
+}
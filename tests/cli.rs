@@ -0,0 +1,351 @@
+//! Running the actual raytracer binary
+
+// The binary needs std
+#![cfg(feature = "std")]
+
+use std::{
+    env, fs,
+    process::{Child, Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[path = "../src/test_util.rs"]
+mod test_util;
+
+use test_util::scratch_dir;
+
+fn raytracer(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_raytracer"))
+        .args(args)
+        .output()
+        .expect("couldn't run raytracer")
+}
+
+fn model(name: &str) -> String {
+    format!("{}/models/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn default_world() {
+    let dir = scratch_dir("cli-default");
+    // Somewhere that doesn't exist yet
+    let out = dir.join("nested/default.ppm");
+    let output = raytracer(&[
+        "--width=11",
+        "--height=11",
+        "--fov=90",
+        "--out",
+        out.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{output:?}");
+
+    let ppm = fs::read(&out).unwrap();
+    let header = b"P6 11 11 255\n";
+    assert_eq!(&ppm[..header.len()], header);
+    assert_eq!(ppm.len(), header.len() + 11 * 11 * 3);
+
+    // The book's 11x11 render of the default world, the middle pixel is
+    // Colour::new(0.38066, 0.47583, 0.2855)
+    let middle = header.len() + (5 * 11 + 5) * 3;
+    assert_eq!(&ppm[middle..middle + 3], &[97, 121, 73]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn model_to_png() {
+    let dir = scratch_dir("cli-model");
+    let out = dir.join("tetrahedron.png");
+    let output = raytracer(&[
+        &model("tetrahedron.obj"),
+        "--width",
+        "24",
+        "--height",
+        "16",
+        "--samples",
+        "2",
+        "--out",
+        out.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{output:?}");
+
+    let png = fs::read(&out).unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    // IHDR's width and height
+    assert_eq!(&png[16..24], &[0, 0, 0, 24, 0, 0, 0, 16]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn model_is_in_view() {
+    let dir = scratch_dir("cli-in-view");
+    let out = dir.join("tetrahedron.ppm");
+    let output = raytracer(&[
+        &model("tetrahedron.obj"),
+        "--width=20",
+        "--height=20",
+        "--out",
+        out.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{output:?}");
+
+    let ppm = fs::read(&out).unwrap();
+    let header = b"P6 20 20 255\n";
+    assert_eq!(&ppm[..header.len()], header);
+    // Not just one flat colour, there's something there
+    let pixels: Vec<_> = ppm[header.len()..].chunks(3).collect();
+    assert_eq!(pixels.len(), 400);
+    assert!(pixels.iter().any(|p| *p != pixels[0]));
+    // And it's in the middle
+    assert_ne!(pixels[10 * 20 + 10], pixels[0]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn bad_arguments() {
+    for args in [
+        &["--width", "nope"][..],
+        &["--samples=0"],
+        &["--out", "render.jpg"],
+        &["--frobnicate"],
+        &["--height"],
+    ] {
+        let output = raytracer(args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.starts_with("error: "), "{stderr}");
+        assert!(stderr.contains("usage: raytracer"), "{stderr}");
+        assert!(!stderr.contains("panicked"), "{stderr}");
+    }
+}
+
+#[test]
+fn help() {
+    let output = raytracer(&["--help"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("usage: raytracer"));
+}
+
+#[test]
+fn unloadable_models() {
+    let dir = scratch_dir("cli-unloadable");
+    fs::create_dir_all(&dir).unwrap();
+    let out = dir.join("never.ppm");
+    let missing = dir.join("missing.obj");
+    let unknown = dir.join("model.xyz");
+    fs::write(&unknown, "").unwrap();
+
+    for (model, message) in [
+        (&missing, "can't open"),
+        (&unknown, "don't know how to load"),
+    ] {
+        let output = raytracer(&[model.to_str().unwrap(), "--out", out.to_str().unwrap()]);
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.starts_with("error: "), "{stderr}");
+        assert!(stderr.contains(message), "{stderr}");
+        assert!(!stderr.contains("panicked"), "{stderr}");
+    }
+    assert!(!out.exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+fn scene(name: &str) -> String {
+    format!("{}/scenes/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn scene_file() {
+    let dir = scratch_dir("cli-scene");
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("small.yml");
+    fs::write(
+        &file,
+        "\
+- add: camera
+  width: 12
+  height: 6
+  field-of-view: 1.5
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+- add: sphere
+  material: {color: red}
+  colour: blue
+",
+    )
+    .unwrap();
+    let out = dir.join("small.ppm");
+    // The camera's from the file, not --width
+    let output = raytracer(&[
+        file.to_str().unwrap(),
+        "--width=100",
+        "--out",
+        out.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("small.yml: line 13: warning: sphere.colour: unknown key `colour`"),
+        "{stderr}"
+    );
+
+    let ppm = fs::read(&out).unwrap();
+    let header = b"P6 12 6 255\n";
+    assert_eq!(&ppm[..header.len()], header);
+    // Red in the middle, black in the corner
+    let middle = header.len() + (3 * 12 + 6) * 3;
+    assert!(ppm[middle] > 0 && ppm[middle + 1] == 0 && ppm[middle + 2] == 0);
+    assert_eq!(&ppm[header.len()..header.len() + 3], &[0, 0, 0]);
+
+    // Errors stop it rendering at all
+    let broken = raytracer(&[&scene("lint_issues.yaml"), "--out", out.to_str().unwrap()]);
+    assert_eq!(broken.status.code(), Some(1));
+    let stderr = String::from_utf8(broken.stderr).unwrap();
+    assert!(stderr.contains("scene has 4 error(s)"), "{stderr}");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lint() {
+    let clean = raytracer(&["lint", &scene("example.yaml")]);
+    assert!(clean.status.success(), "{clean:?}");
+    assert!(String::from_utf8(clean.stdout)
+        .unwrap()
+        .ends_with("example.yaml: ok\n"));
+
+    let issues = raytracer(&["lint", &scene("lint_issues.yaml"), "missing.yaml"]);
+    assert_eq!(issues.status.code(), Some(1));
+    let stdout = String::from_utf8(issues.stdout).unwrap();
+    let lines: Vec<_> = stdout
+        .lines()
+        .map(|l| l.rsplit_once("scenes/").map_or(l, |(_, l)| l))
+        .collect();
+    assert_eq!(lines.len(), 8, "{stdout}");
+    assert_eq!(
+        lines[0],
+        "lint_issues.yaml: line 3: error: camera: from and to must be different"
+    );
+    assert_eq!(
+        lines[5],
+        "lint_issues.yaml: line 26: warning: plane.shiny: unknown key `shiny`, ignored"
+    );
+    assert!(lines[7].starts_with("missing.yaml: error: reading scene: "));
+
+    // Only warnings still passes
+    let dir = scratch_dir("cli-lint");
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("warnings.json");
+    fs::write(
+        &file,
+        r#"[{"add": "camera", "width": 2, "height": 2, "field-of-view": 1,
+             "from": [0, 0, -5], "to": [0, 0, 0], "up": [0, 1, 0], "fov": 1}]"#,
+    )
+    .unwrap();
+    let warnings = raytracer(&["lint", file.to_str().unwrap()]);
+    assert!(warnings.status.success(), "{warnings:?}");
+    assert!(String::from_utf8(warnings.stdout)
+        .unwrap()
+        .contains("line 2: warning: camera.fov: unknown key `fov`, ignored"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Kills the process if the test fails part way, rather than leaving it running
+struct KillOnDrop(Option<Child>);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.0 {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+#[test]
+fn watch() {
+    let dir = scratch_dir("cli-watch");
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("live.yaml");
+    let scene = |colour: &str| {
+        format!(
+            "\
+- add: camera
+  width: 8
+  height: 4
+  field-of-view: 1.5
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+- add: sphere
+  material: {{colour: {colour}}}
+"
+        )
+    };
+    fs::write(&file, scene("red")).unwrap();
+    let out = dir.join("live.ppm");
+
+    let mut child = KillOnDrop(Some(
+        Command::new(env!("CARGO_BIN_EXE_raytracer"))
+            .args([
+                "watch",
+                file.to_str().unwrap(),
+                "--out",
+                out.to_str().unwrap(),
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap(),
+    ));
+    let wait_for = |what: &str, done: &dyn Fn() -> bool| {
+        let start = Instant::now();
+        while !done() {
+            assert!(
+                start.elapsed() < Duration::from_secs(30),
+                "gave up waiting for {what}"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    };
+    // The middle pixel's red channel
+    let middle = |ppm: &[u8]| ppm[b"P6 8 4 255\n".len() + (2 * 8 + 4) * 3];
+
+    wait_for("the first render", &|| out.exists());
+    let first = fs::read(&out).unwrap();
+    assert!(middle(&first) > 0);
+
+    // Broken, so the image stays as it was
+    fs::write(&file, "- add: camera\n  width: [\n").unwrap();
+    thread::sleep(Duration::from_millis(1000));
+    assert_eq!(fs::read(&out).unwrap(), first);
+
+    fs::write(&file, scene("blue")).unwrap();
+    wait_for("the second render", &|| fs::read(&out).unwrap() != first);
+    assert_eq!(middle(&fs::read(&out).unwrap()), 0);
+    // Nothing left behind by the atomic writes
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+    let mut child = child.0.take().unwrap();
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("error: can't load"), "{stderr}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("written to").count(), 2, "{stdout}");
+
+    fs::remove_dir_all(dir).unwrap();
+}
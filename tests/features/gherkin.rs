@@ -0,0 +1,185 @@
+//! Just enough Gherkin for the book's feature files: Feature, Background, Scenario, Scenario
+//! Outline + Examples, tags, and tables attached to steps. Outlines are expanded into one
+//! scenario per example row.
+
+pub type Table = Vec<Vec<String>>;
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub text: String,
+    pub table: Option<Table>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Default)]
+pub struct Feature {
+    pub name: String,
+    pub background: Vec<Step>,
+    pub scenarios: Vec<Scenario>,
+}
+
+/// Where steps currently being read end up
+enum Section {
+    None,
+    Background,
+    Scenario,
+    Outline,
+    Examples,
+}
+
+const STEP_KEYWORDS: [&str; 6] = ["Given ", "When ", "Then ", "And ", "But ", "* "];
+
+pub fn parse(source: &str) -> Result<Feature, String> {
+    let mut feature = Feature::default();
+    let mut section = Section::None;
+    let mut tags = vec![];
+    // The outline being read, and the example rows (header first) seen for it so far
+    let mut outline: Option<Scenario> = None;
+    let mut examples: Table = vec![];
+
+    for (n, raw) in source.lines().enumerate() {
+        let line = raw.trim();
+        let number = n + 1;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('@') {
+            tags.extend(line.split_whitespace().map(str::to_owned));
+        } else if let Some(name) = line.strip_prefix("Feature:") {
+            feature.name = name.trim().to_owned();
+        } else if line.starts_with("Background:") {
+            section = Section::Background;
+        } else if let Some(name) = line
+            .strip_prefix("Scenario Outline:")
+            .or_else(|| line.strip_prefix("Scenario Template:"))
+        {
+            finish_outline(&mut feature, outline.take(), &mut examples)?;
+            outline = Some(Scenario {
+                name: name.trim().to_owned(),
+                tags: std::mem::take(&mut tags),
+                steps: vec![],
+            });
+            section = Section::Outline;
+        } else if let Some(name) = line
+            .strip_prefix("Scenario:")
+            .or_else(|| line.strip_prefix("Example:"))
+        {
+            finish_outline(&mut feature, outline.take(), &mut examples)?;
+            feature.scenarios.push(Scenario {
+                name: name.trim().to_owned(),
+                tags: std::mem::take(&mut tags),
+                steps: vec![],
+            });
+            section = Section::Scenario;
+        } else if line.starts_with("Examples:") || line.starts_with("Scenarios:") {
+            if outline.is_none() {
+                return Err(format!(
+                    "line {number}: Examples without a Scenario Outline"
+                ));
+            }
+            section = Section::Examples;
+        } else if line.starts_with('|') {
+            let row = table_row(line);
+            if let Section::Examples = section {
+                // Each Examples block repeats the header, only the first is kept
+                if examples.is_empty() || row != examples[0] {
+                    examples.push(row);
+                }
+                continue;
+            }
+            let step = current_steps(&mut feature, &mut outline, &section)
+                .and_then(|steps| steps.last_mut())
+                .ok_or(format!("line {number}: table without a step"))?;
+            step.table.get_or_insert_with(Vec::new).push(row);
+        } else if let Some(keyword) = STEP_KEYWORDS.iter().find(|k| line.starts_with(*k)) {
+            let step = Step {
+                text: line[keyword.len()..].trim().to_owned(),
+                table: None,
+                line: number,
+            };
+            current_steps(&mut feature, &mut outline, &section)
+                .ok_or(format!("line {number}: step outside of a scenario"))?
+                .push(step);
+        } else if let Section::None = section {
+            // Free text describing the feature
+        } else {
+            return Err(format!(
+                "line {number}: don't know what to do with {line:?}"
+            ));
+        }
+    }
+
+    finish_outline(&mut feature, outline, &mut examples)?;
+    Ok(feature)
+}
+
+fn current_steps<'a>(
+    feature: &'a mut Feature,
+    outline: &'a mut Option<Scenario>,
+    section: &Section,
+) -> Option<&'a mut Vec<Step>> {
+    match section {
+        Section::Background => Some(&mut feature.background),
+        Section::Scenario => feature.scenarios.last_mut().map(|s| &mut s.steps),
+        Section::Outline => outline.as_mut().map(|s| &mut s.steps),
+        Section::None | Section::Examples => None,
+    }
+}
+
+fn table_row(line: &str) -> Vec<String> {
+    let inner = line.trim_matches('|');
+    inner.split('|').map(|c| c.trim().to_owned()).collect()
+}
+
+/// One scenario per example row, with every `<name>` swapped for that row's value
+fn finish_outline(
+    feature: &mut Feature,
+    outline: Option<Scenario>,
+    examples: &mut Table,
+) -> Result<(), String> {
+    let Some(outline) = outline else {
+        return Ok(());
+    };
+    let rows = std::mem::take(examples);
+    let Some((header, rows)) = rows.split_first() else {
+        return Err(format!("outline {:?} has no examples", outline.name));
+    };
+
+    for (n, row) in rows.iter().enumerate() {
+        let fill = |s: &str| {
+            header
+                .iter()
+                .zip(row)
+                .fold(s.to_owned(), |s, (k, v)| s.replace(&format!("<{k}>"), v))
+        };
+
+        let steps = outline
+            .steps
+            .iter()
+            .map(|step| Step {
+                text: fill(&step.text),
+                table: step.table.as_ref().map(|t| {
+                    t.iter()
+                        .map(|r| r.iter().map(|c| fill(c)).collect())
+                        .collect()
+                }),
+                line: step.line,
+            })
+            .collect();
+
+        feature.scenarios.push(Scenario {
+            name: format!("{} (example {})", outline.name, n + 1),
+            tags: outline.tags.clone(),
+            steps,
+        });
+    }
+    Ok(())
+}
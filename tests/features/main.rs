@@ -0,0 +1,134 @@
+//! Runs the Gherkin feature files in this directory against the crate, in the style of the
+//! book's own feature files. Any other `.feature` file dropped in here (say, the rest of the
+//! book's) is run by `dropped_in`. Scenarios tagged `@skip` are left out.
+
+mod gherkin;
+mod steps;
+
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+/// The files with their own test below
+const SHIPPED: [&str; 10] = [
+    "tuples.feature",
+    "matrices.feature",
+    "transformations.feature",
+    "rays.feature",
+    "spheres.feature",
+    "intersections.feature",
+    "lights.feature",
+    "materials.feature",
+    "world.feature",
+    "camera.feature",
+];
+
+fn features_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/features")
+}
+
+/// Every failing scenario in the file, with the step that failed and why
+fn run(path: &Path) -> Vec<String> {
+    let source = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => return vec![format!("can't read it: {e}")],
+    };
+    let feature = match gherkin::parse(&source) {
+        Ok(f) => f,
+        Err(e) => return vec![e],
+    };
+
+    feature
+        .scenarios
+        .iter()
+        .filter(|s| !s.tags.iter().any(|t| t == "@skip"))
+        .filter_map(|scenario| {
+            let mut env = steps::Env::default();
+            for step in feature.background.iter().chain(&scenario.steps) {
+                // Our own asserts/expects panicking is a failure of the step, not the harness
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    env.run(&step.text, step.table.as_ref())
+                }))
+                .unwrap_or_else(|_| Err("panicked".to_owned()));
+
+                if let Err(e) = result {
+                    return Some(format!(
+                        "{} / {}, line {}: {:?}: {e}",
+                        feature.name, scenario.name, step.line, step.text
+                    ));
+                }
+            }
+            None
+        })
+        .collect()
+}
+
+fn check(file: &str) {
+    let failures = run(&features_dir().join(file));
+    assert!(failures.is_empty(), "{file}:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn tuples() {
+    check("tuples.feature");
+}
+
+#[test]
+fn matrices() {
+    check("matrices.feature");
+}
+
+#[test]
+fn transformations() {
+    check("transformations.feature");
+}
+
+#[test]
+fn rays() {
+    check("rays.feature");
+}
+
+#[test]
+fn spheres() {
+    check("spheres.feature");
+}
+
+#[test]
+fn intersections() {
+    check("intersections.feature");
+}
+
+#[test]
+fn lights() {
+    check("lights.feature");
+}
+
+#[test]
+fn materials() {
+    check("materials.feature");
+}
+
+#[test]
+fn world() {
+    check("world.feature");
+}
+
+#[test]
+fn camera() {
+    check("camera.feature");
+}
+
+#[test]
+fn dropped_in() {
+    let mut failures = vec![];
+    for entry in fs::read_dir(features_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy();
+        if path.extension().is_some_and(|e| e == "feature") && !SHIPPED.contains(&&*name) {
+            failures.extend(run(&path).into_iter().map(|f| format!("{name}: {f}")));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,1138 @@
+//! Step definitions. The book writes its steps as pseudo-code (`n ← normal_at(s, p)`,
+//! `Then determinant(A) = 17`), so rather than one pattern per step this evaluates that
+//! pseudo-code, mapping the book's names onto ours (determinant -> determinate, color_at ->
+//! colour_at, ...).
+
+use std::{
+    cell::{Ref, RefCell},
+    collections::HashMap,
+    f64::consts::PI,
+    rc::Rc,
+};
+
+use raytracer::{
+    camera::Camera,
+    canvas::Canvas,
+    colour::Colour,
+    intersection::{IntersectVec, Intersection},
+    lights::PointLight,
+    materials::Material,
+    math::{
+        float::{self, EPSILON},
+        matrix::{Matrix, IDENTITY_4X4},
+        tuple::{point, vector, Tuple},
+    },
+    pattern::solid::SolidPattern,
+    ray::{Ray, RayIntersect},
+    shape::{plane::Plane, sphere::Sphere, Shape},
+    world::World,
+};
+
+use crate::gherkin::Table;
+
+/// A shape the steps can keep hold of. Either one of our own, or one that's been handed over
+/// to a world (which owns its objects).
+#[derive(Clone)]
+enum ShapeRef {
+    Own(Rc<RefCell<Box<dyn Shape>>>),
+    InWorld(Rc<RefCell<World>>, usize),
+}
+
+impl ShapeRef {
+    fn new(shape: impl Shape + 'static) -> Self {
+        ShapeRef::Own(Rc::new(RefCell::new(Box::new(shape))))
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&dyn Shape) -> R) -> R {
+        self.guard().with(f)
+    }
+
+    fn with_mut<R>(&self, f: impl FnOnce(&mut dyn Shape) -> R) -> R {
+        match self {
+            ShapeRef::Own(s) => f(&mut **s.borrow_mut()),
+            ShapeRef::InWorld(w, i) => f(w.borrow_mut().object_mut(*i).expect("object index")),
+        }
+    }
+
+    fn guard(&self) -> Guard<'_> {
+        match self {
+            ShapeRef::Own(s) => Guard::Own(s.borrow()),
+            ShapeRef::InWorld(w, i) => Guard::World(w.borrow(), *i),
+        }
+    }
+
+    fn same(&self, other: &ShapeRef) -> bool {
+        self.with(|a| other.with(|b| a.id() == b.id()))
+    }
+}
+
+enum Guard<'a> {
+    Own(Ref<'a, Box<dyn Shape>>),
+    World(Ref<'a, World>, usize),
+}
+
+impl Guard<'_> {
+    fn shape(&self) -> &dyn Shape {
+        match self {
+            Guard::Own(s) => &***s,
+            Guard::World(w, i) => w.object(*i).expect("object index"),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&dyn Shape) -> R) -> R {
+        f(self.shape())
+    }
+}
+
+/// An Intersection that doesn't borrow its object
+#[derive(Clone)]
+struct Hit {
+    t: f64,
+    object: ShapeRef,
+}
+
+impl Hit {
+    fn same(&self, other: &Hit) -> bool {
+        self.t.to_bits() == other.t.to_bits() && self.object.same(&other.object)
+    }
+}
+
+/// Turn `hits` back into real Intersections for as long as `f` runs
+fn with_intersections<R>(hits: &[Hit], f: impl FnOnce(&[Intersection<'_>]) -> R) -> R {
+    let guards: Vec<_> = hits.iter().map(|h| h.object.guard()).collect();
+    let xs: Vec<_> = hits
+        .iter()
+        .zip(&guards)
+        .map(|(h, g)| Intersection::new(h.t, g.shape()))
+        .collect();
+    f(&xs)
+}
+
+/// IntersectionComputions, copied out so it doesn't borrow anything. Keeps what it was made
+/// from so shade_hit and friends can make the real thing again.
+struct Comps {
+    hits: Vec<Hit>,
+    index: usize,
+    ray: Ray,
+    t: f64,
+    point: Tuple,
+    over_point: Tuple,
+    under_point: Tuple,
+    eyev: Tuple,
+    normalv: Tuple,
+    reflectv: Tuple,
+    inside: bool,
+    n1: f64,
+    n2: f64,
+    schlick: f64,
+}
+
+impl Comps {
+    fn new(hits: Vec<Hit>, index: usize, ray: Ray) -> Self {
+        let c = with_intersections(&hits, |xs| {
+            let c = xs[index].prepare_computations_with(ray, xs);
+            Comps {
+                hits: vec![],
+                index,
+                ray,
+                t: c.t,
+                point: c.point,
+                over_point: c.over_point,
+                under_point: c.under_point,
+                eyev: c.eye_vector,
+                normalv: c.normal_vector,
+                reflectv: c.reflect_vector,
+                inside: c.inside,
+                n1: c.n1,
+                n2: c.n2,
+                schlick: c.schlick(),
+            }
+        });
+        Comps { hits, ..c }
+    }
+}
+
+#[derive(Clone)]
+enum Value {
+    Num(f64),
+    Bool(bool),
+    Tuple(Tuple),
+    Colour(Colour),
+    Matrix(Matrix),
+    Ray(Ray),
+    Shape(ShapeRef),
+    Hit(Hit),
+    Hits(Vec<Hit>),
+    Comps(Rc<Comps>),
+    Material(Material),
+    Light(PointLight),
+    World(Rc<RefCell<World>>),
+    Camera(Camera),
+    Canvas(Rc<Canvas>),
+    /// `(a, b, c)`, only used for colours in tables
+    List(Vec<Value>),
+    Nothing,
+}
+
+macro_rules! getter {
+    ($name:ident, $variant:ident, $ty:ty) => {
+        fn $name(&self) -> Result<$ty, String> {
+            match self {
+                Value::$variant(v) => Ok(v.clone()),
+                v => Err(format!(
+                    "expected {}, got {}",
+                    stringify!($variant).to_lowercase(),
+                    v.kind()
+                )),
+            }
+        }
+    };
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Num(_) => "a number",
+            Value::Bool(_) => "a bool",
+            Value::Tuple(_) => "a tuple",
+            Value::Colour(_) => "a colour",
+            Value::Matrix(_) => "a matrix",
+            Value::Ray(_) => "a ray",
+            Value::Shape(_) => "a shape",
+            Value::Hit(_) => "an intersection",
+            Value::Hits(_) => "intersections",
+            Value::Comps(_) => "computations",
+            Value::Material(_) => "a material",
+            Value::Light(_) => "a light",
+            Value::World(_) => "a world",
+            Value::Camera(_) => "a camera",
+            Value::Canvas(_) => "a canvas",
+            Value::List(_) => "a list",
+            Value::Nothing => "nothing",
+        }
+    }
+
+    getter!(num, Num, f64);
+    getter!(bool, Bool, bool);
+    getter!(tuple, Tuple, Tuple);
+    getter!(matrix, Matrix, Matrix);
+    getter!(ray, Ray, Ray);
+    getter!(shape, Shape, ShapeRef);
+    getter!(hit, Hit, Hit);
+    getter!(hits, Hits, Vec<Hit>);
+    getter!(comps, Comps, Rc<Comps>);
+    getter!(material, Material, Material);
+    getter!(light, Light, PointLight);
+    getter!(world, World, Rc<RefCell<World>>);
+    getter!(camera, Camera, Camera);
+    getter!(canvas, Canvas, Rc<Canvas>);
+
+    fn index(&self) -> Result<usize, String> {
+        let n = self.num()?;
+        if n < 0.0 || n.fract() != 0.0 {
+            return Err(format!("expected a whole number, got {n}"));
+        }
+        Ok(n as usize)
+    }
+
+    fn colour(&self) -> Result<Colour, String> {
+        match self {
+            Value::Colour(c) => Ok(*c),
+            Value::List(l) if l.len() == 3 => {
+                Ok(Colour::new(l[0].num()?, l[1].num()?, l[2].num()?))
+            }
+            v => Err(format!("expected a colour, got {}", v.kind())),
+        }
+    }
+
+    fn field(&self, name: &str) -> Result<Value, String> {
+        let v = match (self, name) {
+            (Value::Tuple(t), "x") => Value::Num(t.x),
+            (Value::Tuple(t), "y") => Value::Num(t.y),
+            (Value::Tuple(t), "z") => Value::Num(t.z),
+            (Value::Tuple(t), "w") => Value::Num(t.w),
+            (Value::Colour(c), "red") => Value::Num(c.red),
+            (Value::Colour(c), "green") => Value::Num(c.green),
+            (Value::Colour(c), "blue") => Value::Num(c.blue),
+            (Value::Ray(r), "origin") => Value::Tuple(r.origin.into()),
+            (Value::Ray(r), "direction") => Value::Tuple(r.direction.into()),
+            (Value::Shape(s), "transform") => Value::Matrix(s.with(|s| s.transform().clone())),
+            (Value::Shape(s), "material") => Value::Material(s.with(|s| s.material().clone())),
+            (Value::Material(m), field) => material_field(m, field)?,
+            (Value::Light(l), "position") => Value::Tuple(l.position),
+            (Value::Light(l), "intensity") => Value::Colour(l.intensity),
+            (Value::Hit(h), "t") => Value::Num(h.t),
+            (Value::Hit(h), "object") => Value::Shape(h.object.clone()),
+            (Value::Hits(xs), "count") => Value::Num(xs.len() as f64),
+            (Value::Comps(c), field) => match field {
+                "t" => Value::Num(c.t),
+                "object" => Value::Shape(c.hits[c.index].object.clone()),
+                "point" => Value::Tuple(c.point),
+                "over_point" => Value::Tuple(c.over_point),
+                "under_point" => Value::Tuple(c.under_point),
+                "eyev" => Value::Tuple(c.eyev),
+                "normalv" => Value::Tuple(c.normalv),
+                "reflectv" => Value::Tuple(c.reflectv),
+                "inside" => Value::Bool(c.inside),
+                "n1" => Value::Num(c.n1),
+                "n2" => Value::Num(c.n2),
+                _ => return Err(format!("computations have no {name}")),
+            },
+            (Value::Camera(c), "hsize") => Value::Num(c.hsize as f64),
+            (Value::Camera(c), "vsize") => Value::Num(c.vsize as f64),
+            (Value::Camera(c), "field_of_view") => Value::Num(c.fov),
+            (Value::Camera(c), "pixel_size") => Value::Num(c.pixel_size),
+            (Value::Camera(c), "transform") => Value::Matrix(c.transform.clone()),
+            (Value::Canvas(c), "width") => Value::Num(c.width as f64),
+            (Value::Canvas(c), "height") => Value::Num(c.height as f64),
+            (Value::World(w), "light") => match w.borrow().lights().next() {
+                Some(l) => Value::Light(
+                    *l.downcast_ref::<PointLight>()
+                        .ok_or("only point lights are supported")?,
+                ),
+                None => Value::Nothing,
+            },
+            (v, _) => return Err(format!("{} has no {name}", v.kind())),
+        };
+        Ok(v)
+    }
+}
+
+fn material_field(m: &Material, name: &str) -> Result<Value, String> {
+    Ok(match name {
+        "color" => Value::Colour(
+            m.colour()
+                .ok_or_else(|| "the material's colour isn't solid".to_string())?,
+        ),
+        "ambient" => Value::Num(m.ambient),
+        "diffuse" => Value::Num(m.diffuse),
+        "specular" => Value::Num(m.specular),
+        "shininess" => Value::Num(m.shininess),
+        "reflective" => Value::Num(m.reflective),
+        "transparency" => Value::Num(m.transparency),
+        "refractive_index" => Value::Num(m.refractive_index),
+        _ => return Err(format!("a material has no {name}")),
+    })
+}
+
+fn set_material_field(m: &mut Material, name: &str, v: &Value) -> Result<(), String> {
+    match name {
+        "color" => m.pattern = Box::new(SolidPattern(v.colour()?)),
+        "ambient" => m.ambient = v.num()?,
+        "diffuse" => m.diffuse = v.num()?,
+        "specular" => m.specular = v.num()?,
+        "shininess" => m.shininess = v.num()?,
+        "reflective" => m.reflective = v.num()?,
+        "transparency" => m.transparency = v.num()?,
+        "refractive_index" => m.refractive_index = v.num()?,
+        _ => return Err(format!("a material has no {name}")),
+    }
+    Ok(())
+}
+
+/// `target.path... ← v`
+fn set_path(target: &mut Value, path: &[&str], v: Value) -> Result<(), String> {
+    match (target, path) {
+        (Value::Shape(s), ["transform"]) => {
+            let m = v.matrix()?;
+            s.with_mut(|s| s.set_transform(m));
+        }
+        (Value::Shape(s), ["material"]) => {
+            let m = v.material()?;
+            s.with_mut(|s| s.set_material(m));
+        }
+        (Value::Shape(s), ["material", field]) => {
+            let mut m = s.with(|s| s.material().clone());
+            set_material_field(&mut m, field, &v)?;
+            s.with_mut(|s| s.set_material(m));
+        }
+        (Value::Material(m), [field]) => set_material_field(m, field, &v)?,
+        (Value::Camera(c), ["transform"]) => {
+            *c = Camera::try_new_with_transform(c.hsize, c.vsize, c.fov, v.matrix()?)
+                .map_err(|e| e.to_string())?;
+        }
+        (Value::World(w), ["light"]) => {
+            let light = v.light()?;
+            let mut w = w.borrow_mut();
+            let old = std::mem::replace(&mut *w, World::new(vec![], vec![]));
+            *w = old.with_lights(vec![Box::new(light)]);
+        }
+        (target, path) => {
+            return Err(format!("can't set {} on {}", path.join("."), target.kind()));
+        }
+    }
+    Ok(())
+}
+
+fn equal(a: &Value, b: &Value) -> Result<bool, String> {
+    Ok(match (a, b) {
+        (Value::Num(a), Value::Num(b)) => float::equal(*a, *b),
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Tuple(a), Value::Tuple(b)) => a == b,
+        (Value::Colour(a), Value::Colour(b)) => a == b,
+        (Value::Matrix(a), Value::Matrix(b)) => a == b,
+        (Value::Ray(a), Value::Ray(b)) => a.origin == b.origin && a.direction == b.direction,
+        (Value::Shape(a), Value::Shape(b)) => a.same(b),
+        (Value::Hit(a), Value::Hit(b)) => a.same(b),
+        // The book compares intersections to plain t values now and then
+        (Value::Hit(h), Value::Num(t)) | (Value::Num(t), Value::Hit(h)) => float::equal(h.t, *t),
+        (Value::Material(a), Value::Material(b)) => a == b,
+        (Value::Light(a), Value::Light(b)) => {
+            a.position == b.position && a.intensity == b.intensity
+        }
+        (Value::Nothing, Value::Nothing) => true,
+        (a, b) => return Err(format!("can't compare {} with {}", a.kind(), b.kind())),
+    })
+}
+
+fn binary(op: char, a: Value, b: Value) -> Result<Value, String> {
+    use Value::*;
+    Ok(match (op, a, b) {
+        ('+', Num(a), Num(b)) => Num(a + b),
+        ('-', Num(a), Num(b)) => Num(a - b),
+        ('*', Num(a), Num(b)) => Num(a * b),
+        ('/', Num(a), Num(b)) => Num(a / b),
+        ('+', Tuple(a), Tuple(b)) => Tuple(a + b),
+        ('-', Tuple(a), Tuple(b)) => Tuple(a - b),
+        ('*', Tuple(a), Num(b)) | ('*', Num(b), Tuple(a)) => Tuple(a * b),
+        ('/', Tuple(a), Num(b)) => Tuple(a * b.recip()),
+        ('+', Colour(a), Colour(b)) => Colour(a + b),
+        ('-', Colour(a), Colour(b)) => Colour(a - b),
+        ('*', Colour(a), Colour(b)) => Colour(a * b),
+        ('*', Colour(a), Num(b)) | ('*', Num(b), Colour(a)) => Colour(a * b),
+        ('/', Colour(a), Num(b)) => Colour(a / b),
+        ('*', Matrix(a), Matrix(b)) => Matrix(&a * &b),
+        ('*', Matrix(a), Tuple(b)) => Tuple(&a * b),
+        (op, a, b) => return Err(format!("can't do {} {op} {}", a.kind(), b.kind())),
+    })
+}
+
+fn args<const N: usize>(name: &str, args: Vec<Value>) -> Result<[Value; N], String> {
+    let got = args.len();
+    args.try_into()
+        .map_err(|_| format!("{name} takes {N} arguments, got {got}"))
+}
+
+fn matrix_from(table: Option<&Table>, env: &Env) -> Result<Matrix, String> {
+    let table = table.ok_or("expected a table of values")?;
+    let width = table.first().map_or(0, |r| r.len());
+    let mut data = vec![];
+    for row in table {
+        if row.len() != width {
+            return Err("matrix rows are different lengths".to_owned());
+        }
+        for cell in row {
+            data.push(env.eval(cell)?.num()?);
+        }
+    }
+    Matrix::try_new_with_data(width, table.len(), data).map_err(|e| e.to_string())
+}
+
+/// The variables set by a scenario's steps so far
+#[derive(Default)]
+pub struct Env {
+    vars: HashMap<String, Value>,
+}
+
+impl Env {
+    /// Run a single step, `text` being everything after the Given/When/Then
+    pub fn run(&mut self, text: &str, table: Option<&Table>) -> Result<(), String> {
+        if let Some(rest) = text.strip_prefix("the following ") {
+            // the following 4x4 matrix M:
+            let (_, name) = rest
+                .trim_end_matches(':')
+                .rsplit_once(' ')
+                .ok_or("no name")?;
+            let m = matrix_from(table, self)?;
+            self.vars.insert(name.to_owned(), Value::Matrix(m));
+            return Ok(());
+        }
+        if let Some((lhs, _)) = text.split_once(" is the following ") {
+            let expected = Value::Matrix(matrix_from(table, self)?);
+            return self.check(lhs, "=", expected);
+        }
+        if let Some((name, rest)) = text.split_once(" ← the ") {
+            // shape ← the first object in w
+            let (nth, world) = rest.split_once(" object in ").ok_or("expected an object")?;
+            let index = match nth {
+                "first" => 0,
+                "second" => 1,
+                _ => return Err(format!("which object is the {nth}?")),
+            };
+            let world = self.eval(world)?.world()?;
+            if world.borrow().object(index).is_none() {
+                return Err(format!("there is no {nth} object"));
+            }
+            let shape = Value::Shape(ShapeRef::InWorld(world, index));
+            self.vars.insert(name.to_owned(), shape);
+            return Ok(());
+        }
+        if let Some((lhs, rhs)) = text.split_once(" ← ") {
+            let (rhs, with) = match rhs.strip_suffix(" with:") {
+                Some(rhs) => (rhs, true),
+                None => (rhs, false),
+            };
+            let mut value = self.eval(rhs)?;
+            if with {
+                for row in table.ok_or("expected a table of properties")? {
+                    let [path, v] = row.as_slice() else {
+                        return Err("property rows need a name and a value".to_owned());
+                    };
+                    let path: Vec<_> = path.split('.').collect();
+                    set_path(&mut value, &path, self.eval(v)?)?;
+                }
+            }
+            return self.assign(lhs, value);
+        }
+        if let Some((shape, world)) = text.split_once(" is added to ") {
+            return self.add_to_world(shape, world);
+        }
+        if let Some((world, shape)) = text.split_once(" contains ") {
+            let shape = self.eval(shape)?.shape()?;
+            let (transform, material) =
+                shape.with(|s| (s.transform().clone(), s.material().clone()));
+            let world = self.eval(world)?.world()?;
+            let found = world
+                .borrow()
+                .objects()
+                .any(|o| o.transform() == &transform && o.material() == &material);
+            return found.then_some(()).ok_or("not in the world".to_owned());
+        }
+        if let Some(world) = text.strip_suffix(" has no objects") {
+            let count = self.eval(world)?.world()?.borrow().objects().len();
+            return (count == 0).then_some(()).ok_or(format!("it has {count}"));
+        }
+        if let Some(world) = text.strip_suffix(" has no light source") {
+            let count = self.eval(world)?.world()?.borrow().lights().len();
+            return (count == 0).then_some(()).ok_or(format!("it has {count}"));
+        }
+        for op in ["!=", "=", "<", ">"] {
+            if let Some((lhs, rhs)) = text.split_once(&format!(" {op} ")) {
+                let rhs = rhs.strip_prefix("approximately ").unwrap_or(rhs);
+                let rhs = self.eval(rhs)?;
+                return self.check(lhs, op, rhs);
+            }
+        }
+        if let Some((lhs, rhs)) = text.split_once(" is ") {
+            return self.check_is(lhs, rhs);
+        }
+
+        // Something like `set_transform(s, t)`, done for its side effects
+        self.eval(text).map(|_| ())
+    }
+
+    fn assign(&mut self, lhs: &str, value: Value) -> Result<(), String> {
+        let mut path = lhs.split('.');
+        let name = path.next().unwrap_or_default();
+        let path: Vec<_> = path.collect();
+        if path.is_empty() {
+            self.vars.insert(name.to_owned(), value);
+            return Ok(());
+        }
+
+        let target = self.vars.get_mut(name).ok_or(format!("{name} isn't set"))?;
+        set_path(target, &path, value)
+    }
+
+    fn add_to_world(&mut self, shape: &str, world: &str) -> Result<(), String> {
+        let world = self.eval(world)?.world()?;
+        let ShapeRef::Own(own) = self.eval(shape)?.shape()? else {
+            return Err(format!("{shape} is already in a world"));
+        };
+        // The world needs to own it, anything still pointing at the old one won't see changes
+        let boxed = std::mem::replace(&mut *own.borrow_mut(), Box::new(Sphere::default()));
+        world.borrow_mut().add_object(boxed);
+        let index = world.borrow().objects().len() - 1;
+        self.vars.insert(
+            shape.to_owned(),
+            Value::Shape(ShapeRef::InWorld(world, index)),
+        );
+        Ok(())
+    }
+
+    fn check(&self, lhs: &str, op: &str, rhs: Value) -> Result<(), String> {
+        let lhs = self.eval(lhs)?;
+        let ok = match op {
+            "=" => equal(&lhs, &rhs)?,
+            "!=" => !equal(&lhs, &rhs)?,
+            "<" => lhs.num()? < rhs.num()?,
+            ">" => lhs.num()? > rhs.num()?,
+            _ => unreachable!(),
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(format!("got {}", describe(&lhs)))
+        }
+    }
+
+    fn check_is(&self, lhs: &str, what: &str) -> Result<(), String> {
+        let v = self.eval(lhs)?;
+        let (negated, what) = match what.strip_prefix("not ") {
+            Some(what) => (true, what),
+            None => (false, what),
+        };
+        let is = match what {
+            "a point" => v.tuple()?.is_point(),
+            "a vector" => v.tuple()?.is_vector(),
+            "a shape" => matches!(v, Value::Shape(_)),
+            "invertible" => v.matrix()?.can_invert(),
+            "nothing" => matches!(v, Value::Nothing),
+            "true" => v.bool()?,
+            "false" => !v.bool()?,
+            _ => return Err(format!("don't know how to check for {what:?}")),
+        };
+        if is != negated {
+            Ok(())
+        } else {
+            Err(format!("got {}", describe(&v)))
+        }
+    }
+
+    fn eval(&self, expr: &str) -> Result<Value, String> {
+        let mut parser = Parser {
+            env: self,
+            tokens: tokenize(expr)?,
+            pos: 0,
+        };
+        let v = parser.args_item()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(v),
+            Some(t) => Err(format!("unexpected {t:?} in {expr:?}")),
+        }
+    }
+
+    fn variable(&self, name: &str) -> Result<Value, String> {
+        match name {
+            "identity_matrix" => Ok(Value::Matrix(IDENTITY_4X4)),
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            "EPSILON" => Ok(Value::Num(EPSILON)),
+            _ => self
+                .vars
+                .get(name)
+                .cloned()
+                .ok_or(format!("{name} isn't set")),
+        }
+    }
+
+    fn call(&self, name: &str, a: Vec<Value>) -> Result<Value, String> {
+        use Value as V;
+        Ok(match name {
+            // Tuples and colours
+            "tuple" => {
+                let [x, y, z, w] = args(name, a)?;
+                V::Tuple(Tuple {
+                    x: x.num()?,
+                    y: y.num()?,
+                    z: z.num()?,
+                    w: w.num()?,
+                })
+            }
+            "point" => {
+                let [x, y, z] = args(name, a)?;
+                V::Tuple(point(x.num()?, y.num()?, z.num()?))
+            }
+            "vector" => {
+                let [x, y, z] = args(name, a)?;
+                V::Tuple(vector(x.num()?, y.num()?, z.num()?))
+            }
+            "color" => {
+                let [r, g, b] = args(name, a)?;
+                V::Colour(Colour::new(r.num()?, g.num()?, b.num()?))
+            }
+            "magnitude" => {
+                let [v] = args(name, a)?;
+                V::Num(v.tuple()?.magnitude())
+            }
+            "normalize" => {
+                let [v] = args(name, a)?;
+                V::Tuple(v.tuple()?.normalize())
+            }
+            "dot" => {
+                let [a, b] = args(name, a)?;
+                V::Num(a.tuple()?.dot(&b.tuple()?))
+            }
+            "cross" => {
+                let [a, b] = args(name, a)?;
+                V::Tuple(a.tuple()?.cross(&b.tuple()?))
+            }
+            "reflect" => {
+                let [v, n] = args(name, a)?;
+                V::Tuple(v.tuple()?.reflect(&n.tuple()?))
+            }
+            "hadamard_product" => {
+                let [a, b] = args(name, a)?;
+                V::Colour(a.colour()? * b.colour()?)
+            }
+
+            // Matrices and transformations
+            "transpose" => {
+                let [m] = args(name, a)?;
+                V::Matrix(m.matrix()?.transpose())
+            }
+            "determinant" => {
+                let [m] = args(name, a)?;
+                V::Num(m.matrix()?.determinate())
+            }
+            "submatrix" | "minor" | "cofactor" => {
+                let [m, row, col] = args(name, a)?;
+                let (m, row, col) = (m.matrix()?, row.index()?, col.index()?);
+                match name {
+                    "submatrix" => V::Matrix(m.submatrix(row, col)),
+                    "minor" => V::Num(m.minor(row, col)),
+                    _ => V::Num(m.cofactor(row, col)),
+                }
+            }
+            "inverse" => {
+                let [m] = args(name, a)?;
+                V::Matrix(m.matrix()?.inverse().ok_or("not invertible")?)
+            }
+            "translation" | "scaling" => {
+                let [x, y, z] = args(name, a)?;
+                let (x, y, z) = (x.num()?, y.num()?, z.num()?);
+                V::Matrix(match name {
+                    "translation" => Matrix::translation(x, y, z),
+                    _ => Matrix::scaling(x, y, z),
+                })
+            }
+            "rotation_x" | "rotation_y" | "rotation_z" => {
+                let [r] = args(name, a)?;
+                let r = r.num()?;
+                V::Matrix(match name {
+                    "rotation_x" => Matrix::rotation_x(r),
+                    "rotation_y" => Matrix::rotation_y(r),
+                    _ => Matrix::rotation_z(r),
+                })
+            }
+            "shearing" => {
+                let [xy, xz, yx, yz, zx, zy] = args(name, a)?;
+                V::Matrix(Matrix::shearing(
+                    xy.num()?,
+                    xz.num()?,
+                    yx.num()?,
+                    yz.num()?,
+                    zx.num()?,
+                    zy.num()?,
+                ))
+            }
+            "view_transform" => {
+                let [from, to, up] = args(name, a)?;
+                V::Matrix(Matrix::view_transform(
+                    from.tuple()?,
+                    to.tuple()?,
+                    up.tuple()?,
+                ))
+            }
+
+            // Rays, shapes and intersections
+            "ray" => {
+                let [origin, direction] = args(name, a)?;
+                V::Ray(
+                    Ray::try_new(origin.tuple()?, direction.tuple()?).map_err(|e| e.to_string())?,
+                )
+            }
+            "position" => {
+                let [r, t] = args(name, a)?;
+                V::Tuple(r.ray()?.position(t.num()?).into())
+            }
+            "transform" => {
+                let [r, m] = args(name, a)?;
+                V::Ray(r.ray()?.transform(&m.matrix()?))
+            }
+            "sphere" => {
+                let [] = args(name, a)?;
+                V::Shape(ShapeRef::new(Sphere::default()))
+            }
+            "plane" => {
+                let [] = args(name, a)?;
+                V::Shape(ShapeRef::new(Plane::default()))
+            }
+            "glass_sphere" => {
+                let [] = args(name, a)?;
+                V::Shape(ShapeRef::new(Sphere::new_with_material(Material {
+                    transparency: 1.0,
+                    refractive_index: 1.5,
+                    ..Default::default()
+                })))
+            }
+            "set_transform" => {
+                let [s, m] = args(name, a)?;
+                let m = m.matrix()?;
+                s.shape()?.with_mut(|s| s.set_transform(m));
+                V::Nothing
+            }
+            "normal_at" => {
+                let [s, p] = args(name, a)?;
+                let p = p.tuple()?;
+                V::Tuple(s.shape()?.with(|s| s.normal_at_point(p)))
+            }
+            "intersect" => {
+                let [s, r] = args(name, a)?;
+                let (s, r) = (s.shape()?, r.ray()?);
+                let ts: Vec<_> = s.with(|shape| {
+                    let xs = shape.intersect(r).unwrap_or_default();
+                    xs.iter().map(|i| i.t).collect()
+                });
+                let hits = ts.into_iter().map(|t| Hit {
+                    t,
+                    object: s.clone(),
+                });
+                V::Hits(hits.collect())
+            }
+            "intersection" => {
+                let [t, s] = args(name, a)?;
+                V::Hit(Hit {
+                    t: t.num()?,
+                    object: s.shape()?,
+                })
+            }
+            "intersections" => V::Hits(a.iter().map(Value::hit).collect::<Result<_, _>>()?),
+            "hit" => {
+                let [xs] = args(name, a)?;
+                let hits = xs.hits()?;
+                let index = with_intersections(&hits, |xs| {
+                    let xs = xs.to_vec();
+                    xs.hit().and_then(|h| xs.iter().position(|i| *i == h))
+                });
+                index.map_or(V::Nothing, |i| V::Hit(hits[i].clone()))
+            }
+            "prepare_computations" => {
+                let (hit, ray, hits) = match a.as_slice() {
+                    [i, r] => (i.hit()?, r.ray()?, vec![i.hit()?]),
+                    [i, r, xs] => (i.hit()?, r.ray()?, xs.hits()?),
+                    _ => return Err(format!("{name} takes 2 or 3 arguments")),
+                };
+                let index = hits
+                    .iter()
+                    .position(|h| h.same(&hit))
+                    .ok_or("the intersection isn't in xs")?;
+                V::Comps(Rc::new(Comps::new(hits, index, ray)))
+            }
+            "schlick" => {
+                let [c] = args(name, a)?;
+                V::Num(c.comps()?.schlick)
+            }
+
+            // Lights and materials
+            "point_light" => {
+                let [position, intensity] = args(name, a)?;
+                V::Light(PointLight::new(intensity.colour()?, position.tuple()?))
+            }
+            "material" => {
+                let [] = args(name, a)?;
+                V::Material(Material::default())
+            }
+            "lighting" => {
+                // The object (for patterns) is only there from chapter 10, and in_shadow from 8
+                let (m, object, rest) = match a.as_slice() {
+                    [m, V::Shape(s), rest @ ..] => (m, Some(s), rest),
+                    [m, rest @ ..] => (m, None, rest),
+                    [] => return Err(format!("{name} needs arguments")),
+                };
+                let (light, p, eyev, normalv, in_shadow) = match rest {
+                    [l, p, e, n] => (l, p, e, n, false),
+                    [l, p, e, n, s] => (l, p, e, n, s.bool()?),
+                    _ => return Err(format!("wrong number of arguments for {name}")),
+                };
+                let p = p.tuple()?;
+                let object_point = match object {
+                    Some(s) => {
+                        let inverse = s.with(|s| s.transform().inverse()).ok_or("bad transform")?;
+                        &inverse * p
+                    }
+                    None => p,
+                };
+                V::Colour(m.material()?.lighting(
+                    &light.light()?,
+                    p,
+                    object_point,
+                    eyev.tuple()?,
+                    normalv.tuple()?,
+                    if in_shadow { 0.0 } else { 1.0 },
+                ))
+            }
+
+            // Worlds
+            "world" => {
+                let [] = args(name, a)?;
+                V::World(Rc::new(RefCell::new(World::new(vec![], vec![]))))
+            }
+            "default_world" => {
+                let [] = args(name, a)?;
+                V::World(Rc::new(RefCell::new(World::default())))
+            }
+            "intersect_world" => {
+                let [w, r] = args(name, a)?;
+                let (w, r) = (w.world()?, r.ray()?);
+                let hits = {
+                    let world = w.borrow();
+                    let ids: Vec<_> = world.objects().map(|o| o.id()).collect();
+                    world
+                        .intersect_world(r)
+                        .iter()
+                        .map(|i| {
+                            let index = ids.iter().position(|&id| id == i.object.id());
+                            index.map(|index| (i.t, index)).ok_or("hit a nested object")
+                        })
+                        .collect::<Result<Vec<_>, _>>()?
+                };
+                let hits = hits.into_iter().map(|(t, index)| Hit {
+                    t,
+                    object: ShapeRef::InWorld(w.clone(), index),
+                });
+                V::Hits(hits.collect())
+            }
+            "shade_hit" => {
+                let [w, c] = args(name, a)?;
+                let (w, c) = (w.world()?, c.comps()?);
+                let w = w.borrow();
+                V::Colour(with_intersections(&c.hits, |xs| {
+                    w.shade_hit(xs[c.index].prepare_computations_with(c.ray, xs))
+                }))
+            }
+            "color_at" => {
+                let [w, r] = args(name, a)?;
+                V::Colour(w.world()?.borrow().colour_at(r.ray()?))
+            }
+            "is_shadowed" => {
+                let [w, p] = args(name, a)?;
+                V::Bool(w.world()?.borrow().is_shadowed(p.tuple()?))
+            }
+
+            // Cameras
+            "camera" => {
+                let [h, v, fov] = args(name, a)?;
+                V::Camera(Camera::new(h.index()?, v.index()?, fov.num()?))
+            }
+            "ray_for_pixel" => {
+                let [c, x, y] = args(name, a)?;
+                V::Ray(c.camera()?.ray_for_pixel(x.index()?, y.index()?))
+            }
+            "render" => {
+                let [c, w] = args(name, a)?;
+                V::Canvas(Rc::new(c.camera()?.render(&w.world()?.borrow())))
+            }
+            "pixel_at" => {
+                let [c, x, y] = args(name, a)?;
+                let (c, x, y) = (c.canvas()?, x.index()?, y.index()?);
+                if x >= c.width || y >= c.height {
+                    return Err(format!("({x}, {y}) is off the canvas"));
+                }
+                V::Colour(c[(x, y)])
+            }
+            _ => return Err(format!("unknown function {name}")),
+        })
+    }
+}
+
+/// Enough of a value to make sense of a failed check
+fn describe(v: &Value) -> String {
+    match v {
+        Value::Num(n) => format!("{n}"),
+        Value::Bool(b) => format!("{b}"),
+        Value::Tuple(t) => format!("{t:?}"),
+        Value::Colour(c) => format!("{c:?}"),
+        Value::Matrix(m) => format!("{m:?}"),
+        Value::Ray(r) => format!("{:?} -> {:?}", r.origin, r.direction),
+        Value::Hit(h) => format!("an intersection at t = {}", h.t),
+        Value::Hits(xs) => format!("{:?}", xs.iter().map(|h| h.t).collect::<Vec<_>>()),
+        Value::Material(m) => format!("{m:?}"),
+        Value::Light(l) => format!("{l:?}"),
+        v => v.kind().to_owned(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Sym(char),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let mut n = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                n.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Num(
+                n.parse().map_err(|_| format!("bad number {n:?}"))?,
+            ));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(match name.as_str() {
+                "π" => Token::Num(PI),
+                _ => Token::Ident(name),
+            });
+        } else if "+-*/()[],.:√".contains(c) {
+            tokens.push(Token::Sym(c));
+            chars.next();
+        } else {
+            return Err(format!("unexpected {c:?} in {s:?}"));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Evaluates as it parses. Precedence is the usual, with √ binding tightest (`√2/2` is half of
+/// root two).
+struct Parser<'e> {
+    env: &'e Env,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Sym(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("expected {c:?}, got {:?}", self.peek()))
+        }
+    }
+
+    /// An expression, or `t:shape` shorthand for an intersection
+    fn args_item(&mut self) -> Result<Value, String> {
+        let v = self.expr()?;
+        if self.eat(':') {
+            let object = self.expr()?.shape()?;
+            return Ok(Value::Hit(Hit {
+                t: v.num()?,
+                object,
+            }));
+        }
+        Ok(v)
+    }
+
+    fn list(&mut self, close: char) -> Result<Vec<Value>, String> {
+        let mut items = vec![];
+        if self.eat(close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(self.args_item()?);
+            if self.eat(close) {
+                return Ok(items);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn expr(&mut self) -> Result<Value, String> {
+        let mut v = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Sym(op @ ('+' | '-'))) => *op,
+                _ => return Ok(v),
+            };
+            self.pos += 1;
+            v = binary(op, v, self.term()?)?;
+        }
+    }
+
+    fn term(&mut self) -> Result<Value, String> {
+        let mut v = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Sym(op @ ('*' | '/'))) => *op,
+                _ => return Ok(v),
+            };
+            self.pos += 1;
+            v = binary(op, v, self.unary()?)?;
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        if self.eat('-') {
+            return match self.unary()? {
+                Value::Num(n) => Ok(Value::Num(-n)),
+                Value::Tuple(t) => Ok(Value::Tuple(-t)),
+                v => Err(format!("can't negate {}", v.kind())),
+            };
+        }
+        if self.eat('√') {
+            return Ok(Value::Num(self.postfix()?.num()?.sqrt()));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Value, String> {
+        let mut v = self.primary()?;
+        loop {
+            if self.eat('.') {
+                let Some(Token::Ident(name)) = self.peek().cloned() else {
+                    return Err("expected a field name after '.'".to_owned());
+                };
+                self.pos += 1;
+                v = v.field(&name)?;
+            } else if self.eat('[') {
+                let index = self.list(']')?;
+                v = match (v, index.as_slice()) {
+                    (Value::Hits(xs), [i]) => {
+                        let i = i.index()?;
+                        Value::Hit(xs.get(i).cloned().ok_or(format!("no xs[{i}]"))?)
+                    }
+                    (Value::Matrix(m), [r, c]) => Value::Num(m[(r.index()?, c.index()?)]),
+                    (v, _) => return Err(format!("can't index {}", v.kind())),
+                };
+            } else {
+                return Ok(v);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value, String> {
+        let token = self.peek().cloned().ok_or("unexpected end")?;
+        self.pos += 1;
+        match token {
+            Token::Num(n) => Ok(Value::Num(n)),
+            Token::Ident(name) => {
+                if self.eat('(') {
+                    let args = self.list(')')?;
+                    self.env.call(&name, args)
+                } else {
+                    self.env.variable(&name)
+                }
+            }
+            Token::Sym('(') => {
+                let mut items = self.list(')')?;
+                if items.len() == 1 {
+                    Ok(items.remove(0))
+                } else {
+                    Ok(Value::List(items))
+                }
+            }
+            Token::Sym(c) => Err(format!("unexpected {c:?}")),
+        }
+    }
+}